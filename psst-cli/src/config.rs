@@ -0,0 +1,85 @@
+//! Settings shared with the GUI.  The CLI uses the directories of the app, so it
+//! shares the audio cache and the daemon token with the GUI, and takes the
+//! playback settings from the config file of the GUI, if there is one.
+
+use std::{env, fs::File, io, io::BufReader, path::PathBuf, time::Duration};
+
+use platform_dirs::AppDirs;
+use psst_core::player::{daemon, PlaybackConfig};
+use serde::Deserialize;
+
+const APP_NAME: &str = "Psst";
+const CONFIG_FILENAME: &str = "config.json";
+const PROXY_ENV_VAR: &str = "SOCKS_PROXY";
+
+#[derive(Clone, Copy, Deserialize)]
+enum AudioQuality {
+    Low,
+    Normal,
+    High,
+}
+
+/// The part of the GUI config the CLI cares about.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Config {
+    audio_quality: AudioQuality,
+    up_next_lead_secs: u64,
+    pub report_playback: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            audio_quality: AudioQuality::High,
+            up_next_lead_secs: 10,
+            report_playback: true,
+        }
+    }
+}
+
+impl Config {
+    fn app_dirs() -> AppDirs {
+        AppDirs::new(Some(APP_NAME), false).expect("Failed to get app dirs")
+    }
+
+    pub fn cache_dir() -> PathBuf {
+        Self::app_dirs().cache_dir
+    }
+
+    pub fn config_dir() -> PathBuf {
+        Self::app_dirs().config_dir
+    }
+
+    /// Config of the GUI, or the defaults if it was never saved.
+    pub fn load() -> Self {
+        let path = Self::config_dir().join(CONFIG_FILENAME);
+        match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|err| {
+                log::error!("failed to read config {:?}: {}", path, err);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn playback(&self) -> PlaybackConfig {
+        PlaybackConfig {
+            bitrate: match self.audio_quality {
+                AudioQuality::Low => 96,
+                AudioQuality::Normal => 160,
+                AudioQuality::High => 320,
+            },
+            up_next_lead: Duration::from_secs(self.up_next_lead_secs),
+            ..PlaybackConfig::default()
+        }
+    }
+
+    pub fn daemon_token() -> io::Result<String> {
+        daemon::load_or_create_token(&Self::config_dir())
+    }
+
+    pub fn proxy() -> Option<String> {
+        env::var(PROXY_ENV_VAR).ok()
+    }
+}
@@ -0,0 +1,56 @@
+//! `psst-cli daemon [<port>]` runs the player headless, for the GUI to attach to.
+//! The playback keeps going while the GUI is closed or being upgraded.  It plays
+//! with the settings of the GUI, and clients authenticate with the token in the
+//! config directory.
+
+use std::io;
+
+use psst_core::{
+    audio::output::{AudioOutput, AudioSink, DefaultAudioOutput},
+    cache::Cache,
+    cdn::Cdn,
+    error::Error,
+    player::{
+        daemon::{PlayerDaemon, DEFAULT_PORT},
+        report::PlaybackReporter,
+        Player,
+    },
+    session::SessionService,
+};
+
+use crate::config::Config;
+
+pub fn run(args: &[String], session: SessionService) -> Result<(), Error> {
+    let port = match args.first() {
+        Some(port) => port.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid port: {}", port),
+            )
+        })?,
+        None => DEFAULT_PORT,
+    };
+
+    let config = Config::load();
+    let token = Config::daemon_token()?;
+    let proxy_url = Config::proxy();
+    let cdn = Cdn::new(session.clone(), proxy_url.as_deref())?;
+    let cache = Cache::new(Config::cache_dir())?;
+    let output = DefaultAudioOutput::open()?;
+    // The GUI attached to the daemon switches it off for private sessions.
+    let reporter = PlaybackReporter::new(session.clone());
+    reporter.set_enabled(config.report_playback);
+    let mut player = Player::new(session, cdn, cache, config.playback(), &output);
+    let daemon = PlayerDaemon::bind(port, &token, player.sender())?;
+    println!("Playback daemon listening on {}", daemon.local_addr());
+
+    for event in player.receiver() {
+        daemon.observe(&event);
+        reporter.observe(&event);
+        player.handle(event);
+    }
+    reporter.close();
+    output.sink().close();
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+//! `psst-cli events [--json] [<port>]` follows the playback of the daemon or the
+//! GUI over IPC, and prints a line for every change, so status bar modules don't
+//! have to poll.
+
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+use psst_core::{
+    player::daemon::{DaemonClient, DaemonEvent, DaemonItem, DEFAULT_PORT},
+    uri::SpotifyUri,
+};
+use serde::Serialize;
+
+use crate::config::Config;
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    TrackChanged { uri: &'a SpotifyUri },
+    UpNext { uri: &'a SpotifyUri },
+    State { state: State },
+    Position { position_ms: u64 },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum State {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+impl fmt::Display for Event<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TrackChanged { uri } => write!(f, "track {}", uri),
+            Self::UpNext { uri } => write!(f, "up next {}", uri),
+            Self::State { state } => match state {
+                State::Playing => write!(f, "playing"),
+                State::Paused => write!(f, "paused"),
+                State::Stopped => write!(f, "stopped"),
+            },
+            Self::Position { position_ms } => {
+                let seconds = position_ms / 1000;
+                write!(f, "position {}:{:02}", seconds / 60, seconds % 60)
+            }
+        }
+    }
+}
+
+pub fn run(args: &[String]) -> io::Result<()> {
+    let mut json = false;
+    let mut port = DEFAULT_PORT;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ => {
+                port = arg.parse().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("invalid port: {}", arg),
+                    )
+                })?
+            }
+        }
+    }
+
+    let client = DaemonClient::connect(port, &Config::daemon_token()?)?;
+    let mut printer = Printer {
+        json,
+        track: None,
+        state: None,
+    };
+    for event in client.events()? {
+        match event {
+            DaemonEvent::State(state) => {
+                let playing = match (&state.item, state.paused) {
+                    (None, _) => State::Stopped,
+                    (Some(_), true) => State::Paused,
+                    (Some(_), false) => State::Playing,
+                };
+                printer.track(state.item.as_ref())?;
+                printer.state(playing)?;
+                if state.item.is_some() {
+                    printer.print(&Event::Position {
+                        position_ms: state.position_ms,
+                    })?;
+                }
+            }
+            DaemonEvent::Playing { item, position_ms } => {
+                printer.track(Some(&item))?;
+                printer.state(State::Playing)?;
+                printer.print(&Event::Position { position_ms })?;
+            }
+            DaemonEvent::Pausing => printer.state(State::Paused)?,
+            DaemonEvent::Resuming => printer.state(State::Playing)?,
+            DaemonEvent::Position { position_ms } => {
+                printer.print(&Event::Position { position_ms })?;
+            }
+            DaemonEvent::UpNext { item, .. } => {
+                if let DaemonItem::Spotify { uri, .. } = &item {
+                    printer.print(&Event::UpNext { uri })?;
+                }
+            }
+            DaemonEvent::Stopped => {
+                printer.track(None)?;
+                printer.state(State::Stopped)?;
+            }
+            DaemonEvent::Loading { .. } | DaemonEvent::Blocked | DaemonEvent::Stalled => {}
+        }
+    }
+    Ok(())
+}
+
+/// Prints the track and the state only when they change, as the daemon repeats
+/// them in its events.
+struct Printer {
+    json: bool,
+    track: Option<DaemonItem>,
+    state: Option<State>,
+}
+
+impl Printer {
+    fn track(&mut self, item: Option<&DaemonItem>) -> io::Result<()> {
+        if self.track.as_ref() == item {
+            return Ok(());
+        }
+        self.track = item.cloned();
+        // Local files have no URI to print.
+        if let Some(DaemonItem::Spotify { uri, .. }) = item {
+            self.print(&Event::TrackChanged { uri })?;
+        }
+        Ok(())
+    }
+
+    fn state(&mut self, state: State) -> io::Result<()> {
+        if self.state == Some(state) {
+            return Ok(());
+        }
+        self.state = Some(state);
+        self.print(&Event::State { state })
+    }
+
+    /// Events are written one per line, as JSON objects with `json`.
+    fn print(&self, event: &Event) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        if self.json {
+            serde_json::to_writer(&mut out, event)?;
+            writeln!(out)
+        } else {
+            writeln!(out, "{}", event)
+        }
+    }
+}
@@ -8,34 +8,58 @@ use psst_core::{
     connection::Credentials,
     error::Error,
     item_id::{ItemId, ItemIdType},
-    player::{item::PlaybackItem, PlaybackConfig, Player, PlayerCommand, PlayerEvent},
+    player::{item::PlaybackItem, Player, PlayerCommand, PlayerEvent},
     session::{SessionConfig, SessionService},
+    uri::SpotifyUri,
 };
-use std::{env, io, io::BufRead, path::PathBuf, thread};
+use std::{env, io, io::BufRead, thread};
+
+use crate::config::Config;
+
+mod config;
+mod daemon;
+mod events;
+mod repl;
+mod search;
 
 fn main() {
     env_logger::init();
 
     let args: Vec<String> = env::args().collect();
-    let track_id = args
-        .get(1)
-        .expect("Expected <track_id> in the first parameter");
+    // Following the playback of another instance doesn't need a session.
+    if args.get(1).map(String::as_str) == Some("events") {
+        events::run(&args[2..]).unwrap();
+        return;
+    }
+
     let login_creds = Credentials::from_username_and_password(
         env::var("SPOTIFY_USERNAME").unwrap(),
         env::var("SPOTIFY_PASSWORD").unwrap(),
     );
     let session = SessionService::with_config(SessionConfig {
         login_creds,
-        proxy_url: None,
+        proxy_url: Config::proxy(),
     });
 
-    start(track_id, session).unwrap();
+    // Without a track to play, commands are read interactively.
+    match args.get(1).map(String::as_str) {
+        Some("daemon") => daemon::run(&args[2..], session).unwrap(),
+        Some(track) => start(track, session).unwrap(),
+        None => repl::run(session).unwrap(),
+    }
 }
 
-fn start(track_id: &str, session: SessionService) -> Result<(), Error> {
-    let cdn = Cdn::new(session.clone(), None)?;
-    let cache = Cache::new(PathBuf::from("cache"))?;
-    let item_id = ItemId::from_base62(track_id, ItemIdType::Track).unwrap();
+/// Play a track given by its URI, its `open.spotify.com` URL, or its bare ID.
+fn start(track: &str, session: SessionService) -> Result<(), Error> {
+    let proxy_url = Config::proxy();
+    let cdn = Cdn::new(session.clone(), proxy_url.as_deref())?;
+    let cache = Cache::new(Config::cache_dir())?;
+    let item_id = match SpotifyUri::parse(track) {
+        Some(uri @ SpotifyUri::Track(_)) => uri.item_id(),
+        Some(_) => None,
+        None => ItemId::from_base62(track, ItemIdType::Track),
+    }
+    .unwrap();
     play_item(
         session,
         cdn,
@@ -54,7 +78,7 @@ fn play_item(
     item: PlaybackItem,
 ) -> Result<(), Error> {
     let output = DefaultAudioOutput::open()?;
-    let config = PlaybackConfig::default();
+    let config = Config::load().playback();
 
     let mut player = Player::new(session, cdn, cache, config, &output);
 
@@ -0,0 +1,493 @@
+//! Interactive mode, used when no track is given on the command line.  The
+//! commands drive the playback daemon or the GUI if one of them is running, or a
+//! player on the main thread otherwise.  They are read on a thread of their own.
+
+use std::{
+    collections::HashMap,
+    fs,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::{unbounded, Sender};
+use psst_core::{
+    audio::{
+        normalize::NormalizationLevel,
+        output::{AudioOutput, AudioSink, DefaultAudioOutput},
+    },
+    cache::Cache,
+    cdn::Cdn,
+    error::Error,
+    item_id::ItemId,
+    player::{
+        daemon::{DaemonClient, DaemonEvent, DaemonItem, DaemonRequest, DEFAULT_PORT},
+        item::PlaybackItem,
+        Player, PlayerCommand, PlayerEvent,
+    },
+    session::SessionService,
+};
+use rustyline::{
+    completion::{Completer, Pair},
+    error::ReadlineError,
+    history::DefaultHistory,
+    Context, Editor, Helper, Highlighter, Hinter, Validator,
+};
+
+use crate::{
+    config::Config,
+    search::{Search, TrackInfo},
+};
+
+/// File in the config directory keeping the command history.
+const HISTORY_FILENAME: &str = "cli-history";
+const PROMPT: &str = "psst> ";
+const SEARCH_LIMIT: usize = 10;
+
+/// Usage and description of each command.
+const COMMANDS: &[(&str, &str)] = &[
+    (
+        "search <query>",
+        "Find tracks, numbered for the commands below",
+    ),
+    (
+        "play <n>",
+        "Play the search results, starting with the n-th one",
+    ),
+    (
+        "queue [<n>...]",
+        "Add search results to the queue, or show the queue",
+    ),
+    (
+        "next <n>...",
+        "Play search results right after the current track",
+    ),
+    ("pause", "Pause the playback"),
+    ("resume", "Resume the playback"),
+    ("toggle", "Pause or resume the playback"),
+    ("skip", "Play the next track"),
+    ("prev", "Play the previous track"),
+    ("stop", "Stop the playback"),
+    ("seek <seconds>", "Jump to a position in the current track"),
+    ("volume <0-100>", "Change the volume"),
+    ("help", "Show this list"),
+    ("quit", "Exit, a daemon or the GUI keeps playing"),
+];
+
+/// Commands taking the numbers of the search results.
+const RESULT_COMMANDS: &[&str] = &["play", "queue", "next"];
+
+pub fn run(session: SessionService) -> Result<(), Error> {
+    let search = Search::new(session.clone())?;
+    match Config::daemon_token().and_then(|token| DaemonClient::connect(DEFAULT_PORT, &token)) {
+        Ok(client) => {
+            run_attached(client, search);
+            Ok(())
+        }
+        Err(err) => {
+            log::info!("no player to attach to, playing here: {}", err);
+            run_local(session, search)
+        }
+    }
+}
+
+/// Send the commands to the daemon or the GUI over IPC, like the GUI does when it
+/// attaches to the daemon.
+fn run_attached(client: DaemonClient, search: Search) {
+    let client = Arc::new(client);
+    let status = Arc::new(Mutex::new(Status::default()));
+    let (sender, receiver) = unbounded();
+
+    thread::spawn({
+        let client = Arc::clone(&client);
+        move || {
+            for event in receiver {
+                let request = match event {
+                    PlayerEvent::Command(command) => DaemonRequest::from_command(&command),
+                    _ => None,
+                };
+                if let Some(request) = request {
+                    if let Err(err) = client.send(&request) {
+                        log::error!("failed to send to the player: {}", err);
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    thread::spawn({
+        let client = Arc::clone(&client);
+        let status = Arc::clone(&status);
+        move || match client.events() {
+            Ok(events) => {
+                for event in events {
+                    status.lock().unwrap().observe(&event);
+                }
+                println!("The player went away.");
+            }
+            Err(err) => log::error!("failed to read from the player: {}", err),
+        }
+    });
+
+    println!("Attached to the running player.");
+    let repl = Repl {
+        sender,
+        search,
+        status,
+        results: Vec::new(),
+    };
+    if let Err(err) = repl.run() {
+        log::error!("failed to read commands: {}", err);
+    }
+    client.detach();
+}
+
+fn run_local(session: SessionService, search: Search) -> Result<(), Error> {
+    let config = Config::load();
+    let proxy_url = Config::proxy();
+    let cdn = Cdn::new(session.clone(), proxy_url.as_deref())?;
+    let cache = Cache::new(Config::cache_dir())?;
+    let output = DefaultAudioOutput::open()?;
+    let mut player = Player::new(session, cdn, cache, config.playback(), &output);
+
+    let status = Arc::new(Mutex::new(Status::default()));
+    let quit = Arc::new(AtomicBool::new(false));
+
+    let _repl_thread = thread::spawn({
+        let sender = player.sender();
+        let repl = Repl {
+            sender: sender.clone(),
+            search,
+            status: status.clone(),
+            results: Vec::new(),
+        };
+        let quit = quit.clone();
+        move || {
+            if let Err(err) = repl.run() {
+                log::error!("failed to read commands: {}", err);
+            }
+            quit.store(true, Ordering::SeqCst);
+            // Wake the player loop up, so it notices.
+            let _ = sender.send(PlayerEvent::Command(PlayerCommand::Stop));
+        }
+    });
+
+    for event in player.receiver() {
+        if let PlayerEvent::Loading { item } = &event {
+            status.lock().unwrap().started(item.item_id);
+        }
+        player.handle(event);
+        if quit.load(Ordering::SeqCst) {
+            break;
+        }
+    }
+    output.sink().close();
+
+    Ok(())
+}
+
+/// What the player is doing, as far as the commands sent to it tell.
+#[derive(Default)]
+struct Status {
+    /// Tracks from the search results, so the queue can be shown with their names.
+    tracks: HashMap<ItemId, TrackInfo>,
+    playing: Option<ItemId>,
+    /// Queued items that didn't start playing yet.
+    queued: Vec<ItemId>,
+}
+
+impl Status {
+    fn started(&mut self, item_id: ItemId) {
+        self.playing = Some(item_id);
+        if let Some(position) = self.queued.iter().position(|&id| id == item_id) {
+            self.queued.remove(position);
+        }
+    }
+
+    /// Follow the player we're attached to, including the commands of its other
+    /// clients.
+    fn observe(&mut self, event: &DaemonEvent) {
+        let item_id = |item: &DaemonItem| item.to_playback_item().map(|item| item.item_id);
+        match event {
+            DaemonEvent::State(state) => {
+                self.playing = state.item.as_ref().and_then(item_id);
+                self.queued = state.user_queue.iter().filter_map(item_id).collect();
+            }
+            DaemonEvent::Loading { item } => {
+                if let Some(item_id) = item_id(item) {
+                    self.started(item_id);
+                }
+            }
+            DaemonEvent::Stopped => {
+                self.playing = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn describe(&self, item_id: &ItemId) -> String {
+        match self.tracks.get(item_id) {
+            Some(track) => describe_track(track),
+            None => item_id.to_base62(),
+        }
+    }
+}
+
+enum Flow {
+    Continue,
+    Quit,
+}
+
+struct Repl {
+    sender: Sender<PlayerEvent>,
+    search: Search,
+    status: Arc<Mutex<Status>>,
+    results: Vec<TrackInfo>,
+}
+
+impl Repl {
+    fn run(mut self) -> rustyline::Result<()> {
+        let mut editor: Editor<CommandHelper, DefaultHistory> = Editor::new()?;
+        editor.set_helper(Some(CommandHelper { result_count: 0 }));
+        let history_path = Config::config_dir().join(HISTORY_FILENAME);
+        if let Err(err) = editor.load_history(&history_path) {
+            log::debug!("no command history loaded: {}", err);
+        }
+        println!("Type \"help\" to list the commands.");
+        loop {
+            match editor.readline(PROMPT) {
+                Ok(line) => {
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    editor.add_history_entry(line)?;
+                    match self.execute(line) {
+                        Ok(Flow::Continue) => {}
+                        Ok(Flow::Quit) => break,
+                        Err(err) => println!("{}", err),
+                    }
+                    if let Some(helper) = editor.helper_mut() {
+                        helper.result_count = self.results.len();
+                    }
+                }
+                // Ctrl+C only discards the line being typed.
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(err),
+            }
+        }
+        fs::create_dir_all(Config::config_dir())?;
+        editor.save_history(&history_path)
+    }
+
+    fn execute(&mut self, line: &str) -> Result<Flow, String> {
+        let (command, args) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(command, args)| (command, args.trim()));
+        match command {
+            "search" => self.search(args)?,
+            "play" => {
+                let position = match self.result_numbers(args)?.as_slice() {
+                    [position] => *position,
+                    _ => return Err("Give the number of one search result.".to_string()),
+                };
+                let items = self.results.iter().map(playback_item).collect();
+                self.send(PlayerCommand::LoadQueue { items, position })?;
+            }
+            "queue" if args.is_empty() => self.print_queue(),
+            "queue" => {
+                let tracks = self.result_tracks(args)?;
+                self.status
+                    .lock()
+                    .unwrap()
+                    .queued
+                    .extend(tracks.iter().map(|track| track.id));
+                let items = tracks.iter().map(playback_item).collect();
+                self.send(PlayerCommand::AddToQueue { items })?;
+            }
+            "next" => {
+                let tracks = self.result_tracks(args)?;
+                self.status
+                    .lock()
+                    .unwrap()
+                    .queued
+                    .splice(0..0, tracks.iter().map(|track| track.id));
+                let items = tracks.iter().map(playback_item).collect();
+                self.send(PlayerCommand::PlayNext { items })?;
+            }
+            "pause" => self.send(PlayerCommand::Pause)?,
+            "resume" => self.send(PlayerCommand::Resume)?,
+            "toggle" => self.send(PlayerCommand::PauseOrResume)?,
+            "skip" => self.send(PlayerCommand::Next)?,
+            "prev" => self.send(PlayerCommand::Previous)?,
+            "stop" => self.send(PlayerCommand::Stop)?,
+            "seek" => {
+                let seconds: f64 = args
+                    .parse()
+                    .map_err(|_| "Give the position in seconds.".to_string())?;
+                let position = Duration::from_secs_f64(seconds.max(0.0));
+                self.send(PlayerCommand::Seek { position })?;
+            }
+            "volume" => {
+                let percent: u8 = args
+                    .parse()
+                    .ok()
+                    .filter(|percent| *percent <= 100)
+                    .ok_or_else(|| "Give the volume from 0 to 100.".to_string())?;
+                let volume = f64::from(percent) / 100.0;
+                self.send(PlayerCommand::SetVolume { volume })?;
+            }
+            "help" => print_help(),
+            "quit" | "exit" => return Ok(Flow::Quit),
+            _ => return Err(format!("Unknown command \"{}\", try \"help\".", command)),
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn search(&mut self, query: &str) -> Result<(), String> {
+        if query.is_empty() {
+            return Err("Give something to search for.".to_string());
+        }
+        let tracks = self
+            .search
+            .tracks(query, SEARCH_LIMIT)
+            .map_err(|err| format!("Search failed: {}", err))?;
+        if tracks.is_empty() {
+            println!("Nothing found.");
+        }
+        for (index, track) in tracks.iter().enumerate() {
+            println!("{:>3}. {}", index + 1, describe_track(track));
+        }
+        let mut status = self.status.lock().unwrap();
+        for track in &tracks {
+            status.tracks.insert(track.id, track.clone());
+        }
+        self.results = tracks;
+        Ok(())
+    }
+
+    /// Zero-based positions of the search results numbered in `args`.
+    fn result_numbers(&self, args: &str) -> Result<Vec<usize>, String> {
+        if self.results.is_empty() {
+            return Err("Search for some tracks first.".to_string());
+        }
+        args.split_whitespace()
+            .map(|arg| match arg.parse::<usize>() {
+                Ok(number) if (1..=self.results.len()).contains(&number) => Ok(number - 1),
+                _ => Err(format!(
+                    "\"{}\" is not a search result, pick from 1 to {}.",
+                    arg,
+                    self.results.len()
+                )),
+            })
+            .collect()
+    }
+
+    fn result_tracks(&self, args: &str) -> Result<Vec<TrackInfo>, String> {
+        let numbers = self.result_numbers(args)?;
+        if numbers.is_empty() {
+            return Err("Give the numbers of the search results.".to_string());
+        }
+        Ok(numbers
+            .into_iter()
+            .map(|position| self.results[position].clone())
+            .collect())
+    }
+
+    fn print_queue(&self) {
+        let status = self.status.lock().unwrap();
+        match &status.playing {
+            Some(item_id) => println!("Playing: {}", status.describe(item_id)),
+            None => println!("Nothing is playing."),
+        }
+        if status.queued.is_empty() {
+            println!("The queue is empty.");
+        }
+        for (index, item_id) in status.queued.iter().enumerate() {
+            println!("{:>3}. {}", index + 1, status.describe(item_id));
+        }
+    }
+
+    fn send(&self, command: PlayerCommand) -> Result<(), String> {
+        self.sender
+            .send(PlayerEvent::Command(command))
+            .map_err(|_| "The player is not running.".to_string())
+    }
+}
+
+fn playback_item(track: &TrackInfo) -> PlaybackItem {
+    PlaybackItem {
+        item_id: track.id,
+        norm_level: NormalizationLevel::Track,
+    }
+}
+
+fn describe_track(track: &TrackInfo) -> String {
+    let seconds = track.duration.as_secs();
+    format!(
+        "{} – {} ({}:{:02})",
+        track.name,
+        track.artists,
+        seconds / 60,
+        seconds % 60
+    )
+}
+
+fn print_help() {
+    for (usage, description) in COMMANDS {
+        println!("  {:<16} {}", usage, description);
+    }
+}
+
+/// Completes the command names, and the numbers of the search results after the
+/// commands taking them.
+#[derive(Helper, Highlighter, Hinter, Validator)]
+struct CommandHelper {
+    result_count: usize,
+}
+
+impl Completer for CommandHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let line = &line[..pos];
+        let start = line.rfind(' ').map_or(0, |index| index + 1);
+        let word = &line[start..];
+        let candidates = if start == 0 {
+            COMMANDS
+                .iter()
+                .filter_map(|(usage, _)| usage.split(' ').next())
+                .filter(|name| name.starts_with(word))
+                .map(|name| Pair {
+                    display: name.to_string(),
+                    replacement: format!("{} ", name),
+                })
+                .collect()
+        } else if line
+            .split_whitespace()
+            .next()
+            .map_or(false, |command| RESULT_COMMANDS.contains(&command))
+        {
+            (1..=self.result_count)
+                .map(|number| number.to_string())
+                .filter(|number| number.starts_with(word))
+                .map(|number| Pair {
+                    display: number.clone(),
+                    replacement: format!("{} ", number),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Ok((start, candidates))
+    }
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use psst_core::{
+    error::Error,
+    item_id::{ItemId, ItemIdType},
+    session::{access_token::TokenProvider, SessionService},
+    util::default_ureq_agent_builder,
+};
+use serde::Deserialize;
+
+#[derive(Clone, Debug)]
+pub struct TrackInfo {
+    pub id: ItemId,
+    pub name: String,
+    pub artists: String,
+    pub duration: Duration,
+}
+
+/// Track search through the Web API, the core itself doesn't know about it.
+pub struct Search {
+    session: SessionService,
+    agent: ureq::Agent,
+    token_provider: TokenProvider,
+}
+
+impl Search {
+    pub fn new(session: SessionService) -> Result<Self, Error> {
+        Ok(Self {
+            session,
+            agent: default_ureq_agent_builder(None)?.build(),
+            token_provider: TokenProvider::new(),
+        })
+    }
+
+    pub fn tracks(&self, query: &str, limit: usize) -> Result<Vec<TrackInfo>, Error> {
+        #[derive(Deserialize)]
+        struct Response {
+            tracks: Page,
+        }
+        #[derive(Deserialize)]
+        struct Page {
+            items: Vec<Track>,
+        }
+        #[derive(Deserialize)]
+        struct Track {
+            id: Option<String>,
+            name: String,
+            artists: Vec<Artist>,
+            duration_ms: u64,
+        }
+        #[derive(Deserialize)]
+        struct Artist {
+            name: String,
+        }
+
+        let access_token = self.token_provider.get(&self.session)?;
+        let response: Response = self
+            .agent
+            .get("https://api.spotify.com/v1/search")
+            .query("q", query)
+            .query("type", "track")
+            .query("limit", &limit.to_string())
+            .set("Authorization", &format!("Bearer {}", access_token.token))
+            .call()?
+            .into_json()?;
+        let tracks = response
+            .tracks
+            .items
+            .into_iter()
+            .filter_map(|track| {
+                Some(TrackInfo {
+                    id: ItemId::from_base62(track.id.as_ref()?, ItemIdType::Track)?,
+                    name: track.name,
+                    artists: track
+                        .artists
+                        .into_iter()
+                        .map(|artist| artist.name)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    duration: Duration::from_millis(track.duration_ms),
+                })
+            })
+            .collect();
+        Ok(tracks)
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::probe::TrackProbe;
+
+/// Frames per second of the timestamps in cue sheets.
+const CUE_FRAMES_PER_SEC: u64 = 75;
+
+/// Marked section of a long local file, like a track of a DJ mix or a chapter of
+/// an audiobook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub start: Duration,
+}
+
+/// Read the chapters of the local file at `path`, ordered by their start.  A cue
+/// sheet next to the file takes precedence over the chapters embedded in it.
+pub fn read_chapters(path: &Path) -> Vec<Chapter> {
+    let mut chapters = cue_sheet_paths(path)
+        .into_iter()
+        .find_map(|cue_path| fs::read(cue_path).ok())
+        .map(|sheet| {
+            let file_name = path.file_name().and_then(|name| name.to_str());
+            parse_cue_sheet(&String::from_utf8_lossy(&sheet), file_name)
+        })
+        .filter(|chapters| !chapters.is_empty())
+        .unwrap_or_else(|| match TrackProbe::new(&path.to_path_buf()) {
+            Ok(probe) => probe.chapters,
+            Err(err) => {
+                log::warn!("failed to probe {:?} for chapters: {}", path, err);
+                Vec::new()
+            }
+        });
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters.dedup_by_key(|chapter| chapter.start);
+    chapters
+}
+
+/// Both `mix.cue` and `mix.mp3.cue` are common names for the sheet of `mix.mp3`.
+fn cue_sheet_paths(path: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![path.with_extension("cue")];
+    if let Some(name) = path.file_name() {
+        let mut name = name.to_owned();
+        name.push(".cue");
+        paths.push(path.with_file_name(name));
+    }
+    paths
+}
+
+/// Parse the tracks of a cue sheet.  Sheets can describe more than one file, in
+/// that case only the tracks of `file_name` are returned.
+pub fn parse_cue_sheet(sheet: &str, file_name: Option<&str>) -> Vec<Chapter> {
+    let mut files: Vec<(String, Vec<Chapter>)> = Vec::new();
+    let mut chapter: Option<Chapter> = None;
+
+    for line in sheet.lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command.to_ascii_uppercase().as_str() {
+            "FILE" => {
+                // The name is followed by the type of the file.
+                let name = if rest.starts_with('"') {
+                    unquote(rest)
+                } else {
+                    rest.rsplit_once(char::is_whitespace)
+                        .map_or(rest, |(name, _)| name)
+                };
+                files.push((name.to_string(), Vec::new()));
+                chapter = None;
+            }
+            "TRACK" => {
+                chapter = Some(Chapter {
+                    title: None,
+                    performer: None,
+                    start: Duration::ZERO,
+                });
+            }
+            // Titles and performers outside of a track belong to the whole sheet.
+            "TITLE" => {
+                if let Some(chapter) = &mut chapter {
+                    chapter.title = Some(unquote(rest).to_string());
+                }
+            }
+            "PERFORMER" => {
+                if let Some(chapter) = &mut chapter {
+                    chapter.performer = Some(unquote(rest).to_string());
+                }
+            }
+            "INDEX" => {
+                // Index 0 marks the pregap, the track itself starts at index 1.
+                let (number, time) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                if number.parse::<u32>() != Ok(1) {
+                    continue;
+                }
+                if let (Some(mut chapter), Some(start), Some((_, chapters))) = (
+                    chapter.take(),
+                    parse_cue_time(time.trim()),
+                    files.last_mut(),
+                ) {
+                    chapter.start = start;
+                    chapters.push(chapter);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let is_same_file = |name: &str| {
+        let name = Path::new(name).file_name().and_then(|name| name.to_str());
+        name.is_some() && name == file_name
+    };
+    match files.iter().position(|(name, _)| is_same_file(name)) {
+        Some(index) => files.swap_remove(index).1,
+        None if files.len() == 1 => files.remove(0).1,
+        None => Vec::new(),
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    match value.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or(quoted),
+        None => value,
+    }
+}
+
+/// Parse the `mm:ss:ff` timestamps of cue sheets.
+fn parse_cue_time(time: &str) -> Option<Duration> {
+    let mut parts = time.splitn(3, ':').map(|part| part.parse::<u64>().ok());
+    let minutes = parts.next()??;
+    let seconds = parts.next()??;
+    let frames = parts.next()??;
+    Some(Duration::from_millis(
+        (minutes * 60 + seconds) * 1_000 + frames * 1_000 / CUE_FRAMES_PER_SEC,
+    ))
+}
@@ -1,11 +1,13 @@
-use std::{io, time::Duration};
+use std::{io, ops::Range, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
 
 use symphonia::{
     core::{
         audio::{SampleBuffer, SignalSpec},
         codecs::{CodecParameters, Decoder, DecoderOptions},
         conv::ConvertibleSample,
-        errors::Error as SymphoniaError,
+        errors::{Error as SymphoniaError, SeekErrorKind},
         formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
         io::{MediaSource, MediaSourceStream, MediaSourceStreamOptions},
         units::TimeStamp,
@@ -16,8 +18,19 @@ use symphonia::{
     },
 };
 
-use crate::{error::Error, util::FileWithConstSize};
+use crate::{
+    audio::ogg_index::{IndexingReader, SharedSeekIndex, SplicePoint, SplicedReader},
+    error::Error,
+    util::FileWithConstSize,
+};
+
+/// Seeks further than this from the closest indexed page are left to the demuxer.
+const MAX_INDEXED_SEEK_DISTANCE: Duration = Duration::from_secs(10);
+
+/// How far before the page it was spliced at can the first packet plausibly start.
+const MAX_PAGE_FRAMES: u64 = 1 << 16;
 
+#[derive(Clone, Copy)]
 pub enum AudioCodecFormat {
     Mp3,
     OggVorbis,
@@ -54,10 +67,34 @@ impl AudioCodecFormat {
     }
 }
 
+trait ReadSeek: io::Read + io::Seek + Send + Sync {}
+
+impl<T> ReadSeek for T where T: io::Read + io::Seek + Send + Sync {}
+
+type SharedReader = Arc<Mutex<Box<dyn ReadSeek>>>;
+
+/// Ogg stream seeked with the help of a seek index.
+struct IndexedOgg {
+    reader: SharedReader,
+    index: SharedSeekIndex,
+}
+
+impl Drop for IndexedOgg {
+    fn drop(&mut self) {
+        if let Err(err) = self.index.save() {
+            log::warn!("failed to save seek index: {}", err);
+        }
+    }
+}
+
 pub struct AudioDecoder {
     track_id: u32, // Internal track index.
     decoder: Box<dyn Decoder>,
     format: Box<dyn FormatReader>,
+    codec: AudioCodecFormat,
+    /// After a seek, frames before this timestamp are decoded but not returned.
+    skip_until: Option<TimeStamp>,
+    indexed_ogg: Option<IndexedOgg>,
 }
 
 impl AudioDecoder {
@@ -77,9 +114,29 @@ impl AudioDecoder {
             track_id: track.id,
             decoder,
             format,
+            codec,
+            skip_until: None,
+            indexed_ogg: None,
         })
     }
 
+    /// Decode an Ogg Vorbis stream, indexing its pages as they are read.  Seeks
+    /// into the indexed parts of the stream start right at the closest page,
+    /// instead of searching for it.
+    pub fn with_seek_index<T>(input: T, index: SharedSeekIndex) -> Result<Self, Error>
+    where
+        T: io::Read + io::Seek + Send + Sync + 'static,
+    {
+        let reader: SharedReader = Arc::new(Mutex::new(Box::new(IndexingReader::new(
+            input,
+            index.clone(),
+        ))));
+        let spliced = SplicedReader::new(Arc::clone(&reader), None)?;
+        let mut decoder = Self::new(spliced, AudioCodecFormat::OggVorbis)?;
+        decoder.indexed_ogg = Some(IndexedOgg { reader, index });
+        Ok(decoder)
+    }
+
     pub fn codec_params(&self) -> &CodecParameters {
         self.decoder.codec_params()
     }
@@ -91,7 +148,16 @@ impl AudioDecoder {
         }
     }
 
+    /// Seek to `time`, returning the timestamp of the frame the following reads
+    /// start at.
     pub fn seek(&mut self, time: Duration) -> Result<TimeStamp, Error> {
+        match self.seek_indexed(time) {
+            Ok(Some(timestamp)) => return Ok(timestamp),
+            Ok(None) => {}
+            Err(err) => log::warn!("indexed seek failed, searching instead: {}", err),
+        }
+        // The demuxer can only search in the whole stream, not in a spliced one.
+        self.reopen(None)?;
         let seeked_to = self.format.seek(
             SeekMode::Accurate,
             SeekTo::Time {
@@ -99,12 +165,69 @@ impl AudioDecoder {
                 track_id: Some(self.track_id),
             },
         )?;
-        Ok(seeked_to.actual_ts)
+        // The demuxer stops at the packet containing the requested frame, the rest
+        // of the way is up to us.
+        self.decoder.reset();
+        self.skip_until = Some(seeked_to.required_ts);
+        Ok(seeked_to.required_ts)
+    }
+
+    /// Continue from an indexed page close to `time`, if there is one.
+    fn seek_indexed(&mut self, time: Duration) -> Result<Option<TimeStamp>, Error> {
+        let (index, sample_rate) = match (&self.indexed_ogg, self.codec_params().sample_rate) {
+            (Some(indexed), Some(sample_rate)) => (indexed.index.clone(), sample_rate as f64),
+            _ => return Ok(None),
+        };
+        let target = (time.as_secs_f64() * sample_rate) as u64;
+        let max_distance = (MAX_INDEXED_SEEK_DISTANCE.as_secs_f64() * sample_rate) as u64;
+        let splice = match index.splice_point(target, max_distance) {
+            Some(splice) => splice,
+            None => return Ok(None),
+        };
+        self.reopen(Some(splice))?;
+
+        // Make sure the demuxer picked up the timestamps from the spliced page, the
+        // frames to skip are counted from them.
+        let first_packet = loop {
+            let packet = self.format.next_packet()?;
+            if packet.track_id() == self.track_id {
+                break packet;
+            }
+        };
+        let first_ts = first_packet.ts();
+        if first_ts > splice.granule || splice.granule - first_ts > MAX_PAGE_FRAMES {
+            return Err(Error::AudioDecodingError(Box::new(
+                SymphoniaError::SeekError(SeekErrorKind::OutOfRange),
+            )));
+        }
+        // Let the decoder see the first packet, its blocks overlap with the next one.
+        let _ = self.decoder.decode(&first_packet);
+        self.skip_until = Some(target);
+        Ok(Some(target))
+    }
+
+    /// Start a new demuxer and decoder on the indexed stream, spliced at `splice`.
+    /// Streams without an index are left as they are.
+    fn reopen(&mut self, splice: Option<SplicePoint>) -> Result<(), Error> {
+        let reader = match &self.indexed_ogg {
+            Some(indexed) => Arc::clone(&indexed.reader),
+            None => return Ok(()),
+        };
+        let mss = MediaSourceStream::new(
+            Box::new(FileWithConstSize::new(SplicedReader::new(reader, splice)?)),
+            MediaSourceStreamOptions::default(),
+        );
+        let format = self.codec.format_reader(mss)?;
+        let track = format.default_track().unwrap();
+        self.decoder = self.codec.decoder(&track.codec_params)?;
+        self.track_id = track.id;
+        self.format = format;
+        Ok(())
     }
 
-    /// Read a next packet of audio from this decoder.  Returns `None` in case
-    /// of EOF or internal error.
-    pub fn read_packet<S>(&mut self, samples: &mut SampleBuffer<S>) -> Option<TimeStamp>
+    /// Read a next packet of audio from this decoder.  Returns the range of the
+    /// samples in `samples` to play, or `None` in case of EOF or internal error.
+    pub fn read_packet<S>(&mut self, samples: &mut SampleBuffer<S>) -> Option<Range<usize>>
     where
         S: ConvertibleSample,
     {
@@ -131,9 +254,19 @@ impl AudioDecoder {
             // Decode the packet into an audio buffer.
             match self.decoder.decode(&packet) {
                 Ok(decoded) => {
+                    let frames = decoded.frames() as u64;
+                    let channels = decoded.spec().channels.count();
+                    // Drop the frames preceding the position of the last seek.
+                    let skipped_frames = match self.skip_until {
+                        Some(until) if packet.ts() + frames <= until => continue,
+                        Some(until) => until.saturating_sub(packet.ts()),
+                        None => 0,
+                    };
+                    self.skip_until = None;
                     // Interleave the samples into the buffer.
                     samples.copy_interleaved_ref(decoded);
-                    return Some(packet.ts());
+                    let len = samples.samples().len();
+                    return Some((skipped_frames as usize * channels).min(len)..len);
                 }
                 Err(SymphoniaError::IoError(err)) => {
                     // The packet failed to decode due to an IO error, skip the packet.
@@ -1,6 +1,8 @@
+pub mod chapters;
 pub mod decode;
 pub mod decrypt;
 pub mod normalize;
+pub mod ogg_index;
 pub mod output;
 pub mod probe;
 pub mod resample;
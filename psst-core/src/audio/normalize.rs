@@ -4,8 +4,12 @@ use std::{
 };
 
 use byteorder::{ReadBytesExt, LE};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+use super::probe::ReplayGain;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum NormalizationLevel {
     None,
     Track,
@@ -39,6 +43,21 @@ impl NormalizationData {
         })
     }
 
+    /// Normalization data of a local file, from its ReplayGain tags.  When only one
+    /// of the track and album gains is known, it stands in for the other.
+    pub fn from_replay_gain(replay_gain: &ReplayGain) -> Option<Self> {
+        let track_gain_db = replay_gain.track_gain_db.or(replay_gain.album_gain_db)?;
+        let album_gain_db = replay_gain.album_gain_db.unwrap_or(track_gain_db);
+        let track_peak = replay_gain.track_peak.or(replay_gain.album_peak);
+        let album_peak = replay_gain.album_peak.or(track_peak);
+        Some(Self {
+            track_gain_db,
+            track_peak: track_peak.unwrap_or(1.0),
+            album_gain_db,
+            album_peak: album_peak.unwrap_or(1.0),
+        })
+    }
+
     pub fn factor_for_level(&self, level: NormalizationLevel, pregain: f32) -> f32 {
         match level {
             NormalizationLevel::None => 1.0,
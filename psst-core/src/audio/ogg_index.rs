@@ -0,0 +1,291 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+const PAGE_CAPTURE_PATTERN: &[u8] = b"OggS";
+
+/// Length of the fixed part of an Ogg page header, up to the segment table.
+const PAGE_HEADER_LEN: usize = 27;
+
+/// Granule position of pages that don't finish any packet.
+const NO_GRANULE: i64 = -1;
+
+/// Byte offsets of the pages of an Ogg stream, keyed by their granule position.
+/// For Vorbis, the granule position is the number of the last frame finished on
+/// the page, so the index tells which page to start decoding from to reach a
+/// particular frame, without the demuxer having to bisect the stream.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct OggSeekIndex {
+    /// Offset of the first page after the header packets.  Everything before it
+    /// needs to be fed to the demuxer before starting from an indexed page.
+    audio_start: Option<u64>,
+    pages: BTreeMap<u64, u64>,
+    #[serde(skip)]
+    is_changed: bool,
+}
+
+impl OggSeekIndex {
+    fn insert_page(&mut self, offset: u64, granule: i64) {
+        match granule {
+            // Pages of the header packets.
+            0 => return,
+            NO_GRANULE => {}
+            granule => {
+                let previous = self.pages.insert(granule as u64, offset);
+                self.is_changed |= previous != Some(offset);
+            }
+        }
+        if self.audio_start.map_or(true, |start| offset < start) {
+            self.audio_start = Some(offset);
+            self.is_changed = true;
+        }
+    }
+
+    /// Find the page to start decoding from to reach the frame at `target`.  Pages
+    /// not closer than `max_distance` frames are not worth it, the demuxer can
+    /// search for the position faster than all of them would decode.
+    fn splice_point(&self, target: u64, max_distance: u64) -> Option<SplicePoint> {
+        let header_len = self.audio_start?;
+        // The timestamps of the packets on the first page can't be trusted, the
+        // decoder doesn't know the size of the preceding blocks.  Skip a page.
+        let mut preceding = self.pages.range(..=target).rev();
+        let (&granule, &offset) = preceding.nth(1)?;
+        if target - granule > max_distance {
+            return None;
+        }
+        Some(SplicePoint {
+            header_len,
+            offset,
+            granule,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SplicePoint {
+    /// Length of the header pages at the beginning of the stream.
+    pub header_len: u64,
+    /// Offset of the page to continue from after the headers.
+    pub offset: u64,
+    /// Granule position of that page.
+    pub granule: u64,
+}
+
+/// Index shared by the reader filling it and the decoder seeking with it.  If
+/// it belongs to a cached file, it's stored next to it.
+#[derive(Clone)]
+pub struct SharedSeekIndex {
+    index: Arc<Mutex<OggSeekIndex>>,
+    path: Option<PathBuf>,
+}
+
+impl SharedSeekIndex {
+    /// Load the index stored at `path`, or start a new one there.
+    pub fn open(path: PathBuf) -> Self {
+        let index = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|err| {
+                log::warn!("failed to read seek index {:?}: {}", path, err);
+                OggSeekIndex::default()
+            }),
+            Err(_) => OggSeekIndex::default(),
+        };
+        Self {
+            index: Arc::new(Mutex::new(index)),
+            path: Some(path),
+        }
+    }
+
+    pub fn splice_point(&self, target: u64, max_distance: u64) -> Option<SplicePoint> {
+        self.index.lock().splice_point(target, max_distance)
+    }
+
+    /// Write the index next to the cached file, if anything new was indexed since
+    /// it was loaded.  Indices of streams that aren't cached, named after the file
+    /// they would be cached as, are only kept in memory.
+    pub fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) if path.with_extension("").is_file() => path,
+            _ => return Ok(()),
+        };
+        let mut index = self.index.lock();
+        if !index.is_changed {
+            return Ok(());
+        }
+        let temp_path = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        serde_json::to_writer(&mut writer, &*index)?;
+        writer.flush()?;
+        fs::rename(temp_path, path)?;
+        index.is_changed = false;
+        Ok(())
+    }
+}
+
+/// Reader noting down the pages of the Ogg stream passing through it.  As the
+/// pages are found in whatever the decoder reads, the index grows along with the
+/// playback, and the pages of the downloaded ranges are indexed only once they're
+/// actually read.
+pub struct IndexingReader<R> {
+    inner: R,
+    position: u64,
+    index: SharedSeekIndex,
+}
+
+impl<R> IndexingReader<R> {
+    pub fn new(inner: R, index: SharedSeekIndex) -> Self {
+        Self {
+            inner,
+            position: 0,
+            index,
+        }
+    }
+
+    fn index_pages(&self, buf: &[u8]) {
+        if buf.len() < PAGE_HEADER_LEN {
+            return;
+        }
+        let mut index = self.index.index.lock();
+        for start in 0..=buf.len() - PAGE_HEADER_LEN {
+            let header = &buf[start..start + PAGE_HEADER_LEN];
+            // Captures in the middle of the packet data are improbable, but check
+            // the version and the flags to make them even less so.
+            if &header[..4] != PAGE_CAPTURE_PATTERN || header[4] != 0 || header[5] > 0x07 {
+                continue;
+            }
+            let granule = i64::from_le_bytes(header[6..14].try_into().unwrap());
+            index.insert_page(self.position + start as u64, granule);
+        }
+    }
+}
+
+impl<R: Read> Read for IndexingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        // Pages split between two reads are missed, but there are plenty of them.
+        self.index_pages(&buf[..n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for IndexingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        Ok(self.position)
+    }
+}
+
+/// Stream presenting the header pages of an Ogg stream immediately followed by
+/// the pages from a splice point on.  Starting a demuxer on it avoids any search
+/// for the position, it just continues from the spliced page.  Without a splice
+/// point, the stream is passed through unchanged.
+///
+/// The underlying stream is shared, so it can be spliced again for the next seek,
+/// while the demuxer of the previous one is still around.
+pub struct SplicedReader<R> {
+    inner: Arc<Mutex<R>>,
+    splice: Option<SplicePoint>,
+    position: u64,
+    len: u64,
+}
+
+impl<R: Seek> SplicedReader<R> {
+    pub fn new(inner: Arc<Mutex<R>>, splice: Option<SplicePoint>) -> io::Result<Self> {
+        let inner_len = inner.lock().seek(SeekFrom::End(0))?;
+        let len = match splice {
+            Some(splice) => splice.header_len + inner_len.saturating_sub(splice.offset),
+            None => inner_len,
+        };
+        Ok(Self {
+            inner,
+            splice,
+            position: 0,
+            len,
+        })
+    }
+
+    /// Position in the underlying stream, and how much can be read from it before
+    /// the splice.
+    fn inner_position(&self) -> (u64, Option<u64>) {
+        match self.splice {
+            Some(splice) if self.position < splice.header_len => {
+                (self.position, Some(splice.header_len - self.position))
+            }
+            Some(splice) => (splice.offset + self.position - splice.header_len, None),
+            None => (self.position, None),
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for SplicedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let (position, limit) = self.inner_position();
+        let len = limit.map_or(buf.len(), |limit| buf.len().min(limit as usize));
+        let mut inner = self.inner.lock();
+        inner.seek(SeekFrom::Start(position))?;
+        let n = inner.read(&mut buf[..len])?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Read + Seek> Seek for SplicedReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Header of an Ogg page finishing the frame at `granule`.
+    fn page_header(granule: i64) -> Vec<u8> {
+        let mut header = PAGE_CAPTURE_PATTERN.to_vec();
+        header.extend([0, 0]);
+        header.extend(granule.to_le_bytes());
+        header.resize(PAGE_HEADER_LEN, 0);
+        header
+    }
+
+    fn index_pages(index: &SharedSeekIndex) {
+        let mut reader = IndexingReader::new(io::Cursor::new(page_header(1000)), index.clone());
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+    }
+
+    #[test]
+    fn index_is_saved_only_next_to_cached_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let index_path = dir.path().join("0123abcd.index");
+
+        let index = SharedSeekIndex::open(index_path.clone());
+        index_pages(&index);
+        index.save().unwrap();
+        assert!(!index_path.exists());
+
+        fs::write(dir.path().join("0123abcd"), b"").unwrap();
+        index.save().unwrap();
+        assert!(index_path.exists());
+
+        let loaded = SharedSeekIndex::open(index_path);
+        assert_eq!(loaded.index.lock().pages.get(&1000), Some(&0));
+    }
+}
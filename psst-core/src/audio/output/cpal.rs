@@ -1,19 +1,23 @@
+use std::{mem, sync::Arc, thread, time::Duration};
+
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 use num_traits::Pow;
+use parking_lot::Mutex;
 
 use crate::{
-    actor::{Act, Actor, ActorHandle},
+    actor::{Act, Actor},
     audio::{
-        output::{AudioOutput, AudioSink},
+        output::{AudioOutput, AudioSink, DeviceEvent},
         source::{AudioSource, Empty},
     },
     error::Error,
 };
 
 pub struct CpalOutput {
-    _handle: ActorHandle<StreamMsg>,
     sink: CpalSink,
+    device_name: Option<String>,
+    device_recv: Receiver<DeviceEvent>,
 }
 
 impl CpalOutput {
@@ -23,52 +27,110 @@ impl CpalOutput {
             .default_output_device()
             .ok_or(cpal::DefaultStreamConfigError::DeviceNotAvailable)?;
 
-        if let Ok(name) = device.name() {
+        let device_name = device.name().ok();
+        if let Some(name) = &device_name {
             log::info!("using audio device: {:?}", name);
         }
 
-        // Get the default device config, so we know what sample format and sample rate
-        // the device supports.
-        let supported = Self::preferred_output_config(&device)?;
+        let formats = Self::output_formats(&device)?;
 
         let (callback_send, callback_recv) = bounded(16);
-
-        let handle = Stream::spawn_with_default_cap("audio_output", {
-            let config = supported.config();
-            // TODO: Support additional sample formats.
-            move |this| Stream::open(device, config, callback_recv, this).unwrap()
-        });
+        let (device_send, device_recv) = unbounded();
+        let (format_send, format_recv) = bounded(1);
+        let (stream_send, stream_recv) = bounded(128);
+
+        // The stream has to stay on the thread it's opened on, so it's opened by
+        // the actor itself, which reports back how that went.
+        thread::Builder::new()
+            .name("audio_output".to_string())
+            .spawn({
+                let stream_send = stream_send.clone();
+                move || {
+                    let stream =
+                        Stream::open(device, formats, callback_recv, device_send, stream_send);
+                    match stream {
+                        Ok(stream) => {
+                            let _ = format_send.send(Ok(stream.format.clone()));
+                            stream.process(stream_recv);
+                        }
+                        Err(err) => {
+                            let _ = format_send.send(Err(err));
+                        }
+                    }
+                }
+            })?;
+        let format = format_recv.recv().map_err(|_| Error::SendError)??;
         let sink = CpalSink {
-            channel_count: supported.channels(),
-            sample_rate: supported.sample_rate(),
-            stream_send: handle.sender(),
+            channel_count: format.channels(),
+            sample_rate: format.sample_rate(),
+            stream_send,
             callback_send,
         };
 
         Ok(Self {
-            _handle: handle,
             sink,
+            device_name,
+            device_recv,
         })
     }
 
-    fn preferred_output_config(
-        device: &cpal::Device,
-    ) -> Result<cpal::SupportedStreamConfig, Error> {
-        const PREFERRED_SAMPLE_FORMAT: cpal::SampleFormat = cpal::SampleFormat::F32;
+    /// Formats supported by `device` we can open the stream with, best first.  The
+    /// fallback chain goes:
+    ///
+    /// 1. Sample format: `f32`, which is what the sources produce, then `i16`, which
+    ///    nearly every device takes.  Samples are converted on the way out.
+    /// 2. Sample rate: 44.1 kHz, the rate of the tracks, then the native rate of the
+    ///    device.  The sources get resampled to it, see `FormatNegotiation`.
+    /// 3. Channels: stereo, then whatever else the device has, the channels get
+    ///    mapped.
+    ///
+    /// The default config of the device closes the chain, if it's in a sample
+    /// format we can write.
+    fn output_formats(device: &cpal::Device) -> Result<Vec<cpal::SupportedStreamConfig>, Error> {
+        const SAMPLE_FORMATS: [cpal::SampleFormat; 2] =
+            [cpal::SampleFormat::F32, cpal::SampleFormat::I16];
         const PREFERRED_SAMPLE_RATE: cpal::SampleRate = cpal::SampleRate(44_100);
         const PREFERRED_CHANNELS: cpal::ChannelCount = 2;
 
-        for s in device.supported_output_configs()? {
-            let rates = s.min_sample_rate()..=s.max_sample_rate();
-            if s.channels() == PREFERRED_CHANNELS
-                && s.sample_format() == PREFERRED_SAMPLE_FORMAT
-                && rates.contains(&PREFERRED_SAMPLE_RATE)
-            {
-                return Ok(s.with_sample_rate(PREFERRED_SAMPLE_RATE));
+        let supported: Vec<_> = device.supported_output_configs()?.collect();
+        let default = device
+            .default_output_config()
+            .ok()
+            .filter(|config| SAMPLE_FORMATS.contains(&config.sample_format()));
+        let mut rates = vec![PREFERRED_SAMPLE_RATE];
+        rates.extend(default.as_ref().map(|config| config.sample_rate()));
+
+        let mut formats = Vec::new();
+        for sample_format in SAMPLE_FORMATS {
+            for &rate in &rates {
+                let mut matching: Vec<_> = supported
+                    .iter()
+                    .filter(|range| {
+                        range.sample_format() == sample_format
+                            && (range.min_sample_rate()..=range.max_sample_rate()).contains(&rate)
+                    })
+                    .collect();
+                matching.sort_by_key(|range| range.channels() != PREFERRED_CHANNELS);
+                formats.extend(
+                    matching
+                        .into_iter()
+                        .map(|range| range.clone().with_sample_rate(rate)),
+                );
             }
         }
+        formats.extend(default);
 
-        Ok(device.default_output_config()?)
+        let mut unique = Vec::with_capacity(formats.len());
+        for format in formats {
+            if !unique.contains(&format) {
+                unique.push(format);
+            }
+        }
+        if unique.is_empty() {
+            log::error!("audio device supports none of the sample formats we can write");
+            return Err(cpal::BuildStreamError::StreamConfigNotSupported.into());
+        }
+        Ok(unique)
     }
 }
 
@@ -78,6 +140,14 @@ impl AudioOutput for CpalOutput {
     fn sink(&self) -> Self::Sink {
         self.sink.clone()
     }
+
+    fn device_name(&self) -> Option<String> {
+        self.device_name.clone()
+    }
+
+    fn device_events(&self) -> Receiver<DeviceEvent> {
+        self.device_recv.clone()
+    }
 }
 
 #[derive(Clone)]
@@ -137,44 +207,214 @@ impl AudioSink for CpalSink {
     fn close(&self) {
         self.send_to_stream(StreamMsg::Close);
     }
+
+    fn restart(&self) {
+        self.send_to_stream(StreamMsg::Restart);
+    }
 }
 
+/// How often to look for a disconnected device to come back.
+const DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the default device is checked for changes, to follow it.
+const DEFAULT_DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
 struct Stream {
     stream: cpal::Stream,
-    _device: cpal::Device,
+    device_name: Option<String>,
+    format: cpal::SupportedStreamConfig,
+    // Shared with the data callback of the stream, so a new stream can take over
+    // the playback state when the device is reconnected.
+    callback: Arc<Mutex<StreamCallback>>,
+    stream_send: Sender<StreamMsg>,
+    device_send: Sender<DeviceEvent>,
+    is_playing: bool,
+    is_disconnected: bool,
 }
 
 impl Stream {
+    /// Open the stream in the first of `formats` the device accepts.
     fn open(
         device: cpal::Device,
-        config: cpal::StreamConfig,
+        formats: Vec<cpal::SupportedStreamConfig>,
         callback_recv: Receiver<CallbackMsg>,
+        device_send: Sender<DeviceEvent>,
         stream_send: Sender<StreamMsg>,
     ) -> Result<Self, Error> {
-        let mut callback = StreamCallback {
+        let callback = Arc::new(Mutex::new(StreamCallback {
             callback_recv,
-            stream_send,
             source: Box::new(Empty),
+            buffer: Vec::new(),
             volume: 1.0, // We start with the full volume.
             state: CallbackState::Paused,
+        }));
+        let mut last_err = None;
+        let mut opened = None;
+        for format in formats {
+            match Self::build(&device, &format, &callback, &stream_send) {
+                Ok(stream) => {
+                    opened = Some((stream, format));
+                    break;
+                }
+                Err(err) => {
+                    log::warn!("audio device rejected {:?}: {}", format, err);
+                    last_err = Some(err);
+                }
+            }
+        }
+        let (stream, format) = match opened {
+            Some(opened) => opened,
+            None => {
+                return Err(last_err
+                    .unwrap_or_else(|| cpal::BuildStreamError::StreamConfigNotSupported.into()))
+            }
         };
+        log::info!(
+            "negotiated output format: {:?}, {} Hz, {} channels",
+            format.sample_format(),
+            format.sample_rate().0,
+            format.channels()
+        );
+
+        Ok(Self {
+            stream,
+            device_name: device.name().ok(),
+            format,
+            callback,
+            stream_send,
+            device_send,
+            is_playing: false,
+            is_disconnected: false,
+        })
+    }
 
-        log::info!("opening output stream: {:?}", config);
+    fn build(
+        device: &cpal::Device,
+        format: &cpal::SupportedStreamConfig,
+        callback: &Arc<Mutex<StreamCallback>>,
+        stream_send: &Sender<StreamMsg>,
+    ) -> Result<cpal::Stream, Error> {
+        let config = format.config();
+        log::info!(
+            "opening output stream: {:?}, {:?}",
+            config,
+            format.sample_format()
+        );
+        match format.sample_format() {
+            // The sources write right into the buffer of the stream.
+            cpal::SampleFormat::F32 => {
+                Self::build_with(device, &config, callback, stream_send, StreamCallback::fill)
+            }
+            cpal::SampleFormat::I16 => Self::build_with(
+                device,
+                &config,
+                callback,
+                stream_send,
+                StreamCallback::write_samples::<i16>,
+            ),
+            _ => Err(cpal::BuildStreamError::StreamConfigNotSupported.into()),
+        }
+    }
+
+    fn build_with<T: cpal::SizedSample>(
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        callback: &Arc<Mutex<StreamCallback>>,
+        stream_send: &Sender<StreamMsg>,
+        write: fn(&mut StreamCallback, &mut [T]),
+    ) -> Result<cpal::Stream, Error> {
+        let callback = Arc::clone(callback);
+        let stream_send = stream_send.clone();
         let stream = device.build_output_stream(
-            &config,
-            move |output, _| {
-                callback.write_samples(output);
+            config,
+            move |output: &mut [T], _| {
+                write(&mut callback.lock(), output);
             },
-            |err| {
+            move |err| {
                 log::error!("audio output error: {}", err);
+                if let cpal::StreamError::DeviceNotAvailable = err {
+                    let _ = stream_send.send(StreamMsg::DeviceLost);
+                }
             },
             None,
         )?;
+        Ok(stream)
+    }
 
-        Ok(Self {
-            _device: device,
-            stream,
-        })
+    /// Find the device we were playing to, if it is connected.
+    fn find_device(&self) -> Option<cpal::Device> {
+        let name = self.device_name.as_ref()?;
+        cpal::default_host()
+            .output_devices()
+            .ok()?
+            .find(|device| device.name().ok().as_ref() == Some(name))
+    }
+
+    fn reconnect(&mut self) {
+        let device = match self.find_device() {
+            Some(device) => device,
+            None => return,
+        };
+        if self.reopen(&device) {
+            self.is_disconnected = false;
+            log::info!("audio device reconnected");
+            let _ = self.device_send.send(DeviceEvent::Reconnected);
+        }
+    }
+
+    /// Move the stream over to the default device, if the system switched to
+    /// another one.
+    fn follow_default_device(&mut self) {
+        let device = match cpal::default_host().default_output_device() {
+            Some(device) => device,
+            None => return,
+        };
+        let name = device.name().ok();
+        if name.is_none() || name == self.device_name {
+            return;
+        }
+        log::info!("default audio device changed to {:?}", name);
+        if self.reopen(&device) {
+            self.device_name = name.clone();
+            let _ = self.device_send.send(DeviceEvent::Switched(name));
+        }
+    }
+
+    /// Replace the stream with a new one on `device`, in the same state.
+    fn reopen(&mut self, device: &cpal::Device) -> bool {
+        match Self::build(device, &self.format, &self.callback, &self.stream_send) {
+            Ok(stream) => {
+                if self.is_playing {
+                    if let Err(err) = stream.play() {
+                        log::error!("failed to start stream: {}", err);
+                    }
+                } else {
+                    let _ = stream.pause();
+                }
+                self.stream = stream;
+                true
+            }
+            Err(err) => {
+                log::error!("failed to reopen output stream: {}", err);
+                false
+            }
+        }
+    }
+
+    /// While disconnected, keep polling for the device to come back, otherwise
+    /// for the default device to change.
+    fn next_act(&self) -> Act<Self> {
+        if self.is_disconnected {
+            Act::WaitOr {
+                timeout: DEVICE_POLL_INTERVAL,
+                timeout_msg: StreamMsg::CheckDevice,
+            }
+        } else {
+            Act::WaitOr {
+                timeout: DEFAULT_DEVICE_POLL_INTERVAL,
+                timeout_msg: StreamMsg::CheckDefaultDevice,
+            }
+        }
     }
 }
 
@@ -186,23 +426,56 @@ impl Actor for Stream {
         match msg {
             StreamMsg::Pause => {
                 log::debug!("pausing audio output stream");
+                self.is_playing = false;
                 if let Err(err) = self.stream.pause() {
                     log::error!("failed to stop stream: {}", err);
                 }
-                Ok(Act::Continue)
+                Ok(self.next_act())
             }
             StreamMsg::Resume => {
                 log::debug!("resuming audio output stream");
+                self.is_playing = true;
                 if let Err(err) = self.stream.play() {
                     log::error!("failed to start stream: {}", err);
                 }
-                Ok(Act::Continue)
+                Ok(self.next_act())
             }
             StreamMsg::Close => {
                 log::debug!("closing audio output stream");
                 let _ = self.stream.pause();
                 Ok(Act::Shutdown)
             }
+            StreamMsg::Restart => {
+                // A disconnected device gets reopened once it's back, don't get in
+                // the way of that.
+                if !self.is_disconnected {
+                    if let Some(device) = self.find_device() {
+                        log::info!("restarting audio output stream");
+                        self.reopen(&device);
+                    }
+                }
+                Ok(self.next_act())
+            }
+            StreamMsg::DeviceLost => {
+                if !self.is_disconnected {
+                    log::info!("audio device disconnected");
+                    self.is_disconnected = true;
+                    let _ = self.device_send.send(DeviceEvent::Disconnected);
+                }
+                Ok(self.next_act())
+            }
+            StreamMsg::CheckDevice => {
+                if self.is_disconnected {
+                    self.reconnect();
+                }
+                Ok(self.next_act())
+            }
+            StreamMsg::CheckDefaultDevice => {
+                if !self.is_disconnected {
+                    self.follow_default_device();
+                }
+                Ok(self.next_act())
+            }
         }
     }
 }
@@ -211,6 +484,10 @@ enum StreamMsg {
     Pause,
     Resume,
     Close,
+    Restart,
+    DeviceLost,
+    CheckDevice,
+    CheckDefaultDevice,
 }
 
 enum CallbackMsg {
@@ -226,16 +503,28 @@ enum CallbackState {
 }
 
 struct StreamCallback {
-    #[allow(unused)]
-    stream_send: Sender<StreamMsg>,
     callback_recv: Receiver<CallbackMsg>,
     source: Box<dyn AudioSource>,
+    /// The sources write `f32` samples, for streams in another sample format
+    /// they are converted from here.
+    buffer: Vec<f32>,
     state: CallbackState,
     volume: f32,
 }
 
 impl StreamCallback {
-    fn write_samples(&mut self, output: &mut [f32]) {
+    /// Fill `output` of a stream that doesn't take `f32` samples.
+    fn write_samples<T: cpal::Sample + cpal::FromSample<f32>>(&mut self, output: &mut [T]) {
+        let mut buffer = mem::take(&mut self.buffer);
+        buffer.resize(output.len(), 0.0);
+        self.fill(&mut buffer);
+        for (out, &sample) in output.iter_mut().zip(&buffer) {
+            *out = T::from_sample(sample);
+        }
+        self.buffer = buffer;
+    }
+
+    fn fill(&mut self, output: &mut [f32]) {
         // Process any pending data messages.
         while let Ok(msg) = self.callback_recv.try_recv() {
             match msg {
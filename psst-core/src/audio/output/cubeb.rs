@@ -1,11 +1,11 @@
 use std::{env, ffi::CString, ops::Deref};
 
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, Sender};
 
 use crate::{
     actor::{Act, Actor, ActorHandle},
     audio::{
-        output::{AudioOutput, AudioSink},
+        output::{AudioOutput, AudioSink, DeviceEvent},
         source::{AudioSource, Empty},
     },
     error::Error,
@@ -15,21 +15,27 @@ pub struct CubebOutput {
     #[allow(unused)]
     handle: ActorHandle<StreamMsg>,
     sink: CubebSink,
+    device_recv: Receiver<DeviceEvent>,
 }
 
 impl CubebOutput {
     pub fn open() -> Result<Self, Error> {
         let (callback_send, callback_recv) = bounded(16);
+        let (device_send, device_recv) = unbounded();
 
         let handle = Stream::spawn_with_default_cap("audio_output", {
-            move |_| Stream::open(callback_recv).unwrap()
+            move |_| Stream::open(callback_recv, device_send).unwrap()
         });
         let sink = CubebSink {
             callback_send,
             stream_send: handle.sender(),
         };
 
-        Ok(Self { handle, sink })
+        Ok(Self {
+            handle,
+            sink,
+            device_recv,
+        })
     }
 }
 
@@ -39,6 +45,16 @@ impl AudioOutput for CubebOutput {
     fn sink(&self) -> Self::Sink {
         self.sink.clone()
     }
+
+    fn device_name(&self) -> Option<String> {
+        // We always play to the default device, and cubeb switches between devices
+        // behind our back.
+        None
+    }
+
+    fn device_events(&self) -> Receiver<DeviceEvent> {
+        self.device_recv.clone()
+    }
 }
 
 type Frame = cubeb::StereoFrame<f32>;
@@ -54,7 +70,10 @@ struct Stream {
 }
 
 impl Stream {
-    fn open(callback_recv: Receiver<CallbackMsg>) -> Result<Self, Error> {
+    fn open(
+        callback_recv: Receiver<CallbackMsg>,
+        device_send: Sender<DeviceEvent>,
+    ) -> Result<Self, Error> {
         // Call CoInitialize() before any other calls to the API.
         #[cfg(target_os = "windows")]
         unsafe {
@@ -90,8 +109,14 @@ impl Stream {
                 callback.write_samples(output);
                 output.len() as isize
             })
-            .state_callback(|state| {
+            .state_callback(move |state| {
                 log::debug!("stream state: {:?}", state);
+                // Cubeb follows the default device on its own, so it only errors out when
+                // there is nothing left to play to.  We don't get to know when a device
+                // is back.
+                if matches!(state, cubeb::State::Error) {
+                    let _ = device_send.send(DeviceEvent::Disconnected);
+                }
             });
         let stream = builder.init(&ctx)?;
 
@@ -104,6 +129,7 @@ enum StreamMsg {
     Resume,
     Close,
     SetVolume(f32),
+    Restart,
 }
 
 impl Actor for Stream {
@@ -131,6 +157,16 @@ impl Actor for Stream {
                 let _ = self.stream.stop();
                 Ok(Act::Shutdown)
             }
+            StreamMsg::Restart => {
+                // The stream is bound to its callback, so instead of building a new
+                // one, kick the existing one.
+                log::debug!("restarting audio output stream");
+                let _ = self.stream.stop();
+                if let Err(err) = self.stream.start() {
+                    log::error!("failed to start stream: {}", err);
+                }
+                Ok(Act::Continue)
+            }
             StreamMsg::SetVolume(volume) => {
                 log::debug!("setting volume");
                 if let Err(err) = self.stream.set_volume(volume) {
@@ -184,6 +220,10 @@ impl AudioSink for CubebSink {
     fn close(&self) {
         self.stop();
     }
+
+    fn restart(&self) {
+        self.stream_send.send(StreamMsg::Restart).unwrap();
+    }
 }
 
 enum CallbackMsg {
@@ -1,14 +1,21 @@
+use crossbeam_channel::Receiver;
+
 use crate::audio::source::AudioSource;
 
 #[cfg(feature = "cpal")]
 pub mod cpal;
 #[cfg(feature = "cubeb")]
 pub mod cubeb;
+pub mod negotiate;
+pub mod null;
 
 #[cfg(feature = "cubeb")]
 pub type DefaultAudioOutput = cubeb::CubebOutput;
 #[cfg(feature = "cpal")]
 pub type DefaultAudioOutput = cpal::CpalOutput;
+// Headless builds, for running the pipeline without a sound card.
+#[cfg(all(feature = "null-output", not(any(feature = "cpal", feature = "cubeb"))))]
+pub type DefaultAudioOutput = null::NullOutput;
 
 pub type DefaultAudioSink = <DefaultAudioOutput as AudioOutput>::Sink;
 
@@ -16,6 +23,23 @@ pub trait AudioOutput {
     type Sink: AudioSink;
 
     fn sink(&self) -> Self::Sink;
+
+    /// Name of the device the output is playing to, if the backend knows it.
+    fn device_name(&self) -> Option<String>;
+
+    /// Changes of the output device, as far as the backend is able to tell.
+    fn device_events(&self) -> Receiver<DeviceEvent>;
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeviceEvent {
+    /// The device we are playing to went away, e.g. the headphones were unplugged.
+    Disconnected,
+    /// The device is back, and the output stream was reopened on it.
+    Reconnected,
+    /// The default device of the system changed, and the output stream moved over
+    /// to it.  Carries the name of the new device, if known.
+    Switched(Option<String>),
 }
 
 pub trait AudioSink {
@@ -27,4 +51,7 @@ pub trait AudioSink {
     fn resume(&self);
     fn stop(&self);
     fn close(&self);
+    /// Open the output stream again, for when it quietly stopped asking for
+    /// samples.
+    fn restart(&self);
 }
@@ -0,0 +1,97 @@
+use crate::audio::{
+    output::AudioSink,
+    resample::ResamplingQuality,
+    source::{AudioSource, MuteGuard, ResampledSource, StereoMappedSource},
+};
+
+/// How long the output stays silent after switching to a source of a different
+/// format, before fading the source in.
+const MUTE_GUARD_MS: u32 = 30;
+const FADE_IN_MS: u32 = 20;
+
+/// Channel count and sample rate of a stream of samples.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct StreamFormat {
+    pub channel_count: usize,
+    pub sample_rate: u32,
+}
+
+impl StreamFormat {
+    pub fn of_source(source: &impl AudioSource) -> Self {
+        Self {
+            channel_count: source.channel_count(),
+            sample_rate: source.sample_rate(),
+        }
+    }
+
+    pub fn of_sink(sink: &impl AudioSink) -> Self {
+        Self {
+            channel_count: sink.channel_count(),
+            sample_rate: sink.sample_rate(),
+        }
+    }
+
+    fn samples_in_ms(&self, ms: u32) -> usize {
+        (self.sample_rate * ms / 1_000) as usize * self.channel_count
+    }
+}
+
+/// Decides how the sources are converted to the format of the output, one track
+/// after another.  The output keeps its format, so sources of other formats are
+/// resampled and their channels mapped.  Whenever the format of the sources
+/// changes between two tracks, the new one starts behind a short mute guard, so
+/// the switch doesn't click.
+pub struct FormatNegotiation {
+    last_format: Option<StreamFormat>,
+}
+
+impl FormatNegotiation {
+    pub fn new() -> Self {
+        Self { last_format: None }
+    }
+
+    pub fn negotiate(
+        &mut self,
+        source: impl AudioSource,
+        sink: &impl AudioSink,
+    ) -> Box<dyn AudioSource> {
+        let source_format = StreamFormat::of_source(&source);
+        let output_format = StreamFormat::of_sink(sink);
+        let format_changed = self
+            .last_format
+            .replace(source_format)
+            .map_or(false, |last_format| last_format != source_format);
+
+        let mut source: Box<dyn AudioSource> = Box::new(source);
+        if source_format.sample_rate != output_format.sample_rate {
+            log::debug!(
+                "resampling from {} Hz to {} Hz",
+                source_format.sample_rate,
+                output_format.sample_rate
+            );
+            source = Box::new(ResampledSource::new(
+                source,
+                output_format.sample_rate,
+                ResamplingQuality::SincMediumQuality,
+            ));
+        }
+        if source_format.channel_count != output_format.channel_count {
+            source = Box::new(StereoMappedSource::new(source, output_format.channel_count));
+        }
+        if format_changed {
+            log::debug!("source format changed to {:?}", source_format);
+            source = Box::new(MuteGuard::new(
+                source,
+                output_format.samples_in_ms(MUTE_GUARD_MS),
+                output_format.samples_in_ms(FADE_IN_MS),
+            ));
+        }
+        source
+    }
+}
+
+impl Default for FormatNegotiation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use byteorder::{WriteBytesExt, LE};
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use num_traits::Pow;
+use parking_lot::Mutex;
+
+use crate::{
+    audio::{
+        output::{AudioOutput, AudioSink, DeviceEvent},
+        source::{AudioSource, Empty},
+    },
+    error::Error,
+};
+
+/// How much of the output is kept in the recording, older samples are dropped.
+const MAX_RECORDING_DURATION: Duration = Duration::from_secs(2 * 60);
+
+/// Pace of the clock rendering the output in real time.
+const CLOCK_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Output without an audio device.  Instead of being played, the samples are
+/// recorded, so the result of the whole pipeline can be inspected or written
+/// into a WAV file.
+///
+/// `new` creates an output that renders only when asked to with `render`,
+/// which makes it usable from tests.  `open` starts a clock rendering it in
+/// real time, the same way a device would consume the samples.
+pub struct NullOutput {
+    sink: NullSink,
+    _device_send: Sender<DeviceEvent>,
+    device_recv: Receiver<DeviceEvent>,
+}
+
+impl NullOutput {
+    pub fn open() -> Result<Self, Error> {
+        const SAMPLE_RATE: u32 = 44_100;
+        const CHANNELS: usize = 2;
+
+        let output = Self::new(CHANNELS, SAMPLE_RATE);
+        let sink = output.sink();
+        thread::Builder::new()
+            .name("null_output".to_string())
+            .spawn(move || sink.run_clock())?;
+        log::info!("using null audio output");
+        Ok(output)
+    }
+
+    pub fn new(channel_count: usize, sample_rate: u32) -> Self {
+        let (device_send, device_recv) = unbounded();
+        let max_recording_len =
+            MAX_RECORDING_DURATION.as_secs() as usize * sample_rate as usize * channel_count;
+        Self {
+            sink: NullSink {
+                channel_count,
+                sample_rate,
+                state: Arc::new(Mutex::new(SinkState {
+                    source: Box::new(Empty),
+                    volume: 1.0,
+                    is_playing: false,
+                    is_closed: false,
+                    recording: VecDeque::new(),
+                    max_recording_len,
+                    buffer: Vec::new(),
+                })),
+            },
+            _device_send: device_send,
+            device_recv,
+        }
+    }
+
+    /// Render `frames` frames of the output, as if a device asked for them.
+    /// Returns the number of frames that came from the playing source, the rest is
+    /// silence.
+    pub fn render(&self, frames: usize) -> usize {
+        let mut state = self.sink.state.lock();
+        state.render(frames * self.sink.channel_count) / self.sink.channel_count
+    }
+
+    /// Interleaved samples rendered so far.
+    pub fn recording(&self) -> Vec<f32> {
+        self.sink.state.lock().recording.iter().copied().collect()
+    }
+
+    pub fn clear_recording(&self) {
+        self.sink.state.lock().recording.clear();
+    }
+
+    /// Write the recording as a 32-bit float WAV file.
+    pub fn write_wav(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write_wav(
+            &mut writer,
+            self.sink.channel_count,
+            self.sink.sample_rate,
+            &self.recording(),
+        )?;
+        writer.flush()
+    }
+}
+
+impl AudioOutput for NullOutput {
+    type Sink = NullSink;
+
+    fn sink(&self) -> Self::Sink {
+        self.sink.clone()
+    }
+
+    fn device_name(&self) -> Option<String> {
+        None
+    }
+
+    fn device_events(&self) -> Receiver<DeviceEvent> {
+        self.device_recv.clone()
+    }
+}
+
+#[derive(Clone)]
+pub struct NullSink {
+    channel_count: usize,
+    sample_rate: u32,
+    state: Arc<Mutex<SinkState>>,
+}
+
+impl NullSink {
+    fn run_clock(&self) {
+        let started_at = Instant::now();
+        let mut rendered_frames = 0;
+        loop {
+            thread::sleep(CLOCK_INTERVAL);
+            let mut state = self.state.lock();
+            if state.is_closed {
+                break;
+            }
+            // Catch up with the wall clock, so oversleeping doesn't slow the
+            // playback down.
+            let elapsed_frames =
+                (started_at.elapsed().as_secs_f64() * self.sample_rate as f64) as usize;
+            let frames = elapsed_frames - rendered_frames;
+            state.render(frames * self.channel_count);
+            rendered_frames = elapsed_frames;
+        }
+    }
+}
+
+impl AudioSink for NullSink {
+    fn channel_count(&self) -> usize {
+        self.channel_count
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.state.lock().volume = volume;
+    }
+
+    fn play(&self, source: impl AudioSource) {
+        self.state.lock().source = Box::new(source);
+    }
+
+    fn pause(&self) {
+        self.state.lock().is_playing = false;
+    }
+
+    fn resume(&self) {
+        self.state.lock().is_playing = true;
+    }
+
+    fn stop(&self) {
+        self.play(Empty);
+        self.pause();
+    }
+
+    fn close(&self) {
+        self.state.lock().is_closed = true;
+    }
+
+    fn restart(&self) {
+        // The clock never stops on its own.
+    }
+}
+
+struct SinkState {
+    source: Box<dyn AudioSource>,
+    volume: f32,
+    is_playing: bool,
+    is_closed: bool,
+    recording: VecDeque<f32>,
+    max_recording_len: usize,
+    buffer: Vec<f32>,
+}
+
+impl SinkState {
+    /// Same as the data callback of the real outputs: write as many samples of
+    /// the source as possible, scale them by the volume, and fill the rest with
+    /// silence.  Returns the number of samples taken from the source.
+    fn render(&mut self, len: usize) -> usize {
+        self.buffer.resize(len, 0.0);
+        let written = if self.is_playing {
+            let written = self.source.write(&mut self.buffer);
+            let scaled_volume = self.volume.pow(4);
+            self.buffer[..written]
+                .iter_mut()
+                .for_each(|s| *s *= scaled_volume);
+            written
+        } else {
+            0
+        };
+        self.buffer[written..].iter_mut().for_each(|s| *s = 0.0);
+
+        self.recording.extend(&self.buffer);
+        let excess = self.recording.len().saturating_sub(self.max_recording_len);
+        self.recording.drain(..excess);
+        written
+    }
+}
+
+/// Write interleaved samples as a WAV file in the IEEE float format.
+pub fn write_wav(
+    mut writer: impl Write,
+    channel_count: usize,
+    sample_rate: u32,
+    samples: &[f32],
+) -> io::Result<()> {
+    const FORMAT_IEEE_FLOAT: u16 = 3;
+    const BYTES_PER_SAMPLE: u32 = 4;
+    const FMT_CHUNK_LEN: u32 = 16;
+
+    let channel_count = channel_count as u32;
+    let data_len = samples.len() as u32 * BYTES_PER_SAMPLE;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_u32::<LE>(4 + (8 + FMT_CHUNK_LEN) + (8 + data_len))?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_u32::<LE>(FMT_CHUNK_LEN)?;
+    writer.write_u16::<LE>(FORMAT_IEEE_FLOAT)?;
+    writer.write_u16::<LE>(channel_count as u16)?;
+    writer.write_u32::<LE>(sample_rate)?;
+    writer.write_u32::<LE>(sample_rate * channel_count * BYTES_PER_SAMPLE)?;
+    writer.write_u16::<LE>((channel_count * BYTES_PER_SAMPLE) as u16)?;
+    writer.write_u16::<LE>((BYTES_PER_SAMPLE * 8) as u16)?;
+
+    writer.write_all(b"data")?;
+    writer.write_u32::<LE>(data_len)?;
+    for &sample in samples {
+        writer.write_f32::<LE>(sample)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use byteorder::ReadBytesExt;
+
+    use super::*;
+    use crate::audio::output::negotiate::FormatNegotiation;
+
+    const RATE: u32 = 44_100;
+
+    struct Samples {
+        samples: Vec<f32>,
+        position: usize,
+        channel_count: usize,
+    }
+
+    impl Samples {
+        fn new(channel_count: usize, samples: Vec<f32>) -> Self {
+            Self {
+                samples,
+                position: 0,
+                channel_count,
+            }
+        }
+    }
+
+    impl AudioSource for Samples {
+        fn write(&mut self, output: &mut [f32]) -> usize {
+            let left = &self.samples[self.position..];
+            let len = left.len().min(output.len());
+            output[..len].copy_from_slice(&left[..len]);
+            self.position += len;
+            len
+        }
+
+        fn channel_count(&self) -> usize {
+            self.channel_count
+        }
+
+        fn sample_rate(&self) -> u32 {
+            RATE
+        }
+    }
+
+    fn playing(output: &NullOutput, source: impl AudioSource) {
+        let sink = output.sink();
+        sink.play(source);
+        sink.resume();
+    }
+
+    #[test]
+    fn renders_source_then_silence() {
+        let output = NullOutput::new(2, RATE);
+        playing(&output, Samples::new(2, vec![0.5; 20]));
+
+        assert_eq!(output.render(15), 10);
+        let recording = output.recording();
+        assert_eq!(recording.len(), 30);
+        assert!(recording[..20].iter().all(|&s| s == 0.5));
+        assert!(recording[20..].iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn renders_silence_while_paused() {
+        let output = NullOutput::new(2, RATE);
+        playing(&output, Samples::new(2, vec![0.5; 20]));
+        output.sink().pause();
+
+        assert_eq!(output.render(5), 0);
+        assert_eq!(output.recording(), [0.0; 10]);
+
+        output.sink().resume();
+        assert_eq!(output.render(5), 5);
+    }
+
+    #[test]
+    fn scales_samples_by_volume() {
+        let output = NullOutput::new(2, RATE);
+        playing(&output, Samples::new(2, vec![1.0; 4]));
+        output.sink().set_volume(0.5);
+
+        output.render(2);
+        assert_eq!(output.recording(), [0.0625; 4]);
+    }
+
+    #[test]
+    fn renders_negotiated_mono_source_on_both_channels() {
+        let output = NullOutput::new(2, RATE);
+        let source = FormatNegotiation::new()
+            .negotiate(Samples::new(1, vec![0.1, 0.2, 0.3]), &output.sink());
+        playing(&output, source);
+
+        assert_eq!(output.render(4), 3);
+        assert_eq!(output.recording(), [0.1, 0.1, 0.2, 0.2, 0.3, 0.3, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn recording_keeps_the_latest_samples() {
+        // Two minutes at 10 Hz, in mono.
+        let output = NullOutput::new(1, 10);
+        let samples: Vec<f32> = (0..1500).map(|i| i as f32).collect();
+        playing(&output, Samples::new(1, samples));
+
+        output.render(1500);
+        let recording = output.recording();
+        assert_eq!(recording.len(), 1200);
+        assert_eq!(recording[0], 300.0);
+        assert_eq!(recording[1199], 1499.0);
+
+        output.clear_recording();
+        assert!(output.recording().is_empty());
+    }
+
+    fn read_tag(reader: &mut impl Read) -> [u8; 4] {
+        let mut tag = [0; 4];
+        reader.read_exact(&mut tag).unwrap();
+        tag
+    }
+
+    #[test]
+    fn wav_round_trip() {
+        let output = NullOutput::new(2, RATE);
+        playing(&output, Samples::new(2, vec![0.25, -0.25, 1.0, -1.0]));
+        output.render(3);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        output.write_wav(file.path()).unwrap();
+
+        let mut reader = Cursor::new(std::fs::read(file.path()).unwrap());
+        let len = reader.get_ref().len() as u32;
+        assert_eq!(&read_tag(&mut reader), b"RIFF");
+        assert_eq!(reader.read_u32::<LE>().unwrap(), len - 8);
+        assert_eq!(&read_tag(&mut reader), b"WAVE");
+
+        assert_eq!(&read_tag(&mut reader), b"fmt ");
+        assert_eq!(reader.read_u32::<LE>().unwrap(), 16);
+        assert_eq!(reader.read_u16::<LE>().unwrap(), 3); // IEEE float
+        assert_eq!(reader.read_u16::<LE>().unwrap(), 2);
+        assert_eq!(reader.read_u32::<LE>().unwrap(), RATE);
+        assert_eq!(reader.read_u32::<LE>().unwrap(), RATE * 2 * 4);
+        assert_eq!(reader.read_u16::<LE>().unwrap(), 8);
+        assert_eq!(reader.read_u16::<LE>().unwrap(), 32);
+
+        assert_eq!(&read_tag(&mut reader), b"data");
+        let data_len = reader.read_u32::<LE>().unwrap();
+        assert_eq!(data_len, 6 * 4);
+        let samples: Vec<f32> = (0..data_len / 4)
+            .map(|_| reader.read_f32::<LE>().unwrap())
+            .collect();
+        assert_eq!(samples, output.recording());
+        assert_eq!(samples, [0.25, -0.25, 1.0, -1.0, 0.0, 0.0]);
+        assert_eq!(reader.position(), len as u64);
+    }
+}
@@ -5,15 +5,106 @@ use std::time::Duration;
 use symphonia::core::codecs::CodecType;
 use symphonia::core::formats::FormatOptions;
 use symphonia::core::io::{MediaSourceStream, MediaSourceStreamOptions};
-use symphonia::core::meta::MetadataOptions;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, Tag};
 use symphonia::core::probe::{Hint, Probe};
-use symphonia::default::formats::{MpaReader, OggReader};
 
+use super::chapters::Chapter;
 use crate::error::Error;
 
 pub struct TrackProbe {
     pub codec: CodecType,
     pub duration: Option<Duration>,
+    pub tags: TrackTags,
+    /// Chapters embedded in the container.
+    pub chapters: Vec<Chapter>,
+}
+
+/// Tags of a local file, as far as they are present.
+#[derive(Debug, Clone, Default)]
+pub struct TrackTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub replay_gain: ReplayGain,
+}
+
+/// Loudness tags of a local file.  Gains are in dB, relative to the ReplayGain
+/// reference level, and peaks are linear sample amplitudes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReplayGain {
+    pub track_gain_db: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain_db: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl TrackTags {
+    fn read(&mut self, revision: &MetadataRevision) {
+        self.read_tags(revision.tags());
+    }
+
+    fn read_tags(&mut self, tags: &[Tag]) {
+        for tag in tags {
+            let value = tag.value.to_string();
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => self.title = Some(value.to_string()),
+                Some(StandardTagKey::Artist) => self.artist = Some(value.to_string()),
+                Some(StandardTagKey::Album) => self.album = Some(value.to_string()),
+                Some(StandardTagKey::AlbumArtist) => self.album_artist = Some(value.to_string()),
+                Some(StandardTagKey::TrackNumber) => self.track_number = parse_number(value),
+                Some(StandardTagKey::DiscNumber) => self.disc_number = parse_number(value),
+                Some(StandardTagKey::ReplayGainTrackGain) => {
+                    self.replay_gain.track_gain_db = parse_gain(value)
+                }
+                Some(StandardTagKey::ReplayGainTrackPeak) => {
+                    self.replay_gain.track_peak = value.parse().ok()
+                }
+                Some(StandardTagKey::ReplayGainAlbumGain) => {
+                    self.replay_gain.album_gain_db = parse_gain(value)
+                }
+                Some(StandardTagKey::ReplayGainAlbumPeak) => {
+                    self.replay_gain.album_peak = value.parse().ok()
+                }
+                // Opus files carry R128 gains instead, see RFC 7845.
+                _ if tag.key.eq_ignore_ascii_case("R128_TRACK_GAIN") => {
+                    self.replay_gain.track_gain_db = parse_r128_gain(value)
+                }
+                _ if tag.key.eq_ignore_ascii_case("R128_ALBUM_GAIN") => {
+                    self.replay_gain.album_gain_db = parse_r128_gain(value)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parse numbers like "3" or "3/12".
+fn parse_number(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// Parse ReplayGain values like "-6.48 dB".
+fn parse_gain(value: &str) -> Option<f32> {
+    let value = value
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .trim();
+    value.parse().ok()
+}
+
+/// R128 gains are Q7.8 fixed-point numbers relative to -23 LUFS, while ReplayGain
+/// uses a reference of about -18 LUFS.
+fn parse_r128_gain(value: &str) -> Option<f32> {
+    const R128_TO_REPLAY_GAIN_DB: f32 = 5.0;
+
+    let gain = value.parse::<i16>().ok()?;
+    Some(f32::from(gain) / 256.0 + R128_TO_REPLAY_GAIN_DB)
 }
 
 macro_rules! probe_err {
@@ -31,10 +122,10 @@ macro_rules! probe_err {
 
 impl TrackProbe {
     pub fn new(path: &PathBuf) -> Result<Self, Error> {
-        // Register all supported file formats for detection.
+        // Register all supported file formats for detection, together with the
+        // readers of the tags preceding them (ID3v2).
         let mut probe = Probe::default();
-        probe.register_all::<MpaReader>();
-        probe.register_all::<OggReader>();
+        symphonia::default::register_enabled_formats(&mut probe);
 
         let mut hint = Hint::new();
         if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
@@ -47,7 +138,7 @@ impl TrackProbe {
 
         let fmt_opts = FormatOptions::default();
         let meta_opts = MetadataOptions::default();
-        let probe_result = probe
+        let mut probe_result = probe
             .format(&hint, mss, &fmt_opts, &meta_opts)
             .map_err(|_| probe_err!("failed to probe file"))?;
         let track = probe_result
@@ -66,9 +157,50 @@ impl TrackProbe {
                 None
             };
 
+        let codec = params.codec;
+        let time_base = params.time_base;
+
+        // Tags can be both in front of the container and inside of it, the latter
+        // take precedence.
+        let mut tags = TrackTags::default();
+        if let Some(revision) = probe_result
+            .metadata
+            .get()
+            .as_ref()
+            .and_then(|m| m.current())
+        {
+            tags.read(revision);
+        }
+        if let Some(revision) = probe_result.format.metadata().current() {
+            tags.read(revision);
+        }
+
+        let chapters = time_base
+            .map(|time_base| {
+                probe_result
+                    .format
+                    .cues()
+                    .iter()
+                    .map(|cue| {
+                        let time = time_base.calc_time(cue.start_ts);
+                        let mut tags = TrackTags::default();
+                        tags.read_tags(&cue.tags);
+                        Chapter {
+                            title: tags.title,
+                            performer: tags.artist,
+                            start: Duration::from_secs(time.seconds)
+                                + Duration::from_secs_f64(time.frac),
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
         Ok(Self {
-            codec: params.codec,
+            codec,
             duration,
+            tags,
+            chapters,
         })
     }
 }
@@ -13,6 +13,20 @@ pub trait AudioSource: Send + 'static {
     fn sample_rate(&self) -> u32;
 }
 
+impl AudioSource for Box<dyn AudioSource> {
+    fn write(&mut self, output: &mut [f32]) -> usize {
+        self.as_mut().write(output)
+    }
+
+    fn channel_count(&self) -> usize {
+        self.as_ref().channel_count()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.as_ref().sample_rate()
+    }
+}
+
 /// Empty audio source. Does not produce any samples.
 pub struct Empty;
 
@@ -65,12 +79,18 @@ where
         let input = &self.buffer[..written];
         let input_frames = input.chunks_exact(self.input_channels);
         let output_frames = output.chunks_exact_mut(self.output_channels);
+        let mut frames = 0;
         for (i, o) in input_frames.zip(output_frames) {
-            o[0] = i[0];
-            o[1] = i[1];
-            // Assume the rest is is implicitly silence.
+            o.fill(0.0);
+            if self.input_channels == 1 {
+                // Play mono sources on both of the stereo channels.
+                o.iter_mut().take(2).for_each(|o| *o = i[0]);
+            } else {
+                o.iter_mut().zip(i).take(2).for_each(|(o, i)| *o = *i);
+            }
+            frames += 1;
         }
-        output.len()
+        frames * self.output_channels
     }
 
     fn channel_count(&self) -> usize {
@@ -163,6 +183,64 @@ where
     }
 }
 
+/// Source starting with a moment of silence, after which `source` fades in.
+/// Covers up the discontinuity when the output switches between sources.
+pub struct MuteGuard<S> {
+    source: S,
+    silence_left: usize,
+    fade_len: usize,
+    faded: usize,
+}
+
+impl<S> MuteGuard<S>
+where
+    S: AudioSource,
+{
+    /// Both lengths are in samples, and get rounded down to whole frames.
+    pub fn new(source: S, silence_len: usize, fade_len: usize) -> Self {
+        let channels = source.channel_count().max(1);
+        Self {
+            silence_left: silence_len / channels * channels,
+            fade_len: fade_len / channels * channels,
+            faded: 0,
+            source,
+        }
+    }
+}
+
+impl<S> AudioSource for MuteGuard<S>
+where
+    S: AudioSource,
+{
+    fn write(&mut self, output: &mut [f32]) -> usize {
+        if self.silence_left > 0 {
+            let silent = self.silence_left.min(output.len());
+            let silent = silent - silent % self.channel_count().max(1);
+            output[..silent].fill(0.0);
+            self.silence_left -= silent;
+            return silent;
+        }
+        let written = self.source.write(output);
+        if self.faded < self.fade_len {
+            let channels = self.channel_count().max(1);
+            for frame in output[..written].chunks_mut(channels) {
+                let gain = (self.faded as f32 / self.fade_len as f32).min(1.0);
+                frame.iter_mut().for_each(|s| *s *= gain);
+                self.faded += channels;
+            }
+        }
+        written
+    }
+
+    fn channel_count(&self) -> usize {
+        self.source.channel_count()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+}
+
 struct Buf {
     buf: Vec<f32>,
     start: usize,
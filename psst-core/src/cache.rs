@@ -1,7 +1,10 @@
 use std::{
-    fs, io,
+    collections::HashSet,
+    fs,
+    io::{self, Write},
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, SystemTime},
 };
 
 use crate::{
@@ -102,6 +105,55 @@ impl Cache {
         self.base.join("audio").join(file_id.to_base16())
     }
 
+    /// Page index of a cached Ogg file, built while it's played.
+    pub fn seek_index_path(&self, file_id: FileId) -> PathBuf {
+        self.base
+            .join("audio")
+            .join(format!("{}.index", file_id.to_base16()))
+    }
+
+    /// Audio file being downloaded for offline playback.  It's renamed to the
+    /// cached file once complete, until then the download resumes from its end.
+    pub fn partial_audio_file_path(&self, file_id: FileId) -> PathBuf {
+        self.base
+            .join("audio")
+            .join(format!("{}.part", file_id.to_base16()))
+    }
+
+    /// Marks an audio file downloaded for offline playback, the garbage collection
+    /// never removes it.
+    fn pin_path(&self, file_id: FileId) -> PathBuf {
+        self.base
+            .join("audio")
+            .join(format!("{}.pinned", file_id.to_base16()))
+    }
+
+    /// Keep the audio file in the cache even when it's over the size limit.
+    pub fn pin_audio_file(&self, file_id: FileId) -> Result<(), Error> {
+        fs::write(self.pin_path(file_id), [])?;
+        Ok(())
+    }
+
+    /// Space taken by the audio files downloaded for offline playback, including
+    /// the ones still being downloaded.  Files cached from streaming don't count.
+    pub fn downloaded_size(&self) -> Result<u64, Error> {
+        let audio = self.audio_entries()?;
+        let pinned: HashSet<&str> = audio
+            .iter()
+            .filter(|entry| entry.extension.as_deref() == Some("pinned"))
+            .map(|entry| entry.stem.as_str())
+            .collect();
+        Ok(audio
+            .iter()
+            .filter(|entry| match entry.extension.as_deref() {
+                None => pinned.contains(entry.stem.as_str()),
+                Some("part") => true,
+                _ => false,
+            })
+            .map(|entry| entry.len)
+            .sum())
+    }
+
     pub fn save_audio_file(&self, file_id: FileId, from_path: PathBuf) -> Result<(), Error> {
         log::debug!("saving audio file to cache: {:?}", file_id);
         fs::copy(from_path, self.audio_file_path(file_id))?;
@@ -125,6 +177,218 @@ impl Cache {
     }
 }
 
+/// Partial downloads not touched for this long are left over from interrupted
+/// downloads, and are removed.
+const ABANDONED_PARTIAL_FILE_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GcPhase {
+    /// Removing leftovers of interrupted downloads and of evicted files.
+    Orphans,
+    /// Evicting the least recently used audio files over the size limit.
+    SizeLimit,
+    /// Removing unreadable metadata entries.
+    Metadata,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GcProgress {
+    pub phase: GcPhase,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GcStats {
+    pub removed_files: usize,
+    pub freed_bytes: u64,
+}
+
+impl GcStats {
+    fn remove(&mut self, path: &Path, len: u64) {
+        match fs::remove_file(path) {
+            Ok(_) => {
+                self.removed_files += 1;
+                self.freed_bytes += len;
+            }
+            Err(err) => log::warn!("failed to remove {:?} from cache: {}", path, err),
+        }
+    }
+}
+
+struct AudioEntry {
+    path: PathBuf,
+    stem: String,
+    extension: Option<String>,
+    len: u64,
+    used_at: SystemTime,
+}
+
+// Garbage collection.
+impl Cache {
+    /// Clean up the cache: remove leftovers of interrupted downloads, evict the
+    /// least recently used audio files until the cache fits into `max_size`
+    /// bytes, and drop metadata entries that can't be read.  Pinned audio files
+    /// are never evicted.  Errors with single files are logged and skipped.
+    /// Takes a while with a large cache, so it's meant to run in the background.
+    pub fn collect_garbage(
+        &self,
+        max_size: Option<u64>,
+        mut progress: impl FnMut(GcProgress),
+    ) -> Result<GcStats, Error> {
+        let mut stats = GcStats::default();
+        let mut audio = self.audio_entries()?;
+        let stems_with = |audio: &[AudioEntry], extension: Option<&str>| -> HashSet<String> {
+            audio
+                .iter()
+                .filter(|entry| entry.extension.as_deref() == extension)
+                .map(|entry| entry.stem.clone())
+                .collect()
+        };
+
+        // Partial downloads and files belonging to audio that isn't there anymore.
+        let present = stems_with(&audio, None);
+        let now = SystemTime::now();
+        let total = audio.len();
+        let mut done = 0;
+        audio.retain(|entry| {
+            let is_orphan = match entry.extension.as_deref() {
+                Some("part") => now
+                    .duration_since(entry.used_at)
+                    .map_or(false, |age| age > ABANDONED_PARTIAL_FILE_AGE),
+                Some("index") | Some("pinned") => !present.contains(&entry.stem),
+                _ => false,
+            };
+            if is_orphan {
+                stats.remove(&entry.path, entry.len);
+            }
+            done += 1;
+            progress(GcProgress {
+                phase: GcPhase::Orphans,
+                done,
+                total,
+            });
+            !is_orphan
+        });
+
+        // Least recently used audio, until the cache fits.
+        if let Some(max_size) = max_size {
+            let pinned = stems_with(&audio, Some("pinned"));
+            let mut size = fs_size(&self.base);
+            let mut evictable: Vec<&AudioEntry> = audio
+                .iter()
+                .filter(|entry| entry.extension.is_none() && !pinned.contains(&entry.stem))
+                .collect();
+            evictable.sort_by_key(|entry| entry.used_at);
+            let total = evictable.len();
+            for (done, entry) in evictable.into_iter().enumerate() {
+                if size <= max_size {
+                    break;
+                }
+                let freed_before = stats.freed_bytes;
+                stats.remove(&entry.path, entry.len);
+                // The seek index is useless without the file.
+                let index_path = entry.path.with_extension("index");
+                if let Ok(metadata) = fs::metadata(&index_path) {
+                    stats.remove(&index_path, metadata.len());
+                }
+                size = size.saturating_sub(stats.freed_bytes - freed_before);
+                progress(GcProgress {
+                    phase: GcPhase::SizeLimit,
+                    done: done + 1,
+                    total,
+                });
+            }
+        }
+
+        // Metadata that fails to parse, most likely written only partially.
+        let metadata = self.metadata_entries()?;
+        let total = metadata.len();
+        for (done, (path, kind)) in metadata.into_iter().enumerate() {
+            let is_valid = fs::read(&path).map_or(false, |buf| match kind {
+                "track" => deserialize_protobuf::<Track>(&buf).is_ok(),
+                "episode" => deserialize_protobuf::<Episode>(&buf).is_ok(),
+                _ => AudioKey::from_raw(&buf).is_some(),
+            });
+            if !is_valid {
+                let len = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+                stats.remove(&path, len);
+            }
+            progress(GcProgress {
+                phase: GcPhase::Metadata,
+                done: done + 1,
+                total,
+            });
+        }
+
+        log::info!(
+            "cache garbage collected, removed {} files, {} bytes",
+            stats.removed_files,
+            stats.freed_bytes
+        );
+        Ok(stats)
+    }
+
+    fn audio_entries(&self) -> io::Result<Vec<AudioEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(self.base.join("audio"))? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let stem = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(stem) => stem.to_string(),
+                None => continue,
+            };
+            let extension = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(str::to_string);
+            // Access times are often not updated, so whichever is later counts.
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            let used_at = metadata
+                .accessed()
+                .map_or(modified, |accessed| accessed.max(modified));
+            entries.push(AudioEntry {
+                path,
+                stem,
+                extension,
+                len: metadata.len(),
+                used_at,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn metadata_entries(&self) -> io::Result<Vec<(PathBuf, &'static str)>> {
+        let mut entries = Vec::new();
+        for kind in ["track", "episode", "key"] {
+            for entry in fs::read_dir(self.base.join(kind))? {
+                entries.push((entry?.path(), kind));
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Size of all files under `path`, unreadable entries are skipped.
+fn fs_size(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| fs_size(&entry.path()))
+                    .sum()
+            })
+            .unwrap_or(0),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
 pub fn mkdir_if_not_exists(path: &Path) -> io::Result<()> {
     fs::create_dir(path).or_else(|err| {
         if err.kind() == io::ErrorKind::AlreadyExists {
@@ -134,3 +398,66 @@ pub fn mkdir_if_not_exists(path: &Path) -> io::Result<()> {
         }
     })
 }
+
+/// Overwrite the files at `path` with zeros before removing them, so that their
+/// content isn't left behind in the free space of the disk.  Directories are
+/// removed with everything in them.  SSDs and copy-on-write filesystems might
+/// keep the old blocks around regardless, this is as far as a program can go.
+pub fn secure_remove(path: &Path) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    if metadata.is_dir() {
+        // Keep going after a failure, so as much as possible is removed.
+        let mut result = Ok(());
+        for entry in fs::read_dir(path)? {
+            let entry_result = entry.and_then(|entry| secure_remove(&entry.path()));
+            if result.is_ok() {
+                result = entry_result;
+            }
+        }
+        result.and_then(|_| fs::remove_dir(path))
+    } else {
+        if metadata.is_file() {
+            overwrite_with_zeros(path, metadata.len())?;
+        }
+        fs::remove_file(path)
+    }
+}
+
+fn overwrite_with_zeros(path: &Path, len: u64) -> io::Result<()> {
+    const CHUNK_LEN: u64 = 64 * 1024;
+
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    let zeros = [0; CHUNK_LEN as usize];
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(CHUNK_LEN);
+        file.write_all(&zeros[..n as usize])?;
+        remaining -= n;
+    }
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downloaded_size_counts_only_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new(dir.path().to_path_buf()).unwrap();
+        let streamed = FileId([1; 20]);
+        let downloaded = FileId([2; 20]);
+        let downloading = FileId([3; 20]);
+        fs::write(cache.audio_file_path(streamed), [0; 100]).unwrap();
+        fs::write(cache.seek_index_path(streamed), [0; 10]).unwrap();
+        fs::write(cache.audio_file_path(downloaded), [0; 200]).unwrap();
+        cache.pin_audio_file(downloaded).unwrap();
+        fs::write(cache.partial_audio_file_path(downloading), [0; 50]).unwrap();
+
+        assert_eq!(cache.downloaded_size().unwrap(), 250);
+    }
+}
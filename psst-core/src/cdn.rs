@@ -4,33 +4,72 @@ use std::{
     time::{Duration, Instant},
 };
 
+use parking_lot::Mutex;
 use serde::Deserialize;
+use url::Url;
 
 use crate::{
     error::Error,
     item_id::FileId,
+    net,
     session::{access_token::TokenProvider, SessionService},
     util::default_ureq_agent_builder,
 };
 
 pub type CdnHandle = Arc<Cdn>;
 
+const STORAGE_RESOLVE_ORIGIN: &str = "https://api.spotify.com";
+
+// `ureq` only speaks HTTP/1.1, so a connection can't be shared by the ranges that are
+// downloaded in parallel.  Keep enough of them idle in the pool, so each range can
+// reuse one instead of paying for a new TCP and TLS handshake.
+const MAX_IDLE_CONNECTIONS_PER_HOST: usize = 6;
+
 pub struct Cdn {
     session: SessionService,
     agent: ureq::Agent,
     token_provider: TokenProvider,
+    // Origin of the last file URL, the next one is usually served from the same host.
+    last_origin: Mutex<Option<String>>,
+    throughput: Mutex<Throughput>,
 }
 
 impl Cdn {
     pub fn new(session: SessionService, proxy_url: Option<&str>) -> Result<CdnHandle, Error> {
-        let agent = default_ureq_agent_builder(proxy_url)?.build();
+        let agent = default_ureq_agent_builder(proxy_url)?
+            .max_idle_connections_per_host(MAX_IDLE_CONNECTIONS_PER_HOST)
+            .build();
         Ok(Arc::new(Self {
             session,
             agent,
             token_provider: TokenProvider::new(),
+            last_origin: Mutex::new(None),
+            throughput: Mutex::new(Throughput::default()),
         }))
     }
 
+    /// Open connections to the storage resolver and the last used CDN host ahead of
+    /// time, so they are pooled by the time the file is requested.  Blocks until
+    /// done, errors are only logged.
+    pub fn preconnect(&self) {
+        let last_origin = self.last_origin.lock().clone();
+        for origin in [Some(STORAGE_RESOLVE_ORIGIN.to_string()), last_origin]
+            .into_iter()
+            .flatten()
+        {
+            // We don't care about the response, but it needs to be complete for the
+            // connection to return to the pool, so ask just for the headers.
+            match self.agent.head(&origin).call() {
+                Ok(_) | Err(ureq::Error::Status(..)) => {
+                    log::debug!("preconnected to {}", origin);
+                }
+                Err(err) => {
+                    log::debug!("failed to preconnect to {}: {}", origin, err);
+                }
+            }
+        }
+    }
+
     pub fn resolve_audio_file_url(&self, id: FileId) -> Result<CdnUrl, Error> {
         let locations_uri = format!(
             "https://api.spotify.com/v1/storage-resolve/files/audio/interactive/{}",
@@ -64,6 +103,10 @@ impl Cdn {
             // TODO: Avoid panicking here.
             .expect("No file URI found");
 
+        if let Ok(url) = Url::parse(&file_uri) {
+            *self.last_origin.lock() = Some(url.origin().ascii_serialization());
+        }
+
         let uri = CdnUrl::new(file_uri);
         Ok(uri)
     }
@@ -83,6 +126,64 @@ impl Cdn {
         let data_reader = response.into_reader();
         Ok((total_length, data_reader))
     }
+
+    /// Account a finished download of `bytes` that took `elapsed` since the request
+    /// was sent.
+    pub fn record_download(&self, bytes: u64, elapsed: Duration) {
+        self.throughput.lock().record(bytes, elapsed);
+    }
+
+    /// How much data to keep requested in front of the playback, based on the
+    /// measured throughput.  `None` until there is something measured.
+    pub fn prefetch_length(&self) -> Option<u64> {
+        self.throughput.lock().prefetch_length()
+    }
+}
+
+/// Running estimate of the download throughput and its jitter, in bytes per second.
+#[derive(Default)]
+struct Throughput {
+    mean: f64,
+    deviation: f64,
+    samples: u32,
+}
+
+impl Throughput {
+    // Weight of the newest sample in the moving averages.
+    const SMOOTHING: f64 = 0.25;
+
+    // Assume the highest bitrate we play, 320 kbit/s.
+    const AUDIO_BYTES_PER_SEC: f64 = 40_000.0;
+
+    // Seconds of audio to keep requested ahead, on the fastest and the slowest links.
+    const MIN_PREFETCH_SECS: f64 = 3.0;
+    const MAX_PREFETCH_SECS: f64 = 60.0;
+
+    fn record(&mut self, bytes: u64, elapsed: Duration) {
+        let sample = bytes as f64 / elapsed.as_secs_f64().max(0.001);
+        if self.samples == 0 {
+            self.mean = sample;
+            self.deviation = sample / 2.0;
+        } else {
+            self.deviation += Self::SMOOTHING * ((sample - self.mean).abs() - self.deviation);
+            self.mean += Self::SMOOTHING * (sample - self.mean);
+        }
+        self.samples = self.samples.saturating_add(1);
+    }
+
+    fn prefetch_length(&self) -> Option<u64> {
+        if self.samples == 0 {
+            return None;
+        }
+        // Plan for the throughput being noticeably worse than usual, so a jittery
+        // link ends up with a bigger buffer than a steady one of the same speed.
+        let pessimistic = (self.mean - 2.0 * self.deviation).max(Self::AUDIO_BYTES_PER_SEC / 4.0);
+        // Fetching the audio takes this fraction of its playback time.
+        let load = Self::AUDIO_BYTES_PER_SEC / pessimistic;
+        let secs = (Self::MIN_PREFETCH_SECS + 20.0 * load)
+            .clamp(Self::MIN_PREFETCH_SECS, Self::MAX_PREFETCH_SECS);
+        Some((secs * Self::AUDIO_BYTES_PER_SEC) as u64)
+    }
 }
 
 #[derive(Clone)]
@@ -114,7 +215,10 @@ impl CdnUrl {
 
 impl From<ureq::Error> for Error {
     fn from(err: ureq::Error) -> Self {
-        Error::AudioFetchingError(Box::new(err))
+        match net::certificate_rejection(&err) {
+            Some(reason) => Error::CertificateRejected(reason),
+            None => Error::AudioFetchingError(Box::new(err)),
+        }
     }
 }
 
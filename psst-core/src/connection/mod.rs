@@ -5,7 +5,7 @@ use std::{
     convert::TryInto,
     io,
     io::{Read, Write},
-    net::{TcpStream, ToSocketAddrs},
+    net::TcpStream,
 };
 
 use byteorder::{ReadBytesExt, BE};
@@ -21,6 +21,7 @@ use crate::{
         shannon_codec::{ShannonDecoder, ShannonEncoder, ShannonMsg},
     },
     error::Error,
+    net,
     protocol::authentication::AuthenticationType,
     util::{
         default_ureq_agent_builder, deserialize_protobuf, serialize_protobuf, NET_CONNECT_TIMEOUT,
@@ -29,7 +30,7 @@ use crate::{
 };
 
 // Device ID used for authentication message.
-const DEVICE_ID: &str = "Psst";
+pub const DEVICE_ID: &str = "Psst";
 
 // URI of access-point resolve endpoint.
 const AP_RESOLVE_ENDPOINT: &str = "http://apresolve.spotify.com";
@@ -130,23 +131,9 @@ impl Transport {
     }
 
     fn stream_without_proxy(ap: &str) -> Result<TcpStream, io::Error> {
-        let mut last_err = None;
-        for addr in ap.to_socket_addrs()? {
-            match TcpStream::connect_timeout(&addr, NET_CONNECT_TIMEOUT) {
-                Ok(stream) => {
-                    return Ok(stream);
-                }
-                Err(err) => {
-                    last_err.replace(err);
-                }
-            }
-        }
-        Err(last_err.unwrap_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "could not resolve to any addresses",
-            )
-        }))
+        let addrs = net::resolve(ap)?;
+        let (stream, _) = net::connect(&addrs, NET_CONNECT_TIMEOUT)?;
+        Ok(stream)
     }
 
     fn stream_through_proxy(ap: &str, url: &str) -> Result<TcpStream, Error> {
@@ -0,0 +1,541 @@
+//! Offline downloads.  Items are downloaded whole into the audio cache, together
+//! with their metadata and audio keys, from where the player loads them without
+//! a connection.  Downloaded files are pinned, so the cache garbage collection
+//! leaves them alone.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use parking_lot::{Condvar, Mutex};
+
+use crate::{
+    cache::CacheHandle,
+    cdn::{CdnHandle, CdnUrl},
+    error::Error,
+    item_id::ItemId,
+    player::{file::MediaPath, item::PlaybackItem, PlaybackConfig},
+    session::SessionService,
+};
+
+pub const DEFAULT_CONCURRENCY: usize = 2;
+
+/// Size of the ranges the files are downloaded in.  Progress is reported, and
+/// cancellation checked, after each of them.
+const CHUNK_LENGTH: u64 = 512 * 1024;
+
+/// How often the failed downloads are checked for a retry.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Failed downloads are retried automatically only this many times, after that
+/// it's up to the user.
+const MAX_AUTOMATIC_RETRIES: u32 = 3;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    Done,
+    Failed(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct DownloadState {
+    pub item_id: ItemId,
+    pub status: DownloadStatus,
+    pub downloaded: u64,
+    /// Size of the file, known once the download starts.
+    pub total: Option<u64>,
+}
+
+struct Job {
+    item: PlaybackItem,
+    /// Tells the job apart from earlier ones of the same item, which might still
+    /// be finishing their download after a cancel.
+    generation: u64,
+    /// Resolved once the download starts, so the partial file can be removed
+    /// without asking the server again.
+    path: Option<MediaPath>,
+    state: DownloadState,
+    interrupt: Arc<AtomicBool>,
+    retries: u32,
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: Vec<Job>,
+    paused: bool,
+    concurrency: usize,
+    next_generation: u64,
+    /// Items with a cancelled download still running.  They are not started
+    /// again until it's finished, so two downloads never write the same file.
+    cancelling: Vec<ItemId>,
+}
+
+/// A job picked up by a download thread.
+struct Run {
+    item: PlaybackItem,
+    generation: u64,
+    interrupt: Arc<AtomicBool>,
+}
+
+impl Queue {
+    fn job_mut(&mut self, item_id: ItemId) -> Option<&mut Job> {
+        self.jobs.iter_mut().find(|job| job.item.item_id == item_id)
+    }
+
+    /// The job `generation` of the item, if it's still in the queue.
+    fn job_of_run(&mut self, item_id: ItemId, generation: u64) -> Option<&mut Job> {
+        self.jobs
+            .iter_mut()
+            .find(|job| job.item.item_id == item_id && job.generation == generation)
+    }
+
+    /// Add the item, unless it's queued already.
+    fn push(&mut self, item: PlaybackItem) {
+        if self.job_mut(item.item_id).is_some() {
+            return;
+        }
+        self.jobs.push(Job {
+            item,
+            generation: self.next_generation,
+            path: None,
+            state: DownloadState {
+                item_id: item.item_id,
+                status: DownloadStatus::Queued,
+                downloaded: 0,
+                total: None,
+            },
+            interrupt: Arc::new(AtomicBool::new(false)),
+            retries: 0,
+        });
+        self.next_generation += 1;
+    }
+
+    /// Take the item out of the queue.  A running download of it is interrupted,
+    /// and the item marked as cancelling until the download thread is done.
+    fn remove(&mut self, item_id: ItemId) -> Option<Job> {
+        let index = self
+            .jobs
+            .iter()
+            .position(|job| job.item.item_id == item_id)?;
+        let job = self.jobs.remove(index);
+        if job.state.status == DownloadStatus::Downloading {
+            job.interrupt.store(true, Ordering::Relaxed);
+            self.cancelling.push(item_id);
+        }
+        Some(job)
+    }
+
+    /// The cancelled download of the item is done, so it can be started again.
+    fn cancelled_run_finished(&mut self, item_id: ItemId) {
+        self.cancelling.retain(|id| *id != item_id);
+    }
+
+    /// Mark the queued jobs as downloading, as long as there are free slots, and
+    /// return them to be run.
+    fn start_queued(&mut self) -> Vec<Run> {
+        if self.paused {
+            return Vec::new();
+        }
+        let running = self
+            .jobs
+            .iter()
+            .filter(|job| job.state.status == DownloadStatus::Downloading)
+            .count();
+        let free_slots = self.concurrency.saturating_sub(running);
+        let cancelling = &self.cancelling;
+        self.jobs
+            .iter_mut()
+            .filter(|job| {
+                job.state.status == DownloadStatus::Queued
+                    && !cancelling.contains(&job.item.item_id)
+            })
+            .take(free_slots)
+            .map(|job| {
+                job.state.status = DownloadStatus::Downloading;
+                job.interrupt = Arc::new(AtomicBool::new(false));
+                Run {
+                    item: job.item,
+                    generation: job.generation,
+                    interrupt: Arc::clone(&job.interrupt),
+                }
+            })
+            .collect()
+    }
+
+    fn states(&self) -> Vec<DownloadState> {
+        self.jobs.iter().map(|job| job.state.clone()).collect()
+    }
+}
+
+/// Queue of offline downloads.  At most `concurrency` items are downloaded at the
+/// same time, each on its own thread.  The whole queue can be paused, in which
+/// case the running downloads stop and later continue from where they were.
+/// Whenever the queue changes, a snapshot of it is sent to the receiver returned
+/// from `new`.
+#[derive(Clone)]
+pub struct DownloadManager {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    session: SessionService,
+    cdn: CdnHandle,
+    cache: CacheHandle,
+    config: PlaybackConfig,
+    queue: Mutex<Queue>,
+    /// Signalled whenever a download thread finishes.
+    run_finished: Condvar,
+    state_sender: Sender<Vec<DownloadState>>,
+}
+
+impl DownloadManager {
+    pub fn new(
+        session: SessionService,
+        cdn: CdnHandle,
+        cache: CacheHandle,
+        config: PlaybackConfig,
+    ) -> (Self, Receiver<Vec<DownloadState>>) {
+        let (state_sender, state_receiver) = unbounded();
+        let manager = Self {
+            inner: Arc::new(Inner {
+                session,
+                cdn,
+                cache,
+                config,
+                queue: Mutex::new(Queue {
+                    concurrency: DEFAULT_CONCURRENCY,
+                    ..Queue::default()
+                }),
+                run_finished: Condvar::new(),
+                state_sender,
+            }),
+        };
+        let inner = Arc::downgrade(&manager.inner);
+        thread::spawn(move || Self::retry_on_reconnect(inner));
+        (manager, state_receiver)
+    }
+
+    pub fn enqueue(&self, items: impl IntoIterator<Item = PlaybackItem>) {
+        {
+            let mut queue = self.inner.queue.lock();
+            for item in items {
+                queue.push(item);
+            }
+        }
+        self.schedule();
+    }
+
+    /// Remove the item from the queue, stopping its download.  Whatever was
+    /// downloaded of it so far is thrown away.
+    pub fn cancel(&self, item_id: ItemId) {
+        let job = self.inner.queue.lock().remove(item_id);
+        if let Some(job) = job {
+            // A running download cleans up after itself.
+            if job.state.status != DownloadStatus::Downloading {
+                self.remove_partial_file(job.path);
+            }
+        }
+        self.schedule();
+    }
+
+    pub fn pause(&self) {
+        let mut queue = self.inner.queue.lock();
+        queue.paused = true;
+        for job in &queue.jobs {
+            if job.state.status == DownloadStatus::Downloading {
+                job.interrupt.store(true, Ordering::Relaxed);
+            }
+        }
+        self.notify(&queue);
+    }
+
+    /// Pause the queue and wait until the running downloads, including the
+    /// cancelled ones, have stopped writing into the cache.
+    pub fn stop(&self) {
+        self.pause();
+        let mut queue = self.inner.queue.lock();
+        while !queue.cancelling.is_empty()
+            || queue
+                .jobs
+                .iter()
+                .any(|job| job.state.status == DownloadStatus::Downloading)
+        {
+            self.inner.run_finished.wait(&mut queue);
+        }
+    }
+
+    pub fn resume(&self) {
+        self.inner.queue.lock().paused = false;
+        self.schedule();
+    }
+
+    pub fn set_concurrency(&self, concurrency: usize) {
+        self.inner.queue.lock().concurrency = concurrency.max(1);
+        self.schedule();
+    }
+
+    /// Queue all the failed items again.
+    pub fn retry_failed(&self) {
+        {
+            let mut queue = self.inner.queue.lock();
+            for job in &mut queue.jobs {
+                if let DownloadStatus::Failed(_) = job.state.status {
+                    job.state.status = DownloadStatus::Queued;
+                    job.retries = 0;
+                }
+            }
+        }
+        self.schedule();
+    }
+
+    /// Forget about the completed items.  Their files stay in the cache.
+    pub fn clear_finished(&self) {
+        let mut queue = self.inner.queue.lock();
+        queue
+            .jobs
+            .retain(|job| job.state.status != DownloadStatus::Done);
+        self.notify(&queue);
+    }
+
+    pub fn states(&self) -> Vec<DownloadState> {
+        self.inner.queue.lock().states()
+    }
+
+    fn notify(&self, queue: &Queue) {
+        let _ = self.inner.state_sender.send(queue.states());
+    }
+
+    /// Start the queued downloads, as long as there are free slots.
+    fn schedule(&self) {
+        let mut queue = self.inner.queue.lock();
+        for run in queue.start_queued() {
+            let manager = self.clone();
+            thread::spawn(move || manager.run(run));
+        }
+        self.notify(&queue);
+    }
+
+    fn run(&self, run: Run) {
+        let Run {
+            item,
+            generation,
+            interrupt,
+        } = run;
+        let prepared = self.prepare(item);
+        let path = prepared.as_ref().ok().copied();
+        if let Some(job) = self.inner.queue.lock().job_of_run(item.item_id, generation) {
+            job.path = path;
+        }
+        let result = prepared.and_then(|path| self.download(item, generation, path, &interrupt));
+        {
+            let mut queue = self.inner.queue.lock();
+            match queue.job_of_run(item.item_id, generation) {
+                // Removed from the queue while downloading, so cancelled.
+                None => {
+                    self.remove_partial_file(path);
+                    queue.cancelled_run_finished(item.item_id);
+                }
+                Some(job) => {
+                    job.state.status = match result {
+                        Ok(true) => DownloadStatus::Done,
+                        // Interrupted by a pause, continue once resumed.
+                        Ok(false) => DownloadStatus::Queued,
+                        Err(err) => {
+                            log::warn!("failed to download {}: {}", item.item_id.to_base62(), err);
+                            DownloadStatus::Failed(err.to_string())
+                        }
+                    };
+                }
+            }
+            self.inner.run_finished.notify_all();
+        }
+        self.schedule();
+    }
+
+    fn prepare(&self, item: PlaybackItem) -> Result<MediaPath, Error> {
+        let inner = &self.inner;
+        item.prepare_download(&inner.session, &inner.cache, &inner.config)
+    }
+
+    /// Jobs that never started downloading have no `path`, and no partial file.
+    fn remove_partial_file(&self, path: Option<MediaPath>) {
+        if let Some(path) = path {
+            let _ = fs::remove_file(self.inner.cache.partial_audio_file_path(path.file_id));
+        }
+    }
+
+    /// Download the item into the cache.  Returns `false` if interrupted before
+    /// it's complete.
+    fn download(
+        &self,
+        item: PlaybackItem,
+        generation: u64,
+        path: MediaPath,
+        interrupt: &AtomicBool,
+    ) -> Result<bool, Error> {
+        let cached_path = self.inner.cache.audio_file_path(path.file_id);
+        if let Ok(metadata) = fs::metadata(&cached_path) {
+            // Cached from streaming already, keep it from being evicted now.
+            self.inner.cache.pin_audio_file(path.file_id)?;
+            self.update_progress(item.item_id, generation, metadata.len(), metadata.len());
+            return Ok(true);
+        }
+        let partial_path = self.inner.cache.partial_audio_file_path(path.file_id);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&partial_path)?;
+        let mut offset = file.metadata()?.len();
+        let mut url = self.inner.cdn.resolve_audio_file_url(path.file_id)?;
+        let total = {
+            let (total, _) = self.inner.cdn.fetch_file_range(&url.url, 0, 1)?;
+            total
+        };
+        while offset < total {
+            if interrupt.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            if url.is_expired() {
+                url = self.inner.cdn.resolve_audio_file_url(path.file_id)?;
+            }
+            let length = CHUNK_LENGTH.min(total - offset);
+            offset += self.fetch_range(&url, offset, length, &mut file)?;
+            self.update_progress(item.item_id, generation, offset, total);
+        }
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&partial_path, &cached_path)?;
+        self.inner.cache.pin_audio_file(path.file_id)?;
+        log::info!(
+            "downloaded {} into {:?}",
+            item.item_id.to_base62(),
+            cached_path
+        );
+        Ok(true)
+    }
+
+    fn fetch_range(
+        &self,
+        url: &CdnUrl,
+        offset: u64,
+        length: u64,
+        writer: &mut impl io::Write,
+    ) -> Result<u64, Error> {
+        let (_, reader) = self.inner.cdn.fetch_file_range(&url.url, offset, length)?;
+        let copied = io::copy(&mut reader.take(length), writer)?;
+        if copied < length {
+            return Err(Error::AudioFetchingError(Box::new(io::Error::from(
+                io::ErrorKind::UnexpectedEof,
+            ))));
+        }
+        Ok(copied)
+    }
+
+    fn update_progress(&self, item_id: ItemId, generation: u64, downloaded: u64, total: u64) {
+        let mut queue = self.inner.queue.lock();
+        if let Some(job) = queue.job_of_run(item_id, generation) {
+            job.state.downloaded = downloaded;
+            job.state.total = Some(total);
+        }
+        self.notify(&queue);
+    }
+
+    /// Periodically check whether there are failed downloads worth retrying, and
+    /// if the session connects again, queue them.  Exits once the manager is gone.
+    fn retry_on_reconnect(inner: Weak<Inner>) {
+        loop {
+            thread::sleep(RETRY_INTERVAL);
+            let manager = match inner.upgrade() {
+                Some(inner) => Self { inner },
+                None => break,
+            };
+            let is_retriable = |job: &Job| {
+                matches!(job.state.status, DownloadStatus::Failed(_))
+                    && job.retries < MAX_AUTOMATIC_RETRIES
+            };
+            let has_retriable = {
+                let queue = manager.inner.queue.lock();
+                !queue.paused && queue.jobs.iter().any(is_retriable)
+            };
+            if !has_retriable || manager.inner.session.connected().is_err() {
+                continue;
+            }
+            log::info!("connected, retrying failed downloads");
+            for job in manager.inner.queue.lock().jobs.iter_mut() {
+                if is_retriable(job) {
+                    job.state.status = DownloadStatus::Queued;
+                    job.retries += 1;
+                }
+            }
+            manager.schedule();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{audio::normalize::NormalizationLevel, item_id::ItemIdType};
+
+    fn item(id: u128) -> PlaybackItem {
+        PlaybackItem {
+            item_id: ItemId::new(id, ItemIdType::Track),
+            norm_level: NormalizationLevel::None,
+        }
+    }
+
+    fn queue(concurrency: usize) -> Queue {
+        Queue {
+            concurrency,
+            ..Queue::default()
+        }
+    }
+
+    #[test]
+    fn starts_only_as_many_as_there_are_slots() {
+        let mut queue = queue(2);
+        for id in 1..=3 {
+            queue.push(item(id));
+        }
+        assert_eq!(queue.start_queued().len(), 2);
+        assert!(queue.start_queued().is_empty());
+    }
+
+    #[test]
+    fn finished_run_of_a_cancelled_job_does_not_touch_the_new_one() {
+        let mut queue = queue(1);
+        queue.push(item(1));
+        let run = queue.start_queued().pop().unwrap();
+
+        queue.remove(run.item.item_id).unwrap();
+        assert!(run.interrupt.load(Ordering::Relaxed));
+        queue.push(item(1));
+
+        assert!(queue.job_of_run(run.item.item_id, run.generation).is_none());
+        assert!(queue.job_mut(run.item.item_id).is_some());
+    }
+
+    #[test]
+    fn cancelled_item_is_not_started_again_while_its_download_runs() {
+        let mut queue = queue(2);
+        queue.push(item(1));
+        let run = queue.start_queued().pop().unwrap();
+        queue.remove(run.item.item_id);
+        queue.push(item(1));
+        assert!(queue.start_queued().is_empty());
+
+        queue.cancelled_run_finished(run.item.item_id);
+        let restarted = queue.start_queued().pop().unwrap();
+        assert_ne!(restarted.generation, run.generation);
+    }
+}
@@ -6,6 +6,7 @@ pub enum Error {
     UnexpectedResponse,
     MediaFileNotFound,
     ProxyUrlInvalid,
+    CertificateRejected(String),
     AuthFailed { code: i32 },
     JsonError(Box<dyn error::Error + Send>),
     AudioFetchingError(Box<dyn error::Error + Send>),
@@ -26,6 +27,13 @@ impl fmt::Display for Error {
             Self::UnexpectedResponse => write!(f, "Unknown server response"),
             Self::MediaFileNotFound => write!(f, "Audio file not found"),
             Self::ProxyUrlInvalid => write!(f, "Invalid proxy URL"),
+            Self::CertificateRejected(reason) => write!(
+                f,
+                "Server certificate was rejected ({}). The connection is probably intercepted \
+                 by a proxy or security software. If it uses its own certificate authority, \
+                 add it in the network preferences.",
+                reason
+            ),
             Self::AuthFailed { code } => match code {
                 0 => write!(f, "Authentication failed: protocol error"),
                 2 => write!(f, "Authentication failed: try another AP"),
@@ -1,6 +1,8 @@
 use once_cell::sync::Lazy;
 use std::{collections::HashMap, convert::TryInto, fmt, ops::Deref, path::PathBuf, sync::Mutex};
 
+use crate::uri::SpotifyUri;
+
 static LOCAL_REGISTRY: Lazy<Mutex<LocalItemRegistry>> =
     Lazy::new(|| Mutex::new(LocalItemRegistry::new()));
 
@@ -37,6 +39,7 @@ impl LocalItemRegistry {
             let id = registry.next_id;
             registry.next_id += 1;
             registry.id_to_path.insert(id, path.clone());
+            registry.path_to_id.insert(path, id);
             id
         })
     }
@@ -96,27 +99,18 @@ impl ItemId {
         Some(Self::new(n, id_type))
     }
 
+    /// ID of the item of a URI, or of its `open.spotify.com` URL.  Items other than
+    /// tracks and episodes get the `Unknown` type.
     pub fn from_uri(uri: &str) -> Option<Self> {
-        let gid = uri.split(':').last()?;
-        if uri.contains(":episode:") {
-            Self::from_base62(gid, ItemIdType::Podcast)
-        } else if uri.contains(":track:") {
-            Self::from_base62(gid, ItemIdType::Track)
-        } else {
-            Self::from_base62(gid, ItemIdType::Unknown)
-        }
+        let uri = SpotifyUri::parse(uri)?;
+        uri.item_id()
+            .or_else(|| Self::from_base62(uri.id(), ItemIdType::Unknown))
     }
 
     /// Converts an ID to an URI as described in: https://developer.spotify.com/documentation/web-api/#spotify-uris-and-ids
     pub fn to_uri(&self) -> Option<String> {
-        let b64 = self.to_base62();
-        match self.id_type {
-            ItemIdType::Track => Some(format!("spotify:track:{}", b64)),
-            ItemIdType::Podcast => Some(format!("spotify:podcast:{}", b64)),
-            // TODO: support adding local files to playlists
-            ItemIdType::LocalFile => None,
-            ItemIdType::Unknown => None,
-        }
+        // TODO: support adding local files to playlists
+        SpotifyUri::from_item_id(*self).map(|uri| uri.to_string())
     }
 
     pub fn to_base16(&self) -> String {
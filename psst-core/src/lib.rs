@@ -11,11 +11,14 @@ pub mod audio;
 pub mod cache;
 pub mod cdn;
 pub mod connection;
+pub mod download;
 pub mod error;
 pub mod item_id;
 pub mod metadata;
+pub mod net;
 pub mod player;
 pub mod session;
+pub mod uri;
 pub mod util;
 
 pub use psst_protocol as protocol;
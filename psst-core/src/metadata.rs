@@ -32,7 +32,9 @@ impl Fetch for Episode {
 pub trait ToMediaPath {
     fn is_restricted_in_region(&self, country: &str) -> bool;
     fn find_allowed_alternative(&self, country: &str) -> Option<ItemId>;
-    fn to_media_path(&self, preferred_bitrate: usize) -> Option<MediaPath>;
+    /// Audio files of the item in a supported format, ordered by their closeness to
+    /// `preferred_bitrate`.
+    fn to_media_paths(&self, preferred_bitrate: usize) -> Vec<MediaPath>;
 }
 
 impl ToMediaPath for Track {
@@ -50,14 +52,20 @@ impl ToMediaPath for Track {
         ItemId::from_raw(alt_track.gid.as_ref()?, ItemIdType::Track)
     }
 
-    fn to_media_path(&self, preferred_bitrate: usize) -> Option<MediaPath> {
-        let file = select_preferred_file(&self.file, preferred_bitrate)?;
-        Some(MediaPath {
-            item_id: ItemId::from_raw(self.gid.as_ref()?, ItemIdType::Track)?,
-            file_id: FileId::from_raw(file.file_id.as_ref()?)?,
-            file_format: AudioFormat::from_protocol(file.format?),
-            duration: Duration::from_millis(self.duration? as u64),
-        })
+    fn to_media_paths(&self, preferred_bitrate: usize) -> Vec<MediaPath> {
+        let item_id = self
+            .gid
+            .as_ref()
+            .and_then(|gid| ItemId::from_raw(gid, ItemIdType::Track));
+        match (item_id, self.duration) {
+            (Some(item_id), Some(duration)) => media_paths(
+                &self.file,
+                preferred_bitrate,
+                item_id,
+                Duration::from_millis(duration as u64),
+            ),
+            _ => Vec::new(),
+        }
     }
 }
 
@@ -72,25 +80,53 @@ impl ToMediaPath for Episode {
         None
     }
 
-    fn to_media_path(&self, preferred_bitrate: usize) -> Option<MediaPath> {
-        let file = select_preferred_file(&self.file, preferred_bitrate)?;
-        Some(MediaPath {
-            item_id: ItemId::from_raw(self.gid.as_ref()?, ItemIdType::Podcast)?,
-            file_id: FileId::from_raw(file.file_id.as_ref()?)?,
-            file_format: AudioFormat::from_protocol(file.format?),
-            duration: Duration::from_millis(self.duration? as u64),
-        })
+    fn to_media_paths(&self, preferred_bitrate: usize) -> Vec<MediaPath> {
+        let item_id = self
+            .gid
+            .as_ref()
+            .and_then(|gid| ItemId::from_raw(gid, ItemIdType::Podcast));
+        match (item_id, self.duration) {
+            (Some(item_id), Some(duration)) => media_paths(
+                &self.file,
+                preferred_bitrate,
+                item_id,
+                Duration::from_millis(duration as u64),
+            ),
+            _ => Vec::new(),
+        }
     }
 }
 
-fn select_preferred_file(files: &[AudioFile], preferred_bitrate: usize) -> Option<&AudioFile> {
+fn media_paths(
+    files: &[AudioFile],
+    preferred_bitrate: usize,
+    item_id: ItemId,
+    duration: Duration,
+) -> Vec<MediaPath> {
     MediaFile::supported_audio_formats_for_bitrate(preferred_bitrate)
         .iter()
-        .find_map(|&preferred_format| {
+        .filter_map(|&preferred_format| {
             files
                 .iter()
                 .find(|file| file.format == Some(preferred_format))
         })
+        .filter_map(|file| {
+            let file_id = FileId::from_raw(file.file_id.as_ref()?)?;
+            let format = file.format?;
+            log::debug!(
+                "{} has file {} in {:?}",
+                item_id.to_base62(),
+                file_id.to_base16(),
+                format
+            );
+            Some(MediaPath {
+                item_id,
+                file_id,
+                file_format: AudioFormat::from_protocol(format),
+                duration,
+            })
+        })
+        .collect()
 }
 
 fn is_restricted_in_region(restriction: &Restriction, country: &str) -> bool {
@@ -0,0 +1,661 @@
+//! Connection establishment shared by the AP transport and the HTTP agents.
+
+use std::{
+    error, fs, io,
+    io::{Cursor, Read},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use byteorder::{ReadBytesExt, BE};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use url::Url;
+
+use crate::{
+    error::Error,
+    util::{default_ureq_agent_builder, NET_CONNECT_TIMEOUT},
+};
+
+/// Delay between starting connection attempts to consecutive addresses, as
+/// recommended by RFC 8305.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Set when IPv4 won the last connection race in `connect`, so the resolved
+/// addresses start with IPv4 instead of IPv6.
+static PREFER_IPV4: AtomicBool = AtomicBool::new(false);
+
+/// DNS-over-HTTPS server used instead of the system resolver, if configured.
+static DOH_RESOLVER: Lazy<RwLock<Option<DohResolver>>> = Lazy::new(|| RwLock::new(None));
+
+/// TLS configuration with additional trusted roots, if configured.
+static TLS_CONFIG: Lazy<RwLock<Option<Arc<rustls::ClientConfig>>>> =
+    Lazy::new(|| RwLock::new(None));
+
+/// Trust the PEM-encoded certificates in `path` in addition to the built-in
+/// roots, for networks intercepting TLS with their own certificate authority.
+/// Applies to the HTTP agents created afterwards.  Returns the number of added
+/// certificates.
+pub fn add_ca_certificates(path: &Path) -> io::Result<usize> {
+    let certs = parse_pem_certificates(&fs::read_to_string(path)?)?;
+    if certs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no certificates found",
+        ));
+    }
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_server_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.0.iter().map(|anchor| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+            anchor.subject,
+            anchor.spki,
+            anchor.name_constraints,
+        )
+    }));
+    for cert in &certs {
+        roots
+            .add(&rustls::Certificate(cert.to_owned()))
+            .map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid certificate: {:?}", err),
+                )
+            })?;
+    }
+    let config = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    *TLS_CONFIG.write() = Some(Arc::new(config));
+    Ok(certs.len())
+}
+
+/// Apply the configured TLS settings to `builder`.
+pub fn with_tls_config(builder: ureq::AgentBuilder) -> ureq::AgentBuilder {
+    match TLS_CONFIG.read().clone() {
+        Some(config) => builder.tls_config(config),
+        None => builder,
+    }
+}
+
+/// If `err` was caused by the server certificate failing validation, return a
+/// description of the problem.
+pub fn certificate_rejection(err: &ureq::Error) -> Option<String> {
+    let mut source: Option<&(dyn error::Error + 'static)> = Some(err);
+    while let Some(err) = source {
+        // `io::Error` doesn't report the error it wraps as its source.
+        let err = err
+            .downcast_ref::<io::Error>()
+            .and_then(|err| err.get_ref())
+            .map(|inner| inner as &(dyn error::Error + 'static))
+            .unwrap_or(err);
+        if let Some(tls_err) = err.downcast_ref::<rustls::Error>() {
+            if matches!(
+                tls_err,
+                rustls::Error::InvalidCertificateData(_)
+                    | rustls::Error::InvalidCertificateEncoding
+                    | rustls::Error::InvalidCertificateSignature
+                    | rustls::Error::InvalidCertificateSignatureType
+                    | rustls::Error::UnsupportedNameType
+            ) {
+                return Some(tls_err.to_string());
+            }
+        }
+        source = err.source();
+    }
+    None
+}
+
+/// Answers plain HTTP requests with an empty 204 response when the internet is
+/// reachable.
+const CONNECTIVITY_CHECK_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    Online,
+    /// Requests get intercepted by a login page, as on hotel or airport Wi-Fi.
+    CaptivePortal,
+    Offline,
+}
+
+/// Probe the connection after a failed login, so that a login portal or a missing
+/// connection can be told apart from wrong credentials.
+pub fn check_connectivity(proxy_url: Option<&str>) -> Connectivity {
+    let agent = match default_ureq_agent_builder(proxy_url) {
+        Ok(builder) => builder.redirects(0).build(),
+        Err(_) => return Connectivity::Online,
+    };
+    match agent.get(CONNECTIVITY_CHECK_URL).call() {
+        Ok(response) if response.status() == 204 => Connectivity::Online,
+        // A login page, or a redirect to one.
+        Ok(_) => Connectivity::CaptivePortal,
+        // 511 Network Authentication Required.
+        Err(ureq::Error::Status(511, _)) => Connectivity::CaptivePortal,
+        // The check server itself might be blocked, don't guess.
+        Err(ureq::Error::Status(..)) => Connectivity::Online,
+        Err(ureq::Error::Transport(_)) => Connectivity::Offline,
+    }
+}
+
+fn parse_pem_certificates(pem: &str) -> io::Result<Vec<Vec<u8>>> {
+    let mut certs = Vec::new();
+    let mut encoded: Option<String> = None;
+    for line in pem.lines().map(str::trim) {
+        if line == "-----BEGIN CERTIFICATE-----" {
+            encoded = Some(String::new());
+        } else if line == "-----END CERTIFICATE-----" {
+            if let Some(encoded) = encoded.take() {
+                let der = base64::decode(encoded)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                certs.push(der);
+            }
+        } else if let Some(encoded) = &mut encoded {
+            encoded.push_str(line);
+        }
+    }
+    Ok(certs)
+}
+
+/// Resolve hostnames through the DNS-over-HTTPS server at `url`, or through the
+/// system resolver if `None`.  The queries go through the proxy at `proxy_url`,
+/// if any.  Applies to all connections opened afterwards.
+pub fn set_dns_over_https(url: Option<&str>, proxy_url: Option<&str>) {
+    let resolver = url.and_then(|url| match Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "https" => match DohResolver::new(parsed, proxy_url) {
+            Ok(resolver) => Some(resolver),
+            Err(err) => {
+                log::warn!("failed to set up DNS-over-HTTPS: {}", err);
+                None
+            }
+        },
+        _ => {
+            log::warn!("ignoring invalid DNS-over-HTTPS URL: {:?}", url);
+            None
+        }
+    });
+    *DOH_RESOLVER.write() = resolver;
+}
+
+/// Resolve `host:port` into a list of addresses, alternating between IPv6 and
+/// IPv4, so that a broken family doesn't have to time out completely before the
+/// other one is tried.  The family that connected last time goes first.
+pub fn resolve(netloc: &str) -> io::Result<Vec<SocketAddr>> {
+    let doh = DOH_RESOLVER.read().clone();
+    let addrs = match doh {
+        Some(doh) => doh.resolve(netloc)?,
+        None => netloc.to_socket_addrs()?.collect(),
+    };
+    Ok(interleave_families(
+        addrs,
+        PREFER_IPV4.load(Ordering::Relaxed),
+    ))
+}
+
+fn interleave_families(addrs: Vec<SocketAddr>, prefer_v4: bool) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+    let (mut first, mut second) = if prefer_v4 {
+        (v4.into_iter(), v6.into_iter())
+    } else {
+        (v6.into_iter(), v4.into_iter())
+    };
+    let mut interleaved = Vec::new();
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => break,
+            (a, b) => {
+                interleaved.extend(a);
+                interleaved.extend(b);
+            }
+        }
+    }
+    interleaved
+}
+
+/// Wake up the thread accepting connections on `local_addr` with a connection of
+/// our own, so it notices the listener is being shut down.  Connects in the
+/// background, so the caller isn't held up.
+pub fn wake_listener(local_addr: SocketAddr) {
+    let mut wake_addr = local_addr;
+    if wake_addr.ip().is_unspecified() {
+        wake_addr.set_ip(match wake_addr.ip() {
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        });
+    }
+    thread::spawn(move || {
+        let _ = TcpStream::connect_timeout(&wake_addr, NET_CONNECT_TIMEOUT);
+    });
+}
+
+/// Connect to the first reachable address out of `addrs` ("happy eyeballs").
+/// Attempts are started `CONNECTION_ATTEMPT_DELAY` apart, or right after the
+/// previous one fails, and run concurrently until one of them succeeds or
+/// `timeout` runs out.  The family of the winning address is remembered for the
+/// following calls of `resolve`.
+pub fn connect(addrs: &[SocketAddr], timeout: Duration) -> io::Result<(TcpStream, SocketAddr)> {
+    let deadline = Instant::now() + timeout;
+    let (result_send, result_recv) = mpsc::channel();
+    let mut addrs = addrs.iter().copied();
+    let mut pending = 0;
+    let mut last_err = None;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "connection timed out",
+            ));
+        }
+        if let Some(addr) = addrs.next() {
+            thread::spawn({
+                let result_send = result_send.clone();
+                move || {
+                    let result = TcpStream::connect_timeout(&addr, remaining);
+                    // The receiver is gone if another attempt already won, in which case
+                    // this connection just gets dropped.
+                    let _ = result_send.send((addr, result));
+                }
+            });
+            pending += 1;
+        }
+        if pending == 0 {
+            break;
+        }
+        let wait = if addrs.len() > 0 {
+            CONNECTION_ATTEMPT_DELAY.min(remaining)
+        } else {
+            remaining
+        };
+        match result_recv.recv_timeout(wait) {
+            Ok((addr, Ok(stream))) => {
+                log::debug!("connected to {}", addr);
+                PREFER_IPV4.store(addr.is_ipv4(), Ordering::Relaxed);
+                return Ok((stream, addr));
+            }
+            Ok((addr, Err(err))) => {
+                log::debug!("failed to connect to {}: {}", addr, err);
+                pending -= 1;
+                last_err.replace(err);
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                // Time to start the next attempt, or to give up.
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                unreachable!("result sender is kept alive by this loop")
+            }
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "could not resolve to any addresses",
+        )
+    }))
+}
+
+/// Resolver for the `ureq` agents.  `ureq` opens its own connections and tries
+/// the addresses one by one, so it can't race them.  Instead, the addresses
+/// start with the family that won the last race of `connect`, which is the one
+/// reachable on this network.
+pub struct InterleavingResolver;
+
+impl ureq::Resolver for InterleavingResolver {
+    fn resolve(&self, netloc: &str) -> io::Result<Vec<SocketAddr>> {
+        resolve(netloc)
+    }
+}
+
+const DNS_TYPE_A: u16 = 1;
+const DNS_TYPE_AAAA: u16 = 28;
+const DNS_CLASS_IN: u16 = 1;
+
+/// ID of the queries, zero to make the responses cacheable (RFC 8484).
+const DNS_QUERY_ID: u16 = 0;
+const DNS_FLAG_RESPONSE: u16 = 0x8000;
+const DNS_FLAG_TRUNCATED: u16 = 0x0200;
+
+/// Upper bound of a DNS message, see RFC 8484.
+const DNS_MAX_MESSAGE_SIZE: u64 = 65535;
+
+/// Resolver speaking the RFC 8484 wire format.  The hostname of the DoH server
+/// itself goes through the system resolver, so a URL with an IP address avoids
+/// depending on the local DNS entirely.
+#[derive(Clone)]
+struct DohResolver {
+    url: Url,
+    agent: ureq::Agent,
+}
+
+impl DohResolver {
+    fn new(url: Url, proxy_url: Option<&str>) -> Result<Self, Error> {
+        let agent = default_ureq_agent_builder(proxy_url)?
+            // The default resolver would end up asking this one for its own host.
+            .resolver(|netloc: &str| netloc.to_socket_addrs().map(Iterator::collect))
+            .build();
+        Ok(Self { url, agent })
+    }
+
+    fn resolve(&self, netloc: &str) -> io::Result<Vec<SocketAddr>> {
+        // Literal addresses don't need resolving.
+        if let Ok(addr) = netloc.parse::<SocketAddr>() {
+            return Ok(vec![addr]);
+        }
+        let (host, port) = netloc
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse::<u16>().ok()?)))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid socket address"))?;
+
+        let mut addrs = Vec::new();
+        let mut last_err = None;
+        for record_type in [DNS_TYPE_AAAA, DNS_TYPE_A] {
+            match self.query(host, record_type) {
+                Ok(ips) => addrs.extend(ips.into_iter().map(|ip| SocketAddr::new(ip, port))),
+                Err(err) => {
+                    log::warn!("DNS-over-HTTPS query for {} failed: {}", host, err);
+                    last_err.replace(err);
+                }
+            }
+        }
+        if addrs.is_empty() {
+            Err(last_err.unwrap_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no addresses found for {}", host),
+                )
+            }))
+        } else {
+            log::debug!("resolved {} over HTTPS: {:?}", host, addrs);
+            Ok(addrs)
+        }
+    }
+
+    fn query(&self, host: &str, record_type: u16) -> io::Result<Vec<IpAddr>> {
+        let response = self
+            .agent
+            .post(self.url.as_str())
+            .set("Content-Type", "application/dns-message")
+            .set("Accept", "application/dns-message")
+            .send_bytes(&dns_query(host, record_type)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+        let mut message = Vec::new();
+        response
+            .into_reader()
+            .take(DNS_MAX_MESSAGE_SIZE)
+            .read_to_end(&mut message)?;
+        parse_dns_response(&message)
+    }
+}
+
+fn dns_query(host: &str, record_type: u16) -> io::Result<Vec<u8>> {
+    let mut query = DNS_QUERY_ID.to_be_bytes().to_vec();
+    query.extend_from_slice(&[
+        1, 0, // Flags, recursion desired
+        0, 1, // Number of questions
+        0, 0, 0, 0, 0, 0, // Number of answer, authority and additional records
+    ]);
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid hostname: {}", host),
+            ));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&record_type.to_be_bytes());
+    query.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Collect the A and AAAA records from the answer section of `message`.  Other
+/// records, like the CNAMEs leading to them, are skipped.  Names are skipped
+/// without following their compression pointers, so a malicious message can't
+/// send the parsing in circles.
+fn parse_dns_response(message: &[u8]) -> io::Result<Vec<IpAddr>> {
+    let mut cursor = Cursor::new(message);
+    let id = cursor.read_u16::<BE>()?;
+    let flags = cursor.read_u16::<BE>()?;
+    let questions = cursor.read_u16::<BE>()?;
+    let answers = cursor.read_u16::<BE>()?;
+    let _authorities = cursor.read_u16::<BE>()?;
+    let _additionals = cursor.read_u16::<BE>()?;
+
+    if id != DNS_QUERY_ID || flags & DNS_FLAG_RESPONSE == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a response to the DNS query",
+        ));
+    }
+    if flags & DNS_FLAG_TRUNCATED != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "DNS response is truncated",
+        ));
+    }
+    let rcode = flags & 0xF;
+    if rcode != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("DNS server responded with error code {}", rcode),
+        ));
+    }
+    for _ in 0..questions {
+        skip_dns_name(&mut cursor)?;
+        skip_dns_bytes(&mut cursor, 4)?; // Type and class.
+    }
+    let mut ips = Vec::new();
+    for _ in 0..answers {
+        skip_dns_name(&mut cursor)?;
+        let record_type = cursor.read_u16::<BE>()?;
+        let _class = cursor.read_u16::<BE>()?;
+        let _ttl = cursor.read_u32::<BE>()?;
+        let len = cursor.read_u16::<BE>()?;
+        match (record_type, len) {
+            (DNS_TYPE_A, 4) => {
+                let mut octets = [0; 4];
+                cursor.read_exact(&mut octets)?;
+                ips.push(IpAddr::from(octets));
+            }
+            (DNS_TYPE_AAAA, 16) => {
+                let mut octets = [0; 16];
+                cursor.read_exact(&mut octets)?;
+                ips.push(IpAddr::from(octets));
+            }
+            _ => {
+                skip_dns_bytes(&mut cursor, len.into())?;
+            }
+        }
+    }
+    Ok(ips)
+}
+
+fn skip_dns_name(cursor: &mut Cursor<&[u8]>) -> io::Result<()> {
+    loop {
+        match cursor.read_u8()? {
+            0 => return Ok(()),
+            // Compressed name, pointing somewhere else in the message.
+            len if len & 0xC0 == 0xC0 => {
+                cursor.read_u8()?;
+                return Ok(());
+            }
+            len => {
+                skip_dns_bytes(cursor, len.into())?;
+            }
+        }
+    }
+}
+
+/// Unlike seeking, fails on skipping past the end of the message.
+fn skip_dns_bytes(cursor: &mut Cursor<&[u8]>, len: u64) -> io::Result<()> {
+    let position = cursor.position() + len;
+    if position > cursor.get_ref().len() as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "DNS message is cut short",
+        ));
+    }
+    cursor.set_position(position);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Response to the A query of `example.com`, the answer refers to the name in
+    /// the question with a compression pointer.
+    const A_RESPONSE: &[u8] = &[
+        0x00, 0x00, 0x81, 0x80, // ID, flags
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, // Counts
+        0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, // Name
+        0x00, 0x01, 0x00, 0x01, // Type, class
+        0xC0, 0x0C, // Pointer to the name in the question
+        0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x04, // A, IN, TTL, length
+        93, 184, 216, 34,
+    ];
+
+    /// Response to the AAAA query of `www.example.com`, with a CNAME leading to
+    /// `example.com` first.
+    const AAAA_RESPONSE: &[u8] = &[
+        0x00, 0x00, 0x81, 0x80, // ID, flags
+        0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, // Counts
+        0x03, b'w', b'w', b'w', //
+        0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o', b'm', 0x00, // Name
+        0x00, 0x1C, 0x00, 0x01, // Type, class
+        0xC0, 0x0C, // Pointer to `www.example.com`
+        0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x02, // CNAME, IN, TTL, length
+        0xC0, 0x10, // Pointer to `example.com`
+        0xC0, 0x10, // Pointer to `example.com`
+        0x00, 0x1C, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x10, // AAAA, IN, TTL, length
+        0x26, 0x06, 0x28, 0x00, 0x02, 0x20, 0x00, 0x01, //
+        0x02, 0x48, 0x18, 0x93, 0x25, 0xC8, 0x19, 0x46,
+    ];
+
+    #[test]
+    fn encodes_queries() {
+        let query = dns_query("example.com.", DNS_TYPE_AAAA).unwrap();
+        assert_eq!(&query[..12], &[0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&query[12..25], b"\x07example\x03com\x00");
+        assert_eq!(&query[25..], &[0x00, 0x1C, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn rejects_invalid_hostnames() {
+        assert!(dns_query("", DNS_TYPE_A).is_err());
+        assert!(dns_query("example..com", DNS_TYPE_A).is_err());
+        assert!(dns_query(&format!("{}.com", "a".repeat(64)), DNS_TYPE_A).is_err());
+    }
+
+    #[test]
+    fn parses_a_records() {
+        let ips = parse_dns_response(A_RESPONSE).unwrap();
+        assert_eq!(ips, [IpAddr::from([93, 184, 216, 34])]);
+    }
+
+    #[test]
+    fn parses_aaaa_records_behind_cnames() {
+        let ips = parse_dns_response(AAAA_RESPONSE).unwrap();
+        let ip: Ipv6Addr = "2606:2800:220:1:248:1893:25c8:1946".parse().unwrap();
+        assert_eq!(ips, [IpAddr::from(ip)]);
+    }
+
+    #[test]
+    fn compression_pointer_loops_end() {
+        let mut message = A_RESPONSE.to_vec();
+        // The name of the answer points to itself.
+        message[29..31].copy_from_slice(&[0xC0, 29]);
+        assert_eq!(
+            parse_dns_response(&message).unwrap(),
+            [IpAddr::from([93, 184, 216, 34])]
+        );
+        // So does the name of the question.
+        message[12..14].copy_from_slice(&[0xC0, 12]);
+        assert!(parse_dns_response(&message).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_messages() {
+        for response in [A_RESPONSE, AAAA_RESPONSE] {
+            for len in 0..response.len() {
+                assert!(parse_dns_response(&response[..len]).is_err());
+            }
+        }
+        let mut message = A_RESPONSE.to_vec();
+        message[2] |= 0x02; // Truncation flag
+        assert!(parse_dns_response(&message).is_err());
+    }
+
+    #[test]
+    fn rejects_answers_longer_than_the_message() {
+        let mut message = AAAA_RESPONSE.to_vec();
+        // Length of the CNAME data.
+        message[43] = 0xFF;
+        assert!(parse_dns_response(&message).is_err());
+    }
+
+    #[test]
+    fn rejects_other_messages() {
+        let mut message = A_RESPONSE.to_vec();
+        message[1] = 0x2A; // ID
+        assert!(parse_dns_response(&message).is_err());
+        let query = dns_query("example.com", DNS_TYPE_A).unwrap();
+        assert!(parse_dns_response(&query).is_err());
+        let mut message = A_RESPONSE.to_vec();
+        message[3] |= 0x03; // NXDOMAIN
+        assert!(parse_dns_response(&message).is_err());
+    }
+
+    fn socket_addrs(addrs: &[&str]) -> Vec<SocketAddr> {
+        addrs.iter().map(|addr| addr.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn interleaves_starting_with_ipv6() {
+        let addrs = socket_addrs(&["1.1.1.1:443", "1.0.0.1:443", "[::1]:443", "[::2]:443"]);
+        assert_eq!(
+            interleave_families(addrs, false),
+            socket_addrs(&["[::1]:443", "1.1.1.1:443", "[::2]:443", "1.0.0.1:443"])
+        );
+    }
+
+    #[test]
+    fn interleaves_starting_with_ipv4_if_preferred() {
+        let addrs = socket_addrs(&["[::1]:443", "[::2]:443", "1.1.1.1:443", "1.0.0.1:443"]);
+        assert_eq!(
+            interleave_families(addrs, true),
+            socket_addrs(&["1.1.1.1:443", "[::1]:443", "1.0.0.1:443", "[::2]:443"])
+        );
+    }
+
+    #[test]
+    fn keeps_a_single_family_in_order() {
+        let addrs = socket_addrs(&["1.1.1.1:443", "1.0.0.1:443"]);
+        assert_eq!(interleave_families(addrs.clone(), false), addrs);
+        assert_eq!(interleave_families(addrs.clone(), true), addrs);
+        assert!(interleave_families(Vec::new(), false).is_empty());
+    }
+
+    #[test]
+    fn appends_the_rest_of_the_longer_family() {
+        let addrs = socket_addrs(&["[::1]:443", "1.1.1.1:443", "1.0.0.1:443", "1.0.0.2:443"]);
+        assert_eq!(
+            interleave_families(addrs.clone(), false),
+            socket_addrs(&["[::1]:443", "1.1.1.1:443", "1.0.0.1:443", "1.0.0.2:443"])
+        );
+        assert_eq!(
+            interleave_families(addrs, true),
+            socket_addrs(&["1.1.1.1:443", "[::1]:443", "1.0.0.1:443", "1.0.0.2:443"])
+        );
+    }
+}
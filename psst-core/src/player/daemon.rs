@@ -0,0 +1,657 @@
+//! Playback daemon.  A long-lived process owns the player and the GUI attaches
+//! to it, so closing or upgrading the GUI doesn't interrupt the audio.  Clients
+//! talk to the daemon over TCP on the loopback interface, one JSON object per
+//! line, like the listen-along followers do: they first send the token from the
+//! token file in the config directory, then `DaemonRequest`s, and receive a
+//! `DaemonEvent::State` snapshot on attaching, followed by the events of the
+//! player.  The first line that isn't a valid request ends the connection.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    net::{Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::Sender;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audio::normalize::NormalizationLevel,
+    item_id::{ItemId, ItemIdType},
+    net,
+    uri::SpotifyUri,
+    util::NET_CONNECT_TIMEOUT,
+};
+
+use super::{
+    item::PlaybackItem,
+    peers::{self, Peers},
+    queue::QueueBehavior,
+    PlayerCommand, PlayerEvent,
+};
+
+pub const DEFAULT_PORT: u16 = 47411;
+
+/// File in the config directory holding the token the clients authenticate with.
+pub const TOKEN_FILENAME: &str = "daemon-token";
+
+/// Token of the daemon stored in `config_dir`, created on first use.  Only the
+/// user can read the file, so only their processes can control the playback.
+pub fn load_or_create_token(config_dir: &Path) -> io::Result<String> {
+    let path = config_dir.join(TOKEN_FILENAME);
+    fs::create_dir_all(config_dir)?;
+    match create_private_file(&path) {
+        Ok(mut file) => {
+            let token = peers::generate_token();
+            file.write_all(token.as_bytes())?;
+            Ok(token)
+        }
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            let token = fs::read_to_string(&path)?.trim().to_string();
+            if token.is_empty() {
+                // Left over from a crash before the token got written.
+                fs::remove_file(&path)?;
+                return load_or_create_token(config_dir);
+            }
+            Ok(token)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn create_private_file(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// Item of the queue, as sent over the wire.  Local files are sent by path, the
+/// IDs they get are only valid within a single process.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DaemonItem {
+    Spotify {
+        uri: SpotifyUri,
+        norm_level: NormalizationLevel,
+    },
+    LocalFile {
+        path: PathBuf,
+        norm_level: NormalizationLevel,
+    },
+}
+
+impl DaemonItem {
+    pub fn from_playback_item(item: &PlaybackItem) -> Option<Self> {
+        let norm_level = item.norm_level;
+        match item.item_id.id_type {
+            ItemIdType::LocalFile => Some(Self::LocalFile {
+                path: item.item_id.to_local(),
+                norm_level,
+            }),
+            _ => Some(Self::Spotify {
+                uri: SpotifyUri::from_item_id(item.item_id)?,
+                norm_level,
+            }),
+        }
+    }
+
+    pub fn to_playback_item(&self) -> Option<PlaybackItem> {
+        let (item_id, norm_level) = match self {
+            Self::Spotify { uri, norm_level } => (uri.item_id()?, *norm_level),
+            Self::LocalFile { path, norm_level } => {
+                (ItemId::from_local(path.to_owned()), *norm_level)
+            }
+        };
+        Some(PlaybackItem {
+            item_id,
+            norm_level,
+        })
+    }
+
+    fn from_items(items: &[PlaybackItem]) -> Vec<Self> {
+        items.iter().filter_map(Self::from_playback_item).collect()
+    }
+
+    fn to_items(items: &[Self]) -> Vec<PlaybackItem> {
+        items.iter().filter_map(Self::to_playback_item).collect()
+    }
+}
+
+/// Commands a client can send to the daemon.  The configuration of the player
+/// and the smart shuffle hints stay with the daemon.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    LoadQueue {
+        items: Vec<DaemonItem>,
+        position: usize,
+    },
+    UpdateQueue {
+        items: Vec<DaemonItem>,
+    },
+    AddToQueue {
+        items: Vec<DaemonItem>,
+    },
+    PlayNext {
+        items: Vec<DaemonItem>,
+    },
+    Pause,
+    Resume,
+    PauseOrResume,
+    Previous,
+    Next,
+    Stop,
+    Seek {
+        position_ms: u64,
+    },
+    SetQueueBehavior {
+        behavior: QueueBehavior,
+    },
+    SetVolume {
+        volume: f64,
+    },
+    SetReporting {
+        enabled: bool,
+    },
+}
+
+impl DaemonRequest {
+    /// Request for a command of the local player, `None` if it's not sent over.
+    pub fn from_command(command: &PlayerCommand) -> Option<Self> {
+        Some(match command {
+            PlayerCommand::LoadQueue { items, position } => Self::LoadQueue {
+                items: DaemonItem::from_items(items),
+                position: *position,
+            },
+            PlayerCommand::LoadAndPlay { item } => Self::LoadQueue {
+                items: DaemonItem::from_items(&[*item]),
+                position: 0,
+            },
+            PlayerCommand::UpdateQueue { items } => Self::UpdateQueue {
+                items: DaemonItem::from_items(items),
+            },
+            PlayerCommand::AddToQueue { items } => Self::AddToQueue {
+                items: DaemonItem::from_items(items),
+            },
+            PlayerCommand::PlayNext { items } => Self::PlayNext {
+                items: DaemonItem::from_items(items),
+            },
+            PlayerCommand::Pause => Self::Pause,
+            PlayerCommand::Resume => Self::Resume,
+            PlayerCommand::PauseOrResume => Self::PauseOrResume,
+            PlayerCommand::Previous => Self::Previous,
+            PlayerCommand::Next => Self::Next,
+            PlayerCommand::Stop => Self::Stop,
+            PlayerCommand::Seek { position } => Self::Seek {
+                position_ms: position.as_millis() as u64,
+            },
+            PlayerCommand::SetQueueBehavior { behavior } => Self::SetQueueBehavior {
+                behavior: *behavior,
+            },
+            PlayerCommand::SetVolume { volume } => Self::SetVolume { volume: *volume },
+            PlayerCommand::SetReporting { enabled } => Self::SetReporting { enabled: *enabled },
+            PlayerCommand::Preload { .. }
+            | PlayerCommand::Configure { .. }
+            | PlayerCommand::SetShuffleHints { .. } => return None,
+        })
+    }
+
+    pub fn into_command(self) -> PlayerCommand {
+        match self {
+            Self::LoadQueue { items, position } => PlayerCommand::LoadQueue {
+                items: DaemonItem::to_items(&items),
+                position,
+            },
+            Self::UpdateQueue { items } => PlayerCommand::UpdateQueue {
+                items: DaemonItem::to_items(&items),
+            },
+            Self::AddToQueue { items } => PlayerCommand::AddToQueue {
+                items: DaemonItem::to_items(&items),
+            },
+            Self::PlayNext { items } => PlayerCommand::PlayNext {
+                items: DaemonItem::to_items(&items),
+            },
+            Self::Pause => PlayerCommand::Pause,
+            Self::Resume => PlayerCommand::Resume,
+            Self::PauseOrResume => PlayerCommand::PauseOrResume,
+            Self::Previous => PlayerCommand::Previous,
+            Self::Next => PlayerCommand::Next,
+            Self::Stop => PlayerCommand::Stop,
+            Self::Seek { position_ms } => PlayerCommand::Seek {
+                position: Duration::from_millis(position_ms),
+            },
+            Self::SetQueueBehavior { behavior } => PlayerCommand::SetQueueBehavior { behavior },
+            Self::SetVolume { volume } => PlayerCommand::SetVolume { volume },
+            Self::SetReporting { enabled } => PlayerCommand::SetReporting { enabled },
+        }
+    }
+}
+
+/// Playback of the daemon, as a client attaching to it needs to render it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DaemonState {
+    /// Items the playing context was loaded with, in their original order.
+    pub queue: Vec<DaemonItem>,
+    /// Items queued by the user, not played yet.
+    pub user_queue: Vec<DaemonItem>,
+    /// The playing item, `None` if the daemon is not playing anything.
+    pub item: Option<DaemonItem>,
+    pub position_ms: u64,
+    pub paused: bool,
+    pub volume: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DaemonEvent {
+    /// Sent once, right after attaching.
+    State(DaemonState),
+    Loading {
+        item: DaemonItem,
+    },
+    Playing {
+        item: DaemonItem,
+        position_ms: u64,
+    },
+    Pausing,
+    Resuming,
+    Position {
+        position_ms: u64,
+    },
+    /// `item` plays after the current one, which ends in `remaining_ms`.
+    UpNext {
+        item: DaemonItem,
+        remaining_ms: u64,
+    },
+    Blocked,
+    Stalled,
+    Stopped,
+}
+
+impl DaemonEvent {
+    fn from_player_event(event: &PlayerEvent) -> Option<Self> {
+        Some(match event {
+            PlayerEvent::Loading { item } => Self::Loading {
+                item: DaemonItem::from_playback_item(item)?,
+            },
+            PlayerEvent::Playing { path, position } => Self::Playing {
+                item: DaemonItem::from_playback_item(&PlaybackItem {
+                    item_id: path.item_id,
+                    norm_level: NormalizationLevel::None,
+                })?,
+                position_ms: position.as_millis() as u64,
+            },
+            PlayerEvent::Pausing { .. } => Self::Pausing,
+            PlayerEvent::Resuming { .. } => Self::Resuming,
+            PlayerEvent::Position { position, .. } => Self::Position {
+                position_ms: position.as_millis() as u64,
+            },
+            PlayerEvent::UpNext { item, remaining } => Self::UpNext {
+                item: DaemonItem::from_playback_item(item)?,
+                remaining_ms: remaining.as_millis() as u64,
+            },
+            PlayerEvent::Blocked { .. } => Self::Blocked,
+            PlayerEvent::Stalled { .. } => Self::Stalled,
+            PlayerEvent::Stopped => Self::Stopped,
+            _ => return None,
+        })
+    }
+}
+
+struct DaemonShared {
+    state: DaemonState,
+    clients: Peers,
+}
+
+impl DaemonShared {
+    /// Follow the player, so the state is ready for the clients attaching later.
+    fn update(&mut self, event: &PlayerEvent) {
+        let state = &mut self.state;
+        match event {
+            PlayerEvent::Command(PlayerCommand::LoadQueue { items, .. })
+            | PlayerEvent::Command(PlayerCommand::UpdateQueue { items }) => {
+                state.queue = DaemonItem::from_items(items);
+            }
+            PlayerEvent::Command(PlayerCommand::LoadAndPlay { item }) => {
+                state.queue = DaemonItem::from_items(&[*item]);
+            }
+            PlayerEvent::Command(PlayerCommand::AddToQueue { items }) => {
+                state.user_queue.extend(DaemonItem::from_items(items));
+            }
+            PlayerEvent::Command(PlayerCommand::PlayNext { items }) => {
+                let mut queued = DaemonItem::from_items(items);
+                queued.append(&mut state.user_queue);
+                state.user_queue = queued;
+            }
+            PlayerEvent::Command(PlayerCommand::SetVolume { volume }) => {
+                state.volume = *volume;
+            }
+            PlayerEvent::Playing { path, position } => {
+                let item_id = Some(path.item_id);
+                let item = state
+                    .queue
+                    .iter()
+                    .chain(&state.user_queue)
+                    .find(|queued| queued.to_playback_item().map(|item| item.item_id) == item_id)
+                    .cloned();
+                // Items queued by the user are done with once they play.
+                if let Some(index) = state
+                    .user_queue
+                    .iter()
+                    .position(|queued| Some(queued) == item.as_ref())
+                {
+                    state.user_queue.remove(index);
+                }
+                state.item = item;
+                state.position_ms = position.as_millis() as u64;
+                state.paused = false;
+            }
+            PlayerEvent::Pausing { position, .. } => {
+                state.position_ms = position.as_millis() as u64;
+                state.paused = true;
+            }
+            PlayerEvent::Resuming { position, .. } => {
+                state.position_ms = position.as_millis() as u64;
+                state.paused = false;
+            }
+            PlayerEvent::Position { position, .. } => {
+                state.position_ms = position.as_millis() as u64;
+            }
+            PlayerEvent::Stopped => {
+                state.item = None;
+                state.position_ms = 0;
+                state.paused = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn broadcast(&mut self, event: &DaemonEvent) {
+        self.clients.send(event);
+    }
+}
+
+/// Server side of the daemon, feeding the requests of the clients to the player
+/// and the events of the player to the clients.
+pub struct PlayerDaemon {
+    local_addr: SocketAddr,
+    shared: Arc<Mutex<DaemonShared>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl PlayerDaemon {
+    /// Start accepting clients on the loopback interface, at `port`.  Clients
+    /// have to send `token` first.
+    pub fn bind(port: u16, token: &str, player: Sender<PlayerEvent>) -> io::Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let local_addr = listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(DaemonShared {
+            state: DaemonState {
+                volume: 1.0,
+                ..DaemonState::default()
+            },
+            clients: Peers::default(),
+        }));
+        let closed = Arc::new(AtomicBool::new(false));
+        thread::spawn({
+            let shared = Arc::clone(&shared);
+            let closed = Arc::clone(&closed);
+            let token = token.to_string();
+            move || Self::accept_clients(listener, token, shared, player, closed)
+        });
+        log::info!("playback daemon listening on {}", local_addr);
+        Ok(Self {
+            local_addr,
+            shared,
+            closed,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    fn accept_clients(
+        listener: TcpListener,
+        token: String,
+        shared: Arc<Mutex<DaemonShared>>,
+        player: Sender<PlayerEvent>,
+        closed: Arc<AtomicBool>,
+    ) {
+        for stream in listener.incoming() {
+            if closed.load(Ordering::Acquire) {
+                break;
+            }
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    log::warn!("failed to accept daemon client: {}", err);
+                    continue;
+                }
+            };
+            // Waiting for the token must not hold up the other clients.
+            thread::spawn({
+                let token = token.clone();
+                let shared = Arc::clone(&shared);
+                let player = player.clone();
+                move || {
+                    let result = peers::accept_token(&stream, &token).and_then(|reader| {
+                        stream.set_nodelay(true)?;
+                        let mut shared = shared.lock();
+                        let state = DaemonEvent::State(shared.state.clone());
+                        shared.clients.add(stream.try_clone()?, &state)?;
+                        log::info!("daemon client attached: {:?}", stream.peer_addr());
+                        Ok(reader)
+                    });
+                    match result {
+                        Ok(reader) => Self::serve_client(reader, player),
+                        Err(err) => {
+                            log::warn!("failed to accept daemon client: {}", err);
+                            let _ = stream.shutdown(Shutdown::Both);
+                        }
+                    }
+                }
+            });
+        }
+    }
+
+    fn serve_client(reader: BufReader<TcpStream>, player: Sender<PlayerEvent>) {
+        let stream = reader.get_ref().try_clone();
+        for line in reader.lines().map_while(Result::ok) {
+            match serde_json::from_str::<DaemonRequest>(&line) {
+                Ok(request) => {
+                    let command = request.into_command();
+                    if player.send(PlayerEvent::Command(command)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    log::warn!("malformed daemon request, detaching the client: {}", err);
+                    break;
+                }
+            }
+        }
+        if let Ok(stream) = stream {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    /// Update the state from a player event, sending it over to the clients.
+    pub fn observe(&self, event: &PlayerEvent) {
+        let mut shared = self.shared.lock();
+        shared.update(event);
+        if let Some(event) = DaemonEvent::from_player_event(event) {
+            shared.broadcast(&event);
+        }
+    }
+}
+
+impl Drop for PlayerDaemon {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Release);
+        net::wake_listener(self.local_addr);
+        self.shared.lock().clients.close();
+    }
+}
+
+/// Client side of the daemon, used by the GUI in place of a player of its own.
+pub struct DaemonClient {
+    stream: TcpStream,
+}
+
+impl DaemonClient {
+    pub fn connect(port: u16, token: &str) -> io::Result<Self> {
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let stream = TcpStream::connect_timeout(&addr, NET_CONNECT_TIMEOUT)?;
+        stream.set_nodelay(true)?;
+        peers::send_token(&stream, token)?;
+        Ok(Self { stream })
+    }
+
+    pub fn send(&self, request: &DaemonRequest) -> io::Result<()> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        (&self.stream).write_all(&line)
+    }
+
+    /// Blocking iterator over the events of the daemon, starting with its state and
+    /// ending when either side closes the connection.
+    pub fn events(&self) -> io::Result<impl Iterator<Item = DaemonEvent>> {
+        let reader = BufReader::new(self.stream.try_clone()?);
+        Ok(reader.lines().map_while(Result::ok).filter_map(|line| {
+            match serde_json::from_str(&line) {
+                Ok(event) => Some(event),
+                Err(err) => {
+                    log::warn!("malformed daemon event: {}", err);
+                    None
+                }
+            }
+        }))
+    }
+
+    pub fn detach(&self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use crossbeam_channel::unbounded;
+
+    use super::*;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn daemon() -> (PlayerDaemon, crossbeam_channel::Receiver<PlayerEvent>) {
+        let (sender, receiver) = unbounded();
+        let daemon = PlayerDaemon::bind(0, TOKEN, sender).unwrap();
+        (daemon, receiver)
+    }
+
+    /// Whatever the daemon answers `request` with, before closing the connection.
+    fn raw_exchange(daemon: &PlayerDaemon, request: &str) -> String {
+        let mut stream = TcpStream::connect(daemon.local_addr()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        response
+    }
+
+    #[test]
+    fn rejects_clients_without_the_token() {
+        let (daemon, _player) = daemon();
+        assert_eq!(raw_exchange(&daemon, "not the token\n"), "");
+        assert_eq!(raw_exchange(&daemon, "\n"), "");
+    }
+
+    #[test]
+    fn rejects_requests_of_web_pages() {
+        let (daemon, player) = daemon();
+        let request = "POST / HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n{\"request\":\"stop\"}\n";
+        assert_eq!(raw_exchange(&daemon, request), "");
+        assert!(player.try_recv().is_err());
+    }
+
+    #[test]
+    fn serves_clients_with_the_token() {
+        let (daemon, player) = daemon();
+        let client = DaemonClient::connect(daemon.local_addr().port(), TOKEN).unwrap();
+        let mut events = client.events().unwrap();
+        assert!(matches!(events.next(), Some(DaemonEvent::State(_))));
+
+        client.send(&DaemonRequest::Pause).unwrap();
+        let event = player.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(matches!(event, PlayerEvent::Command(PlayerCommand::Pause)));
+
+        daemon.observe(&PlayerEvent::Stopped);
+        assert!(matches!(events.next(), Some(DaemonEvent::Stopped)));
+    }
+
+    #[test]
+    fn forwards_the_reporting_switch() {
+        let (daemon, player) = daemon();
+        let client = DaemonClient::connect(daemon.local_addr().port(), TOKEN).unwrap();
+        let command = PlayerCommand::SetReporting { enabled: false };
+        client
+            .send(&DaemonRequest::from_command(&command).unwrap())
+            .unwrap();
+        let event = player.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(matches!(
+            event,
+            PlayerEvent::Command(PlayerCommand::SetReporting { enabled: false })
+        ));
+    }
+
+    #[test]
+    fn detaches_clients_sending_malformed_requests() {
+        let (daemon, player) = daemon();
+        let client = DaemonClient::connect(daemon.local_addr().port(), TOKEN).unwrap();
+        let mut events = client.events().unwrap();
+        assert!(matches!(events.next(), Some(DaemonEvent::State(_))));
+
+        (&client.stream).write_all(b"not json\n").unwrap();
+        client.send(&DaemonRequest::Stop).unwrap();
+        assert!(events.next().is_none());
+        assert!(player.try_recv().is_err());
+    }
+
+    #[test]
+    fn creates_the_token_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let token = load_or_create_token(dir.path()).unwrap();
+        assert!(!token.is_empty());
+        assert_eq!(load_or_create_token(dir.path()).unwrap(), token);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = fs::metadata(dir.path().join(TOKEN_FILENAME)).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn replaces_an_empty_token_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(TOKEN_FILENAME), "").unwrap();
+        assert!(!load_or_create_token(dir.path()).unwrap().is_empty());
+    }
+}
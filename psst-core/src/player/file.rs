@@ -5,7 +5,7 @@ use std::{
     sync::Arc,
     thread,
     thread::JoinHandle,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use symphonia::core::codecs::CodecType;
@@ -15,6 +15,8 @@ use crate::{
         decode::{AudioCodecFormat, AudioDecoder},
         decrypt::{AudioDecrypt, AudioKey},
         normalize::NormalizationData,
+        ogg_index::SharedSeekIndex,
+        probe::TrackProbe,
     },
     cache::CacheHandle,
     cdn::{CdnHandle, CdnUrl},
@@ -62,6 +64,15 @@ impl AudioFormat {
             Self::Unsupported
         }
     }
+
+    /// Name of the codec, as the event service knows it.
+    pub fn codec_name(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::OggVorbis => "vorbis",
+            Self::Unsupported => "unknown",
+        }
+    }
 }
 
 pub enum MediaFile {
@@ -159,6 +170,7 @@ impl MediaFile {
     pub fn remote_audio_source(
         &self,
         key: AudioKey,
+        cache: &CacheHandle,
     ) -> Result<(AudioDecoder, NormalizationData), Error> {
         let reader = self
             .storage()
@@ -167,15 +179,26 @@ impl MediaFile {
         let mut decrypted = AudioDecrypt::new(key, reader);
         let normalization = NormalizationData::parse(&mut decrypted)?;
         let encoded = OffsetFile::new(decrypted, self.header_length())?;
-        let decoded = AudioDecoder::new(encoded, self.codec_format())?;
+        let decoded = match self.path().file_format {
+            AudioFormat::OggVorbis => {
+                let index = SharedSeekIndex::open(cache.seek_index_path(self.path().file_id));
+                AudioDecoder::with_seek_index(encoded, index)?
+            }
+            _ => AudioDecoder::new(encoded, self.codec_format())?,
+        };
         Ok((decoded, normalization))
     }
 
-    pub fn local_audio_source(&self) -> Result<(AudioDecoder, NormalizationData), Error> {
-        let mut reader = fs::File::open(self.path().item_id.to_local())?;
-        let normalization = NormalizationData::parse(&mut reader)?;
-        let encoded = OffsetFile::new(reader, self.header_length())?;
-        let decoded = AudioDecoder::new(encoded, self.codec_format())?;
+    /// Local files are plain MP3 or Ogg files, without the header carrying the
+    /// normalization data in front of the files from the CDN.  Their ReplayGain
+    /// tags are used instead, if there are any.
+    pub fn local_audio_source(&self) -> Result<(AudioDecoder, Option<NormalizationData>), Error> {
+        let path = self.path().item_id.to_local();
+        let normalization = TrackProbe::new(&path)
+            .ok()
+            .and_then(|probe| NormalizationData::from_replay_gain(&probe.tags.replay_gain));
+        let reader = fs::File::open(path)?;
+        let decoded = AudioDecoder::new(reader, self.codec_format())?;
         Ok((decoded, normalization))
     }
 
@@ -222,6 +245,12 @@ impl StreamedFile {
         // Pipe the initial data from the request body into storage.
         io::copy(&mut initial_data, &mut storage.writer()?)?;
 
+        // Start with the buffering measured on the previous files.  The initial request
+        // is too small to tell anything about the throughput.
+        if let Some(length) = cdn.prefetch_length() {
+            storage.set_prefetch_length(length);
+        }
+
         Ok(StreamedFile {
             path,
             storage,
@@ -318,12 +347,20 @@ fn load_range(
 
     // Download range of data from the CDN.  Block until we a have reader of the
     // request body.
+    let requested_at = Instant::now();
     let (_total_length, mut reader) = cdn.fetch_file_range(url, offset, length)?;
 
     // Pipe it into storage. Blocks until fully written, but readers sleeping on
     // this file should be notified as soon as their offset is covered.
     writer.seek(SeekFrom::Start(offset))?;
-    io::copy(&mut reader, writer)?;
+    let written = io::copy(&mut reader, writer)?;
+
+    // Adapt the buffering of the rest of the file to how fast this went.
+    cdn.record_download(written, requested_at.elapsed());
+    if let Some(prefetch_length) = cdn.prefetch_length() {
+        log::trace!("prefetching {} bytes ahead", prefetch_length);
+        writer.set_prefetch_length(prefetch_length);
+    }
 
     Ok(())
 }
@@ -1,8 +1,11 @@
-use std::time::Duration;
+use std::{thread, time::Duration};
 
 use crate::{
     audio::{
-        decode::AudioDecoder, decrypt::AudioKey, normalize::NormalizationLevel, probe::TrackProbe,
+        decode::AudioDecoder,
+        decrypt::AudioKey,
+        normalize::{NormalizationData, NormalizationLevel},
+        probe::TrackProbe,
     },
     cache::CacheHandle,
     cdn::CdnHandle,
@@ -38,54 +41,119 @@ impl PlaybackItem {
         cache: CacheHandle,
         config: &PlaybackConfig,
     ) -> Result<LoadedPlaybackItem, Error> {
-        let path = load_media_path(self.item_id, session, &cache, config)?;
-        let (file, source, norm_data) = match self.item_id.id_type {
+        if self.item_id.id_type != ItemIdType::LocalFile {
+            // Get the HTTPS handshakes out of the way while the metadata and the key are
+            // being fetched.
+            let cdn = cdn.clone();
+            thread::spawn(move || cdn.preconnect());
+        }
+        let paths = load_media_paths(self.item_id, session, &cache, config)?;
+        let mut last_err = Error::MediaFileNotFound;
+        // Fall back through the less preferred files if the best one can't be loaded.
+        for (index, path) in paths.iter().enumerate() {
+            match self.load_path(*path, session, &cdn, &cache) {
+                Ok((file, source, norm_data)) => {
+                    log::info!(
+                        "loaded {} from file {} ({:?}, choice {} of {})",
+                        self.item_id.to_base62(),
+                        path.file_id.to_base16(),
+                        path.file_format,
+                        index + 1,
+                        paths.len()
+                    );
+                    // Without any normalization data, play the file as it is.
+                    let norm_factor = norm_data.map_or(1.0, |norm_data| {
+                        norm_data.factor_for_level(self.norm_level, config.pregain)
+                    });
+                    return Ok(LoadedPlaybackItem {
+                        file,
+                        source,
+                        norm_factor,
+                    });
+                }
+                // Other files won't fare any better without a session.
+                Err(err @ Error::SessionDisconnected) => return Err(err),
+                Err(err) => {
+                    log::warn!(
+                        "failed to load {} from file {} ({:?}): {}",
+                        self.item_id.to_base62(),
+                        path.file_id.to_base16(),
+                        path.file_format,
+                        err
+                    );
+                    last_err = err;
+                }
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Pick the file an offline download of the item should fetch, and put the
+    /// metadata and the audio key needed to play it into the cache.  The file is
+    /// the one `load` prefers, so it's found in the cache later.
+    pub fn prepare_download(
+        &self,
+        session: &SessionService,
+        cache: &CacheHandle,
+        config: &PlaybackConfig,
+    ) -> Result<MediaPath, Error> {
+        if self.item_id.id_type == ItemIdType::LocalFile {
+            return Err(Error::MediaFileNotFound);
+        }
+        let paths = load_media_paths(self.item_id, session, cache, config)?;
+        let path = paths.first().copied().ok_or(Error::MediaFileNotFound)?;
+        load_audio_key(&path, session, cache)?;
+        Ok(path)
+    }
+
+    fn load_path(
+        &self,
+        path: MediaPath,
+        session: &SessionService,
+        cdn: &CdnHandle,
+        cache: &CacheHandle,
+    ) -> Result<(MediaFile, AudioDecoder, Option<NormalizationData>), Error> {
+        match self.item_id.id_type {
             ItemIdType::LocalFile => {
                 let file = MediaFile::local(path);
                 let (source, norm_data) = file.local_audio_source()?;
-                (file, source, norm_data)
+                Ok((file, source, norm_data))
             }
             _ => {
-                let key = load_audio_key(&path, session, &cache)?;
-                let file = MediaFile::open(path, cdn, cache)?;
-                let (source, norm_data) = file.remote_audio_source(key)?;
-                (file, source, norm_data)
+                let key = load_audio_key(&path, session, cache)?;
+                let file = MediaFile::open(path, cdn.clone(), cache.clone())?;
+                let (source, norm_data) = file.remote_audio_source(key, cache)?;
+                Ok((file, source, Some(norm_data)))
             }
-        };
-        let norm_factor = norm_data.factor_for_level(self.norm_level, config.pregain);
-        Ok(LoadedPlaybackItem {
-            file,
-            source,
-            norm_factor,
-        })
+        }
     }
 }
 
-fn load_media_path(
+fn load_media_paths(
     item_id: ItemId,
     session: &SessionService,
     cache: &CacheHandle,
     config: &PlaybackConfig,
-) -> Result<MediaPath, Error> {
+) -> Result<Vec<MediaPath>, Error> {
     match item_id.id_type {
         ItemIdType::Track => {
-            load_media_path_from_track_or_alternative(item_id, session, cache, config)
+            load_media_paths_from_track_or_alternative(item_id, session, cache, config)
         }
-        ItemIdType::Podcast => load_media_path_from_episode(item_id, session, cache, config),
-        ItemIdType::LocalFile => load_media_path_from_local(item_id),
+        ItemIdType::Podcast => load_media_paths_from_episode(item_id, session, cache, config),
+        ItemIdType::LocalFile => load_media_path_from_local(item_id).map(|path| vec![path]),
         ItemIdType::Unknown => unimplemented!(),
     }
 }
 
-fn load_media_path_from_track_or_alternative(
+fn load_media_paths_from_track_or_alternative(
     item_id: ItemId,
     session: &SessionService,
     cache: &CacheHandle,
     config: &PlaybackConfig,
-) -> Result<MediaPath, Error> {
+) -> Result<Vec<MediaPath>, Error> {
     let track = load_track(item_id, session, cache)?;
     let country = get_country_code(session, cache);
-    let path = match country {
+    let paths = match country {
         Some(user_country) if track.is_restricted_in_region(&user_country) => {
             // The track is regionally restricted and is unavailable.  Let's try to find an
             // alternative track.
@@ -93,46 +161,49 @@ fn load_media_path_from_track_or_alternative(
                 .find_allowed_alternative(&user_country)
                 .ok_or(Error::MediaFileNotFound)?;
             let alt_track = load_track(alt_id, session, cache)?;
-            let alt_path = alt_track
-                .to_media_path(config.bitrate)
-                .ok_or(Error::MediaFileNotFound)?;
-            // We've found an alternative track with a fitting audio file.  Let's cheat a
-            // little and pretend we've obtained it from the requested track.
+            // We've found an alternative track with fitting audio files.  Let's cheat a
+            // little and pretend we've obtained them from the requested track.
             // TODO: We should be honest and display the real track information.
-            MediaPath {
-                item_id,
-                ..alt_path
-            }
+            alt_track
+                .to_media_paths(config.bitrate)
+                .into_iter()
+                .map(|alt_path| MediaPath {
+                    item_id,
+                    ..alt_path
+                })
+                .collect()
         }
         _ => {
             // Either we do not have a country code loaded or the track is available, return
             // it.
-            track
-                .to_media_path(config.bitrate)
-                .ok_or(Error::MediaFileNotFound)?
+            track.to_media_paths(config.bitrate)
         }
     };
-    Ok(path)
+    if paths.is_empty() {
+        return Err(Error::MediaFileNotFound);
+    }
+    Ok(paths)
 }
 
-fn load_media_path_from_episode(
+fn load_media_paths_from_episode(
     item_id: ItemId,
     session: &SessionService,
     cache: &CacheHandle,
     config: &PlaybackConfig,
-) -> Result<MediaPath, Error> {
+) -> Result<Vec<MediaPath>, Error> {
     let episode = load_episode(item_id, session, cache)?;
     let country = get_country_code(session, cache);
-    let path = match country {
+    let paths = match country {
         Some(user_country) if episode.is_restricted_in_region(&user_country) => {
             // Episode is restricted, and doesn't have any alternatives.
             return Err(Error::MediaFileNotFound);
         }
-        _ => episode
-            .to_media_path(config.bitrate)
-            .ok_or(Error::MediaFileNotFound)?,
+        _ => episode.to_media_paths(config.bitrate),
     };
-    Ok(path)
+    if paths.is_empty() {
+        return Err(Error::MediaFileNotFound);
+    }
+    Ok(paths)
 }
 
 fn load_media_path_from_local(item_id: ItemId) -> Result<MediaPath, Error> {
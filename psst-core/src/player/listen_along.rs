@@ -0,0 +1,274 @@
+//! Experimental "listen together" mode.  A hosting instance shares its playback
+//! state with any number of followers over plain TCP, one JSON object per line,
+//! and the followers mirror it with their own player.  Followers first send the
+//! token of the session, which the host hands out along with its address.
+
+use std::{
+    io::{self, BufRead, BufReader},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{net, uri::SpotifyUri, util::NET_CONNECT_TIMEOUT};
+
+use super::{
+    peers::{self, Peers},
+    PlayerEvent,
+};
+
+pub const DEFAULT_PORT: u16 = 47410;
+
+/// Hosting is only reachable from this machine, unless another address is given.
+pub const DEFAULT_HOST_ADDRESS: &str = "127.0.0.1";
+
+/// Position updates are only sent this often, changes of the track or of the
+/// paused state are sent right away.
+const BROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedPlayback {
+    /// URI of the playing item, `None` if the host is not playing anything.
+    pub item: Option<SpotifyUri>,
+    pub position_ms: u64,
+    pub paused: bool,
+    /// URI of the item following the playing one, known shortly before the end
+    /// of the playing item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up_next: Option<SpotifyUri>,
+}
+
+impl SharedPlayback {
+    /// Estimated position of the host, `elapsed` after this state was received.
+    pub fn position_after(&self, elapsed: Duration) -> Duration {
+        let position = Duration::from_millis(self.position_ms);
+        if self.paused || self.item.is_none() {
+            position
+        } else {
+            position + elapsed
+        }
+    }
+}
+
+/// Append the default port to `address`, unless it already has one.
+pub fn with_default_port(address: &str) -> String {
+    let has_port = address
+        .rsplit_once(':')
+        .map_or(false, |(_, port)| port.parse::<u16>().is_ok());
+    if has_port {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    }
+}
+
+struct HostState {
+    playback: SharedPlayback,
+    followers: Peers,
+    last_broadcast: Instant,
+    /// During a private session, the followers see nothing playing.
+    private: bool,
+}
+
+impl HostState {
+    fn shared(&self) -> SharedPlayback {
+        if self.private {
+            SharedPlayback::default()
+        } else {
+            self.playback.clone()
+        }
+    }
+
+    fn broadcast(&mut self) {
+        let playback = self.shared();
+        self.followers.send(&playback);
+        self.last_broadcast = Instant::now();
+    }
+}
+
+pub struct ListenAlongHost {
+    local_addr: SocketAddr,
+    token: String,
+    state: Arc<Mutex<HostState>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl ListenAlongHost {
+    /// Start accepting followers on `address`, sharing `playback` until the
+    /// first player event arrives.  Nothing is shared while `private` is set.
+    pub fn bind(address: &str, playback: SharedPlayback, private: bool) -> io::Result<Self> {
+        let listener = TcpListener::bind(with_default_port(address))?;
+        let local_addr = listener.local_addr()?;
+        let token = peers::generate_token();
+        let state = Arc::new(Mutex::new(HostState {
+            playback,
+            followers: Peers::default(),
+            last_broadcast: Instant::now(),
+            private,
+        }));
+        let closed = Arc::new(AtomicBool::new(false));
+        thread::spawn({
+            let state = Arc::clone(&state);
+            let closed = Arc::clone(&closed);
+            let token = token.clone();
+            move || Self::accept_followers(listener, token, state, closed)
+        });
+        log::info!("hosting listen-along session on {}", local_addr);
+        Ok(Self {
+            local_addr,
+            token,
+            state,
+            closed,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Token the followers have to join with.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    fn accept_followers(
+        listener: TcpListener,
+        token: String,
+        state: Arc<Mutex<HostState>>,
+        closed: Arc<AtomicBool>,
+    ) {
+        for stream in listener.incoming() {
+            if closed.load(Ordering::Acquire) {
+                break;
+            }
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    log::warn!("failed to accept listen-along follower: {}", err);
+                    continue;
+                }
+            };
+            // Waiting for the token must not hold up the other followers.
+            thread::spawn({
+                let token = token.clone();
+                let state = Arc::clone(&state);
+                move || {
+                    let result = peers::accept_token(&stream, &token).and_then(|_| {
+                        stream.set_nodelay(true)?;
+                        log::info!("listen-along follower joined: {:?}", stream.peer_addr());
+                        let mut state = state.lock();
+                        let playback = state.shared();
+                        state.followers.add(stream, &playback)
+                    });
+                    if let Err(err) = result {
+                        log::warn!("failed to accept listen-along follower: {}", err);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Stop or start sharing the playback, for private sessions.
+    pub fn set_private(&self, private: bool) {
+        let mut state = self.state.lock();
+        if state.private != private {
+            state.private = private;
+            state.broadcast();
+        }
+    }
+
+    /// Update the shared state from a player event, sending it to the followers
+    /// if it changed.
+    pub fn observe(&self, event: &PlayerEvent) {
+        let mut state = self.state.lock();
+        let changed = match event {
+            PlayerEvent::Playing { path, position } => {
+                state.playback = SharedPlayback {
+                    item: SpotifyUri::from_item_id(path.item_id),
+                    position_ms: position.as_millis() as u64,
+                    paused: false,
+                    up_next: None,
+                };
+                true
+            }
+            PlayerEvent::Pausing { position, .. } => {
+                state.playback.position_ms = position.as_millis() as u64;
+                state.playback.paused = true;
+                true
+            }
+            PlayerEvent::Resuming { position, .. } => {
+                state.playback.position_ms = position.as_millis() as u64;
+                state.playback.paused = false;
+                true
+            }
+            PlayerEvent::Position { position, .. } => {
+                state.playback.position_ms = position.as_millis() as u64;
+                false
+            }
+            PlayerEvent::UpNext { item, .. } => {
+                state.playback.up_next = SpotifyUri::from_item_id(item.item_id);
+                true
+            }
+            PlayerEvent::Stopped => {
+                state.playback = SharedPlayback::default();
+                true
+            }
+            _ => {
+                return;
+            }
+        };
+        if state.private {
+            return;
+        }
+        if changed || state.last_broadcast.elapsed() >= BROADCAST_INTERVAL {
+            state.broadcast();
+        }
+    }
+}
+
+impl Drop for ListenAlongHost {
+    fn drop(&mut self) {
+        self.closed.store(true, Ordering::Release);
+        net::wake_listener(self.local_addr);
+        self.state.lock().followers.close();
+    }
+}
+
+pub struct ListenAlongFollower {
+    stream: TcpStream,
+}
+
+impl ListenAlongFollower {
+    pub fn connect(address: &str, token: &str) -> io::Result<Self> {
+        let addrs = net::resolve(&with_default_port(address))?;
+        let (stream, _) = net::connect(&addrs, NET_CONNECT_TIMEOUT)?;
+        peers::send_token(&stream, token)?;
+        Ok(Self { stream })
+    }
+
+    /// Blocking iterator over the state updates of the host, ending when either
+    /// side closes the connection.
+    pub fn updates(&self) -> io::Result<impl Iterator<Item = SharedPlayback>> {
+        let reader = BufReader::new(self.stream.try_clone()?);
+        Ok(reader.lines().map_while(Result::ok).filter_map(|line| {
+            match serde_json::from_str(&line) {
+                Ok(playback) => Some(playback),
+                Err(err) => {
+                    log::warn!("malformed listen-along update: {}", err);
+                    None
+                }
+            }
+        }))
+    }
+
+    pub fn leave(&self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
@@ -1,10 +1,15 @@
+pub mod daemon;
 pub mod file;
 pub mod item;
+pub mod listen_along;
+mod peers;
 pub mod queue;
+pub mod report;
 mod storage;
+mod watchdog;
 mod worker;
 
-use std::{mem, thread, thread::JoinHandle, time::Duration};
+use std::{collections::HashMap, mem, thread, thread::JoinHandle, time::Duration};
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
 
@@ -13,13 +18,15 @@ use crate::{
     cache::CacheHandle,
     cdn::CdnHandle,
     error::Error,
+    item_id::ItemId,
     session::SessionService,
 };
 
 use self::{
     file::MediaPath,
     item::{LoadedPlaybackItem, PlaybackItem},
-    queue::{Queue, QueueBehavior},
+    queue::{Queue, QueueBehavior, ShuffleHint},
+    watchdog::{Recovery, Watchdog},
     worker::PlaybackManager,
 };
 
@@ -30,6 +37,8 @@ const STOP_AFTER_CONSECUTIVE_LOADING_FAILURES: usize = 3;
 pub struct PlaybackConfig {
     pub bitrate: usize,
     pub pregain: f32,
+    /// How long before the end of a track `UpNext` is sent, zero to never send it.
+    pub up_next_lead: Duration,
 }
 
 impl Default for PlaybackConfig {
@@ -37,6 +46,7 @@ impl Default for PlaybackConfig {
         Self {
             bitrate: 320,
             pregain: 3.0,
+            up_next_lead: Duration::from_secs(10),
         }
     }
 }
@@ -49,11 +59,19 @@ pub struct Player {
     cache: CacheHandle,
     config: PlaybackConfig,
     queue: Queue,
+    /// Item whose `UpNext` event has been sent already.
+    up_next_sent_for: Option<ItemId>,
     sender: Sender<PlayerEvent>,
     receiver: Receiver<PlayerEvent>,
     audio_output_sink: DefaultAudioSink,
     playback_mgr: PlaybackManager,
     consecutive_loading_failures: usize,
+    watchdog: Watchdog,
+    /// Item reloaded after a stall, to continue with once it's loaded.
+    recovering: Option<Reload>,
+    /// Recovering from a stall didn't get the item loaded, load it again when the
+    /// playback is resumed.
+    reload_on_resume: bool,
 }
 
 impl Player {
@@ -67,6 +85,7 @@ impl Player {
         let (sender, receiver) = unbounded();
         Self {
             playback_mgr: PlaybackManager::new(audio_output.sink(), sender.clone()),
+            watchdog: Watchdog::start(sender.clone()),
             session,
             cdn,
             cache,
@@ -77,7 +96,10 @@ impl Player {
             state: PlayerState::Stopped,
             preload: PreloadState::None,
             queue: Queue::new(),
+            up_next_sent_for: None,
             consecutive_loading_failures: 0,
+            recovering: None,
+            reload_on_resume: false,
         }
     }
 
@@ -96,19 +118,32 @@ impl Player {
             PlayerEvent::Preloaded { item, result } => self.handle_preloaded(item, result),
             PlayerEvent::Position { position, path } => self.handle_position(position, path),
             PlayerEvent::EndOfTrack { .. } => self.handle_end_of_track(),
+            PlayerEvent::Tick => self.check_stall(),
             PlayerEvent::Loading { .. }
             | PlayerEvent::Playing { .. }
             | PlayerEvent::Pausing { .. }
             | PlayerEvent::Resuming { .. }
             | PlayerEvent::Stopped { .. }
-            | PlayerEvent::Blocked { .. } => {}
+            | PlayerEvent::UpNext { .. }
+            | PlayerEvent::Blocked { .. }
+            | PlayerEvent::Stalled { .. } => {}
         };
+        self.watchdog.set_active(self.is_watched());
+    }
+
+    /// The watchdog only needs to tick while something plays or recovers.
+    fn is_watched(&self) -> bool {
+        matches!(self.state, PlayerState::Playing { .. })
+            || self.recovering.map_or(false, |reload| !reload.paused)
     }
 
     fn handle_command(&mut self, cmd: PlayerCommand) {
         match cmd {
             PlayerCommand::LoadQueue { items, position } => self.load_queue(items, position),
             PlayerCommand::LoadAndPlay { item } => self.load_and_play(item),
+            PlayerCommand::UpdateQueue { items } => self.queue.update_items(items),
+            PlayerCommand::AddToQueue { items } => self.add_to_queue(items, false),
+            PlayerCommand::PlayNext { items } => self.add_to_queue(items, true),
             PlayerCommand::Preload { item } => self.preload(item),
             PlayerCommand::Pause => self.pause(),
             PlayerCommand::Resume => self.resume(),
@@ -119,7 +154,9 @@ impl Player {
             PlayerCommand::Seek { position } => self.seek(position),
             PlayerCommand::Configure { config } => self.configure(config),
             PlayerCommand::SetQueueBehavior { behavior } => self.queue.set_behaviour(behavior),
+            PlayerCommand::SetShuffleHints { hints } => self.queue.set_hints(hints),
             PlayerCommand::SetVolume { volume } => self.set_volume(volume),
+            PlayerCommand::SetReporting { .. } => {}
         }
     }
 
@@ -133,6 +170,11 @@ impl Player {
                     self.consecutive_loading_failures = 0;
                     self.play_loaded(loaded_item);
                 }
+                Err(err) if self.recovering.is_some() => {
+                    // Not a reason to skip the item, the watchdog tries again or
+                    // reports the stall once it runs out of attempts.
+                    log::warn!("failed to reload the stalled item: {}", err);
+                }
                 Err(err) => {
                     self.consecutive_loading_failures += 1;
                     if self.consecutive_loading_failures < STOP_AFTER_CONSECUTIVE_LOADING_FAILURES {
@@ -185,6 +227,7 @@ impl Player {
                 log::warn!("received unexpected position report");
             }
         }
+        self.watchdog.observe(new_position);
         const PRELOAD_BEFORE_END_OF_TRACK: Duration = Duration::from_secs(30);
         let time_until_end_of_track = path.duration.checked_sub(new_position).unwrap_or_default();
         if time_until_end_of_track <= PRELOAD_BEFORE_END_OF_TRACK {
@@ -192,6 +235,65 @@ impl Player {
                 self.preload(item_to_preload);
             }
         }
+        if !self.config.up_next_lead.is_zero()
+            && time_until_end_of_track <= self.config.up_next_lead
+            && self.up_next_sent_for != Some(path.item_id)
+        {
+            if let Some(&item) = self.queue.get_following() {
+                self.up_next_sent_for = Some(path.item_id);
+                self.sender
+                    .send(PlayerEvent::UpNext {
+                        item,
+                        remaining: time_until_end_of_track,
+                    })
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Try to get a stuck playback going again without the user noticing: reopen
+    /// the output stream and load the item again, which fetches the file anew,
+    /// continuing from the same position.  Only after a few failed attempts is
+    /// the playback paused and the stall reported.
+    fn check_stall(&mut self) {
+        let (path, position) = match (&self.state, self.recovering) {
+            (PlayerState::Playing { path, position }, _) => (*path, *position),
+            // The reload failed or hangs, which is a stall just the same.
+            (PlayerState::Loading { .. }, Some(reload)) if !reload.paused => {
+                (reload.path, reload.position)
+            }
+            _ => return,
+        };
+        if !self.watchdog.is_stalled() {
+            return;
+        }
+        match self.watchdog.recover(position) {
+            Recovery::Attempt(attempt) => {
+                log::warn!(
+                    "playback stalled at {:?}, recovering (attempt {})",
+                    position,
+                    attempt
+                );
+                self.audio_output_sink.restart();
+                if let Some(&item) = self.queue.get_current() {
+                    self.reload(item, path, position);
+                }
+            }
+            Recovery::GiveUp => {
+                log::error!("playback stalled at {:?}, giving up", position);
+                self.sender
+                    .send(PlayerEvent::Stalled { path, position })
+                    .unwrap();
+                if self.recovering.take().is_some() {
+                    // There's nothing loaded to pause, pause the position the item
+                    // was at and load it again on resume.
+                    self.audio_output_sink.stop();
+                    self.reload_on_resume = true;
+                    self.state = PlayerState::Playing { path, position };
+                }
+                self.pause();
+            }
+        }
     }
 
     fn handle_end_of_track(&mut self) {
@@ -212,7 +314,25 @@ impl Player {
         }
     }
 
+    fn add_to_queue(&mut self, items: Vec<PlaybackItem>, next: bool) {
+        if next {
+            self.queue.add_next(items);
+        } else {
+            self.queue.add(items);
+        }
+        // Nothing is playing, so start with the queued items right away.
+        if matches!(self.state, PlayerState::Stopped) {
+            self.queue.skip_to_next();
+            if let Some(&item) = self.queue.get_current() {
+                self.load_and_play(item);
+            }
+        }
+    }
+
     fn load_and_play(&mut self, item: PlaybackItem) {
+        self.recovering = None;
+        self.reload_on_resume = false;
+
         // Make sure to stop the sink, so any current audio source is cleared and the
         // playback stopped.
         self.audio_output_sink.stop();
@@ -239,17 +359,7 @@ impl Player {
             preloading_other_file_or_none => {
                 self.preload = preloading_other_file_or_none;
                 // Item is not preloaded yet, load it in a background thread.
-                thread::spawn({
-                    let sender = self.sender.clone();
-                    let session = self.session.clone();
-                    let cdn = self.cdn.clone();
-                    let cache = self.cache.clone();
-                    let config = self.config.clone();
-                    move || {
-                        let result = item.load(&session, cdn, cache, &config);
-                        sender.send(PlayerEvent::Loaded { item, result }).unwrap();
-                    }
-                })
+                self.load_in_background(item)
             }
         };
 
@@ -260,6 +370,35 @@ impl Player {
         };
     }
 
+    /// Load the playing item again after a stall.  Unlike `load_and_play`, no
+    /// events are sent, for the upper layers the item keeps playing.
+    fn reload(&mut self, item: PlaybackItem, path: MediaPath, position: Duration) {
+        self.audio_output_sink.stop();
+        self.recovering = Some(Reload {
+            path,
+            position,
+            paused: false,
+        });
+        self.state = PlayerState::Loading {
+            item,
+            _loading_handle: self.load_in_background(item),
+        };
+    }
+
+    fn load_in_background(&self, item: PlaybackItem) -> JoinHandle<()> {
+        thread::spawn({
+            let sender = self.sender.clone();
+            let session = self.session.clone();
+            let cdn = self.cdn.clone();
+            let cache = self.cache.clone();
+            let config = self.config.clone();
+            move || {
+                let result = item.load(&session, cdn, cache, &config);
+                sender.send(PlayerEvent::Loaded { item, result }).unwrap();
+            }
+        })
+    }
+
     fn preload(&mut self, item: PlaybackItem) {
         if self.is_in_preload(item) {
             return;
@@ -288,17 +427,40 @@ impl Player {
     }
 
     fn play_loaded(&mut self, loaded_item: LoadedPlaybackItem) {
-        log::info!("starting playback");
         let path = loaded_item.file.path();
-        let position = Duration::default();
         self.playback_mgr.play(loaded_item);
+        self.watchdog.reset();
+        if let Some(reload) = self.recovering.take() {
+            log::info!("continuing the recovered playback");
+            self.playback_mgr.seek(reload.position);
+            if reload.paused {
+                self.audio_output_sink.pause();
+            }
+            self.state = reload.state(path);
+            return;
+        }
+        log::info!("starting playback");
+        let position = Duration::default();
         self.state = PlayerState::Playing { path, position };
+        self.up_next_sent_for = None;
         self.sender
             .send(PlayerEvent::Playing { path, position })
             .unwrap();
     }
 
     fn pause(&mut self) {
+        if let Some(reload) = &mut self.recovering {
+            if reload.pause() {
+                log::info!("pausing the recovering playback");
+                self.sender
+                    .send(PlayerEvent::Pausing {
+                        path: reload.path,
+                        position: reload.position,
+                    })
+                    .unwrap();
+            }
+            return;
+        }
         match mem::replace(&mut self.state, PlayerState::Invalid) {
             PlayerState::Playing { path, position } | PlayerState::Paused { path, position } => {
                 log::info!("pausing playback");
@@ -315,14 +477,33 @@ impl Player {
     }
 
     fn resume(&mut self) {
+        if let Some(reload) = &mut self.recovering {
+            if reload.resume() {
+                log::info!("resuming the recovering playback");
+                self.watchdog.reset();
+                self.sender
+                    .send(PlayerEvent::Resuming {
+                        path: reload.path,
+                        position: reload.position,
+                    })
+                    .unwrap();
+            }
+            return;
+        }
         match mem::replace(&mut self.state, PlayerState::Invalid) {
             PlayerState::Playing { path, position } | PlayerState::Paused { path, position } => {
                 log::info!("resuming playback");
                 self.audio_output_sink.resume();
+                self.watchdog.reset();
                 self.sender
                     .send(PlayerEvent::Resuming { path, position })
                     .unwrap();
                 self.state = PlayerState::Playing { path, position };
+                if mem::take(&mut self.reload_on_resume) {
+                    if let Some(&item) = self.queue.get_current() {
+                        self.reload(item, path, position);
+                    }
+                }
             }
             _ => {
                 log::warn!("invalid state transition");
@@ -331,9 +512,11 @@ impl Player {
     }
 
     fn pause_or_resume(&mut self) {
-        match &self.state {
-            PlayerState::Playing { .. } => self.pause(),
-            PlayerState::Paused { .. } => self.resume(),
+        match (&self.state, self.recovering) {
+            (_, Some(reload)) if reload.paused => self.resume(),
+            (_, Some(_)) => self.pause(),
+            (PlayerState::Playing { .. }, _) => self.pause(),
+            (PlayerState::Paused { .. }, _) => self.resume(),
             _ => {
                 // Do nothing.
             }
@@ -368,10 +551,17 @@ impl Player {
         self.state = PlayerState::Stopped;
         self.queue.clear();
         self.consecutive_loading_failures = 0;
+        self.recovering = None;
+        self.reload_on_resume = false;
     }
 
     fn seek(&mut self, position: Duration) {
-        self.playback_mgr.seek(position);
+        match &mut self.recovering {
+            // Nothing is loaded to seek in yet.
+            Some(reload) => reload.seek(position),
+            None => self.playback_mgr.seek(position),
+        }
+        self.watchdog.reset();
     }
 
     fn configure(&mut self, config: PlaybackConfig) {
@@ -379,10 +569,13 @@ impl Player {
     }
 
     fn is_near_playback_start(&self) -> bool {
-        match self.state {
-            PlayerState::Playing { position, .. } | PlayerState::Paused { position, .. } => {
-                position < PREVIOUS_TRACK_THRESHOLD
+        match (&self.state, self.recovering) {
+            (_, Some(reload)) => reload.position < PREVIOUS_TRACK_THRESHOLD,
+            (PlayerState::Playing { position, .. } | PlayerState::Paused { position, .. }, _) => {
+                *position < PREVIOUS_TRACK_THRESHOLD
             }
+            // Nothing of it has played yet, like when skipping over a blocked item.
+            (PlayerState::Loading { .. }, _) => true,
             _ => false,
         }
     }
@@ -404,6 +597,19 @@ pub enum PlayerCommand {
     LoadAndPlay {
         item: PlaybackItem,
     },
+    /// Replace the items of the playing context, keeping the current one playing.
+    UpdateQueue {
+        items: Vec<PlaybackItem>,
+    },
+    /// Append items to the user queue, after the already queued ones.
+    AddToQueue {
+        items: Vec<PlaybackItem>,
+    },
+    /// Put items to the front of the user queue, so they play right after the
+    /// current item.
+    PlayNext {
+        items: Vec<PlaybackItem>,
+    },
     Preload {
         item: PlaybackItem,
     },
@@ -422,10 +628,20 @@ pub enum PlayerCommand {
     SetQueueBehavior {
         behavior: QueueBehavior,
     },
+    /// Replace the hints used by `QueueBehavior::SmartShuffle`.  Takes effect the
+    /// next time the queue order is computed.
+    SetShuffleHints {
+        hints: HashMap<ItemId, ShuffleHint>,
+    },
     /// Change playback volume to a value in 0.0..=1.0 range.
     SetVolume {
         volume: f64,
     },
+    /// Turn the reporting of the played items to Spotify on or off.  Only the
+    /// `PlaybackReporter` following the events acts on it.
+    SetReporting {
+        enabled: bool,
+    },
 }
 
 pub enum PlayerEvent {
@@ -466,11 +682,25 @@ pub enum PlayerEvent {
         path: MediaPath,
         position: Duration,
     },
+    /// The playing track ends in `remaining`, and `item` will follow it.  Sent
+    /// once per track, `PlaybackConfig::up_next_lead` before its end.
+    UpNext {
+        item: PlaybackItem,
+        remaining: Duration,
+    },
     /// Player would like to continue playing, but is blocked, waiting for I/O.
     Blocked {
         path: MediaPath,
         position: Duration,
     },
+    /// Playback got stuck and recovering from it failed repeatedly.  `Pausing`
+    /// follows.
+    Stalled {
+        path: MediaPath,
+        position: Duration,
+    },
+    /// Sent periodically by the watchdog, so the player can notice stalls.
+    Tick,
     /// Player has finished playing a track.  `Loading` or `Playing` might
     /// follow if the queue is not empty, `Stopped` will follow if it is.
     EndOfTrack,
@@ -495,6 +725,41 @@ enum PlayerState {
     Invalid,
 }
 
+/// Playing item loaded again after a stall.  For the upper layers it keeps
+/// playing, so the commands meant for it are applied here until it's loaded.
+#[derive(Clone, Copy)]
+struct Reload {
+    path: MediaPath,
+    position: Duration,
+    paused: bool,
+}
+
+impl Reload {
+    /// Returns whether it wasn't paused already.
+    fn pause(&mut self) -> bool {
+        !mem::replace(&mut self.paused, true)
+    }
+
+    /// Returns whether it was paused.
+    fn resume(&mut self) -> bool {
+        mem::replace(&mut self.paused, false)
+    }
+
+    fn seek(&mut self, position: Duration) {
+        self.position = position;
+    }
+
+    /// State to continue in, once the item is loaded from `path`.
+    fn state(&self, path: MediaPath) -> PlayerState {
+        let position = self.position;
+        if self.paused {
+            PlayerState::Paused { path, position }
+        } else {
+            PlayerState::Playing { path, position }
+        }
+    }
+}
+
 enum PreloadState {
     Preloading {
         item: PlaybackItem,
@@ -506,3 +771,72 @@ enum PreloadState {
     },
     None,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::item_id::{FileId, ItemIdType};
+
+    use super::{file::AudioFormat, *};
+
+    const AT: Duration = Duration::from_secs(30);
+
+    fn path() -> MediaPath {
+        MediaPath {
+            item_id: ItemId::new(1, ItemIdType::Track),
+            file_id: FileId::default(),
+            file_format: AudioFormat::OggVorbis,
+            duration: Duration::from_secs(180),
+        }
+    }
+
+    fn reload() -> Reload {
+        Reload {
+            path: path(),
+            position: AT,
+            paused: false,
+        }
+    }
+
+    #[test]
+    fn reload_continues_playing_where_it_stalled() {
+        let reload = reload();
+        assert!(matches!(
+            reload.state(path()),
+            PlayerState::Playing { position: AT, .. }
+        ));
+    }
+
+    #[test]
+    fn pause_while_reloading_continues_paused() {
+        let mut reload = reload();
+        assert!(reload.pause());
+        assert!(!reload.pause());
+        assert!(matches!(
+            reload.state(path()),
+            PlayerState::Paused { position: AT, .. }
+        ));
+    }
+
+    #[test]
+    fn resume_while_reloading_continues_playing() {
+        let mut reload = reload();
+        assert!(!reload.resume());
+        reload.pause();
+        assert!(reload.resume());
+        assert!(matches!(
+            reload.state(path()),
+            PlayerState::Playing { position: AT, .. }
+        ));
+    }
+
+    #[test]
+    fn seek_while_reloading_continues_from_there() {
+        let mut reload = reload();
+        reload.seek(Duration::from_secs(90));
+        reload.pause();
+        assert!(matches!(
+            reload.state(path()),
+            PlayerState::Paused { position, .. } if position == Duration::from_secs(90)
+        ));
+    }
+}
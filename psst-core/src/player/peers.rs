@@ -0,0 +1,134 @@
+//! Connections of the listen-along followers and of the daemon clients.  Every
+//! peer gets a thread of its own writing the messages out, so a stalled peer
+//! can't hold up the player thread broadcasting to them.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{Shutdown, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::{bounded, Sender, TrySendError};
+use rand::Rng;
+use serde::Serialize;
+
+/// Messages waiting to be written to a peer, before it's dropped for not taking
+/// them in time.
+const PEER_BACKLOG: usize = 64;
+
+/// Peers not taking a message in time are dropped.
+const PEER_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Peers have this long to send their token after connecting.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Length of a token, in bytes before hex encoding.
+const TOKEN_LEN: usize = 16;
+
+/// Anything longer than this is not a token.
+const MAX_TOKEN_LINE: u64 = 256;
+
+struct Peer {
+    stream: TcpStream,
+    lines: Sender<Vec<u8>>,
+}
+
+#[derive(Default)]
+pub(crate) struct Peers {
+    peers: Vec<Peer>,
+}
+
+impl Peers {
+    /// Start writing the messages to `stream`, beginning with `greeting`.
+    pub fn add(&mut self, stream: TcpStream, greeting: &impl Serialize) -> io::Result<()> {
+        stream.set_write_timeout(Some(PEER_WRITE_TIMEOUT))?;
+        let (lines, receiver) = bounded::<Vec<u8>>(PEER_BACKLOG);
+        lines
+            .try_send(to_line(greeting)?)
+            .expect("new channel has room");
+        thread::spawn({
+            let mut stream = stream.try_clone()?;
+            move || {
+                for line in receiver {
+                    if let Err(err) = stream.write_all(&line) {
+                        log::info!("dropping peer {:?}: {}", stream.peer_addr(), err);
+                        let _ = stream.shutdown(Shutdown::Both);
+                        break;
+                    }
+                }
+            }
+        });
+        self.peers.push(Peer { stream, lines });
+        Ok(())
+    }
+
+    /// Queue `message` for all the peers, dropping the ones lagging behind.
+    pub fn send(&mut self, message: &impl Serialize) {
+        let line = match to_line(message) {
+            Ok(line) => line,
+            Err(err) => {
+                log::error!("failed to serialize message for peers: {}", err);
+                return;
+            }
+        };
+        self.peers
+            .retain(|peer| match peer.lines.try_send(line.clone()) {
+                Ok(_) => true,
+                Err(TrySendError::Full(_)) => {
+                    log::info!(
+                        "dropping peer {:?}: too far behind",
+                        peer.stream.peer_addr()
+                    );
+                    let _ = peer.stream.shutdown(Shutdown::Both);
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+
+    pub fn close(&mut self) {
+        for peer in self.peers.drain(..) {
+            let _ = peer.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+fn to_line(message: &impl Serialize) -> io::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Random token for peers to authenticate with, hex encoded.
+pub(crate) fn generate_token() -> String {
+    let bytes: [u8; TOKEN_LEN] = rand::thread_rng().gen();
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Send `token` as the first line, before anything else is sent over `stream`.
+pub(crate) fn send_token(mut stream: &TcpStream, token: &str) -> io::Result<()> {
+    stream.write_all(format!("{}\n", token.trim()).as_bytes())
+}
+
+/// Read the first line of a peer and check that it's `token`.  Returns the reader
+/// to continue with, or an error if the peer didn't authenticate in time.
+pub(crate) fn accept_token(stream: &TcpStream, token: &str) -> io::Result<BufReader<TcpStream>> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    (&mut reader).take(MAX_TOKEN_LINE).read_line(&mut line)?;
+    // An empty token would let in anyone sending an empty line.
+    if token.is_empty() || !constant_time_eq(line.trim_end().as_bytes(), token.as_bytes()) {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "invalid token",
+        ));
+    }
+    stream.set_read_timeout(None)?;
+    Ok(reader)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
@@ -1,26 +1,81 @@
-use rand::prelude::SliceRandom;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    mem,
+    sync::Arc,
+};
+
+use rand::{prelude::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::item_id::ItemId;
 
 use super::PlaybackItem;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum QueueBehavior {
     Sequential,
     Random,
+    /// Like `Random`, but frequently skipped items are pushed towards the end and
+    /// items by the same artist are spread apart.  Uses the hints passed in through
+    /// `Queue::set_hints`.
+    SmartShuffle,
     LoopTrack,
     LoopAll,
 }
 
+/// Information about a queued item, used for the smart shuffle ordering.
+#[derive(Debug, Clone, Default)]
+pub struct ShuffleHint {
+    pub skip_count: u32,
+    pub artist: Option<Arc<str>>,
+}
+
 impl Default for QueueBehavior {
     fn default() -> Self {
         Self::Sequential
     }
 }
 
+/// Items queued by the user, in the order they are going to play.
+#[derive(Default)]
+struct ManualLane {
+    items: VecDeque<PlaybackItem>,
+    /// Item of this lane that is playing, if any.
+    current: Option<PlaybackItem>,
+}
+
+impl ManualLane {
+    /// Start playing the next item of the lane.  Returns false if it's empty, and
+    /// the context should continue instead.
+    fn advance(&mut self) -> bool {
+        self.current = self.items.pop_front();
+        self.current.is_some()
+    }
+
+    /// Stop playing the item of this lane.  Returns false if none was playing.
+    fn leave(&mut self) -> bool {
+        self.current.take().is_some()
+    }
+}
+
+/// The queue of the player, made of two lanes that don't mix:
+///
+/// - The manual lane always goes first.  Its items play before the context
+///   continues, in the order they were queued: "Play Next" puts items in front
+///   of the lane, "Add to Queue" at its end.
+/// - The queue behavior only orders the context lane.  Shuffling, looping, or
+///   changing the context keeps the manual lane as it is.
+/// - Going back from an item of the manual lane returns to the context item it
+///   interrupted, and the context continues after that item once the manual
+///   lane is empty.
 pub struct Queue {
     items: Vec<PlaybackItem>,
     position: usize,
     positions: Vec<usize>,
     behavior: QueueBehavior,
+    manual: ManualLane,
+    hints: HashMap<ItemId, ShuffleHint>,
 }
 
 impl Queue {
@@ -30,6 +85,8 @@ impl Queue {
             position: 0,
             positions: Vec::new(),
             behavior: QueueBehavior::default(),
+            manual: ManualLane::default(),
+            hints: HashMap::new(),
         }
     }
 
@@ -37,14 +94,75 @@ impl Queue {
         self.items.clear();
         self.positions.clear();
         self.position = 0;
+        self.manual = ManualLane::default();
     }
 
     pub fn fill(&mut self, items: Vec<PlaybackItem>, position: usize) {
+        // Keep the manual lane around, it survives switching the context.
+        self.manual.leave();
         self.items = items;
         self.position = position;
         self.compute_positions();
     }
 
+    /// Replace the items of the playing context, after some got added to it or
+    /// removed from it.  The current item keeps playing.  When shuffling, the
+    /// items that already played stay behind, and the new ones get shuffled into
+    /// the rest.
+    pub fn update_items(&mut self, items: Vec<PlaybackItem>) {
+        let new_indices: HashMap<ItemId, usize> = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| (item.item_id, index))
+            .collect();
+        // Follow the current order with the new indices.  If the current item got
+        // removed, continue from the one before it, so the next one stays next.
+        let mut kept = Vec::with_capacity(self.positions.len());
+        let mut position = 0;
+        for (order, &index) in self.positions.iter().enumerate() {
+            let new_index = self
+                .items
+                .get(index)
+                .and_then(|item| new_indices.get(&item.item_id));
+            kept.extend(new_index);
+            if order == self.position {
+                position = kept.len().saturating_sub(1);
+            }
+        }
+        if let QueueBehavior::Random | QueueBehavior::SmartShuffle = self.behavior {
+            let is_kept: HashSet<usize> = kept.iter().copied().collect();
+            let mut rng = rand::thread_rng();
+            for index in (0..items.len()).filter(|index| !is_kept.contains(index)) {
+                let at = rng.gen_range((position + 1).min(kept.len())..=kept.len());
+                kept.insert(at, index);
+            }
+            self.positions = kept;
+            self.position = position;
+        } else {
+            self.position = kept.get(position).copied().unwrap_or(0);
+            self.positions = (0..items.len()).collect();
+        }
+        self.items = items;
+    }
+
+    /// Queue `items` at the end of the manual lane.
+    pub fn add(&mut self, items: Vec<PlaybackItem>) {
+        self.manual.items.extend(items);
+    }
+
+    /// Queue `items` in front of the manual lane, so they are played right after
+    /// the current one.
+    pub fn add_next(&mut self, items: Vec<PlaybackItem>) {
+        for item in items.into_iter().rev() {
+            self.manual.items.push_front(item);
+        }
+    }
+
+    pub fn set_hints(&mut self, hints: HashMap<ItemId, ShuffleHint>) {
+        self.hints = hints;
+    }
+
+    /// Change how the context lane is ordered, the manual lane stays as it is.
     pub fn set_behaviour(&mut self, behavior: QueueBehavior) {
         self.behavior = behavior;
         self.compute_positions();
@@ -61,13 +179,17 @@ impl Queue {
         // Start with an ordered 1:1 mapping.
         self.positions = (0..self.items.len()).collect();
 
-        if let QueueBehavior::Random = self.behavior {
+        if let QueueBehavior::Random | QueueBehavior::SmartShuffle = self.behavior {
             // Swap the current position with the first item, so we will start from the
             // beginning, with the full queue ahead of us.  Then shuffle the rest of the
             // items and set the position to 0.
             if self.positions.len() > 1 {
                 self.positions.swap(0, self.position);
-                self.positions[1..].shuffle(&mut rand::thread_rng());
+                if let QueueBehavior::SmartShuffle = self.behavior {
+                    self.smart_shuffle();
+                } else {
+                    self.positions[1..].shuffle(&mut rand::thread_rng());
+                }
             }
             self.position = 0;
         } else {
@@ -75,24 +197,82 @@ impl Queue {
         }
     }
 
+    fn smart_shuffle(&mut self) {
+        let mut positions = mem::take(&mut self.positions);
+        let mut rng = rand::thread_rng();
+
+        // Weighted random permutation: every item draws a key of `u^(1/weight)` and
+        // the items are sorted by it, so skipped items tend to end up later in the
+        // queue, but can still appear anywhere.
+        let mut keyed: Vec<(f64, usize)> = positions[1..]
+            .iter()
+            .map(|&position| {
+                let skips = self.hint(position).map_or(0, |hint| hint.skip_count);
+                let weight = 1.0 / (1.0 + skips as f64);
+                (rng.gen::<f64>().powf(1.0 / weight), position)
+            })
+            .collect();
+        keyed.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (slot, (_, position)) in positions[1..].iter_mut().zip(keyed) {
+            *slot = position;
+        }
+
+        // Spread out the artists.  When two neighbours share an artist, pull the next
+        // item with a different artist in between them, keeping the rest of the order.
+        for i in 1..positions.len() {
+            let previous = self.artist(positions[i - 1]);
+            if previous.is_none() || self.artist(positions[i]) != previous {
+                continue;
+            }
+            if let Some(j) =
+                (i + 1..positions.len()).find(|&j| self.artist(positions[j]) != previous)
+            {
+                positions[i..=j].rotate_right(1);
+            }
+        }
+
+        self.positions = positions;
+    }
+
+    fn hint(&self, position: usize) -> Option<&ShuffleHint> {
+        self.hints.get(&self.items.get(position)?.item_id)
+    }
+
+    fn artist(&self, position: usize) -> Option<&Arc<str>> {
+        self.hint(position)?.artist.as_ref()
+    }
+
     pub fn skip_to_previous(&mut self) {
-        self.position = self.previous_position();
+        // Going back from a manually queued item returns to the item it interrupted.
+        if !self.manual.leave() {
+            self.position = self.previous_position();
+        }
     }
 
     pub fn skip_to_next(&mut self) {
-        self.position = self.next_position();
+        if !self.manual.advance() {
+            self.position = self.next_position();
+        }
     }
 
     pub fn skip_to_following(&mut self) {
-        self.position = self.following_position();
+        if !self.manual.advance() {
+            self.position = self.following_position();
+        }
     }
 
     pub fn get_current(&self) -> Option<&PlaybackItem> {
+        if let Some(item) = &self.manual.current {
+            return Some(item);
+        }
         let position = self.positions.get(self.position).copied()?;
         self.items.get(position)
     }
 
     pub fn get_following(&self) -> Option<&PlaybackItem> {
+        if let Some(item) = self.manual.items.front() {
+            return Some(item);
+        }
         let position = self.positions.get(self.following_position()).copied()?;
         self.items.get(position)
     }
@@ -101,6 +281,7 @@ impl Queue {
         match self.behavior {
             QueueBehavior::Sequential
             | QueueBehavior::Random
+            | QueueBehavior::SmartShuffle
             | QueueBehavior::LoopTrack
             | QueueBehavior::LoopAll => self.position.saturating_sub(1),
         }
@@ -108,18 +289,112 @@ impl Queue {
 
     fn next_position(&self) -> usize {
         match self.behavior {
-            QueueBehavior::Sequential | QueueBehavior::Random | QueueBehavior::LoopTrack => {
-                self.position + 1
-            }
-            QueueBehavior::LoopAll => (self.position + 1) % self.items.len(),
+            QueueBehavior::Sequential
+            | QueueBehavior::Random
+            | QueueBehavior::SmartShuffle
+            | QueueBehavior::LoopTrack => self.position + 1,
+            QueueBehavior::LoopAll => (self.position + 1) % self.items.len().max(1),
         }
     }
 
     fn following_position(&self) -> usize {
         match self.behavior {
-            QueueBehavior::Sequential | QueueBehavior::Random => self.position + 1,
+            QueueBehavior::Sequential | QueueBehavior::Random | QueueBehavior::SmartShuffle => {
+                self.position + 1
+            }
             QueueBehavior::LoopTrack => self.position,
-            QueueBehavior::LoopAll => (self.position + 1) % self.items.len(),
+            QueueBehavior::LoopAll => (self.position + 1) % self.items.len().max(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{audio::normalize::NormalizationLevel, item_id::ItemIdType};
+
+    use super::*;
+
+    fn item(id: u128) -> PlaybackItem {
+        PlaybackItem {
+            item_id: ItemId::new(id, ItemIdType::Track),
+            norm_level: NormalizationLevel::None,
         }
     }
+
+    fn items(ids: impl IntoIterator<Item = u128>) -> Vec<PlaybackItem> {
+        ids.into_iter().map(item).collect()
+    }
+
+    fn current(queue: &Queue) -> Option<u128> {
+        queue.get_current().map(|item| item.item_id.id)
+    }
+
+    /// The current item and the `count` ones skipped to after it.
+    fn play(queue: &mut Queue, count: usize) -> Vec<u128> {
+        let mut played = current(queue).into_iter().collect::<Vec<_>>();
+        for _ in 0..count {
+            queue.skip_to_next();
+            played.extend(current(queue));
+        }
+        played
+    }
+
+    #[test]
+    fn play_next_goes_before_add_to_queue() {
+        let mut queue = Queue::new();
+        queue.fill(items(1..=3), 0);
+        queue.add(items([10]));
+        queue.add(items([11]));
+        queue.add_next(items([20, 21]));
+        assert_eq!(queue.get_following(), Some(&item(20)));
+        assert_eq!(play(&mut queue, 7), [1, 20, 21, 10, 11, 2, 3]);
+    }
+
+    #[test]
+    fn shuffling_keeps_the_manual_lane() {
+        let mut queue = Queue::new();
+        queue.fill(items(1..=20), 4);
+        queue.add(items([100, 101]));
+        queue.set_behaviour(QueueBehavior::Random);
+        assert_eq!(current(&queue), Some(5));
+
+        let played = play(&mut queue, 21);
+        assert_eq!(played[..3], [5, 100, 101]);
+        let mut context = played[3..].to_vec();
+        context.sort_unstable();
+        assert_eq!(context, (1..=20).filter(|&id| id != 5).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn changing_the_context_keeps_the_manual_lane() {
+        let mut queue = Queue::new();
+        queue.fill(items(1..=3), 0);
+        queue.add(items([10]));
+        queue.fill(items(4..=6), 1);
+        assert_eq!(play(&mut queue, 3), [5, 10, 6]);
+    }
+
+    #[test]
+    fn going_back_returns_to_the_interrupted_item() {
+        let mut queue = Queue::new();
+        queue.fill(items(1..=3), 1);
+        queue.add(items([10, 11]));
+        queue.skip_to_next();
+        assert_eq!(current(&queue), Some(10));
+        queue.skip_to_previous();
+        assert_eq!(current(&queue), Some(2));
+        assert_eq!(play(&mut queue, 2), [2, 11, 3]);
+    }
+
+    #[test]
+    fn manual_lane_plays_before_looping_the_track() {
+        let mut queue = Queue::new();
+        queue.fill(items(1..=3), 0);
+        queue.set_behaviour(QueueBehavior::LoopTrack);
+        queue.add(items([10]));
+        queue.skip_to_following();
+        assert_eq!(current(&queue), Some(10));
+        queue.skip_to_following();
+        assert_eq!(current(&queue), Some(1));
+    }
 }
@@ -0,0 +1,239 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crossbeam_channel::{bounded, unbounded, Sender};
+use parking_lot::Mutex;
+
+use crate::{
+    item_id::{ItemId, ItemIdType},
+    session::{
+        event::{EndReason, StartReason, TrackTransition},
+        SessionService,
+    },
+};
+
+use super::{PlayerCommand, PlayerEvent};
+
+/// Position reports further apart than this are seeks, the time in between
+/// didn't play.
+const MAX_POSITION_STEP: Duration = Duration::from_secs(3);
+
+/// How long closing the reporter waits for the last reports to be sent.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(2);
+
+struct CurrentPlayback {
+    item_id: ItemId,
+    playback_id: String,
+    context_uri: Option<String>,
+    start_reason: StartReason,
+    started_at: SystemTime,
+    duration: Duration,
+    codec: &'static str,
+    position: Duration,
+    played: Duration,
+}
+
+enum Report {
+    Transition(TrackTransition),
+    /// Answered once the reports before it are sent.
+    Flush(Sender<()>),
+}
+
+struct ReporterState {
+    current: Option<CurrentPlayback>,
+    /// Reasons for the next transition, known from the last command.
+    pending: Option<(EndReason, StartReason)>,
+    sequence: u64,
+}
+
+/// Reports the start, skip and completion of the played tracks to Spotify, from
+/// the player events.  Local files are never reported.
+///
+/// Every played item is reported once, when its playback ends, with the
+/// position it ended at.  Other devices offer to continue from there.
+#[derive(Clone)]
+pub struct PlaybackReporter {
+    /// Reports waiting to be sent by the reporting thread, so the player doesn't
+    /// wait for the session.
+    reports: Sender<Report>,
+    enabled: Arc<AtomicBool>,
+    state: Arc<Mutex<ReporterState>>,
+}
+
+impl PlaybackReporter {
+    pub fn new(session: SessionService) -> Self {
+        let (reports, report_recv) = unbounded();
+        thread::spawn(move || {
+            for report in report_recv {
+                match report {
+                    Report::Transition(transition) => Self::send(&session, &transition),
+                    Report::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        Self {
+            reports,
+            enabled: Arc::new(AtomicBool::new(true)),
+            state: Arc::new(Mutex::new(ReporterState {
+                current: None,
+                pending: None,
+                sequence: 0,
+            })),
+        }
+    }
+
+    /// Disabled reporters keep following the playback, but don't send anything.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Report the playing item as ended, as its playback ends with the app, and
+    /// give the reports a moment to go out.
+    pub fn close(&self) {
+        self.finish(&mut self.state.lock(), EndReason::EndPlay);
+        let (done, flushed) = bounded(1);
+        if self.reports.send(Report::Flush(done)).is_ok() {
+            let _ = flushed.recv_timeout(CLOSE_TIMEOUT);
+        }
+    }
+
+    /// Attach the URI of the context the playing item was started from.
+    pub fn set_context(&self, item_id: ItemId, context_uri: Option<String>) {
+        if let Some(current) = &mut self.state.lock().current {
+            if current.item_id == item_id {
+                current.context_uri = context_uri;
+            }
+        }
+    }
+
+    pub fn observe(&self, event: &PlayerEvent) {
+        let mut state = self.state.lock();
+        match event {
+            PlayerEvent::Command(command) => {
+                let reasons = match command {
+                    PlayerCommand::SetReporting { enabled } => {
+                        self.set_enabled(*enabled);
+                        return;
+                    }
+                    PlayerCommand::Next => (EndReason::ForwardButton, StartReason::ForwardButton),
+                    PlayerCommand::Previous => (EndReason::BackButton, StartReason::BackButton),
+                    PlayerCommand::LoadQueue { .. } | PlayerCommand::LoadAndPlay { .. } => {
+                        (EndReason::ClickRow, StartReason::ClickRow)
+                    }
+                    _ => return,
+                };
+                state.pending = Some(reasons);
+            }
+            PlayerEvent::Playing { path, .. } => {
+                let (end_reason, start_reason) = state
+                    .pending
+                    .take()
+                    .unwrap_or((EndReason::TrackDone, StartReason::TrackDone));
+                self.finish(&mut state, end_reason);
+                state.current = Some(CurrentPlayback {
+                    item_id: path.item_id,
+                    playback_id: format!("{:032x}", rand::random::<u128>()),
+                    context_uri: None,
+                    start_reason,
+                    started_at: SystemTime::now(),
+                    duration: path.duration,
+                    codec: path.file_format.codec_name(),
+                    position: Duration::ZERO,
+                    played: Duration::ZERO,
+                });
+            }
+            PlayerEvent::Position { position, .. } => {
+                if let Some(current) = &mut state.current {
+                    if *position > current.position
+                        && *position - current.position <= MAX_POSITION_STEP
+                    {
+                        current.played += *position - current.position;
+                    }
+                    current.position = *position;
+                }
+            }
+            PlayerEvent::Pausing { position, .. } => {
+                if let Some(current) = &mut state.current {
+                    current.position = *position;
+                }
+            }
+            PlayerEvent::EndOfTrack => {
+                if let Some(current) = &mut state.current {
+                    current.position = current.duration;
+                }
+                self.finish(&mut state, EndReason::TrackDone);
+                state.pending = Some((EndReason::TrackDone, StartReason::TrackDone));
+            }
+            PlayerEvent::Stopped => {
+                self.finish(&mut state, EndReason::EndPlay);
+                state.pending = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn is_reported(&self, current: &CurrentPlayback) -> bool {
+        let is_catalog_item = matches!(
+            current.item_id.id_type,
+            ItemIdType::Track | ItemIdType::Podcast
+        );
+        is_catalog_item && self.enabled.load(Ordering::Relaxed)
+    }
+
+    fn finish(&self, state: &mut ReporterState, end_reason: EndReason) {
+        let current = match state.current.take() {
+            Some(current) => current,
+            None => return,
+        };
+        if self.is_reported(&current) {
+            state.sequence += 1;
+            self.report(Self::transition(state.sequence, &current, end_reason));
+        }
+    }
+
+    fn transition(
+        sequence: u64,
+        current: &CurrentPlayback,
+        end_reason: EndReason,
+    ) -> TrackTransition {
+        TrackTransition {
+            sequence,
+            playback_id: current.playback_id.clone(),
+            item_id: current.item_id,
+            context_uri: current.context_uri.clone(),
+            start_reason: current.start_reason,
+            end_reason,
+            played: current.played,
+            position: current.position,
+            duration: current.duration,
+            started_at: current.started_at,
+            codec: current.codec,
+        }
+    }
+
+    fn report(&self, transition: TrackTransition) {
+        let _ = self.reports.send(Report::Transition(transition));
+    }
+
+    fn send(session: &SessionService, transition: &TrackTransition) {
+        let result = session
+            .connected()
+            .and_then(|session| session.send_event(&transition.to_event()));
+        match result {
+            Ok(_) => log::debug!(
+                "reported playback of {} ({})",
+                transition.item_id.to_base62(),
+                transition.end_reason
+            ),
+            Err(err) => log::warn!("failed to report playback: {}", err),
+        }
+    }
+}
@@ -4,7 +4,10 @@ use std::{
     io::{Read, Seek, SeekFrom, Write},
     ops::Range,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
 };
 
 use crossbeam_channel::{unbounded, Receiver, Sender};
@@ -54,6 +57,7 @@ impl StreamStorage {
                 downloaded: Mutex::new(RangeSet::new()),
                 requested: Mutex::new(RangeSet::new()),
                 condvar: Condvar::new(),
+                prefetch_len: AtomicU64::new(DEFAULT_PREFETCH_READ_LENGTH),
             }),
         })
     }
@@ -82,6 +86,7 @@ impl StreamStorage {
                 downloaded: Mutex::new(downloaded_set),
                 requested: Mutex::new(requested_set),
                 condvar: Condvar::new(),
+                prefetch_len: AtomicU64::new(DEFAULT_PREFETCH_READ_LENGTH),
             }),
         })
     }
@@ -108,6 +113,11 @@ impl StreamStorage {
     pub fn path(&self) -> &Path {
         self.file.path()
     }
+
+    /// Change how far in front of the reading head the data gets requested.
+    pub fn set_prefetch_length(&self, length: u64) {
+        self.data_map.set_prefetch_length(length);
+    }
 }
 
 enum StreamFile {
@@ -139,6 +149,10 @@ impl StreamWriter {
     pub fn mark_as_not_requested(&self, offset: u64, length: u64) {
         self.data_map.mark_as_not_requested(offset, length);
     }
+
+    pub fn set_prefetch_length(&self, length: u64) {
+        self.data_map.set_prefetch_length(length);
+    }
 }
 
 impl Write for StreamWriter {
@@ -161,7 +175,7 @@ impl Seek for StreamWriter {
 }
 
 const MINIMUM_READ_LENGTH: u64 = 1024 * 64;
-const PREFETCH_READ_LENGTH: u64 = 1024 * 256;
+const DEFAULT_PREFETCH_READ_LENGTH: u64 = 1024 * 256;
 
 impl Read for StreamReader {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -172,9 +186,11 @@ impl Read for StreamReader {
         }
         let needed_len = remaining_len.min(buf.len() as u64);
 
-        // Make sure that at least `PREFETCH_READ_LENGTH` bytes in front of the reading
-        // head is requested.
-        let prefetch_len = needed_len.max(PREFETCH_READ_LENGTH).min(remaining_len);
+        // Make sure that at least the prefetch length in front of the reading head is
+        // requested.
+        let prefetch_len = needed_len
+            .max(self.data_map.prefetch_len.load(Ordering::Relaxed))
+            .min(remaining_len);
         for (pos, len) in self.data_map.not_yet_requested(position, prefetch_len) {
             let req_len = len.max(MINIMUM_READ_LENGTH);
             self.data_map.mark_as_requested(pos, req_len);
@@ -216,6 +232,9 @@ struct StreamDataMap {
     // requested ranges.
     downloaded: Mutex<RangeSet<u64>>,
     condvar: Condvar,
+    // How many bytes in front of the reading head should be requested.  Tuned from the
+    // measured throughput, see `Cdn::prefetch_length`.
+    prefetch_len: AtomicU64,
 }
 
 impl StreamDataMap {
@@ -240,6 +259,11 @@ impl StreamDataMap {
         self.requested.lock().insert(offset..offset + length);
     }
 
+    fn set_prefetch_length(&self, length: u64) {
+        self.prefetch_len
+            .store(length.max(MINIMUM_READ_LENGTH), Ordering::Relaxed);
+    }
+
     /// Remove range previously marked as requested.
     fn mark_as_not_requested(&self, offset: u64, length: u64) {
         self.requested.lock().remove(offset..offset + length);
@@ -0,0 +1,208 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{unbounded, RecvTimeoutError, Sender};
+
+use super::PlayerEvent;
+
+/// How often the player gets to check on the playback.
+const TICK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Position reports come in about every second, so going without one for this
+/// long while playing means the playback is stuck.
+const STALL_TIMEOUT: Duration = Duration::from_secs(6);
+
+/// Recovery is attempted this many times in a row, then the stall is reported.
+const MAX_RECOVERIES: usize = 3;
+
+/// Notices when the player is playing, but the position doesn't move, because
+/// the download of the file stalled or the output stream died without an
+/// error.
+pub struct Watchdog {
+    /// Switches the ticks on and off, `None` when there's no thread sending them.
+    ticking: Option<Sender<bool>>,
+    is_ticking: bool,
+    position: Option<Duration>,
+    progressed_at: Instant,
+    /// Position the playback last got stuck at, and how many times in a row
+    /// recovering from it was attempted.
+    stalled_at: Option<Duration>,
+    recoveries: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Recovery {
+    Attempt(usize),
+    GiveUp,
+}
+
+impl Watchdog {
+    fn new(ticking: Option<Sender<bool>>) -> Self {
+        Self {
+            ticking,
+            is_ticking: false,
+            position: None,
+            progressed_at: Instant::now(),
+            stalled_at: None,
+            recoveries: 0,
+        }
+    }
+
+    /// Start a thread sending `PlayerEvent::Tick` to the player while the
+    /// watchdog is active.  The thread ends together with the watchdog.
+    pub fn start(sender: Sender<PlayerEvent>) -> Self {
+        let (ticking, switches) = unbounded();
+        thread::Builder::new()
+            .name("player_watchdog".to_string())
+            .spawn(move || {
+                let mut active = false;
+                loop {
+                    let received = if active {
+                        switches.recv_timeout(TICK_INTERVAL)
+                    } else {
+                        // Nothing to watch, sleep until there is.
+                        switches.recv().map_err(|_| RecvTimeoutError::Disconnected)
+                    };
+                    match received {
+                        Ok(is_active) => active = is_active,
+                        Err(RecvTimeoutError::Timeout) => {
+                            if sender.send(PlayerEvent::Tick).is_err() {
+                                break;
+                            }
+                        }
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            })
+            .unwrap();
+        Self::new(Some(ticking))
+    }
+
+    /// Send the ticks only while there's playback to watch.
+    pub fn set_active(&mut self, active: bool) {
+        if self.is_ticking != active {
+            self.is_ticking = active;
+            if let Some(ticking) = &self.ticking {
+                let _ = ticking.send(active);
+            }
+        }
+    }
+
+    /// Give the playback a fresh `STALL_TIMEOUT` to move, after it started,
+    /// resumed or seeked.
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.progressed_at = Instant::now();
+    }
+
+    pub fn observe(&mut self, position: Duration) {
+        if self.position != Some(position) {
+            self.position = Some(position);
+            self.progressed_at = Instant::now();
+        }
+        if self
+            .stalled_at
+            .map_or(false, |stalled_at| position > stalled_at)
+        {
+            // Got past the place it was stuck at, so the recovery worked.
+            self.stalled_at = None;
+            self.recoveries = 0;
+        }
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.progressed_at.elapsed() >= STALL_TIMEOUT
+    }
+
+    pub fn recover(&mut self, position: Duration) -> Recovery {
+        self.reset();
+        if self.stalled_at != Some(position) {
+            self.stalled_at = Some(position);
+            self.recoveries = 0;
+        }
+        if self.recoveries < MAX_RECOVERIES {
+            self.recoveries += 1;
+            Recovery::Attempt(self.recoveries)
+        } else {
+            // Let the next stall, after the user resumed, be tried again.
+            self.stalled_at = None;
+            self.recoveries = 0;
+            Recovery::GiveUp
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AT: Duration = Duration::from_secs(30);
+
+    /// Let `STALL_TIMEOUT` pass without the position moving.
+    fn stall(watchdog: &mut Watchdog) {
+        watchdog.progressed_at -= STALL_TIMEOUT;
+    }
+
+    #[test]
+    fn moving_position_is_not_a_stall() {
+        let mut watchdog = Watchdog::new(None);
+        watchdog.observe(AT);
+        assert!(!watchdog.is_stalled());
+        stall(&mut watchdog);
+        assert!(watchdog.is_stalled());
+        watchdog.observe(AT + Duration::from_secs(1));
+        assert!(!watchdog.is_stalled());
+    }
+
+    #[test]
+    fn repeated_position_is_a_stall() {
+        let mut watchdog = Watchdog::new(None);
+        watchdog.observe(AT);
+        stall(&mut watchdog);
+        watchdog.observe(AT);
+        assert!(watchdog.is_stalled());
+    }
+
+    #[test]
+    fn recovery_gives_up_after_the_attempts_and_starts_over() {
+        let mut watchdog = Watchdog::new(None);
+        for attempt in 1..=MAX_RECOVERIES {
+            assert_eq!(watchdog.recover(AT), Recovery::Attempt(attempt));
+            assert!(!watchdog.is_stalled());
+        }
+        assert_eq!(watchdog.recover(AT), Recovery::GiveUp);
+        assert_eq!(watchdog.recover(AT), Recovery::Attempt(1));
+    }
+
+    #[test]
+    fn getting_past_the_stall_resets_the_attempts() {
+        let mut watchdog = Watchdog::new(None);
+        watchdog.recover(AT);
+        watchdog.recover(AT);
+        watchdog.observe(AT);
+        assert_eq!(watchdog.recover(AT), Recovery::Attempt(3));
+        watchdog.observe(AT + Duration::from_secs(1));
+        assert_eq!(watchdog.recover(AT), Recovery::Attempt(1));
+    }
+
+    #[test]
+    fn stall_elsewhere_starts_counting_over() {
+        let mut watchdog = Watchdog::new(None);
+        watchdog.recover(AT);
+        watchdog.recover(AT);
+        assert_eq!(watchdog.recover(Duration::ZERO), Recovery::Attempt(1));
+    }
+
+    #[test]
+    fn ticks_are_switched_only_on_changes() {
+        let (ticking, switched) = unbounded();
+        let mut watchdog = Watchdog::new(Some(ticking));
+        watchdog.set_active(false);
+        watchdog.set_active(true);
+        watchdog.set_active(true);
+        watchdog.set_active(false);
+        assert_eq!(switched.try_iter().collect::<Vec<_>>(), [true, false]);
+    }
+}
@@ -18,9 +18,8 @@ use crate::{
     actor::{Act, Actor, ActorHandle},
     audio::{
         decode::AudioDecoder,
-        output::{AudioSink, DefaultAudioSink},
-        resample::ResamplingQuality,
-        source::{AudioSource, ResampledSource, StereoMappedSource},
+        output::{negotiate::FormatNegotiation, AudioSink, DefaultAudioSink},
+        source::AudioSource,
     },
     error::Error,
 };
@@ -34,6 +33,7 @@ pub struct PlaybackManager {
     sink: DefaultAudioSink,
     event_send: Sender<PlayerEvent>,
     current: Option<(MediaPath, Sender<Msg>)>,
+    negotiation: FormatNegotiation,
 }
 
 impl PlaybackManager {
@@ -42,6 +42,7 @@ impl PlaybackManager {
             sink,
             event_send,
             current: None,
+            negotiation: FormatNegotiation::new(),
         }
     }
 
@@ -54,24 +55,8 @@ impl PlaybackManager {
             self.event_send.clone(),
         );
         self.current = Some((path, source.actor.sender()));
-        if source.sample_rate() == self.sink.sample_rate()
-            && source.channel_count() == self.sink.channel_count()
-        {
-            // We can start playing the source right away.
-            self.sink.play(source);
-        } else {
-            // Some output streams have different sample rate than the source, so we need to
-            // resample before pushing to the sink.
-            let source = ResampledSource::new(
-                source,
-                self.sink.sample_rate(),
-                ResamplingQuality::SincMediumQuality,
-            );
-            // Source output streams also have a different channel count. Map the stereo
-            // channels and silence the others.
-            let source = StereoMappedSource::new(source, self.sink.channel_count());
-            self.sink.play(source);
-        }
+        let source = self.negotiation.negotiate(source, &self.sink);
+        self.sink.play(source);
         self.sink.resume();
     }
 
@@ -364,8 +349,8 @@ impl Worker {
             }
         } else {
             match self.input.read_packet(&mut self.input_packet) {
-                Some(_) => {
-                    self.samples_to_write = 0..self.input_packet.samples().len();
+                Some(samples_to_write) => {
+                    self.samples_to_write = samples_to_write;
                     self.is_reading = true;
                     self.this.send(Msg::Read)?;
                 }
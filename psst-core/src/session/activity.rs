@@ -0,0 +1,257 @@
+//! Local log of what happened to the session and the playback around it, so a
+//! stopped playback can be explained without going through the terminal output.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// Only this many of the latest entries are kept, both in memory and in the file.
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SessionActivity {
+    LoggedIn {
+        username: String,
+        access_point: String,
+    },
+    Reconnected {
+        access_point: String,
+    },
+    AccessPointSwitched {
+        from: String,
+        to: String,
+    },
+    ConnectionFailed {
+        error: String,
+    },
+    ConnectionLost {
+        error: String,
+    },
+    Closed,
+    OutputDeviceOpened {
+        device: Option<String>,
+    },
+    OutputDeviceDisconnected,
+    OutputDeviceReconnected,
+    /// The playback moved over to another output device.
+    OutputDeviceSwitched {
+        from: Option<String>,
+        to: Option<String>,
+    },
+    ListenAlongStarted {
+        address: String,
+    },
+    ListenAlongEnded {
+        error: Option<String>,
+    },
+}
+
+impl fmt::Display for SessionActivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LoggedIn {
+                username,
+                access_point,
+            } => write!(f, "Logged in as {} through {}", username, access_point),
+            Self::Reconnected { access_point } => write!(f, "Reconnected through {}", access_point),
+            Self::AccessPointSwitched { from, to } => {
+                write!(f, "Switched access point from {} to {}", from, to)
+            }
+            Self::ConnectionFailed { error } => write!(f, "Failed to connect: {}", error),
+            Self::ConnectionLost { error } => write!(f, "Connection lost: {}", error),
+            Self::Closed => write!(f, "Session closed"),
+            Self::OutputDeviceOpened { device: Some(name) } => {
+                write!(f, "Playing through {}", name)
+            }
+            Self::OutputDeviceOpened { device: None } => {
+                write!(f, "Playing through the default device")
+            }
+            Self::OutputDeviceDisconnected => write!(f, "Audio device disconnected"),
+            Self::OutputDeviceReconnected => write!(f, "Audio device reconnected"),
+            Self::OutputDeviceSwitched { from, to } => write!(
+                f,
+                "Playback moved from {} to {}",
+                from.as_deref().unwrap_or("the default device"),
+                to.as_deref().unwrap_or("the default device")
+            ),
+            Self::ListenAlongStarted { address } => write!(f, "Listening along with {}", address),
+            Self::ListenAlongEnded { error: Some(error) } => {
+                write!(f, "Stopped listening along: {}", error)
+            }
+            Self::ListenAlongEnded { error: None } => write!(f, "Stopped listening along"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    /// Seconds since the Unix epoch, in UTC.
+    pub timestamp: u64,
+    #[serde(flatten)]
+    pub activity: SessionActivity,
+}
+
+struct LogState {
+    entries: VecDeque<ActivityEntry>,
+    file: Option<File>,
+}
+
+/// Cheap to clone, shareable log of the session activity.  Entries are kept in
+/// memory, and once `persist_to` is called, also appended to a file, one JSON
+/// object per line.
+#[derive(Clone)]
+pub struct ActivityLog {
+    state: Arc<Mutex<LogState>>,
+}
+
+impl Default for ActivityLog {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LogState {
+                entries: VecDeque::new(),
+                file: None,
+            })),
+        }
+    }
+}
+
+impl ActivityLog {
+    /// Load the entries already in the file at `path`, and from now on, append
+    /// the new ones to it.
+    pub fn persist_to(&self, path: PathBuf) -> io::Result<()> {
+        let mut loaded: VecDeque<ActivityEntry> = match File::open(&path) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| serde_json::from_str(&line).ok())
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => VecDeque::new(),
+            Err(err) => return Err(err),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut state = self.state.lock();
+        // Entries recorded before the file was set up get written out as well.
+        let is_truncated = loaded.len() + state.entries.len() > MAX_ENTRIES;
+        let pending = state.entries.len();
+        loaded.extend(state.entries.drain(..));
+        while loaded.len() > MAX_ENTRIES {
+            loaded.pop_front();
+        }
+        let file = if is_truncated {
+            // Rewrite the file so it doesn't grow without bounds.
+            let file = File::create(&path)?;
+            for entry in &loaded {
+                write_entry(&file, entry);
+            }
+            file
+        } else {
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            for entry in loaded.iter().skip(loaded.len() - pending) {
+                write_entry(&file, entry);
+            }
+            file
+        };
+        state.entries = loaded;
+        state.file = Some(file);
+        Ok(())
+    }
+
+    pub fn record(&self, activity: SessionActivity) {
+        log::info!("session activity: {}", activity);
+        let entry = ActivityEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            activity,
+        };
+        let mut state = self.state.lock();
+        if let Some(file) = &state.file {
+            write_entry(file, &entry);
+        }
+        if state.entries.len() == MAX_ENTRIES {
+            state.entries.pop_front();
+        }
+        state.entries.push_back(entry);
+    }
+
+    /// Close the file and drop the entries, before the file is deleted.  New
+    /// entries are kept in memory only, until `persist_to` is called again.
+    pub fn close(&self) {
+        let mut state = self.state.lock();
+        state.file = None;
+        state.entries.clear();
+    }
+
+    /// The recorded entries, oldest first.
+    pub fn entries(&self) -> Vec<ActivityEntry> {
+        self.state.lock().entries.iter().cloned().collect()
+    }
+}
+
+fn write_entry(mut file: &File, entry: &ActivityEntry) {
+    let result = serde_json::to_string(entry)
+        .map_err(io::Error::from)
+        .and_then(|line| writeln!(file, "{}", line));
+    if let Err(err) = result {
+        log::warn!("failed to write session activity: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(path: &std::path::Path) -> Vec<String> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn entries_are_appended_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("activity.json");
+        let log = ActivityLog::default();
+        log.record(SessionActivity::Closed);
+        log.persist_to(path.clone()).unwrap();
+        log.record(SessionActivity::OutputDeviceDisconnected);
+
+        assert_eq!(lines(&path).len(), 2);
+        let reloaded = ActivityLog::default();
+        reloaded.persist_to(path).unwrap();
+        assert_eq!(reloaded.entries().len(), 2);
+    }
+
+    #[test]
+    fn log_is_reopened_after_being_closed_and_deleted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("activity.json");
+        let log = ActivityLog::default();
+        log.persist_to(path.clone()).unwrap();
+        log.record(SessionActivity::Closed);
+
+        log.close();
+        fs::remove_file(&path).unwrap();
+        log.record(SessionActivity::OutputDeviceReconnected);
+        assert!(!path.exists());
+
+        log.persist_to(path.clone()).unwrap();
+        assert_eq!(log.entries().len(), 1);
+        assert_eq!(lines(&path).len(), 1);
+        assert!(lines(&path)[0].contains("output_device_reconnected"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Playback reporting to the event service of the access point.  The official
+//! clients report every track they play this way, and it's what the recently
+//! played items, the recommendations and the resume points of other devices are
+//! based on.
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{connection::DEVICE_ID, item_id::ItemId};
+
+pub const EVENT_SERVICE_URI: &str = "hm://event-service/v1/events";
+
+/// Fields of an event, sent to the service separated by tabs.
+#[derive(Debug, Clone)]
+pub struct Event {
+    fields: Vec<String>,
+}
+
+impl Event {
+    fn new(kind: u32, version: u32) -> Self {
+        Self {
+            fields: vec![kind.to_string(), version.to_string()],
+        }
+    }
+
+    fn field(mut self, value: impl ToString) -> Self {
+        self.fields.push(value.to_string());
+        self
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        self.fields.join("\t").into_bytes()
+    }
+}
+
+/// Why did the playback of a track start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartReason {
+    /// The previous track played until its end.
+    TrackDone,
+    /// The track was picked from a list.
+    ClickRow,
+    ForwardButton,
+    BackButton,
+}
+
+/// Why did the playback of a track end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndReason {
+    /// The track played until its end.
+    TrackDone,
+    /// A different track was picked from a list.
+    ClickRow,
+    ForwardButton,
+    BackButton,
+    /// The playback was stopped.
+    EndPlay,
+}
+
+impl fmt::Display for StartReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TrackDone => "trackdone",
+            Self::ClickRow => "clickrow",
+            Self::ForwardButton => "fwdbtn",
+            Self::BackButton => "backbtn",
+        })
+    }
+}
+
+impl fmt::Display for EndReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::TrackDone => "trackdone",
+            Self::ClickRow => "clickrow",
+            Self::ForwardButton => "fwdbtn",
+            Self::BackButton => "backbtn",
+            Self::EndPlay => "endplay",
+        })
+    }
+}
+
+/// Playback of a single track, from its start to its end or skip.
+#[derive(Debug, Clone)]
+pub struct TrackTransition {
+    /// Number of the transition within the session.
+    pub sequence: u64,
+    /// Random ID of this playback, as hex.
+    pub playback_id: String,
+    pub item_id: ItemId,
+    pub context_uri: Option<String>,
+    pub start_reason: StartReason,
+    pub end_reason: EndReason,
+    /// How long the track actually played, seeked over parts excluded.
+    pub played: Duration,
+    /// Position the playback ended at.
+    pub position: Duration,
+    pub duration: Duration,
+    pub started_at: SystemTime,
+    /// Codec of the played file, like `vorbis`.
+    pub codec: &'static str,
+}
+
+impl TrackTransition {
+    const EVENT_KIND: u32 = 12;
+    const EVENT_VERSION: u32 = 38;
+
+    /// Field layout of the track transition events of the desktop client.  Most
+    /// of the latencies the official client measures are not known to us, those
+    /// get placeholder values.
+    pub fn to_event(&self) -> Event {
+        let played_ms = self.played.as_millis();
+        let position_ms = self.position.as_millis();
+        let started_at_ms = self
+            .started_at
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_millis())
+            .unwrap_or_default();
+        let context_uri = self.context_uri.clone().unwrap_or_default();
+        Event::new(Self::EVENT_KIND, Self::EVENT_VERSION)
+            .field(self.sequence)
+            .field(DEVICE_ID)
+            .field(&self.playback_id)
+            .field("00000000000000000000000000000000")
+            .field("unknown")
+            .field(self.start_reason)
+            .field("unknown")
+            .field(self.end_reason)
+            .field(0) // Decoded length.
+            .field(0) // File size.
+            .field(position_ms)
+            .field(position_ms)
+            .field(self.duration.as_millis())
+            .field(0) // Decryption latency.
+            .field(0) // Fade overlap.
+            .field(0)
+            .field(0)
+            .field(u8::from(played_ms > 0))
+            .field(played_ms)
+            .field(0) // Play latency.
+            .field(-1)
+            .field("context")
+            .field(-1) // Audio key latency.
+            .field(0)
+            .field(0) // Audio key was preloaded.
+            .field(0)
+            .field(0)
+            .field(0)
+            .field(played_ms)
+            .field(played_ms)
+            .field(0)
+            .field(0) // Bitrate.
+            .field(&context_uri)
+            .field(self.codec)
+            .field(self.item_id.to_base16())
+            .field("")
+            .field(0)
+            .field(started_at_ms)
+            .field(0)
+            .field("context")
+            .field(&context_uri)
+            .field(env!("CARGO_PKG_VERSION"))
+            .field("com.spotify")
+            .field("none")
+            .field("none")
+            .field("local")
+            .field("na")
+            .field("none")
+    }
+}
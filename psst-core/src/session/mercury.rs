@@ -1,6 +1,6 @@
 use std::{
     collections::HashMap,
-    io::{Cursor, Read},
+    io::{self, Cursor, Read},
 };
 
 use byteorder::{ReadBytesExt, BE};
@@ -16,6 +16,9 @@ use crate::{
 pub struct MercuryDispatcher {
     sequence: Sequence<u64>,
     pending: HashMap<u64, Pending>,
+    subscriptions: Vec<Subscription>,
+    /// Parts of the published messages that didn't arrive whole yet.
+    partial_events: HashMap<u64, Vec<Msg>>,
 }
 
 impl MercuryDispatcher {
@@ -23,6 +26,8 @@ impl MercuryDispatcher {
         Self {
             sequence: Sequence::new(0),
             pending: HashMap::new(),
+            subscriptions: Vec::new(),
+            partial_events: HashMap::new(),
         }
     }
 
@@ -31,6 +36,29 @@ impl MercuryDispatcher {
         req: MercuryRequest,
         callback: Sender<MercuryResponse>,
     ) -> ShannonMsg {
+        let seq = self.enqueue(callback);
+        ShannonMsg::new(ShannonMsg::MERCURY_REQ, req.encode_to_mercury_message(seq))
+    }
+
+    /// Like `enqueue_request`, but everything published under the URI of `req`
+    /// is sent to `events` afterwards, until the receiving side is dropped or the
+    /// subscription is rejected.
+    pub fn enqueue_subscription(
+        &mut self,
+        req: MercuryRequest,
+        callback: Sender<MercuryResponse>,
+        events: Sender<MercuryResponse>,
+    ) -> ShannonMsg {
+        let seq = self.enqueue(callback);
+        self.subscriptions.push(Subscription {
+            seq,
+            uri: req.uri.clone(),
+            events,
+        });
+        ShannonMsg::new(ShannonMsg::MERCURY_SUB, req.encode_to_mercury_message(seq))
+    }
+
+    fn enqueue(&mut self, callback: Sender<MercuryResponse>) -> u64 {
         let seq = self.sequence.advance();
         self.pending.insert(
             seq,
@@ -39,11 +67,64 @@ impl MercuryDispatcher {
                 messages: Vec::new(),
             },
         );
-        ShannonMsg::new(ShannonMsg::MERCURY_REQ, req.encode_to_mercury_message(seq))
+        seq
+    }
+
+    /// Stop sending published messages to `events`, for the subscriptions that
+    /// were given up on before anything was published under them.
+    pub fn remove_subscriptions(&mut self, events: &Sender<MercuryResponse>) {
+        self.subscriptions
+            .retain(|sub| !sub.events.same_channel(events));
+    }
+
+    pub fn handle_mercury_event(&mut self, shannon_msg: ShannonMsg) {
+        let msg = match Msg::decode(shannon_msg.payload) {
+            Ok(msg) => msg,
+            Err(err) => {
+                log::warn!("dropping malformed mercury event: {}", err);
+                return;
+            }
+        };
+        let msg_flags = msg.flags;
+        let msg_seq = msg.seq;
+        let mut messages = self.partial_events.remove(&msg_seq).unwrap_or_default();
+        messages.push(msg);
+        if msg_flags != Msg::FINAL {
+            self.partial_events.insert(msg_seq, messages);
+            return;
+        }
+        let event = match MercuryResponse::decode_from_parts(Msg::aggregate(messages)) {
+            Ok(event) => event,
+            Err(err) => {
+                log::warn!("dropping malformed mercury event: {}", err);
+                return;
+            }
+        };
+        let mut is_delivered = false;
+        // Subscriptions with the receiving side gone are dropped on the way.
+        self.subscriptions.retain(|sub| {
+            if !event.uri.starts_with(&sub.uri) {
+                return true;
+            }
+            is_delivered = true;
+            sub.events.send(event.clone()).is_ok()
+        });
+        if !is_delivered {
+            log::debug!(
+                "received mercury event without a subscription: {}",
+                event.uri
+            );
+        }
     }
 
     pub fn handle_mercury_req(&mut self, shannon_msg: ShannonMsg) {
-        let msg = Msg::decode(shannon_msg.payload);
+        let msg = match Msg::decode(shannon_msg.payload) {
+            Ok(msg) => msg,
+            Err(err) => {
+                log::warn!("dropping malformed mercury msg: {}", err);
+                return;
+            }
+        };
         let msg_flags = msg.flags;
         let msg_seq = msg.seq;
         if let Some(mut pending) = self.pending.remove(&msg_seq) {
@@ -51,7 +132,19 @@ impl MercuryDispatcher {
             if msg_flags == Msg::FINAL {
                 // This is the final message.  Aggregate all pending parts and process further.
                 let parts = Msg::aggregate(pending.messages);
-                let response = MercuryResponse::decode_from_parts(parts);
+                let response = match MercuryResponse::decode_from_parts(parts) {
+                    Ok(response) => response,
+                    Err(err) => {
+                        // Dropping the callback fails the request.
+                        log::warn!("dropping malformed mercury msg, seq: {}: {}", msg_seq, err);
+                        self.subscriptions.retain(|sub| sub.seq != msg_seq);
+                        return;
+                    }
+                };
+                if response.status_code != 200 {
+                    // A rejected subscription won't get any events.
+                    self.subscriptions.retain(|sub| sub.seq != msg_seq);
+                }
                 // Send the response.  If the response channel is closed, ignore it.
                 let _ = pending.callback.send(response);
             } else {
@@ -80,6 +173,14 @@ impl MercuryRequest {
         }
     }
 
+    pub fn subscribe(uri: String) -> Self {
+        Self {
+            uri,
+            method: "SUB".to_string(),
+            payload: Vec::new(),
+        }
+    }
+
     pub fn send(uri: String, data: Vec<u8>) -> Self {
         Self {
             uri,
@@ -115,18 +216,29 @@ pub struct MercuryResponse {
 }
 
 impl MercuryResponse {
-    fn decode_from_parts(mut parts: Vec<Vec<u8>>) -> Self {
+    fn decode_from_parts(mut parts: Vec<Vec<u8>>) -> Result<Self, Error> {
+        if parts.is_empty() {
+            return Err(Error::UnexpectedResponse);
+        }
         let header_part = parts.remove(0);
-        let header: Header =
-            deserialize_protobuf(&header_part).expect("Failed to deserialize message header");
-        Self {
-            uri: header.uri.unwrap(),
-            status_code: header.status_code.unwrap(),
+        let header: Header = deserialize_protobuf(&header_part)?;
+        Ok(Self {
+            uri: header.uri.ok_or(Error::UnexpectedResponse)?,
+            // Published events come without a status.
+            status_code: header.status_code.unwrap_or_default(),
             payload: parts,
-        }
+        })
     }
 }
 
+#[derive(Debug)]
+struct Subscription {
+    /// Sequence number of the subscribe request.
+    seq: u64,
+    uri: String,
+    events: Sender<MercuryResponse>,
+}
+
 #[derive(Debug)]
 struct Pending {
     messages: Vec<Msg>,
@@ -155,25 +267,31 @@ impl Msg {
         }
     }
 
-    fn decode(buf: Vec<u8>) -> Self {
+    fn decode(buf: Vec<u8>) -> io::Result<Self> {
         let mut buf = Cursor::new(buf);
-        let seq_len = buf.read_u16::<BE>().unwrap();
-        let seq = buf.read_uint::<BE>(seq_len.into()).unwrap();
-        let flags = buf.read_u8().unwrap();
-        let count = buf.read_u16::<BE>().unwrap();
+        let seq_len = buf.read_u16::<BE>()?;
+        if !(1..=8).contains(&seq_len) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid sequence length",
+            ));
+        }
+        let seq = buf.read_uint::<BE>(seq_len.into())?;
+        let flags = buf.read_u8()?;
+        let count = buf.read_u16::<BE>()?;
         let mut parts = Vec::with_capacity(count.into());
         for _ in 0..count {
-            let part_len = buf.read_u16::<BE>().unwrap();
+            let part_len = buf.read_u16::<BE>()?;
             let mut part = vec![0_u8; part_len.into()];
-            buf.read_exact(&mut part).unwrap();
+            buf.read_exact(&mut part)?;
             parts.push(part);
         }
-        Self {
+        Ok(Self {
             seq,
             flags,
             count,
             parts,
-        }
+        })
     }
 
     fn encode(&self) -> Vec<u8> {
@@ -221,3 +339,88 @@ impl From<quick_protobuf::Error> for Error {
         Error::IoError(err.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crossbeam_channel::{unbounded, Receiver};
+
+    use super::*;
+
+    const URI: &str = "hm://playlist/user/someone/rootlist";
+
+    fn message(seq: u64, uri: &str, status_code: Option<i32>) -> ShannonMsg {
+        let header = Header {
+            uri: Some(uri.to_string()),
+            status_code,
+            ..Header::default()
+        };
+        let parts = vec![serialize_protobuf(&header).unwrap(), b"payload".to_vec()];
+        ShannonMsg::new(
+            ShannonMsg::MERCURY_PUB,
+            Msg::new(seq, Msg::FINAL, parts).encode(),
+        )
+    }
+
+    /// Subscribe to `URI`, returning the sequence number of the request, and the
+    /// receivers of the response and the events.
+    fn subscribe(
+        mercury: &mut MercuryDispatcher,
+    ) -> (u64, Receiver<MercuryResponse>, Receiver<MercuryResponse>) {
+        let (callback, response) = unbounded();
+        let (events, received) = unbounded();
+        let msg = mercury.enqueue_subscription(
+            MercuryRequest::subscribe(URI.to_string()),
+            callback,
+            events,
+        );
+        let seq = Msg::decode(msg.payload).unwrap().seq;
+        (seq, response, received)
+    }
+
+    #[test]
+    fn malformed_events_are_dropped() {
+        let mut mercury = MercuryDispatcher::new();
+        let (seq, _response, events) = subscribe(&mut mercury);
+        mercury.handle_mercury_req(message(seq, URI, Some(200)));
+
+        mercury.handle_mercury_event(ShannonMsg::new(ShannonMsg::MERCURY_PUB, vec![0, 8, 1]));
+        mercury.handle_mercury_event(ShannonMsg::new(ShannonMsg::MERCURY_PUB, vec![0, 99]));
+        let headerless = Msg::new(7, Msg::FINAL, vec![vec![0xff, 0xff]]).encode();
+        mercury.handle_mercury_event(ShannonMsg::new(ShannonMsg::MERCURY_PUB, headerless));
+        let uriless = serialize_protobuf(&Header::default()).unwrap();
+        let uriless = Msg::new(8, Msg::FINAL, vec![uriless]).encode();
+        mercury.handle_mercury_event(ShannonMsg::new(ShannonMsg::MERCURY_PUB, uriless));
+        assert!(events.try_recv().is_err());
+
+        mercury.handle_mercury_event(message(9, URI, None));
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.uri, URI);
+        assert_eq!(event.payload, vec![b"payload".to_vec()]);
+    }
+
+    #[test]
+    fn rejected_subscriptions_are_removed() {
+        let mut mercury = MercuryDispatcher::new();
+        let (seq, response, events) = subscribe(&mut mercury);
+        mercury.handle_mercury_req(message(seq, URI, Some(403)));
+        assert_eq!(response.try_recv().unwrap().status_code, 403);
+        assert!(mercury.subscriptions.is_empty());
+
+        mercury.handle_mercury_event(message(9, URI, None));
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn subscriptions_given_up_on_are_removed() {
+        let mut mercury = MercuryDispatcher::new();
+        let (seq, _response, _events) = subscribe(&mut mercury);
+        mercury.handle_mercury_req(message(seq, URI, Some(200)));
+        let (_, _, kept) = subscribe(&mut mercury);
+        let given_up = mercury.subscriptions[0].events.clone();
+
+        mercury.remove_subscriptions(&given_up);
+        assert_eq!(mercury.subscriptions.len(), 1);
+        mercury.handle_mercury_event(message(9, URI, None));
+        assert!(kept.try_recv().is_ok());
+    }
+}
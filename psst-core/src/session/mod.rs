@@ -1,5 +1,7 @@
 pub mod access_token;
+pub mod activity;
 pub mod audio_key;
+pub mod event;
 pub mod mercury;
 
 use std::{
@@ -29,7 +31,9 @@ use crate::{
 };
 
 use self::{
+    activity::{ActivityLog, SessionActivity},
     audio_key::AudioKeyDispatcher,
+    event::{Event, EVENT_SERVICE_URI},
     mercury::{MercuryDispatcher, MercuryRequest, MercuryResponse},
 };
 
@@ -49,6 +53,9 @@ pub struct SessionConfig {
 pub struct SessionService {
     connected: Arc<Mutex<Option<SessionWorker>>>,
     config: Arc<Mutex<Option<SessionConfig>>>,
+    /// Access point of the last opened connection.
+    access_point: Arc<Mutex<Option<String>>>,
+    activity: ActivityLog,
 }
 
 impl SessionService {
@@ -58,6 +65,8 @@ impl SessionService {
         Self {
             connected: Arc::default(),
             config: Arc::default(),
+            access_point: Arc::default(),
+            activity: ActivityLog::default(),
         }
     }
 
@@ -66,6 +75,8 @@ impl SessionService {
         Self {
             connected: Arc::default(),
             config: Arc::new(Mutex::new(Some(config))),
+            access_point: Arc::default(),
+            activity: ActivityLog::default(),
         }
     }
 
@@ -74,6 +85,8 @@ impl SessionService {
     pub fn update_config(&self, config: SessionConfig) {
         self.config.lock().replace(config);
         self.shutdown();
+        // The next connection is a new login.
+        self.access_point.lock().take();
     }
 
     /// Returns true if a session worker is actively servicing the connected
@@ -93,14 +106,20 @@ impl SessionService {
         let is_connected_and_not_terminated =
             matches!(connected.as_ref(), Some(worker) if !worker.has_terminated());
         if !is_connected_and_not_terminated {
-            let connection = SessionConnection::open(
-                self.config
-                    .lock()
-                    .as_ref()
-                    .ok_or(Error::SessionDisconnected)?
-                    .clone(),
-            )?;
-            let worker = SessionWorker::run(connection.transport);
+            let config = self
+                .config
+                .lock()
+                .as_ref()
+                .ok_or(Error::SessionDisconnected)?
+                .clone();
+            let connection = SessionConnection::open(config).map_err(|err| {
+                self.activity.record(SessionActivity::ConnectionFailed {
+                    error: err.to_string(),
+                });
+                err
+            })?;
+            self.record_connected(&connection);
+            let worker = SessionWorker::run(connection.transport, self.activity.clone());
             connected.replace(worker);
         }
         connected
@@ -112,8 +131,37 @@ impl SessionService {
     /// Signal a shutdown to the active worker and wait until it terminates.
     pub fn shutdown(&self) {
         if let Some(worker) = self.connected.lock().take() {
+            let has_terminated = worker.has_terminated();
             worker.handle().request_shutdown();
             worker.join();
+            if !has_terminated {
+                self.activity.record(SessionActivity::Closed);
+            }
+        }
+    }
+
+    /// Log of the logins, reconnections and failures of this session.
+    pub fn activity(&self) -> &ActivityLog {
+        &self.activity
+    }
+
+    fn record_connected(&self, connection: &SessionConnection) {
+        let access_point = connection.access_point.clone();
+        let previous = self.access_point.lock().replace(access_point.clone());
+        match previous {
+            None => self.activity.record(SessionActivity::LoggedIn {
+                username: connection.credentials.username.clone(),
+                access_point,
+            }),
+            Some(previous) if previous != access_point => {
+                self.activity.record(SessionActivity::AccessPointSwitched {
+                    from: previous,
+                    to: access_point,
+                })
+            }
+            Some(_) => self
+                .activity
+                .record(SessionActivity::Reconnected { access_point }),
         }
     }
 }
@@ -125,6 +173,8 @@ pub struct SessionConnection {
     pub credentials: Credentials,
     /// I/O codec for the Shannon messages.
     pub transport: Transport,
+    /// Address of the access point we're connected to.
+    pub access_point: String,
 }
 
 impl SessionConnection {
@@ -141,6 +191,7 @@ impl SessionConnection {
         Ok(Self {
             credentials,
             transport,
+            access_point: ap_url,
         })
     }
 }
@@ -154,7 +205,9 @@ pub struct SessionWorker {
 }
 
 impl SessionWorker {
-    pub fn run(transport: Transport) -> Self {
+    /// Start servicing the connection.  In case the connection breaks, the reason
+    /// is recorded into `activity`.
+    pub fn run(transport: Transport, activity: ActivityLog) -> Self {
         let (disp_send, disp_recv) = unbounded();
         let (msg_send, msg_recv) = unbounded();
         let terminated = Arc::new(AtomicBool::new(false));
@@ -173,7 +226,11 @@ impl SessionWorker {
                 let stream = transport.stream;
                 let terminated = terminated.clone();
                 thread::spawn(move || {
-                    dispatch_messages(disp_recv, msg_send, stream);
+                    if let Err(err) = dispatch_messages(disp_recv, msg_send, stream) {
+                        activity.record(SessionActivity::ConnectionLost {
+                            error: err.to_string(),
+                        });
+                    }
                     terminated.store(true, Ordering::SeqCst);
                 })
             },
@@ -245,6 +302,54 @@ impl SessionHandle {
         Ok(first_part)
     }
 
+    pub fn send_event(&self, event: &Event) -> Result<(), Error> {
+        let (callback, receiver) = unbounded();
+        let request = MercuryRequest::send(EVENT_SERVICE_URI.to_string(), event.encode());
+        self.sender
+            .send(DispatchCmd::MercuryReq { callback, request })
+            .ok()
+            .ok_or(Error::SessionDisconnected)?;
+        let response = receiver.recv().ok().ok_or(Error::SessionDisconnected)?;
+        if response.status_code == 200 {
+            Ok(())
+        } else {
+            log::warn!("event rejected with status {}", response.status_code);
+            Err(Error::UnexpectedResponse)
+        }
+    }
+
+    /// Subscribe to the messages published under `uri`, like the changes of the
+    /// playlists.  They are sent to `events` until the session closes.
+    pub fn subscribe_mercury(
+        &self,
+        uri: String,
+        events: Sender<MercuryResponse>,
+    ) -> Result<(), Error> {
+        let (callback, receiver) = unbounded();
+        let request = MercuryRequest::subscribe(uri);
+        self.sender
+            .send(DispatchCmd::MercurySub {
+                request,
+                callback,
+                events,
+            })
+            .ok()
+            .ok_or(Error::SessionDisconnected)?;
+        let response = receiver.recv().ok().ok_or(Error::SessionDisconnected)?;
+        if response.status_code == 200 {
+            Ok(())
+        } else {
+            log::warn!("subscription rejected with status {}", response.status_code);
+            Err(Error::UnexpectedResponse)
+        }
+    }
+
+    /// Stop sending published messages to `events`, after giving up on the
+    /// subscriptions made with it.
+    pub fn unsubscribe_mercury(&self, events: Sender<MercuryResponse>) {
+        let _ = self.sender.send(DispatchCmd::MercuryUnsub { events });
+    }
+
     pub fn get_audio_key(&self, track: ItemId, file: FileId) -> Result<AudioKey, Error> {
         let (callback, receiver) = unbounded();
         self.sender
@@ -320,6 +425,14 @@ enum DispatchCmd {
         request: MercuryRequest,
         callback: Sender<MercuryResponse>,
     },
+    MercurySub {
+        request: MercuryRequest,
+        callback: Sender<MercuryResponse>,
+        events: Sender<MercuryResponse>,
+    },
+    MercuryUnsub {
+        events: Sender<MercuryResponse>,
+    },
     AudioKeyReq {
         track: ItemId,
         file: FileId,
@@ -334,11 +447,13 @@ enum DispatchCmd {
     Shutdown,
 }
 
+/// Service the requests and incoming messages until the connection is shut down
+/// (returns `Ok`) or breaks (returns the error it broke with).
 fn dispatch_messages(
     dispatch: Receiver<DispatchCmd>,
     messages: Sender<ShannonMsg>,
     stream: TcpStream,
-) {
+) -> io::Result<()> {
     let mut mercury = MercuryDispatcher::new();
     let mut audio_key = AudioKeyDispatcher::new();
     let mut country_code = None;
@@ -349,6 +464,17 @@ fn dispatch_messages(
                 let msg = mercury.enqueue_request(request, callback);
                 let _ = messages.send(msg);
             }
+            DispatchCmd::MercurySub {
+                request,
+                callback,
+                events,
+            } => {
+                let msg = mercury.enqueue_subscription(request, callback, events);
+                let _ = messages.send(msg);
+            }
+            DispatchCmd::MercuryUnsub { events } => {
+                mercury.remove_subscriptions(&events);
+            }
             DispatchCmd::AudioKeyReq {
                 track,
                 file,
@@ -372,21 +498,26 @@ fn dispatch_messages(
             DispatchCmd::DecodedMsg(msg) if msg.cmd == ShannonMsg::AES_KEY_ERROR => {
                 audio_key.handle_aes_key_error(msg)
             }
-            DispatchCmd::DecodedMsg(msg) if msg.cmd == ShannonMsg::MERCURY_REQ => {
+            DispatchCmd::DecodedMsg(msg)
+                if msg.cmd == ShannonMsg::MERCURY_REQ || msg.cmd == ShannonMsg::MERCURY_SUB =>
+            {
                 mercury.handle_mercury_req(msg)
             }
+            DispatchCmd::DecodedMsg(msg) if msg.cmd == ShannonMsg::MERCURY_PUB => {
+                mercury.handle_mercury_event(msg)
+            }
             DispatchCmd::DecodedMsg(msg) => {
                 log::debug!("ignored message: {:?}", msg.cmd);
             }
             DispatchCmd::DecoderError(err) => {
                 log::error!("connection error: {:?}", err);
                 let _ = stream.shutdown(Shutdown::Write);
-                break;
+                return Err(err);
             }
             DispatchCmd::EncoderError(err) => {
                 log::error!("connection error: {:?}", err);
                 let _ = stream.shutdown(Shutdown::Read);
-                break;
+                return Err(err);
             }
             DispatchCmd::Shutdown => {
                 log::info!("connection shutdown");
@@ -395,6 +526,7 @@ fn dispatch_messages(
             }
         }
     }
+    Ok(())
 }
 
 fn pong_message() -> ShannonMsg {
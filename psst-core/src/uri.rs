@@ -0,0 +1,318 @@
+//! Typed Spotify URIs.  Items are referred to by `spotify:<kind>:<id>` URIs, or by
+//! the `open.spotify.com/<kind>/<id>` URLs of the web player, both are accepted
+//! wherever the user can paste a link.
+
+use std::{fmt, sync::Arc};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use url::Url;
+
+use crate::item_id::{ItemId, ItemIdType};
+
+/// Item on Spotify, with its base62 ID, or the name of the user.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SpotifyUri {
+    Track(Arc<str>),
+    Album(Arc<str>),
+    Artist(Arc<str>),
+    Playlist(Arc<str>),
+    Show(Arc<str>),
+    Episode(Arc<str>),
+    User(Arc<str>),
+}
+
+impl SpotifyUri {
+    /// Parse either a `spotify:` URI or an `open.spotify.com` URL.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let uri = uri.trim();
+        match uri.strip_prefix("spotify:") {
+            Some(uri) => Self::from_segments(uri.split(':')),
+            None => {
+                let url = Url::parse(uri).ok()?;
+                if url.host_str() != Some("open.spotify.com") {
+                    return None;
+                }
+                let segments = url
+                    .path_segments()?
+                    .filter(|segment| !segment.is_empty())
+                    // Localized links go by `/intl-de/track/<id>`.
+                    .skip_while(|segment| segment.starts_with("intl-"));
+                Self::from_segments(segments)
+            }
+        }
+    }
+
+    fn from_segments<'a>(mut segments: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let kind = segments.next()?;
+        let id = Arc::from(segments.next().filter(|id| !id.is_empty())?);
+        let uri = match kind {
+            "track" => Self::Track(id),
+            "album" => Self::Album(id),
+            "artist" => Self::Artist(id),
+            "playlist" => Self::Playlist(id),
+            "show" => Self::Show(id),
+            "episode" => Self::Episode(id),
+            "user" => match segments.next() {
+                None => return Some(Self::User(id)),
+                // Playlists used to be namespaced by their owner.
+                Some("playlist") => Self::Playlist(Arc::from(segments.next()?)),
+                Some(_) => return None,
+            },
+            _ => return None,
+        };
+        // Anything further, like `spotify:user:<name>:collection`, is a different
+        // kind of item.
+        segments.next().is_none().then_some(uri)
+    }
+
+    /// URI of a playable item, `None` for local files.
+    pub fn from_item_id(item_id: ItemId) -> Option<Self> {
+        match item_id.id_type {
+            ItemIdType::Track => Some(Self::Track(item_id.to_base62().into())),
+            ItemIdType::Podcast => Some(Self::Episode(item_id.to_base62().into())),
+            ItemIdType::LocalFile | ItemIdType::Unknown => None,
+        }
+    }
+
+    /// ID of a track or an episode, `None` for the other kinds and malformed IDs.
+    pub fn item_id(&self) -> Option<ItemId> {
+        match self {
+            Self::Track(id) => ItemId::from_base62(id, ItemIdType::Track),
+            Self::Episode(id) => ItemId::from_base62(id, ItemIdType::Podcast),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> &Arc<str> {
+        match self {
+            Self::Track(id)
+            | Self::Album(id)
+            | Self::Artist(id)
+            | Self::Playlist(id)
+            | Self::Show(id)
+            | Self::Episode(id)
+            | Self::User(id) => id,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Track(_) => "track",
+            Self::Album(_) => "album",
+            Self::Artist(_) => "artist",
+            Self::Playlist(_) => "playlist",
+            Self::Show(_) => "show",
+            Self::Episode(_) => "episode",
+            Self::User(_) => "user",
+        }
+    }
+
+    /// Link to the item in the web player, for sharing.
+    pub fn to_url(&self) -> String {
+        format!("https://open.spotify.com/{}/{}", self.kind(), self.id())
+    }
+}
+
+impl fmt::Display for SpotifyUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "spotify:{}:{}", self.kind(), self.id())
+    }
+}
+
+/// Serialized as the `spotify:` URI.
+impl Serialize for SpotifyUri {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SpotifyUri {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let uri = String::deserialize(deserializer)?;
+        Self::parse(&uri).ok_or_else(|| de::Error::custom(format!("invalid Spotify URI: {}", uri)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "4uLU6hMCjMI75M1A2tKUQC";
+
+    fn parse(uri: &str) -> Option<SpotifyUri> {
+        SpotifyUri::parse(uri)
+    }
+
+    #[test]
+    fn parses_uris() {
+        let id = Arc::from(ID);
+        let uri = |kind| format!("spotify:{}:{}", kind, ID);
+        assert_eq!(
+            parse(&uri("track")),
+            Some(SpotifyUri::Track(Arc::clone(&id)))
+        );
+        assert_eq!(
+            parse(&uri("album")),
+            Some(SpotifyUri::Album(Arc::clone(&id)))
+        );
+        assert_eq!(
+            parse(&uri("artist")),
+            Some(SpotifyUri::Artist(Arc::clone(&id)))
+        );
+        assert_eq!(
+            parse(&uri("playlist")),
+            Some(SpotifyUri::Playlist(Arc::clone(&id)))
+        );
+        assert_eq!(parse(&uri("show")), Some(SpotifyUri::Show(Arc::clone(&id))));
+        assert_eq!(
+            parse(&uri("episode")),
+            Some(SpotifyUri::Episode(Arc::clone(&id)))
+        );
+        assert_eq!(
+            parse("spotify:user:someone"),
+            Some(SpotifyUri::User("someone".into()))
+        );
+        assert_eq!(
+            parse(&format!("  {}\n", uri("track"))),
+            parse(&uri("track"))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_uris() {
+        assert_eq!(parse("spotify:track"), None);
+        assert_eq!(parse("spotify:track:"), None);
+        assert_eq!(parse(&format!("spotify:genre:{}", ID)), None);
+        assert_eq!(parse(&format!("spotify:track:{}:extra", ID)), None);
+        assert_eq!(parse(ID), None);
+    }
+
+    #[test]
+    fn parses_urls() {
+        let track = Some(SpotifyUri::Track(ID.into()));
+        assert_eq!(
+            parse(&format!("https://open.spotify.com/track/{}", ID)),
+            track
+        );
+        assert_eq!(
+            parse(&format!(
+                "https://open.spotify.com/track/{}?si=abc123&nd=1",
+                ID
+            )),
+            track
+        );
+        assert_eq!(
+            parse(&format!("https://open.spotify.com/track/{}/", ID)),
+            track
+        );
+        assert_eq!(
+            parse(&format!("https://open.spotify.com/intl-de/track/{}", ID)),
+            track
+        );
+        assert_eq!(
+            parse(&format!(
+                "https://open.spotify.com/intl-pt/album/{}?si=x",
+                ID
+            )),
+            Some(SpotifyUri::Album(ID.into()))
+        );
+    }
+
+    #[test]
+    fn rejects_other_hosts() {
+        assert_eq!(parse(&format!("https://example.com/track/{}", ID)), None);
+        assert_eq!(parse(&format!("https://spotify.com/track/{}", ID)), None);
+        assert_eq!(parse("https://open.spotify.com/"), None);
+    }
+
+    #[test]
+    fn parses_legacy_user_playlists() {
+        let playlist = Some(SpotifyUri::Playlist(ID.into()));
+        assert_eq!(
+            parse(&format!("spotify:user:someone:playlist:{}", ID)),
+            playlist
+        );
+        assert_eq!(
+            parse(&format!(
+                "https://open.spotify.com/user/someone/playlist/{}",
+                ID
+            )),
+            playlist
+        );
+        assert_eq!(parse("spotify:user:someone:playlist"), None);
+    }
+
+    #[test]
+    fn rejects_other_user_items() {
+        assert_eq!(parse("spotify:user:someone:collection"), None);
+        assert_eq!(parse("spotify:user:someone:collection:your-episodes"), None);
+        assert_eq!(
+            parse(&format!("spotify:user:someone:playlist:{}:extra", ID)),
+            None
+        );
+    }
+
+    #[test]
+    fn formats_back_to_uris_and_urls() {
+        let uris = [
+            SpotifyUri::Track(ID.into()),
+            SpotifyUri::Album(ID.into()),
+            SpotifyUri::Artist(ID.into()),
+            SpotifyUri::Playlist(ID.into()),
+            SpotifyUri::Show(ID.into()),
+            SpotifyUri::Episode(ID.into()),
+            SpotifyUri::User("someone".into()),
+        ];
+        for uri in uris {
+            assert_eq!(parse(&uri.to_string()), Some(uri.clone()));
+            assert_eq!(parse(&uri.to_url()), Some(uri));
+        }
+        assert_eq!(
+            SpotifyUri::Track(ID.into()).to_string(),
+            format!("spotify:track:{}", ID)
+        );
+        assert_eq!(
+            SpotifyUri::Track(ID.into()).to_url(),
+            format!("https://open.spotify.com/track/{}", ID)
+        );
+    }
+
+    #[test]
+    fn converts_item_ids() {
+        let track = ItemId::from_base62(ID, ItemIdType::Track).unwrap();
+        let episode = ItemId::from_base62(ID, ItemIdType::Podcast).unwrap();
+        assert_eq!(
+            SpotifyUri::from_item_id(track),
+            Some(SpotifyUri::Track(ID.into()))
+        );
+        assert_eq!(
+            SpotifyUri::from_item_id(episode),
+            Some(SpotifyUri::Episode(ID.into()))
+        );
+        assert_eq!(SpotifyUri::from_item_id(ItemId::INVALID), None);
+        assert_eq!(SpotifyUri::Track(ID.into()).item_id(), Some(track));
+        assert_eq!(SpotifyUri::Episode(ID.into()).item_id(), Some(episode));
+        assert_eq!(SpotifyUri::Album(ID.into()).item_id(), None);
+        assert_eq!(SpotifyUri::Track("not base62!".into()).item_id(), None);
+    }
+
+    #[test]
+    fn item_ids_from_uris_keep_other_kinds() {
+        let uri = format!("spotify:album:{}", ID);
+        let id = ItemId::from_uri(&uri).unwrap();
+        assert_eq!(id.id_type, ItemIdType::Unknown);
+        assert_eq!(id.to_base62(), ID);
+        let url = format!("https://open.spotify.com/track/{}?si=abc", ID);
+        assert_eq!(ItemId::from_uri(&url).unwrap().id_type, ItemIdType::Track);
+        assert_eq!(ItemId::from_uri("spotify:user:someone:collection"), None);
+    }
+
+    #[test]
+    fn serializes_as_the_uri() {
+        let uri = SpotifyUri::Playlist(ID.into());
+        let json = serde_json::to_string(&uri).unwrap();
+        assert_eq!(json, format!("\"spotify:playlist:{}\"", ID));
+        assert_eq!(serde_json::from_str::<SpotifyUri>(&json).unwrap(), uri);
+        assert!(serde_json::from_str::<SpotifyUri>("\"spotify:nothing\"").is_err());
+    }
+}
@@ -3,7 +3,10 @@ use std::{io, io::SeekFrom, mem, time::Duration};
 use num_traits::{One, WrappingAdd};
 use quick_protobuf::{BytesReader, MessageRead, MessageWrite, Writer};
 
-use crate::error::Error;
+use crate::{
+    error::Error,
+    net::{self, InterleavingResolver},
+};
 
 pub const NET_CONNECT_TIMEOUT: Duration = Duration::from_millis(8 * 1000);
 
@@ -11,9 +14,11 @@ pub const NET_IO_TIMEOUT: Duration = Duration::from_millis(8 * 1000);
 
 pub fn default_ureq_agent_builder(proxy_url: Option<&str>) -> Result<ureq::AgentBuilder, Error> {
     let builder = ureq::AgentBuilder::new()
+        .resolver(InterleavingResolver)
         .timeout_connect(NET_CONNECT_TIMEOUT)
         .timeout_read(NET_IO_TIMEOUT)
         .timeout_write(NET_IO_TIMEOUT);
+    let builder = net::with_tls_config(builder);
     if let Some(url) = proxy_url {
         let proxy = ureq::Proxy::new(url)?;
         Ok(builder.proxy(proxy))
@@ -1,10 +1,14 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-use druid::{Selector, WidgetId};
+use crossbeam_channel::Sender;
+use druid::{im::Vector, Selector, WidgetId};
 use psst_core::item_id::ItemId;
 
 use crate::{
-    data::{Nav, PlaybackPayload, QueueBehavior},
+    data::{
+        ArtistLink, Integration, LibraryBatch, Nav, PlaybackPayload, QueueBehavior, QueueRequest,
+        QueueSource, RadioSeed, SidePanel, Track, TrackId, ZoomStep,
+    },
     ui::find::Find,
 };
 
@@ -19,6 +23,33 @@ pub const SHOW_ACCOUNT_SETUP: Selector = Selector::new("app.show-initial");
 pub const CLOSE_ALL_WINDOWS: Selector = Selector::new("app.close-all-windows");
 pub const SET_FOCUS: Selector = Selector::new("app.set-focus");
 pub const COPY: Selector<String> = Selector::new("app.copy-to-clipboard");
+pub const SHOW_OSD: Selector = Selector::new("app.show-osd");
+pub const TOGGLE_PIP: Selector = Selector::new("app.toggle-pip");
+pub const ZOOM: Selector<ZoomStep> = Selector::new("app.zoom");
+/// Zoom step for the monitor with the given key, sent by the zoom widget.
+pub const ZOOM_MONITOR: Selector<(String, ZoomStep)> = Selector::new("app.zoom-monitor");
+pub const SYSTEM_REDUCED_MOTION: Selector<bool> = Selector::new("app.system-reduced-motion");
+
+// Library
+
+pub const SAVE_ALL_TRACKS: Selector<QueueSource> = Selector::new("app.library.save-all-tracks");
+pub const UNSAVE_ALL_TRACKS: Selector<QueueSource> = Selector::new("app.library.unsave-all-tracks");
+pub const APPLY_LIBRARY_BATCH: Selector<LibraryBatch> =
+    Selector::new("app.library.apply-library-batch");
+
+// Blocklist
+
+pub const BLOCK_ARTIST: Selector<ArtistLink> = Selector::new("app.block-artist");
+pub const UNBLOCK_ARTIST: Selector<ArtistLink> = Selector::new("app.unblock-artist");
+pub const BLOCK_TRACK: Selector<Arc<Track>> = Selector::new("app.block-track");
+pub const UNBLOCK_TRACK: Selector<TrackId> = Selector::new("app.unblock-track");
+
+// Radio builder
+
+pub const ADD_RADIO_SEED: Selector<RadioSeed> = Selector::new("app.radio.add-seed");
+pub const REMOVE_RADIO_SEED: Selector<RadioSeed> = Selector::new("app.radio.remove-seed");
+// Play a radio of the seeds, which keeps getting more tracks while it plays.
+pub const START_RADIO: Selector<Arc<Vector<RadioSeed>>> = Selector::new("app.radio.start");
 
 // Find
 
@@ -30,12 +61,18 @@ pub const FIND_IN_SAVED_TRACKS: Selector<Find> = Selector::new("find-in-saved-tr
 
 pub const SESSION_CONNECT: Selector = Selector::new("app.session-connect");
 pub const LOG_OUT: Selector = Selector::new("app.log-out");
+// Log out and securely delete the data stored for the account.
+pub const LOG_OUT_AND_WIPE: Selector = Selector::new("app.log-out-and-wipe");
+// Stop the background work writing into the data of the account before it's
+// wiped.  Whatever is stopping keeps a clone of the sender until it's done.
+pub const STOP_FOR_WIPE: Selector<Sender<()>> = Selector::new("app.stop-for-wipe");
 
 // Navigation
 
 pub const NAVIGATE: Selector<Nav> = Selector::new("app.navigates");
 pub const NAVIGATE_BACK: Selector<usize> = Selector::new("app.navigate-back");
 pub const NAVIGATE_REFRESH: Selector = Selector::new("app.navigate-refresh");
+pub const TOGGLE_SIDE_PANEL: Selector<SidePanel> = Selector::new("app.toggle-side-panel");
 
 // Playback state
 
@@ -45,7 +82,9 @@ pub const PLAYBACK_PROGRESS: Selector<Duration> = Selector::new("app.playback-pr
 pub const PLAYBACK_PAUSING: Selector = Selector::new("app.playback-pausing");
 pub const PLAYBACK_RESUMING: Selector = Selector::new("app.playback-resuming");
 pub const PLAYBACK_BLOCKED: Selector = Selector::new("app.playback-blocked");
+pub const PLAYBACK_STALLED: Selector = Selector::new("app.playback-stalled");
 pub const PLAYBACK_STOPPED: Selector = Selector::new("app.playback-stopped");
+pub const PLAYBACK_UP_NEXT: Selector<ItemId> = Selector::new("app.playback-up-next");
 
 // Playback control
 
@@ -57,7 +96,35 @@ pub const PLAY_RESUME: Selector = Selector::new("app.play-resume");
 pub const PLAY_NEXT: Selector = Selector::new("app.play-next");
 pub const PLAY_STOP: Selector = Selector::new("app.play-stop");
 pub const PLAY_QUEUE_BEHAVIOR: Selector<QueueBehavior> = Selector::new("app.play-queue-behavior");
+// Remember the queue behavior for the context of the playing item, or forget it.
+pub const REMEMBER_CONTEXT_QUEUE_BEHAVIOR: Selector<Option<QueueBehavior>> =
+    Selector::new("app.remember-context-queue-behavior");
 pub const PLAY_SEEK: Selector<f64> = Selector::new("app.play-seek");
+pub const PLAY_SEEK_TO: Selector<Duration> = Selector::new("app.play-seek-to");
+pub const PLAY_PREVIOUS_CHAPTER: Selector = Selector::new("app.play-previous-chapter");
+pub const PLAY_NEXT_CHAPTER: Selector = Selector::new("app.play-next-chapter");
+pub const ADD_TO_QUEUE: Selector<QueueRequest> = Selector::new("app.add-to-queue");
+pub const EXPORT_QUEUE: Selector = Selector::new("app.export-queue");
+pub const IMPORT_QUEUE: Selector = Selector::new("app.import-queue");
+pub const SAVE_QUEUE_AS_PLAYLIST: Selector = Selector::new("app.save-queue-as-playlist");
+pub const SAVE_HISTORY_AS_PLAYLIST: Selector = Selector::new("app.save-history-as-playlist");
+pub const TOGGLE_PRIVATE_SESSION: Selector = Selector::new("app.toggle-private-session");
+pub const LISTEN_ALONG_HOST: Selector = Selector::new("app.listen-along-host");
+pub const LISTEN_ALONG_JOIN: Selector = Selector::new("app.listen-along-join");
+pub const LISTEN_ALONG_LEAVE: Selector = Selector::new("app.listen-along-leave");
+pub const RECONNECT_INTEGRATION: Selector<Integration> = Selector::new("app.reconnect-integration");
+
+// Downloads
+
+pub const DOWNLOAD: Selector<QueueSource> = Selector::new("app.download");
+pub const PAUSE_DOWNLOADS: Selector = Selector::new("app.download.pause");
+pub const RESUME_DOWNLOADS: Selector = Selector::new("app.download.resume");
+pub const CANCEL_DOWNLOAD: Selector<TrackId> = Selector::new("app.download.cancel");
+pub const RETRY_DOWNLOADS: Selector = Selector::new("app.download.retry");
+pub const CLEAR_FINISHED_DOWNLOADS: Selector = Selector::new("app.download.clear-finished");
+
+// Cache
+pub const COLLECT_CACHE_GARBAGE: Selector = Selector::new("app.cache.collect-garbage");
 
 // Sorting control
 pub const SORT_BY_DATE_ADDED: Selector = Selector::new("app.sort-by-date-added");
@@ -0,0 +1,117 @@
+use std::{
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use druid::{
+    widget::{prelude::*, Controller},
+    Selector, TimerToken,
+};
+
+use crate::{
+    data::{AppState, PlaylistBackups, PlaylistSnapshot},
+    error::Error,
+    webapi,
+};
+
+/// Give the session some time to connect before the first check.
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Periodically saves snapshots of the playlists in the library, if enabled in
+/// the config.
+pub struct PlaylistBackupController {
+    timer: TimerToken,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PlaylistBackupController {
+    const DONE: Selector<Result<usize, Error>> = Selector::new("app.playlist-backup.done");
+
+    pub fn new() -> Self {
+        Self {
+            timer: TimerToken::INVALID,
+            thread: None,
+        }
+    }
+
+    fn start_if_due(&mut self, ctx: &mut EventCtx, data: &AppState) {
+        if !data.config.playlist_backup || !data.config.has_credentials() || self.thread.is_some() {
+            return;
+        }
+        let backups = match PlaylistBackups::open() {
+            Some(backups) if backups.is_due() => backups,
+            _ => return,
+        };
+        let keep = data.config.playlist_backup_keep;
+        let widget_id = ctx.widget_id();
+        let event_sink = ctx.get_external_handle();
+        self.thread.replace(thread::spawn(move || {
+            let result = Self::backup(&backups, keep);
+            event_sink
+                .submit_command(Self::DONE, result, widget_id)
+                .unwrap();
+        }));
+    }
+
+    fn backup(backups: &PlaylistBackups, keep: usize) -> Result<usize, Error> {
+        let mut snapshot = PlaylistSnapshot::now();
+        for playlist in webapi::global().get_playlists()? {
+            let tracks = webapi::global().get_playlist_tracks(&playlist.id)?;
+            snapshot.add(&playlist, &tracks);
+        }
+        backups
+            .write(&snapshot, keep)
+            .map_err(|err| Error::WebApiError(err.to_string()))?;
+        Ok(snapshot.playlist_count())
+    }
+}
+
+impl<W> Controller<AppState, W> for PlaylistBackupController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Timer(token) if *token == self.timer => {
+                self.start_if_due(ctx, data);
+                self.timer = ctx.request_timer(CHECK_INTERVAL);
+            }
+            Event::Command(cmd) if cmd.is(Self::DONE) => {
+                self.thread.take();
+                match cmd.get_unchecked(Self::DONE) {
+                    Ok(count) => log::info!("backed up {} playlists", count),
+                    Err(err) => {
+                        log::error!("failed to back up playlists: {}", err);
+                        data.error_alert(format!("Failed to back up playlists: {}", err));
+                    }
+                }
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.timer = ctx.request_timer(FIRST_CHECK_DELAY);
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use druid::{
+    widget::{prelude::*, Controller},
+    Selector, TimerToken,
+};
+use psst_core::cache::{Cache, GcPhase, GcProgress, GcStats};
+
+use crate::{
+    cmd,
+    data::{AppState, Config, Preferences},
+    ui::utils,
+    webapi,
+};
+
+/// Let the startup loading settle before the first collection.
+const FIRST_RUN_DELAY: Duration = Duration::from_secs(5 * 60);
+const RUN_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Progress reports are sent to the UI at most this often.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Periodically runs the cache garbage collection on a background thread, and on
+/// request from the cache preferences.
+pub struct CacheGcController {
+    timer: TimerToken,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CacheGcController {
+    const PROGRESS: Selector<GcProgress> = Selector::new("app.cache-gc.progress");
+    const DONE: Selector<(Result<GcStats, String>, Option<u64>)> =
+        Selector::new("app.cache-gc.done");
+
+    pub fn new() -> Self {
+        Self {
+            timer: TimerToken::INVALID,
+            thread: None,
+        }
+    }
+
+    fn start(&mut self, ctx: &mut EventCtx, data: &mut AppState) {
+        if self.thread.is_some() {
+            return;
+        }
+        let cache_dir = match Config::cache_dir() {
+            Some(dir) => dir,
+            None => return,
+        };
+        let max_size = match data.config.cache_limit_gb {
+            0 => None,
+            limit => Some(limit as u64 * 1_000_000_000),
+        };
+        data.preferences.cache_cleanup.running = true;
+        data.preferences.cache_cleanup.status = "Starting cleanup…".to_string();
+        let widget_id = ctx.widget_id();
+        let event_sink = ctx.get_external_handle();
+        self.thread.replace(thread::spawn(move || {
+            let mut reported_at = Instant::now();
+            let result = Cache::new(cache_dir)
+                .and_then(|cache| {
+                    cache.collect_garbage(max_size, |progress| {
+                        let is_last = progress.done == progress.total;
+                        if is_last || reported_at.elapsed() >= PROGRESS_INTERVAL {
+                            reported_at = Instant::now();
+                            let _ = event_sink.submit_command(Self::PROGRESS, progress, widget_id);
+                        }
+                    })
+                })
+                .map(|mut stats| {
+                    stats.removed_files += webapi::global().collect_cache_garbage();
+                    stats
+                })
+                .map_err(|err| err.to_string());
+            let size = Preferences::measure_cache_usage();
+            event_sink
+                .submit_command(Self::DONE, (result, size), widget_id)
+                .unwrap();
+        }));
+    }
+}
+
+fn progress_text(progress: &GcProgress) -> String {
+    let phase = match progress.phase {
+        GcPhase::Orphans => "Removing leftover files",
+        GcPhase::SizeLimit => "Removing the least recently played audio",
+        GcPhase::Metadata => "Checking metadata",
+    };
+    format!("{}… {} of {}", phase, progress.done, progress.total)
+}
+
+impl<W> Controller<AppState, W> for CacheGcController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Timer(token) if *token == self.timer => {
+                self.start(ctx, data);
+                self.timer = ctx.request_timer(RUN_INTERVAL);
+            }
+            Event::Command(cmd) if cmd.is(cmd::COLLECT_CACHE_GARBAGE) => {
+                self.start(ctx, data);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::STOP_FOR_WIPE) => {
+                // No more runs, and the running one gets to finish first.
+                self.timer = TimerToken::INVALID;
+                if let Some(thread) = self.thread.take() {
+                    let stopped = cmd.get_unchecked(cmd::STOP_FOR_WIPE).clone();
+                    thread::spawn(move || {
+                        let _ = thread.join();
+                        drop(stopped);
+                    });
+                }
+                child.event(ctx, event, data, env);
+            }
+            Event::Command(cmd) if cmd.is(Self::PROGRESS) => {
+                if data.preferences.cache_cleanup.running {
+                    data.preferences.cache_cleanup.status =
+                        progress_text(cmd.get_unchecked(Self::PROGRESS));
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(Self::DONE) => {
+                self.thread.take();
+                let (result, size) = cmd.get_unchecked(Self::DONE);
+                data.preferences.cache_cleanup.running = false;
+                data.preferences.cache_cleanup.status = match result {
+                    Ok(stats) => format!(
+                        "Last cleanup removed {} files, freeing {}.",
+                        stats.removed_files,
+                        utils::as_human_size(stats.freed_bytes)
+                    ),
+                    Err(err) => {
+                        log::error!("failed to clean up the cache: {}", err);
+                        format!("Cleanup failed: {}", err)
+                    }
+                };
+                if let Some(size) = size {
+                    data.preferences.cache_size.resolve((), *size);
+                }
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.timer = ctx.request_timer(FIRST_RUN_DELAY);
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
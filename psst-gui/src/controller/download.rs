@@ -0,0 +1,305 @@
+use std::{sync::Arc, thread};
+
+use druid::{
+    im::Vector,
+    widget::{prelude::*, Controller},
+    ExtEventSink, Selector,
+};
+use psst_core::{
+    audio::normalize::NormalizationLevel,
+    cache::{Cache, CacheHandle},
+    cdn::Cdn,
+    download::{DownloadManager, DownloadState},
+    player::item::PlaybackItem,
+};
+
+use crate::{
+    cmd,
+    data::{estimate_download_size, AppState, Config, QueueSource, StorageInfo, Track},
+    error::Error,
+    ui::utils,
+    webapi,
+};
+
+const DOWNLOADS_UPDATED: Selector<Vec<DownloadState>> = Selector::new("app.download.updated");
+const DOWNLOAD_TRACKS_LOADED: Selector<Result<(Vector<Arc<Track>>, Option<StorageInfo>), Error>> =
+    Selector::new("app.download.tracks-loaded");
+const STORAGE_MEASURED: Selector<Option<StorageInfo>> =
+    Selector::new("app.download.storage-measured");
+
+/// Owns the offline download manager, feeds it the tracks to download, and keeps
+/// the download queue in the app state in sync with it.
+pub struct DownloadController {
+    manager: Option<DownloadManager>,
+    cache: Option<CacheHandle>,
+}
+
+impl DownloadController {
+    pub fn new() -> Self {
+        Self {
+            manager: None,
+            cache: None,
+        }
+    }
+
+    fn start_manager(&mut self, data: &AppState, event_sink: ExtEventSink, widget_id: WidgetId) {
+        let cache_dir = Config::cache_dir().unwrap();
+        let proxy_url = Config::proxy();
+        let cache = Cache::new(cache_dir).unwrap();
+        let (manager, state_receiver) = DownloadManager::new(
+            data.session.clone(),
+            Cdn::new(data.session.clone(), proxy_url.as_deref()).unwrap(),
+            cache.clone(),
+            data.config.playback(),
+        );
+        manager.set_concurrency(data.config.download_concurrency);
+        thread::spawn(move || {
+            for states in state_receiver {
+                if event_sink
+                    .submit_command(DOWNLOADS_UPDATED, states, widget_id)
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+        self.manager.replace(manager);
+        self.cache.replace(cache);
+    }
+
+    fn load_tracks(
+        source: QueueSource,
+        cache: CacheHandle,
+        event_sink: ExtEventSink,
+        widget_id: WidgetId,
+    ) {
+        thread::spawn(move || {
+            let tracks = match &source {
+                QueueSource::Album(link) => webapi::global()
+                    .get_album(&link.id)
+                    .map(|album| album.data.tracks.clone()),
+                QueueSource::Playlist(link) => webapi::global().get_playlist_tracks(&link.id),
+                QueueSource::ArtistTopTracks(link) => {
+                    webapi::global().get_artist_top_tracks(&link.id)
+                }
+                QueueSource::Track(track, _) => Ok(Vector::unit(track.clone())),
+            };
+            // The space is checked against the latest numbers, other downloads might be
+            // running.
+            let result = tracks.map(|tracks| (tracks, StorageInfo::measure(&cache)));
+            event_sink
+                .submit_command(DOWNLOAD_TRACKS_LOADED, result, widget_id)
+                .unwrap();
+        });
+    }
+
+    fn measure_storage(cache: CacheHandle, event_sink: ExtEventSink, widget_id: WidgetId) {
+        thread::spawn(move || {
+            event_sink
+                .submit_command(STORAGE_MEASURED, StorageInfo::measure(&cache), widget_id)
+                .unwrap();
+        });
+    }
+
+    /// Refuse downloads that wouldn't fit on the disk.  Going over the configured
+    /// limit is up to the user, they just get a warning about it.
+    fn check_space(
+        data: &AppState,
+        tracks: &[Arc<Track>],
+    ) -> Result<(u64, Option<String>), String> {
+        let bitrate = data.config.playback().bitrate;
+        let estimate = estimate_download_size(tracks, bitrate);
+        let needed = estimate + data.downloads.pending_size(bitrate);
+        let storage = match data.downloads.storage {
+            Some(storage) => storage,
+            None => return Ok((estimate, None)),
+        };
+        if needed > storage.available {
+            return Err(format!(
+                "Not enough disk space to download {}, about {} is needed but only {} is free.",
+                tracks_text(tracks.len()),
+                utils::as_human_size(needed),
+                utils::as_human_size(storage.available)
+            ));
+        }
+        let quota = data.config.download_quota_gb as u64 * 1_000_000_000;
+        let warning = (quota > 0 && storage.used + needed > quota).then(|| {
+            format!(
+                "This goes over the download limit of {}, {} is downloaded already.",
+                utils::as_human_size(quota),
+                utils::as_human_size(storage.used)
+            )
+        });
+        Ok((estimate, warning))
+    }
+
+    fn enqueue(&self, data: &mut AppState, tracks: &Vector<Arc<Track>>) {
+        let tracks: Vec<_> = tracks
+            .iter()
+            .filter(|track| !track.is_local && !data.downloads.contains(track.id))
+            .cloned()
+            .collect();
+        if tracks.is_empty() {
+            data.info_alert("Already in downloads.");
+            return;
+        }
+        let (estimate, warning) = match Self::check_space(data, &tracks) {
+            Ok(checked) => checked,
+            Err(message) => {
+                data.error_alert(message);
+                return;
+            }
+        };
+        if let Some(manager) = &self.manager {
+            manager.enqueue(tracks.iter().map(|track| PlaybackItem {
+                item_id: track.id.0,
+                norm_level: NormalizationLevel::Track,
+            }));
+        }
+        let added = format!(
+            "Added {} to downloads, about {}.",
+            tracks_text(tracks.len()),
+            utils::as_human_size(estimate)
+        );
+        match warning {
+            Some(warning) => data.error_alert(format!("{} {}", added, warning)),
+            None => data.info_alert(added),
+        }
+        data.downloads.add(tracks);
+    }
+}
+
+fn tracks_text(count: usize) -> String {
+    if count == 1 {
+        "1 track".to_string()
+    } else {
+        format!("{} tracks", count)
+    }
+}
+
+impl<W> Controller<AppState, W> for DownloadController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        let manager = match &self.manager {
+            Some(manager) => manager,
+            None => {
+                child.event(ctx, event, data, env);
+                return;
+            }
+        };
+        match event {
+            Event::Command(cmd) if cmd.is(cmd::DOWNLOAD) => {
+                let source = cmd.get_unchecked(cmd::DOWNLOAD).clone();
+                let cache = self.cache.clone().unwrap();
+                Self::load_tracks(source, cache, ctx.get_external_handle(), ctx.widget_id());
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(DOWNLOAD_TRACKS_LOADED) => {
+                match cmd.get_unchecked(DOWNLOAD_TRACKS_LOADED) {
+                    Ok((tracks, storage)) => {
+                        if storage.is_some() {
+                            data.downloads.storage = *storage;
+                        }
+                        self.enqueue(data, tracks);
+                    }
+                    Err(err) => {
+                        data.error_alert(format!("Failed to load tracks to download: {}", err));
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(DOWNLOADS_UPDATED) => {
+                let finished_count = data.downloads.finished_count();
+                data.downloads.update(cmd.get_unchecked(DOWNLOADS_UPDATED));
+                if data.downloads.finished_count() > finished_count {
+                    let cache = self.cache.clone().unwrap();
+                    Self::measure_storage(cache, ctx.get_external_handle(), ctx.widget_id());
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(STORAGE_MEASURED) => {
+                data.downloads.storage = *cmd.get_unchecked(STORAGE_MEASURED);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::PAUSE_DOWNLOADS) => {
+                manager.pause();
+                data.downloads.paused = true;
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::RESUME_DOWNLOADS) => {
+                manager.resume();
+                data.downloads.paused = false;
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::CANCEL_DOWNLOAD) => {
+                let id = cmd.get_unchecked(cmd::CANCEL_DOWNLOAD);
+                manager.cancel(id.0);
+                data.downloads.remove(*id);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::RETRY_DOWNLOADS) => {
+                manager.retry_failed();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::STOP_FOR_WIPE) => {
+                let stopped = cmd.get_unchecked(cmd::STOP_FOR_WIPE).clone();
+                let manager = manager.clone();
+                thread::spawn(move || {
+                    manager.stop();
+                    drop(stopped);
+                });
+                data.downloads.paused = true;
+                child.event(ctx, event, data, env);
+            }
+            Event::Command(cmd) if cmd.is(cmd::CLEAR_FINISHED_DOWNLOADS) => {
+                manager.clear_finished();
+                data.downloads.clear_finished();
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.start_manager(data, ctx.get_external_handle(), ctx.widget_id());
+            let cache = self.cache.clone().unwrap();
+            Self::measure_storage(cache, ctx.get_external_handle(), ctx.widget_id());
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if old_data.config.download_concurrency != data.config.download_concurrency {
+            if let Some(manager) = &self.manager {
+                manager.set_concurrency(data.config.download_concurrency);
+            }
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
@@ -6,6 +6,7 @@ use druid::{
 pub struct ExClick<T> {
     button: Option<MouseButton>,
     action: Box<dyn Fn(&mut EventCtx, &MouseEvent, &mut T, &Env)>,
+    click_count: u8,
 }
 
 impl<T: Data> ExClick<T> {
@@ -16,6 +17,7 @@ impl<T: Data> ExClick<T> {
         ExClick {
             button,
             action: Box::new(action),
+            click_count: 0,
         }
     }
 }
@@ -27,6 +29,7 @@ impl<T: Data, W: Widget<T>> Controller<T, W> for ExClick<T> {
                 if mouse_event.button == self.button.unwrap_or(mouse_event.button) {
                     ctx.set_active(true);
                     ctx.request_paint();
+                    self.click_count = mouse_event.count;
                 }
             }
             Event::MouseUp(mouse_event) => {
@@ -35,7 +38,11 @@ impl<T: Data, W: Widget<T>> Controller<T, W> for ExClick<T> {
                 {
                     ctx.set_active(false);
                     if ctx.is_hot() {
-                        (self.action)(ctx, mouse_event, data, env);
+                        // Not every platform counts the clicks on mouse-up, report the
+                        // count from the matching mouse-down.
+                        let mut mouse_event = mouse_event.clone();
+                        mouse_event.count = self.click_count;
+                        (self.action)(ctx, &mouse_event, data, env);
                     }
                     ctx.request_paint();
                 }
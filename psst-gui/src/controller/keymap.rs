@@ -0,0 +1,68 @@
+use druid::widget::{prelude::*, Controller};
+
+use crate::{
+    cmd,
+    data::{AppState, KeyAction, Nav, SidePanel},
+};
+
+/// Runs the actions bound in the configured keymap.  Keys are first offered to
+/// the focused widget, so typing into inputs isn't affected.
+pub struct KeymapController;
+
+impl KeymapController {
+    fn run(ctx: &mut EventCtx, data: &AppState, action: KeyAction) {
+        match action {
+            KeyAction::FocusSearch => {
+                ctx.submit_command(cmd::SET_FOCUS.to(cmd::WIDGET_SEARCH_INPUT));
+            }
+            KeyAction::Find => ctx.submit_command(cmd::TOGGLE_FINDER),
+            KeyAction::Home => ctx.submit_command(cmd::NAVIGATE.with(Nav::Home)),
+            KeyAction::SavedTracks => ctx.submit_command(cmd::NAVIGATE.with(Nav::SavedTracks)),
+            KeyAction::Playlists => {
+                let first = data
+                    .library
+                    .playlists
+                    .resolved()
+                    .and_then(|playlists| playlists.front());
+                if let Some(playlist) = first {
+                    ctx.submit_command(cmd::NAVIGATE.with(Nav::PlaylistDetail(playlist.link())));
+                }
+            }
+            KeyAction::Queue => ctx.submit_command(cmd::TOGGLE_SIDE_PANEL.with(SidePanel::Queue)),
+            KeyAction::SavedAlbums => ctx.submit_command(cmd::NAVIGATE.with(Nav::SavedAlbums)),
+            KeyAction::SavedShows => ctx.submit_command(cmd::NAVIGATE.with(Nav::SavedShows)),
+            KeyAction::Downloads => ctx.submit_command(cmd::NAVIGATE.with(Nav::Downloads)),
+            KeyAction::NavigateBack => {
+                if !data.history.is_empty() {
+                    ctx.submit_command(cmd::NAVIGATE_BACK.with(1));
+                }
+            }
+            KeyAction::Zoom(step) => ctx.submit_command(cmd::ZOOM.with(step)),
+        }
+    }
+}
+
+impl<W> Controller<AppState, W> for KeymapController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        child.event(ctx, event, data, env);
+        if let Event::KeyDown(key) = event {
+            if ctx.is_handled() {
+                return;
+            }
+            if let Some(action) = data.config.keymap.action(key) {
+                Self::run(ctx, data, action);
+                ctx.set_handled();
+            }
+        }
+    }
+}
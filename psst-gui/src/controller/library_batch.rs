@@ -0,0 +1,344 @@
+use std::{sync::Arc, thread};
+
+use druid::{
+    im::Vector,
+    widget::{prelude::*, Controller},
+    ExtEventSink, Selector,
+};
+
+use crate::{
+    cmd,
+    data::{AppState, LibraryBatch, LoadProgress, QueueSource, Track},
+    error::Error,
+    webapi::{self, WebApi},
+};
+
+const TRACKS_LOADED: Selector<(bool, Result<Vector<Arc<Track>>, Error>)> =
+    Selector::new("app.library-batch.tracks-loaded");
+const CHUNK_DONE: Selector<usize> = Selector::new("app.library-batch.chunk-done");
+const BATCH_DONE: Selector<(LibraryBatch, usize, Result<(), Error>)> =
+    Selector::new("app.library-batch.done");
+
+/// Saves or removes all tracks of an album or a playlist, using as few requests
+/// as the Web API allows, and offers to undo it afterwards.
+pub struct LibraryBatchController;
+
+impl LibraryBatchController {
+    fn load_tracks(source: QueueSource, save: bool, event_sink: ExtEventSink, widget_id: WidgetId) {
+        thread::spawn(move || {
+            let tracks = source_tracks(&*webapi::global(), &source);
+            event_sink
+                .submit_command(TRACKS_LOADED, (save, tracks), widget_id)
+                .unwrap();
+        });
+    }
+
+    /// Leave out the tracks the change doesn't apply to, if the saved tracks are
+    /// known.
+    fn batch(data: &AppState, tracks: &Vector<Arc<Track>>, save: bool) -> LibraryBatch {
+        let is_known = data.library.saved_tracks.is_resolved();
+        let tracks = tracks
+            .iter()
+            .filter_map(Track::to_catalog_track)
+            .filter(|track| !is_known || data.library.contains_track(track) != save)
+            .collect();
+        LibraryBatch { tracks, save }
+    }
+
+    fn apply(batch: LibraryBatch, data: &mut AppState, ctx: &mut EventCtx) {
+        data.library_batch = Some(LoadProgress {
+            loaded: 0,
+            total: batch.tracks.len(),
+        });
+        let event_sink = ctx.get_external_handle();
+        let widget_id = ctx.widget_id();
+        thread::spawn(move || {
+            let total = batch.tracks.len();
+            let (done, result) = run(&*webapi::global(), &batch, &mut |count| {
+                let _ = event_sink.submit_command(CHUNK_DONE, count, widget_id);
+            });
+            event_sink
+                .submit_command(BATCH_DONE, (done, total, result), widget_id)
+                .unwrap();
+        });
+    }
+
+    /// Apply `batch`, the done part of a batch of `total` tracks, to the local
+    /// library even if the rest failed, so it matches the server and can be undone.
+    fn finish(batch: &LibraryBatch, total: usize, result: &Result<(), Error>, data: &mut AppState) {
+        data.library_batch = None;
+        if let Err(err) = result {
+            data.error_alert(err);
+            if batch.tracks.is_empty() {
+                return;
+            }
+        }
+        data.with_library_mut(|library| {
+            // Saved tracks are listed newest first, keep the order of the container.
+            for track in batch.tracks.iter().rev() {
+                if batch.save {
+                    library.add_track(track.clone());
+                } else {
+                    library.remove_track(&track.id);
+                }
+            }
+        });
+        let count = if batch.tracks.len() < total {
+            format!("{} of {}", batch.tracks.len(), tracks_text(total))
+        } else {
+            tracks_text(total)
+        };
+        let message = if batch.save {
+            format!("{} added to library.", count)
+        } else {
+            format!("{} removed from library.", count)
+        };
+        data.info_alert_with_action(message, "Undo", cmd::APPLY_LIBRARY_BATCH.with(batch.undo()));
+    }
+}
+
+fn source_tracks(api: &dyn WebApi, source: &QueueSource) -> Result<Vector<Arc<Track>>, Error> {
+    match source {
+        QueueSource::Album(link) => api
+            .get_album(&link.id)
+            .map(|album| album.data.tracks.clone()),
+        QueueSource::Playlist(link) => api.get_playlist_tracks(&link.id),
+        QueueSource::ArtistTopTracks(link) => api.get_artist_top_tracks(&link.id),
+        QueueSource::Track(track, _) => Ok(Vector::unit(track.clone())),
+    }
+}
+
+/// Send the batch in chunks, returning the part of it the server applied, which
+/// is all of it unless a request failed.
+fn run(
+    api: &dyn WebApi,
+    batch: &LibraryBatch,
+    on_chunk: &mut dyn FnMut(usize),
+) -> (LibraryBatch, Result<(), Error>) {
+    let ids: Vec<_> = batch.tracks.iter().map(|track| track.id).collect();
+    let mut done = 0;
+    let mut on_chunk = |count| {
+        done += count;
+        on_chunk(count);
+    };
+    let result = if batch.save {
+        api.save_tracks(&ids, &mut on_chunk)
+    } else {
+        api.unsave_tracks(&ids, &mut on_chunk)
+    };
+    let done = LibraryBatch {
+        tracks: batch.tracks.take(done),
+        save: batch.save,
+    };
+    (done, result)
+}
+
+fn tracks_text(count: usize) -> String {
+    if count == 1 {
+        "1 track".to_string()
+    } else {
+        format!("{} tracks", count)
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for LibraryBatchController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd)
+                if (cmd.is(cmd::SAVE_ALL_TRACKS)
+                    || cmd.is(cmd::UNSAVE_ALL_TRACKS)
+                    || cmd.is(cmd::APPLY_LIBRARY_BATCH))
+                    && data.library_batch.is_some() =>
+            {
+                data.info_alert("Wait until the library change in progress is done.");
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::SAVE_ALL_TRACKS) => {
+                let source = cmd.get_unchecked(cmd::SAVE_ALL_TRACKS).clone();
+                Self::load_tracks(source, true, ctx.get_external_handle(), ctx.widget_id());
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::UNSAVE_ALL_TRACKS) => {
+                let source = cmd.get_unchecked(cmd::UNSAVE_ALL_TRACKS).clone();
+                Self::load_tracks(source, false, ctx.get_external_handle(), ctx.widget_id());
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(TRACKS_LOADED) => {
+                let (save, result) = cmd.get_unchecked(TRACKS_LOADED);
+                match result {
+                    Ok(tracks) => {
+                        let batch = Self::batch(data, tracks, *save);
+                        if batch.tracks.is_empty() {
+                            data.info_alert(if *save {
+                                "All tracks are in the library already."
+                            } else {
+                                "None of the tracks are in the library."
+                            });
+                        } else {
+                            Self::apply(batch, data, ctx);
+                        }
+                    }
+                    Err(err) => {
+                        data.error_alert(err);
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::APPLY_LIBRARY_BATCH) => {
+                let batch = cmd.get_unchecked(cmd::APPLY_LIBRARY_BATCH).clone();
+                Self::apply(batch, data, ctx);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(CHUNK_DONE) => {
+                if let Some(progress) = &mut data.library_batch {
+                    progress.loaded += cmd.get_unchecked(CHUNK_DONE);
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(BATCH_DONE) => {
+                let (done, total, result) = cmd.get_unchecked(BATCH_DONE);
+                Self::finish(done, *total, result, data);
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        data::{Config, Playlist, PlaylistLink, SavedTracks, TrackId},
+        webapi::MockWebApi,
+    };
+
+    fn track(number: usize) -> Arc<Track> {
+        let track = json!({
+            "id": format!("{:0>22}", number),
+            "name": format!("Track {}", number),
+            "album": null,
+            "artists": [],
+            "duration_ms": 180_000,
+            "disc_number": 1,
+            "track_number": number,
+            "explicit": false,
+            "is_local": false,
+            "is_playable": true,
+            "popularity": null,
+        });
+        Arc::new(serde_json::from_value(track).unwrap())
+    }
+
+    fn playlist(tracks: &Vector<Arc<Track>>) -> Playlist {
+        let playlist = json!({
+            "id": "playlist",
+            "name": "Playlist",
+            "images": [],
+            "description": "",
+            "tracks": { "total": tracks.len() },
+            "owner": { "id": "user", "display_name": "User" },
+            "collaborative": false,
+            "snapshot_id": null,
+        });
+        serde_json::from_value(playlist).unwrap()
+    }
+
+    fn playlist_batch(api: &MockWebApi, save: bool) -> LibraryBatch {
+        let source = QueueSource::Playlist(PlaylistLink {
+            id: "playlist".into(),
+            name: "Playlist".into(),
+        });
+        let tracks = source_tracks(api, &source).unwrap();
+        LibraryBatch { tracks, save }
+    }
+
+    fn ids(tracks: &Vector<Arc<Track>>) -> Vec<TrackId> {
+        tracks.iter().map(|track| track.id).collect()
+    }
+
+    fn mock_with_tracks(count: usize) -> MockWebApi {
+        let tracks: Vector<_> = (1..=count).map(track).collect();
+        MockWebApi::default().with_playlist(playlist(&tracks), tracks)
+    }
+
+    #[test]
+    fn saves_all_tracks_of_a_playlist_in_chunks() {
+        let api = mock_with_tracks(120);
+        let batch = playlist_batch(&api, true);
+        let mut chunks = Vec::new();
+        let (done, result) = run(&api, &batch, &mut |count| chunks.push(count));
+
+        assert!(result.is_ok());
+        assert_eq!(chunks, [50, 50, 20]);
+        assert_eq!(ids(&done.tracks), ids(&batch.tracks));
+        let saved = api.saved_track_ids();
+        assert_eq!(saved.len(), 120);
+        assert!(batch.tracks.iter().all(|track| saved.contains(&track.id)));
+    }
+
+    #[test]
+    fn undo_removes_the_saved_tracks_again() {
+        let api = mock_with_tracks(3);
+        let batch = playlist_batch(&api, true);
+        run(&api, &batch, &mut |_| {}).1.unwrap();
+        run(&api, &batch.undo(), &mut |_| {}).1.unwrap();
+
+        assert!(api.saved_track_ids().is_empty());
+    }
+
+    #[test]
+    fn failing_request_stops_the_batch() {
+        let api = mock_with_tracks(120).fail_after_requests(1);
+        let batch = playlist_batch(&api, true);
+        let mut chunks = Vec::new();
+        let (done, result) = run(&api, &batch, &mut |count| chunks.push(count));
+
+        assert!(result.is_err());
+        assert_eq!(chunks, [50]);
+        assert_eq!(api.saved_track_ids().len(), 50);
+        assert_eq!(ids(&done.tracks), ids(&batch.tracks.take(50)));
+    }
+
+    #[test]
+    fn finishing_a_failed_batch_applies_the_done_part() {
+        let api = mock_with_tracks(120).fail_after_requests(1);
+        let batch = playlist_batch(&api, true);
+        let (done, result) = run(&api, &batch, &mut |_| {});
+        let mut data = AppState::default_with_config(Config::default());
+        data.with_library_mut(|library| {
+            library
+                .saved_tracks
+                .resolve((), SavedTracks::new(Vector::new()));
+        });
+        LibraryBatchController::finish(&done, batch.tracks.len(), &result, &mut data);
+
+        assert!(batch
+            .tracks
+            .iter()
+            .take(50)
+            .all(|t| data.library.contains_track(t)));
+        assert!(!data.library.contains_track(&batch.tracks[50]));
+        assert_eq!(data.alerts.len(), 2);
+        assert_eq!(
+            &*data.alerts[1].message,
+            "50 of 120 tracks added to library."
+        );
+
+        let undo = data.alerts[1].action.as_ref().unwrap().command.clone();
+        let undo = undo.get_unchecked(cmd::APPLY_LIBRARY_BATCH);
+        assert_eq!(ids(&undo.tracks), ids(&done.tracks));
+        assert!(!undo.save);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::unbounded;
+use druid::{
+    widget::{prelude::*, Controller},
+    ExtEventSink, Selector, TimerToken,
+};
+use psst_core::session::SessionService;
+
+use crate::{
+    cmd,
+    data::{AppState, SavedAlbums, SavedTracks},
+    error::Error,
+    ui::playlist,
+    webapi,
+};
+
+/// Changes usually come in bursts, wait for them to settle before reloading.
+const SETTLE_DELAY: Duration = Duration::from_secs(2);
+/// Delay before subscribing again after the session got lost.
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Parts of the library the changes of are published to the session.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Topic {
+    Rootlist,
+    /// Saved tracks and albums.
+    Collection,
+}
+
+impl Topic {
+    const ALL: [Self; 2] = [Self::Rootlist, Self::Collection];
+
+    fn uri(self, username: &str) -> String {
+        match self {
+            Self::Rootlist => format!("hm://playlist/user/{}/rootlist", username),
+            Self::Collection => format!("hm://collection/collection/{}/json", username),
+        }
+    }
+}
+
+/// Keeps subscriptions to the changes of the library open over the session, so
+/// changes made on other devices show up without reloading.
+pub struct LiveUpdatesController {
+    stop: Option<Arc<AtomicBool>>,
+    changed: Vec<Topic>,
+    timer: TimerToken,
+}
+
+impl LiveUpdatesController {
+    const CHANGED: Selector<Topic> = Selector::new("app.live-updates.changed");
+    const SAVED_TRACKS_LOADED: Selector<Result<SavedTracks, Error>> =
+        Selector::new("app.live-updates.saved-tracks-loaded");
+    const SAVED_ALBUMS_LOADED: Selector<Result<SavedAlbums, Error>> =
+        Selector::new("app.live-updates.saved-albums-loaded");
+
+    pub fn new() -> Self {
+        Self {
+            stop: None,
+            changed: Vec::new(),
+            timer: TimerToken::INVALID,
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn start(&mut self, ctx: &mut EventCtx, data: &AppState) {
+        self.stop();
+        let username = match data.config.username() {
+            Some(username) => username.to_string(),
+            None => return,
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = Some(stop.clone());
+        let session = data.session.clone();
+        let event_sink = ctx.get_external_handle();
+        let widget_id = ctx.widget_id();
+        thread::spawn(move || Self::subscribe(session, username, stop, event_sink, widget_id));
+    }
+
+    fn subscribe(
+        session: SessionService,
+        username: String,
+        stop: Arc<AtomicBool>,
+        event_sink: ExtEventSink,
+        widget_id: WidgetId,
+    ) {
+        while !stop.load(Ordering::SeqCst) {
+            let (sender, events) = unbounded();
+            let result = session.connected().and_then(|handle| {
+                let result = Topic::ALL.iter().try_for_each(|topic| {
+                    handle.subscribe_mercury(topic.uri(&username), sender.clone())
+                });
+                if result.is_err() {
+                    // Don't leave the topics subscribed before the failure behind, the
+                    // next attempt subscribes them again.
+                    handle.unsubscribe_mercury(sender.clone());
+                }
+                result
+            });
+            drop(sender);
+            match result {
+                Ok(()) => {
+                    // Runs until the session closes.
+                    for event in events {
+                        if stop.load(Ordering::SeqCst) {
+                            return;
+                        }
+                        let topic = Topic::ALL
+                            .into_iter()
+                            .find(|topic| event.uri.starts_with(&topic.uri(&username)));
+                        if let Some(topic) = topic {
+                            let _ = event_sink.submit_command(Self::CHANGED, topic, widget_id);
+                        }
+                    }
+                }
+                Err(err) => {
+                    log::warn!("failed to subscribe to library changes: {}", err);
+                }
+            }
+            thread::sleep(RETRY_DELAY);
+        }
+    }
+
+    fn reload(ctx: &mut EventCtx, data: &AppState, topic: Topic) {
+        match topic {
+            Topic::Rootlist => ctx.submit_command(playlist::REFRESH_LIST),
+            Topic::Collection => {
+                // Only what has been loaded already, the rest loads fresh when it's
+                // needed.
+                let reload_tracks = data.library.saved_tracks.is_resolved();
+                let reload_albums = data.library.saved_albums.is_resolved();
+                let event_sink = ctx.get_external_handle();
+                let widget_id = ctx.widget_id();
+                thread::spawn(move || {
+                    if reload_tracks {
+                        let result = webapi::global().get_saved_tracks().map(SavedTracks::new);
+                        let _ =
+                            event_sink.submit_command(Self::SAVED_TRACKS_LOADED, result, widget_id);
+                    }
+                    if reload_albums {
+                        let result = webapi::global().get_saved_albums().map(SavedAlbums::new);
+                        let _ =
+                            event_sink.submit_command(Self::SAVED_ALBUMS_LOADED, result, widget_id);
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl<W> Controller<AppState, W> for LiveUpdatesController
+where
+    W: Widget<AppState>,
+{
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(cmd::SESSION_CONNECT) => {
+                // Let the session pick up the new configuration first.
+                child.event(ctx, event, data, env);
+                if data.config.has_credentials() {
+                    self.start(ctx, data);
+                }
+            }
+            Event::Command(cmd) if cmd.is(cmd::LOG_OUT) || cmd.is(cmd::LOG_OUT_AND_WIPE) => {
+                self.stop();
+                child.event(ctx, event, data, env);
+            }
+            Event::Command(cmd) if cmd.is(Self::CHANGED) => {
+                let topic = *cmd.get_unchecked(Self::CHANGED);
+                if !self.changed.contains(&topic) {
+                    self.changed.push(topic);
+                }
+                if self.timer == TimerToken::INVALID {
+                    self.timer = ctx.request_timer(SETTLE_DELAY);
+                }
+                ctx.set_handled();
+            }
+            Event::Timer(token) if token == &self.timer => {
+                self.timer = TimerToken::INVALID;
+                for topic in self.changed.drain(..) {
+                    Self::reload(ctx, data, topic);
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(Self::SAVED_TRACKS_LOADED) => {
+                match cmd.get_unchecked(Self::SAVED_TRACKS_LOADED) {
+                    Ok(tracks) => data.with_library_mut(|library| {
+                        library.saved_tracks.resolve((), tracks.clone());
+                    }),
+                    Err(err) => log::warn!("failed to reload saved tracks: {}", err),
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(Self::SAVED_ALBUMS_LOADED) => {
+                match cmd.get_unchecked(Self::SAVED_ALBUMS_LOADED) {
+                    Ok(albums) => data.with_library_mut(|library| {
+                        library.saved_albums.resolve((), albums.clone());
+                    }),
+                    Err(err) => log::warn!("failed to reload saved albums: {}", err),
+                }
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+}
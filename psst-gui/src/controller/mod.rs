@@ -1,27 +1,45 @@
 mod after_delay;
+mod backup;
+mod cache_gc;
+mod download;
 mod ex_click;
 mod ex_cursor;
 mod ex_scroll;
 mod input;
+mod keymap;
+mod library_batch;
+mod live_updates;
 mod nav;
 mod on_command;
 mod on_command_async;
 mod on_debounce;
 mod on_update;
 mod playback;
+mod prefetch;
+mod scroll_memory;
 mod session;
 mod sort;
+mod tooltip;
 
 pub use after_delay::AfterDelay;
+pub use backup::PlaylistBackupController;
+pub use cache_gc::CacheGcController;
+pub use download::DownloadController;
 pub use ex_click::ExClick;
 pub use ex_cursor::ExCursor;
 pub use ex_scroll::ExScroll;
 pub use input::InputController;
+pub use keymap::KeymapController;
+pub use library_batch::LibraryBatchController;
+pub use live_updates::LiveUpdatesController;
 pub use nav::NavController;
 pub use on_command::OnCommand;
 pub use on_command_async::OnCommandAsync;
 pub use on_debounce::OnDebounce;
 pub use on_update::OnUpdate;
 pub use playback::PlaybackController;
+pub use prefetch::{PrefetchController, PrefetchRow};
+pub use scroll_memory::{ScrollMemory, ScrollOffsets};
 pub use session::SessionController;
 pub use sort::SortController;
+pub use tooltip::Tooltip;
@@ -1,8 +1,11 @@
+use std::sync::Arc;
+
 use druid::widget::{prelude::*, Controller};
+use psst_core::uri::SpotifyUri;
 
 use crate::{
     cmd,
-    data::{AppState, Nav, SpotifyUrl},
+    data::{is_liked_songs_link, AppState, Nav},
     ui::{album, artist, library, playlist, recommend, search, show},
 };
 
@@ -27,8 +30,17 @@ impl NavController {
                     ctx.submit_command(library::LOAD_SHOWS);
                 }
             }
+            Nav::LocalFiles => {
+                // Rescanning is cheap, only the files that changed get probed.
+                let dirs = data.config.local_music_dirs();
+                ctx.submit_command(library::LOAD_LOCAL_FILES.with(Arc::new(dirs)));
+            }
+            Nav::Downloads => {}
+            Nav::RadioBuilder => {}
             Nav::SearchResults(query) => {
-                if let Some(link) = SpotifyUrl::parse(query) {
+                if is_liked_songs_link(query) {
+                    ctx.submit_command(cmd::NAVIGATE.with(Nav::SavedTracks));
+                } else if let Some(link) = SpotifyUri::parse(query) {
                     ctx.submit_command(search::OPEN_LINK.with(link));
                 } else if !data.search.results.contains(query) {
                     ctx.submit_command(search::LOAD_RESULTS.with(query.to_owned()));
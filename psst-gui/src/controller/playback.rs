@@ -1,20 +1,44 @@
 use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     thread::{self, JoinHandle},
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
 use crossbeam_channel::Sender;
 use druid::{
+    commands,
     im::Vector,
     widget::{prelude::*, Controller},
-    Code, ExtEventSink, InternalLifeCycle, KbKey, WindowHandle,
+    Code, ExtEventSink, FileDialogOptions, FileInfo, FileSpec, InternalLifeCycle, KbKey, Selector,
+    SingleUse, TimerToken, WindowHandle,
 };
+use parking_lot::Mutex;
 use psst_core::{
-    audio::{normalize::NormalizationLevel, output::DefaultAudioOutput},
+    audio::{
+        normalize::NormalizationLevel,
+        output::{AudioOutput, DefaultAudioOutput, DeviceEvent},
+    },
     cache::Cache,
     cdn::Cdn,
-    player::{item::PlaybackItem, PlaybackConfig, Player, PlayerCommand, PlayerEvent},
-    session::SessionService,
+    item_id::{ItemId, ItemIdType},
+    player::{
+        daemon::{
+            DaemonClient, DaemonEvent, DaemonItem, DaemonRequest, DaemonState, PlayerDaemon,
+            DEFAULT_PORT,
+        },
+        item::PlaybackItem,
+        listen_along::{self, ListenAlongFollower, ListenAlongHost, SharedPlayback},
+        queue::ShuffleHint,
+        report::PlaybackReporter,
+        PlaybackConfig, Player, PlayerCommand, PlayerEvent,
+    },
+    session::{activity::SessionActivity, SessionService},
+    uri::SpotifyUri,
 };
 use souvlaki::{
     MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig,
@@ -22,23 +46,172 @@ use souvlaki::{
 
 use crate::{
     cmd,
-    data::{AppState, Config, Playback, PlaybackOrigin, PlaybackState, QueueBehavior, QueueEntry},
+    data::{
+        load_chapters, load_local_track, AppState, Canvas, Chapter, Config, Integration,
+        LastPlayback, ListenAlongMode, LoopSection, NowPlaying, PlayRecord, Playable, Playback,
+        PlaybackOrigin, PlaybackPayload, PlaybackState, QueueBehavior, QueueEntry, QueueFile,
+        QueueFileItem, QueuePlacement, QueueRequest, QueueSource, RadioBuilder, RadioSeed,
+        Recommendations, SavedTracks, StartupPlayback, Statistics, Track, TrackId,
+    },
+    error::Error,
+    ui::{radio, utils},
+    webapi,
 };
 
+const USER_QUEUE_LOADED: Selector<(QueuePlacement, Result<Vector<QueueEntry>, Error>)> =
+    Selector::new("app.playback.user-queue-loaded");
+const LAST_PLAYBACK_LOADED: Selector<(Arc<Track>, LastPlayback)> =
+    Selector::new("app.playback.last-playback-loaded");
+const QUEUE_EXPORT_SAVE_AS: Selector<FileInfo> = Selector::new("app.playback.queue-export-save-as");
+const QUEUE_IMPORT_OPEN: Selector<FileInfo> = Selector::new("app.playback.queue-import-open");
+const QUEUE_IMPORTED: Selector<
+    Result<(QueueFile, Vector<Arc<Track>>, Vector<Arc<Track>>), String>,
+> = Selector::new("app.playback.queue-imported");
+const CHAPTERS_LOADED: Selector<(ItemId, Vector<Chapter>)> =
+    Selector::new("app.playback.chapters-loaded");
+const CANVAS_LOADED: Selector<(ItemId, Canvas)> = Selector::new("app.playback.canvas-loaded");
+const OUTPUT_DEVICE_CHANGED: Selector<DeviceEvent> =
+    Selector::new("app.playback.output-device-changed");
+const OUTPUT_DEVICE_OPENED: Selector<Option<String>> =
+    Selector::new("app.playback.output-device-opened");
+/// Carries the error if the media controls could not be connected.
+const MEDIA_CONTROLS_OPENED: Selector<Option<String>> =
+    Selector::new("app.playback.media-controls-opened");
+const LISTEN_ALONG_JOINED: Selector<SingleUse<ListenAlongFollower>> =
+    Selector::new("app.playback.listen-along-joined");
+const LISTEN_ALONG_UPDATE: Selector<(SharedPlayback, Instant)> =
+    Selector::new("app.playback.listen-along-update");
+const LISTEN_ALONG_TRACK_LOADED: Selector<(
+    ItemId,
+    Result<Arc<Track>, Error>,
+    SharedPlayback,
+    Instant,
+)> = Selector::new("app.playback.listen-along-track-loaded");
+const LISTEN_ALONG_ENDED: Selector<Option<String>> =
+    Selector::new("app.playback.listen-along-ended");
+const SYNC_LIKED_SONGS: Selector = Selector::new("app.playback.sync-liked-songs");
+const LIKED_SONGS_FETCHED: Selector<Result<Vector<Arc<Track>>, Error>> =
+    Selector::new("app.playback.liked-songs-fetched");
+const RADIO_STARTED: Selector<(Arc<Vector<RadioSeed>>, Result<Recommendations, Error>)> =
+    Selector::new("app.playback.radio-started");
+const RADIO_EXTENDED: Selector<(Arc<Vector<RadioSeed>>, Result<Recommendations, Error>)> =
+    Selector::new("app.playback.radio-extended");
+const DAEMON_ATTACHED: Selector<DaemonPlayback> = Selector::new("app.playback.daemon-attached");
+const DAEMON_DETACHED: Selector = Selector::new("app.playback.daemon-detached");
+
+/// Followers seek only if they drift further than this from the host, so the
+/// playback isn't interrupted by the network latency.
+const LISTEN_ALONG_TOLERANCE: Duration = Duration::from_secs(2);
+
+#[cfg(target_os = "linux")]
+const MEDIA_CONTROLS_STATUS: &str = "Published on D-Bus as org.mpris.MediaPlayer2.psst";
+#[cfg(not(target_os = "linux"))]
+const MEDIA_CONTROLS_STATUS: &str = "Connected to the media controls of the system";
+
+/// Position reports arrive roughly every second, so once the end of an A-B loop
+/// gets closer than this, the jump back is scheduled on a timer instead.
+const LOOP_LOOKAHEAD: Duration = Duration::from_millis(1200);
+
+/// Going to the previous chapter restarts the current one instead, unless it has
+/// been playing for less than this.
+const CHAPTER_RESTART_THRESHOLD: Duration = Duration::from_secs(3);
+
+/// While playing the saved tracks, they are fetched this often, so tracks saved or
+/// removed on other devices make it into the queue.
+const LIKED_SONGS_SYNC_INTERVAL: Duration = Duration::from_secs(2 * 60);
+
+/// In the low power mode, the progress shown in a background window is only
+/// updated this often.
+const LOW_POWER_PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A playing radio gets more tracks once fewer than this many are left after the
+/// current one.
+const RADIO_REFILL_THRESHOLD: usize = 5;
+
+/// Playback of the daemon at the time of attaching, with the metadata of its items
+/// loaded.
+struct DaemonPlayback {
+    queue: Vector<Playable>,
+    user_queue: Vector<Playable>,
+    item: Option<ItemId>,
+    position: Duration,
+    paused: bool,
+    volume: f64,
+}
+
 pub struct PlaybackController {
     sender: Option<Sender<PlayerEvent>>,
     thread: Option<JoinHandle<()>>,
     output: Option<DefaultAudioOutput>,
     media_controls: Option<MediaControls>,
+    statistics: Option<Statistics>,
+    started_at: Option<SystemTime>,
+    skip_counts: HashMap<Arc<str>, u32>,
+    // Item being restored, the position to seek to, and whether to pause, once it
+    // starts playing.
+    restoring: Option<(ItemId, Duration, bool)>,
+    // Shared with the event thread, which feeds it the player events.
+    listen_along_host: Arc<Mutex<Option<ListenAlongHost>>>,
+    reporter: Option<PlaybackReporter>,
+    listen_along_follower: Option<ListenAlongFollower>,
+    // Track being loaded to catch up with the listen-along host.
+    listen_along_pending: Option<ItemId>,
+    // Whether we last tried to host or to follow, to know what reconnecting means.
+    listen_along_attempt: ListenAlongMode,
+    loop_timer: TimerToken,
+    liked_songs_timer: TimerToken,
+    // Set when the playback got paused because the output device went away.
+    paused_by_disconnect: bool,
+    // When the progress was last written into the app state.
+    progress_updated_at: Option<Instant>,
+    // Seeds of the radio last started, which keeps getting more tracks while it plays.
+    radio: Option<Arc<Vector<RadioSeed>>>,
+    radio_loading: bool,
+    // Set when playing through `psst-cli daemon` instead of a player of our own.
+    attached_to_daemon: bool,
+    // Whether the last skip went backwards, so blocked items are skipped the same
+    // way.  Shared with the media controls, which send their skips directly.
+    skipping_back: Arc<AtomicBool>,
+    // Blocked item last skipped backwards from, going back from the start of the
+    // queue loads it again.
+    blocked_skipped_back: Option<ItemId>,
 }
 
 impl PlaybackController {
     pub fn new() -> Self {
+        let statistics = Statistics::open();
+        let skip_counts = statistics
+            .as_ref()
+            .and_then(|statistics| {
+                statistics
+                    .skip_counts()
+                    .map_err(|err| log::error!("failed to load listening history: {}", err))
+                    .ok()
+            })
+            .unwrap_or_default();
         Self {
             sender: None,
             thread: None,
             output: None,
             media_controls: None,
+            statistics,
+            started_at: None,
+            skip_counts,
+            restoring: None,
+            listen_along_host: Arc::default(),
+            reporter: None,
+            listen_along_follower: None,
+            listen_along_pending: None,
+            listen_along_attempt: ListenAlongMode::Off,
+            loop_timer: TimerToken::INVALID,
+            liked_songs_timer: TimerToken::INVALID,
+            paused_by_disconnect: false,
+            progress_updated_at: None,
+            radio: None,
+            radio_loading: false,
+            attached_to_daemon: false,
+            skipping_back: Arc::default(),
+            blocked_skipped_back: None,
         }
     }
 
@@ -46,11 +219,27 @@ impl PlaybackController {
         &mut self,
         session: SessionService,
         config: PlaybackConfig,
+        attach_to_daemon: bool,
         event_sink: ExtEventSink,
         widget_id: WidgetId,
         #[allow(unused_variables)] window: &WindowHandle,
     ) {
+        if attach_to_daemon {
+            match Config::daemon_token()
+                .and_then(|token| DaemonClient::connect(DEFAULT_PORT, &token))
+            {
+                Ok(client) => {
+                    self.attach_to_daemon(client, event_sink, widget_id, window);
+                    return;
+                }
+                Err(err) => {
+                    log::warn!("no playback daemon to attach to, playing here: {}", err);
+                }
+            }
+        }
+
         let output = DefaultAudioOutput::open().unwrap();
+        let reporter = PlaybackReporter::new(session.clone());
         let cache_dir = Config::cache_dir().unwrap();
         let proxy_url = Config::proxy();
         let player = Player::new(
@@ -61,19 +250,237 @@ impl PlaybackController {
             &output,
         );
 
-        self.media_controls = Self::create_media_controls(player.sender(), window)
-            .map_err(|err| log::error!("failed to connect to media control interface: {:?}", err))
-            .ok();
+        self.open_media_controls(player.sender(), &event_sink, widget_id, window);
+
+        event_sink
+            .submit_command(OUTPUT_DEVICE_OPENED, output.device_name(), widget_id)
+            .unwrap();
+        thread::spawn({
+            let device_events = output.device_events();
+            let event_sink = event_sink.clone();
+            move || {
+                for event in device_events {
+                    event_sink
+                        .submit_command(OUTPUT_DEVICE_CHANGED, event, widget_id)
+                        .unwrap();
+                }
+            }
+        });
+
+        // Let `psst-cli` drive this player, like it drives the daemon.
+        let daemon = match Config::daemon_token()
+            .and_then(|token| PlayerDaemon::bind(DEFAULT_PORT, &token, player.sender()))
+        {
+            Ok(daemon) => Some(daemon),
+            Err(err) => {
+                log::info!("not serving the playback to other clients: {}", err);
+                None
+            }
+        };
 
         self.sender = Some(player.sender());
-        self.thread = Some(thread::spawn(move || {
-            Self::service_events(player, event_sink, widget_id);
+        self.thread = Some(thread::spawn({
+            let listen_along_host = Arc::clone(&self.listen_along_host);
+            let reporter = reporter.clone();
+            move || {
+                Self::service_events(
+                    player,
+                    daemon,
+                    listen_along_host,
+                    reporter,
+                    event_sink,
+                    widget_id,
+                );
+            }
         }));
         self.output.replace(output);
+        self.reporter.replace(reporter);
+    }
+
+    /// Play through the daemon instead of a player of our own.  The commands are sent
+    /// over to it, and its events come back as the ones of a local player would.
+    fn attach_to_daemon(
+        &mut self,
+        client: DaemonClient,
+        event_sink: ExtEventSink,
+        widget_id: WidgetId,
+        window: &WindowHandle,
+    ) {
+        let client = Arc::new(client);
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        self.open_media_controls(sender.clone(), &event_sink, widget_id, window);
+
+        thread::spawn({
+            let client = Arc::clone(&client);
+            move || {
+                for event in receiver {
+                    let request = match event {
+                        PlayerEvent::Command(command) => DaemonRequest::from_command(&command),
+                        _ => None,
+                    };
+                    if let Some(request) = request {
+                        if let Err(err) = client.send(&request) {
+                            log::error!("failed to send to the playback daemon: {}", err);
+                            break;
+                        }
+                    }
+                }
+                client.detach();
+            }
+        });
+
+        self.sender = Some(sender);
+        self.thread = Some(thread::spawn(move || {
+            Self::service_daemon_events(&client, event_sink, widget_id);
+        }));
+        self.attached_to_daemon = true;
+    }
+
+    fn service_daemon_events(client: &DaemonClient, event_sink: ExtEventSink, widget_id: WidgetId) {
+        let events = match client.events() {
+            Ok(events) => events,
+            Err(err) => {
+                log::error!("failed to read from the playback daemon: {}", err);
+                event_sink
+                    .submit_command(DAEMON_DETACHED, (), widget_id)
+                    .unwrap();
+                return;
+            }
+        };
+        let item_id = |item: &DaemonItem| item.to_playback_item().map(|item| item.item_id);
+        for event in events {
+            match event {
+                DaemonEvent::State(state) => {
+                    event_sink
+                        .submit_command(DAEMON_ATTACHED, Self::load_daemon_state(state), widget_id)
+                        .unwrap();
+                }
+                DaemonEvent::Loading { item } => {
+                    if let Some(item_id) = item_id(&item) {
+                        event_sink
+                            .submit_command(cmd::PLAYBACK_LOADING, item_id, widget_id)
+                            .unwrap();
+                    }
+                }
+                DaemonEvent::Playing { item, position_ms } => {
+                    if let Some(item_id) = item_id(&item) {
+                        let progress = Duration::from_millis(position_ms);
+                        event_sink
+                            .submit_command(cmd::PLAYBACK_PLAYING, (item_id, progress), widget_id)
+                            .unwrap();
+                    }
+                }
+                DaemonEvent::Pausing => {
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_PAUSING, (), widget_id)
+                        .unwrap();
+                }
+                DaemonEvent::Resuming => {
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_RESUMING, (), widget_id)
+                        .unwrap();
+                }
+                DaemonEvent::Position { position_ms } => {
+                    let progress = Duration::from_millis(position_ms);
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_PROGRESS, progress, widget_id)
+                        .unwrap();
+                }
+                DaemonEvent::UpNext { item, .. } => {
+                    if let Some(item_id) = item_id(&item) {
+                        event_sink
+                            .submit_command(cmd::PLAYBACK_UP_NEXT, item_id, widget_id)
+                            .unwrap();
+                    }
+                }
+                DaemonEvent::Blocked => {
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_BLOCKED, (), widget_id)
+                        .unwrap();
+                }
+                DaemonEvent::Stalled => {
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_STALLED, (), widget_id)
+                        .unwrap();
+                }
+                DaemonEvent::Stopped => {
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_STOPPED, (), widget_id)
+                        .unwrap();
+                }
+            }
+        }
+        event_sink
+            .submit_command(DAEMON_DETACHED, (), widget_id)
+            .unwrap();
     }
 
-    fn service_events(mut player: Player, event_sink: ExtEventSink, widget_id: WidgetId) {
+    /// Load the metadata of the items the daemon plays.  Episodes can't be looked up
+    /// on their own, so they are left out, same as the items that fail to load.
+    fn load_daemon_state(state: DaemonState) -> DaemonPlayback {
+        let ids: Vec<_> = state
+            .queue
+            .iter()
+            .chain(&state.user_queue)
+            .filter_map(|item| match item {
+                DaemonItem::Spotify {
+                    uri: uri @ SpotifyUri::Track(_),
+                    ..
+                } => uri.item_id().map(TrackId),
+                _ => None,
+            })
+            .collect();
+        let streamed: HashMap<_, _> = match webapi::global().get_tracks(&ids) {
+            Ok(tracks) => tracks.into_iter().map(|track| (track.id, track)).collect(),
+            Err(err) => {
+                log::error!("failed to load the queue of the playback daemon: {}", err);
+                HashMap::new()
+            }
+        };
+        let load = |items: &[DaemonItem]| -> Vector<Playable> {
+            items
+                .iter()
+                .filter_map(|item| match item {
+                    DaemonItem::Spotify { uri, .. } => {
+                        let id = uri.item_id().map(TrackId)?;
+                        streamed.get(&id).cloned()
+                    }
+                    DaemonItem::LocalFile { path, .. } => load_local_track(path),
+                })
+                .map(Playable::Track)
+                .collect()
+        };
+        DaemonPlayback {
+            queue: load(&state.queue),
+            user_queue: load(&state.user_queue),
+            item: state
+                .item
+                .and_then(|item| item.to_playback_item())
+                .map(|item| item.item_id),
+            position: Duration::from_millis(state.position_ms),
+            paused: state.paused,
+            volume: state.volume,
+        }
+    }
+
+    fn service_events(
+        mut player: Player,
+        daemon: Option<PlayerDaemon>,
+        listen_along_host: Arc<Mutex<Option<ListenAlongHost>>>,
+        reporter: PlaybackReporter,
+        event_sink: ExtEventSink,
+        widget_id: WidgetId,
+    ) {
         for event in player.receiver() {
+            if let Some(daemon) = &daemon {
+                daemon.observe(&event);
+            }
+            // Share the playback with the listen-along followers, if hosting.
+            if let Some(host) = listen_along_host.lock().as_ref() {
+                host.observe(&event);
+            }
+            reporter.observe(&event);
+
             // Forward events that affect the UI state to the UI thread.
             match &event {
                 PlayerEvent::Loading { item } => {
@@ -108,11 +515,21 @@ impl PlaybackController {
                         .submit_command(cmd::PLAYBACK_BLOCKED, (), widget_id)
                         .unwrap();
                 }
+                PlayerEvent::Stalled { .. } => {
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_STALLED, (), widget_id)
+                        .unwrap();
+                }
                 PlayerEvent::Stopped => {
                     event_sink
                         .submit_command(cmd::PLAYBACK_STOPPED, (), widget_id)
                         .unwrap();
                 }
+                PlayerEvent::UpNext { item, .. } => {
+                    event_sink
+                        .submit_command(cmd::PLAYBACK_UP_NEXT, item.item_id, widget_id)
+                        .unwrap();
+                }
                 _ => {}
             }
 
@@ -121,8 +538,34 @@ impl PlaybackController {
         }
     }
 
+    fn open_media_controls(
+        &mut self,
+        sender: Sender<PlayerEvent>,
+        event_sink: &ExtEventSink,
+        widget_id: WidgetId,
+        window: &WindowHandle,
+    ) {
+        let skipping_back = self.skipping_back.clone();
+        self.media_controls = match Self::create_media_controls(sender, skipping_back, window) {
+            Ok(media_controls) => {
+                event_sink
+                    .submit_command(MEDIA_CONTROLS_OPENED, None, widget_id)
+                    .unwrap();
+                Some(media_controls)
+            }
+            Err(err) => {
+                log::error!("failed to connect to media control interface: {:?}", err);
+                event_sink
+                    .submit_command(MEDIA_CONTROLS_OPENED, Some(format!("{:?}", err)), widget_id)
+                    .unwrap();
+                None
+            }
+        };
+    }
+
     fn create_media_controls(
         sender: Sender<PlayerEvent>,
+        skipping_back: Arc<AtomicBool>,
         #[allow(unused_variables)] window: &WindowHandle,
     ) -> Result<MediaControls, souvlaki::Error> {
         let hwnd = {
@@ -146,13 +589,47 @@ impl PlaybackController {
         })?;
 
         media_controls.attach(move |event| {
-            Self::handle_media_control_event(event, &sender);
+            Self::handle_media_control_event(event, &sender, &skipping_back);
         })?;
 
         Ok(media_controls)
     }
 
-    fn handle_media_control_event(event: MediaControlEvent, sender: &Sender<PlayerEvent>) {
+    fn reconnect_media_controls(&mut self, data: &mut AppState, window: &WindowHandle) {
+        // Let go of the old connection first, the D-Bus name can only be taken once.
+        self.media_controls = None;
+        let sender = match self.sender.clone() {
+            Some(sender) => sender,
+            None => return,
+        };
+        match Self::create_media_controls(sender, self.skipping_back.clone(), window) {
+            Ok(media_controls) => {
+                self.media_controls = Some(media_controls);
+                self.update_media_control_metadata(&data.playback);
+                self.update_media_control_playback(&data.playback);
+                data.integrations
+                    .media_controls
+                    .set_connected(MEDIA_CONTROLS_STATUS);
+            }
+            Err(err) => {
+                log::error!("failed to connect to media control interface: {:?}", err);
+                data.integrations
+                    .media_controls
+                    .set_failed(format!("{:?}", err));
+            }
+        }
+    }
+
+    fn handle_media_control_event(
+        event: MediaControlEvent,
+        sender: &Sender<PlayerEvent>,
+        skipping_back: &AtomicBool,
+    ) {
+        match event {
+            MediaControlEvent::Next => skipping_back.store(false, Ordering::Relaxed),
+            MediaControlEvent::Previous => skipping_back.store(true, Ordering::Relaxed),
+            _ => {}
+        }
         let cmd = match event {
             MediaControlEvent::Play => PlayerEvent::Command(PlayerCommand::Resume),
             MediaControlEvent::Pause => PlayerEvent::Command(PlayerCommand::Pause),
@@ -215,6 +692,40 @@ impl PlaybackController {
         }
     }
 
+    fn record_play(&mut self, data: &AppState) {
+        let started_at = match self.started_at.take() {
+            Some(started_at) => started_at,
+            None => return,
+        };
+        if data.playback.private_session {
+            return;
+        }
+        let rules = &data.config.history_rules;
+        let record = data
+            .playback
+            .now_playing
+            .as_ref()
+            .filter(|now_playing| !rules.ignores(&now_playing.item))
+            .and_then(|now_playing| PlayRecord::new(now_playing, started_at, rules));
+        if let (Some(statistics), Some(record)) = (&self.statistics, record) {
+            if let Err(err) = statistics.record(&record) {
+                log::error!("failed to save play record: {}", err);
+            }
+            if record.is_skip() {
+                *self.skip_counts.entry(record.uri).or_default() += 1;
+            }
+        }
+    }
+
+    /// Private sessions are kept off the account, same as the local history.  Sent
+    /// as a command, so the reporter of the daemon follows it too.
+    fn update_reporting(&mut self, data: &AppState) {
+        let enabled = data.config.report_playback && !data.playback.private_session;
+        self.send(PlayerEvent::Command(PlayerCommand::SetReporting {
+            enabled,
+        }));
+    }
+
     fn send(&mut self, event: PlayerEvent) {
         if let Some(s) = &self.sender {
             s.send(event)
@@ -223,14 +734,53 @@ impl PlaybackController {
         }
     }
 
-    fn play(&mut self, items: &Vector<QueueEntry>, position: usize) {
-        let playback_items = items.iter().map(|queued| PlaybackItem {
+    fn playback_item(queued: &QueueEntry) -> PlaybackItem {
+        PlaybackItem {
             item_id: queued.item.id(),
             norm_level: match queued.origin {
                 PlaybackOrigin::Album(_) => NormalizationLevel::Album,
                 _ => NormalizationLevel::Track,
             },
-        });
+        }
+    }
+
+    fn shuffle_hint(&self, queued: &QueueEntry) -> ShuffleHint {
+        let skip_count = queued
+            .item
+            .id()
+            .to_uri()
+            .and_then(|uri| self.skip_counts.get(uri.as_str()).copied())
+            .unwrap_or_default();
+        let artist = match &queued.item {
+            Playable::Track(track) => track.artists.front().map(|artist| artist.id.clone()),
+            Playable::Episode(episode) => Some(episode.show.id.clone()),
+        };
+        ShuffleHint { skip_count, artist }
+    }
+
+    /// Blocked items are skipped only where the user didn't pick them: in the
+    /// radio and in shuffled queues.  Items queued by hand always play.
+    fn skips_blocked(data: &AppState, queued: &QueueEntry) -> bool {
+        let is_picked_by_app = matches!(queued.origin, PlaybackOrigin::Recommendations(_))
+            || data.playback.queue_behavior == QueueBehavior::Random;
+        let is_queued_by_hand = data
+            .playback
+            .user_queue
+            .iter()
+            .any(|entry| entry.item.id() == queued.item.id());
+        is_picked_by_app && !is_queued_by_hand && data.config.blocklist.blocks(&queued.item)
+    }
+
+    fn play(&mut self, items: &Vector<QueueEntry>, position: usize) {
+        let hints: HashMap<ItemId, ShuffleHint> = items
+            .iter()
+            .map(|queued| (queued.item.id(), self.shuffle_hint(queued)))
+            .collect();
+        self.send(PlayerEvent::Command(PlayerCommand::SetShuffleHints {
+            hints,
+        }));
+
+        let playback_items = items.iter().map(Self::playback_item);
         let playback_items_vec: Vec<PlaybackItem> = playback_items.collect();
 
         // Make sure position is within bounds
@@ -239,6 +789,11 @@ impl PlaybackController {
         } else {
             position
         };
+        // A restore is only meant for the load that was set up with it.
+        let loading = playback_items_vec.get(position).map(|item| item.item_id);
+        if self.restoring.map(|(restored, ..)| restored) != loading {
+            self.restoring = None;
+        }
 
         self.send(PlayerEvent::Command(PlayerCommand::LoadQueue {
             items: playback_items_vec,
@@ -246,6 +801,419 @@ impl PlaybackController {
         }));
     }
 
+    /// Replace the queue with the items of `payload`.  Returns false if the content
+    /// filter left nothing to play.
+    fn play_tracks(&mut self, data: &mut AppState, payload: &PlaybackPayload) -> bool {
+        let (items, position) = data.filter_playable(&payload.items, payload.position);
+        if items.is_empty() {
+            data.info_alert("Explicit content is filtered out.");
+            return false;
+        }
+        self.apply_context_queue_behavior(data, &payload.origin);
+        data.playback.queue = items
+            .into_iter()
+            .map(|item| QueueEntry {
+                origin: payload.origin.to_owned(),
+                item,
+            })
+            .collect();
+        self.play(&data.playback.queue, position);
+        true
+    }
+
+    /// Send the changed queue over to the player, which keeps playing the current
+    /// item.
+    fn update_queue(&mut self, queue: &Vector<QueueEntry>) {
+        let hints = queue
+            .iter()
+            .map(|queued| (queued.item.id(), self.shuffle_hint(queued)))
+            .collect();
+        self.send(PlayerEvent::Command(PlayerCommand::SetShuffleHints {
+            hints,
+        }));
+        let items = queue.iter().map(Self::playback_item).collect();
+        self.send(PlayerEvent::Command(PlayerCommand::UpdateQueue { items }));
+    }
+
+    fn load_radio(
+        seeds: Arc<Vector<RadioSeed>>,
+        selector: Selector<(Arc<Vector<RadioSeed>>, Result<Recommendations, Error>)>,
+        event_sink: ExtEventSink,
+        widget_id: WidgetId,
+    ) {
+        thread::spawn(move || {
+            let result = radio::generate(seeds.clone());
+            event_sink
+                .submit_command(selector, (seeds, result), widget_id)
+                .unwrap();
+        });
+    }
+
+    fn is_playing_radio(data: &AppState, seeds: &Vector<RadioSeed>) -> bool {
+        let request = RadioBuilder::request(seeds);
+        data.playback
+            .now_playing
+            .as_ref()
+            .map_or(false, |now_playing| {
+                matches!(&now_playing.origin, PlaybackOrigin::Recommendations(r) if **r == request)
+            })
+    }
+
+    /// Mix more tracks into the playing radio once it's close to running out, so
+    /// it keeps on playing.
+    fn extend_radio(&mut self, ctx: &mut EventCtx, data: &AppState) {
+        let seeds = match &self.radio {
+            Some(seeds) if !self.radio_loading => seeds.clone(),
+            _ => return,
+        };
+        if !Self::is_playing_radio(data, &seeds) {
+            return;
+        }
+        let current = data.playback.now_playing.as_ref().map(|np| np.item.id());
+        let position = data
+            .playback
+            .queue
+            .iter()
+            .position(|queued| Some(queued.item.id()) == current);
+        if let Some(position) = position {
+            if data.playback.queue.len() - position - 1 < RADIO_REFILL_THRESHOLD {
+                self.radio_loading = true;
+                Self::load_radio(
+                    seeds,
+                    RADIO_EXTENDED,
+                    ctx.get_external_handle(),
+                    ctx.widget_id(),
+                );
+            }
+        }
+    }
+
+    fn add_to_queue(&mut self, items: &Vector<QueueEntry>, placement: QueuePlacement) {
+        let items = items.iter().map(Self::playback_item).collect();
+        self.send(PlayerEvent::Command(match placement {
+            QueuePlacement::Next => PlayerCommand::PlayNext { items },
+            QueuePlacement::Last => PlayerCommand::AddToQueue { items },
+        }));
+    }
+
+    fn load_user_queue(request: QueueRequest, event_sink: ExtEventSink, widget_id: WidgetId) {
+        thread::spawn(move || {
+            let origin = request.source.origin();
+            let tracks = match &request.source {
+                QueueSource::Album(link) => webapi::global()
+                    .get_album(&link.id)
+                    .map(|album| album.data.tracks.clone()),
+                QueueSource::Playlist(link) => webapi::global().get_playlist_tracks(&link.id),
+                QueueSource::ArtistTopTracks(link) => {
+                    webapi::global().get_artist_top_tracks(&link.id)
+                }
+                QueueSource::Track(track, _) => Ok(Vector::unit(track.clone())),
+            };
+            let result = tracks.map(|tracks| {
+                tracks
+                    .into_iter()
+                    .map(|track| QueueEntry {
+                        item: Playable::Track(track),
+                        origin: origin.clone(),
+                    })
+                    .collect()
+            });
+            event_sink
+                .submit_command(USER_QUEUE_LOADED, (request.placement, result), widget_id)
+                .unwrap();
+        });
+    }
+
+    fn load_last_playback(last: LastPlayback, event_sink: ExtEventSink, widget_id: WidgetId) {
+        thread::spawn(move || {
+            let id = last.track_id.0.to_base62();
+            match webapi::global().get_track(&id) {
+                Ok(track) => {
+                    event_sink
+                        .submit_command(LAST_PLAYBACK_LOADED, (track, last), widget_id)
+                        .unwrap();
+                }
+                Err(err) => {
+                    log::error!("failed to restore last playback: {}", err);
+                }
+            }
+        });
+    }
+
+    fn load_queue_file(path: PathBuf, event_sink: ExtEventSink, widget_id: WidgetId) {
+        thread::spawn(move || {
+            let result = QueueFile::read(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|file| {
+                    let tracks = Self::load_queue_file_items(&file.tracks);
+                    let user_tracks = Self::load_queue_file_items(&file.user_queue);
+                    match (tracks, user_tracks) {
+                        (Ok(tracks), Ok(user_tracks)) => Ok((file, tracks, user_tracks)),
+                        (Err(err), _) | (_, Err(err)) => Err(err.to_string()),
+                    }
+                });
+            event_sink
+                .submit_command(QUEUE_IMPORTED, result, widget_id)
+                .unwrap();
+        });
+    }
+
+    /// Resolve the entries of a queue file, keeping their order.  Streamed tracks are
+    /// fetched in bulk, local files are probed from the disk.  Entries that can't be
+    /// resolved are left out.
+    fn load_queue_file_items(items: &[QueueFileItem]) -> Result<Vector<Arc<Track>>, Error> {
+        let ids: Vec<_> = items
+            .iter()
+            .filter_map(|item| match item {
+                QueueFileItem::Spotify(id) => Some(*id),
+                QueueFileItem::Local { .. } => None,
+            })
+            .collect();
+        let streamed: HashMap<_, _> = webapi::global()
+            .get_tracks(&ids)?
+            .into_iter()
+            .map(|track| (track.id, track))
+            .collect();
+        Ok(items
+            .iter()
+            .filter_map(|item| match item {
+                QueueFileItem::Spotify(id) => streamed.get(id).cloned(),
+                QueueFileItem::Local { path } => load_local_track(path),
+            })
+            .collect())
+    }
+
+    fn import_queue(
+        &mut self,
+        data: &mut AppState,
+        file: &QueueFile,
+        tracks: &Vector<Arc<Track>>,
+        user_tracks: &Vector<Arc<Track>>,
+    ) {
+        // Unavailable tracks are missing from the response, look the playing one up by ID.
+        let playing = file.tracks.get(file.position).map(QueueFileItem::id);
+        let position = playing
+            .and_then(|id| tracks.iter().position(|track| track.id == id))
+            .unwrap_or(0);
+        let items: Vector<_> = tracks.iter().cloned().map(Playable::Track).collect();
+        let (items, position) = data.filter_playable(&items, position);
+        if items.is_empty() {
+            data.info_alert("Nothing to play in the imported queue.");
+            return;
+        }
+
+        if file.queue_behavior != data.playback.queue_behavior {
+            data.use_queue_behavior(file.queue_behavior);
+            self.set_queue_behavior(file.queue_behavior, data.config.smart_shuffle);
+        }
+        let origin = PlaybackOrigin::from_nav(&file.origin);
+        data.playback.queue = items
+            .iter()
+            .map(|item| QueueEntry {
+                origin: origin.clone(),
+                item: item.to_owned(),
+            })
+            .collect();
+        // Continue where the exported playback left off, unless the playing track got
+        // filtered out.
+        if let Some(item_id) = playing.map(|id| id.0) {
+            if items.get(position).map(Playable::id) == Some(item_id) {
+                self.restoring = Some((item_id, Duration::from_millis(file.progress_ms), false));
+            }
+        }
+        self.play(&data.playback.queue, position);
+
+        let filter_explicit = data.config.filter_explicit;
+        let user_entries: Vector<_> = user_tracks
+            .iter()
+            .filter(|track| !filter_explicit || !track.explicit)
+            .map(|track| QueueEntry {
+                item: Playable::Track(track.to_owned()),
+                origin: origin.clone(),
+            })
+            .collect();
+        if !user_entries.is_empty() {
+            self.add_to_queue(&user_entries, QueuePlacement::Last);
+            data.add_to_user_queue(user_entries, QueuePlacement::Last);
+        }
+        data.info_alert("Queue imported.");
+    }
+
+    fn host_listen_along(&mut self, data: &mut AppState) {
+        if self.attached_to_daemon {
+            data.error_alert("Sharing the playback of the daemon is not supported.");
+            return;
+        }
+        self.leave_listen_along();
+        self.listen_along_attempt = ListenAlongMode::Hosting;
+        let address = match data.playback.listen_along.address.trim() {
+            "" => listen_along::DEFAULT_HOST_ADDRESS,
+            address => address,
+        };
+        let now_playing = data.playback.now_playing.as_ref();
+        let playback = SharedPlayback {
+            item: now_playing
+                .and_then(|now_playing| SpotifyUri::from_item_id(now_playing.item.id())),
+            position_ms: now_playing
+                .map_or(0, |now_playing| now_playing.progress.as_millis() as u64),
+            paused: data.playback.state == PlaybackState::Paused,
+            up_next: None,
+        };
+        match ListenAlongHost::bind(address, playback, data.playback.private_session) {
+            Ok(host) => {
+                let port = host.local_addr().port();
+                data.info_alert(format!("Sharing playback on port {}.", port));
+                data.playback.listen_along.token = host.token().to_string();
+                data.integrations
+                    .listen_along
+                    .set_connected(format!("Sharing playback on port {}", port));
+                self.listen_along_host.lock().replace(host);
+                data.playback.listen_along.mode = ListenAlongMode::Hosting;
+            }
+            Err(err) => {
+                data.error_alert(format!("Failed to start listening along: {}", err));
+                data.integrations.listen_along.set_failed(err);
+            }
+        }
+    }
+
+    fn join_listen_along(
+        address: String,
+        token: String,
+        event_sink: ExtEventSink,
+        widget_id: WidgetId,
+    ) {
+        thread::spawn(move || {
+            let follower = match ListenAlongFollower::connect(&address, &token) {
+                Ok(follower) => follower,
+                Err(err) => {
+                    event_sink
+                        .submit_command(LISTEN_ALONG_ENDED, Some(err.to_string()), widget_id)
+                        .unwrap();
+                    return;
+                }
+            };
+            match follower.updates() {
+                Ok(updates) => {
+                    event_sink
+                        .submit_command(LISTEN_ALONG_JOINED, SingleUse::new(follower), widget_id)
+                        .unwrap();
+                    for playback in updates {
+                        event_sink
+                            .submit_command(
+                                LISTEN_ALONG_UPDATE,
+                                (playback, Instant::now()),
+                                widget_id,
+                            )
+                            .unwrap();
+                    }
+                    event_sink
+                        .submit_command(LISTEN_ALONG_ENDED, None, widget_id)
+                        .unwrap();
+                }
+                Err(err) => {
+                    event_sink
+                        .submit_command(LISTEN_ALONG_ENDED, Some(err.to_string()), widget_id)
+                        .unwrap();
+                }
+            }
+        });
+    }
+
+    fn start_following(&mut self, ctx: &mut EventCtx, data: &mut AppState) {
+        self.leave_listen_along();
+        self.listen_along_attempt = ListenAlongMode::Following;
+        let address = data.playback.listen_along.address.trim().to_string();
+        if address.is_empty() {
+            data.error_alert("Enter the address of the host to listen along.");
+        } else if data.playback.listen_along.token.trim().is_empty() {
+            data.error_alert("Enter the code the host is sharing with.");
+        } else {
+            data.playback.listen_along.mode = ListenAlongMode::Following;
+            data.integrations
+                .listen_along
+                .set_connecting(address.as_str());
+            let token = data.playback.listen_along.token.trim().to_string();
+            Self::join_listen_along(address, token, ctx.get_external_handle(), ctx.widget_id());
+        }
+    }
+
+    fn leave_listen_along(&mut self) {
+        self.listen_along_host.lock().take();
+        if let Some(follower) = self.listen_along_follower.take() {
+            follower.leave();
+        }
+        self.listen_along_pending = None;
+    }
+
+    /// Mirror the playback of the listen-along host.
+    fn follow_playback(
+        &mut self,
+        ctx: &mut EventCtx,
+        data: &AppState,
+        playback: &SharedPlayback,
+        received: Instant,
+    ) {
+        let item_id = match playback.item.as_ref().and_then(SpotifyUri::item_id) {
+            Some(item_id) => item_id,
+            None => {
+                if data.playback.now_playing.is_some() {
+                    self.stop();
+                }
+                return;
+            }
+        };
+        let now_playing = data
+            .playback
+            .now_playing
+            .as_ref()
+            .filter(|now_playing| now_playing.item.id() == item_id);
+        if let Some(now_playing) = now_playing {
+            match data.playback.state {
+                PlaybackState::Playing if playback.paused => self.pause(),
+                PlaybackState::Paused if !playback.paused => self.resume(),
+                _ => {}
+            }
+            let position = playback.position_after(received.elapsed());
+            let drift = position.max(now_playing.progress) - position.min(now_playing.progress);
+            if drift > LISTEN_ALONG_TOLERANCE {
+                self.seek(position);
+            }
+        } else if self.listen_along_pending != Some(item_id) {
+            if item_id.id_type == ItemIdType::Track {
+                self.listen_along_pending = Some(item_id);
+                Self::load_listen_along_track(
+                    item_id,
+                    playback.to_owned(),
+                    received,
+                    ctx.get_external_handle(),
+                    ctx.widget_id(),
+                );
+            } else {
+                log::warn!("listening along is only supported for tracks");
+            }
+        }
+    }
+
+    fn load_listen_along_track(
+        item_id: ItemId,
+        playback: SharedPlayback,
+        received: Instant,
+        event_sink: ExtEventSink,
+        widget_id: WidgetId,
+    ) {
+        thread::spawn(move || {
+            let result = webapi::global().get_track(&item_id.to_base62());
+            event_sink
+                .submit_command(
+                    LISTEN_ALONG_TRACK_LOADED,
+                    (item_id, result, playback, received),
+                    widget_id,
+                )
+                .unwrap();
+        });
+    }
+
     fn pause(&mut self) {
         self.send(PlayerEvent::Command(PlayerCommand::Pause));
     }
@@ -259,10 +1227,12 @@ impl PlaybackController {
     }
 
     fn previous(&mut self) {
+        self.skipping_back.store(true, Ordering::Relaxed);
         self.send(PlayerEvent::Command(PlayerCommand::Previous));
     }
 
     fn next(&mut self) {
+        self.skipping_back.store(false, Ordering::Relaxed);
         self.send(PlayerEvent::Command(PlayerCommand::Next));
     }
 
@@ -274,14 +1244,235 @@ impl PlaybackController {
         self.send(PlayerEvent::Command(PlayerCommand::Seek { position }));
     }
 
+    fn set_loop_start(&mut self, data: &mut AppState) {
+        if let Some(now_playing) = &mut data.playback.now_playing {
+            let position = now_playing.progress;
+            let section = &mut now_playing.loop_section;
+            section.start = Some(position);
+            if section.end.map_or(false, |end| end <= position) {
+                section.end = None;
+            }
+            data.info_alert(format!(
+                "Loop starts at {}.",
+                utils::as_minutes_and_seconds(position)
+            ));
+        }
+    }
+
+    fn set_loop_end(&mut self, data: &mut AppState) {
+        if let Some(now_playing) = &mut data.playback.now_playing {
+            // Without a start marker, loop from the beginning of the track.
+            let start = now_playing.loop_section.start.unwrap_or_default();
+            let end = now_playing.progress;
+            if end <= start {
+                return;
+            }
+            now_playing.loop_section = LoopSection {
+                start: Some(start),
+                end: Some(end),
+            };
+            self.seek(start);
+            data.info_alert(format!(
+                "Looping from {} to {}.",
+                utils::as_minutes_and_seconds(start),
+                utils::as_minutes_and_seconds(end)
+            ));
+        }
+    }
+
+    fn clear_loop(&mut self, data: &mut AppState) {
+        self.loop_timer = TimerToken::INVALID;
+        if let Some(now_playing) = &mut data.playback.now_playing {
+            if now_playing.loop_section.start.is_some() {
+                now_playing.loop_section = LoopSection::default();
+                data.info_alert("Loop cleared.");
+            }
+        }
+    }
+
+    fn is_playing_liked_songs(data: &AppState) -> bool {
+        data.playback
+            .now_playing
+            .as_ref()
+            .map_or(false, |now_playing| {
+                matches!(now_playing.origin, PlaybackOrigin::LikedSongs)
+            })
+    }
+
+    /// Bring the queue in line with the saved tracks, which changed while they are
+    /// playing.  The player keeps playing the current track.
+    fn sync_liked_songs(&mut self, data: &mut AppState) {
+        let tracks = match data.library.saved_tracks.resolved() {
+            Some(saved) => saved.tracks.clone(),
+            None => return,
+        };
+        let items: Vector<_> = tracks.into_iter().map(Playable::Track).collect();
+        let (items, _) = data.filter_playable(&items, 0);
+        data.playback.queue = items
+            .into_iter()
+            .map(|item| QueueEntry {
+                item,
+                origin: PlaybackOrigin::LikedSongs,
+            })
+            .collect();
+        self.update_queue(&data.playback.queue);
+    }
+
+    fn fetch_liked_songs(event_sink: ExtEventSink, widget_id: WidgetId) {
+        thread::spawn(move || {
+            let result = webapi::global().get_saved_tracks();
+            event_sink
+                .submit_command(LIKED_SONGS_FETCHED, result, widget_id)
+                .unwrap();
+        });
+    }
+
+    fn load_now_playing_chapters(
+        now_playing: &NowPlaying,
+        event_sink: ExtEventSink,
+        widget_id: WidgetId,
+    ) {
+        let path = match now_playing
+            .item
+            .track()
+            .and_then(|track| track.local_path.clone())
+        {
+            Some(path) => PathBuf::from(&*path),
+            None => return,
+        };
+        let item_id = now_playing.item.id();
+        thread::spawn(move || {
+            let chapters = load_chapters(&path);
+            if !chapters.is_empty() {
+                event_sink
+                    .submit_command(CHAPTERS_LOADED, (item_id, chapters), widget_id)
+                    .unwrap();
+            }
+        });
+    }
+
+    fn load_now_playing_canvas(
+        now_playing: &NowPlaying,
+        event_sink: ExtEventSink,
+        widget_id: WidgetId,
+    ) {
+        let track_id = match now_playing.item.track() {
+            Some(track) if !track.is_local => track.id,
+            _ => return,
+        };
+        thread::spawn(move || match webapi::global().get_canvas(track_id) {
+            Ok(Some(canvas)) => {
+                event_sink
+                    .submit_command(CANVAS_LOADED, (track_id.0, canvas), widget_id)
+                    .unwrap();
+            }
+            Ok(None) => {}
+            Err(err) => {
+                log::warn!("failed to load the canvas: {}", err);
+            }
+        });
+    }
+
+    fn seek_chapter(&mut self, data: &mut AppState, forward: bool) {
+        let now_playing = match &data.playback.now_playing {
+            Some(now_playing) if !now_playing.chapters.is_empty() => now_playing,
+            _ => return,
+        };
+        let current = now_playing.current_chapter();
+        let target = if forward {
+            now_playing
+                .chapters
+                .get(current.map_or(0, |index| index + 1))
+        } else {
+            current.and_then(|index| {
+                let start = now_playing.chapters[index].start;
+                if now_playing.progress - start < CHAPTER_RESTART_THRESHOLD && index > 0 {
+                    now_playing.chapters.get(index - 1)
+                } else {
+                    now_playing.chapters.get(index)
+                }
+            })
+        };
+        match target {
+            Some(chapter) => {
+                let (start, title) = (chapter.start, chapter.title.clone());
+                self.seek(start);
+                data.info_alert(title);
+            }
+            // Before the first chapter, or past the last one.
+            None if forward => self.next(),
+            None => self.seek(Duration::ZERO),
+        }
+    }
+
+    /// Jump back to the start of the A-B loop if its end has been reached, or
+    /// schedule the jump if it's about to be.
+    fn check_loop(&mut self, ctx: &mut EventCtx, playback: &Playback) {
+        let now_playing = match &playback.now_playing {
+            Some(now_playing) if playback.state == PlaybackState::Playing => now_playing,
+            _ => {
+                self.loop_timer = TimerToken::INVALID;
+                return;
+            }
+        };
+        if let Some((start, end)) = now_playing.loop_section.bounds() {
+            if now_playing.progress >= end {
+                self.loop_timer = TimerToken::INVALID;
+                self.seek(start);
+            } else if end - now_playing.progress <= LOOP_LOOKAHEAD {
+                self.loop_timer = ctx.request_timer(end - now_playing.progress);
+            } else {
+                // Seeked away from the end, drop the scheduled jump.
+                self.loop_timer = TimerToken::INVALID;
+            }
+        } else {
+            self.loop_timer = TimerToken::INVALID;
+        }
+    }
+
+    /// Every progress report changes the app state and repaints the playback bar, so
+    /// in the low power mode we skip most of them while the window is in the
+    /// background.  A-B loops need the exact progress, so they are never throttled.
+    fn is_progress_throttled(&self, ctx: &EventCtx, data: &AppState) -> bool {
+        let is_looping = data
+            .playback
+            .now_playing
+            .as_ref()
+            .map_or(false, |now_playing| {
+                now_playing.loop_section.bounds().is_some()
+            });
+        data.config.low_power_mode
+            && !is_looping
+            && utils::is_in_background(ctx.window())
+            && self
+                .progress_updated_at
+                .map_or(false, |at| at.elapsed() < LOW_POWER_PROGRESS_INTERVAL)
+    }
+
     fn set_volume(&mut self, volume: f64) {
         self.send(PlayerEvent::Command(PlayerCommand::SetVolume { volume }));
     }
 
-    fn set_queue_behavior(&mut self, behavior: QueueBehavior) {
+    /// Switch to the queue behavior remembered for `origin`, if there is one.
+    fn apply_context_queue_behavior(&mut self, data: &mut AppState, origin: &PlaybackOrigin) {
+        let remembered = origin
+            .context_uri()
+            .and_then(|uri| data.config.context_queue_behavior.get(&uri).copied());
+        if let Some(behavior) = remembered {
+            if behavior != data.playback.queue_behavior {
+                data.use_queue_behavior(behavior);
+                self.set_queue_behavior(behavior, data.config.smart_shuffle);
+            }
+        }
+    }
+
+    fn set_queue_behavior(&mut self, behavior: QueueBehavior, smart_shuffle: bool) {
         self.send(PlayerEvent::Command(PlayerCommand::SetQueueBehavior {
             behavior: match behavior {
                 QueueBehavior::Sequential => psst_core::player::queue::QueueBehavior::Sequential,
+                QueueBehavior::Random if smart_shuffle => {
+                    psst_core::player::queue::QueueBehavior::SmartShuffle
+                }
                 QueueBehavior::Random => psst_core::player::queue::QueueBehavior::Random,
                 QueueBehavior::LoopTrack => psst_core::player::queue::QueueBehavior::LoopTrack,
                 QueueBehavior::LoopAll => psst_core::player::queue::QueueBehavior::LoopAll,
@@ -310,31 +1501,154 @@ where
             Event::Command(cmd) if cmd.is(cmd::PLAYBACK_LOADING) => {
                 let item = cmd.get_unchecked(cmd::PLAYBACK_LOADING);
 
-                if let Some(queued) = data.queued_entry(*item) {
-                    data.loading_playback(queued.item, queued.origin);
-                    self.update_media_control_playback(&data.playback);
-                    self.update_media_control_metadata(&data.playback);
-                } else {
-                    log::warn!("loaded item not found in playback queue");
+                // The previous item is done playing, put it into the listening history.
+                self.record_play(data);
+
+                match data.queued_entry(*item) {
+                    Some(queued) if Self::skips_blocked(data, &queued) => {
+                        data.info_alert(format!(
+                            "Skipped “{}”, it's on your blocklist.",
+                            queued.item.name()
+                        ));
+                        // Keep going the way the user skipped, but turn around at the
+                        // start of the queue, where going back loads the same item again.
+                        let is_at_start = self.blocked_skipped_back == Some(*item);
+                        if self.skipping_back.load(Ordering::Relaxed) && !is_at_start {
+                            self.blocked_skipped_back = Some(*item);
+                            self.previous();
+                        } else {
+                            self.next();
+                        }
+                    }
+                    Some(queued) => {
+                        data.loading_playback(queued.item, queued.origin);
+                        self.update_media_control_playback(&data.playback);
+                        self.update_media_control_metadata(&data.playback);
+                    }
+                    None => {
+                        log::warn!("loaded item not found in playback queue");
+                    }
                 }
                 ctx.set_handled();
             }
             Event::Command(cmd) if cmd.is(cmd::PLAYBACK_PLAYING) => {
                 let (item, progress) = cmd.get_unchecked(cmd::PLAYBACK_PLAYING);
 
+                // Whatever plays next after this one, it's going forward.
+                self.skipping_back.store(false, Ordering::Relaxed);
+                self.blocked_skipped_back = None;
+
+                // Anything else started playing in the meantime, the restore is off.
+                if let Some((restored, position, paused)) = self.restoring.take() {
+                    if restored == *item {
+                        self.seek(position);
+                        if paused {
+                            self.pause();
+                        }
+                    }
+                }
+
                 if let Some(queued) = data.queued_entry(*item) {
+                    data.take_user_queued(*item);
+                    self.started_at.replace(SystemTime::now());
+                    if let Some(reporter) = &self.reporter {
+                        reporter.set_context(*item, queued.origin.context_uri());
+                    }
                     data.start_playback(queued.item, queued.origin, progress.to_owned());
+                    self.extend_radio(ctx, data);
+                    if Self::is_playing_liked_songs(data)
+                        && self.liked_songs_timer == TimerToken::INVALID
+                    {
+                        self.liked_songs_timer = ctx.request_timer(LIKED_SONGS_SYNC_INTERVAL);
+                    }
+                    if let Some(now_playing) = &data.playback.now_playing {
+                        Self::load_now_playing_chapters(
+                            now_playing,
+                            ctx.get_external_handle(),
+                            ctx.widget_id(),
+                        );
+                        if data.config.show_canvas {
+                            Self::load_now_playing_canvas(
+                                now_playing,
+                                ctx.get_external_handle(),
+                                ctx.widget_id(),
+                            );
+                        }
+                    }
                     self.update_media_control_playback(&data.playback);
                     self.update_media_control_metadata(&data.playback);
+                    ctx.submit_command(cmd::SHOW_OSD);
                 } else {
                     log::warn!("played item not found in playback queue");
                 }
                 ctx.set_handled();
             }
+            Event::Command(cmd) if cmd.is(CHAPTERS_LOADED) => {
+                let (item_id, chapters) = cmd.get_unchecked(CHAPTERS_LOADED);
+                if let Some(now_playing) = &mut data.playback.now_playing {
+                    if &now_playing.item.id() == item_id {
+                        now_playing.chapters = chapters.to_owned();
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(CANVAS_LOADED) => {
+                let (item_id, canvas) = cmd.get_unchecked(CANVAS_LOADED);
+                if let Some(now_playing) = &mut data.playback.now_playing {
+                    if &now_playing.item.id() == item_id {
+                        now_playing.canvas = Some(canvas.to_owned());
+                    }
+                }
+                ctx.set_handled();
+            }
             Event::Command(cmd) if cmd.is(cmd::PLAYBACK_PROGRESS) => {
                 let progress = cmd.get_unchecked(cmd::PLAYBACK_PROGRESS);
-                data.progress_playback(progress.to_owned());
-                self.update_media_control_playback(&data.playback);
+                if !self.is_progress_throttled(ctx, data) {
+                    self.progress_updated_at = Some(Instant::now());
+                    data.progress_playback(progress.to_owned());
+                    self.update_media_control_playback(&data.playback);
+                    self.check_loop(ctx, &data.playback);
+                }
+                ctx.set_handled();
+            }
+            Event::Timer(token) if token == &self.liked_songs_timer => {
+                self.liked_songs_timer = TimerToken::INVALID;
+                if Self::is_playing_liked_songs(data) {
+                    Self::fetch_liked_songs(ctx.get_external_handle(), ctx.widget_id());
+                    self.liked_songs_timer = ctx.request_timer(LIKED_SONGS_SYNC_INTERVAL);
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(LIKED_SONGS_FETCHED) => {
+                match cmd.get_unchecked(LIKED_SONGS_FETCHED) {
+                    Ok(tracks) => {
+                        let tracks = tracks.to_owned();
+                        data.with_library_mut(|library| {
+                            library.saved_tracks.resolve((), SavedTracks::new(tracks));
+                        });
+                    }
+                    Err(err) => log::warn!("failed to sync the saved tracks: {}", err),
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(SYNC_LIKED_SONGS) => {
+                if Self::is_playing_liked_songs(data) {
+                    self.sync_liked_songs(data);
+                }
+                ctx.set_handled();
+            }
+            Event::Timer(token) if token == &self.loop_timer => {
+                self.loop_timer = TimerToken::INVALID;
+                let start = data
+                    .playback
+                    .now_playing
+                    .as_ref()
+                    .and_then(|now_playing| now_playing.loop_section.bounds());
+                if let Some((start, _)) = start {
+                    if data.playback.state == PlaybackState::Playing {
+                        self.seek(start);
+                    }
+                }
                 ctx.set_handled();
             }
             Event::Command(cmd) if cmd.is(cmd::PLAYBACK_PAUSING) => {
@@ -343,6 +1657,7 @@ where
                 ctx.set_handled();
             }
             Event::Command(cmd) if cmd.is(cmd::PLAYBACK_RESUMING) => {
+                self.paused_by_disconnect = false;
                 data.resume_playback();
                 self.update_media_control_playback(&data.playback);
                 ctx.set_handled();
@@ -351,23 +1666,403 @@ where
                 data.block_playback();
                 ctx.set_handled();
             }
+            Event::Command(cmd) if cmd.is(cmd::PLAYBACK_STALLED) => {
+                data.error_alert("Playback got stuck and couldn't be recovered, it's paused.");
+                ctx.set_handled();
+            }
             Event::Command(cmd) if cmd.is(cmd::PLAYBACK_STOPPED) => {
+                self.record_play(data);
                 data.stop_playback();
                 self.update_media_control_playback(&data.playback);
                 ctx.set_handled();
             }
+            Event::Command(cmd) if cmd.is(cmd::PLAYBACK_UP_NEXT) => {
+                let item = cmd.get_unchecked(cmd::PLAYBACK_UP_NEXT);
+                data.playback.up_next = data.queued_entry(*item);
+                ctx.set_handled();
+            }
             // Playback actions.
             Event::Command(cmd) if cmd.is(cmd::PLAY_TRACKS) => {
                 let payload = cmd.get_unchecked(cmd::PLAY_TRACKS);
-                data.playback.queue = payload
-                    .items
-                    .iter()
-                    .map(|item| QueueEntry {
-                        origin: payload.origin.to_owned(),
-                        item: item.to_owned(),
-                    })
-                    .collect();
-                self.play(&data.playback.queue, payload.position);
+                self.play_tracks(data, payload);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::START_RADIO) => {
+                let seeds = cmd.get_unchecked(cmd::START_RADIO);
+                Self::load_radio(
+                    seeds.to_owned(),
+                    RADIO_STARTED,
+                    ctx.get_external_handle(),
+                    ctx.widget_id(),
+                );
+                data.info_alert("Starting the radio…");
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(RADIO_STARTED) => {
+                let (seeds, result) = cmd.get_unchecked(RADIO_STARTED);
+                match result {
+                    Ok(recommendations) if recommendations.tracks.is_empty() => {
+                        data.info_alert("No tracks came up for this radio.");
+                    }
+                    Ok(recommendations) => {
+                        let payload = PlaybackPayload {
+                            origin: PlaybackOrigin::Recommendations(
+                                recommendations.request.clone(),
+                            ),
+                            items: recommendations
+                                .tracks
+                                .iter()
+                                .map(|track| Playable::Track(track.clone()))
+                                .collect(),
+                            position: 0,
+                        };
+                        if self.play_tracks(data, &payload) {
+                            self.radio = Some(seeds.to_owned());
+                            self.radio_loading = false;
+                        }
+                    }
+                    Err(err) => {
+                        data.error_alert(err);
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(RADIO_EXTENDED) => {
+                let (seeds, result) = cmd.get_unchecked(RADIO_EXTENDED);
+                self.radio_loading = false;
+                let is_current = self.radio.as_ref() == Some(seeds);
+                match result {
+                    Ok(recommendations) if is_current && Self::is_playing_radio(data, seeds) => {
+                        let queued: HashSet<ItemId> = data
+                            .playback
+                            .queue
+                            .iter()
+                            .map(|queued| queued.item.id())
+                            .collect();
+                        let items: Vector<_> = recommendations
+                            .tracks
+                            .iter()
+                            .map(|track| Playable::Track(track.clone()))
+                            .filter(|item| !queued.contains(&item.id()))
+                            .collect();
+                        let (items, _) = data.filter_playable(&items, 0);
+                        let origin =
+                            PlaybackOrigin::Recommendations(recommendations.request.clone());
+                        data.playback
+                            .queue
+                            .extend(items.into_iter().map(|item| QueueEntry {
+                                item,
+                                origin: origin.clone(),
+                            }));
+                        self.update_queue(&data.playback.queue);
+                    }
+                    Ok(_) => {}
+                    Err(err) => log::warn!("failed to extend the radio: {}", err),
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::ADD_TO_QUEUE) => {
+                let request = cmd.get_unchecked(cmd::ADD_TO_QUEUE);
+                Self::load_user_queue(
+                    request.to_owned(),
+                    ctx.get_external_handle(),
+                    ctx.widget_id(),
+                );
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(USER_QUEUE_LOADED) => {
+                let (placement, result) = cmd.get_unchecked(USER_QUEUE_LOADED);
+                let filter_explicit = data.config.filter_explicit;
+                let result = result.as_ref().map(|entries| {
+                    entries
+                        .iter()
+                        .filter(|entry| !filter_explicit || !entry.item.is_explicit())
+                        .cloned()
+                        .collect::<Vector<_>>()
+                });
+                match result {
+                    Ok(entries) if entries.is_empty() => {
+                        data.info_alert("Nothing to add to the queue.");
+                    }
+                    Ok(entries) => {
+                        self.add_to_queue(&entries, *placement);
+                        data.add_to_user_queue(entries, *placement);
+                        data.info_alert(match placement {
+                            QueuePlacement::Next => "Playing next.",
+                            QueuePlacement::Last => "Added to queue.",
+                        });
+                    }
+                    Err(err) => {
+                        data.error_alert(err);
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(LAST_PLAYBACK_LOADED) => {
+                let (track, last) = cmd.get_unchecked(LAST_PLAYBACK_LOADED);
+                // Don't interrupt anything the user started in the meantime.
+                if data.playback.now_playing.is_none() {
+                    data.playback.queue = Vector::unit(QueueEntry {
+                        item: Playable::Track(track.to_owned()),
+                        origin: PlaybackOrigin::from_nav(&last.origin),
+                    });
+                    self.restoring = Some((
+                        track.id.0,
+                        Duration::from_millis(last.progress_ms),
+                        data.config.startup_playback == StartupPlayback::Paused,
+                    ));
+                    self.play(&data.playback.queue, 0);
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(DAEMON_ATTACHED) => {
+                let attached = cmd.get_unchecked(DAEMON_ATTACHED);
+                // The daemon doesn't know where the items were played from.
+                let entry = |item: &Playable| QueueEntry {
+                    item: item.to_owned(),
+                    origin: PlaybackOrigin::Library,
+                };
+                data.playback.queue = attached.queue.iter().map(entry).collect();
+                data.playback.user_queue = attached.user_queue.iter().map(entry).collect();
+                data.playback.volume = attached.volume;
+                if let Some(queued) = attached.item.and_then(|item| data.queued_entry(item)) {
+                    self.started_at.replace(SystemTime::now());
+                    data.start_playback(queued.item, queued.origin, attached.position);
+                    if attached.paused {
+                        data.pause_playback();
+                    }
+                    self.update_media_control_playback(&data.playback);
+                    self.update_media_control_metadata(&data.playback);
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(DAEMON_DETACHED) => {
+                self.sender = None;
+                data.stop_playback();
+                self.update_media_control_playback(&data.playback);
+                data.error_alert(
+                    "Lost the connection to the playback daemon, restart to play again.",
+                );
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(OUTPUT_DEVICE_OPENED) => {
+                let device = cmd.get_unchecked(OUTPUT_DEVICE_OPENED).as_deref();
+                // Restore the volume last used on this device, `update` will apply it.
+                data.playback.volume = data.config.device_volume(device);
+                data.playback.output_device = device.map(Arc::from);
+                data.session
+                    .activity()
+                    .record(SessionActivity::OutputDeviceOpened {
+                        device: device.map(str::to_string),
+                    });
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(OUTPUT_DEVICE_CHANGED) => {
+                match cmd.get_unchecked(OUTPUT_DEVICE_CHANGED) {
+                    DeviceEvent::Disconnected => {
+                        data.session
+                            .activity()
+                            .record(SessionActivity::OutputDeviceDisconnected);
+                        if data.playback.state == PlaybackState::Playing {
+                            self.pause();
+                            self.paused_by_disconnect = true;
+                            data.info_alert("Paused, the audio device was disconnected.");
+                        }
+                    }
+                    DeviceEvent::Reconnected => {
+                        data.session
+                            .activity()
+                            .record(SessionActivity::OutputDeviceReconnected);
+                        if self.paused_by_disconnect && data.config.resume_on_reconnect {
+                            self.resume();
+                        }
+                        self.paused_by_disconnect = false;
+                    }
+                    DeviceEvent::Switched(device) => {
+                        // Keep the volume of the previous device, then switch to the one
+                        // last used on the new device, `update` will apply it.
+                        let previous = data.playback.output_device.clone();
+                        data.config
+                            .remember_volume(previous.as_deref(), data.playback.volume);
+                        data.config.save();
+                        data.playback.volume = data.config.device_volume(device.as_deref());
+                        data.playback.output_device = device.as_deref().map(Arc::from);
+                        data.session
+                            .activity()
+                            .record(SessionActivity::OutputDeviceSwitched {
+                                from: previous.as_deref().map(str::to_string),
+                                to: device.to_owned(),
+                            });
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::EXPORT_QUEUE) => {
+                let options = FileDialogOptions::new()
+                    .allowed_types(vec![FileSpec::new("JSON", &["json"])])
+                    .default_name("queue.json")
+                    .accept_command(QUEUE_EXPORT_SAVE_AS);
+                ctx.submit_command(commands::SHOW_SAVE_PANEL.with(options));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(QUEUE_EXPORT_SAVE_AS) => {
+                let path = cmd.get_unchecked(QUEUE_EXPORT_SAVE_AS).path();
+                match QueueFile::new(&data.playback) {
+                    Some(file) => match file.write(path) {
+                        Ok(_) => data.info_alert("Queue exported."),
+                        Err(err) => {
+                            data.error_alert(format!("Failed to export the queue: {}", err))
+                        }
+                    },
+                    None => data.info_alert("There are no tracks in the queue."),
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::IMPORT_QUEUE) => {
+                let options = FileDialogOptions::new()
+                    .allowed_types(vec![FileSpec::new("JSON", &["json"])])
+                    .accept_command(QUEUE_IMPORT_OPEN);
+                ctx.submit_command(commands::SHOW_OPEN_PANEL.with(options));
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(QUEUE_IMPORT_OPEN) => {
+                let path = cmd.get_unchecked(QUEUE_IMPORT_OPEN).path().to_owned();
+                Self::load_queue_file(path, ctx.get_external_handle(), ctx.widget_id());
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(QUEUE_IMPORTED) => {
+                match cmd.get_unchecked(QUEUE_IMPORTED) {
+                    Ok((file, tracks, user_tracks)) => {
+                        self.import_queue(data, file, tracks, user_tracks);
+                    }
+                    Err(err) => {
+                        data.error_alert(format!("Failed to import the queue: {}", err));
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::LISTEN_ALONG_HOST) => {
+                self.host_listen_along(data);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::LISTEN_ALONG_JOIN) => {
+                self.start_following(ctx, data);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::LISTEN_ALONG_LEAVE) => {
+                self.leave_listen_along();
+                data.playback.listen_along.mode = ListenAlongMode::Off;
+                data.integrations.listen_along.set_off();
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::RECONNECT_INTEGRATION) => {
+                match cmd.get_unchecked(cmd::RECONNECT_INTEGRATION) {
+                    Integration::MediaControls => {
+                        self.reconnect_media_controls(data, ctx.window());
+                    }
+                    Integration::ListenAlong => match self.listen_along_attempt {
+                        ListenAlongMode::Hosting => self.host_listen_along(data),
+                        ListenAlongMode::Following => self.start_following(ctx, data),
+                        ListenAlongMode::Off => {}
+                    },
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(MEDIA_CONTROLS_OPENED) => {
+                match cmd.get_unchecked(MEDIA_CONTROLS_OPENED) {
+                    Some(err) => data.integrations.media_controls.set_failed(err),
+                    None => data
+                        .integrations
+                        .media_controls
+                        .set_connected(MEDIA_CONTROLS_STATUS),
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(LISTEN_ALONG_JOINED) => {
+                if let Some(follower) = cmd.get_unchecked(LISTEN_ALONG_JOINED).take() {
+                    if data.playback.listen_along.mode == ListenAlongMode::Following {
+                        data.info_alert("Listening along.");
+                        data.integrations.listen_along.set_connected(format!(
+                            "Listening along with {}",
+                            data.playback.listen_along.address.trim()
+                        ));
+                        data.session
+                            .activity()
+                            .record(SessionActivity::ListenAlongStarted {
+                                address: data.playback.listen_along.address.trim().to_string(),
+                            });
+                        if let Some(previous) = self.listen_along_follower.replace(follower) {
+                            previous.leave();
+                        }
+                    } else {
+                        // Left before the connection got established.
+                        follower.leave();
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(LISTEN_ALONG_UPDATE) => {
+                let (playback, received) = cmd.get_unchecked(LISTEN_ALONG_UPDATE);
+                if data.playback.listen_along.mode == ListenAlongMode::Following {
+                    self.follow_playback(ctx, data, playback, *received);
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(LISTEN_ALONG_TRACK_LOADED) => {
+                let (item_id, result, playback, received) =
+                    cmd.get_unchecked(LISTEN_ALONG_TRACK_LOADED);
+                if self.listen_along_pending == Some(*item_id) {
+                    self.listen_along_pending = None;
+                    match result {
+                        Ok(track) => {
+                            data.playback.queue = Vector::unit(QueueEntry {
+                                item: Playable::Track(track.to_owned()),
+                                origin: PlaybackOrigin::Library,
+                            });
+                            self.restoring = Some((
+                                *item_id,
+                                playback.position_after(received.elapsed()),
+                                playback.paused,
+                            ));
+                            self.play(&data.playback.queue, 0);
+                        }
+                        Err(err) => {
+                            log::error!("failed to load the listen-along track: {}", err);
+                        }
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(LISTEN_ALONG_ENDED) => {
+                let error = cmd.get_unchecked(LISTEN_ALONG_ENDED);
+                if data.playback.listen_along.mode == ListenAlongMode::Following {
+                    self.leave_listen_along();
+                    data.playback.listen_along.mode = ListenAlongMode::Off;
+                    data.session
+                        .activity()
+                        .record(SessionActivity::ListenAlongEnded {
+                            error: error.clone(),
+                        });
+                    match error {
+                        Some(err) => {
+                            data.error_alert(format!("Failed to listen along: {}", err));
+                            data.integrations.listen_along.set_failed(err);
+                        }
+                        None => {
+                            data.info_alert("The listen-along session has ended.");
+                            data.integrations.listen_along.set_off();
+                        }
+                    }
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::TOGGLE_PRIVATE_SESSION) => {
+                data.playback.private_session = !data.playback.private_session;
+                if data.playback.private_session {
+                    data.info_alert("Private session started, listening history is paused.");
+                } else {
+                    data.info_alert("Private session ended.");
+                }
                 ctx.set_handled();
             }
             Event::Command(cmd) if cmd.is(cmd::PLAY_PAUSE) => {
@@ -393,7 +2088,23 @@ where
             Event::Command(cmd) if cmd.is(cmd::PLAY_QUEUE_BEHAVIOR) => {
                 let behavior = cmd.get_unchecked(cmd::PLAY_QUEUE_BEHAVIOR);
                 data.set_queue_behavior(behavior.to_owned());
-                self.set_queue_behavior(behavior.to_owned());
+                self.set_queue_behavior(behavior.to_owned(), data.config.smart_shuffle);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::REMEMBER_CONTEXT_QUEUE_BEHAVIOR) => {
+                let behavior = cmd.get_unchecked(cmd::REMEMBER_CONTEXT_QUEUE_BEHAVIOR);
+                let origin = data.playback.now_playing.as_ref().map(|np| &np.origin);
+                if let Some(uri) = origin.and_then(|origin| origin.context_uri()) {
+                    match behavior {
+                        Some(behavior) => {
+                            data.config.context_queue_behavior.insert(uri, *behavior);
+                        }
+                        None => {
+                            data.config.context_queue_behavior.remove(&uri);
+                        }
+                    }
+                    data.config.save();
+                }
                 ctx.set_handled();
             }
             Event::Command(cmd) if cmd.is(cmd::PLAY_SEEK) => {
@@ -406,27 +2117,83 @@ where
                 }
                 ctx.set_handled();
             }
-            // Keyboard shortcuts.
-            Event::KeyDown(key) if key.code == Code::Space => {
+            Event::Command(cmd) if cmd.is(cmd::PLAY_SEEK_TO) => {
+                if let Some(now_playing) = &data.playback.now_playing {
+                    let position = cmd.get_unchecked(cmd::PLAY_SEEK_TO);
+                    self.seek(position.to_owned().min(now_playing.item.duration()));
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::PLAY_PREVIOUS_CHAPTER) => {
+                self.seek_chapter(data, false);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::PLAY_NEXT_CHAPTER) => {
+                self.seek_chapter(data, true);
+                ctx.set_handled();
+            }
+            // Keyboard shortcuts, unless typing into an input in the playback bar.
+            Event::KeyDown(key) if ctx.is_focused() && key.code == Code::Space => {
                 self.pause_or_resume();
                 ctx.set_handled();
             }
-            Event::KeyDown(key) if key.code == Code::ArrowRight => {
+            Event::KeyDown(key) if ctx.is_focused() && key.code == Code::ArrowRight => {
                 self.next();
                 ctx.set_handled();
             }
-            Event::KeyDown(key) if key.code == Code::ArrowLeft => {
+            Event::KeyDown(key) if ctx.is_focused() && key.code == Code::ArrowLeft => {
                 self.previous();
                 ctx.set_handled();
             }
-            Event::KeyDown(key) if key.key == KbKey::Character("+".to_string()) => {
+            Event::KeyDown(key)
+                if ctx.is_focused() && key.key == KbKey::Character("+".to_string()) =>
+            {
                 data.playback.volume = (data.playback.volume + 0.1).min(1.0);
                 ctx.set_handled();
             }
-            Event::KeyDown(key) if key.key == KbKey::Character("-".to_string()) => {
+            Event::KeyDown(key)
+                if ctx.is_focused() && key.key == KbKey::Character("-".to_string()) =>
+            {
                 data.playback.volume = (data.playback.volume - 0.1).max(0.0);
                 ctx.set_handled();
             }
+            Event::KeyDown(key)
+                if ctx.is_focused() && key.key == KbKey::Character(",".to_string()) =>
+            {
+                self.seek_chapter(data, false);
+                ctx.set_handled();
+            }
+            Event::KeyDown(key)
+                if ctx.is_focused() && key.key == KbKey::Character(".".to_string()) =>
+            {
+                self.seek_chapter(data, true);
+                ctx.set_handled();
+            }
+            Event::KeyDown(key)
+                if ctx.is_focused() && key.key == KbKey::Character("[".to_string()) =>
+            {
+                self.set_loop_start(data);
+                ctx.set_handled();
+            }
+            Event::KeyDown(key)
+                if ctx.is_focused() && key.key == KbKey::Character("]".to_string()) =>
+            {
+                self.set_loop_end(data);
+                ctx.set_handled();
+            }
+            Event::KeyDown(key)
+                if ctx.is_focused() && key.key == KbKey::Character("\\".to_string()) =>
+            {
+                self.clear_loop(data);
+                ctx.set_handled();
+            }
+            Event::WindowDisconnected => {
+                // The playback ends with the main window, report where it got to.
+                if let Some(reporter) = &self.reporter {
+                    reporter.close();
+                }
+                child.event(ctx, event, data, env);
+            }
             //
             _ => child.event(ctx, event, data, env),
         }
@@ -445,14 +2212,28 @@ where
                 self.open_audio_output_and_start_threads(
                     data.session.clone(),
                     data.config.playback(),
+                    data.config.attach_to_daemon,
                     ctx.get_external_handle(),
                     ctx.widget_id(),
                     ctx.window(),
                 );
+                self.update_reporting(data);
+
+                // Initialize values loaded from the config.  The daemon keeps its own
+                // volume, and the playback it's in the middle of.
+                if !self.attached_to_daemon {
+                    self.set_volume(data.playback.volume);
+                }
+                self.set_queue_behavior(data.playback.queue_behavior, data.config.smart_shuffle);
 
-                // Initialize values loaded from the config.
-                self.set_volume(data.playback.volume);
-                self.set_queue_behavior(data.playback.queue_behavior);
+                // Pick up where the last session ended, if configured to.
+                if !self.attached_to_daemon
+                    && data.config.startup_playback != StartupPlayback::Stopped
+                {
+                    if let Some(last) = data.config.last_playback.clone() {
+                        Self::load_last_playback(last, ctx.get_external_handle(), ctx.widget_id());
+                    }
+                }
 
                 // Request focus so we can receive keyboard events.
                 ctx.submit_command(cmd::SET_FOCUS.to(ctx.widget_id()));
@@ -478,6 +2259,36 @@ where
         if !old_data.playback.volume.same(&data.playback.volume) {
             self.set_volume(data.playback.volume);
         }
+        if !old_data
+            .library
+            .saved_tracks
+            .same(&data.library.saved_tracks)
+            && Self::is_playing_liked_songs(data)
+        {
+            ctx.submit_command(SYNC_LIKED_SONGS.to(ctx.widget_id()));
+        }
+        if old_data.playback.private_session != data.playback.private_session
+            || old_data.config.report_playback != data.config.report_playback
+        {
+            self.update_reporting(data);
+        }
+        if old_data.playback.private_session != data.playback.private_session {
+            if let Some(host) = self.listen_along_host.lock().as_ref() {
+                host.set_private(data.playback.private_session);
+            }
+        }
+        if !old_data
+            .config
+            .smart_shuffle
+            .same(&data.config.smart_shuffle)
+        {
+            self.set_queue_behavior(data.playback.queue_behavior, data.config.smart_shuffle);
+        }
+        if old_data.config.up_next_lead_secs != data.config.up_next_lead_secs {
+            self.send(PlayerEvent::Command(PlayerCommand::Configure {
+                config: data.config.playback(),
+            }));
+        }
         child.update(ctx, old_data, data, env);
     }
 }
@@ -0,0 +1,339 @@
+use std::{collections::HashSet, mem, sync::Arc, thread, time::Duration};
+
+use druid::{
+    im::Vector,
+    widget::{prelude::*, Controller},
+    Selector, TimerToken,
+};
+
+use crate::{
+    data::{AppState, Track, TrackId, TrackMetadata, TrackPrefetch},
+    error::Error,
+    ui::{library, playable::PlayRow},
+    webapi,
+};
+
+/// Rows come into view one after another while scrolling, gather them up for
+/// this long so they are loaded in a few requests.
+const BATCH_DELAY: Duration = Duration::from_millis(150);
+
+/// Result of a batch, with the IDs it was for.
+struct Prefetched {
+    details: Vec<TrackId>,
+    tracks: Result<Vector<Arc<Track>>, Error>,
+    saved: Vec<TrackId>,
+    is_saved: Result<Vec<bool>, Error>,
+}
+
+/// Loads the metadata the track rows in view ask for with `PrefetchRow`, in
+/// batches, into `CommonCtx::track_metadata`.
+pub struct PrefetchController {
+    details: Vec<TrackId>,
+    saved: Vec<TrackId>,
+    /// Tracks in a batch that hasn't loaded yet, so they aren't asked for twice.
+    /// Afterwards `TrackMetadata` tells what's loaded, tracks evicted from it or
+    /// failed to load are asked for again when their rows come into view.
+    loading_details: HashSet<TrackId>,
+    loading_saved: HashSet<TrackId>,
+    timer: TimerToken,
+}
+
+impl PrefetchController {
+    pub const REQUEST: Selector<TrackPrefetch> = Selector::new("app.prefetch.request");
+    const LOADED: Selector<Prefetched> = Selector::new("app.prefetch.loaded");
+
+    pub fn new() -> Self {
+        Self {
+            details: Vec::new(),
+            saved: Vec::new(),
+            loading_details: HashSet::new(),
+            loading_saved: HashSet::new(),
+            timer: TimerToken::INVALID,
+        }
+    }
+
+    /// Add what `request` asks for to the next batch, unless it's loaded or
+    /// loading already.  Returns whether the next batch has anything to load.
+    fn request(&mut self, request: &TrackPrefetch, metadata: &TrackMetadata) -> bool {
+        if request.details
+            && !metadata.contains_details(&request.id)
+            && self.loading_details.insert(request.id)
+        {
+            self.details.push(request.id);
+        }
+        if request.saved
+            && !metadata.saved.contains_key(&request.id)
+            && self.loading_saved.insert(request.id)
+        {
+            self.saved.push(request.id);
+        }
+        !self.details.is_empty() || !self.saved.is_empty()
+    }
+
+    fn loaded(&mut self, loaded: &Prefetched, metadata: &mut TrackMetadata) {
+        for id in &loaded.details {
+            self.loading_details.remove(id);
+        }
+        for id in &loaded.saved {
+            self.loading_saved.remove(id);
+        }
+        match &loaded.tracks {
+            Ok(tracks) => {
+                for track in tracks {
+                    metadata.insert_details(track.clone());
+                }
+            }
+            Err(err) => log::warn!("failed to prefetch tracks: {}", err),
+        }
+        match &loaded.is_saved {
+            Ok(is_saved) => metadata
+                .saved
+                .extend(loaded.saved.iter().copied().zip(is_saved.iter().copied())),
+            Err(err) => log::warn!("failed to prefetch saved tracks: {}", err),
+        }
+    }
+
+    fn load(&mut self, ctx: &mut EventCtx) {
+        let details = mem::take(&mut self.details);
+        let saved = mem::take(&mut self.saved);
+        let widget_id = ctx.widget_id();
+        let event_sink = ctx.get_external_handle();
+        thread::spawn(move || {
+            let tracks = if details.is_empty() {
+                Ok(Vector::new())
+            } else {
+                webapi::global().get_tracks(&details)
+            };
+            let is_saved = if saved.is_empty() {
+                Ok(Vec::new())
+            } else {
+                webapi::global().contains_saved_tracks(&saved)
+            };
+            let loaded = Prefetched {
+                details,
+                tracks,
+                saved,
+                is_saved,
+            };
+            event_sink
+                .submit_command(Self::LOADED, loaded, widget_id)
+                .unwrap();
+        });
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for PrefetchController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(Self::REQUEST) => {
+                let request = cmd.get_unchecked(Self::REQUEST);
+                let is_pending = self.request(request, &data.common_ctx.track_metadata);
+                if is_pending && self.timer == TimerToken::INVALID {
+                    self.timer = ctx.request_timer(BATCH_DELAY);
+                }
+                ctx.set_handled();
+            }
+            Event::Timer(token) if *token == self.timer => {
+                self.timer = TimerToken::INVALID;
+                self.load(ctx);
+            }
+            Event::Command(cmd) if cmd.is(Self::LOADED) => {
+                let loaded = cmd.get_unchecked(Self::LOADED);
+                self.loaded(loaded, &mut data.common_ctx_mut().track_metadata);
+                ctx.set_handled();
+            }
+            // Not handled here, only keeping the prefetched state in line with
+            // the library.
+            Event::Command(cmd) if cmd.is(library::SAVE_TRACK) => {
+                let id = cmd.get_unchecked(library::SAVE_TRACK).id;
+                data.common_ctx_mut().track_metadata.saved.insert(id, true);
+            }
+            Event::Command(cmd) if cmd.is(library::UNSAVE_TRACK) => {
+                let id = *cmd.get_unchecked(library::UNSAVE_TRACK);
+                data.common_ctx_mut().track_metadata.saved.insert(id, false);
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env);
+    }
+}
+
+/// Asks `PrefetchController` for the metadata the track row is missing, once the
+/// row is scrolled into view.
+pub struct PrefetchRow {
+    popularity: bool,
+    is_visible: bool,
+    requested: bool,
+}
+
+impl PrefetchRow {
+    pub fn new(popularity: bool) -> Self {
+        Self {
+            popularity,
+            is_visible: false,
+            requested: false,
+        }
+    }
+
+    fn missing(&self, row: &PlayRow<Arc<Track>>) -> Option<TrackPrefetch> {
+        row.ctx
+            .track_metadata
+            .missing(&row.item, &row.ctx.library, self.popularity)
+    }
+}
+
+impl<W: Widget<PlayRow<Arc<Track>>>> Controller<PlayRow<Arc<Track>>, W> for PrefetchRow {
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &PlayRow<Arc<Track>>,
+        env: &Env,
+    ) {
+        if let LifeCycle::ViewContextChanged(view) = event {
+            self.is_visible = view.clip.intersect(ctx.size().to_rect()).area() > 0.0;
+            if self.is_visible && !self.requested {
+                self.requested = true;
+                if let Some(missing) = self.missing(data) {
+                    ctx.submit_command(PrefetchController::REQUEST.with(missing));
+                }
+            }
+        }
+        child.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &PlayRow<Arc<Track>>,
+        data: &PlayRow<Arc<Track>>,
+        env: &Env,
+    ) {
+        if !old_data.item.same(&data.item) {
+            // The row shows another track now, e.g. after sorting.
+            self.requested = self.is_visible;
+            if self.is_visible {
+                if let Some(missing) = self.missing(data) {
+                    ctx.submit_command(PrefetchController::REQUEST.with(missing));
+                }
+            }
+        }
+        child.update(ctx, old_data, data, env);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn track(number: usize) -> Arc<Track> {
+        let track = json!({
+            "id": format!("{:0>22}", number),
+            "name": format!("Track {}", number),
+            "album": null,
+            "artists": [],
+            "duration_ms": 180_000,
+            "disc_number": 1,
+            "track_number": number,
+            "explicit": false,
+            "is_local": false,
+            "is_playable": true,
+            "popularity": null,
+        });
+        Arc::new(serde_json::from_value(track).unwrap())
+    }
+
+    fn request(number: usize) -> TrackPrefetch {
+        TrackPrefetch {
+            id: track(number).id,
+            details: true,
+            saved: true,
+        }
+    }
+
+    /// Take the next batch, as the timer would.
+    fn batch(controller: &mut PrefetchController) -> (Vec<TrackId>, Vec<TrackId>) {
+        (
+            mem::take(&mut controller.details),
+            mem::take(&mut controller.saved),
+        )
+    }
+
+    /// Batch loading the details of `numbers`, all of them saved.
+    fn loaded(numbers: &[usize]) -> Prefetched {
+        let ids: Vec<_> = numbers.iter().map(|&number| track(number).id).collect();
+        Prefetched {
+            details: ids.clone(),
+            tracks: Ok(numbers.iter().map(|&number| track(number)).collect()),
+            is_saved: Ok(vec![true; ids.len()]),
+            saved: ids,
+        }
+    }
+
+    #[test]
+    fn tracks_are_not_requested_twice_while_loading() {
+        let mut controller = PrefetchController::new();
+        let metadata = TrackMetadata::default();
+        assert!(controller.request(&request(1), &metadata));
+        assert_eq!(
+            batch(&mut controller),
+            (vec![track(1).id], vec![track(1).id])
+        );
+        assert!(!controller.request(&request(1), &metadata));
+    }
+
+    #[test]
+    fn loaded_tracks_are_not_requested_again() {
+        let mut controller = PrefetchController::new();
+        let mut metadata = TrackMetadata::default();
+        controller.request(&request(1), &metadata);
+        batch(&mut controller);
+        controller.loaded(&loaded(&[1]), &mut metadata);
+        assert!(metadata.contains_details(&track(1).id));
+        assert_eq!(metadata.saved.get(&track(1).id), Some(&true));
+        assert!(!controller.request(&request(1), &metadata));
+    }
+
+    #[test]
+    fn evicted_tracks_are_requested_again() {
+        let mut controller = PrefetchController::new();
+        let mut metadata = TrackMetadata::default();
+        controller.request(&request(1), &metadata);
+        batch(&mut controller);
+        controller.loaded(&loaded(&[1]), &mut metadata);
+        metadata.set_limit(Some(0));
+        assert!(controller.request(&request(1), &metadata));
+        assert_eq!(batch(&mut controller), (vec![track(1).id], vec![]));
+    }
+
+    #[test]
+    fn failed_tracks_are_requested_again() {
+        let mut controller = PrefetchController::new();
+        let mut metadata = TrackMetadata::default();
+        controller.request(&request(1), &metadata);
+        let (details, saved) = batch(&mut controller);
+        let failed = Prefetched {
+            details,
+            tracks: Err(Error::WebApiError("failed".into())),
+            saved,
+            is_saved: Err(Error::WebApiError("failed".into())),
+        };
+        controller.loaded(&failed, &mut metadata);
+        assert!(controller.request(&request(1), &metadata));
+        assert_eq!(
+            batch(&mut controller),
+            (vec![track(1).id], vec![track(1).id])
+        );
+    }
+}
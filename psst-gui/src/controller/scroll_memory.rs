@@ -0,0 +1,127 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use druid::widget::{prelude::*, Axis, Controller, Scroll};
+
+use crate::data::{AppState, Nav, Route};
+
+/// Scroll positions of the pages, shared by the scroll widgets of all routes, as
+/// the widget of a route is dropped when navigating to another one.
+#[derive(Clone, Default)]
+pub struct ScrollOffsets(Rc<RefCell<HashMap<Nav, f64>>>);
+
+impl ScrollOffsets {
+    fn get(&self, nav: &Nav) -> f64 {
+        self.0.borrow().get(nav).copied().unwrap_or(0.0)
+    }
+
+    fn set(&self, nav: &Nav, offset: f64) {
+        self.0.borrow_mut().insert(nav.clone(), offset);
+    }
+}
+
+/// Remembers how far each page of a route was scrolled, and scrolls back there
+/// when the page is shown again.  Pages seen for the first time start at the
+/// top, instead of wherever the previous page of the route was left.
+pub struct ScrollMemory {
+    route: Route,
+    offsets: ScrollOffsets,
+    /// Offset to scroll to once the page has grown tall enough, pages are
+    /// usually still loading when navigated to.
+    pending: Option<f64>,
+}
+
+impl ScrollMemory {
+    pub fn new(route: Route, offsets: ScrollOffsets) -> Self {
+        Self {
+            route,
+            offsets,
+            pending: None,
+        }
+    }
+
+    fn restore<W: Widget<AppState>>(
+        &mut self,
+        scroll: &mut Scroll<AppState, W>,
+        ctx: &mut EventCtx,
+    ) {
+        if let Some(offset) = self.pending {
+            let max_offset = (scroll.child_size().height - ctx.size().height).max(0.0);
+            scroll.scroll_to_on_axis(ctx, Axis::Vertical, offset.min(max_offset));
+            if max_offset >= offset {
+                self.pending = None;
+            }
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, Scroll<AppState, W>> for ScrollMemory {
+    fn event(
+        &mut self,
+        child: &mut Scroll<AppState, W>,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::AnimFrame(_) => {
+                self.restore(child, ctx);
+            }
+            Event::Wheel(_) | Event::MouseDown(_) | Event::KeyDown(_) => {
+                // Don't fight the user over the position.
+                self.pending = None;
+            }
+            _ => {}
+        }
+        let offset = child.offset().y;
+        child.event(ctx, event, data, env);
+        // Record the position as it changes, there's no telling whether the next
+        // navigation drops this widget before it sees it.
+        if self.pending.is_none() && child.offset().y != offset && data.nav.route() == self.route {
+            self.offsets.set(&data.nav, child.offset().y);
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut Scroll<AppState, W>,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            let offset = self.offsets.get(&data.nav);
+            if offset > 0.0 {
+                self.pending = Some(offset);
+                ctx.request_anim_frame();
+            }
+        }
+        child.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(
+        &mut self,
+        child: &mut Scroll<AppState, W>,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if old_data.nav != data.nav {
+            if old_data.nav.route() == self.route && self.pending.is_none() {
+                self.offsets.set(&old_data.nav, child.offset().y);
+            }
+            self.pending = if data.nav.route() == self.route {
+                Some(self.offsets.get(&data.nav))
+            } else {
+                None
+            };
+        }
+        child.update(ctx, old_data, data, env);
+        if self.pending.is_some() {
+            // Try again after the new data is laid out.
+            ctx.request_anim_frame();
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use druid::{
+    commands,
+    widget::{prelude::*, Controller, Label, LineBreaking},
+    Point, TimerToken, Vec2, WidgetExt, WindowConfig, WindowId, WindowLevel, WindowSizePolicy,
+};
+
+use crate::ui::theme;
+
+/// How long the pointer has to rest on the widget before the tooltip shows up.
+const DELAY: Duration = Duration::from_millis(600);
+
+/// Shows a small window with a text next to the pointer while it rests on the
+/// widget.  Nothing shows up if the text is `None`.
+pub struct Tooltip<T> {
+    text: Box<dyn Fn(&T) -> Option<String>>,
+    timer: TimerToken,
+    position: Point,
+    window: Option<WindowId>,
+}
+
+impl<T> Tooltip<T> {
+    pub fn new(text: impl Fn(&T) -> Option<String> + 'static) -> Self {
+        Self {
+            text: Box::new(text),
+            timer: TimerToken::INVALID,
+            position: Point::ZERO,
+            window: None,
+        }
+    }
+
+    fn show(&mut self, ctx: &mut EventCtx, text: String, env: &Env) {
+        let label = Label::new(text)
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .padding(theme::grid(0.5))
+            .background(theme::BACKGROUND_DARK)
+            .border(theme::GREY_500, 1.0);
+        let config = WindowConfig::default()
+            .show_titlebar(false)
+            .resizable(false)
+            .window_size_policy(WindowSizePolicy::Content)
+            .set_level(WindowLevel::Tooltip(ctx.window().clone()))
+            // Below the pointer, so it doesn't cover what it describes.
+            .set_position(self.position + Vec2::new(0.0, theme::grid(2.5)));
+        self.window = Some(ctx.new_sub_window(config, label, (), env.clone()));
+    }
+}
+
+impl<T: Data, W: Widget<T>> Controller<T, W> for Tooltip<T> {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::MouseMove(mouse) if self.window.is_none() => {
+                self.position = mouse.window_pos;
+                self.timer = ctx.request_timer(DELAY);
+            }
+            Event::MouseDown(_) | Event::Wheel(_) => {
+                self.timer = TimerToken::INVALID;
+                if let Some(window) = self.window.take() {
+                    ctx.submit_command(commands::CLOSE_WINDOW.to(window));
+                }
+            }
+            Event::Timer(token) if token == &self.timer => {
+                self.timer = TimerToken::INVALID;
+                if ctx.is_hot() {
+                    if let Some(text) = (self.text)(data) {
+                        self.show(ctx, text, env);
+                    }
+                }
+                ctx.set_handled();
+                return;
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env);
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &T,
+        env: &Env,
+    ) {
+        if let LifeCycle::HotChanged(false) = event {
+            self.timer = TimerToken::INVALID;
+            if let Some(window) = self.window.take() {
+                ctx.submit_command(commands::CLOSE_WINDOW.to(window));
+            }
+        }
+        child.lifecycle(ctx, event, data, env);
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use druid::Data;
+use psst_core::session::activity::ActivityEntry;
+use time::{macros::format_description, OffsetDateTime};
+
+/// Entry of the session activity log, ready to be displayed.
+#[derive(Clone, Debug, Data)]
+pub struct ActivityRecord {
+    pub time: Arc<str>,
+    pub text: Arc<str>,
+}
+
+impl ActivityRecord {
+    /// Record of something happening right now.
+    pub fn now(text: impl Into<Arc<str>>) -> Self {
+        Self {
+            time: format_time(OffsetDateTime::now_utc()).into(),
+            text: text.into(),
+        }
+    }
+}
+
+impl From<&ActivityEntry> for ActivityRecord {
+    fn from(entry: &ActivityEntry) -> Self {
+        let time = OffsetDateTime::from_unix_timestamp(entry.timestamp as i64)
+            .ok()
+            .map(format_time)
+            .unwrap_or_default();
+        Self {
+            time: time.into(),
+            text: entry.activity.to_string().into(),
+        }
+    }
+}
+
+fn format_time(date: OffsetDateTime) -> String {
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute]:[second] UTC");
+    date.format(format).unwrap_or_default()
+}
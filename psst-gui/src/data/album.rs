@@ -1,14 +1,18 @@
 use std::sync::Arc;
 
 use druid::{im::Vector, Data, Lens};
+use itertools::Itertools;
+use psst_core::uri::SpotifyUri;
 use serde::{Deserialize, Serialize};
 use time::{formatting::Formattable, macros::format_description, Date};
 
-use crate::data::{ArtistLink, Cached, Image, Promise, Track};
+use crate::data::{ArtistLink, Cached, Image, MusicBrainzRelease, Promise, Track};
 
 #[derive(Clone, Data, Lens)]
 pub struct AlbumDetail {
     pub album: Promise<Cached<Arc<Album>>, AlbumLink>,
+    /// Looked up only on request, `None` if MusicBrainz doesn't know the album.
+    pub musicbrainz: Promise<Option<MusicBrainzRelease>, Arc<Album>>,
 }
 
 #[derive(Clone, Data, Lens, Deserialize)]
@@ -26,8 +30,13 @@ pub struct Album {
     #[serde(deserialize_with = "super::utils::deserialize_null_arc_str")]
     pub label: Arc<str>,
     #[serde(default)]
+    pub external_ids: AlbumExternalIds,
+    /// Only the first page of the tracks, see `total_tracks`.
+    #[serde(default)]
     #[serde(deserialize_with = "super::utils::deserialize_first_page")]
     pub tracks: Vector<Arc<Track>>,
+    #[serde(default)]
+    pub total_tracks: usize,
     #[serde(deserialize_with = "super::utils::deserialize_date_option")]
     #[data(same_fn = "PartialEq::eq")]
     pub release_date: Option<Date>,
@@ -36,6 +45,13 @@ pub struct Album {
 }
 
 impl Album {
+    pub fn artist_names(&self) -> String {
+        self.artists
+            .iter()
+            .map(|artist| artist.name.clone())
+            .join(", ")
+    }
+
     pub fn release(&self) -> String {
         self.release_with_format(match self.release_date_precision {
             Some(DatePrecision::Year) | None => format_description!("[year]"),
@@ -67,7 +83,7 @@ impl Album {
     }
 
     pub fn url(&self) -> String {
-        format!("https://open.spotify.com/album/{id}", id = self.id)
+        SpotifyUri::Album(self.id.clone()).to_url()
     }
 
     pub fn link(&self) -> AlbumLink {
@@ -83,6 +99,12 @@ impl Album {
     }
 }
 
+#[derive(Clone, Debug, Default, Data, Deserialize)]
+pub struct AlbumExternalIds {
+    /// Barcode of the release, used to find it in other catalogs.
+    pub upc: Option<Arc<str>>,
+}
+
 #[derive(Clone, Debug, Data, Lens, Eq, PartialEq, Hash, Deserialize, Serialize)]
 pub struct AlbumLink {
     pub id: Arc<str>,
@@ -134,3 +156,29 @@ pub enum CopyrightType {
     #[serde(rename = "P")]
     Performance,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::utils::load_fixture;
+
+    #[test]
+    fn deserializes_album_with_first_page_of_tracks() {
+        let album: Album = load_fixture("album.json");
+
+        assert_eq!(&*album.id, "4aawyAB9vmqN3uQ7FjRGTy");
+        assert_eq!(album.album_type, AlbumType::Compilation);
+        assert_eq!(album.artist_names(), "Various Artists");
+        assert_eq!(&*album.label, "");
+        assert_eq!(album.external_ids.upc.as_deref(), Some("886443671584"));
+        assert_eq!(album.copyrights.len(), 2);
+        assert_eq!(album.copyrights[1].kind, CopyrightType::Performance);
+        assert_eq!(album.release_date_precision, Some(DatePrecision::Month));
+        assert_eq!(album.release(), "November 2012");
+        assert_eq!(album.total_tracks, 18);
+        assert_eq!(album.tracks.len(), 2);
+        assert_eq!(album.tracks[1].track_number, 2);
+        assert!(album.tracks.iter().all(|track| track.album.is_none()));
+        assert!(album.has_explicit());
+    }
+}
@@ -1,16 +1,18 @@
 use std::sync::Arc;
 
 use druid::{im::Vector, Data, Lens};
+use psst_core::uri::SpotifyUri;
 use serde::{Deserialize, Serialize};
 
 use crate::data::{Album, Cached, Image, Promise, Track};
 
 #[derive(Clone, Data, Lens)]
 pub struct ArtistDetail {
-    pub artist: Promise<Artist, ArtistLink>,
+    pub artist: Promise<ArtistProfile, ArtistLink>,
     pub albums: Promise<ArtistAlbums, ArtistLink>,
     pub top_tracks: Promise<ArtistTracks, ArtistLink>,
     pub related_artists: Promise<Cached<Vector<Artist>>, ArtistLink>,
+    pub events: Promise<Vector<ArtistEvent>, ArtistLink>,
 }
 
 #[derive(Clone, Data, Lens, Deserialize)]
@@ -18,6 +20,15 @@ pub struct Artist {
     pub id: Arc<str>,
     pub name: Arc<str>,
     pub images: Vector<Image>,
+    #[serde(default)]
+    pub genres: Vector<Arc<str>>,
+    #[serde(default)]
+    pub followers: Followers,
+}
+
+#[derive(Clone, Copy, Default, Data, Lens, Deserialize)]
+pub struct Followers {
+    pub total: usize,
 }
 
 impl Artist {
@@ -33,6 +44,22 @@ impl Artist {
     }
 }
 
+/// Artist shown in the header of the detail page.
+#[derive(Clone, Data, Lens)]
+pub struct ArtistProfile {
+    pub artist: Artist,
+    pub is_followed: bool,
+}
+
+impl ArtistProfile {
+    pub fn followers_label(&self) -> String {
+        match self.artist.followers.total {
+            1 => "1 follower".to_string(),
+            n => format!("{} followers", n),
+        }
+    }
+}
+
 #[derive(Clone, Data, Lens)]
 pub struct ArtistAlbums {
     pub albums: Vector<Arc<Album>>,
@@ -65,6 +92,17 @@ pub struct ArtistLink {
 
 impl ArtistLink {
     pub fn url(&self) -> String {
-        format!("https://open.spotify.com/artist/{id}", id = self.id)
+        SpotifyUri::Artist(self.id.clone()).to_url()
     }
 }
+
+/// Upcoming concert of an artist, as listed by the configured events provider.
+#[derive(Clone, Debug, Data, Lens)]
+pub struct ArtistEvent {
+    /// Day of the event, as `YYYY-MM-DD`.
+    pub date: Arc<str>,
+    pub venue: Arc<str>,
+    pub location: Arc<str>,
+    /// Page with the details and tickets.
+    pub url: Arc<str>,
+}
@@ -0,0 +1,149 @@
+//! Registration of Psst in the platform's list of apps launched on login.
+
+use std::{env, io, path::Path};
+
+#[cfg(unix)]
+use std::{fs, path::PathBuf};
+
+const APP_ID: &str = "com.jpochyla.psst";
+
+pub fn set_launch_on_login(enabled: bool) -> io::Result<()> {
+    if enabled {
+        register(&env::current_exe()?)
+    } else {
+        unregister()
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn entry_path() -> io::Result<PathBuf> {
+    // https://specifications.freedesktop.org/autostart-spec/autostart-spec-latest.html
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+    Ok(config_home
+        .join("autostart")
+        .join(format!("{}.desktop", APP_ID)))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn register(exe: &Path) -> io::Result<()> {
+    let path = entry_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let entry = format!(
+        "[Desktop Entry]\n\
+         Type=Application\n\
+         Name=Psst\n\
+         Exec=\"{}\"\n\
+         X-GNOME-Autostart-enabled=true\n",
+        exe.display()
+    );
+    fs::write(path, entry)
+}
+
+#[cfg(target_os = "macos")]
+fn entry_path() -> io::Result<PathBuf> {
+    let home = env::var_os("HOME")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home directory"))?;
+    Ok(home
+        .join("Library")
+        .join("LaunchAgents")
+        .join(format!("{}.plist", APP_ID)))
+}
+
+#[cfg(target_os = "macos")]
+fn register(exe: &Path) -> io::Result<()> {
+    let path = entry_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let agent = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{}</string>
+    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#,
+        APP_ID,
+        exe.display()
+    );
+    fs::write(path, agent)
+}
+
+#[cfg(unix)]
+fn unregister() -> io::Result<()> {
+    match fs::remove_file(entry_path()?) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(windows)]
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+#[cfg(windows)]
+fn register(exe: &Path) -> io::Result<()> {
+    let value = format!("\"{}\"", exe.display());
+    reg(&[
+        "add", RUN_KEY, "/v", "Psst", "/t", "REG_SZ", "/d", &value, "/f",
+    ])
+}
+
+#[cfg(windows)]
+fn unregister() -> io::Result<()> {
+    // Deleting a missing value fails, so only delete if it's there.
+    if reg(&["query", RUN_KEY, "/v", "Psst"]).is_ok() {
+        reg(&["delete", RUN_KEY, "/v", "Psst", "/f"])
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+fn reg(args: &[&str]) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let status = Command::new("reg")
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .creation_flags(CREATE_NO_WINDOW)
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("reg {} failed: {}", args[0], status),
+        ))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn register(_exe: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "launching on login is not supported on this platform",
+    ))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn unregister() -> io::Result<()> {
+    Ok(())
+}
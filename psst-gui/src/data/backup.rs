@@ -0,0 +1,463 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use druid::{im::Vector, Data, Lens};
+use psst_core::cache::mkdir_if_not_exists;
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::data::{Config, Playlist, PlaylistLink, Track};
+
+const BACKUP_DIRNAME: &str = "playlist-backups";
+const SNAPSHOT_PREFIX: &str = "playlists-";
+const SNAPSHOT_EXTENSION: &str = ".json";
+
+/// Time between two automatic snapshots.
+pub const BACKUP_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Directory of JSON snapshots of the user's playlists.  Every snapshot is a
+/// separate file named after the moment it was taken, so the newest one tells
+/// when the next backup is due.
+#[derive(Clone, Debug)]
+pub struct PlaylistBackups {
+    dir: PathBuf,
+}
+
+impl PlaylistBackups {
+    pub fn open() -> Option<Self> {
+        Config::config_dir().map(|dir| Self {
+            dir: dir.join(BACKUP_DIRNAME),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Unix timestamps of the existing snapshots, oldest first.
+    fn snapshots(&self) -> io::Result<Vec<u64>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            let timestamp = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(SNAPSHOT_PREFIX))
+                .and_then(|name| name.strip_suffix(SNAPSHOT_EXTENSION))
+                .and_then(|timestamp| timestamp.parse().ok());
+            snapshots.extend(timestamp);
+        }
+        snapshots.sort_unstable();
+        Ok(snapshots)
+    }
+
+    fn snapshot_path(&self, timestamp: u64) -> PathBuf {
+        self.dir
+            .join(format!("{SNAPSHOT_PREFIX}{timestamp}{SNAPSHOT_EXTENSION}"))
+    }
+
+    pub fn is_due(&self) -> bool {
+        match self.snapshots() {
+            Ok(snapshots) => snapshots.last().map_or(true, |&latest| {
+                let taken_at = UNIX_EPOCH + Duration::from_secs(latest);
+                taken_at + BACKUP_INTERVAL <= SystemTime::now()
+            }),
+            Err(err) => {
+                log::warn!("failed to list playlist backups: {}", err);
+                false
+            }
+        }
+    }
+
+    /// The playlist with `id` from the newest snapshot that has it, and when that
+    /// snapshot was taken.
+    pub fn latest_of(&self, id: &str) -> io::Result<Option<(SystemTime, PlaylistRecord)>> {
+        for timestamp in self.snapshots()?.into_iter().rev() {
+            let file = File::open(self.snapshot_path(timestamp))?;
+            let snapshot: PlaylistSnapshot = serde_json::from_reader(BufReader::new(file))?;
+            let record = snapshot
+                .playlists
+                .into_iter()
+                .find(|playlist| playlist.id.as_ref() == id);
+            if let Some(record) = record {
+                let taken_at = UNIX_EPOCH + Duration::from_secs(timestamp);
+                return Ok(Some((taken_at, record)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Write a new snapshot and remove all but the `keep` newest ones.
+    pub fn write(&self, snapshot: &PlaylistSnapshot, keep: usize) -> io::Result<PathBuf> {
+        mkdir_if_not_exists(&self.dir)?;
+        let path = self.snapshot_path(snapshot.timestamp);
+        let mut writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(&mut writer, snapshot)?;
+        writer.flush()?;
+        log::info!("saved playlist backup: {:?}", path);
+
+        let snapshots = self.snapshots()?;
+        let excess = snapshots.len().saturating_sub(keep.max(1));
+        for &timestamp in &snapshots[..excess] {
+            fs::remove_file(self.snapshot_path(timestamp))?;
+        }
+        Ok(path)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlaylistSnapshot {
+    #[serde(skip)]
+    timestamp: u64,
+    created_at: String,
+    playlists: Vec<PlaylistRecord>,
+}
+
+impl PlaylistSnapshot {
+    pub fn now() -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        let created_at = OffsetDateTime::from_unix_timestamp(timestamp as i64)
+            .ok()
+            .and_then(|date| date.format(&Rfc3339).ok())
+            .unwrap_or_default();
+        Self {
+            timestamp,
+            created_at,
+            playlists: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, playlist: &Playlist, tracks: &Vector<Arc<Track>>) {
+        self.playlists.push(PlaylistRecord {
+            id: playlist.id.clone(),
+            name: playlist.name.clone(),
+            description: playlist.description.clone(),
+            owner: playlist.owner.display_name.clone(),
+            collaborative: playlist.collaborative,
+            tracks: tracks
+                .iter()
+                .map(|track| TrackRecord::from(&**track))
+                .collect(),
+        });
+    }
+
+    pub fn playlist_count(&self) -> usize {
+        self.playlists.len()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlaylistRecord {
+    id: Arc<str>,
+    name: Arc<str>,
+    description: Arc<str>,
+    owner: Arc<str>,
+    collaborative: bool,
+    tracks: Vec<TrackRecord>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TrackRecord {
+    /// Missing for local files.
+    uri: Option<String>,
+    name: Arc<str>,
+    artist: String,
+    album: Arc<str>,
+    duration_ms: u64,
+}
+
+impl From<&Track> for TrackRecord {
+    fn from(track: &Track) -> Self {
+        Self {
+            uri: track.id.0.to_uri(),
+            name: track.name.clone(),
+            artist: track.artist_names(),
+            album: track.album_name(),
+            duration_ms: track.duration.as_millis() as u64,
+        }
+    }
+}
+
+/// Changes restoring a playlist from its backup would make, to be confirmed
+/// before they are applied.
+#[derive(Clone, Debug, Data, Lens)]
+pub struct PlaylistRestore {
+    pub link: PlaylistLink,
+    /// Age of the backup.
+    pub age: Duration,
+    /// Version of the playlist the changes were computed against, the restore is
+    /// refused if the playlist changed since.
+    pub snapshot_id: Option<Arc<str>>,
+    /// Items of the playlist after the restore.
+    pub uris: Vector<Arc<str>>,
+    pub added: Vector<DiffTrack>,
+    pub removed: Vector<DiffTrack>,
+    pub moved: Vector<DiffTrack>,
+    /// Local files in the backup, which can't be added back through the Web API.
+    pub skipped_local: usize,
+}
+
+#[derive(Clone, Debug, Data, Lens)]
+pub struct DiffTrack {
+    pub name: Arc<str>,
+    pub artist: Arc<str>,
+}
+
+impl DiffTrack {
+    fn new(record: &TrackRecord) -> Self {
+        Self {
+            name: record.name.clone(),
+            artist: record.artist.as_str().into(),
+        }
+    }
+}
+
+impl PlaylistRestore {
+    pub fn new(
+        playlist: &Playlist,
+        tracks: &Vector<Arc<Track>>,
+        taken_at: SystemTime,
+        backup: &PlaylistRecord,
+    ) -> Self {
+        let current: Vec<TrackRecord> = tracks
+            .iter()
+            .map(|track| TrackRecord::from(&**track))
+            .collect();
+
+        // Pair the backed up tracks with the current ones, duplicates in order.
+        let mut positions: HashMap<&str, VecDeque<usize>> = HashMap::new();
+        for (index, record) in current.iter().enumerate() {
+            if let Some(uri) = &record.uri {
+                positions.entry(uri).or_default().push_back(index);
+            }
+        }
+        let mut uris = Vector::new();
+        let mut added = Vector::new();
+        let mut kept = Vec::new();
+        let mut skipped_local = 0;
+        for record in &backup.tracks {
+            let uri = match &record.uri {
+                Some(uri) => uri,
+                None => {
+                    skipped_local += 1;
+                    continue;
+                }
+            };
+            uris.push_back(Arc::from(uri.as_str()));
+            match positions
+                .get_mut(uri.as_str())
+                .and_then(VecDeque::pop_front)
+            {
+                Some(index) => kept.push(index),
+                None => added.push_back(DiffTrack::new(record)),
+            }
+        }
+
+        let mut is_kept = vec![false; current.len()];
+        for &index in &kept {
+            is_kept[index] = true;
+        }
+        let removed = current
+            .iter()
+            .zip(&is_kept)
+            .filter(|(_, &is_kept)| !is_kept)
+            .map(|(record, _)| DiffTrack::new(record))
+            .collect();
+
+        // The longest run of tracks already in the right order stays in place,
+        // everything else in both lists counts as moved.
+        let mut in_place = vec![false; current.len()];
+        for index in longest_increasing_subsequence(&kept) {
+            in_place[kept[index]] = true;
+        }
+        let moved = kept
+            .iter()
+            .filter(|&&index| !in_place[index])
+            .map(|&index| DiffTrack::new(&current[index]))
+            .collect();
+
+        Self {
+            link: playlist.link(),
+            age: taken_at.elapsed().unwrap_or_default(),
+            snapshot_id: playlist.snapshot_id.clone(),
+            uris,
+            added,
+            removed,
+            moved,
+            skipped_local,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+/// Indices into `values` of one of their longest strictly increasing
+/// subsequences.
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+    // Index of the smallest tail of all increasing subsequences of each length.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+    for (index, &value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&tail| values[tail] < value);
+        previous[index] = length.checked_sub(1).map(|length| tails[length]);
+        if length == tails.len() {
+            tails.push(index);
+        } else {
+            tails[length] = index;
+        }
+    }
+    let mut result = Vec::with_capacity(tails.len());
+    let mut next = tails.last().copied();
+    while let Some(index) = next {
+        result.push(index);
+        next = previous[index];
+    }
+    result.reverse();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn track(number: usize) -> Arc<Track> {
+        let track = json!({
+            "id": format!("{:0>22}", number),
+            "name": format!("Track {}", number),
+            "album": null,
+            "artists": [],
+            "duration_ms": 180_000,
+            "disc_number": 1,
+            "track_number": number,
+            "explicit": false,
+            "is_local": false,
+            "is_playable": true,
+            "popularity": null,
+        });
+        Arc::new(serde_json::from_value(track).unwrap())
+    }
+
+    fn tracks(numbers: &[usize]) -> Vector<Arc<Track>> {
+        numbers.iter().map(|&number| track(number)).collect()
+    }
+
+    fn playlist() -> Playlist {
+        let playlist = json!({
+            "id": "playlist",
+            "name": "Playlist",
+            "images": [],
+            "description": "",
+            "tracks": { "total": 0 },
+            "owner": { "id": "user", "display_name": "User" },
+            "collaborative": false,
+            "snapshot_id": "snapshot",
+        });
+        serde_json::from_value(playlist).unwrap()
+    }
+
+    fn backup(numbers: &[usize]) -> PlaylistRecord {
+        let mut snapshot = PlaylistSnapshot::now();
+        snapshot.add(&playlist(), &tracks(numbers));
+        snapshot.playlists.remove(0)
+    }
+
+    fn restore(current: &[usize], backup: &PlaylistRecord) -> PlaylistRestore {
+        PlaylistRestore::new(&playlist(), &tracks(current), SystemTime::now(), backup)
+    }
+
+    fn names(diff: &Vector<DiffTrack>) -> Vec<&str> {
+        diff.iter().map(|track| track.name.as_ref()).collect()
+    }
+
+    fn is_longest_increasing(values: &[usize], expected_len: usize) -> bool {
+        let indices = longest_increasing_subsequence(values);
+        indices.len() == expected_len
+            && indices.windows(2).all(|pair| pair[0] < pair[1])
+            && indices
+                .windows(2)
+                .all(|pair| values[pair[0]] < values[pair[1]])
+    }
+
+    #[test]
+    fn longest_increasing_subsequence_is_found() {
+        assert!(longest_increasing_subsequence(&[]).is_empty());
+        assert_eq!(longest_increasing_subsequence(&[0, 1, 2]), vec![0, 1, 2]);
+        assert!(is_longest_increasing(&[3, 2, 1, 0], 1));
+        assert!(is_longest_increasing(&[3, 0, 1, 4, 2, 5], 4));
+        assert!(is_longest_increasing(&[1, 1, 1], 1));
+        assert_eq!(
+            longest_increasing_subsequence(&[5, 0, 6, 1, 2, 3]),
+            vec![1, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn unchanged_playlist_has_no_changes() {
+        let restore = restore(&[1, 2, 3], &backup(&[1, 2, 3]));
+        assert!(restore.is_empty());
+        assert_eq!(restore.uris.len(), 3);
+        assert_eq!(restore.snapshot_id.as_deref(), Some("snapshot"));
+    }
+
+    #[test]
+    fn added_removed_and_moved_tracks_are_listed() {
+        let restore = restore(&[1, 2, 3, 4], &backup(&[2, 1, 3, 5]));
+        assert_eq!(names(&restore.added), ["Track 5"]);
+        assert_eq!(names(&restore.removed), ["Track 4"]);
+        assert_eq!(restore.moved.len(), 1);
+        let uris: Vec<_> = tracks(&[2, 1, 3, 5])
+            .iter()
+            .map(|track| Arc::from(track.id.0.to_uri().unwrap()))
+            .collect();
+        assert_eq!(restore.uris, uris.into_iter().collect::<Vector<Arc<str>>>());
+    }
+
+    #[test]
+    fn moving_one_track_moves_only_that_track() {
+        let restore = restore(&[1, 2, 3, 4, 5], &backup(&[2, 3, 4, 5, 1]));
+        assert_eq!(names(&restore.moved), ["Track 1"]);
+        assert!(restore.added.is_empty());
+        assert!(restore.removed.is_empty());
+    }
+
+    #[test]
+    fn duplicates_are_paired_in_order() {
+        let restore = restore(&[1, 2, 1], &backup(&[1, 2]));
+        assert_eq!(names(&restore.removed), ["Track 1"]);
+        assert!(restore.added.is_empty());
+        assert!(restore.moved.is_empty());
+    }
+
+    #[test]
+    fn local_files_in_the_backup_are_skipped() {
+        let mut backup = backup(&[1]);
+        backup.tracks.push(TrackRecord {
+            uri: None,
+            name: "Local".into(),
+            artist: String::new(),
+            album: "".into(),
+            duration_ms: 0,
+        });
+        let restore = restore(&[1], &backup);
+        assert_eq!(restore.skipped_local, 1);
+        assert_eq!(restore.uris.len(), 1);
+        assert!(restore.is_empty());
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use druid::{im::Vector, Data, Lens};
+use serde::{Deserialize, Serialize};
+
+use super::{ArtistLink, Playable, Track, TrackId};
+
+/// Artists and tracks skipped automatically when they come up in the radio or in
+/// a shuffled queue.  Playing them directly still works.
+#[derive(Clone, Debug, Default, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Blocklist {
+    pub artists: Vector<ArtistLink>,
+    pub tracks: Vector<BlockedTrack>,
+}
+
+/// Enough of a track to list it in the preferences without loading it again.
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
+pub struct BlockedTrack {
+    pub id: TrackId,
+    pub name: Arc<str>,
+    pub artist: Arc<str>,
+}
+
+impl Blocklist {
+    pub fn blocks(&self, item: &Playable) -> bool {
+        match item {
+            Playable::Track(track) => {
+                self.tracks.iter().any(|blocked| blocked.id == track.id)
+                    || track
+                        .artists
+                        .iter()
+                        .any(|artist| self.artists.iter().any(|blocked| blocked.id == artist.id))
+            }
+            Playable::Episode(_) => false,
+        }
+    }
+
+    pub fn block_artist(&mut self, artist: &ArtistLink) {
+        if !self.artists.iter().any(|blocked| blocked.id == artist.id) {
+            self.artists.push_back(artist.clone());
+        }
+    }
+
+    pub fn unblock_artist(&mut self, artist: &ArtistLink) {
+        self.artists.retain(|blocked| blocked.id != artist.id);
+    }
+
+    pub fn block_track(&mut self, track: &Track) {
+        if !self.tracks.iter().any(|blocked| blocked.id == track.id) {
+            self.tracks.push_back(BlockedTrack {
+                id: track.id,
+                name: track.name.clone(),
+                artist: track.artist_name(),
+            });
+        }
+    }
+
+    pub fn unblock_track(&mut self, id: TrackId) {
+        self.tracks.retain(|blocked| blocked.id != id);
+    }
+}
@@ -1,35 +1,63 @@
-use std::io::{BufReader, BufWriter};
-use std::{env, env::VarError, fs::File, path::PathBuf};
+use std::io::{self, BufReader, BufWriter};
+use std::{env, env::VarError, fs::File, path::PathBuf, time::Duration};
 
 use std::fs::OpenOptions;
 #[cfg(target_family = "unix")]
 use std::os::unix::fs::OpenOptionsExt;
 
-use druid::{Data, Lens, Size};
+use druid::{
+    im::{HashMap, Vector},
+    Data, Lens, Size,
+};
 use platform_dirs::AppDirs;
 use psst_core::{
     cache::mkdir_if_not_exists,
     connection::Credentials,
-    player::PlaybackConfig,
+    download,
+    net::{self, Connectivity},
+    player::{daemon, PlaybackConfig},
     session::{SessionConfig, SessionConnection},
 };
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
 
 use crate::ui::theme;
 
-use super::{Nav, Promise, QueueBehavior, SliderScrollScale};
+use super::{
+    ActivityRecord, Blocklist, HistoryRules, Keymap, Nav, NowPlaying, Promise, QueueBehavior,
+    SliderScrollScale, StatsExport, TrackId,
+};
 
 #[derive(Clone, Debug, Data, Lens)]
 pub struct Preferences {
     pub active: PreferencesTab,
     pub cache_size: Promise<u64, (), ()>,
     pub auth: Authentication,
+    pub export: StatsExport,
+    pub pin: String,
+    pub pin_rejected: bool,
+    pub unlocked: bool,
+    /// Session activity, newest first.  Loaded when the diagnostics are shown.
+    pub activity: Vector<ActivityRecord>,
+    pub cache_cleanup: CacheCleanup,
+    /// Measured when the diagnostics are shown.
+    pub memory_usage: MemoryUsage,
+    /// Filters the settings of all tabs when not empty.
+    pub search: String,
 }
 
 impl Preferences {
     pub fn reset(&mut self) {
         self.cache_size.clear();
         self.auth.result.clear();
+        self.export.result.clear();
+        // Lock the settings again once the preferences are closed.
+        self.pin.clear();
+        self.pin_rejected = false;
+        self.unlocked = false;
+        self.activity.clear();
+        self.search.clear();
     }
 
     pub fn measure_cache_usage() -> Option<u64> {
@@ -37,14 +65,68 @@ impl Preferences {
     }
 }
 
+/// State of the cache garbage collection, running in the background.
+#[derive(Clone, Debug, Default, Data, Lens)]
+pub struct CacheCleanup {
+    pub running: bool,
+    /// Progress of the running collection, or the result of the last one.
+    pub status: String,
+}
+
+/// Memory taken by one of the in-memory caches.
+#[derive(Clone, Copy, Debug, Default, Data)]
+pub struct CacheUsage {
+    pub entries: usize,
+    pub bytes: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, Data, Lens)]
+pub struct MemoryUsage {
+    pub images: CacheUsage,
+    pub track_metadata: CacheUsage,
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Data)]
 pub enum PreferencesTab {
     General,
     Account,
     Cache,
+    Diagnostics,
     About,
 }
 
+/// PIN guarding the content filter and the account settings.  Only a salted hash of
+/// the PIN is kept in the config.
+#[derive(Clone, Debug, Data, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParentalLock {
+    salt: String,
+    hash: String,
+}
+
+impl ParentalLock {
+    pub fn new(pin: &str) -> Self {
+        let salt: [u8; 16] = rand::thread_rng().gen();
+        let salt = to_hex(&salt);
+        let hash = Self::hash(&salt, pin);
+        Self { salt, hash }
+    }
+
+    pub fn verify(&self, pin: &str) -> bool {
+        Self::hash(&self.salt, pin) == self.hash
+    }
+
+    fn hash(salt: &str, pin: &str) -> String {
+        let mut hasher = Sha1::new();
+        hasher.update(salt.as_bytes());
+        hasher.update(pin.trim().as_bytes());
+        to_hex(&hasher.finalize())
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 #[derive(Clone, Debug, Data, Lens)]
 pub struct Authentication {
     pub username: String,
@@ -63,9 +145,30 @@ impl Authentication {
         }
     }
 
-    pub fn authenticate_and_get_credentials(config: SessionConfig) -> Result<Credentials, String> {
-        let connection = SessionConnection::open(config).map_err(|err| err.to_string())?;
-        Ok(connection.credentials)
+    /// Log in with `config`.  With `check_connectivity`, a failed login probes the
+    /// connection, to tell a login portal or a missing connection apart from wrong
+    /// credentials in the error.
+    pub fn authenticate_and_get_credentials(
+        config: SessionConfig,
+        check_connectivity: bool,
+    ) -> Result<Credentials, String> {
+        let proxy_url = config.proxy_url.clone();
+        let err = match SessionConnection::open(config) {
+            Ok(connection) => return Ok(connection.credentials),
+            Err(err) => err,
+        };
+        if !check_connectivity {
+            return Err(err.to_string());
+        }
+        Err(match net::check_connectivity(proxy_url.as_deref()) {
+            Connectivity::CaptivePortal => format!(
+                "You appear to be behind a login portal ({}). Open a web page in your browser \
+                 to sign in to the network, then try again.",
+                err
+            ),
+            Connectivity::Offline => format!("You appear to be offline ({}).", err),
+            Connectivity::Online => err.to_string(),
+        })
     }
 
     pub fn clear(&mut self) {
@@ -76,7 +179,11 @@ impl Authentication {
 
 const APP_NAME: &str = "Psst";
 const CONFIG_FILENAME: &str = "config.json";
+const SESSION_ACTIVITY_FILENAME: &str = "session-activity.jsonl";
 const PROXY_ENV_VAR: &str = "SOCKS_PROXY";
+/// The track metadata gets this fraction of the memory cache limit, it's small
+/// next to the decoded images.
+const TRACK_METADATA_SHARE: usize = 8;
 
 #[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
 #[serde(default)]
@@ -86,14 +193,94 @@ pub struct Config {
     pub audio_quality: AudioQuality,
     pub theme: Theme,
     pub volume: f64,
+    /// Last volume used with each of the output devices, keyed by the device name.
+    pub device_volume: HashMap<String, f64>,
     pub last_route: Option<Nav>,
     pub queue_behavior: QueueBehavior,
+    pub smart_shuffle: bool,
+    /// Continue playing when the output device that got disconnected comes back.
+    pub resume_on_reconnect: bool,
+    pub filter_explicit: bool,
+    pub parental_lock: Option<ParentalLock>,
     pub show_track_cover: bool,
+    /// Load the canvas of the playing track for the artwork window, if it's a still
+    /// image.
+    pub show_canvas: bool,
+    /// Put "Now playing: …" into the window title, which screen readers follow.
+    pub announce_track_changes: bool,
+    pub reduced_motion: ReducedMotion,
+    /// Paint less often, and not at all while the window is in the background.
+    pub low_power_mode: bool,
+    pub click_to_play: ClickToPlay,
     pub window_size: Size,
     pub slider_scroll_scale: SliderScrollScale,
     pub sort_order: SortOrder,
     pub sort_criteria: SortCriteria,
     pub paginated_limit: usize,
+    pub start_minimized: bool,
+    /// Attach to a running `psst-cli daemon` instead of playing in this process, so
+    /// the playback survives closing the app.
+    pub attach_to_daemon: bool,
+    pub startup_playback: StartupPlayback,
+    pub launch_on_login: bool,
+    pub last_playback: Option<LastPlayback>,
+    /// Queue behavior to switch to when playing from a context, keyed by its URI.
+    pub context_queue_behavior: HashMap<String, QueueBehavior>,
+    pub dns_over_https: String,
+    pub ca_certificates: String,
+    /// Probe the connection when logging in fails, to word the error.  The probe
+    /// is a request to a server of Google, so it's off unless enabled.
+    pub connectivity_check: bool,
+    /// Country code of the market to browse, empty for the country of the account.
+    pub market: String,
+    /// Save snapshots of all playlists in the library once a week.
+    pub playlist_backup: bool,
+    /// Number of playlist snapshots to keep, older ones get removed.
+    pub playlist_backup_keep: usize,
+    /// Report the played tracks to Spotify, so they show up in the recently played
+    /// items and on other devices.
+    pub report_playback: bool,
+    /// Directories with local music files, one per line.
+    pub local_music_dirs: String,
+    /// Number of offline downloads running at the same time.
+    pub download_concurrency: usize,
+    /// Largest size of the downloaded audio in gigabytes, zero for no limit.
+    pub download_quota_gb: usize,
+    /// Size the cache is trimmed to in gigabytes, zero for no limit.  Downloads
+    /// are never removed.
+    pub cache_limit_gb: usize,
+    /// Memory the decoded images and the track metadata may take in megabytes,
+    /// zero for no limit.
+    pub memory_cache_limit_mb: usize,
+    /// Show a small overlay with the playing track whenever it changes.
+    pub osd_enabled: bool,
+    pub osd_position: ScreenCorner,
+    /// How long the overlay stays on the screen, in seconds.
+    pub osd_duration_secs: usize,
+    /// Corner of the picture-in-picture artwork window.
+    pub pip_position: ScreenCorner,
+    pub keymap: Keymap,
+    /// When a playback counts as a play in the listening history.
+    pub history_rules: HistoryRules,
+    pub blocklist: Blocklist,
+    /// Seconds before the end of a track the following one is announced with the
+    /// up-next event, which the queue panel, the listen-along followers and the
+    /// daemon clients receive.  Zero to not announce it.  Also read by `psst-cli`.
+    pub up_next_lead_secs: usize,
+    /// Family of the interface font, empty for the font of the system.
+    pub ui_font: String,
+    /// Scale of the whole main window, the zoom shortcuts apply on top of it.
+    pub ui_scale: f64,
+    /// Zoom level picked with the shortcuts, keyed by the geometry of the monitor.
+    pub monitor_zoom: HashMap<String, f64>,
+    /// Bandsintown app ID used for the concerts on the artist pages, empty to not
+    /// show them.
+    pub bandsintown_app_id: String,
+    /// Offer to look up albums on MusicBrainz for details Spotify doesn't have.
+    pub musicbrainz_lookup: bool,
+    /// Panel docked to the right of the main view, if any.
+    pub side_panel: Option<SidePanel>,
+    pub side_panel_width: f64,
 }
 
 impl Default for Config {
@@ -103,14 +290,57 @@ impl Default for Config {
             audio_quality: Default::default(),
             theme: Default::default(),
             volume: 1.0,
+            device_volume: HashMap::new(),
             last_route: Default::default(),
             queue_behavior: Default::default(),
+            smart_shuffle: false,
+            resume_on_reconnect: false,
+            filter_explicit: false,
+            parental_lock: None,
             show_track_cover: Default::default(),
+            show_canvas: false,
+            announce_track_changes: false,
+            reduced_motion: Default::default(),
+            low_power_mode: false,
+            click_to_play: Default::default(),
             window_size: Size::new(theme::grid(80.0), theme::grid(100.0)),
             slider_scroll_scale: Default::default(),
             sort_order: Default::default(),
             sort_criteria: Default::default(),
             paginated_limit: 500,
+            start_minimized: false,
+            attach_to_daemon: false,
+            startup_playback: Default::default(),
+            launch_on_login: false,
+            last_playback: None,
+            context_queue_behavior: HashMap::new(),
+            dns_over_https: String::new(),
+            ca_certificates: String::new(),
+            connectivity_check: false,
+            market: String::new(),
+            playlist_backup: false,
+            playlist_backup_keep: 4,
+            report_playback: true,
+            local_music_dirs: String::new(),
+            download_concurrency: download::DEFAULT_CONCURRENCY,
+            download_quota_gb: 0,
+            cache_limit_gb: 0,
+            memory_cache_limit_mb: 512,
+            osd_enabled: false,
+            osd_position: Default::default(),
+            osd_duration_secs: 4,
+            pip_position: ScreenCorner::BottomRight,
+            keymap: Default::default(),
+            history_rules: Default::default(),
+            blocklist: Default::default(),
+            up_next_lead_secs: 10,
+            ui_font: String::new(),
+            ui_scale: 1.0,
+            monitor_zoom: HashMap::new(),
+            bandsintown_app_id: String::new(),
+            musicbrainz_lookup: false,
+            side_panel: None,
+            side_panel_width: theme::grid(40.0),
         }
     }
 }
@@ -137,10 +367,21 @@ impl Config {
         Self::app_dirs().map(|dirs| dirs.config_dir)
     }
 
-    fn config_path() -> Option<PathBuf> {
+    /// Token for talking to the playback daemon, shared with `psst-cli` through the
+    /// config directory.
+    pub fn daemon_token() -> io::Result<String> {
+        let dir = Self::config_dir().expect("Failed to get config dir");
+        daemon::load_or_create_token(&dir)
+    }
+
+    pub fn config_path() -> Option<PathBuf> {
         Self::config_dir().map(|dir| dir.join(CONFIG_FILENAME))
     }
 
+    pub fn session_activity_path() -> Option<PathBuf> {
+        Self::config_dir().map(|dir| dir.join(SESSION_ACTIVITY_FILENAME))
+    }
+
     pub fn load() -> Option<Config> {
         let path = Self::config_path().expect("Failed to get config path");
         if let Ok(file) = File::open(&path) {
@@ -169,6 +410,66 @@ impl Config {
         log::info!("saved config: {:?}", &path);
     }
 
+    /// Remember `volume` as the last one used, and the one used with `device`.
+    pub fn remember_volume(&mut self, device: Option<&str>, volume: f64) {
+        self.volume = volume;
+        if let Some(device) = device {
+            self.device_volume.insert(device.to_string(), volume);
+        }
+    }
+
+    /// Volume to start with on `device`, falling back to the last used volume.
+    pub fn device_volume(&self, device: Option<&str>) -> f64 {
+        device
+            .and_then(|device| self.device_volume.get(device).copied())
+            .unwrap_or(self.volume)
+    }
+
+    /// Zoom level picked for `monitor`.
+    pub fn zoom(&self, monitor: Option<&str>) -> f64 {
+        monitor
+            .and_then(|monitor| self.monitor_zoom.get(monitor).copied())
+            .unwrap_or(1.0)
+    }
+
+    /// Move the zoom level of `monitor` by `step`.
+    pub fn step_zoom(&mut self, monitor: &str, step: ZoomStep) {
+        let current = self.zoom(Some(monitor));
+        let zoom = match step {
+            ZoomStep::In => ZOOM_LEVELS
+                .iter()
+                .copied()
+                .find(|&level| level > current + f64::EPSILON),
+            ZoomStep::Out => ZOOM_LEVELS
+                .iter()
+                .rev()
+                .copied()
+                .find(|&level| level < current - f64::EPSILON),
+            ZoomStep::Reset => Some(1.0),
+        };
+        let zoom = zoom.unwrap_or(current);
+        if zoom == 1.0 {
+            self.monitor_zoom.remove(monitor);
+        } else {
+            self.monitor_zoom.insert(monitor.to_string(), zoom);
+        }
+    }
+
+    /// Scale of the main window on `monitor`, the UI scale with the zoom level on
+    /// top.
+    pub fn ui_scale(&self, monitor: Option<&str>) -> f64 {
+        self.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE) * self.zoom(monitor)
+    }
+
+    /// Whether to turn animations off, `system_prefers` being the platform hint.
+    pub fn reduce_motion(&self, system_prefers: bool) -> bool {
+        match self.reduced_motion {
+            ReducedMotion::System => system_prefers,
+            ReducedMotion::Always => true,
+            ReducedMotion::Never => false,
+        }
+    }
+
     pub fn has_credentials(&self) -> bool {
         self.credentials.is_some()
     }
@@ -195,10 +496,67 @@ impl Config {
     pub fn playback(&self) -> PlaybackConfig {
         PlaybackConfig {
             bitrate: self.audio_quality.as_bitrate(),
+            up_next_lead: Duration::from_secs(self.up_next_lead_secs as u64),
             ..PlaybackConfig::default()
         }
     }
 
+    pub fn ui_font(&self) -> Option<&str> {
+        let family = self.ui_font.trim();
+        (!family.is_empty()).then_some(family)
+    }
+
+    pub fn bandsintown_app_id(&self) -> Option<&str> {
+        let app_id = self.bandsintown_app_id.trim();
+        (!app_id.is_empty()).then_some(app_id)
+    }
+
+    /// URL of the DNS-over-HTTPS server to use, if any.
+    pub fn dns_over_https(&self) -> Option<&str> {
+        let url = self.dns_over_https.trim();
+        (!url.is_empty()).then_some(url)
+    }
+
+    /// Path to a PEM bundle of additionally trusted certificate authorities.
+    pub fn ca_certificates(&self) -> Option<PathBuf> {
+        let path = self.ca_certificates.trim();
+        (!path.is_empty()).then(|| PathBuf::from(path))
+    }
+
+    /// Upper-cased market override, if a valid ISO 3166-1 alpha-2 code is set.
+    pub fn market(&self) -> Option<String> {
+        let code = self.market.trim();
+        (code.len() == 2 && code.chars().all(|c| c.is_ascii_alphabetic()))
+            .then(|| code.to_ascii_uppercase())
+    }
+
+    /// Bytes the decoded images may take in memory.  The track metadata gets an
+    /// eighth of the limit, the images the rest.
+    pub fn image_cache_limit(&self) -> Option<usize> {
+        self.memory_cache_limit()
+            .map(|limit| limit - limit / TRACK_METADATA_SHARE)
+    }
+
+    /// Bytes the prefetched track metadata may take in memory.
+    pub fn track_metadata_limit(&self) -> Option<usize> {
+        self.memory_cache_limit()
+            .map(|limit| limit / TRACK_METADATA_SHARE)
+    }
+
+    fn memory_cache_limit(&self) -> Option<usize> {
+        (self.memory_cache_limit_mb > 0)
+            .then_some(self.memory_cache_limit_mb.saturating_mul(1_000_000))
+    }
+
+    pub fn local_music_dirs(&self) -> Vec<PathBuf> {
+        self.local_music_dirs
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect()
+    }
+
     pub fn proxy() -> Option<String> {
         env::var(PROXY_ENV_VAR).map_or_else(
             |err| match err {
@@ -213,6 +571,11 @@ impl Config {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize)]
+pub enum SidePanel {
+    Queue,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize)]
 pub enum AudioQuality {
     Low,
@@ -236,6 +599,30 @@ impl Default for AudioQuality {
     }
 }
 
+pub const MIN_UI_SCALE: f64 = 0.5;
+pub const MAX_UI_SCALE: f64 = 2.0;
+
+/// Levels the zoom shortcuts step through.
+const ZOOM_LEVELS: [f64; 11] = [0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data)]
+pub enum ZoomStep {
+    In,
+    Out,
+    Reset,
+}
+
+/// Corner of the screen a floating window, like the track change overlay, shows
+/// up in.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Data, Serialize, Deserialize)]
+pub enum ScreenCorner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize)]
 pub enum Theme {
     Light,
@@ -248,6 +635,34 @@ impl Default for Theme {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize)]
+pub enum ClickToPlay {
+    /// Play a track as soon as its row is clicked.
+    SingleClick,
+    /// Select on the first click, play on a double-click.
+    DoubleClick,
+}
+
+impl Default for ClickToPlay {
+    fn default() -> Self {
+        Self::SingleClick
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize)]
+pub enum ReducedMotion {
+    /// Follow the platform setting.
+    System,
+    Always,
+    Never,
+}
+
+impl Default for ReducedMotion {
+    fn default() -> Self {
+        Self::System
+    }
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize)]
 pub enum SortOrder {
     Ascending,
@@ -272,3 +687,40 @@ impl Default for SortCriteria {
         Self::DateAdded
     }
 }
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data, Serialize, Deserialize)]
+pub enum StartupPlayback {
+    /// Start with nothing playing.
+    Stopped,
+    /// Load the last played track, but keep it paused.
+    Paused,
+    /// Continue playing the last played track.
+    Playing,
+}
+
+impl Default for StartupPlayback {
+    fn default() -> Self {
+        Self::Stopped
+    }
+}
+
+/// Track that was playing when the app was closed, restored on the next start
+/// according to `StartupPlayback`.
+#[derive(Clone, Debug, Data, Serialize, Deserialize)]
+pub struct LastPlayback {
+    pub track_id: TrackId,
+    pub origin: Nav,
+    pub progress_ms: u64,
+}
+
+impl LastPlayback {
+    pub fn new(now_playing: &NowPlaying) -> Option<Self> {
+        // Local files are known by their path only for the running session.
+        let track = now_playing.item.track().filter(|track| !track.is_local)?;
+        Some(Self {
+            track_id: track.id,
+            origin: now_playing.origin.to_nav(),
+            progress_ms: now_playing.progress.as_millis() as u64,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use druid::{
+    im::{HashMap, Vector},
+    Data, Lens,
+};
+use psst_core::{
+    cache::Cache,
+    download::{self, DownloadState},
+};
+
+use crate::data::{Config, Track, TrackId};
+
+/// The offline download queue, as last reported by the download manager.
+#[derive(Clone, Default, Data, Lens)]
+pub struct Downloads {
+    pub items: Vector<DownloadItem>,
+    /// Tracks of the queued items.  The manager knows just their IDs.
+    tracks: HashMap<TrackId, Arc<Track>>,
+    pub paused: bool,
+    pub storage: Option<StorageInfo>,
+}
+
+impl Downloads {
+    pub fn contains(&self, id: TrackId) -> bool {
+        self.tracks.contains_key(&id)
+    }
+
+    pub fn add(&mut self, tracks: impl IntoIterator<Item = Arc<Track>>) {
+        for track in tracks {
+            self.tracks.insert(track.id, track.clone());
+            self.items.push_back(DownloadItem::new(track));
+        }
+    }
+
+    pub fn remove(&mut self, id: TrackId) {
+        self.tracks.remove(&id);
+        self.items.retain(|item| item.track.id != id);
+    }
+
+    pub fn clear_finished(&mut self) {
+        let tracks = &mut self.tracks;
+        self.items.retain(|item| {
+            let is_done = item.status == DownloadStatus::Done;
+            if is_done {
+                tracks.remove(&item.track.id);
+            }
+            !is_done
+        });
+    }
+
+    /// Take over the state of the queue reported by the manager.
+    pub fn update(&mut self, states: &[DownloadState]) {
+        self.items = states
+            .iter()
+            .filter_map(|state| {
+                let track = self.tracks.get(&TrackId(state.item_id))?;
+                Some(DownloadItem {
+                    track: track.clone(),
+                    status: DownloadStatus::from(&state.status),
+                    downloaded: state.downloaded,
+                    total: state.total,
+                })
+            })
+            .collect();
+    }
+
+    pub fn has_failed(&self) -> bool {
+        self.items
+            .iter()
+            .any(|item| matches!(item.status, DownloadStatus::Failed(_)))
+    }
+
+    pub fn has_finished(&self) -> bool {
+        self.items
+            .iter()
+            .any(|item| item.status == DownloadStatus::Done)
+    }
+
+    pub fn finished_count(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.status == DownloadStatus::Done)
+            .count()
+    }
+
+    /// Estimated size of what's left to download of the queued items.
+    pub fn pending_size(&self, bitrate: usize) -> u64 {
+        self.items
+            .iter()
+            .filter(|item| item.status != DownloadStatus::Done)
+            .map(|item| {
+                let estimate = item
+                    .total
+                    .unwrap_or_else(|| estimate_download_size(&[item.track.clone()], bitrate));
+                estimate.saturating_sub(item.downloaded)
+            })
+            .sum()
+    }
+}
+
+/// Expected size of the audio files of the tracks, from their duration and the
+/// bitrate in kbit/s.  The files have some overhead, but the bitrate of Vorbis is
+/// just nominal anyway.
+pub fn estimate_download_size(tracks: &[Arc<Track>], bitrate: usize) -> u64 {
+    let secs: f64 = tracks
+        .iter()
+        .map(|track| track.duration.as_secs_f64())
+        .sum();
+    (secs * bitrate as f64 * 1000.0 / 8.0) as u64
+}
+
+/// Space taken by the downloaded audio and left on the disk it's stored on.
+/// Audio cached from streaming is not counted, the cache size limit takes care
+/// of that.
+#[derive(Clone, Copy, Debug, Data)]
+pub struct StorageInfo {
+    pub used: u64,
+    pub available: u64,
+}
+
+impl StorageInfo {
+    /// Might take a while with a large cache.
+    pub fn measure(cache: &Cache) -> Option<Self> {
+        let cache_dir = Config::cache_dir()?;
+        let used = cache.downloaded_size().unwrap_or(0);
+        let available = fs2::available_space(cache_dir).ok()?;
+        Some(Self { used, available })
+    }
+}
+
+#[derive(Clone, Data, Lens)]
+pub struct DownloadItem {
+    pub track: Arc<Track>,
+    pub status: DownloadStatus,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+impl DownloadItem {
+    pub fn new(track: Arc<Track>) -> Self {
+        Self {
+            track,
+            status: DownloadStatus::Queued,
+            downloaded: 0,
+            total: None,
+        }
+    }
+
+    pub fn status_text(&self) -> String {
+        match &self.status {
+            DownloadStatus::Queued => "Queued".to_string(),
+            DownloadStatus::Downloading => match self.total {
+                Some(total) if total > 0 => {
+                    format!("Downloading {}%", self.downloaded * 100 / total)
+                }
+                _ => "Downloading".to_string(),
+            },
+            DownloadStatus::Done => "Downloaded".to_string(),
+            DownloadStatus::Failed(err) => format!("Failed: {}", err),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Data, PartialEq, Eq)]
+pub enum DownloadStatus {
+    Queued,
+    Downloading,
+    Done,
+    Failed(Arc<str>),
+}
+
+impl From<&download::DownloadStatus> for DownloadStatus {
+    fn from(status: &download::DownloadStatus) -> Self {
+        match status {
+            download::DownloadStatus::Queued => Self::Queued,
+            download::DownloadStatus::Downloading => Self::Downloading,
+            download::DownloadStatus::Done => Self::Done,
+            download::DownloadStatus::Failed(err) => Self::Failed(err.as_str().into()),
+        }
+    }
+}
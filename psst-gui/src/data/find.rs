@@ -1,6 +1,8 @@
 use druid::{Data, Lens};
 use regex::{Regex, RegexBuilder};
 
+use super::PlaybackOrigin;
+
 #[derive(Clone, Default, Debug, Data, Lens)]
 pub struct Finder {
     pub focused_result: usize,
@@ -46,6 +48,34 @@ impl Finder {
     }
 }
 
+/// Narrows the track list of a playlist or an album down to the rows matching
+/// the query.  Unlike the finder, the rows that don't match are left out.
+#[derive(Clone, Default, Debug, Data, Lens)]
+pub struct TrackFilter {
+    /// The list the filter was typed into, other lists are left alone.
+    pub origin: Option<PlaybackOrigin>,
+    pub query: String,
+}
+
+impl TrackFilter {
+    pub fn new(origin: PlaybackOrigin) -> Self {
+        Self {
+            origin: Some(origin),
+            query: String::new(),
+        }
+    }
+
+    /// Compare by context URI, the names in the links can differ between the
+    /// route and the loaded list.
+    pub fn query_for(&self, origin: &PlaybackOrigin) -> Option<FindQuery> {
+        let applies = self.origin.as_ref().map_or(false, |filtered| {
+            filtered.context_uri().is_some() && filtered.context_uri() == origin.context_uri()
+        });
+        let query = self.query.trim();
+        (applies && !query.is_empty()).then(|| FindQuery::new(query))
+    }
+}
+
 #[derive(Clone)]
 pub struct FindQuery {
     regex: Regex,
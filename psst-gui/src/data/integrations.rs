@@ -0,0 +1,94 @@
+use std::{fmt::Display, sync::Arc};
+
+use druid::{im::Vector, Data, Lens};
+
+use super::ActivityRecord;
+
+/// Only the last few errors of an integration are kept around.
+const MAX_INTEGRATION_ERRORS: usize = 5;
+
+/// Parts of the app talking to the outside world on their own, like the
+/// media controls of the system.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Data)]
+pub enum Integration {
+    MediaControls,
+    ListenAlong,
+}
+
+impl Integration {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::MediaControls if cfg!(target_os = "linux") => "Media Controls (MPRIS)",
+            Self::MediaControls => "Media Controls",
+            Self::ListenAlong => "Listen Along",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Data)]
+pub enum IntegrationStatus {
+    Off,
+    Connecting(Arc<str>),
+    Connected(Arc<str>),
+    Failed,
+}
+
+#[derive(Clone, Data, Lens)]
+pub struct IntegrationHealth {
+    pub integration: Integration,
+    pub status: IntegrationStatus,
+    /// Most recent first.
+    pub errors: Vector<ActivityRecord>,
+}
+
+impl IntegrationHealth {
+    pub fn new(integration: Integration) -> Self {
+        Self {
+            integration,
+            status: IntegrationStatus::Off,
+            errors: Vector::new(),
+        }
+    }
+
+    pub fn set_off(&mut self) {
+        self.status = IntegrationStatus::Off;
+    }
+
+    pub fn set_connecting(&mut self, detail: impl Into<Arc<str>>) {
+        self.status = IntegrationStatus::Connecting(detail.into());
+    }
+
+    pub fn set_connected(&mut self, detail: impl Into<Arc<str>>) {
+        self.status = IntegrationStatus::Connected(detail.into());
+    }
+
+    pub fn set_failed(&mut self, err: impl Display) {
+        self.status = IntegrationStatus::Failed;
+        self.errors.push_front(ActivityRecord::now(err.to_string()));
+        self.errors.truncate(MAX_INTEGRATION_ERRORS);
+    }
+
+    pub fn status_text(&self) -> String {
+        match &self.status {
+            IntegrationStatus::Off => "Off".to_string(),
+            IntegrationStatus::Connecting(detail) => format!("Connecting: {}", detail),
+            IntegrationStatus::Connected(detail) => detail.to_string(),
+            IntegrationStatus::Failed => "Failed".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Data, Lens)]
+pub struct Integrations {
+    pub media_controls: IntegrationHealth,
+    pub listen_along: IntegrationHealth,
+}
+
+impl Default for Integrations {
+    fn default() -> Self {
+        Self {
+            media_controls: IntegrationHealth::new(Integration::MediaControls),
+            listen_along: IntegrationHealth::new(Integration::ListenAlong),
+        }
+    }
+}
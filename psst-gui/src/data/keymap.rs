@@ -0,0 +1,209 @@
+use druid::{Data, HotKey, KbKey, KeyEvent, Lens, RawMods};
+use serde::{Deserialize, Serialize};
+
+use super::ZoomStep;
+
+/// Keyboard shortcuts of the main window, written like "Ctrl+Shift+L".  "Cmd"
+/// stands for the Command key on macOS and for Ctrl elsewhere.  An empty binding
+/// turns the shortcut off.  The menu items show, and trigger, the same bindings.
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Keymap {
+    pub focus_search: String,
+    pub find: String,
+    pub home: String,
+    /// The library, it opens with the saved tracks.
+    pub saved_tracks: String,
+    /// The first playlist in the sidebar.
+    pub playlists: String,
+    /// Second binding to focus the search, next to the other routes.
+    pub search: String,
+    pub queue: String,
+    pub saved_albums: String,
+    pub saved_shows: String,
+    pub downloads: String,
+    pub navigate_back: String,
+    pub zoom_in: String,
+    pub zoom_out: String,
+    pub zoom_reset: String,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            focus_search: "Cmd+L".to_string(),
+            find: "Cmd+F".to_string(),
+            home: "Cmd+1".to_string(),
+            saved_tracks: "Cmd+2".to_string(),
+            playlists: "Cmd+3".to_string(),
+            search: "Cmd+4".to_string(),
+            queue: "Cmd+5".to_string(),
+            saved_albums: "Cmd+6".to_string(),
+            saved_shows: "Cmd+7".to_string(),
+            downloads: "Cmd+8".to_string(),
+            navigate_back: "Escape".to_string(),
+            zoom_in: "Cmd+=".to_string(),
+            zoom_out: "Cmd+-".to_string(),
+            zoom_reset: "Cmd+0".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyAction {
+    FocusSearch,
+    Find,
+    Home,
+    SavedTracks,
+    Playlists,
+    Queue,
+    SavedAlbums,
+    SavedShows,
+    Downloads,
+    NavigateBack,
+    Zoom(ZoomStep),
+}
+
+impl Keymap {
+    /// Hotkey of the `binding` for the menus, `None` if it's empty or invalid.
+    pub fn hotkey(binding: &str) -> Option<HotKey> {
+        KeyBinding::parse(binding).map(|binding| binding.to_hotkey())
+    }
+
+    /// Action bound to the pressed keys, if any.
+    pub fn action(&self, event: &KeyEvent) -> Option<KeyAction> {
+        let bindings = [
+            (&self.focus_search, KeyAction::FocusSearch),
+            (&self.find, KeyAction::Find),
+            (&self.home, KeyAction::Home),
+            (&self.saved_tracks, KeyAction::SavedTracks),
+            (&self.playlists, KeyAction::Playlists),
+            (&self.search, KeyAction::FocusSearch),
+            (&self.queue, KeyAction::Queue),
+            (&self.saved_albums, KeyAction::SavedAlbums),
+            (&self.saved_shows, KeyAction::SavedShows),
+            (&self.downloads, KeyAction::Downloads),
+            (&self.navigate_back, KeyAction::NavigateBack),
+            (&self.zoom_in, KeyAction::Zoom(ZoomStep::In)),
+            (&self.zoom_out, KeyAction::Zoom(ZoomStep::Out)),
+            (&self.zoom_reset, KeyAction::Zoom(ZoomStep::Reset)),
+        ];
+        bindings.into_iter().find_map(|(binding, action)| {
+            KeyBinding::parse(binding)
+                .filter(|binding| binding.matches(event))
+                .map(|_| action)
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct KeyBinding {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    meta: bool,
+    key: Option<KbKey>,
+}
+
+impl KeyBinding {
+    fn parse(binding: &str) -> Option<Self> {
+        let mut parsed = Self::default();
+        for part in binding.split('+').map(str::trim) {
+            match part.to_lowercase().as_str() {
+                "" => return None,
+                "ctrl" | "control" => parsed.ctrl = true,
+                "alt" | "option" => parsed.alt = true,
+                "shift" => parsed.shift = true,
+                "meta" | "super" => parsed.meta = true,
+                "cmd" if cfg!(target_os = "macos") => parsed.meta = true,
+                "cmd" => parsed.ctrl = true,
+                _ if part.chars().count() == 1 => {
+                    parsed.key = Some(KbKey::Character(part.to_lowercase()));
+                }
+                _ => parsed.key = Some(part.parse().ok()?),
+            }
+        }
+        parsed.key.is_some().then_some(parsed)
+    }
+
+    fn to_hotkey(&self) -> HotKey {
+        let mods = match (self.ctrl, self.alt, self.shift, self.meta) {
+            (false, false, false, false) => RawMods::None,
+            (true, false, false, false) => RawMods::Ctrl,
+            (false, true, false, false) => RawMods::Alt,
+            (false, false, true, false) => RawMods::Shift,
+            (false, false, false, true) => RawMods::Meta,
+            (true, true, false, false) => RawMods::AltCtrl,
+            (true, false, true, false) => RawMods::CtrlShift,
+            (true, false, false, true) => RawMods::CtrlMeta,
+            (false, true, true, false) => RawMods::AltShift,
+            (false, true, false, true) => RawMods::AltMeta,
+            (false, false, true, true) => RawMods::MetaShift,
+            (true, true, true, false) => RawMods::AltCtrlShift,
+            (true, true, false, true) => RawMods::AltCtrlMeta,
+            (true, false, true, true) => RawMods::CtrlMetaShift,
+            (false, true, true, true) => RawMods::AltMetaShift,
+            (true, true, true, true) => RawMods::AltCtrlMetaShift,
+        };
+        // Parsed bindings always have a key.
+        HotKey::new(mods, self.key.clone().unwrap_or(KbKey::Unidentified))
+    }
+
+    fn matches(&self, event: &KeyEvent) -> bool {
+        let is_same_key = match (&self.key, &event.key) {
+            // Characters are matched regardless of the case, Shift is checked below.
+            (Some(KbKey::Character(bound)), KbKey::Character(pressed)) => {
+                bound == &pressed.to_lowercase()
+            }
+            (Some(bound), pressed) => bound == pressed,
+            (None, _) => false,
+        };
+        is_same_key
+            && self.ctrl == event.mods.ctrl()
+            && self.alt == event.mods.alt()
+            && self.shift == event.mods.shift()
+            && self.meta == event.mods.meta()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::{Modifiers, SysMods};
+
+    use super::*;
+
+    fn key_down(key: KbKey, mods: Modifiers) -> KeyEvent {
+        KeyEvent::for_test(mods, key)
+    }
+
+    #[test]
+    fn default_routes_are_numbered() {
+        let keymap = Keymap::default();
+        let cmd = if cfg!(target_os = "macos") {
+            Modifiers::META
+        } else {
+            Modifiers::CONTROL
+        };
+        let action = |key: &str| keymap.action(&key_down(KbKey::Character(key.into()), cmd));
+        assert_eq!(action("1"), Some(KeyAction::Home));
+        assert_eq!(action("2"), Some(KeyAction::SavedTracks));
+        assert_eq!(action("3"), Some(KeyAction::Playlists));
+        assert_eq!(action("4"), Some(KeyAction::FocusSearch));
+        assert_eq!(action("5"), Some(KeyAction::Queue));
+        assert_eq!(action("l"), Some(KeyAction::FocusSearch));
+    }
+
+    #[test]
+    fn bindings_turn_into_menu_hotkeys() {
+        assert_eq!(
+            Keymap::hotkey("Cmd+Shift+L"),
+            Some(HotKey::new(SysMods::CmdShift, "l"))
+        );
+        assert_eq!(
+            Keymap::hotkey("Alt+Escape"),
+            Some(HotKey::new(RawMods::Alt, KbKey::Escape))
+        );
+        assert_eq!(Keymap::hotkey(""), None);
+        assert_eq!(Keymap::hotkey("Ctrl+"), None);
+    }
+}
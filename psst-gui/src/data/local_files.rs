@@ -0,0 +1,492 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use druid::{im::Vector, Data, Lens};
+use itertools::Itertools;
+use psst_core::{
+    audio::{chapters, probe::TrackProbe},
+    cache::mkdir_if_not_exists,
+    item_id::ItemId,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::{utils::NaturalKey, AlbumLink, ArtistLink, Chapter, Config, Image, Track, TrackId},
+    error::Error,
+};
+
+const INDEX_FILENAME: &str = "local-files.json";
+
+/// Largest difference in length between a local file and its catalog match.
+const MATCH_DURATION_TOLERANCE_MS: u64 = 5_000;
+
+/// Extensions of the files the player is able to decode.
+const SUPPORTED_EXTENSIONS: &[&str] = &["mp3", "ogg"];
+
+#[derive(Clone, Default, Debug, Data, Lens)]
+pub struct LocalTracks {
+    pub tracks: Vector<Arc<Track>>,
+}
+
+/// Index of the music files found in the configured directories.  It is kept in
+/// the cache directory, so only new or modified files have to be probed for their
+/// tags on the next scan.
+pub struct LocalLibrary {
+    path: Option<PathBuf>,
+    entries: HashMap<PathBuf, LocalFileEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct LocalFileEntry {
+    /// Modification time of the file, in seconds since the Unix epoch.
+    modified: u64,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    album_artist: Option<String>,
+    track_number: u32,
+    disc_number: u32,
+    duration_ms: u64,
+    /// Set once the file has been matched to the catalog, or can't be searched for
+    /// for lack of tags, so it isn't looked up again.
+    #[serde(default)]
+    catalog_checked: bool,
+    #[serde(default)]
+    catalog_match: Option<CatalogMatch>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CatalogMatch {
+    id: TrackId,
+    album_images: Vec<Image>,
+}
+
+/// Outcome of matching the local files to the catalog.
+#[derive(Clone, Debug, Default)]
+pub struct MatchSummary {
+    pub checked: usize,
+    pub matched: usize,
+}
+
+impl LocalLibrary {
+    pub fn open() -> Self {
+        let path = Config::cache_dir().map(|dir| dir.join(INDEX_FILENAME));
+        let entries = path
+            .as_ref()
+            .and_then(|path| File::open(path).ok())
+            .and_then(|file| match serde_json::from_reader(BufReader::new(file)) {
+                Ok(entries) => Some(entries),
+                Err(err) => {
+                    log::warn!("ignoring malformed local files index: {}", err);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        Self { path, entries }
+    }
+
+    /// Walk `dirs`, probe the files that changed since the last scan, and return all
+    /// the tracks found, ordered by artist, album and track number.
+    pub fn scan(&mut self, dirs: &[PathBuf]) -> Vector<Arc<Track>> {
+        let mut files = Vec::new();
+        for dir in dirs {
+            if let Err(err) = collect_files(dir, &mut files) {
+                log::warn!("failed to scan {:?}: {}", dir, err);
+            }
+        }
+
+        let mut changed = false;
+        let mut entries = HashMap::with_capacity(files.len());
+        for (path, modified) in files {
+            let entry = match self.entries.remove(&path) {
+                Some(entry) if entry.modified == modified => entry,
+                _ => match LocalFileEntry::probe(&path, modified) {
+                    Some(entry) => {
+                        changed = true;
+                        entry
+                    }
+                    None => continue,
+                },
+            };
+            entries.insert(path, entry);
+        }
+        // Files left over have been removed.
+        changed |= !self.entries.is_empty();
+        self.entries = entries;
+
+        if changed {
+            if let Err(err) = self.save() {
+                log::error!("failed to save local files index: {}", err);
+            }
+        }
+        self.tracks()
+    }
+
+    /// Look the files that haven't been checked yet up in the catalog, using
+    /// `search` to find the candidates for a query.  Files are matched by their
+    /// title, artist and length, the album only breaks ties.
+    pub fn match_catalog(
+        &mut self,
+        mut search: impl FnMut(&str) -> Result<Vector<Arc<Track>>, Error>,
+    ) -> Result<MatchSummary, Error> {
+        let mut summary = MatchSummary::default();
+        let mut result = Ok(());
+        for (path, entry) in &mut self.entries {
+            if entry.catalog_checked {
+                continue;
+            }
+            let query = entry.search_query();
+            if let Some(query) = &query {
+                match search(query) {
+                    Ok(candidates) => {
+                        entry.catalog_match = entry.best_match(&candidates);
+                    }
+                    Err(err) => {
+                        // Keep what has been matched so far.
+                        result = Err(err);
+                        break;
+                    }
+                }
+            }
+            // Files the search didn't find are looked up again next time, they might
+            // have been added to the catalog since.
+            entry.catalog_checked = query.is_none() || entry.catalog_match.is_some();
+            summary.checked += 1;
+            if entry.catalog_match.is_some() {
+                summary.matched += 1;
+                log::info!("matched {:?} to the catalog", path);
+            }
+        }
+        if summary.checked > 0 {
+            if let Err(err) = self.save() {
+                log::error!("failed to save local files index: {}", err);
+            }
+        }
+        result.map(|_| summary)
+    }
+
+    /// All the indexed tracks, ordered by artist, album and track number.
+    pub fn tracks(&self) -> Vector<Arc<Track>> {
+        let mut tracks: Vec<_> = self.entries.iter().collect();
+        tracks.sort_by_cached_key(|&(path, entry)| {
+            let name = |name: Option<&String>| NaturalKey::new(name.map_or("", String::as_str));
+            (
+                name(entry.artist()),
+                name(entry.album.as_ref()),
+                entry.disc_number,
+                entry.track_number,
+                path,
+            )
+        });
+        tracks
+            .into_iter()
+            .map(|(path, entry)| Arc::new(entry.to_track(path)))
+            .collect()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                mkdir_if_not_exists(dir)?;
+            }
+            let mut writer = BufWriter::new(File::create(path)?);
+            serde_json::to_writer(&mut writer, &self.entries)?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl LocalFileEntry {
+    fn probe(path: &Path, modified: u64) -> Option<Self> {
+        let probe = match TrackProbe::new(&path.to_path_buf()) {
+            Ok(probe) => probe,
+            Err(err) => {
+                log::warn!("skipping {:?}: {}", path, err);
+                return None;
+            }
+        };
+        let tags = probe.tags;
+        Some(Self {
+            modified,
+            title: tags.title,
+            artist: tags.artist,
+            album: tags.album,
+            album_artist: tags.album_artist,
+            track_number: tags.track_number.unwrap_or(0),
+            disc_number: tags.disc_number.unwrap_or(1),
+            duration_ms: probe.duration.unwrap_or_default().as_millis() as u64,
+            catalog_checked: false,
+            catalog_match: None,
+        })
+    }
+
+    fn artist(&self) -> Option<&String> {
+        self.album_artist.as_ref().or(self.artist.as_ref())
+    }
+
+    /// Without a title and an artist, the results would be too random to match.
+    fn search_query(&self) -> Option<String> {
+        let title = self.title.as_deref()?;
+        let artist = self.artist.as_deref().or(self.album_artist.as_deref())?;
+        Some(format!(
+            "track:{} artist:{}",
+            strip_version(title),
+            strip_version(artist)
+        ))
+    }
+
+    fn best_match(&self, candidates: &Vector<Arc<Track>>) -> Option<CatalogMatch> {
+        let title = normalize(self.title.as_deref()?);
+        let artist = normalize(self.artist.as_deref().or(self.album_artist.as_deref())?);
+        let album = self.album.as_deref().map(normalize);
+        candidates
+            .iter()
+            .filter(|track| normalize(&track.name) == title)
+            .filter(|track| {
+                track.artists.iter().any(|candidate| {
+                    let candidate = normalize(&candidate.name);
+                    artist.contains(&candidate) || candidate.contains(&artist)
+                })
+            })
+            .filter_map(|track| {
+                let duration_ms = track.duration.as_millis() as u64;
+                let difference = duration_ms.abs_diff(self.duration_ms);
+                // Files with an unknown length are matched on the metadata only.
+                if self.duration_ms > 0 && difference > MATCH_DURATION_TOLERANCE_MS {
+                    return None;
+                }
+                let same_album = album.is_some() && album == Some(normalize(&track.album_name()));
+                Some(((!same_album, difference), track))
+            })
+            .min_by_key(|(rank, _)| *rank)
+            .map(|(_, track)| CatalogMatch {
+                id: track.id,
+                album_images: track
+                    .album
+                    .as_ref()
+                    .map(|album| album.images.iter().cloned().collect())
+                    .unwrap_or_default(),
+            })
+    }
+
+    fn to_track(&self, path: &Path) -> Track {
+        let name = self.title.clone().unwrap_or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        // Local albums and artists can't be linked to anything, so they stay
+        // without an ID.
+        // Matched files borrow the artwork of their catalog counterparts.
+        let images = self
+            .catalog_match
+            .as_ref()
+            .map(|catalog_match| catalog_match.album_images.iter().cloned().collect())
+            .unwrap_or_default();
+        Track {
+            id: TrackId(ItemId::from_local(path.to_path_buf())),
+            name: name.into(),
+            album: self.album.as_deref().map(|album| AlbumLink {
+                id: "".into(),
+                name: album.into(),
+                images,
+            }),
+            artists: self
+                .artist
+                .as_deref()
+                .map(|artist| ArtistLink {
+                    id: "".into(),
+                    name: artist.into(),
+                })
+                .into_iter()
+                .collect(),
+            duration: Duration::from_millis(self.duration_ms),
+            disc_number: self.disc_number as usize,
+            track_number: self.track_number as usize,
+            explicit: false,
+            is_local: true,
+            local_path: Some(path.to_string_lossy().into()),
+            catalog_id: self
+                .catalog_match
+                .as_ref()
+                .map(|catalog_match| catalog_match.id),
+            is_playable: Some(true),
+            restrictions: None,
+            popularity: None,
+        }
+    }
+}
+
+/// Build a track for a single file outside of the scanned directories, such as one
+/// listed in an imported queue.
+pub fn load_local_track(path: &Path) -> Option<Arc<Track>> {
+    LocalFileEntry::probe(path, 0).map(|entry| Arc::new(entry.to_track(path)))
+}
+
+/// Read the chapters of a long local file from its cue sheet or its tags.  Untitled
+/// chapters are numbered.
+pub fn load_chapters(path: &Path) -> Vector<Chapter> {
+    chapters::read_chapters(path)
+        .into_iter()
+        .enumerate()
+        .map(|(index, chapter)| {
+            let title = match (chapter.performer, chapter.title) {
+                (Some(performer), Some(title)) => format!("{} - {}", performer, title),
+                (_, Some(title)) => title,
+                (_, None) => format!("Chapter {}", index + 1),
+            };
+            Chapter {
+                title: title.into(),
+                start: chapter.start,
+            }
+        })
+        .collect()
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<(PathBuf, u64)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if let Err(err) = collect_files(&path, files) {
+                log::warn!("failed to scan {:?}: {}", path, err);
+            }
+        } else if is_supported_file(&path) {
+            let modified = entry
+                .metadata()?
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default();
+            files.push((path, modified));
+        }
+    }
+    Ok(())
+}
+
+/// Drop the parts of names that usually differ between releases, like "(Live)" or
+/// "- 2011 Remaster".
+fn strip_version(name: &str) -> &str {
+    let end = name
+        .find(|c| c == '(' || c == '[')
+        .into_iter()
+        .chain(name.find(" - "))
+        .min()
+        .unwrap_or(name.len());
+    match name[..end].trim() {
+        "" => name,
+        stripped => stripped,
+    }
+}
+
+/// Lower-cased alphanumeric words of a name, for comparing names regardless of
+/// punctuation and version suffixes.
+fn normalize(name: &str) -> String {
+    strip_version(name)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .join(" ")
+}
+
+/// Whether the player can decode the file, judging by its extension.
+pub fn is_supported_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| {
+            SUPPORTED_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use psst_core::item_id::ItemIdType;
+
+    use super::*;
+
+    fn entry(title: &str, artist: Option<&str>) -> LocalFileEntry {
+        LocalFileEntry {
+            modified: 0,
+            title: Some(title.to_string()),
+            artist: artist.map(str::to_string),
+            album: None,
+            album_artist: None,
+            track_number: 1,
+            disc_number: 1,
+            duration_ms: 200_000,
+            catalog_checked: false,
+            catalog_match: None,
+        }
+    }
+
+    fn catalog_track(name: &str, artist: &str) -> Arc<Track> {
+        Arc::new(Track {
+            id: TrackId(ItemId::from_base62("11dFghVXANMlKmJXsNCbNl", ItemIdType::Track).unwrap()),
+            name: name.into(),
+            album: None,
+            artists: Vector::unit(ArtistLink {
+                id: "6sFIWsNpZYqfjUpaCgueju".into(),
+                name: artist.into(),
+            }),
+            duration: Duration::from_millis(201_000),
+            disc_number: 1,
+            track_number: 1,
+            explicit: false,
+            is_local: false,
+            local_path: None,
+            catalog_id: None,
+            is_playable: Some(true),
+            restrictions: None,
+            popularity: None,
+        })
+    }
+
+    #[test]
+    fn unmatched_files_are_searched_again() {
+        let mut library = LocalLibrary {
+            path: None,
+            entries: HashMap::from([
+                ("found.mp3".into(), entry("Found", Some("Artist"))),
+                ("missing.mp3".into(), entry("Missing", Some("Artist"))),
+                ("untagged.mp3".into(), entry("Untagged", None)),
+            ]),
+        };
+        let candidates = Vector::unit(catalog_track("Found", "Artist"));
+
+        let summary = library.match_catalog(|_| Ok(candidates.clone())).unwrap();
+        assert_eq!((summary.checked, summary.matched), (3, 1));
+        assert!(library.entries[Path::new("found.mp3")].catalog_checked);
+        assert!(!library.entries[Path::new("missing.mp3")].catalog_checked);
+        assert!(library.entries[Path::new("untagged.mp3")].catalog_checked);
+
+        let mut queries = Vec::new();
+        let summary = library
+            .match_catalog(|query| {
+                queries.push(query.to_string());
+                Ok(candidates.clone())
+            })
+            .unwrap();
+        assert_eq!((summary.checked, summary.matched), (1, 0));
+        assert_eq!(queries, ["track:Missing artist:Artist"]);
+    }
+
+    #[test]
+    fn failed_search_leaves_the_file_unchecked() {
+        let mut library = LocalLibrary {
+            path: None,
+            entries: HashMap::from([("song.mp3".into(), entry("Song", Some("Artist")))]),
+        };
+        let result = library.match_catalog(|_| Err(Error::WebApiError("Offline".to_string())));
+
+        assert!(result.is_err());
+        assert!(!library.entries[Path::new("song.mp3")].catalog_checked);
+    }
+}
@@ -1,20 +1,34 @@
+mod activity;
 mod album;
 mod artist;
+pub mod autostart;
+mod backup;
+mod blocklist;
 pub mod config;
 mod ctx;
+mod download;
 mod find;
 mod id;
+mod integrations;
+mod keymap;
+mod local_files;
+pub mod motion;
+mod musicbrainz;
 mod nav;
 mod playback;
 mod playlist;
+mod prefetch;
 mod promise;
+mod radio;
 mod recommend;
 mod search;
 mod show;
 mod slider_scroll_scale;
+mod stats;
 mod track;
 mod user;
 pub mod utils;
+mod wipe;
 
 use std::{
     fmt::Display,
@@ -28,26 +42,46 @@ use std::{
 
 use druid::{
     im::{HashSet, Vector},
-    Data, Lens,
+    Command, Data, Lens,
 };
 use psst_core::{item_id::ItemId, session::SessionService};
 
 pub use crate::data::{
+    activity::ActivityRecord,
     album::{Album, AlbumDetail, AlbumLink, AlbumType, Copyright, CopyrightType},
-    artist::{Artist, ArtistAlbums, ArtistDetail, ArtistLink, ArtistTracks},
-    config::{AudioQuality, Authentication, Config, Preferences, PreferencesTab, Theme},
+    artist::{
+        Artist, ArtistAlbums, ArtistDetail, ArtistEvent, ArtistLink, ArtistProfile, ArtistTracks,
+    },
+    backup::{DiffTrack, PlaylistBackups, PlaylistRestore, PlaylistSnapshot},
+    blocklist::{BlockedTrack, Blocklist},
+    config::{
+        AudioQuality, Authentication, CacheCleanup, CacheUsage, ClickToPlay, Config, LastPlayback,
+        MemoryUsage, ParentalLock, Preferences, PreferencesTab, ReducedMotion, ScreenCorner,
+        StartupPlayback, Theme, ZoomStep,
+    },
     ctx::Ctx,
-    find::{FindQuery, Finder, MatchFindQuery},
-    nav::{Nav, Route, SpotifyUrl},
+    download::{estimate_download_size, DownloadItem, DownloadStatus, Downloads, StorageInfo},
+    find::{FindQuery, Finder, MatchFindQuery, TrackFilter},
+    integrations::{Integration, IntegrationHealth, IntegrationStatus, Integrations},
+    keymap::{KeyAction, Keymap},
+    local_files::{is_supported_file, load_chapters, load_local_track, LocalLibrary, LocalTracks},
+    musicbrainz::{MusicBrainzRelease, Relation},
+    nav::{is_liked_songs_link, Nav, Route},
     playback::{
-        NowPlaying, Playable, PlayableMatcher, Playback, PlaybackOrigin, PlaybackPayload,
-        PlaybackState, QueueBehavior, QueueEntry,
+        Canvas, Chapter, ListenAlong, ListenAlongMode, LoopSection, NowPlaying, Playable,
+        PlayableMatcher, Playback, PlaybackOrigin, PlaybackPayload, PlaybackState, QueueBehavior,
+        QueueEntry, QueueFile, QueueFileItem, QueuePlacement, QueueRequest, QueueSource,
     },
     playlist::{
-        Playlist, PlaylistAddTrack, PlaylistDetail, PlaylistLink, PlaylistRemoveTrack,
-        PlaylistTracks,
+        NewPlaylist, NewPlaylistTracks, Playlist, PlaylistAddTrack, PlaylistDetail, PlaylistLink,
+        PlaylistRemoveTrack, PlaylistTracks,
     },
+    prefetch::{TrackMetadata, TrackPrefetch},
     promise::{Promise, PromiseState},
+    radio::{
+        RadioBuilder, RadioSeed, RadioSeedKind, MAX_RADIO_SEEDS, MAX_RADIO_WEIGHT,
+        MIN_RADIO_WEIGHT, RADIO_LENGTH,
+    },
     recommend::{
         Range, Recommend, Recommendations, RecommendationsKnobs, RecommendationsParams,
         RecommendationsRequest, Toggled,
@@ -55,9 +89,11 @@ pub use crate::data::{
     search::{Search, SearchResults, SearchTopic},
     show::{Episode, EpisodeId, EpisodeLink, Show, ShowDetail, ShowEpisodes, ShowLink},
     slider_scroll_scale::SliderScrollScale,
+    stats::{ExportContent, ExportFormat, HistoryRules, PlayRecord, Statistics, StatsExport},
     track::{AudioAnalysis, AudioSegment, TimeInterval, Track, TrackId},
     user::UserProfile,
-    utils::{Cached, Float64, Image, Page},
+    utils::{Cached, Float64, Image, LoadProgress, Page},
+    wipe::ProfileData,
 };
 
 #[derive(Clone, Data, Lens)]
@@ -71,6 +107,7 @@ pub struct AppState {
     pub playback: Playback,
     pub search: Search,
     pub recommend: Recommend,
+    pub radio: RadioBuilder,
     pub album_detail: AlbumDetail,
     pub artist_detail: ArtistDetail,
     pub playlist_detail: PlaylistDetail,
@@ -79,7 +116,13 @@ pub struct AppState {
     pub common_ctx: Arc<CommonCtx>,
     pub personalized: Personalized,
     pub alerts: Vector<Alert>,
+    pub integrations: Integrations,
     pub finder: Finder,
+    pub downloads: Downloads,
+    /// Progress of saving or removing many tracks at once, see `LibraryBatch`.
+    pub library_batch: Option<LoadProgress>,
+    /// Platform hint for reduced motion, queried in the background at startup.
+    pub system_prefers_reduced_motion: bool,
 }
 
 impl AppState {
@@ -89,19 +132,34 @@ impl AppState {
             saved_albums: Promise::Empty,
             saved_tracks: Promise::Empty,
             saved_shows: Promise::Empty,
+            local_tracks: Promise::Empty,
             playlists: Promise::Empty,
         });
         let common_ctx = Arc::new(CommonCtx {
             now_playing: None,
             library: Arc::clone(&library),
             show_track_cover: config.show_track_cover,
+            click_to_play: config.click_to_play,
+            filter_explicit: config.filter_explicit,
+            selection: None,
+            track_filter: TrackFilter::default(),
+            track_metadata: TrackMetadata::with_limit(config.track_metadata_limit()),
         });
         let playback = Playback {
             state: PlaybackState::Stopped,
             now_playing: None,
             queue_behavior: config.queue_behavior,
             queue: Vector::new(),
+            user_queue: Vector::new(),
+            up_next: None,
             volume: config.volume,
+            output_device: None,
+            private_session: false,
+            listen_along: ListenAlong {
+                address: String::new(),
+                token: String::new(),
+                mode: ListenAlongMode::Off,
+            },
         };
         Self {
             session: SessionService::empty(),
@@ -116,6 +174,14 @@ impl AppState {
                     result: Promise::Empty,
                 },
                 cache_size: Promise::Empty,
+                export: StatsExport::new(),
+                pin: String::new(),
+                pin_rejected: false,
+                unlocked: false,
+                activity: Vector::new(),
+                cache_cleanup: CacheCleanup::default(),
+                memory_usage: MemoryUsage::default(),
+                search: String::new(),
             },
             playback,
             search: Search {
@@ -123,17 +189,25 @@ impl AppState {
                 results: Promise::Empty,
             },
             recommend: Recommend {
+                tuning: false,
                 knobs: Default::default(),
                 results: Promise::Empty,
             },
+            radio: RadioBuilder {
+                seeds: Vector::new(),
+                genre: String::new(),
+                results: Promise::Empty,
+            },
             album_detail: AlbumDetail {
                 album: Promise::Empty,
+                musicbrainz: Promise::Empty,
             },
             artist_detail: ArtistDetail {
                 artist: Promise::Empty,
                 albums: Promise::Empty,
                 top_tracks: Promise::Empty,
                 related_artists: Promise::Empty,
+                events: Promise::Empty,
             },
             playlist_detail: PlaylistDetail {
                 playlist: Promise::Empty,
@@ -149,7 +223,11 @@ impl AppState {
                 made_for_you: Promise::Empty,
             },
             alerts: Vector::new(),
+            integrations: Integrations::default(),
             finder: Finder::new(),
+            downloads: Downloads::default(),
+            library_batch: None,
+            system_prefers_reduced_motion: false,
         }
     }
 }
@@ -161,6 +239,7 @@ impl AppState {
             self.history.push_back(previous);
             self.config.last_route.replace(nav.to_owned());
             self.config.save();
+            self.reset_track_filter();
         }
     }
 
@@ -169,9 +248,15 @@ impl AppState {
             self.config.last_route.replace(nav.clone());
             self.config.save();
             self.nav = nav;
+            self.reset_track_filter();
         }
     }
 
+    fn reset_track_filter(&mut self) {
+        let origin = PlaybackOrigin::from_nav(&self.nav);
+        self.common_ctx_mut().track_filter = TrackFilter::new(origin);
+    }
+
     pub fn refresh(&mut self) {
         let current: Nav = mem::replace(&mut self.nav, Nav::Home);
         self.nav = current;
@@ -181,19 +266,52 @@ impl AppState {
 impl AppState {
     pub fn queued_entry(&self, item_id: ItemId) -> Option<QueueEntry> {
         self.playback
-            .queue
+            .user_queue
             .iter()
+            .chain(self.playback.queue.iter())
             .find(|queued| queued.item.id() == item_id)
             .cloned()
     }
 
+    pub fn add_to_user_queue(
+        &mut self,
+        mut entries: Vector<QueueEntry>,
+        placement: QueuePlacement,
+    ) {
+        match placement {
+            QueuePlacement::Next => {
+                entries.append(self.playback.user_queue.clone());
+                self.playback.user_queue = entries;
+            }
+            QueuePlacement::Last => {
+                self.playback.user_queue.append(entries);
+            }
+        }
+    }
+
+    /// Remove a user-queued item once the player has picked it up.
+    pub fn take_user_queued(&mut self, item_id: ItemId) {
+        if let Some(index) = self
+            .playback
+            .user_queue
+            .iter()
+            .position(|queued| queued.item.id() == item_id)
+        {
+            self.playback.user_queue.remove(index);
+        }
+    }
+
     pub fn loading_playback(&mut self, item: Playable, origin: PlaybackOrigin) {
         self.common_ctx_mut().now_playing.take();
         self.playback.state = PlaybackState::Loading;
+        self.playback.up_next.take();
         self.playback.now_playing.replace(NowPlaying {
             item,
             origin,
             progress: Duration::default(),
+            loop_section: LoopSection::default(),
+            chapters: Vector::new(),
+            canvas: None,
             library: Arc::clone(&self.library),
         });
     }
@@ -205,6 +323,9 @@ impl AppState {
             item,
             origin,
             progress,
+            loop_section: LoopSection::default(),
+            chapters: Vector::new(),
+            canvas: None,
             library: Arc::clone(&self.library),
         });
     }
@@ -229,18 +350,63 @@ impl AppState {
 
     pub fn stop_playback(&mut self) {
         self.playback.state = PlaybackState::Stopped;
+        self.playback.user_queue.clear();
+        self.playback.up_next.take();
         self.playback.now_playing.take();
         self.common_ctx_mut().now_playing.take();
     }
 
+    /// Remove explicit items from `items` if the content filter is enabled, moving
+    /// `position` so it keeps pointing at the same item, or the one following it.
+    pub fn filter_playable(
+        &self,
+        items: &Vector<Playable>,
+        position: usize,
+    ) -> (Vector<Playable>, usize) {
+        if !self.config.filter_explicit {
+            return (items.clone(), position);
+        }
+        let kept_before = items
+            .iter()
+            .take(position)
+            .filter(|item| !item.is_explicit())
+            .count();
+        let filtered = items
+            .iter()
+            .filter(|item| !item.is_explicit())
+            .cloned()
+            .collect();
+        (filtered, kept_before)
+    }
+
+    pub fn toggle_side_panel(&mut self, panel: SidePanel) {
+        if self.config.side_panel == Some(panel) {
+            self.config.side_panel = None;
+        } else {
+            self.config.side_panel = Some(panel);
+        }
+        self.config.save();
+    }
+
     pub fn set_queue_behavior(&mut self, queue_behavior: QueueBehavior) {
         self.playback.queue_behavior = queue_behavior;
         self.config.queue_behavior = queue_behavior;
         self.config.save();
     }
+
+    /// Switch the queue behavior for the current playback only, keeping the
+    /// saved default.
+    pub fn use_queue_behavior(&mut self, queue_behavior: QueueBehavior) {
+        self.playback.queue_behavior = queue_behavior;
+    }
 }
 
 impl AppState {
+    /// True if the parental lock is set up and wasn't unlocked with the PIN.
+    pub fn is_locked(&self) -> bool {
+        self.config.parental_lock.is_some() && !self.preferences.unlocked
+    }
+
     pub fn common_ctx_mut(&mut self) -> &mut CommonCtx {
         Arc::make_mut(&mut self.common_ctx)
     }
@@ -264,6 +430,24 @@ impl AppState {
             message: message.to_string().into(),
             style: AlertStyle::Info,
             id: Alert::fresh_id(),
+            action: None,
+        });
+    }
+
+    pub fn info_alert_with_action(
+        &mut self,
+        message: impl Display,
+        title: &str,
+        command: impl Into<Command>,
+    ) {
+        self.alerts.push_back(Alert {
+            message: message.to_string().into(),
+            style: AlertStyle::Info,
+            id: Alert::fresh_id(),
+            action: Some(AlertAction {
+                title: title.into(),
+                command: command.into(),
+            }),
         });
     }
 
@@ -272,6 +456,7 @@ impl AppState {
             message: message.to_string().into(),
             style: AlertStyle::Error,
             id: Alert::fresh_id(),
+            action: None,
         });
     }
 
@@ -280,6 +465,23 @@ impl AppState {
     }
 }
 
+/// Tracks saved to the library, or removed from it, in one go.
+#[derive(Clone, Debug, Data)]
+pub struct LibraryBatch {
+    pub tracks: Vector<Arc<Track>>,
+    pub save: bool,
+}
+
+impl LibraryBatch {
+    /// Batch reverting this one.
+    pub fn undo(&self) -> Self {
+        Self {
+            tracks: self.tracks.clone(),
+            save: !self.save,
+        }
+    }
+}
+
 #[derive(Clone, Data, Lens)]
 pub struct Library {
     pub user_profile: Promise<UserProfile>,
@@ -287,6 +489,7 @@ pub struct Library {
     pub saved_albums: Promise<SavedAlbums>,
     pub saved_tracks: Promise<SavedTracks>,
     pub saved_shows: Promise<SavedShows>,
+    pub local_tracks: Promise<LocalTracks>,
 }
 
 impl Library {
@@ -306,7 +509,9 @@ impl Library {
 
     pub fn contains_track(&self, track: &Track) -> bool {
         if let Some(saved) = self.saved_tracks.resolved() {
-            saved.set.contains(&track.id)
+            track
+                .catalog_track_id()
+                .map_or(false, |id| saved.set.contains(&id))
         } else {
             false
         }
@@ -474,17 +679,58 @@ impl SavedShows {
     }
 }
 
-#[derive(Clone, Data)]
+#[derive(Clone, Data, Lens)]
 pub struct CommonCtx {
     pub now_playing: Option<Playable>,
     pub library: Arc<Library>,
     pub show_track_cover: bool,
+    pub click_to_play: ClickToPlay,
+    pub filter_explicit: bool,
+    pub selection: Option<Selection>,
+    pub track_filter: TrackFilter,
+    pub track_metadata: TrackMetadata,
 }
 
 impl CommonCtx {
+    /// Whether the track is saved, if known already.
+    pub fn is_track_saved(&self, track: &Track) -> Option<bool> {
+        if self.library.saved_tracks.is_resolved() {
+            Some(self.library.contains_track(track))
+        } else {
+            let id = track.catalog_track_id()?;
+            self.track_metadata.saved.get(&id).copied()
+        }
+    }
+
+    pub fn unavailable_reason(&self, track: &Track) -> Option<&'static str> {
+        match self.track_metadata.details(track) {
+            Some(details) if track.is_playable.is_none() => {
+                details.unavailable_reason(self.filter_explicit)
+            }
+            _ => track.unavailable_reason(self.filter_explicit),
+        }
+    }
+
+    pub fn track_popularity(&self, track: &Track) -> Option<u32> {
+        track
+            .popularity
+            .or_else(|| self.track_metadata.details(track)?.popularity)
+    }
+
     pub fn is_playing(&self, item: &Playable) -> bool {
         matches!(&self.now_playing, Some(i) if i.same(item))
     }
+
+    pub fn is_selected(&self, origin: &PlaybackOrigin, position: usize) -> bool {
+        matches!(&self.selection, Some(s) if s.position == position && s.origin.same(origin))
+    }
+}
+
+/// Row selected in one of the track lists, identified by the list it belongs to.
+#[derive(Clone, Data)]
+pub struct Selection {
+    pub origin: PlaybackOrigin,
+    pub position: usize,
 }
 
 pub type WithCtx<T> = Ctx<Arc<CommonCtx>, T>;
@@ -501,6 +747,7 @@ pub struct Alert {
     pub id: usize,
     pub message: Arc<str>,
     pub style: AlertStyle,
+    pub action: Option<AlertAction>,
 }
 
 impl Alert {
@@ -509,6 +756,14 @@ impl Alert {
     }
 }
 
+/// Button shown next to the message of an alert, like "Undo".
+#[derive(Clone, Data)]
+pub struct AlertAction {
+    pub title: Arc<str>,
+    #[data(ignore)]
+    pub command: Command,
+}
+
 #[derive(Clone, Data, Eq, PartialEq)]
 pub enum AlertStyle {
     Error,
@@ -0,0 +1,71 @@
+//! Reading the platform's preference for reduced motion.  There is no common API
+//! for it, so we ask the desktop settings the same way the user would.
+
+#[cfg(unix)]
+use std::process::{Command, Stdio};
+
+/// Whether the platform asks for animations to be turned off.  This can take a
+/// while, so it's queried once at startup, off the UI thread, and a restart is
+/// needed to pick up changes.
+pub fn query_system_preference() -> bool {
+    let prefers = query().unwrap_or(false);
+    log::info!("system prefers reduced motion: {}", prefers);
+    prefers
+}
+
+#[cfg(unix)]
+fn output(command: &mut Command) -> Option<String> {
+    let output = command.stderr(Stdio::null()).output().ok()?;
+    if output.status.success() {
+        String::from_utf8(output.stdout).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn query() -> Option<bool> {
+    let value = output(Command::new("gsettings").args([
+        "get",
+        "org.gnome.desktop.interface",
+        "enable-animations",
+    ]))?;
+    Some(value.trim() == "false")
+}
+
+#[cfg(target_os = "macos")]
+fn query() -> Option<bool> {
+    let value = output(Command::new("defaults").args([
+        "read",
+        "com.apple.universalaccess",
+        "reduceMotion",
+    ]))?;
+    Some(value.trim() == "1")
+}
+
+#[cfg(windows)]
+fn query() -> Option<bool> {
+    use windows::Win32::{
+        Foundation::BOOL,
+        UI::WindowsAndMessaging::{
+            SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+        },
+    };
+
+    // "Show animations in Windows" in the accessibility settings.
+    let mut enabled = BOOL(1);
+    let ok = unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            Some(&mut enabled as *mut BOOL as *mut _),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+    ok.as_bool().then(|| !enabled.as_bool())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn query() -> Option<bool> {
+    None
+}
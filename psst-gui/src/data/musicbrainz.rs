@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use druid::{im::Vector, Data, Lens};
+use itertools::Itertools;
+use serde::Deserialize;
+
+/// Release on MusicBrainz matching an album by its barcode, with the details
+/// Spotify doesn't have.
+#[derive(Clone, Debug, Data, Lens, Deserialize)]
+pub struct MusicBrainzRelease {
+    pub id: Arc<str>,
+    #[serde(rename = "release-group")]
+    pub release_group: ReleaseGroup,
+    #[serde(rename = "label-info", default)]
+    pub label_info: Vector<LabelInfo>,
+    #[serde(default)]
+    pub relations: Vector<Relation>,
+}
+
+#[derive(Clone, Debug, Data, Lens, Deserialize)]
+pub struct ReleaseGroup {
+    #[serde(rename = "first-release-date")]
+    pub first_release_date: Option<Arc<str>>,
+}
+
+#[derive(Clone, Debug, Data, Lens, Deserialize)]
+pub struct LabelInfo {
+    #[serde(rename = "catalog-number")]
+    pub catalog_number: Option<Arc<str>>,
+    pub label: Option<Label>,
+}
+
+#[derive(Clone, Debug, Data, Lens, Deserialize)]
+pub struct Label {
+    pub name: Arc<str>,
+}
+
+#[derive(Clone, Debug, Data, Lens, Deserialize)]
+pub struct Relation {
+    #[serde(rename = "type")]
+    pub kind: Arc<str>,
+    pub url: Option<RelationUrl>,
+}
+
+#[derive(Clone, Debug, Data, Lens, Deserialize)]
+pub struct RelationUrl {
+    pub resource: Arc<str>,
+}
+
+impl MusicBrainzRelease {
+    pub fn url(&self) -> String {
+        format!("https://musicbrainz.org/release/{id}", id = self.id)
+    }
+
+    /// Year of the first release of any edition, which is often earlier than the
+    /// date of a remaster on Spotify.
+    pub fn original_year(&self) -> Option<&str> {
+        self.release_group
+            .first_release_date
+            .as_deref()
+            .and_then(|date| date.get(..4))
+    }
+
+    /// Labels with their catalog numbers, e.g. "Warp – WARPCD92".
+    pub fn catalog_numbers(&self) -> String {
+        self.label_info
+            .iter()
+            .filter_map(|info| {
+                let label = info.label.as_ref().map(|label| label.name.as_ref());
+                match (label, info.catalog_number.as_deref()) {
+                    (Some(label), Some(number)) => Some(format!("{} – {}", label, number)),
+                    (Some(label), None) => Some(label.to_string()),
+                    (None, Some(number)) => Some(number.to_string()),
+                    (None, None) => None,
+                }
+            })
+            .join(", ")
+    }
+
+    /// Links to other sites about the release, e.g. Discogs or Wikidata.
+    pub fn links(&self) -> Vector<Relation> {
+        self.relations
+            .iter()
+            .filter(|relation| relation.url.is_some())
+            .cloned()
+            .collect()
+    }
+}
@@ -14,27 +14,33 @@ pub enum Route {
     SavedTracks,
     SavedAlbums,
     SavedShows,
+    LocalFiles,
+    Downloads,
     SearchResults,
     ArtistDetail,
     AlbumDetail,
     ShowDetail,
     PlaylistDetail,
     Recommendations,
+    RadioBuilder,
 }
 
-#[derive(Default, Clone, Debug, Data, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Default, Clone, Debug, Data, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum Nav {
     #[default]
     Home,
     SavedTracks,
     SavedAlbums,
     SavedShows,
+    LocalFiles,
+    Downloads,
     SearchResults(Arc<str>),
     ArtistDetail(ArtistLink),
     AlbumDetail(AlbumLink),
     ShowDetail(ShowLink),
     PlaylistDetail(PlaylistLink),
     Recommendations(Arc<RecommendationsRequest>),
+    RadioBuilder,
 }
 
 impl Nav {
@@ -44,12 +50,15 @@ impl Nav {
             Nav::SavedTracks => Route::SavedTracks,
             Nav::SavedAlbums => Route::SavedAlbums,
             Nav::SavedShows => Route::SavedShows,
+            Nav::LocalFiles => Route::LocalFiles,
+            Nav::Downloads => Route::Downloads,
             Nav::SearchResults(_) => Route::SearchResults,
             Nav::ArtistDetail(_) => Route::ArtistDetail,
             Nav::AlbumDetail(_) => Route::AlbumDetail,
             Nav::PlaylistDetail(_) => Route::PlaylistDetail,
             Nav::ShowDetail(_) => Route::ShowDetail,
             Nav::Recommendations(_) => Route::Recommendations,
+            Nav::RadioBuilder => Route::RadioBuilder,
         }
     }
 
@@ -59,12 +68,15 @@ impl Nav {
             Nav::SavedTracks => "Saved Tracks".to_string(),
             Nav::SavedAlbums => "Saved Albums".to_string(),
             Nav::SavedShows => "Saved Podcasts".to_string(),
+            Nav::LocalFiles => "Local Files".to_string(),
+            Nav::Downloads => "Downloads".to_string(),
             Nav::SearchResults(query) => query.to_string(),
             Nav::AlbumDetail(link) => link.name.to_string(),
             Nav::ArtistDetail(link) => link.name.to_string(),
             Nav::PlaylistDetail(link) => link.name.to_string(),
             Nav::ShowDetail(link) => link.name.to_string(),
             Nav::Recommendations(_) => "Recommended".to_string(),
+            Nav::RadioBuilder => "Radio Builder".to_string(),
         }
     }
 
@@ -74,49 +86,35 @@ impl Nav {
             Nav::SavedTracks => "Saved Tracks".to_string(),
             Nav::SavedAlbums => "Saved Albums".to_string(),
             Nav::SavedShows => "Saved Shows".to_string(),
+            Nav::LocalFiles => "Local Files".to_string(),
+            Nav::Downloads => "Downloads".to_string(),
             Nav::SearchResults(query) => format!("Search “{}”", query),
             Nav::AlbumDetail(link) => format!("Album “{}”", link.name),
             Nav::ArtistDetail(link) => format!("Artist “{}”", link.name),
             Nav::PlaylistDetail(link) => format!("Playlist “{}”", link.name),
             Nav::ShowDetail(link) => format!("Show “{}”", link.name),
             Nav::Recommendations(_) => "Recommended".to_string(),
+            Nav::RadioBuilder => "Radio Builder".to_string(),
         }
     }
 }
 
-#[derive(Clone, Debug, Data, Eq, PartialEq, Hash)]
-pub enum SpotifyUrl {
-    Playlist(Arc<str>),
-    Artist(Arc<str>),
-    Album(Arc<str>),
-    Track(Arc<str>),
-    Show(Arc<str>),
-}
+/// Context URI of the saved tracks of the user, known as Liked Songs in the
+/// official clients.
+pub const LIKED_SONGS_URI: &str = "spotify:collection:tracks";
 
-impl SpotifyUrl {
-    pub fn parse(url: &str) -> Option<Self> {
-        let url = Url::parse(url).ok()?;
-        let mut segments = url.path_segments()?;
-        let entity = segments.next()?;
-        let id = segments.next()?;
-        log::info!("url: {:?}", url);
-        match entity {
-            "playlist" => Some(Self::Playlist(id.into())),
-            "artist" => Some(Self::Artist(id.into())),
-            "album" => Some(Self::Album(id.into())),
-            "track" => Some(Self::Track(id.into())),
-            "show" => Some(Self::Show(id.into())),
-            _ => None,
-        }
-    }
-
-    pub fn id(&self) -> Arc<str> {
-        match self {
-            SpotifyUrl::Playlist(id) => id.clone(),
-            SpotifyUrl::Artist(id) => id.clone(),
-            SpotifyUrl::Album(id) => id.clone(),
-            SpotifyUrl::Track(id) => id.clone(),
-            SpotifyUrl::Show(id) => id.clone(),
+/// Liked Songs go by `spotify:collection:tracks`, `spotify:user:<id>:collection`,
+/// or `open.spotify.com/collection/tracks`.  They have no `SpotifyUri` of their own.
+pub fn is_liked_songs_link(url: &str) -> bool {
+    match url.trim().strip_prefix("spotify:") {
+        Some(uri) => {
+            uri == "collection:tracks"
+                || uri == "collection"
+                || uri
+                    .strip_prefix("user:")
+                    .and_then(|uri| uri.split_once(':'))
+                    .map_or(false, |(_, rest)| rest == "collection")
         }
+        None => Url::parse(url.trim()).map_or(false, |url| url.path() == "/collection/tracks"),
     }
 }
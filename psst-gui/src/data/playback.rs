@@ -1,13 +1,20 @@
-use std::{fmt, sync::Arc, time::Duration};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use druid::{im::Vector, Data, Lens};
 use druid_enums::Matcher;
-use psst_core::item_id::ItemId;
+use psst_core::{item_id::ItemId, uri::SpotifyUri};
 use serde::{Deserialize, Serialize};
 
 use super::{
-    AlbumLink, ArtistLink, Episode, Library, Nav, PlaylistLink, RecommendationsRequest, ShowLink,
-    Track,
+    nav::LIKED_SONGS_URI, AlbumLink, ArtistLink, Episode, Library, Nav, PlaylistLink,
+    RecommendationsRequest, ShowLink, Track, TrackId,
 };
 
 #[derive(Clone, Data, Lens)]
@@ -16,7 +23,71 @@ pub struct Playback {
     pub now_playing: Option<NowPlaying>,
     pub queue_behavior: QueueBehavior,
     pub queue: Vector<QueueEntry>,
+    /// Items explicitly queued by the user, played before the rest of `queue`.
+    pub user_queue: Vector<QueueEntry>,
+    /// Entry the player announced to play after the current one, shortly before
+    /// the current one ends.
+    pub up_next: Option<QueueEntry>,
     pub volume: f64,
+    /// Name of the audio device we are playing to, if known.
+    pub output_device: Option<Arc<str>>,
+    /// While enabled, nothing is recorded into the listening history.
+    pub private_session: bool,
+    pub listen_along: ListenAlong,
+}
+
+impl Playback {
+    /// Entries of the playing context after the current one, in the order of the
+    /// context.  With shuffling on, the next one is picked from them at random.
+    pub fn upcoming(&self) -> Vector<QueueEntry> {
+        let current = match &self.now_playing {
+            Some(now_playing) => now_playing.item.id(),
+            None => return Vector::new(),
+        };
+        match self
+            .queue
+            .iter()
+            .position(|entry| entry.item.id() == current)
+        {
+            Some(position) => self.queue.clone().slice(position + 1..),
+            None => Vector::new(),
+        }
+    }
+
+    /// All entries in the order they play in: the context up to the playing entry,
+    /// the entries queued by the user, then the rest of the context.
+    pub fn in_playback_order(&self) -> Vector<QueueEntry> {
+        let split = self
+            .now_playing
+            .as_ref()
+            .and_then(|now_playing| {
+                let current = now_playing.item.id();
+                self.queue
+                    .iter()
+                    .position(|entry| entry.item.id() == current)
+            })
+            .map_or(0, |position| position + 1);
+        let (mut entries, rest) = self.queue.clone().split_at(split);
+        entries.append(self.user_queue.clone());
+        entries.append(rest);
+        entries
+    }
+}
+
+#[derive(Clone, Debug, Data, Lens)]
+pub struct ListenAlong {
+    /// Address to host on, or of the host to follow.
+    pub address: String,
+    /// Token of the session, handed out by the host to its followers.
+    pub token: String,
+    pub mode: ListenAlongMode,
+}
+
+#[derive(Copy, Clone, Debug, Data, Eq, PartialEq)]
+pub enum ListenAlongMode {
+    Off,
+    Hosting,
+    Following,
 }
 
 #[derive(Clone, Debug, Data, Lens)]
@@ -54,6 +125,13 @@ impl Playable {
         }
     }
 
+    pub fn is_explicit(&self) -> bool {
+        match self {
+            Playable::Track(track) => track.explicit,
+            Playable::Episode(_) => false,
+        }
+    }
+
     pub fn duration(&self) -> Duration {
         match self {
             Playable::Track(track) => track.duration,
@@ -84,6 +162,11 @@ pub struct NowPlaying {
     pub item: Playable,
     pub origin: PlaybackOrigin,
     pub progress: Duration,
+    pub loop_section: LoopSection,
+    /// Chapters of long local files, loaded after the playback starts.
+    pub chapters: Vector<Chapter>,
+    /// Canvas of the track, also loaded after the playback starts.
+    pub canvas: Option<Canvas>,
 
     // Although keeping a ref to the `Library` here is a bit of a hack, it dramatically
     // simplifies displaying the track context menu in the playback bar.
@@ -91,6 +174,14 @@ pub struct NowPlaying {
 }
 
 impl NowPlaying {
+    /// Canvas image of the track if it has one, the cover otherwise.
+    pub fn artwork_url(&self, width: f64, height: f64) -> Option<&str> {
+        match &self.canvas {
+            Some(canvas) => Some(&canvas.url),
+            None => self.cover_image_url(width, height),
+        }
+    }
+
     pub fn cover_image_url(&self, width: f64, height: f64) -> Option<&str> {
         match &self.item {
             Playable::Track(track) => {
@@ -103,11 +194,46 @@ impl NowPlaying {
             Playable::Episode(episode) => Some(&episode.image(width, height)?.url),
         }
     }
+
+    /// Index of the chapter containing the playback position.
+    pub fn current_chapter(&self) -> Option<usize> {
+        self.chapters
+            .iter()
+            .rposition(|chapter| chapter.start <= self.progress)
+    }
+}
+
+#[derive(Clone, Debug, Data)]
+pub struct Chapter {
+    pub title: Arc<str>,
+    pub start: Duration,
+}
+
+/// Artwork attached to a track, shown instead of the album cover.  Only the
+/// canvases that are still images are loaded, the looping videos aren't.
+#[derive(Clone, Debug, Data)]
+pub struct Canvas {
+    pub url: Arc<str>,
+}
+
+/// A-B repeat markers of the playing item.
+#[derive(Copy, Clone, Debug, Default, Data)]
+pub struct LoopSection {
+    pub start: Option<Duration>,
+    pub end: Option<Duration>,
+}
+
+impl LoopSection {
+    pub fn bounds(&self) -> Option<(Duration, Duration)> {
+        Some((self.start?, self.end?))
+    }
 }
 
 #[derive(Clone, Debug, Data)]
 pub enum PlaybackOrigin {
     Library,
+    LikedSongs,
+    LocalFiles,
     Album(AlbumLink),
     Artist(ArtistLink),
     Playlist(PlaylistLink),
@@ -117,9 +243,45 @@ pub enum PlaybackOrigin {
 }
 
 impl PlaybackOrigin {
+    /// Reverse of `to_nav`, pages that can't be played from map to the library.
+    pub fn from_nav(nav: &Nav) -> Self {
+        match nav {
+            Nav::AlbumDetail(link) => PlaybackOrigin::Album(link.clone()),
+            Nav::ArtistDetail(link) => PlaybackOrigin::Artist(link.clone()),
+            Nav::PlaylistDetail(link) => PlaybackOrigin::Playlist(link.clone()),
+            Nav::ShowDetail(link) => PlaybackOrigin::Show(link.clone()),
+            Nav::SearchResults(query) => PlaybackOrigin::Search(query.clone()),
+            Nav::Recommendations(request) => PlaybackOrigin::Recommendations(request.clone()),
+            Nav::LocalFiles => PlaybackOrigin::LocalFiles,
+            Nav::SavedTracks => PlaybackOrigin::LikedSongs,
+            Nav::Home | Nav::SavedAlbums | Nav::SavedShows | Nav::Downloads | Nav::RadioBuilder => {
+                PlaybackOrigin::Library
+            }
+        }
+    }
+
+    /// URI identifying the context, used to remember per-context preferences.
+    /// Search results and recommendations change too often to have one.
+    pub fn context_uri(&self) -> Option<String> {
+        match &self {
+            PlaybackOrigin::Library => Some("spotify:collection".to_string()),
+            PlaybackOrigin::LikedSongs => Some(LIKED_SONGS_URI.to_string()),
+            PlaybackOrigin::Album(link) => Some(SpotifyUri::Album(link.id.clone()).to_string()),
+            PlaybackOrigin::Artist(link) => Some(SpotifyUri::Artist(link.id.clone()).to_string()),
+            PlaybackOrigin::Playlist(link) => {
+                Some(SpotifyUri::Playlist(link.id.clone()).to_string())
+            }
+            PlaybackOrigin::Show(link) => Some(SpotifyUri::Show(link.id.clone()).to_string()),
+            PlaybackOrigin::LocalFiles
+            | PlaybackOrigin::Search(_)
+            | PlaybackOrigin::Recommendations(_) => None,
+        }
+    }
+
     pub fn to_nav(&self) -> Nav {
         match &self {
-            PlaybackOrigin::Library => Nav::SavedTracks,
+            PlaybackOrigin::Library | PlaybackOrigin::LikedSongs => Nav::SavedTracks,
+            PlaybackOrigin::LocalFiles => Nav::LocalFiles,
             PlaybackOrigin::Album(link) => Nav::AlbumDetail(link.clone()),
             PlaybackOrigin::Artist(link) => Nav::ArtistDetail(link.clone()),
             PlaybackOrigin::Playlist(link) => Nav::PlaylistDetail(link.clone()),
@@ -133,7 +295,8 @@ impl PlaybackOrigin {
 impl fmt::Display for PlaybackOrigin {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
-            PlaybackOrigin::Library => f.write_str("Saved Tracks"),
+            PlaybackOrigin::Library | PlaybackOrigin::LikedSongs => f.write_str("Saved Tracks"),
+            PlaybackOrigin::LocalFiles => f.write_str("Local Files"),
             PlaybackOrigin::Album(link) => link.name.fmt(f),
             PlaybackOrigin::Artist(link) => link.name.fmt(f),
             PlaybackOrigin::Playlist(link) => link.name.fmt(f),
@@ -150,3 +313,127 @@ pub struct PlaybackPayload {
     pub items: Vector<Playable>,
     pub position: usize,
 }
+
+/// Container of tracks that can be put into the user queue as a whole.  Tracks are
+/// loaded only once the request is handled.
+#[derive(Clone, Debug, Data)]
+pub enum QueueSource {
+    Album(AlbumLink),
+    Playlist(PlaylistLink),
+    ArtistTopTracks(ArtistLink),
+    /// Single track, local or streamed, together with where it's queued from.
+    Track(Arc<Track>, PlaybackOrigin),
+}
+
+impl QueueSource {
+    pub fn origin(&self) -> PlaybackOrigin {
+        match self {
+            QueueSource::Album(link) => PlaybackOrigin::Album(link.clone()),
+            QueueSource::Playlist(link) => PlaybackOrigin::Playlist(link.clone()),
+            QueueSource::ArtistTopTracks(link) => PlaybackOrigin::Artist(link.clone()),
+            QueueSource::Track(_, origin) => origin.clone(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Data, Eq, PartialEq)]
+pub enum QueuePlacement {
+    /// Play right after the current item.
+    Next,
+    /// Play after all the other queued items.
+    Last,
+}
+
+#[derive(Clone, Debug, Data)]
+pub struct QueueRequest {
+    pub source: QueueSource,
+    pub placement: QueuePlacement,
+}
+
+/// Queue saved to a file, so it can be loaded again later or on another machine.
+/// Like `LastPlayback`, only tracks are kept, and the context is stored as the
+/// route it was played from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueueFile {
+    pub origin: Nav,
+    pub tracks: Vec<QueueFileItem>,
+    /// Index into `tracks` of the item that was playing.
+    pub position: usize,
+    pub progress_ms: u64,
+    pub queue_behavior: QueueBehavior,
+    #[serde(default)]
+    pub user_queue: Vec<QueueFileItem>,
+}
+
+/// Entry of an exported queue.  Streamed tracks are stored by their ID, local files
+/// by their path, so both can be mixed in one file.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum QueueFileItem {
+    Spotify(TrackId),
+    Local { path: PathBuf },
+}
+
+impl QueueFileItem {
+    fn from_track(track: &Track) -> Option<Self> {
+        if track.is_local {
+            let path = track.local_path.as_deref()?;
+            Some(Self::Local { path: path.into() })
+        } else {
+            Some(Self::Spotify(track.id))
+        }
+    }
+
+    pub fn id(&self) -> TrackId {
+        match self {
+            Self::Spotify(id) => *id,
+            Self::Local { path } => TrackId(ItemId::from_local(path.to_owned())),
+        }
+    }
+}
+
+impl QueueFile {
+    pub fn new(playback: &Playback) -> Option<Self> {
+        let now_playing = playback.now_playing.as_ref()?;
+        let mut tracks = Vec::new();
+        let mut position = 0;
+        for entry in &playback.queue {
+            if let Some(item) = entry
+                .item
+                .track()
+                .and_then(|track| QueueFileItem::from_track(track))
+            {
+                if entry.item.same(&now_playing.item) {
+                    position = tracks.len();
+                }
+                tracks.push(item);
+            }
+        }
+        if tracks.is_empty() {
+            return None;
+        }
+        Some(Self {
+            origin: now_playing.origin.to_nav(),
+            tracks,
+            position,
+            progress_ms: now_playing.progress.as_millis() as u64,
+            queue_behavior: playback.queue_behavior,
+            user_queue: playback
+                .user_queue
+                .iter()
+                .filter_map(|entry| entry.item.track())
+                .filter_map(|track| QueueFileItem::from_track(track))
+                .collect(),
+        })
+    }
+
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+}
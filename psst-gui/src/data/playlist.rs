@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
 use druid::{im::Vector, Data, Lens};
+use psst_core::uri::SpotifyUri;
 use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::data::{user::PublicUser, Image, Promise, Track, TrackId};
+use crate::data::{user::PublicUser, Image, LoadProgress, Promise, Track, TrackId};
 
 #[derive(Clone, Debug, Data, Lens)]
 pub struct PlaylistDetail {
@@ -17,6 +18,22 @@ pub struct PlaylistAddTrack {
     pub track_id: TrackId,
 }
 
+/// A playlist to create, together with the tracks to fill it with.
+#[derive(Clone, Debug, Data, Lens)]
+pub struct NewPlaylist {
+    pub name: Arc<str>,
+    pub tracks: NewPlaylistTracks,
+}
+
+#[derive(Clone, Debug, Data)]
+pub enum NewPlaylistTracks {
+    /// Track URIs, e.g. taken from the playback queue.
+    Uris(Vector<Arc<str>>),
+    /// The given number of most recently played tracks, read from the
+    /// listening history.
+    RecentlyPlayed(usize),
+}
+
 #[derive(Clone, Debug, Data, Lens, Deserialize)]
 pub struct PlaylistRemoveTrack {
     pub link: PlaylistLink,
@@ -34,6 +51,8 @@ pub struct Playlist {
     pub track_count: usize,
     pub owner: PublicUser,
     pub collaborative: bool,
+    /// Version of the playlist, changes with every edit.
+    pub snapshot_id: Option<Arc<str>>,
 }
 
 impl Playlist {
@@ -49,7 +68,7 @@ impl Playlist {
     }
 
     pub fn url(&self) -> String {
-        format!("https://open.spotify.com/playlist/{id}", id = self.id)
+        SpotifyUri::Playlist(self.id.clone()).to_url()
     }
 }
 
@@ -58,6 +77,8 @@ pub struct PlaylistTracks {
     pub id: Arc<str>,
     pub name: Arc<str>,
     pub tracks: Vector<Arc<Track>>,
+    /// Set while the rest of the tracks is still loading.
+    pub progress: Option<LoadProgress>,
 }
 
 impl PlaylistTracks {
@@ -86,3 +107,21 @@ where
 
     Ok(PlaylistTracksRef::deserialize(deserializer)?.total)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::utils::load_fixture;
+
+    #[test]
+    fn deserializes_playlist_with_track_count() {
+        let playlist: Playlist = load_fixture("playlist.json");
+
+        assert_eq!(&*playlist.id, "3cEYpjA9oz9GiPac4AsH4n");
+        assert_eq!(playlist.track_count, 5);
+        assert_eq!(&*playlist.owner.id, "jmperezperez");
+        assert!(!playlist.collaborative);
+        assert!(playlist.snapshot_id.is_some());
+        assert_eq!(playlist.images[0].width, None);
+    }
+}
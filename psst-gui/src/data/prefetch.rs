@@ -0,0 +1,197 @@
+use std::{mem, sync::Arc};
+
+use druid::{
+    im::{HashMap, Vector},
+    Data,
+};
+
+use super::{AlbumLink, ArtistLink, CacheUsage, Library, Track, TrackId};
+
+/// Metadata loaded in batches for the track rows scrolled into view, so the rows
+/// show up complete without loading anything on their own.
+#[derive(Clone, Default, Data)]
+pub struct TrackMetadata {
+    /// Full track objects, for the availability and popularity that the tracks
+    /// of albums and playlists come without.  The oldest are evicted once they
+    /// take more memory than the limit.
+    tracks: HashMap<TrackId, Arc<Track>>,
+    /// IDs of `tracks`, oldest first.
+    order: Vector<TrackId>,
+    bytes: usize,
+    limit: Option<usize>,
+    /// Whether the tracks are saved, for as long as the saved tracks aren't
+    /// loaded.
+    pub saved: HashMap<TrackId, bool>,
+}
+
+/// Metadata a track row is missing.
+#[derive(Clone, Debug)]
+pub struct TrackPrefetch {
+    pub id: TrackId,
+    pub details: bool,
+    pub saved: bool,
+}
+
+impl TrackMetadata {
+    pub fn with_limit(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    pub fn details(&self, track: &Track) -> Option<&Arc<Track>> {
+        self.tracks.get(&track.id)
+    }
+
+    pub fn contains_details(&self, id: &TrackId) -> bool {
+        self.tracks.contains_key(id)
+    }
+
+    /// What is missing to show the row of `track`, if anything.  Popularity is
+    /// only asked for if the row shows it.
+    pub fn missing(
+        &self,
+        track: &Track,
+        library: &Library,
+        popularity: bool,
+    ) -> Option<TrackPrefetch> {
+        if track.is_local {
+            return None;
+        }
+        let details = (track.is_playable.is_none() || popularity && track.popularity.is_none())
+            && !self.contains_details(&track.id);
+        let saved = !library.saved_tracks.is_resolved() && !self.saved.contains_key(&track.id);
+        (details || saved).then(|| TrackPrefetch {
+            id: track.id,
+            details,
+            saved,
+        })
+    }
+
+    pub fn insert_details(&mut self, track: Arc<Track>) {
+        self.bytes += approximate_size(&track);
+        match self.tracks.insert(track.id, track.clone()) {
+            Some(old) => self.bytes -= approximate_size(&old),
+            None => self.order.push_back(track.id),
+        }
+        self.evict();
+    }
+
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+        self.evict();
+    }
+
+    pub fn usage(&self) -> CacheUsage {
+        CacheUsage {
+            entries: self.tracks.len(),
+            bytes: self.bytes,
+        }
+    }
+
+    fn evict(&mut self) {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return,
+        };
+        while self.bytes > limit {
+            let id = match self.order.pop_front() {
+                Some(id) => id,
+                None => break,
+            };
+            if let Some(track) = self.tracks.remove(&id) {
+                self.bytes -= approximate_size(&track);
+            }
+        }
+    }
+}
+
+/// Rough size of a track in memory, with the strings and links it holds.  The
+/// strings are often shared with other tracks, so this errs on the high side.
+fn approximate_size(track: &Track) -> usize {
+    let artists: usize = track
+        .artists
+        .iter()
+        .map(|artist| mem::size_of::<ArtistLink>() + artist.id.len() + artist.name.len())
+        .sum();
+    let album = track.album.as_ref().map_or(0, |album| {
+        let images: usize = album.images.iter().map(|image| image.url.len()).sum();
+        mem::size_of::<AlbumLink>() + album.id.len() + album.name.len() + images
+    });
+    mem::size_of::<Track>() + track.name.len() + artists + album
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn track(number: usize) -> Arc<Track> {
+        let track = json!({
+            "id": format!("{:0>22}", number),
+            "name": format!("Track {}", number),
+            "album": null,
+            "artists": [],
+            "duration_ms": 180_000,
+            "disc_number": 1,
+            "track_number": number,
+            "explicit": false,
+            "is_local": false,
+            "is_playable": true,
+            "popularity": null,
+        });
+        Arc::new(serde_json::from_value(track).unwrap())
+    }
+
+    /// Metadata with room for `tracks` of the tracks above.
+    fn metadata(tracks: usize) -> TrackMetadata {
+        TrackMetadata::with_limit(Some(tracks * approximate_size(&track(1))))
+    }
+
+    #[test]
+    fn oldest_tracks_are_evicted_over_the_limit() {
+        let mut metadata = metadata(2);
+        for number in 1..=3 {
+            metadata.insert_details(track(number));
+        }
+        assert!(!metadata.contains_details(&track(1).id));
+        assert!(metadata.contains_details(&track(2).id));
+        assert!(metadata.contains_details(&track(3).id));
+        assert_eq!(metadata.usage().entries, 2);
+    }
+
+    #[test]
+    fn replacing_a_track_counts_it_once() {
+        let mut metadata = metadata(2);
+        metadata.insert_details(track(1));
+        metadata.insert_details(track(1));
+        metadata.insert_details(track(2));
+        assert!(metadata.contains_details(&track(1).id));
+        assert_eq!(metadata.usage().bytes, 2 * approximate_size(&track(1)));
+    }
+
+    #[test]
+    fn lowering_the_limit_evicts() {
+        let mut metadata = metadata(3);
+        for number in 1..=3 {
+            metadata.insert_details(track(number));
+        }
+        metadata.set_limit(Some(approximate_size(&track(1))));
+        assert_eq!(metadata.usage().entries, 1);
+        assert!(metadata.contains_details(&track(3).id));
+        metadata.set_limit(Some(0));
+        assert_eq!(metadata.usage().entries, 0);
+        assert_eq!(metadata.usage().bytes, 0);
+    }
+
+    #[test]
+    fn no_limit_keeps_everything() {
+        let mut metadata = TrackMetadata::with_limit(None);
+        for number in 1..=100 {
+            metadata.insert_details(track(number));
+        }
+        assert_eq!(metadata.usage().entries, 100);
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use druid::{im::Vector, Data, Lens};
+
+use super::{ArtistLink, Promise, Recommendations, RecommendationsRequest, Track, TrackId};
+
+/// The endpoint takes up to five seeds in total, we keep to the same limit.
+pub const MAX_RADIO_SEEDS: usize = 5;
+pub const RADIO_LENGTH: usize = 50;
+pub const MIN_RADIO_WEIGHT: f64 = 1.0;
+pub const MAX_RADIO_WEIGHT: f64 = 10.0;
+pub const DEFAULT_RADIO_WEIGHT: f64 = 5.0;
+
+/// A radio mixed from several seeds, each getting a share of the tracks in
+/// proportion to its weight.
+#[derive(Clone, Data, Lens)]
+pub struct RadioBuilder {
+    pub seeds: Vector<RadioSeed>,
+    /// Genre typed into the builder, added as a seed on submit.
+    pub genre: String,
+    pub results: Promise<Recommendations, Arc<Vector<RadioSeed>>>,
+}
+
+#[derive(Clone, Debug, Data, Lens, PartialEq)]
+pub struct RadioSeed {
+    pub kind: RadioSeedKind,
+    pub name: Arc<str>,
+    pub weight: f64,
+}
+
+#[derive(Clone, Debug, Data, PartialEq)]
+pub enum RadioSeedKind {
+    Track(TrackId),
+    Artist(ArtistLink),
+    Genre,
+}
+
+impl RadioSeed {
+    pub fn track(track: &Track) -> Self {
+        Self {
+            kind: RadioSeedKind::Track(track.id),
+            name: format!("{} – {}", track.name, track.artist_name()).into(),
+            weight: DEFAULT_RADIO_WEIGHT,
+        }
+    }
+
+    pub fn artist(link: &ArtistLink) -> Self {
+        Self {
+            kind: RadioSeedKind::Artist(link.clone()),
+            name: link.name.clone(),
+            weight: DEFAULT_RADIO_WEIGHT,
+        }
+    }
+
+    /// Genre seeds are slugs like `hip-hop`, so normalize what the user typed.
+    pub fn genre(genre: &str) -> Option<Self> {
+        let slug = genre
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join("-")
+            .to_lowercase();
+        (!slug.is_empty()).then(|| Self {
+            kind: RadioSeedKind::Genre,
+            name: slug.into(),
+            weight: DEFAULT_RADIO_WEIGHT,
+        })
+    }
+
+    /// A radio of this seed alone, like the ones started from the menus.
+    pub fn into_radio(self) -> Arc<Vector<RadioSeed>> {
+        Arc::new(Vector::unit(self))
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            RadioSeedKind::Track(_) => "Track",
+            RadioSeedKind::Artist(_) => "Artist",
+            RadioSeedKind::Genre => "Genre",
+        }
+    }
+
+    pub fn same_seed(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.name == other.name
+    }
+
+    pub fn add_to(&self, request: &mut RecommendationsRequest) {
+        match &self.kind {
+            RadioSeedKind::Track(id) => request.seed_tracks.push_back(*id),
+            RadioSeedKind::Artist(link) => request.seed_artists.push_back(link.clone()),
+            RadioSeedKind::Genre => request.seed_genres.push_back(self.name.clone()),
+        }
+    }
+
+    pub fn request(&self) -> RecommendationsRequest {
+        let mut request = RecommendationsRequest::default();
+        self.add_to(&mut request);
+        request
+    }
+}
+
+impl RadioBuilder {
+    pub fn contains(&self, seed: &RadioSeed) -> bool {
+        self.seeds.iter().any(|s| s.same_seed(seed))
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.seeds.len() >= MAX_RADIO_SEEDS
+    }
+
+    pub fn add_seed(&mut self, seed: RadioSeed) {
+        if !self.contains(&seed) && !self.is_full() {
+            self.seeds.push_back(seed);
+        }
+    }
+
+    pub fn remove_seed(&mut self, seed: &RadioSeed) {
+        self.seeds.retain(|s| !s.same_seed(seed));
+    }
+
+    /// All of the seeds in one request, the way the recommendations page can
+    /// show them again.
+    pub fn request(seeds: &Vector<RadioSeed>) -> RecommendationsRequest {
+        let mut request = RecommendationsRequest::default();
+        for seed in seeds {
+            seed.add_to(&mut request);
+        }
+        request
+    }
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use druid::{
     im::{vector, Vector},
+    lens::Map,
     Data, Lens,
 };
 use serde::{Deserialize, Serialize};
@@ -10,6 +11,8 @@ use super::{ArtistLink, Float64, Promise, Track, TrackId};
 
 #[derive(Clone, Data, Lens)]
 pub struct Recommend {
+    /// Whether the tuning knobs are shown.
+    pub tuning: bool,
     pub knobs: Arc<RecommendationsKnobs>,
     pub results: Promise<Recommendations, Arc<RecommendationsRequest>>,
 }
@@ -18,6 +21,8 @@ pub struct Recommend {
 pub struct RecommendationsRequest {
     pub seed_artists: Vector<ArtistLink>,
     pub seed_tracks: Vector<TrackId>,
+    #[serde(default)]
+    pub seed_genres: Vector<Arc<str>>,
     #[serde(skip)]
     pub params: RecommendationsParams,
 }
@@ -36,14 +41,17 @@ impl RecommendationsRequest {
     }
 }
 
-#[derive(Clone, Debug, Default, Data, Lens)]
+#[derive(Clone, Debug, Data, Lens)]
 pub struct RecommendationsKnobs {
     pub duration_ms: Toggled<u64>,
-    pub popularity: Toggled<u64>,
+    pub popularity: Toggled<f64>,
     pub key: Toggled<u64>,
     pub mode: Toggled<u64>,
-    pub tempo: Toggled<u64>,
+    /// Tempo range in BPM.
+    pub min_tempo: Toggled<f64>,
+    pub max_tempo: Toggled<f64>,
     pub time_signature: Toggled<u64>,
+    pub exclude_explicit: bool,
 
     pub acousticness: Toggled<f64>,
     pub danceability: Toggled<f64>,
@@ -55,14 +63,68 @@ pub struct RecommendationsKnobs {
     pub valence: Toggled<f64>,
 }
 
+impl Default for RecommendationsKnobs {
+    fn default() -> Self {
+        Self {
+            duration_ms: Default::default(),
+            popularity: Toggled::new(50.0),
+            key: Default::default(),
+            mode: Default::default(),
+            min_tempo: Toggled::new(80.0),
+            max_tempo: Toggled::new(160.0),
+            time_signature: Default::default(),
+            exclude_explicit: false,
+            acousticness: Default::default(),
+            danceability: Default::default(),
+            energy: Default::default(),
+            instrumentalness: Default::default(),
+            liveness: Default::default(),
+            loudness: Default::default(),
+            speechiness: Default::default(),
+            valence: Default::default(),
+        }
+    }
+}
+
 impl RecommendationsKnobs {
+    /// Minimum tempo, pushing the maximum up along with it.
+    pub fn lens_min_tempo() -> impl Lens<Self, Toggled<f64>> {
+        Map::new(
+            |knobs: &Self| knobs.min_tempo,
+            |knobs: &mut Self, min_tempo: Toggled<f64>| {
+                knobs.min_tempo = min_tempo;
+                knobs.max_tempo.value = knobs.max_tempo.value.max(min_tempo.value);
+            },
+        )
+    }
+
+    /// Maximum tempo, pushing the minimum down along with it.
+    pub fn lens_max_tempo() -> impl Lens<Self, Toggled<f64>> {
+        Map::new(
+            |knobs: &Self| knobs.max_tempo,
+            |knobs: &mut Self, max_tempo: Toggled<f64>| {
+                knobs.max_tempo = max_tempo;
+                knobs.min_tempo.value = knobs.min_tempo.value.min(max_tempo.value);
+            },
+        )
+    }
+
+    fn tempo(&self) -> Range<u64> {
+        let min = self.min_tempo.rounded();
+        let max = self.max_tempo.rounded();
+        match (min, max) {
+            (Some(min), Some(max)) => Range::new(Some(min.min(max)), Some(min.max(max)), None),
+            _ => Range::new(min, max, None),
+        }
+    }
+
     pub fn as_params(&self) -> RecommendationsParams {
         RecommendationsParams {
             duration_ms: Range::new(None, None, self.duration_ms.into()),
-            popularity: Range::new(None, None, self.popularity.into()),
+            popularity: Range::new(None, None, self.popularity.rounded()),
             key: Range::new(None, None, self.key.into()),
             mode: Range::new(None, None, self.mode.into()),
-            tempo: Range::new(None, None, self.tempo.into()),
+            tempo: self.tempo(),
             time_signature: Range::new(None, None, self.time_signature.into()),
             acousticness: Range::new(None, None, self.acousticness.into()),
             danceability: Range::new(None, None, self.danceability.into()),
@@ -72,6 +134,7 @@ impl RecommendationsKnobs {
             loudness: Range::new(None, None, self.loudness.into()),
             speechiness: Range::new(None, None, self.speechiness.into()),
             valence: Range::new(None, None, self.valence.into()),
+            exclude_explicit: self.exclude_explicit,
         }
     }
 }
@@ -82,6 +145,23 @@ pub struct Toggled<T> {
     pub value: T,
 }
 
+impl<T> Toggled<T> {
+    /// Disabled, starting at `value` once enabled.
+    pub fn new(value: T) -> Self {
+        Self {
+            enabled: false,
+            value,
+        }
+    }
+}
+
+impl Toggled<f64> {
+    /// For the knobs with integer values, which are set through a slider.
+    pub fn rounded(self) -> Option<u64> {
+        self.enabled.then(|| self.value.round() as u64)
+    }
+}
+
 impl From<Toggled<u64>> for Option<u64> {
     fn from(t: Toggled<u64>) -> Self {
         if t.enabled {
@@ -119,6 +199,9 @@ pub struct RecommendationsParams {
     pub loudness: Range<Float64>,
     pub speechiness: Range<Float64>,
     pub valence: Range<Float64>,
+
+    /// The endpoint can't filter out explicit tracks, we do it after the fact.
+    pub exclude_explicit: bool,
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Data, Lens)]
@@ -163,3 +246,47 @@ pub enum RecommendationsSeedType {
     Track,
     Genre,
 }
+
+#[cfg(test)]
+mod tests {
+    use druid::LensExt;
+
+    use super::*;
+
+    fn enabled(value: f64) -> Toggled<f64> {
+        Toggled {
+            enabled: true,
+            value,
+        }
+    }
+
+    #[test]
+    fn tempo_sliders_keep_the_range_in_order() {
+        let mut knobs = RecommendationsKnobs::default();
+        RecommendationsKnobs::lens_min_tempo().put(&mut knobs, enabled(180.0));
+        assert_eq!(knobs.max_tempo.value, 180.0);
+
+        RecommendationsKnobs::lens_max_tempo().put(&mut knobs, enabled(100.0));
+        assert_eq!(knobs.min_tempo.value, 100.0);
+        assert_eq!(knobs.max_tempo.value, 100.0);
+    }
+
+    #[test]
+    fn tempo_params_are_never_inverted() {
+        let knobs = RecommendationsKnobs {
+            min_tempo: enabled(150.0),
+            max_tempo: enabled(90.0),
+            ..RecommendationsKnobs::default()
+        };
+        assert_eq!(
+            knobs.as_params().tempo,
+            Range::new(Some(90), Some(150), None)
+        );
+
+        let knobs = RecommendationsKnobs {
+            max_tempo: enabled(90.0),
+            ..RecommendationsKnobs::default()
+        };
+        assert_eq!(knobs.as_params().tempo, Range::new(None, Some(90), None));
+    }
+}
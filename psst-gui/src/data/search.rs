@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use druid::{im::Vector, Data, Lens};
 
-use crate::data::{Album, Artist, Playlist, Promise, Show, Track};
+use crate::data::{Album, Artist, Library, Playlist, Promise, Show, Track};
 
 #[derive(Clone, Data, Lens)]
 pub struct Search {
@@ -50,3 +50,22 @@ pub struct SearchResults {
     pub playlists: Vector<Playlist>,
     pub shows: Vector<Arc<Show>>,
 }
+
+impl SearchResults {
+    /// Move the results already saved in the library in front of the rest, keeping
+    /// the relative order given by the search otherwise.
+    pub fn boost_library_matches(&mut self, library: &Library) {
+        boost(&mut self.albums, |album| library.contains_album(album));
+        boost(&mut self.tracks, |track| library.contains_track(track));
+        boost(&mut self.playlists, |playlist| {
+            library.contains_playlist(playlist)
+        });
+        boost(&mut self.shows, |show| library.contains_show(show));
+    }
+}
+
+fn boost<T: Clone>(items: &mut Vector<T>, is_saved: impl Fn(&T) -> bool) {
+    let (mut saved, rest): (Vector<T>, Vector<T>) = items.iter().cloned().partition(is_saved);
+    saved.append(rest);
+    *items = saved;
+}
@@ -1,7 +1,10 @@
 use std::{convert::TryFrom, sync::Arc, time::Duration};
 
 use druid::{im::Vector, Data, Lens};
-use psst_core::item_id::{ItemId, ItemIdType};
+use psst_core::{
+    item_id::{ItemId, ItemIdType},
+    uri::SpotifyUri,
+};
 use serde::{Deserialize, Serialize};
 use time::{macros::format_description, Date};
 
@@ -51,7 +54,7 @@ pub struct ShowLink {
 
 impl ShowLink {
     pub fn url(&self) -> String {
-        format!("https://open.spotify.com/show/{id}", id = self.id)
+        SpotifyUri::Show(self.id.clone()).to_url()
     }
 }
 
@@ -80,10 +83,7 @@ impl Episode {
     }
 
     pub fn url(&self) -> String {
-        format!(
-            "https://open.spotify.com/episode/{id}",
-            id = self.id.0.to_base62()
-        )
+        SpotifyUri::Episode(self.id.0.to_base62().into()).to_url()
     }
 
     pub fn release(&self) -> String {
@@ -135,3 +135,22 @@ impl From<EpisodeId> for String {
         id.0.to_base62()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::utils::load_fixture;
+
+    #[test]
+    fn deserializes_episode_with_resume_point() {
+        let episode: Episode = load_fixture("episode.json");
+
+        assert_eq!(episode.id.0.to_base62(), "512ojhOuo1ktJprKbVcKyQ");
+        assert_eq!(&*episode.show.name, "Spotify Originals");
+        assert_eq!(episode.duration, Duration::from_millis(1686230));
+        assert_eq!(episode.release(), "Mar 05, 2020");
+        let resume_point = episode.resume_point.unwrap();
+        assert!(!resume_point.fully_played);
+        assert_eq!(resume_point.resume_position, Duration::from_secs(612));
+    }
+}
@@ -0,0 +1,575 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use druid::{Data, Lens};
+use psst_core::{cache::mkdir_if_not_exists, uri::SpotifyUri};
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, Date, OffsetDateTime};
+
+use crate::data::{
+    utils::{parse_date, NaturalKey},
+    Config, NowPlaying, Playable, Promise,
+};
+
+const HISTORY_FILENAME: &str = "history.jsonl";
+
+/// Playbacks of records without a decision ending before this point are counted
+/// as skips, unless they reached at least half of the item.
+const SKIP_THRESHOLD_MS: u64 = 30_000;
+
+/// Local store of the listening history.  Every finished (or skipped) playback
+/// is appended as a single JSON line, so the file can grow without ever being
+/// rewritten.
+#[derive(Clone, Debug)]
+pub struct Statistics {
+    path: PathBuf,
+}
+
+impl Statistics {
+    pub fn open() -> Option<Self> {
+        Config::config_dir().map(|dir| Self {
+            path: dir.join(HISTORY_FILENAME),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, record: &PlayRecord) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            mkdir_if_not_exists(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+        Ok(())
+    }
+
+    pub fn load(&self, range: DateRange) -> io::Result<Vec<PlayRecord>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<PlayRecord>(&line) {
+                Ok(record) if range.contains(&record) => records.push(record),
+                Ok(_) => {}
+                Err(err) => log::warn!("skipping malformed history record: {}", err),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Number of skips for every item in the history, keyed by URI.
+    pub fn skip_counts(&self) -> io::Result<HashMap<Arc<str>, u32>> {
+        let mut counts = HashMap::new();
+        for record in self.load(DateRange::default())? {
+            if record.is_skip() {
+                *counts.entry(record.uri).or_default() += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    /// URIs of the `count` most recently played distinct tracks, latest first.
+    pub fn recent_tracks(&self, count: usize) -> io::Result<Vec<Arc<str>>> {
+        let mut records = self.load(DateRange::default())?;
+        records.sort_by_key(|record| record.played_at);
+        let mut seen = HashSet::new();
+        let uris = records
+            .into_iter()
+            .rev()
+            .map(|record| record.uri)
+            .filter(|uri| matches!(SpotifyUri::parse(uri), Some(SpotifyUri::Track(_))))
+            .filter(|uri| seen.insert(uri.clone()))
+            .take(count)
+            .collect();
+        Ok(uris)
+    }
+
+    /// Write the records in `range` into `path`, returning the number of
+    /// exported rows.
+    pub fn export(&self, path: &Path, request: &ExportRequest) -> io::Result<usize> {
+        let records = self.load(request.range)?;
+        let mut writer = BufWriter::new(File::create(path)?);
+        let rows = match request.content {
+            ExportContent::History => {
+                let rows: Vec<_> = records.iter().map(HistoryRow::from).collect();
+                match request.format {
+                    ExportFormat::Csv => write_csv(&mut writer, HistoryRow::HEADER, &rows)?,
+                    ExportFormat::Json => serde_json::to_writer_pretty(&mut writer, &rows)?,
+                }
+                rows.len()
+            }
+            ExportContent::PlayCounts => {
+                let rows = PlayCountRow::aggregate(&records);
+                match request.format {
+                    ExportFormat::Csv => write_csv(&mut writer, PlayCountRow::HEADER, &rows)?,
+                    ExportFormat::Json => serde_json::to_writer_pretty(&mut writer, &rows)?,
+                }
+                rows.len()
+            }
+        };
+        writer.flush()?;
+        log::info!("exported {} history rows: {:?}", rows, path);
+        Ok(rows)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlayRecord {
+    pub uri: Arc<str>,
+    pub name: Arc<str>,
+    pub artist: Arc<str>,
+    pub album: Arc<str>,
+    /// Unix timestamp of the moment the playback started.
+    pub played_at: i64,
+    pub duration_ms: u64,
+    /// Playback position reached before the item ended or got skipped.
+    pub played_ms: u64,
+    /// Whether the playback was a skip according to the `HistoryRules` at the
+    /// time.  Missing in records saved before the rules were configurable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<bool>,
+}
+
+impl PlayRecord {
+    pub fn new(
+        now_playing: &NowPlaying,
+        started_at: SystemTime,
+        rules: &HistoryRules,
+    ) -> Option<Self> {
+        let uri = match &now_playing.item {
+            Playable::Track(track) if track.is_local => track.local_uri(),
+            item => item.id().to_uri()?,
+        };
+        let (artist, album) = match &now_playing.item {
+            Playable::Track(track) => (track.artist_names().into(), track.album_name()),
+            Playable::Episode(episode) => (episode.show.name.clone(), "".into()),
+        };
+        let played_at = started_at
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs() as i64)
+            .unwrap_or_default();
+        Some(Self {
+            uri: uri.into(),
+            name: now_playing.item.name().clone(),
+            artist,
+            album,
+            played_at,
+            duration_ms: now_playing.item.duration().as_millis() as u64,
+            played_ms: now_playing.progress.as_millis() as u64,
+            skipped: Some(!rules.counts(now_playing.progress, now_playing.item.duration())),
+        })
+    }
+
+    pub fn is_skip(&self) -> bool {
+        self.skipped.unwrap_or_else(|| {
+            self.played_ms < SKIP_THRESHOLD_MS && self.played_ms * 2 < self.duration_ms
+        })
+    }
+
+    fn played_at(&self) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(self.played_at).unwrap_or(OffsetDateTime::UNIX_EPOCH)
+    }
+}
+
+/// Decides which playbacks are kept in the listening history, and which of those
+/// count as plays rather than skips.
+#[derive(Clone, Debug, Data, Lens, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryRules {
+    /// A playback counts once it reaches this many seconds, or the percentage
+    /// below, whichever comes first.  Zero turns the condition off.
+    pub count_after_secs: usize,
+    pub count_after_percent: usize,
+    /// Names of the artists never recorded, one per line.
+    pub ignored_artists: String,
+    pub ignore_episodes: bool,
+    /// Items shorter than this many seconds are never recorded.
+    pub ignore_shorter_than_secs: usize,
+}
+
+impl Default for HistoryRules {
+    fn default() -> Self {
+        Self {
+            count_after_secs: 30,
+            count_after_percent: 50,
+            ignored_artists: String::new(),
+            ignore_episodes: false,
+            ignore_shorter_than_secs: 0,
+        }
+    }
+}
+
+impl HistoryRules {
+    pub fn ignores(&self, item: &Playable) -> bool {
+        if item.duration() < Duration::from_secs(self.ignore_shorter_than_secs as u64) {
+            return true;
+        }
+        match item {
+            Playable::Track(track) => track.artists.iter().any(|artist| {
+                self.ignored_artists
+                    .lines()
+                    .map(str::trim)
+                    .any(|ignored| ignored.eq_ignore_ascii_case(&artist.name))
+            }),
+            Playable::Episode(_) => self.ignore_episodes,
+        }
+    }
+
+    pub fn counts(&self, played: Duration, duration: Duration) -> bool {
+        if self.count_after_secs == 0 && self.count_after_percent == 0 {
+            return true;
+        }
+        let by_time = self.count_after_secs > 0
+            && played >= Duration::from_secs(self.count_after_secs as u64);
+        let by_share = self.count_after_percent > 0
+            && played.as_millis() * 100 >= duration.as_millis() * self.count_after_percent as u128;
+        by_time || by_share
+    }
+}
+
+/// Inclusive range of days, in UTC.  Missing bounds are unlimited.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DateRange {
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+}
+
+impl DateRange {
+    pub fn contains(&self, record: &PlayRecord) -> bool {
+        let date = record.played_at().date();
+        self.from.map_or(true, |from| date >= from) && self.to.map_or(true, |to| date <= to)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Data)]
+pub enum ExportContent {
+    History,
+    PlayCounts,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ExportRequest {
+    pub range: DateRange,
+    pub format: ExportFormat,
+    pub content: ExportContent,
+}
+
+#[derive(Clone, Debug, Data, Lens)]
+pub struct StatsExport {
+    pub from: String,
+    pub to: String,
+    pub format: ExportFormat,
+    pub content: ExportContent,
+    pub result: Promise<usize, (), String>,
+}
+
+impl StatsExport {
+    pub fn new() -> Self {
+        Self {
+            from: String::new(),
+            to: String::new(),
+            format: ExportFormat::Csv,
+            content: ExportContent::History,
+            result: Promise::Empty,
+        }
+    }
+
+    pub fn request(&self) -> Result<ExportRequest, String> {
+        fn bound(input: &str) -> Result<Option<Date>, String> {
+            if input.trim().is_empty() {
+                Ok(None)
+            } else {
+                parse_date(input)
+                    .map(Some)
+                    .ok_or_else(|| format!("Invalid date “{}”, expected YYYY-MM-DD", input))
+            }
+        }
+        let range = DateRange {
+            from: bound(&self.from)?,
+            to: bound(&self.to)?,
+        };
+        if let (Some(from), Some(to)) = (range.from, range.to) {
+            if from > to {
+                return Err("The start of the range is after its end".to_string());
+            }
+        }
+        Ok(ExportRequest {
+            range,
+            format: self.format,
+            content: self.content,
+        })
+    }
+
+    pub fn default_file_name(&self) -> String {
+        let name = match self.content {
+            ExportContent::History => "psst-history",
+            ExportContent::PlayCounts => "psst-play-counts",
+        };
+        format!("{}.{}", name, self.format.extension())
+    }
+}
+
+#[derive(Serialize)]
+struct HistoryRow<'a> {
+    played_at: String,
+    uri: &'a str,
+    name: &'a str,
+    artist: &'a str,
+    album: &'a str,
+    duration_ms: u64,
+    played_ms: u64,
+}
+
+impl HistoryRow<'_> {
+    const HEADER: &'static [&'static str] = &[
+        "played_at",
+        "uri",
+        "name",
+        "artist",
+        "album",
+        "duration_ms",
+        "played_ms",
+    ];
+}
+
+impl<'a> From<&'a PlayRecord> for HistoryRow<'a> {
+    fn from(record: &'a PlayRecord) -> Self {
+        Self {
+            played_at: record.played_at().format(&Rfc3339).unwrap_or_default(),
+            uri: &record.uri,
+            name: &record.name,
+            artist: &record.artist,
+            album: &record.album,
+            duration_ms: record.duration_ms,
+            played_ms: record.played_ms,
+        }
+    }
+}
+
+impl CsvRow for HistoryRow<'_> {
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.played_at.clone(),
+            self.uri.to_string(),
+            self.name.to_string(),
+            self.artist.to_string(),
+            self.album.to_string(),
+            self.duration_ms.to_string(),
+            self.played_ms.to_string(),
+        ]
+    }
+}
+
+#[derive(Serialize)]
+struct PlayCountRow<'a> {
+    uri: &'a str,
+    name: &'a str,
+    artist: &'a str,
+    album: &'a str,
+    /// All the recorded playbacks, skips included.
+    count: usize,
+    /// Playbacks that didn't count as plays by the `HistoryRules`.
+    skips: usize,
+    played_ms: u64,
+}
+
+impl<'a> PlayCountRow<'a> {
+    const HEADER: &'static [&'static str] = &[
+        "uri",
+        "name",
+        "artist",
+        "album",
+        "count",
+        "skips",
+        "played_ms",
+    ];
+
+    fn aggregate(records: &'a [PlayRecord]) -> Vec<Self> {
+        let mut counts: HashMap<&str, Self> = HashMap::new();
+        for record in records {
+            let row = counts.entry(record.uri.as_ref()).or_insert_with(|| Self {
+                uri: &record.uri,
+                name: &record.name,
+                artist: &record.artist,
+                album: &record.album,
+                count: 0,
+                skips: 0,
+                played_ms: 0,
+            });
+            row.count += 1;
+            if record.is_skip() {
+                row.skips += 1;
+            }
+            row.played_ms += record.played_ms;
+        }
+        let mut rows: Vec<Self> = counts.into_values().collect();
+        rows.sort_by_cached_key(|row| (Reverse(row.count), NaturalKey::new(row.name)));
+        rows
+    }
+}
+
+impl CsvRow for PlayCountRow<'_> {
+    fn fields(&self) -> Vec<String> {
+        vec![
+            self.uri.to_string(),
+            self.name.to_string(),
+            self.artist.to_string(),
+            self.album.to_string(),
+            self.count.to_string(),
+            self.skips.to_string(),
+            self.played_ms.to_string(),
+        ]
+    }
+}
+
+trait CsvRow {
+    fn fields(&self) -> Vec<String>;
+}
+
+fn write_csv(writer: &mut impl Write, header: &[&str], rows: &[impl CsvRow]) -> io::Result<()> {
+    writeln!(writer, "{}", header.join(","))?;
+    for row in rows {
+        let fields: Vec<String> = row.fields().iter().map(|f| csv_escape(f)).collect();
+        writeln!(writer, "{}", fields.join(","))?;
+    }
+    Ok(())
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{utils::load_fixture, Episode, Track};
+
+    fn track() -> Playable {
+        Playable::Track(Arc::new(load_fixture::<Track>("track.json")))
+    }
+
+    fn record(uri: &str, played_ms: u64, skipped: Option<bool>) -> PlayRecord {
+        PlayRecord {
+            uri: uri.into(),
+            name: uri.into(),
+            artist: "".into(),
+            album: "".into(),
+            played_at: 0,
+            duration_ms: 200_000,
+            played_ms,
+            skipped,
+        }
+    }
+
+    #[test]
+    fn counts_after_the_time_or_the_share() {
+        let rules = HistoryRules::default();
+        let duration = Duration::from_secs(200);
+        assert!(!rules.counts(Duration::from_secs(29), duration));
+        assert!(rules.counts(Duration::from_secs(30), duration));
+
+        let short = Duration::from_secs(40);
+        assert!(rules.counts(Duration::from_secs(20), short));
+        assert!(!rules.counts(Duration::from_secs(19), short));
+    }
+
+    #[test]
+    fn counts_everything_with_both_conditions_off() {
+        let rules = HistoryRules {
+            count_after_secs: 0,
+            count_after_percent: 0,
+            ..HistoryRules::default()
+        };
+        assert!(rules.counts(Duration::ZERO, Duration::from_secs(200)));
+
+        let by_share_only = HistoryRules {
+            count_after_secs: 0,
+            ..HistoryRules::default()
+        };
+        assert!(!by_share_only.counts(Duration::from_secs(99), Duration::from_secs(200)));
+        assert!(by_share_only.counts(Duration::from_secs(100), Duration::from_secs(200)));
+    }
+
+    #[test]
+    fn ignores_artists_regardless_of_case() {
+        let rules = HistoryRules {
+            ignored_artists: "Someone Else\n  carly rae jepsen \n".to_string(),
+            ..HistoryRules::default()
+        };
+        assert!(rules.ignores(&track()));
+        assert!(!HistoryRules::default().ignores(&track()));
+    }
+
+    #[test]
+    fn ignores_short_items_and_episodes() {
+        let rules = HistoryRules {
+            ignore_shorter_than_secs: 208,
+            ..HistoryRules::default()
+        };
+        assert!(rules.ignores(&track()));
+
+        let episode = Playable::Episode(Arc::new(load_fixture::<Episode>("episode.json")));
+        let rules = HistoryRules {
+            ignore_episodes: true,
+            ..HistoryRules::default()
+        };
+        assert!(rules.ignores(&episode));
+        assert!(!HistoryRules::default().ignores(&episode));
+    }
+
+    #[test]
+    fn play_counts_include_the_skips() {
+        let records = [
+            record("a", 100_000, Some(false)),
+            record("a", 5_000, Some(true)),
+            // Saved before the rules, judged by the old threshold.
+            record("a", 10_000, None),
+            record("b", 100_000, None),
+        ];
+        let rows = PlayCountRow::aggregate(&records);
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].uri, rows[0].count, rows[0].skips), ("a", 3, 2));
+        assert_eq!((rows[1].uri, rows[1].count, rows[1].skips), ("b", 1, 0));
+        assert_eq!(rows[0].played_ms, 115_000);
+    }
+}
@@ -2,8 +2,12 @@ use std::{convert::TryFrom, sync::Arc, time::Duration};
 
 use druid::{im::Vector, lens::Map, Data, Lens};
 use itertools::Itertools;
-use psst_core::item_id::{ItemId, ItemIdType};
+use psst_core::{
+    item_id::{ItemId, ItemIdType},
+    uri::SpotifyUri,
+};
 use serde::{Deserialize, Serialize};
+use url::form_urlencoded;
 
 use crate::data::{AlbumLink, ArtistLink};
 
@@ -23,10 +27,22 @@ pub struct Track {
     pub is_local: bool,
     #[serde(skip_deserializing)]
     pub local_path: Option<Arc<str>>,
+    /// Catalog track a local file has been matched to.
+    #[serde(skip_deserializing)]
+    pub catalog_id: Option<TrackId>,
     pub is_playable: Option<bool>,
+    /// Why the track can't be played, sent along with `is_playable: false`.
+    #[serde(default)]
+    pub restrictions: Option<TrackRestrictions>,
     pub popularity: Option<u32>,
 }
 
+#[derive(Clone, Debug, Data, Lens, Deserialize)]
+pub struct TrackRestrictions {
+    /// One of `market`, `product` or `explicit`, more might get added.
+    pub reason: Arc<str>,
+}
+
 impl Track {
     pub fn lens_artist_name() -> impl Lens<Self, Arc<str>> {
         Map::new(
@@ -67,8 +83,69 @@ impl Track {
             .unwrap_or_else(|| "Unknown".into())
     }
 
+    /// ID of the track in the catalog, for streamed tracks and matched local files.
+    pub fn catalog_track_id(&self) -> Option<TrackId> {
+        if self.is_local {
+            self.catalog_id
+        } else {
+            Some(self.id)
+        }
+    }
+
+    /// Streamed counterpart of the track, for the library actions.
+    pub fn to_catalog_track(self: &Arc<Self>) -> Option<Arc<Self>> {
+        if !self.is_local {
+            return Some(self.clone());
+        }
+        Some(Arc::new(Self {
+            id: self.catalog_id?,
+            is_local: false,
+            local_path: None,
+            catalog_id: None,
+            ..(**self).clone()
+        }))
+    }
+
+    /// Why the track is greyed out in the lists, if it is.
+    pub fn unavailable_reason(&self, filter_explicit: bool) -> Option<&'static str> {
+        if self.is_local {
+            return if self.local_path.is_none() {
+                Some("Local file that isn't on this computer")
+            } else if self.is_playable == Some(false) {
+                Some("Local file in a format that can't be played")
+            } else {
+                None
+            };
+        }
+        if self.is_playable == Some(false) {
+            let reason = self.restrictions.as_ref().map(|r| r.reason.as_ref());
+            return Some(match reason {
+                Some("market") => "Not available in your country",
+                Some("product") => "Not available with your subscription",
+                Some("explicit") => "Explicit content is restricted on this account",
+                _ => "No longer available on Spotify",
+            });
+        }
+        if self.explicit && filter_explicit {
+            return Some("Explicit, skipped by the content filter");
+        }
+        None
+    }
+
     pub fn url(&self) -> String {
-        format!("https://open.spotify.com/track/{}", self.id.0.to_base62())
+        SpotifyUri::Track(self.id.0.to_base62().into()).to_url()
+    }
+
+    /// URI in the format Spotify uses for local files, built from the track metadata.
+    pub fn local_uri(&self) -> String {
+        let encode = |s: &str| form_urlencoded::byte_serialize(s.as_bytes()).collect::<String>();
+        format!(
+            "spotify:local:{}:{}:{}:{}",
+            encode(&self.artist_name()),
+            encode(&self.album_name()),
+            encode(&self.name),
+            self.duration.as_secs()
+        )
     }
 }
 
@@ -121,3 +198,46 @@ pub struct TimeInterval {
     pub duration: Duration,
     pub confidence: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::utils::load_fixture;
+
+    #[test]
+    fn deserializes_catalog_track() {
+        let track: Track = load_fixture("track.json");
+
+        assert_eq!(track.id.0.to_base62(), "11dFghVXANMlKmJXsNCbNl");
+        assert_eq!(&*track.name, "Cut To The Feeling");
+        assert_eq!(&*track.album_name(), "Cut To The Feeling");
+        assert_eq!(track.artist_names(), "Carly Rae Jepsen");
+        assert_eq!(track.duration, Duration::from_millis(207959));
+        assert_eq!(track.popularity, Some(63));
+        assert!(!track.is_local);
+        assert_eq!(track.catalog_track_id(), Some(track.id));
+    }
+
+    #[test]
+    fn relinked_track_keeps_the_playable_id() {
+        let track: Track = load_fixture("relinked_track.json");
+
+        assert_eq!(track.id.0.to_base62(), "6kLCHFM39wkFjOuyPGLGeQ");
+        assert!(track.album.is_none());
+        assert_eq!(&*track.album_name(), "Unknown");
+        assert_eq!(track.is_playable, Some(true));
+    }
+
+    #[test]
+    fn unavailable_track_without_id() {
+        let track: Track = load_fixture("unavailable_track.json");
+
+        assert_eq!(track.id, TrackId::default());
+        assert_eq!(track.duration, Duration::ZERO);
+        assert_eq!(track.is_playable, Some(false));
+        assert_eq!(
+            track.restrictions.as_ref().map(|r| &*r.reason),
+            Some("market")
+        );
+    }
+}
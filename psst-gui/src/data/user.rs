@@ -1,13 +1,37 @@
 use std::sync::Arc;
 
-use druid::{Data, Lens};
+use druid::{im::Vector, Data, Lens};
 use serde::Deserialize;
 
+use super::Image;
+
 #[derive(Clone, Data, Lens, Deserialize)]
 pub struct UserProfile {
     pub display_name: Arc<str>,
     pub email: Arc<str>,
     pub id: Arc<str>,
+    /// Subscription level, like "premium" or "free".
+    #[serde(default)]
+    pub product: Option<Arc<str>>,
+    #[serde(default)]
+    pub country: Option<Arc<str>>,
+    #[serde(default)]
+    pub images: Vector<Image>,
+}
+
+impl UserProfile {
+    pub fn subscription(&self) -> String {
+        let product = match self.product.as_deref() {
+            Some("premium") => "Premium",
+            Some("free" | "open") => "Free",
+            Some(product) => product,
+            None => "Unknown subscription",
+        };
+        match &self.country {
+            Some(country) => format!("{} · {}", product, country),
+            None => product.to_string(),
+        }
+    }
 }
 
 #[derive(Clone, Data, Lens, Deserialize, Debug)]
@@ -1,13 +1,16 @@
 use std::{
+    cmp::Ordering,
     convert::TryFrom,
-    fmt, hash,
+    env, fmt, hash, iter,
     sync::Arc,
     time::{Duration, SystemTime},
 };
 
 use druid::{im::Vector, Data, Lens};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Deserializer, Serialize};
 use time::{Date, Month};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 
 #[derive(Clone, Data, Lens)]
 pub struct Cached<T: Data> {
@@ -37,6 +40,13 @@ impl<T: Data> Cached<T> {
             cached_at: self.cached_at,
         }
     }
+
+    /// Whether the value came from the cache, saved more than `age` ago.
+    pub fn is_older_than(&self, age: Duration) -> bool {
+        self.cached_at
+            .and_then(|at| at.elapsed().ok())
+            .map_or(false, |elapsed| elapsed > age)
+    }
 }
 
 #[derive(Deserialize)]
@@ -47,6 +57,33 @@ pub struct Page<T: Clone> {
     pub total: usize,
 }
 
+/// Deserialize one of the recorded Web API responses in `fixtures`.
+#[cfg(test)]
+pub fn load_fixture<T: serde::de::DeserializeOwned>(name: &str) -> T {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name);
+    let json = std::fs::read_to_string(&path).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+/// How far a paginated result set has loaded.
+#[derive(Clone, Copy, Debug, Data)]
+pub struct LoadProgress {
+    pub loaded: usize,
+    pub total: usize,
+}
+
+impl LoadProgress {
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.loaded as f64 / self.total as f64
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Hash, Data, Deserialize, Serialize)]
 pub struct Image {
     pub url: Arc<str>,
@@ -135,14 +172,21 @@ where
     D: Deserializer<'de>,
 {
     let date = String::deserialize(deserializer)?;
-    let mut parts = date.splitn(3, '-');
-    let year = parts.next().and_then(|p| p.parse().ok()).unwrap_or(0);
+    parse_date(&date)
+        .or_else(|| Date::from_calendar_date(0, Month::January, 1).ok())
+        .ok_or_else(|| serde::de::Error::custom("Invalid date"))
+}
+
+/// Parse a `YYYY-MM-DD` date, falling back to the first month/day if they are
+/// missing.
+pub fn parse_date(date: &str) -> Option<Date> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year = parts.next().and_then(|p| p.parse().ok())?;
     let month: u8 = parts.next().and_then(|p| p.parse().ok()).unwrap_or(1);
     let month = Month::try_from(month).unwrap_or(Month::January);
     let day = parts.next().and_then(|p| p.parse().ok()).unwrap_or(1);
 
-    Date::from_calendar_date(year, month, day)
-        .map_err(|_err| serde::de::Error::custom("Invalid date"))
+    Date::from_calendar_date(year, month, day).ok()
 }
 
 pub fn deserialize_date_option<'de, D>(deserializer: D) -> Result<Option<Date>, D::Error>
@@ -172,3 +216,217 @@ where
     let opt = Option::deserialize(deserializer)?;
     Ok(opt.unwrap_or_else(default_str))
 }
+
+/// Key sorting a name the way people expect it in a sorted list.  Case and
+/// diacritics are ignored and runs of digits compare by their value, so "Track 2"
+/// goes before "track 10" and "Édith" sorts with "Edith".  Letters the language of
+/// the user sorts on their own, like the Spanish "ñ" or the Swedish "å", go after
+/// their base letter instead.  Names that only differ in case or accents get equal
+/// keys, so stable sorts keep their original order.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct NaturalKey(Vec<KeyPart>);
+
+impl NaturalKey {
+    pub fn new(text: &str) -> Self {
+        Self::with_collation(text, *COLLATION)
+    }
+
+    fn with_collation(text: &str, collation: Collation) -> Self {
+        let mut letters = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            match collation.tailored(c) {
+                Some(letter) => letters.push(letter),
+                None => fold_for_sorting(c, &mut letters),
+            }
+        }
+        let mut parts = Vec::with_capacity(letters.len());
+        let mut letters = letters.into_iter().peekable();
+        while let Some((c, rank)) = letters.next() {
+            if c.is_ascii_digit() {
+                // Leading zeros don't change the value.
+                let mut digits = String::new();
+                let mut digit = Some(c);
+                while let Some(d) = digit {
+                    if !(digits.is_empty() && d == '0') {
+                        digits.push(d);
+                    }
+                    digit = letters.next_if(|(d, _)| d.is_ascii_digit()).map(|(d, _)| d);
+                }
+                parts.push(KeyPart::Number { first: c, digits });
+            } else {
+                parts.push(KeyPart::Char(c, rank));
+            }
+        }
+        Self(parts)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum KeyPart {
+    /// Folded character, and its rank among the letters tailored after it.
+    Char(char, u8),
+    /// Run of digits without the leading zeros, `first` is the digit it started with.
+    Number { first: char, digits: String },
+}
+
+impl Ord for KeyPart {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Char(a, a_rank), Self::Char(b, b_rank)) => (a, a_rank).cmp(&(b, b_rank)),
+            (Self::Number { digits: a, .. }, Self::Number { digits: b, .. }) => {
+                a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+            }
+            // A digit is never the same character as a non-digit.
+            (Self::Number { first, .. }, Self::Char(c, _)) => first.cmp(c),
+            (Self::Char(c, _), Self::Number { first, .. }) => c.cmp(first),
+        }
+    }
+}
+
+impl PartialOrd for KeyPart {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+static COLLATION: Lazy<Collation> = Lazy::new(Collation::from_env);
+
+/// Languages sorting some accented letters as letters of their own.  Everything
+/// else uses the default, where accents are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Collation {
+    Default,
+    /// Danish and Norwegian, with "æ", "ø" and "å" after "z".
+    Danish,
+    /// Swedish and Finnish, with "å", "ä" and "ö" after "z".
+    Swedish,
+    /// Spanish, with "ñ" after "n".
+    Spanish,
+}
+
+impl Collation {
+    /// Collation of the POSIX locale of the user.  Other platforms don't set the
+    /// variables, and get the default.
+    fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_COLLATE", "LANG"]
+            .iter()
+            .find_map(|var| env::var(var).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default();
+        let language = locale.split(&['_', '-', '.', '@'][..]).next().unwrap_or("");
+        match language {
+            "da" | "nb" | "nn" | "no" => Self::Danish,
+            "sv" | "fi" => Self::Swedish,
+            "es" => Self::Spanish,
+            _ => Self::Default,
+        }
+    }
+
+    fn tailored(self, c: char) -> Option<(char, u8)> {
+        let c = c.to_lowercase().next()?;
+        match (self, c) {
+            (Self::Danish, 'æ' | 'ä') => Some(('z', 1)),
+            (Self::Danish, 'ø' | 'ö') => Some(('z', 2)),
+            (Self::Danish, 'å') => Some(('z', 3)),
+            (Self::Swedish, 'å') => Some(('z', 1)),
+            (Self::Swedish, 'ä' | 'æ') => Some(('z', 2)),
+            (Self::Swedish, 'ö' | 'ø') => Some(('z', 3)),
+            (Self::Spanish, 'ñ') => Some(('n', 1)),
+            _ => None,
+        }
+    }
+}
+
+/// Lowercase the character and strip its accents.  Letters that don't decompose
+/// into a base letter and an accent are spelled out like a dictionary would.
+fn fold_for_sorting(c: char, folded: &mut Vec<(char, u8)>) {
+    for c in iter::once(c).nfd().filter(|&c| !is_combining_mark(c)) {
+        let spelled = match c {
+            'ß' => "ss",
+            'æ' | 'Æ' => "ae",
+            'œ' | 'Œ' => "oe",
+            'ø' | 'Ø' => "o",
+            'đ' | 'Đ' => "d",
+            'ł' | 'Ł' => "l",
+            'ı' => "i",
+            _ => {
+                folded.extend(c.to_lowercase().map(|c| (c, 0)));
+                continue;
+            }
+        };
+        folded.extend(spelled.chars().map(|c| (c, 0)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_values_from_the_cache_get_old() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let week_ago = SystemTime::now() - 7 * day;
+
+        assert!(Cached::new((), week_ago).is_older_than(day));
+        assert!(!Cached::new((), week_ago).is_older_than(8 * day));
+        assert!(!Cached::fresh(()).is_older_than(Duration::ZERO));
+    }
+
+    fn natural_cmp(a: &str, b: &str) -> Ordering {
+        let key = |text: &str| NaturalKey::with_collation(text, Collation::Default);
+        key(a).cmp(&key(b))
+    }
+
+    fn sorted(names: &[&str], collation: Collation) -> Vec<String> {
+        let mut names: Vec<_> = names.iter().map(|name| name.to_string()).collect();
+        names.sort_by_cached_key(|name| NaturalKey::with_collation(name, collation));
+        names
+    }
+
+    #[test]
+    fn digit_runs_compare_by_value() {
+        assert_eq!(natural_cmp("Track 2", "track 10"), Ordering::Less);
+        assert_eq!(natural_cmp("Track 10", "Track 9"), Ordering::Greater);
+        assert_eq!(natural_cmp("Opus 007", "Opus 7"), Ordering::Equal);
+        assert_eq!(natural_cmp("1999", "Abba"), Ordering::Less);
+        assert_eq!(
+            sorted(
+                &["Part 10", "Part 1", "Part 02", "Part"],
+                Collation::Default
+            ),
+            ["Part", "Part 1", "Part 02", "Part 10"]
+        );
+    }
+
+    #[test]
+    fn case_and_diacritics_are_ignored() {
+        assert_eq!(natural_cmp("Édith", "edith"), Ordering::Equal);
+        assert_eq!(natural_cmp("Straße", "STRASSE"), Ordering::Equal);
+        assert_eq!(natural_cmp("Øystein", "Oystein"), Ordering::Equal);
+        // Equal keys keep the original order.
+        assert_eq!(
+            sorted(&["eve", "Ève", "Adam", "EVE"], Collation::Default),
+            ["Adam", "eve", "Ève", "EVE"]
+        );
+    }
+
+    #[test]
+    fn languages_sort_their_own_letters_after_the_base_ones() {
+        let names = ["Zorn", "Åsa", "Ørjan", "Anna", "Ärla"];
+        assert_eq!(
+            sorted(&names, Collation::Default),
+            ["Anna", "Ärla", "Åsa", "Ørjan", "Zorn"]
+        );
+        assert_eq!(
+            sorted(&names, Collation::Swedish),
+            ["Anna", "Zorn", "Åsa", "Ärla", "Ørjan"]
+        );
+        assert_eq!(
+            sorted(&names, Collation::Danish),
+            ["Anna", "Zorn", "Ärla", "Ørjan", "Åsa"]
+        );
+        assert_eq!(
+            sorted(&["Ñu", "Nz", "Oca", "Na"], Collation::Spanish),
+            ["Na", "Nz", "Ñu", "Oca"]
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::{io, path::PathBuf};
+
+use psst_core::cache::{secure_remove, Cache};
+
+use crate::data::{Config, PlaylistBackups, Statistics};
+
+/// Data of the logged in account kept on the disk, removed when logging out with
+/// the wipe option.  The preferences stay, apart from the login credentials.
+#[derive(Clone, Debug)]
+pub struct ProfileData {
+    pub description: &'static str,
+    pub path: PathBuf,
+}
+
+impl ProfileData {
+    pub fn all() -> Vec<Self> {
+        let mut data = Vec::new();
+        if let Some(path) = Config::cache_dir() {
+            data.push(Self {
+                description: "Cached and downloaded audio, decryption keys and metadata",
+                path,
+            });
+        }
+        if let Some(stats) = Statistics::open() {
+            data.push(Self {
+                description: "Listening history",
+                path: stats.path().to_owned(),
+            });
+        }
+        if let Some(path) = Config::session_activity_path() {
+            data.push(Self {
+                description: "Session activity log",
+                path,
+            });
+        }
+        if let Some(backups) = PlaylistBackups::open() {
+            data.push(Self {
+                description: "Playlist backups",
+                path: backups.dir().to_owned(),
+            });
+        }
+        if let Some(path) = Config::config_path() {
+            data.push(Self {
+                description: "Saved login credentials",
+                path,
+            });
+        }
+        data
+    }
+
+    /// Securely remove all of the data.  The config file is removed as well, so
+    /// no copy of the credentials survives in it, and needs to be saved again
+    /// afterwards.  The cache is recreated empty.
+    pub fn wipe_all() -> io::Result<()> {
+        let mut result = Ok(());
+        for data in Self::all() {
+            log::info!("wiping {:?}", data.path);
+            if let Err(err) = secure_remove(&data.path) {
+                log::error!("failed to wipe {:?}: {}", data.path, err);
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+        if let Some(cache_dir) = Config::cache_dir() {
+            if let Err(err) = Cache::new(cache_dir) {
+                log::error!("failed to recreate the cache: {}", err);
+            }
+        }
+        result
+    }
+}
@@ -4,19 +4,22 @@ use druid::{
 use threadpool::ThreadPool;
 
 use crate::ui::playlist::{
-    RENAME_PLAYLIST, RENAME_PLAYLIST_CONFIRM, UNFOLLOW_PLAYLIST, UNFOLLOW_PLAYLIST_CONFIRM,
+    APPLY_PLAYLIST_RESTORE, CREATE_PLAYLIST, CREATE_PLAYLIST_CONFIRM, RENAME_PLAYLIST,
+    RENAME_PLAYLIST_CONFIRM, RESTORE_PLAYLIST_CONFIRM, UNFOLLOW_PLAYLIST,
+    UNFOLLOW_PLAYLIST_CONFIRM,
 };
 use crate::{
     cmd,
-    data::{AppState, Config},
-    ui,
-    webapi::WebApi,
+    data::{AppState, Config, LastPlayback, MAX_RADIO_SEEDS},
+    ui, webapi,
     widget::remote_image,
 };
 
 pub struct Delegate {
     main_window: Option<WindowId>,
     preferences_window: Option<WindowId>,
+    osd_window: Option<WindowId>,
+    pip_window: Option<WindowId>,
     image_pool: ThreadPool,
     size_updated: bool,
 }
@@ -28,6 +31,8 @@ impl Delegate {
         Self {
             main_window: None,
             preferences_window: None,
+            osd_window: None,
+            pip_window: None,
             image_pool: ThreadPool::with_name("image_loading".into(), MAX_IMAGE_THREADS),
             size_updated: false,
         }
@@ -84,10 +89,39 @@ impl Delegate {
         }
     }
 
+    fn show_osd(&mut self, config: &Config, ctx: &mut DelegateCtx) {
+        match self.osd_window {
+            Some(id) => {
+                // Already shown, keep it up for the new track.
+                ctx.submit_command(ui::osd::RESTART.to(id));
+            }
+            None => {
+                let window = ui::osd::osd_window(config);
+                self.osd_window.replace(window.id);
+                ctx.new_window(window);
+            }
+        }
+    }
+
+    fn toggle_pip(&mut self, config: &Config, ctx: &mut DelegateCtx) {
+        match self.pip_window.take() {
+            Some(id) => {
+                ctx.submit_command(commands::CLOSE_WINDOW.to(id));
+            }
+            None => {
+                let window = ui::pip::pip_window(config);
+                self.pip_window.replace(window.id);
+                ctx.new_window(window);
+            }
+        }
+    }
+
     fn close_all_windows(&mut self, ctx: &mut DelegateCtx) {
         ctx.submit_command(commands::CLOSE_ALL_WINDOWS);
         self.main_window = None;
         self.preferences_window = None;
+        self.osd_window = None;
+        self.pip_window = None;
     }
 }
 
@@ -109,12 +143,61 @@ impl AppDelegate<AppState> for Delegate {
         } else if cmd.is(commands::SHOW_PREFERENCES) {
             self.show_preferences(ctx);
             Handled::Yes
+        } else if cmd.is(cmd::SHOW_OSD) {
+            if data.config.osd_enabled {
+                self.show_osd(&data.config, ctx);
+            }
+            Handled::Yes
+        } else if cmd.is(cmd::TOGGLE_PIP) {
+            self.toggle_pip(&data.config, ctx);
+            Handled::Yes
         } else if cmd.is(cmd::CLOSE_ALL_WINDOWS) {
             self.close_all_windows(ctx);
             Handled::Yes
+        } else if let Some((monitor, step)) = cmd.get(cmd::ZOOM_MONITOR) {
+            data.config.step_zoom(monitor, *step);
+            data.config.save();
+            Handled::Yes
+        } else if let Some(prefers) = cmd.get(cmd::SYSTEM_REDUCED_MOTION) {
+            data.system_prefers_reduced_motion = *prefers;
+            Handled::Yes
         } else if let Some(text) = cmd.get(cmd::COPY) {
             Application::global().clipboard().put_string(text);
             Handled::Yes
+        } else if let Some(artist) = cmd.get(cmd::BLOCK_ARTIST) {
+            data.config.blocklist.block_artist(artist);
+            data.config.save();
+            data.info_alert(format!("Blocked “{}”.", artist.name));
+            Handled::Yes
+        } else if let Some(artist) = cmd.get(cmd::UNBLOCK_ARTIST) {
+            data.config.blocklist.unblock_artist(artist);
+            data.config.save();
+            Handled::Yes
+        } else if let Some(track) = cmd.get(cmd::BLOCK_TRACK) {
+            data.config.blocklist.block_track(track);
+            data.config.save();
+            data.info_alert(format!("Blocked “{}”.", track.name));
+            Handled::Yes
+        } else if let Some(id) = cmd.get(cmd::UNBLOCK_TRACK) {
+            data.config.blocklist.unblock_track(*id);
+            data.config.save();
+            Handled::Yes
+        } else if let Some(seed) = cmd.get(cmd::ADD_RADIO_SEED) {
+            if data.radio.contains(seed) {
+                data.info_alert(format!("“{}” is in the radio builder already.", seed.name));
+            } else if data.radio.is_full() {
+                data.info_alert(format!(
+                    "The radio builder takes up to {} seeds.",
+                    MAX_RADIO_SEEDS
+                ));
+            } else {
+                data.radio.add_seed(seed.clone());
+                data.info_alert(format!("Added “{}” to the radio builder.", seed.name));
+            }
+            Handled::Yes
+        } else if let Some(seed) = cmd.get(cmd::REMOVE_RADIO_SEED) {
+            data.radio.remove_seed(seed);
+            Handled::Yes
         } else if let Handled::Yes = self.command_image(ctx, target, cmd, data) {
             Handled::Yes
         } else if let Some(link) = cmd.get(UNFOLLOW_PLAYLIST_CONFIRM) {
@@ -123,6 +206,12 @@ impl AppDelegate<AppState> for Delegate {
         } else if let Some(link) = cmd.get(RENAME_PLAYLIST_CONFIRM) {
             ctx.submit_command(RENAME_PLAYLIST.with(link.clone()));
             Handled::Yes
+        } else if let Some(new) = cmd.get(CREATE_PLAYLIST_CONFIRM) {
+            ctx.submit_command(CREATE_PLAYLIST.with(new.clone()));
+            Handled::Yes
+        } else if let Some(restore) = cmd.get(RESTORE_PLAYLIST_CONFIRM) {
+            ctx.submit_command(APPLY_PLAYLIST_RESTORE.with(restore.clone()));
+            Handled::Yes
         } else {
             Handled::No
         }
@@ -135,12 +224,26 @@ impl AppDelegate<AppState> for Delegate {
         _env: &Env,
         ctx: &mut DelegateCtx,
     ) {
+        if self.osd_window == Some(id) {
+            self.osd_window.take();
+        }
+        if self.pip_window == Some(id) {
+            self.pip_window.take();
+        }
         if self.preferences_window == Some(id) {
             self.preferences_window.take();
             data.preferences.reset();
             data.preferences.auth.clear();
         }
         if self.main_window == Some(id) {
+            // A private session leaves no trace, not even the track to restore.
+            if !data.playback.private_session {
+                data.config.last_playback = data
+                    .playback
+                    .now_playing
+                    .as_ref()
+                    .and_then(LastPlayback::new);
+            }
             data.config.save();
             ctx.submit_command(commands::CLOSE_ALL_WINDOWS);
             ctx.submit_command(commands::QUIT_APP);
@@ -179,7 +282,7 @@ impl Delegate {
     ) -> Handled {
         if let Some(location) = cmd.get(remote_image::REQUEST_DATA).cloned() {
             let sink = ctx.get_external_handle();
-            if let Some(image_buf) = WebApi::global().get_cached_image(&location) {
+            if let Some(image_buf) = webapi::global().get_cached_image(&location) {
                 let payload = remote_image::ImagePayload {
                     location,
                     image_buf,
@@ -188,7 +291,7 @@ impl Delegate {
                     .unwrap();
             } else {
                 self.image_pool.execute(move || {
-                    let result = WebApi::global().get_image(location.clone());
+                    let result = webapi::global().get_image(location.clone());
                     match result {
                         Ok(image_buf) => {
                             let payload = remote_image::ImagePayload {
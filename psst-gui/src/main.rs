@@ -10,12 +10,15 @@ mod ui;
 mod webapi;
 mod widget;
 
-use druid::AppLauncher;
+use std::thread;
+
+use druid::{AppLauncher, Target};
 use env_logger::{Builder, Env};
-use webapi::WebApi;
+use psst_core::net;
+use webapi::Web;
 
 use crate::{
-    data::{AppState, Config},
+    data::{motion, AppState, Config},
     delegate::Delegate,
 };
 
@@ -33,14 +36,32 @@ fn main() {
 
     let config = Config::load().unwrap_or_default();
     let paginated_limit = config.paginated_limit;
+    let market = config.market();
+    if let Some(path) = config.ca_certificates() {
+        match net::add_ca_certificates(&path) {
+            Ok(count) => log::info!("trusting {} additional certificates from {:?}", count, path),
+            Err(err) => log::error!("failed to load certificates from {:?}: {}", path, err),
+        }
+    }
+    net::set_dns_over_https(config.dns_over_https(), Config::proxy().as_deref());
     let state = AppState::default_with_config(config);
-    WebApi::new(
+    if let Some(path) = Config::session_activity_path() {
+        if let Err(err) = state.session.activity().persist_to(path) {
+            log::error!("failed to open the session activity log: {}", err);
+        }
+    }
+    webapi::install_global(Web::new(
         state.session.clone(),
         Config::proxy().as_deref(),
         Config::cache_dir(),
         paginated_limit,
-    )
-    .install_as_global();
+        market,
+    ));
+    webapi::global().set_image_cache_limit(state.config.image_cache_limit());
+    webapi::configure_events(
+        state.config.bandsintown_app_id(),
+        Config::proxy().as_deref(),
+    );
 
     let delegate;
     let launcher;
@@ -51,7 +72,7 @@ fn main() {
         launcher = AppLauncher::with_window(window).configure_env(ui::theme::setup);
 
         // Load user's local tracks for the WebApi.
-        WebApi::global().load_local_tracks(state.config.username().unwrap());
+        webapi::global().load_local_tracks(state.config.username().unwrap());
     } else {
         // No configured credentials, open the account setup.
         let window = ui::account_setup_window();
@@ -59,6 +80,13 @@ fn main() {
         launcher = AppLauncher::with_window(window).configure_env(ui::theme::setup);
     };
 
+    // Asking the platform can take a while, don't hold up the first frame.
+    let event_sink = launcher.get_external_handle();
+    thread::spawn(move || {
+        let prefers = motion::query_system_preference();
+        let _ = event_sink.submit_command(cmd::SYSTEM_REDUCED_MOTION, prefers, Target::Global);
+    });
+
     launcher
         .delegate(delegate)
         .launch(state)
@@ -1,24 +1,35 @@
 use std::sync::Arc;
 
 use druid::{
-    widget::{CrossAxisAlignment, Flex, Label, LineBreaking, List, ViewSwitcher},
-    LensExt, LocalizedString, Menu, MenuItem, Selector, Size, Widget, WidgetExt,
+    lens::Map,
+    widget::{CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, ViewSwitcher},
+    Insets, LensExt, LocalizedString, Menu, MenuItem, Selector, Size, Widget, WidgetExt,
 };
 
 use crate::{
     cmd,
     data::{
-        Album, AlbumDetail, AlbumLink, AppState, ArtistLink, Cached, Ctx, Library, Nav, WithCtx,
+        Album, AlbumDetail, AlbumLink, AppState, ArtistLink, Cached, Ctx, Library,
+        MusicBrainzRelease, Nav, PromiseState, QueuePlacement, QueueRequest, QueueSource, Relation,
+        WithCtx,
     },
-    webapi::WebApi,
-    widget::{icons, Async, MyWidgetExt, RemoteImage},
+    webapi,
+    widget::{icons, Async, Empty, Maybe, MyWidgetExt, RemoteImage},
 };
 
 use super::{artist, library, playable, theme, track, utils};
 
 pub const LOAD_DETAIL: Selector<AlbumLink> = Selector::new("app.album.load-detail");
+pub const LOAD_MUSICBRAINZ: Selector<Arc<Album>> = Selector::new("app.album.load-musicbrainz");
 
 pub fn detail_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Fill)
+        .with_child(async_album_widget())
+        .with_child(musicbrainz_widget())
+}
+
+fn async_album_widget() -> impl Widget<AppState> {
     Async::new(
         utils::spinner_widget,
         loaded_detail_widget,
@@ -33,12 +44,113 @@ pub fn detail_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_DETAIL,
-        |d| WebApi::global().get_album(&d.id),
-        |_, data, d| data.album_detail.album.defer(d),
+        |d| webapi::global().get_album(&d.id),
+        |_, data, d| {
+            data.album_detail.album.defer(d);
+            data.album_detail.musicbrainz.clear();
+        },
         |_, data, r| data.album_detail.album.update(r),
     )
 }
 
+/// Details from MusicBrainz, looked up only when asked for, so that browsing
+/// albums doesn't send requests to a third party.
+fn musicbrainz_widget() -> impl Widget<AppState> {
+    let lookup = Label::new("Look Up on MusicBrainz")
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .link()
+        .on_left_click(|ctx, _, data: &mut AppState, _| {
+            if let Some(album) = data.album_detail.album.resolved() {
+                ctx.submit_command(LOAD_MUSICBRAINZ.with(album.data.clone()));
+            }
+        })
+        .padding(Insets::new(0.0, theme::grid(2.0), 0.0, 0.0));
+
+    let release = Async::new(
+        utils::spinner_widget,
+        || {
+            Maybe::new(release_widget, || {
+                Label::new("The album isn't on MusicBrainz.")
+                    .with_text_size(theme::TEXT_SIZE_SMALL)
+                    .with_text_color(theme::PLACEHOLDER_COLOR)
+            })
+        },
+        utils::error_widget,
+    )
+    .lens(AppState::album_detail.then(AlbumDetail::musicbrainz));
+
+    Either::new(
+        |data: &AppState, _| {
+            data.config.musicbrainz_lookup && data.album_detail.album.is_resolved()
+        },
+        Either::new(
+            |data: &AppState, _| data.album_detail.musicbrainz.state() == PromiseState::Empty,
+            lookup,
+            release,
+        ),
+        Empty,
+    )
+    .on_command_async(
+        LOAD_MUSICBRAINZ,
+        |album| webapi::global().get_musicbrainz_release(&album),
+        |_, data, album| data.album_detail.musicbrainz.defer(album),
+        |_, data, r| data.album_detail.musicbrainz.update(r),
+    )
+}
+
+fn release_widget() -> impl Widget<MusicBrainzRelease> {
+    let header = Label::new("MusicBrainz")
+        .with_font(theme::UI_FONT_MEDIUM)
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .padding(Insets::new(0.0, theme::grid(2.0), 0.0, theme::grid(1.0)));
+
+    let original_year =
+        Label::dynamic(
+            |release: &MusicBrainzRelease, _| match release.original_year() {
+                Some(year) => format!("Originally released in {}", year),
+                None => "Original release date unknown".to_string(),
+            },
+        )
+        .with_text_size(theme::TEXT_SIZE_SMALL);
+
+    let catalog_numbers =
+        Label::dynamic(|release: &MusicBrainzRelease, _| release.catalog_numbers())
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_text_color(theme::PLACEHOLDER_COLOR);
+
+    let links = List::new(|| {
+        Label::dynamic(|relation: &Relation, _| relation.kind.to_string())
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .link()
+            .on_left_click(|_, _, relation: &mut Relation, _| {
+                if let Some(url) = &relation.url {
+                    webbrowser::open(&url.resource).ok();
+                }
+            })
+    })
+    .lens(Map::new(MusicBrainzRelease::links, |_, _| {}));
+
+    let open = Label::new("Open on MusicBrainz")
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .link()
+        .on_left_click(|_, _, release: &mut MusicBrainzRelease, _| {
+            webbrowser::open(&release.url()).ok();
+        });
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(header)
+        .with_child(original_year)
+        .with_default_spacer()
+        .with_child(catalog_numbers)
+        .with_default_spacer()
+        .with_child(links)
+        .with_default_spacer()
+        .with_child(open)
+}
+
 fn loaded_detail_widget() -> impl Widget<WithCtx<Cached<Arc<Album>>>> {
     let album_cover = rounded_cover_widget(theme::grid(10.0))
         .lens(Ctx::data())
@@ -184,8 +296,62 @@ fn album_menu(album: &Arc<Album>, library: &Arc<Library>) -> Menu<AppState> {
         .command(cmd::COPY.with(album.url())),
     );
 
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-copy-name").with_placeholder("Copy Name"))
+            .command(cmd::COPY.with(album.name.to_string())),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-copy-artist-and-title")
+                .with_placeholder("Copy Artist – Title"),
+        )
+        .command(cmd::COPY.with(format!("{} – {}", album.artist_names(), album.name))),
+    );
+
+    menu = menu.separator();
+
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-play-next").with_placeholder("Play Next"))
+            .command(cmd::ADD_TO_QUEUE.with(QueueRequest {
+                source: QueueSource::Album(album.link()),
+                placement: QueuePlacement::Next,
+            })),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-add-to-queue").with_placeholder("Add to Queue"),
+        )
+        .command(cmd::ADD_TO_QUEUE.with(QueueRequest {
+            source: QueueSource::Album(album.link()),
+            placement: QueuePlacement::Last,
+        })),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-download").with_placeholder("Download"))
+            .command(cmd::DOWNLOAD.with(QueueSource::Album(album.link()))),
+    );
+
     menu = menu.separator();
 
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-save-all-tracks")
+                .with_placeholder("Save All Tracks to Library"),
+        )
+        .command(cmd::SAVE_ALL_TRACKS.with(QueueSource::Album(album.link()))),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-unsave-all-tracks")
+                .with_placeholder("Remove All Tracks from Library"),
+        )
+        .command(cmd::UNSAVE_ALL_TRACKS.with(QueueSource::Album(album.link()))),
+    );
+
     if library.contains_album(album) {
         menu = menu.entry(
             MenuItem::new(
@@ -1,31 +1,175 @@
+use std::sync::Arc;
+
 use druid::{
     im::Vector,
     kurbo::Circle,
-    widget::{CrossAxisAlignment, Flex, Label, LabelText, LineBreaking, List},
+    lens::Map,
+    widget::{Button, CrossAxisAlignment, Either, Flex, Label, LabelText, LineBreaking, List},
     Data, Insets, LensExt, LocalizedString, Menu, MenuItem, Selector, Widget, WidgetExt,
 };
 
 use crate::{
     cmd,
     data::{
-        AppState, Artist, ArtistAlbums, ArtistDetail, ArtistLink, ArtistTracks, Cached, Ctx, Nav,
+        AppState, Artist, ArtistAlbums, ArtistDetail, ArtistEvent, ArtistLink, ArtistProfile,
+        ArtistTracks, Cached, Ctx, Nav, QueuePlacement, QueueRequest, QueueSource, RadioSeed,
         WithCtx,
     },
-    webapi::WebApi,
-    widget::{Async, MyWidgetExt, RemoteImage},
+    error::Error,
+    webapi,
+    widget::{Async, Empty, MyWidgetExt, RemoteImage},
 };
 
 use super::{album, playable, theme, track, utils};
 
 pub const LOAD_DETAIL: Selector<ArtistLink> = Selector::new("app.artist.load-detail");
+pub const FOLLOW_ARTIST: Selector<ArtistLink> = Selector::new("app.artist.follow");
+pub const UNFOLLOW_ARTIST: Selector<ArtistLink> = Selector::new("app.artist.unfollow");
+
+/// Most concerts listed in the "On Tour" shelf.
+const MAX_EVENTS: usize = 8;
 
 pub fn detail_widget() -> impl Widget<AppState> {
     Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(async_profile_widget().padding((theme::grid(1.0), 0.0)))
         .with_child(async_top_tracks_widget())
+        .with_child(async_events_widget().padding((theme::grid(1.0), 0.0)))
         .with_child(async_albums_widget().padding((theme::grid(1.0), 0.0)))
         .with_child(async_related_widget().padding((theme::grid(1.0), 0.0)))
 }
 
+fn async_profile_widget() -> impl Widget<AppState> {
+    Async::new(|| Empty, profile_widget, || Empty)
+        .lens(AppState::artist_detail.then(ArtistDetail::artist))
+        .on_command_async(
+            LOAD_DETAIL,
+            |d| -> Result<_, Error> {
+                Ok(ArtistProfile {
+                    artist: webapi::global().get_artist(&d.id)?,
+                    is_followed: webapi::global().is_following_artist(&d.id)?,
+                })
+            },
+            |_, data, d| data.artist_detail.artist.defer(d),
+            |_, data, r| data.artist_detail.artist.update(r),
+        )
+        .on_command_async(
+            FOLLOW_ARTIST,
+            |link| webapi::global().follow_artist(&link.id),
+            |_, data: &mut AppState, link| set_followed(data, &link, true),
+            |_, data, (link, r)| {
+                if let Err(err) = r {
+                    set_followed(data, &link, false);
+                    data.error_alert(err);
+                }
+            },
+        )
+        .on_command_async(
+            UNFOLLOW_ARTIST,
+            |link| webapi::global().unfollow_artist(&link.id),
+            |_, data: &mut AppState, link| set_followed(data, &link, false),
+            |_, data, (link, r)| {
+                if let Err(err) = r {
+                    set_followed(data, &link, true);
+                    data.error_alert(err);
+                }
+            },
+        )
+}
+
+/// Optimistically flip the follow state shown in the header.
+fn set_followed(data: &mut AppState, link: &ArtistLink, followed: bool) {
+    if let Some(profile) = data.artist_detail.artist.resolved_mut() {
+        if profile.artist.id == link.id && profile.is_followed != followed {
+            profile.is_followed = followed;
+            if followed {
+                profile.artist.followers.total += 1;
+            } else {
+                profile.artist.followers.total = profile.artist.followers.total.saturating_sub(1);
+            }
+        }
+    }
+}
+
+/// Genre of the artist, starting a radio of the genre when clicked.
+fn genre_chip_widget() -> impl Widget<Arc<str>> {
+    Label::raw()
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .padding((theme::grid(0.75), theme::grid(0.25)))
+        .link()
+        .border(theme::GREY_500, 1.0)
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .on_left_click(|ctx, _, genre, _| {
+            if let Some(seed) = RadioSeed::genre(genre) {
+                ctx.submit_command(cmd::START_RADIO.with(seed.into_radio()));
+            }
+        })
+        .tooltip(|genre| Some(format!("Start a radio of {}", genre)))
+}
+
+fn profile_widget() -> impl Widget<ArtistProfile> {
+    let artist_image = cover_widget(theme::grid(16.0)).lens(ArtistProfile::artist);
+
+    let artist_name = Label::raw()
+        .with_font(theme::UI_FONT_MEDIUM)
+        .with_text_size(theme::TEXT_SIZE_LARGE)
+        .with_line_break_mode(LineBreaking::WordWrap)
+        .lens(ArtistProfile::artist.then(Artist::name));
+
+    let followers = Label::dynamic(|profile: &ArtistProfile, _| profile.followers_label())
+        .with_text_size(theme::TEXT_SIZE_SMALL);
+
+    let genres = List::new(genre_chip_widget)
+        .horizontal()
+        .with_spacing(theme::grid(0.5))
+        .lens(ArtistProfile::artist.then(Artist::genres));
+
+    let follow_button = Button::dynamic(|profile: &ArtistProfile, _| {
+        if profile.is_followed {
+            "Unfollow".to_string()
+        } else {
+            "Follow".to_string()
+        }
+    })
+    .on_click(|ctx, profile, _| {
+        let link = profile.artist.link();
+        if profile.is_followed {
+            ctx.submit_command(UNFOLLOW_ARTIST.with(link));
+        } else {
+            ctx.submit_command(FOLLOW_ARTIST.with(link));
+        }
+    });
+
+    let radio_button =
+        Button::new("Start Radio").on_click(|ctx, profile: &mut ArtistProfile, _| {
+            let seed = RadioSeed::artist(&profile.artist.link());
+            ctx.submit_command(cmd::START_RADIO.with(seed.into_radio()));
+        });
+
+    let artist_info = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(artist_name)
+        .with_default_spacer()
+        .with_child(followers)
+        .with_default_spacer()
+        .with_child(genres)
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            Flex::row()
+                .with_child(follow_button)
+                .with_default_spacer()
+                .with_child(radio_button),
+        )
+        .padding(theme::grid(1.0));
+
+    Flex::row()
+        .with_child(artist_image)
+        .with_default_spacer()
+        .with_flex_child(artist_info, 1.0)
+        .padding((0.0, theme::grid(1.0)))
+}
+
 fn async_top_tracks_widget() -> impl Widget<AppState> {
     Async::new(
         utils::spinner_widget,
@@ -41,7 +185,7 @@ fn async_top_tracks_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_DETAIL,
-        |d| WebApi::global().get_artist_top_tracks(&d.id),
+        |d| webapi::global().get_artist_top_tracks(&d.id),
         |_, data, d| data.artist_detail.top_tracks.defer(d),
         |_, data, (d, r)| {
             let r = r.map(|tracks| ArtistTracks {
@@ -54,6 +198,25 @@ fn async_top_tracks_widget() -> impl Widget<AppState> {
     )
 }
 
+fn async_events_widget() -> impl Widget<AppState> {
+    Async::new(|| Empty, events_widget, || Empty)
+        .lens(AppState::artist_detail.then(ArtistDetail::events))
+        .on_command_async(
+            LOAD_DETAIL,
+            |d| match webapi::events_provider() {
+                Some(provider) => provider.upcoming_events(&d),
+                None => Ok(Vector::new()),
+            },
+            |_, data, d| data.artist_detail.events.defer(d),
+            |_, data, (d, r)| {
+                if let Err(err) = &r {
+                    log::warn!("failed to load the events of {}: {}", d.name, err);
+                }
+                data.artist_detail.events.update((d, r))
+            },
+        )
+}
+
 fn async_albums_widget() -> impl Widget<AppState> {
     Async::new(utils::spinner_widget, albums_widget, utils::error_widget)
         .lens(
@@ -65,7 +228,7 @@ fn async_albums_widget() -> impl Widget<AppState> {
         )
         .on_command_async(
             LOAD_DETAIL,
-            |d| WebApi::global().get_artist_albums(&d.id),
+            |d| webapi::global().get_artist_albums(&d.id),
             |_, data, d| data.artist_detail.albums.defer(d),
             |_, data, r| data.artist_detail.albums.update(r),
         )
@@ -76,7 +239,7 @@ fn async_related_widget() -> impl Widget<AppState> {
         .lens(AppState::artist_detail.then(ArtistDetail::related_artists))
         .on_command_async(
             LOAD_DETAIL,
-            |d| WebApi::global().get_related_artists(&d.id),
+            |d| webapi::global().get_related_artists(&d.id),
             |_, data, d| data.artist_detail.related_artists.defer(d),
             |_, data, r| data.artist_detail.related_artists.update(r),
         )
@@ -153,6 +316,62 @@ fn related_widget() -> impl Widget<Cached<Vector<Artist>>> {
         .lens(Cached::data)
 }
 
+fn events_widget() -> impl Widget<Vector<ArtistEvent>> {
+    Either::new(
+        |events: &Vector<ArtistEvent>, _| events.is_empty(),
+        Empty,
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(header_widget("On Tour"))
+            .with_child(List::new(event_widget))
+            .lens(Map::new(
+                |events: &Vector<ArtistEvent>| events.take(MAX_EVENTS),
+                |_, _| {},
+            )),
+    )
+}
+
+fn event_widget() -> impl Widget<ArtistEvent> {
+    let date = Label::raw()
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .lens(ArtistEvent::date)
+        .fix_width(theme::grid(12.0));
+
+    let venue = Label::raw()
+        .with_font(theme::UI_FONT_MEDIUM)
+        .with_line_break_mode(LineBreaking::WordWrap)
+        .lens(ArtistEvent::venue);
+
+    let location = Label::raw()
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_line_break_mode(LineBreaking::WordWrap)
+        .lens(ArtistEvent::location);
+
+    Flex::row()
+        .with_child(date)
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(venue)
+                .with_child(location),
+            1.0,
+        )
+        .padding(theme::grid(1.0))
+        .link()
+        .on_left_click(|_, _, event, _| {
+            webbrowser::open(&event.url).ok();
+        })
+        .context_menu(|event| {
+            Menu::empty().entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-copy-link").with_placeholder("Copy Link"),
+                )
+                .command(cmd::COPY.with(event.url.to_string())),
+            )
+        })
+}
+
 fn header_widget<T: Data>(text: impl Into<LabelText<T>>) -> impl Widget<T> {
     Label::new(text)
         .with_font(theme::UI_FONT_MEDIUM)
@@ -171,5 +390,58 @@ fn artist_menu(artist: &ArtistLink) -> Menu<AppState> {
         .command(cmd::COPY.with(artist.url())),
     );
 
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-copy-name").with_placeholder("Copy Name"))
+            .command(cmd::COPY.with(artist.name.to_string())),
+    );
+
+    menu = menu.separator();
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-play-top-tracks-next")
+                .with_placeholder("Play Top Tracks Next"),
+        )
+        .command(cmd::ADD_TO_QUEUE.with(QueueRequest {
+            source: QueueSource::ArtistTopTracks(artist.to_owned()),
+            placement: QueuePlacement::Next,
+        })),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-add-top-tracks-to-queue")
+                .with_placeholder("Add Top Tracks to Queue"),
+        )
+        .command(cmd::ADD_TO_QUEUE.with(QueueRequest {
+            source: QueueSource::ArtistTopTracks(artist.to_owned()),
+            placement: QueuePlacement::Last,
+        })),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-start-radio").with_placeholder("Start Radio"),
+        )
+        .command(cmd::START_RADIO.with(RadioSeed::artist(artist).into_radio())),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-add-to-radio-builder")
+                .with_placeholder("Add to Radio Builder"),
+        )
+        .command(cmd::ADD_RADIO_SEED.with(RadioSeed::artist(artist))),
+    );
+
+    menu = menu.separator();
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-block-artist").with_placeholder("Block Artist"),
+        )
+        .command(cmd::BLOCK_ARTIST.with(artist.to_owned())),
+    );
+
     menu
 }
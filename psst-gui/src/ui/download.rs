@@ -0,0 +1,125 @@
+use druid::{
+    widget::{CrossAxisAlignment, Either, Flex, Label, LineBreaking, List},
+    Data, Widget, WidgetExt,
+};
+
+use crate::{
+    cmd,
+    data::{AppState, DownloadItem, Downloads},
+    widget::{Empty, MyWidgetExt},
+};
+
+use super::{theme, utils};
+
+pub fn downloads_widget() -> impl Widget<AppState> {
+    let queue = Either::new(
+        |downloads: &Downloads, _| downloads.items.is_empty(),
+        Label::new(
+            "Nothing is being downloaded. Choose Download in the menu of a track, an album \
+             or a playlist to keep it available offline.",
+        )
+        .with_line_break_mode(LineBreaking::WordWrap)
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .padding(theme::grid(1.0)),
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(controls_widget())
+            .with_default_spacer()
+            .with_child(List::new(download_item_widget).lens(Downloads::items)),
+    )
+    .lens(AppState::downloads);
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(storage_widget())
+        .with_default_spacer()
+        .with_child(queue)
+}
+
+fn storage_widget() -> impl Widget<AppState> {
+    Label::dynamic(|data: &AppState, _| {
+        let storage = match data.downloads.storage {
+            Some(storage) => storage,
+            None => return String::new(),
+        };
+        let used = utils::as_human_size(storage.used);
+        let available = utils::as_human_size(storage.available);
+        match data.config.download_quota_gb {
+            0 => format!("Using {}, {} free on disk", used, available),
+            quota => format!(
+                "Using {} of the {} GB limit, {} free on disk",
+                used, quota, available
+            ),
+        }
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .padding(theme::grid(1.0))
+}
+
+fn controls_widget() -> impl Widget<Downloads> {
+    let pause_or_resume = Either::new(
+        |downloads: &Downloads, _| downloads.paused,
+        control_link("Resume All").on_left_click(|ctx, _, _, _| {
+            ctx.submit_command(cmd::RESUME_DOWNLOADS);
+        }),
+        control_link("Pause All").on_left_click(|ctx, _, _, _| {
+            ctx.submit_command(cmd::PAUSE_DOWNLOADS);
+        }),
+    );
+    let retry = Either::new(
+        |downloads: &Downloads, _| downloads.has_failed(),
+        control_link("Retry Failed").on_left_click(|ctx, _, _, _| {
+            ctx.submit_command(cmd::RETRY_DOWNLOADS);
+        }),
+        Empty,
+    );
+    let clear = Either::new(
+        |downloads: &Downloads, _| downloads.has_finished(),
+        control_link("Clear Finished").on_left_click(|ctx, _, _, _| {
+            ctx.submit_command(cmd::CLEAR_FINISHED_DOWNLOADS);
+        }),
+        Empty,
+    );
+    Flex::row()
+        .with_child(pause_or_resume)
+        .with_child(retry)
+        .with_child(clear)
+}
+
+fn control_link<T: Data>(title: &str) -> impl Widget<T> {
+    Label::new(title)
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .padding(theme::grid(0.5))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+}
+
+fn download_item_widget() -> impl Widget<DownloadItem> {
+    let title = Label::dynamic(|item: &DownloadItem, _| item.track.name.to_string())
+        .with_font(theme::UI_FONT_MEDIUM);
+    let artist = Label::dynamic(|item: &DownloadItem, _| item.track.artist_names())
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_text_color(theme::PLACEHOLDER_COLOR);
+    let status = Label::dynamic(|item: &DownloadItem, _| item.status_text())
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .with_line_break_mode(LineBreaking::Clip);
+    let cancel = control_link("Cancel").on_left_click(|ctx, _, item: &mut DownloadItem, _| {
+        ctx.submit_command(cmd::CANCEL_DOWNLOAD.with(item.track.id));
+    });
+
+    Flex::row()
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(title)
+                .with_spacer(2.0)
+                .with_child(artist),
+            1.0,
+        )
+        .with_default_spacer()
+        .with_child(status)
+        .with_default_spacer()
+        .with_child(cancel)
+        .padding(theme::grid(1.0))
+}
@@ -8,7 +8,7 @@ use druid::{
 use crate::{
     cmd,
     data::{AppState, Episode, Library, Nav},
-    widget::{FadeOut, MyWidgetExt, RemoteImage},
+    widget::{plain_text, FadeOut, MyWidgetExt, RemoteImage},
 };
 
 use super::{
@@ -73,8 +73,9 @@ pub fn playable_widget() -> impl Widget<PlayRow<Arc<Episode>>> {
         )
         .padding(theme::grid(1.0))
         .link()
+        .active(|row, _| row.is_selected)
         .rounded(theme::BUTTON_BORDER_RADIUS)
-        .on_left_click(|ctx, _, row, _| ctx.submit_notification(cmd::PLAY.with(row.position)))
+        .on_left_click(|ctx, mouse, row, _| playable::on_row_click(ctx, mouse, row))
         .context_menu(episode_row_menu)
 }
 
@@ -110,5 +111,20 @@ pub fn episode_menu(episode: &Episode, _library: &Arc<Library>) -> Menu<AppState
         .command(cmd::COPY.with(episode.url())),
     );
 
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-copy-name").with_placeholder("Copy Name"))
+            .command(cmd::COPY.with(episode.name.to_string())),
+    );
+
+    if !episode.description.is_empty() {
+        menu = menu.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-copy-description")
+                    .with_placeholder("Copy Description"),
+            )
+            .command(cmd::COPY.with(plain_text(&episode.description))),
+        );
+    }
+
     menu
 }
@@ -1,13 +1,13 @@
 use druid::{
     widget::{prelude::*, Controller, Either, Flex, Label, TextBox},
-    KbKey, Selector, WidgetExt,
+    KbKey, LensExt, Selector, WidgetExt,
 };
 
 use crate::{
     cmd,
     controller::InputController,
-    data::{FindQuery, Finder, MatchFindQuery},
-    ui::theme,
+    data::{AppState, CommonCtx, FindQuery, Finder, MatchFindQuery, TrackFilter},
+    ui::{playable, theme},
     widget::{Empty, MyWidgetExt},
 };
 
@@ -161,6 +161,23 @@ pub fn finder_widget(selector: Selector<Find>, label: &'static str) -> impl Widg
         .controller(FinderController { selector, input_id })
 }
 
+/// Filter box above a track list, Enter plays the first of the remaining rows.
+pub fn filter_widget(label: &'static str) -> impl Widget<AppState> {
+    TextBox::new()
+        .with_placeholder(label)
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .controller(InputController::new().on_submit(|ctx, _, _| {
+            ctx.submit_command(playable::PLAY_FIRST_MATCH);
+        }))
+        .expand_width()
+        .lens(
+            AppState::common_ctx
+                .then(CommonCtx::track_filter.in_arc())
+                .then(TrackFilter::query),
+        )
+        .padding((theme::grid(1.0), theme::grid(1.0), theme::grid(1.0), 0.0))
+}
+
 struct FinderController {
     selector: Selector<Find>,
     input_id: WidgetId,
@@ -202,7 +219,11 @@ where
                 ctx.set_handled();
             }
             Event::KeyDown(k_e) if k_e.key == KbKey::Escape => {
-                data.show = false;
+                if data.show {
+                    data.show = false;
+                    // Closing the finder shouldn't also navigate back.
+                    ctx.set_handled();
+                }
             }
             _ => {}
         }
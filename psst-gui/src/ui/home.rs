@@ -3,7 +3,7 @@ use druid::{widget::List, LensExt, Selector, Widget, WidgetExt};
 use crate::data::Ctx;
 use crate::{
     data::{AppState, Personalized},
-    webapi::WebApi,
+    webapi,
     widget::{Async, MyWidgetExt},
 };
 
@@ -29,7 +29,7 @@ pub fn home_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_MADE_FOR_YOU,
-        |_| WebApi::global().get_made_for_you(),
+        |_| webapi::global().get_made_for_you(),
         |_, data, d| data.personalized.made_for_you.defer(d),
         |_, data, r| data.personalized.made_for_you.update(r),
     )
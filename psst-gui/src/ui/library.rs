@@ -1,22 +1,34 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
-use druid::{widget::List, LensExt, Selector, Widget, WidgetExt};
+use druid::{
+    widget::{prelude::*, Controller, CrossAxisAlignment, Either, Flex, Label, LineBreaking, List},
+    LensExt, Selector, TimerToken, Widget, WidgetExt,
+};
 
 use crate::{
     cmd,
     data::{
-        Album, AlbumLink, AppState, Ctx, Library, SavedAlbums, SavedShows, SavedTracks, Show,
-        ShowLink, Track, TrackId,
+        Album, AlbumLink, AppState, Ctx, Library, LocalLibrary, LocalTracks, SavedAlbums,
+        SavedShows, SavedTracks, SearchTopic, Show, ShowLink, Track, TrackId, WithCtx,
     },
-    webapi::WebApi,
+    error::Error,
+    webapi,
     widget::{Async, MyWidgetExt},
 };
 
-use super::{album, playable, show, track, utils};
+use super::{album, playable, show, theme, track, utils};
+
+const LOCAL_FILES_RESCAN_INTERVAL: Duration = Duration::from_secs(30);
+/// Search results to compare every local file with.
+const LOCAL_MATCH_CANDIDATES: usize = 10;
 
 pub const LOAD_TRACKS: Selector = Selector::new("app.library.load-tracks");
 pub const LOAD_ALBUMS: Selector = Selector::new("app.library.load-albums");
 pub const LOAD_SHOWS: Selector = Selector::new("app.library.load-shows");
+pub const LOAD_LOCAL_FILES: Selector<Arc<Vec<PathBuf>>> =
+    Selector::new("app.library.load-local-files");
+pub const MATCH_LOCAL_FILES: Selector<Arc<Vec<PathBuf>>> =
+    Selector::new("app.library.match-local-files");
 
 pub const SAVE_TRACK: Selector<Arc<Track>> = Selector::new("app.library.save-track");
 pub const UNSAVE_TRACK: Selector<TrackId> = Selector::new("app.library.unsave-track");
@@ -55,7 +67,7 @@ pub fn saved_tracks_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_TRACKS,
-        |_| WebApi::global().get_saved_tracks().map(SavedTracks::new),
+        |_| webapi::global().get_saved_tracks().map(SavedTracks::new),
         |_, data, _| {
             data.with_library_mut(|library| {
                 library.saved_tracks.defer_default();
@@ -69,7 +81,7 @@ pub fn saved_tracks_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         SAVE_TRACK,
-        |t| WebApi::global().save_track(&t.id.0.to_base62()),
+        |t| webapi::global().save_track(&t.id.0.to_base62()),
         |_, data, t| {
             data.with_library_mut(|library| {
                 library.add_track(t);
@@ -85,7 +97,7 @@ pub fn saved_tracks_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         UNSAVE_TRACK,
-        |i| WebApi::global().unsave_track(&i.0.to_base62()),
+        |i| webapi::global().unsave_track(&i.0.to_base62()),
         |_, data, i| {
             data.with_library_mut(|library| {
                 library.remove_track(&i);
@@ -116,7 +128,7 @@ pub fn saved_albums_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_ALBUMS,
-        |_| WebApi::global().get_saved_albums().map(SavedAlbums::new),
+        |_| webapi::global().get_saved_albums().map(SavedAlbums::new),
         |_, data, _| {
             data.with_library_mut(|library| {
                 library.saved_albums.defer_default();
@@ -130,7 +142,7 @@ pub fn saved_albums_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         SAVE_ALBUM,
-        |a| WebApi::global().save_album(&a.id),
+        |a| webapi::global().save_album(&a.id),
         |_, data, a| {
             data.with_library_mut(move |library| {
                 library.add_album(a);
@@ -146,7 +158,7 @@ pub fn saved_albums_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         UNSAVE_ALBUM,
-        |l| WebApi::global().unsave_album(&l.id),
+        |l| webapi::global().unsave_album(&l.id),
         |_, data, l| {
             data.with_library_mut(|library| {
                 library.remove_album(&l.id);
@@ -177,7 +189,7 @@ pub fn saved_shows_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_SHOWS,
-        |_| WebApi::global().get_saved_shows().map(SavedShows::new),
+        |_| webapi::global().get_saved_shows().map(SavedShows::new),
         |_, data, _| {
             data.with_library_mut(|library| {
                 library.saved_shows.defer_default();
@@ -191,7 +203,7 @@ pub fn saved_shows_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         SAVE_SHOW,
-        |a| WebApi::global().save_show(&a.id),
+        |a| webapi::global().save_show(&a.id),
         |_, data, s| {
             data.with_library_mut(move |library| {
                 library.add_show(s);
@@ -207,7 +219,7 @@ pub fn saved_shows_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         UNSAVE_SHOW,
-        |l| WebApi::global().unsave_show(&l.id),
+        |l| webapi::global().unsave_show(&l.id),
         |_, data, l| {
             data.with_library_mut(|library| {
                 library.remove_show(&l.id);
@@ -222,3 +234,148 @@ pub fn saved_shows_widget() -> impl Widget<AppState> {
         },
     )
 }
+
+pub fn local_files_widget() -> impl Widget<AppState> {
+    let match_link = Label::new("Match with Spotify")
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .padding(theme::grid(0.5))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .on_left_click(|ctx, _, data: &mut AppState, _| {
+            let dirs = data.config.local_music_dirs();
+            ctx.submit_command(MATCH_LOCAL_FILES.with(Arc::new(dirs)));
+        });
+
+    let tracks = Async::new(
+        utils::spinner_widget,
+        || {
+            Either::new(
+                |tracks: &WithCtx<LocalTracks>, _| tracks.data.tracks.is_empty(),
+                Label::new(
+                    "No music files found. Add the directories with your music in the \
+                     preferences, MP3 and Ogg Vorbis files are supported.",
+                )
+                .with_line_break_mode(LineBreaking::WordWrap)
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .padding(theme::grid(1.0)),
+                playable::list_widget(playable::Display {
+                    track: track::Display {
+                        title: true,
+                        artist: true,
+                        album: true,
+                        ..track::Display::empty()
+                    },
+                }),
+            )
+        },
+        utils::error_widget,
+    )
+    .lens(
+        Ctx::make(
+            AppState::common_ctx,
+            AppState::library.then(Library::local_tracks.in_arc()),
+        )
+        .then(Ctx::in_promise()),
+    )
+    .on_command_async(
+        LOAD_LOCAL_FILES,
+        |dirs| {
+            let tracks = LocalLibrary::open().scan(&dirs);
+            Ok::<_, Error>(LocalTracks { tracks })
+        },
+        |_, data, _| {
+            // Keep showing the previous results while rescanning.
+            if !data.library.local_tracks.is_resolved() {
+                data.with_library_mut(|library| {
+                    library.local_tracks.defer_default();
+                });
+            }
+        },
+        |_, data, (_, r)| {
+            data.with_library_mut(|library| {
+                library.local_tracks.resolve_or_reject((), r);
+            });
+        },
+    )
+    .on_command_async(
+        MATCH_LOCAL_FILES,
+        |dirs| {
+            let mut local = LocalLibrary::open();
+            local.scan(&dirs);
+            let summary = local.match_catalog(|query| {
+                let results = webapi::global().search(
+                    query,
+                    &[SearchTopic::Track],
+                    LOCAL_MATCH_CANDIDATES,
+                )?;
+                Ok(results.tracks)
+            })?;
+            let tracks = LocalTracks {
+                tracks: local.tracks(),
+            };
+            Ok::<_, Error>((tracks, summary))
+        },
+        |_, data, _| data.info_alert("Matching local files with Spotify..."),
+        |_, data, (_, r)| match r {
+            Ok((tracks, summary)) => {
+                data.with_library_mut(|library| {
+                    library.local_tracks.resolve((), tracks);
+                });
+                data.info_alert(format!(
+                    "Matched {} of {} local files.",
+                    summary.matched, summary.checked
+                ));
+            }
+            Err(err) => data.error_alert(err),
+        },
+    );
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(match_link)
+        .with_default_spacer()
+        .with_child(tracks)
+        .controller(WatchLocalFiles {
+            timer: TimerToken::INVALID,
+        })
+}
+
+/// Rescans the local music directories every now and then, while the local files
+/// are shown.
+struct WatchLocalFiles {
+    timer: TimerToken,
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for WatchLocalFiles {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Timer(token) if *token == self.timer => {
+                let dirs = data.config.local_music_dirs();
+                ctx.submit_command(LOAD_LOCAL_FILES.with(Arc::new(dirs)));
+                self.timer = ctx.request_timer(LOCAL_FILES_RESCAN_INTERVAL);
+            }
+            _ => child.event(ctx, event, data, env),
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.timer = ctx.request_timer(LOCAL_FILES_RESCAN_INTERVAL);
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
@@ -2,7 +2,7 @@ use druid::{commands, platform_menus, Env, LocalizedString, Menu, MenuItem, SysM
 
 use crate::{
     cmd,
-    data::{AppState, Nav},
+    data::{AppState, Keymap, Nav, SidePanel},
 };
 
 pub fn main_menu(_window: Option<WindowId>, _data: &AppState, _env: &Env) -> Menu<AppState> {
@@ -13,6 +13,7 @@ pub fn main_menu(_window: Option<WindowId>, _data: &AppState, _env: &Env) -> Men
     }
     .entry(edit_menu())
     .entry(view_menu())
+    .entry(playback_menu())
 }
 
 fn mac_app_menu() -> Menu<AppState> {
@@ -55,37 +56,121 @@ fn view_menu() -> Menu<AppState> {
         .entry(
             MenuItem::new(LocalizedString::new("menu-item-home").with_placeholder("Home"))
                 .command(cmd::NAVIGATE.with(Nav::Home))
-                .hotkey(SysMods::Cmd, "1"),
+                .dynamic_hotkey(|data: &AppState, _| Keymap::hotkey(&data.config.keymap.home)),
         )
         .entry(
             MenuItem::new(
                 LocalizedString::new("menu-item-saved-tracks").with_placeholder("Saved Tracks"),
             )
             .command(cmd::NAVIGATE.with(Nav::SavedTracks))
-            .hotkey(SysMods::Cmd, "2"),
+            .dynamic_hotkey(|data: &AppState, _| Keymap::hotkey(&data.config.keymap.saved_tracks)),
         )
         .entry(
             MenuItem::new(
                 LocalizedString::new("menu-item-saved-albums").with_placeholder("Saved Albums"),
             )
             .command(cmd::NAVIGATE.with(Nav::SavedAlbums))
-            .hotkey(SysMods::Cmd, "3"),
+            .dynamic_hotkey(|data: &AppState, _| Keymap::hotkey(&data.config.keymap.saved_albums)),
         )
         .entry(
             MenuItem::new(
                 LocalizedString::new("menu-item-saved-shows").with_placeholder("Saved Shows"),
             )
             .command(cmd::NAVIGATE.with(Nav::SavedShows))
-            .hotkey(SysMods::Cmd, "4"),
+            .dynamic_hotkey(|data: &AppState, _| Keymap::hotkey(&data.config.keymap.saved_shows)),
         )
         .entry(
             MenuItem::new(LocalizedString::new("menu-item-search").with_placeholder("Search..."))
                 .command(cmd::SET_FOCUS.to(cmd::WIDGET_SEARCH_INPUT))
-                .hotkey(SysMods::Cmd, "l"),
+                .dynamic_hotkey(|data: &AppState, _| {
+                    Keymap::hotkey(&data.config.keymap.focus_search)
+                }),
         )
         .entry(
             MenuItem::new(LocalizedString::new("menu-item-find").with_placeholder("Find..."))
                 .command(cmd::TOGGLE_FINDER)
-                .hotkey(SysMods::Cmd, "f"),
+                .dynamic_hotkey(|data: &AppState, _| Keymap::hotkey(&data.config.keymap.find)),
         )
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-item-queue").with_placeholder("Queue Panel"))
+                .command(cmd::TOGGLE_SIDE_PANEL.with(SidePanel::Queue))
+                .dynamic_hotkey(|data: &AppState, _| Keymap::hotkey(&data.config.keymap.queue))
+                .selected_if(|data: &AppState, _| data.config.side_panel == Some(SidePanel::Queue)),
+        )
+}
+
+fn playback_menu() -> Menu<AppState> {
+    Menu::new(LocalizedString::new("menu-playback-menu").with_placeholder("Playback"))
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-private-session")
+                    .with_placeholder("Private Session"),
+            )
+            .command(cmd::TOGGLE_PRIVATE_SESSION)
+            .selected_if(|data: &AppState, _| data.playback.private_session),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-pip").with_placeholder("Picture in Picture"),
+            )
+            .command(cmd::TOGGLE_PIP),
+        )
+        .separator()
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-previous-chapter")
+                    .with_placeholder("Previous Chapter"),
+            )
+            .command(cmd::PLAY_PREVIOUS_CHAPTER)
+            .enabled_if(has_chapters),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-next-chapter").with_placeholder("Next Chapter"),
+            )
+            .command(cmd::PLAY_NEXT_CHAPTER)
+            .enabled_if(has_chapters),
+        )
+        .separator()
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-export-queue").with_placeholder("Export Queue..."),
+            )
+            .command(cmd::EXPORT_QUEUE)
+            .enabled_if(|data: &AppState, _| data.playback.now_playing.is_some()),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-import-queue").with_placeholder("Import Queue..."),
+            )
+            .command(cmd::IMPORT_QUEUE),
+        )
+        .separator()
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-save-queue-as-playlist")
+                    .with_placeholder("Save Queue as Playlist..."),
+            )
+            .command(cmd::SAVE_QUEUE_AS_PLAYLIST)
+            .enabled_if(|data: &AppState, _| {
+                data.playback
+                    .queue
+                    .iter()
+                    .any(|entry| entry.item.track().is_some())
+            }),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-save-history-as-playlist")
+                    .with_placeholder("Save Recently Played as Playlist..."),
+            )
+            .command(cmd::SAVE_HISTORY_AS_PLAYLIST),
+        )
+}
+
+fn has_chapters(data: &AppState, _env: &Env) -> bool {
+    data.playback
+        .now_playing
+        .as_ref()
+        .map_or(false, |now_playing| !now_playing.chapters.is_empty())
 }
@@ -2,35 +2,50 @@ use std::time::Duration;
 
 use druid::{
     im::Vector,
-    lens::Unit,
-    widget::{CrossAxisAlignment, Either, Flex, Label, List, Scroll, Slider, Split, ViewSwitcher},
+    lens::{Map, Unit},
+    widget::{
+        CrossAxisAlignment, Either, Flex, Label, List, ProgressBar, Scroll, Slider, Split,
+        ViewSwitcher,
+    },
     Color, Env, Insets, Key, LensExt, Menu, MenuItem, Selector, Widget, WidgetExt, WindowDesc,
+    WindowState,
 };
 use druid_shell::Cursor;
 
 use crate::data::config::SortCriteria;
 use crate::{
     cmd,
-    controller::{AfterDelay, NavController, SessionController, SortController},
+    controller::{
+        AfterDelay, CacheGcController, DownloadController, KeymapController,
+        LibraryBatchController, LiveUpdatesController, NavController, PlaylistBackupController,
+        PrefetchController, ScrollMemory, ScrollOffsets, SessionController, SortController,
+    },
     data::{
-        config::SortOrder, Alert, AlertStyle, AppState, Config, Nav, Playable, Playback, Route,
+        config::SortOrder, Alert, AlertAction, AlertStyle, AppState, Config, LoadProgress, Nav,
+        Playable, Playback, Route, SidePanel,
     },
     widget::{
-        icons, icons::SvgIcon, Border, Empty, MyWidgetExt, Overlay, ThemeScope, ViewDispatcher,
+        icons, icons::SvgIcon, Border, Dock, Empty, Maybe, MyWidgetExt, Overlay, ThemeScope,
+        ViewDispatcher, Zoom,
     },
 };
 
 pub mod album;
 pub mod artist;
+pub mod download;
 pub mod episode;
 pub mod find;
 pub mod home;
 pub mod library;
 pub mod menu;
+pub mod osd;
+pub mod pip;
 pub mod playable;
 pub mod playback;
 pub mod playlist;
 pub mod preferences;
+pub mod queue;
+pub mod radio;
 pub mod recommend;
 pub mod search;
 pub mod show;
@@ -46,6 +61,11 @@ pub fn main_window(config: &Config) -> WindowDesc<AppState> {
         .window_size(config.window_size)
         .show_title(false)
         .transparent_titlebar(true);
+    let win = if config.start_minimized {
+        win.set_window_state(WindowState::Minimized)
+    } else {
+        win
+    };
     if cfg!(target_os = "macos") {
         win.menu(menu::main_menu)
     } else {
@@ -144,12 +164,29 @@ fn root_widget() -> impl Widget<AppState> {
         .with_child(topbar_back_button_widget())
         .with_child(topbar_title_widget())
         .with_child(topbar_sort_widget())
+        .with_child(topbar_side_panel_widget())
         .background(Border::Bottom.with_color(theme::BACKGROUND_DARK));
 
     let main = Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_child(topbar)
-        .with_flex_child(Overlay::bottom(route_widget(), alert_widget()), 1.0)
+        .with_flex_child(
+            Dock::new(
+                Overlay::bottom(
+                    route_widget(),
+                    Flex::column()
+                        .with_child(library_batch_widget())
+                        .with_child(alert_widget()),
+                ),
+                queue::side_panel_widget(),
+                |data: &AppState, _| data.config.side_panel.is_some(),
+                AppState::config.then(Config::side_panel_width),
+            )
+            .on_command(cmd::TOGGLE_SIDE_PANEL, |_, &panel, data| {
+                data.toggle_side_panel(panel);
+            }),
+            1.0,
+        )
         .with_child(playback::panel_widget())
         .background(theme::BACKGROUND_LIGHT);
 
@@ -160,10 +197,17 @@ fn root_widget() -> impl Widget<AppState> {
         .min_bar_area(1.0)
         .solid_bar(true);
 
-    ThemeScope::new(split)
+    ThemeScope::new(Zoom::new(split))
         .controller(SessionController)
+        .controller(LiveUpdatesController::new())
         .controller(NavController)
         .controller(SortController)
+        .controller(PlaylistBackupController::new())
+        .controller(DownloadController::new())
+        .controller(LibraryBatchController)
+        .controller(PrefetchController::new())
+        .controller(CacheGcController::new())
+        .controller(KeymapController)
     // .debug_invalidation()
     // .debug_widget_id()
     // .debug_paint_layout()
@@ -185,6 +229,23 @@ fn alert_widget() -> impl Widget<AppState> {
             )
             .with_default_spacer()
             .with_flex_child(Label::raw().lens(Alert::message), 1.0)
+            .with_child(
+                Maybe::or_empty(|| {
+                    Label::raw()
+                        .with_font(theme::UI_FONT_MEDIUM)
+                        .lens(AlertAction::title)
+                        .padding((theme::grid(1.0), 0.0))
+                })
+                .lens(Alert::action)
+                .link()
+                .rounded(theme::BUTTON_BORDER_RADIUS)
+                .on_left_click(|ctx, _, alert: &mut Alert, _| {
+                    if let Some(action) = &alert.action {
+                        ctx.submit_command(action.command.clone());
+                    }
+                    ctx.submit_command(DISMISS_ALERT.with(alert.id));
+                }),
+            )
             .padding(theme::grid(2.0))
             .background(BG)
             .env_scope(|env, alert: &Alert| {
@@ -209,66 +270,97 @@ fn alert_widget() -> impl Widget<AppState> {
     })
 }
 
+fn library_batch_widget() -> impl Widget<AppState> {
+    Maybe::or_empty(|| {
+        Flex::row()
+            .with_child(Label::dynamic(|progress: &LoadProgress, _| {
+                format!(
+                    "Updating the library: {} of {} tracks",
+                    progress.loaded, progress.total
+                )
+            }))
+            .with_default_spacer()
+            .with_flex_child(
+                ProgressBar::new().lens(Map::new(LoadProgress::fraction, |_, _| {
+                    // Immutable.
+                })),
+                1.0,
+            )
+            .padding(theme::grid(2.0))
+            .background(theme::GREY_600)
+    })
+    .lens(AppState::library_batch)
+}
+
 fn route_widget() -> impl Widget<AppState> {
+    // The pages of a route are dropped when navigating to another route, keep
+    // their scroll positions here.
+    let offsets = ScrollOffsets::default();
     ViewDispatcher::new(
         |state: &AppState, _| state.nav.route(),
-        |route: &Route, _, _| match route {
-            Route::Home => Scroll::new(home::home_widget().padding(theme::grid(1.0)))
-                .vertical()
-                .boxed(),
+        move |route: &Route, _, _| match route {
+            Route::Home => scroll_widget(*route, &offsets, home::home_widget()).boxed(),
             Route::SavedTracks => Flex::column()
                 .with_child(
                     find::finder_widget(cmd::FIND_IN_SAVED_TRACKS, "Find in Saved Tracks...")
                         .lens(AppState::finder),
                 )
                 .with_flex_child(
-                    Scroll::new(library::saved_tracks_widget().padding(theme::grid(1.0)))
-                        .vertical(),
+                    scroll_widget(*route, &offsets, library::saved_tracks_widget()),
                     1.0,
                 )
                 .boxed(),
             Route::SavedAlbums => {
-                Scroll::new(library::saved_albums_widget().padding(theme::grid(1.0)))
-                    .vertical()
-                    .boxed()
+                scroll_widget(*route, &offsets, library::saved_albums_widget()).boxed()
             }
             Route::SavedShows => {
-                Scroll::new(library::saved_shows_widget().padding(theme::grid(1.0)))
-                    .vertical()
-                    .boxed()
+                scroll_widget(*route, &offsets, library::saved_shows_widget()).boxed()
             }
-            Route::SearchResults => Scroll::new(search::results_widget().padding(theme::grid(1.0)))
-                .vertical()
-                .boxed(),
-            Route::AlbumDetail => Scroll::new(album::detail_widget().padding(theme::grid(1.0)))
-                .vertical()
-                .boxed(),
-            Route::ArtistDetail => Scroll::new(artist::detail_widget().padding(theme::grid(1.0)))
-                .vertical()
+            Route::LocalFiles => {
+                scroll_widget(*route, &offsets, library::local_files_widget()).boxed()
+            }
+            Route::Downloads => {
+                scroll_widget(*route, &offsets, download::downloads_widget()).boxed()
+            }
+            Route::SearchResults => {
+                scroll_widget(*route, &offsets, search::results_widget()).boxed()
+            }
+            Route::AlbumDetail => Flex::column()
+                .with_child(find::filter_widget("Filter Album..."))
+                .with_flex_child(scroll_widget(*route, &offsets, album::detail_widget()), 1.0)
                 .boxed(),
+            Route::ArtistDetail => scroll_widget(*route, &offsets, artist::detail_widget()).boxed(),
             Route::PlaylistDetail => Flex::column()
                 .with_child(
                     find::finder_widget(cmd::FIND_IN_PLAYLIST, "Find in Playlist...")
                         .lens(AppState::finder),
                 )
+                .with_child(find::filter_widget("Filter Playlist..."))
                 .with_flex_child(
-                    Scroll::new(playlist::detail_widget().padding(theme::grid(1.0))).vertical(),
+                    scroll_widget(*route, &offsets, playlist::detail_widget()),
                     1.0,
                 )
                 .boxed(),
-            Route::ShowDetail => Scroll::new(show::detail_widget().padding(theme::grid(1.0)))
-                .vertical()
-                .boxed(),
+            Route::ShowDetail => scroll_widget(*route, &offsets, show::detail_widget()).boxed(),
             Route::Recommendations => {
-                Scroll::new(recommend::results_widget().padding(theme::grid(1.0)))
-                    .vertical()
-                    .boxed()
+                scroll_widget(*route, &offsets, recommend::results_widget()).boxed()
             }
+            Route::RadioBuilder => scroll_widget(*route, &offsets, radio::builder_widget()).boxed(),
         },
     )
     .expand()
 }
 
+fn scroll_widget(
+    route: Route,
+    offsets: &ScrollOffsets,
+    inner: impl Widget<AppState> + 'static,
+) -> impl Widget<AppState> {
+    Scroll::new(inner.padding(theme::grid(1.0)))
+        .vertical()
+        .controller(ScrollMemory::new(route, offsets.clone()))
+}
+
 fn sidebar_logo_widget() -> impl Widget<AppState> {
     icons::LOGO
         .scale((29.0, 32.0))
@@ -285,6 +377,9 @@ fn sidebar_menu_widget() -> impl Widget<AppState> {
         .with_child(sidebar_link_widget("Tracks", Nav::SavedTracks))
         .with_child(sidebar_link_widget("Albums", Nav::SavedAlbums))
         .with_child(sidebar_link_widget("Podcasts", Nav::SavedShows))
+        .with_child(sidebar_link_widget("Local Files", Nav::LocalFiles))
+        .with_child(sidebar_link_widget("Downloads", Nav::Downloads))
+        .with_child(sidebar_link_widget("Radio Builder", Nav::RadioBuilder))
         .with_child(search::input_widget().padding((theme::grid(1.0), theme::grid(1.0))))
 }
 
@@ -352,7 +447,9 @@ fn volume_slider() -> impl Widget<AppState> {
             },
         )
         .on_command(SAVE_TO_CONFIG, |_, _, data| {
-            data.config.volume = data.playback.volume;
+            let device = data.playback.output_device.clone();
+            data.config
+                .remember_volume(device.as_deref(), data.playback.volume);
             data.config.save();
         })
 }
@@ -401,6 +498,26 @@ fn topbar_sort_widget() -> impl Widget<AppState> {
     .padding(theme::grid(1.0)) //.lens(AppState::nav)
 }
 
+fn topbar_side_panel_widget() -> impl Widget<AppState> {
+    let icon = |color| {
+        icons::QUEUE
+            .scale(theme::ICON_SIZE_MEDIUM)
+            .with_color(color)
+            .padding(theme::grid(1.0))
+            .link()
+            .rounded(theme::BUTTON_BORDER_RADIUS)
+            .on_left_click(|ctx, _, _, _| {
+                ctx.submit_command(cmd::TOGGLE_SIDE_PANEL.with(SidePanel::Queue));
+            })
+    };
+    Either::new(
+        |data: &AppState, _| data.config.side_panel == Some(SidePanel::Queue),
+        icon(theme::GREY_100),
+        icon(theme::GREY_400),
+    )
+    .padding(theme::grid(1.0))
+}
+
 fn topbar_back_button_widget() -> impl Widget<AppState> {
     let icon = icons::BACK.scale((10.0, theme::grid(2.0)));
     let disabled = icon
@@ -485,12 +602,15 @@ fn route_icon_widget() -> impl Widget<Nav> {
                 Nav::SavedTracks => Empty.boxed(),
                 Nav::SavedAlbums => Empty.boxed(),
                 Nav::SavedShows => Empty.boxed(),
+                Nav::LocalFiles => Empty.boxed(),
+                Nav::Downloads => Empty.boxed(),
                 Nav::SearchResults(_) => icon(&icons::SEARCH).boxed(),
                 Nav::AlbumDetail(_) => icon(&icons::ALBUM).boxed(),
                 Nav::ArtistDetail(_) => icon(&icons::ARTIST).boxed(),
                 Nav::PlaylistDetail(_) => icon(&icons::PLAYLIST).boxed(),
                 Nav::ShowDetail(_) => icon(&icons::PODCAST).boxed(),
                 Nav::Recommendations(_) => icon(&icons::SEARCH).boxed(),
+                Nav::RadioBuilder => Empty.boxed(),
             }
         },
     )
@@ -504,6 +624,18 @@ fn route_title_widget() -> impl Widget<Nav> {
 
 fn compute_main_window_title(data: &AppState, _env: &Env) -> String {
     if let Some(now_playing) = &data.playback.now_playing {
+        // Druid doesn't expose an accessibility tree we could put a live region into,
+        // but screen readers do read out title changes of the active window.
+        if data.config.announce_track_changes {
+            return match &now_playing.item {
+                Playable::Track(track) => {
+                    format!("Now playing: {} by {}", track.name, track.artist_names())
+                }
+                Playable::Episode(episode) => {
+                    format!("Now playing: {} from {}", episode.name, episode.show.name)
+                }
+            };
+        }
         match &now_playing.item {
             Playable::Track(track) => {
                 format!("{} - {}", track.artist_name(), track.name)
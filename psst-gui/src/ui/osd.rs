@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use druid::{
+    commands,
+    widget::{prelude::*, Controller, CrossAxisAlignment, Flex, Label, LineBreaking},
+    Selector, TimerToken, WidgetExt, WindowDesc,
+};
+
+use crate::{
+    data::{AppState, Config, NowPlaying, Playable, Playback},
+    widget::{Maybe, ThemeScope},
+};
+
+use super::{playback, theme, utils};
+
+/// Restart the countdown of an overlay that's already shown.
+pub const RESTART: Selector = Selector::new("app.osd.restart");
+
+const WIDTH: f64 = 320.0;
+const HEIGHT: f64 = 80.0;
+
+/// Borderless window floating above everything else, with the playing track.
+/// It closes itself after the configured duration.
+pub fn osd_window(config: &Config) -> WindowDesc<AppState> {
+    let duration = Duration::from_secs(config.osd_duration_secs.max(1) as u64);
+    let widget = ThemeScope::new(
+        Maybe::or_empty(now_playing_widget)
+            .lens(AppState::playback.then(Playback::now_playing))
+            .padding(theme::grid(1.0))
+            .expand()
+            .background(theme::BACKGROUND_DARK)
+            .controller(CloseAfter::new(duration)),
+    );
+    WindowDesc::new(widget)
+        .title("Now Playing")
+        .window_size((WIDTH, HEIGHT))
+        .resizable(false)
+        .show_titlebar(false)
+        .set_always_on_top(true)
+        .set_position(utils::screen_corner_position(
+            config.osd_position,
+            Size::new(WIDTH, HEIGHT),
+        ))
+}
+
+fn now_playing_widget() -> impl Widget<NowPlaying> {
+    let title = Label::dynamic(|now_playing: &NowPlaying, _| match &now_playing.item {
+        Playable::Track(track) => track.name.to_string(),
+        Playable::Episode(episode) => episode.name.to_string(),
+    })
+    .with_font(theme::UI_FONT_MEDIUM)
+    .with_line_break_mode(LineBreaking::Clip);
+    let subtitle = Label::dynamic(|now_playing: &NowPlaying, _| match &now_playing.item {
+        Playable::Track(track) => track.artist_names(),
+        Playable::Episode(episode) => episode.show.name.to_string(),
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .with_line_break_mode(LineBreaking::Clip);
+
+    Flex::row()
+        .with_child(playback::cover_widget(theme::grid(7.0)))
+        .with_default_spacer()
+        .with_flex_child(
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(title)
+                .with_spacer(2.0)
+                .with_child(subtitle),
+            1.0,
+        )
+}
+
+struct CloseAfter {
+    duration: Duration,
+    timer: TimerToken,
+}
+
+impl CloseAfter {
+    fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            timer: TimerToken::INVALID,
+        }
+    }
+}
+
+impl<T, W: Widget<T>> Controller<T, W> for CloseAfter {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::Timer(token) if *token == self.timer => {
+                ctx.submit_command(commands::CLOSE_WINDOW.to(ctx.window_id()));
+            }
+            Event::Command(cmd) if cmd.is(RESTART) => {
+                self.timer = ctx.request_timer(self.duration);
+                ctx.set_handled();
+            }
+            // Clicking the overlay dismisses it right away.
+            Event::MouseDown(_) => {
+                ctx.submit_command(commands::CLOSE_WINDOW.to(ctx.window_id()));
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &T,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.timer = ctx.request_timer(self.duration);
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
@@ -0,0 +1,104 @@
+use druid::{
+    widget::{prelude::*, Controller},
+    LensExt, LocalizedString, Menu, MenuItem, Selector, Target, WidgetExt, WindowDesc,
+};
+
+use crate::{
+    cmd,
+    data::{AppState, Config, Playback, PlaybackState, ScreenCorner},
+    widget::{Maybe, MyWidgetExt, ThemeScope},
+};
+
+use super::{playback, theme, utils};
+
+const MOVE_TO: Selector<ScreenCorner> = Selector::new("app.pip.move-to");
+
+const SIZE: f64 = 160.0;
+
+/// Tiny borderless window with only the artwork of the playing item.  Clicking
+/// it pauses or resumes the playback, scrolling changes the volume.
+pub fn pip_window(config: &Config) -> WindowDesc<AppState> {
+    let widget = ThemeScope::new(
+        Maybe::or_empty(|| playback::artwork_widget(SIZE))
+            .lens(AppState::playback.then(Playback::now_playing))
+            .center()
+            .background(theme::BACKGROUND_DARK)
+            // The playback is controlled from the main window.
+            .on_left_click(|ctx, _, data: &mut AppState, _| match data.playback.state {
+                PlaybackState::Playing => ctx.submit_command(cmd::PLAY_PAUSE.to(Target::Global)),
+                PlaybackState::Paused => ctx.submit_command(cmd::PLAY_RESUME.to(Target::Global)),
+                PlaybackState::Loading | PlaybackState::Stopped => {}
+            })
+            .on_scroll(
+                |data| &data.config.slider_scroll_scale,
+                |_, data, _, scaled_delta| {
+                    data.playback.volume = (data.playback.volume + scaled_delta).clamp(0.0, 1.0);
+                },
+            )
+            .context_menu(pip_menu)
+            .controller(MoveToCorner),
+    );
+    WindowDesc::new(widget)
+        .title("Artwork")
+        .window_size((SIZE, SIZE))
+        .resizable(false)
+        .show_titlebar(false)
+        .set_always_on_top(true)
+        .set_position(utils::screen_corner_position(
+            config.pip_position,
+            Size::new(SIZE, SIZE),
+        ))
+}
+
+fn pip_menu(data: &AppState) -> Menu<AppState> {
+    let corner = |title: &str, corner: ScreenCorner| {
+        MenuItem::new(title)
+            .command(MOVE_TO.with(corner))
+            .selected(data.config.pip_position == corner)
+    };
+    Menu::empty()
+        .entry(corner("Top Left", ScreenCorner::TopLeft))
+        .entry(corner("Top Right", ScreenCorner::TopRight))
+        .entry(corner("Bottom Left", ScreenCorner::BottomLeft))
+        .entry(corner("Bottom Right", ScreenCorner::BottomRight))
+        .separator()
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-show-main").with_placeholder("Show Psst"),
+            )
+            .command(cmd::SHOW_MAIN),
+        )
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-item-close-pip").with_placeholder("Close"))
+                .command(cmd::TOGGLE_PIP),
+        )
+}
+
+/// The window has no title bar to drag it by, so it's moved between the corners
+/// of the screen from its menu instead.
+struct MoveToCorner;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for MoveToCorner {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(MOVE_TO) => {
+                let corner = *cmd.get_unchecked(MOVE_TO);
+                data.config.pip_position = corner;
+                data.config.save();
+                ctx.window()
+                    .set_position(utils::screen_corner_position(corner, Size::new(SIZE, SIZE)));
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+}
@@ -6,15 +6,15 @@ use druid::{
     lens::Map,
     piet::StrokeStyle,
     widget::{Controller, ControllerHost, List, ListIter, Painter, ViewSwitcher},
-    Data, Env, Event, EventCtx, Lens, RenderContext, Selector, Widget, WidgetExt,
+    Data, Env, Event, EventCtx, Lens, MouseEvent, RenderContext, Selector, Widget, WidgetExt,
 };
 
 use crate::{
     cmd,
     data::{
-        Album, ArtistTracks, CommonCtx, FindQuery, MatchFindQuery, Playable, PlaybackOrigin,
-        PlaybackPayload, PlaylistTracks, Recommendations, SavedTracks, SearchResults, ShowEpisodes,
-        WithCtx,
+        Album, ArtistTracks, ClickToPlay, CommonCtx, FindQuery, LocalTracks, MatchFindQuery,
+        Playable, PlaybackOrigin, PlaybackPayload, PlaylistTracks, Recommendations, SavedTracks,
+        SearchResults, Selection, ShowEpisodes, WithCtx,
     },
     ui::theme,
 };
@@ -25,6 +25,10 @@ use super::{
     track,
 };
 
+const SELECT: Selector<usize> = Selector::new("app.playable.select");
+/// Play the list the track filter applies to, starting at its first match.
+pub const PLAY_FIRST_MATCH: Selector = Selector::new("app.playable.play-first-match");
+
 #[derive(Copy, Clone)]
 pub struct Display {
     pub track: track::Display,
@@ -77,6 +81,19 @@ fn playable_widget(display: Display) -> impl Widget<PlayRow<Playable>> {
     )
 }
 
+/// Select the clicked row, and depending on the preference either play it right away
+/// or wait for a double-click.
+pub fn on_row_click<T>(ctx: &mut EventCtx, mouse: &MouseEvent, row: &PlayRow<T>) {
+    ctx.submit_notification(SELECT.with(row.position));
+    let play = match row.ctx.click_to_play {
+        ClickToPlay::SingleClick => true,
+        ClickToPlay::DoubleClick => mouse.count >= 2,
+    };
+    if play {
+        ctx.submit_notification(cmd::PLAY.with(row.position));
+    }
+}
+
 pub fn is_playing_marker_widget() -> impl Widget<bool> {
     Painter::new(|ctx, is_playing, env| {
         const STYLE: StrokeStyle = StrokeStyle::new().dash_pattern(&[1.0, 2.0]);
@@ -99,6 +116,7 @@ pub struct PlayRow<T> {
     pub origin: Arc<PlaybackOrigin>,
     pub position: usize,
     pub is_playing: bool,
+    pub is_selected: bool,
 }
 
 impl<T> PlayRow<T> {
@@ -109,13 +127,20 @@ impl<T> PlayRow<T> {
             origin: self.origin.clone(),
             position: self.position,
             is_playing: self.is_playing,
+            is_selected: self.is_selected,
         }
     }
 }
 
 impl MatchFindQuery for PlayRow<Playable> {
     fn matches_query(&self, q: &FindQuery) -> bool {
-        match &self.item {
+        self.item.matches_query(q)
+    }
+}
+
+impl MatchFindQuery for Playable {
+    fn matches_query(&self, q: &FindQuery) -> bool {
+        match self {
             Playable::Track(track) => {
                 q.matches_str(&track.name)
                     || track.album.iter().any(|a| q.matches_str(&a.name))
@@ -184,9 +209,25 @@ impl PlayableIter for ArtistTracks {
     }
 }
 
+impl PlayableIter for LocalTracks {
+    fn origin(&self) -> PlaybackOrigin {
+        PlaybackOrigin::LocalFiles
+    }
+
+    fn for_each(&self, mut cb: impl FnMut(Playable, usize)) {
+        for (position, track) in self.tracks.iter().enumerate() {
+            cb(Playable::Track(track.to_owned()), position);
+        }
+    }
+
+    fn count(&self) -> usize {
+        self.tracks.len()
+    }
+}
+
 impl PlayableIter for SavedTracks {
     fn origin(&self) -> PlaybackOrigin {
-        PlaybackOrigin::Library
+        PlaybackOrigin::LikedSongs
     }
 
     fn for_each(&self, mut cb: impl FnMut(Playable, usize)) {
@@ -248,44 +289,56 @@ impl PlayableIter for ShowEpisodes {
     }
 }
 
+/// Rows left out by the track filter are skipped, so the list indices don't
+/// always match the positions in the playable.
+fn for_each_row<T>(list: &WithCtx<T>, mut cb: impl FnMut(PlayRow<Playable>, usize))
+where
+    T: PlayableIter + Data,
+{
+    let origin = Arc::new(list.data.origin());
+    let filter = list.ctx.track_filter.query_for(&origin);
+    let mut index = 0;
+    list.data.for_each(|item, position| {
+        if filter.as_ref().map_or(true, |q| item.matches_query(q)) {
+            let row = PlayRow {
+                is_playing: list.ctx.is_playing(&item),
+                is_selected: list.ctx.is_selected(&origin, position),
+                ctx: list.ctx.to_owned(),
+                origin: origin.clone(),
+                item,
+                position,
+            };
+            cb(row, index);
+            index += 1;
+        }
+    });
+}
+
 impl<T> ListIter<PlayRow<Playable>> for WithCtx<T>
 where
     T: PlayableIter + Data,
 {
     fn for_each(&self, mut cb: impl FnMut(&PlayRow<Playable>, usize)) {
-        let origin = Arc::new(self.data.origin());
-        self.data.for_each(|item, position| {
-            cb(
-                &PlayRow {
-                    is_playing: self.ctx.is_playing(&item),
-                    ctx: self.ctx.to_owned(),
-                    origin: origin.clone(),
-                    item,
-                    position,
-                },
-                position,
-            )
-        });
+        for_each_row(self, |row, index| cb(&row, index));
     }
 
     fn for_each_mut(&mut self, mut cb: impl FnMut(&mut PlayRow<Playable>, usize)) {
-        let origin = Arc::new(self.data.origin());
-        self.data.for_each(|item, position| {
-            cb(
-                &mut PlayRow {
-                    is_playing: self.ctx.is_playing(&item),
-                    ctx: self.ctx.to_owned(),
-                    origin: origin.clone(),
-                    item,
-                    position,
-                },
-                position,
-            )
-        });
+        for_each_row(self, |mut row, index| cb(&mut row, index));
     }
 
     fn data_len(&self) -> usize {
-        self.data.count()
+        if self
+            .ctx
+            .track_filter
+            .query_for(&self.data.origin())
+            .is_some()
+        {
+            let mut count = 0;
+            for_each_row(self, |_, _| count += 1);
+            count
+        } else {
+            self.data.count()
+        }
     }
 }
 
@@ -305,6 +358,28 @@ where
         env: &Env,
     ) {
         match event {
+            Event::Command(cmd) if cmd.is(PLAY_FIRST_MATCH) => {
+                let origin = data.data.origin();
+                if let Some(query) = data.ctx.track_filter.query_for(&origin) {
+                    let mut items = Vector::new();
+                    let mut first_match = None;
+                    data.data.for_each(|item, position| {
+                        if first_match.is_none() && item.matches_query(&query) {
+                            first_match = Some(position);
+                        }
+                        items.push_back(item);
+                    });
+                    if let Some(position) = first_match {
+                        let payload = PlaybackPayload {
+                            items,
+                            origin,
+                            position,
+                        };
+                        ctx.submit_command(cmd::PLAY_TRACKS.with(payload));
+                        ctx.set_handled();
+                    }
+                }
+            }
             Event::Notification(note) => {
                 if let Some(position) = note.get(cmd::PLAY) {
                     let mut items = Vector::new();
@@ -316,6 +391,12 @@ where
                     };
                     ctx.submit_command(cmd::PLAY_TRACKS.with(payload));
                     ctx.set_handled();
+                } else if let Some(position) = note.get(SELECT) {
+                    Arc::make_mut(&mut data.ctx).selection = Some(Selection {
+                        origin: data.data.origin(),
+                        position: position.to_owned(),
+                    });
+                    ctx.set_handled();
                 }
             }
             _ => child.event(ctx, event, data, env),
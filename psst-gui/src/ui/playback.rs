@@ -2,16 +2,18 @@ use std::time::Duration;
 
 use druid::{
     kurbo::{Affine, BezPath},
-    widget::{CrossAxisAlignment, Either, Flex, Label, LineBreaking, Spinner, ViewSwitcher},
-    BoxConstraints, Cursor, Data, Env, Event, EventCtx, LayoutCtx, LensExt, LifeCycle,
-    LifeCycleCtx, MouseButton, PaintCtx, Point, Rect, RenderContext, Size, UpdateCtx, Widget,
-    WidgetExt, WidgetPod,
+    widget::{
+        CrossAxisAlignment, Either, Flex, Label, LineBreaking, Spinner, TextBox, ViewSwitcher,
+    },
+    BoxConstraints, Cursor, Data, Env, Event, EventCtx, InternalLifeCycle, LayoutCtx, LensExt,
+    LifeCycle, LifeCycleCtx, LocalizedString, Menu, MenuItem, MouseButton, PaintCtx, Point, Rect,
+    RenderContext, Size, UpdateCtx, Widget, WidgetExt, WidgetId, WidgetPod,
 };
 use itertools::Itertools;
 
 use crate::{
     cmd,
-    controller::PlaybackController,
+    controller::{InputController, PlaybackController},
     data::{
         AppState, AudioAnalysis, Episode, NowPlaying, Playable, PlayableMatcher, Playback,
         PlaybackOrigin, PlaybackState, QueueBehavior, ShowLink, Track,
@@ -37,7 +39,14 @@ pub fn panel_widget() -> impl Widget<AppState> {
 }
 
 fn playing_item_widget() -> impl Widget<NowPlaying> {
-    let cover_art = cover_widget(theme::grid(8.0));
+    let cover_art = cover_widget(theme::grid(8.0)).context_menu(|_| {
+        Menu::empty().entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-pip").with_placeholder("Picture in Picture"),
+            )
+            .command(cmd::TOGGLE_PIP),
+        )
+    });
 
     let name = PlayableMatcher::new()
         .track(
@@ -54,7 +63,7 @@ fn playing_item_widget() -> impl Widget<NowPlaying> {
         )
         .lens(NowPlaying::item);
 
-    let detail = PlayableMatcher::new()
+    let artist = PlayableMatcher::new()
         .track(
             Label::raw()
                 .with_line_break_mode(LineBreaking::Clip)
@@ -69,6 +78,20 @@ fn playing_item_widget() -> impl Widget<NowPlaying> {
         )
         .lens(NowPlaying::item);
 
+    // Long local files show the playing chapter instead of the artist.
+    let detail = Either::new(
+        |now_playing: &NowPlaying, _| now_playing.current_chapter().is_some(),
+        Label::dynamic(|now_playing: &NowPlaying, _| {
+            now_playing
+                .current_chapter()
+                .map(|index| now_playing.chapters[index].title.to_string())
+                .unwrap_or_default()
+        })
+        .with_line_break_mode(LineBreaking::Clip)
+        .with_text_size(theme::TEXT_SIZE_SMALL),
+        artist,
+    );
+
     let origin = ViewSwitcher::new(
         |origin: &PlaybackOrigin, _| origin.clone(),
         |origin, _, _| {
@@ -113,7 +136,11 @@ fn playing_item_widget() -> impl Widget<NowPlaying> {
         )
         .with_child(ViewSwitcher::new(
             |now_playing: &NowPlaying, _| {
-                now_playing.item.track().is_some() && now_playing.library.saved_tracks.is_resolved()
+                let in_catalog = now_playing
+                    .item
+                    .track()
+                    .map_or(false, |track| track.catalog_track_id().is_some());
+                in_catalog && now_playing.library.saved_tracks.is_resolved()
             },
             |selector, _data, _env| match selector {
                 true => {
@@ -135,10 +162,12 @@ fn playing_item_widget() -> impl Widget<NowPlaying> {
                     )
                     .on_left_click(|ctx, _, now_playing, _| {
                         let track = now_playing.item.track().unwrap();
+                        // Matched local files are saved as their catalog tracks.
+                        let catalog_track = track.to_catalog_track().unwrap();
                         if now_playing.library.contains_track(track) {
-                            ctx.submit_command(library::UNSAVE_TRACK.with(track.id))
+                            ctx.submit_command(library::UNSAVE_TRACK.with(catalog_track.id))
                         } else {
-                            ctx.submit_command(library::SAVE_TRACK.with(track.clone()))
+                            ctx.submit_command(library::SAVE_TRACK.with(catalog_track))
                         }
                     })
                     .padding(theme::grid(1.0))
@@ -151,7 +180,7 @@ fn playing_item_widget() -> impl Widget<NowPlaying> {
         .link()
 }
 
-fn cover_widget(size: f64) -> impl Widget<NowPlaying> {
+pub fn cover_widget(size: f64) -> impl Widget<NowPlaying> {
     RemoteImage::new(utils::placeholder_widget(), move |np: &NowPlaying, _| {
         np.cover_image_url(size, size).map(|url| url.into())
     })
@@ -159,9 +188,19 @@ fn cover_widget(size: f64) -> impl Widget<NowPlaying> {
     .clip(Size::new(size, size).to_rounded_rect(4.0))
 }
 
+/// Like `cover_widget`, but showing the canvas of the track when there is one.
+pub fn artwork_widget(size: f64) -> impl Widget<NowPlaying> {
+    RemoteImage::new(utils::placeholder_widget(), move |np: &NowPlaying, _| {
+        np.artwork_url(size, size).map(|url| url.into())
+    })
+    .fix_size(size, size)
+    .clip(Size::new(size, size).to_rounded_rect(4.0))
+}
+
 fn playback_origin_icon(origin: &PlaybackOrigin) -> &'static SvgIcon {
     match origin {
-        PlaybackOrigin::Library => &icons::HEART,
+        PlaybackOrigin::Library | PlaybackOrigin::LikedSongs => &icons::HEART,
+        PlaybackOrigin::LocalFiles => &icons::STORAGE,
         PlaybackOrigin::Album { .. } => &icons::ALBUM,
         PlaybackOrigin::Artist { .. } => &icons::ARTIST,
         PlaybackOrigin::Playlist { .. } => &icons::PLAYLIST,
@@ -187,7 +226,8 @@ fn player_widget() -> impl Widget<Playback> {
         .with_default_spacer()
         .with_child(queue_behavior_widget())
         .with_default_spacer()
-        .with_child(Maybe::or_empty(durations_widget).lens(Playback::now_playing))
+        .with_child(private_session_widget())
+        .with_child(Maybe::or_empty(JumpToTime::new).lens(Playback::now_playing))
         .padding(theme::grid(2.0))
 }
 
@@ -195,15 +235,20 @@ fn player_play_pause_widget() -> impl Widget<Playback> {
     ViewSwitcher::new(
         |playback: &Playback, _| playback.state,
         |state, _, _| match state {
-            PlaybackState::Loading => Spinner::new()
-                .with_color(theme::GREY_400)
-                .fix_size(theme::grid(3.0), theme::grid(3.0))
-                .padding(theme::grid(1.0))
-                .link()
-                .circle()
-                .border(theme::GREY_600, 1.0)
-                .on_left_click(|ctx, _, _, _| ctx.submit_command(cmd::PLAY_STOP))
-                .boxed(),
+            PlaybackState::Loading => Either::new(
+                |_, env| env.get(theme::REDUCED_MOTION),
+                icons::PLAY
+                    .scale((theme::grid(3.0), theme::grid(3.0)))
+                    .with_color(theme::GREY_400),
+                Spinner::new().with_color(theme::GREY_400),
+            )
+            .fix_size(theme::grid(3.0), theme::grid(3.0))
+            .padding(theme::grid(1.0))
+            .link()
+            .circle()
+            .border(theme::GREY_600, 1.0)
+            .on_left_click(|ctx, _, _, _| ctx.submit_command(cmd::PLAY_STOP))
+            .boxed(),
             PlaybackState::Playing => icons::PAUSE
                 .scale((theme::grid(3.0), theme::grid(3.0)))
                 .padding(theme::grid(1.0))
@@ -236,11 +281,56 @@ fn queue_behavior_widget() -> impl Widget<Playback> {
                             .with(cycle_queue_behavior(&playback.queue_behavior)),
                     );
                 })
+                .context_menu(queue_behavior_menu)
                 .boxed()
         },
     )
 }
 
+fn private_session_widget() -> impl Widget<Playback> {
+    Either::new(
+        |playback: &Playback, _| playback.private_session,
+        Label::new("Private")
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .padding(theme::grid(1.0))
+            .link()
+            .rounded(theme::BUTTON_BORDER_RADIUS)
+            .on_left_click(|ctx, _, _, _| ctx.submit_command(cmd::TOGGLE_PRIVATE_SESSION)),
+        Empty,
+    )
+}
+
+fn queue_behavior_menu(playback: &Playback) -> Menu<AppState> {
+    let mut menu = Menu::empty();
+
+    let origin = playback.now_playing.as_ref().map(|np| &np.origin);
+    let context = origin.and_then(|origin| Some((origin, origin.context_uri()?)));
+    if let Some((origin, uri)) = context {
+        let behavior = Some(playback.queue_behavior);
+        menu = menu
+            .entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-remember-queue-behavior")
+                        .with_placeholder(format!("Always Play “{}” Like This", origin)),
+                )
+                .command(cmd::REMEMBER_CONTEXT_QUEUE_BEHAVIOR.with(behavior)),
+            )
+            .entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-forget-queue-behavior")
+                        .with_placeholder(format!("Forget Setting for “{}”", origin)),
+                )
+                .command(cmd::REMEMBER_CONTEXT_QUEUE_BEHAVIOR.with(None))
+                .enabled_if(move |data: &AppState, _| {
+                    data.config.context_queue_behavior.contains_key(&uri)
+                }),
+            );
+    }
+
+    menu
+}
+
 fn cycle_queue_behavior(qb: &QueueBehavior) -> QueueBehavior {
     match qb {
         QueueBehavior::Sequential => QueueBehavior::Random,
@@ -287,6 +377,119 @@ fn durations_widget() -> impl Widget<NowPlaying> {
     .fix_width(theme::grid(8.0))
 }
 
+/// Shows the durations, clicking them turns them into an input for jumping to
+/// a typed timestamp.
+struct JumpToTime {
+    durations: WidgetPod<NowPlaying, Box<dyn Widget<NowPlaying>>>,
+    editor: WidgetPod<String, Box<dyn Widget<String>>>,
+    editor_id: WidgetId,
+    input: String,
+    editing: bool,
+}
+
+impl JumpToTime {
+    fn new() -> Self {
+        let editor_id = WidgetId::next();
+        let editor = TextBox::new()
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .controller(InputController::new().on_submit(|ctx, input, _| {
+                if let Some(position) = utils::parse_timestamp(input) {
+                    ctx.submit_command(cmd::PLAY_SEEK_TO.with(position));
+                }
+            }))
+            .with_id(editor_id)
+            .fix_width(theme::grid(8.0));
+        Self {
+            durations: WidgetPod::new(durations_widget().link().boxed()),
+            editor: WidgetPod::new(editor.boxed()),
+            editor_id,
+            input: String::new(),
+            editing: false,
+        }
+    }
+}
+
+impl Widget<NowPlaying> for JumpToTime {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut NowPlaying, env: &Env) {
+        if self.editing {
+            let input = self.input.clone();
+            self.editor.event(ctx, event, &mut self.input, env);
+            if !input.same(&self.input) {
+                ctx.request_update();
+            }
+            return;
+        }
+        match event {
+            Event::MouseDown(mouse) if mouse.button == MouseButton::Left => {
+                self.input = utils::as_minutes_and_seconds(data.progress);
+                self.editing = true;
+                ctx.submit_command(cmd::SET_FOCUS.to(self.editor_id));
+                ctx.request_layout();
+                ctx.set_handled();
+            }
+            _ => {
+                self.durations.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &NowPlaying,
+        env: &Env,
+    ) {
+        if let LifeCycle::Internal(InternalLifeCycle::RouteFocusChanged { old, new }) = event {
+            // Close the editor once it's submitted or loses focus.
+            let editor_id = Some(self.editor_id);
+            if self.editing && *old == editor_id && *new != editor_id {
+                self.editing = false;
+                ctx.request_layout();
+            }
+        }
+        self.durations.lifecycle(ctx, event, data, env);
+        self.editor.lifecycle(ctx, event, &self.input, env);
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        _old_data: &NowPlaying,
+        data: &NowPlaying,
+        env: &Env,
+    ) {
+        self.durations.update(ctx, data, env);
+        self.editor.update(ctx, &self.input, env);
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &NowPlaying,
+        env: &Env,
+    ) -> Size {
+        let durations = self.durations.layout(ctx, bc, data, env);
+        self.durations.set_origin(ctx, Point::ORIGIN);
+        let editor = self.editor.layout(ctx, bc, &self.input, env);
+        self.editor.set_origin(ctx, Point::ORIGIN);
+        if self.editing {
+            editor
+        } else {
+            durations
+        }
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &NowPlaying, env: &Env) {
+        if self.editing {
+            self.editor.paint(ctx, &self.input, env);
+        } else {
+            self.durations.paint(ctx, data, env);
+        }
+    }
+}
+
 struct BarLayout<T, I, P> {
     item: WidgetPod<T, I>,
     player: WidgetPod<T, P>,
@@ -456,6 +659,8 @@ impl Widget<NowPlaying> for SeekBar {
         } else {
             paint_audio_analysis(ctx, data, &self.loudness_path, env)
         }
+        paint_chapter_markers(ctx, data, env);
+        paint_loop_section(ctx, data, env);
     }
 }
 
@@ -568,3 +773,36 @@ fn paint_progress_bar(ctx: &mut PaintCtx, data: &NowPlaying, env: &Env) {
         &remaining_color,
     );
 }
+
+fn paint_chapter_markers(ctx: &mut PaintCtx, data: &NowPlaying, env: &Env) {
+    let total_time = data.item.duration().as_secs_f64();
+    if total_time == 0.0 {
+        return;
+    }
+    let bounds = ctx.size();
+    let color = env.get(theme::BACKGROUND_LIGHT);
+    for chapter in data.chapters.iter().skip_while(|c| c.start.is_zero()) {
+        let x = (bounds.width * chapter.start.as_secs_f64() / total_time).round();
+        ctx.fill(Rect::new(x - 1.0, 0.0, x + 1.0, bounds.height), &color);
+    }
+}
+
+fn paint_loop_section(ctx: &mut PaintCtx, data: &NowPlaying, env: &Env) {
+    let section = &data.loop_section;
+    let start = match section.start {
+        Some(start) => start,
+        None => return,
+    };
+    let total_time = data.item.duration().as_secs_f64();
+    let bounds = ctx.size();
+    let start_x = bounds.width * start.as_secs_f64() / total_time;
+    // Only the start marker is shown until the end is set.
+    let end_x = section.end.map_or(start_x + 2.0, |end| {
+        bounds.width * end.as_secs_f64() / total_time
+    });
+
+    ctx.fill(
+        Rect::new(start_x, 0.0, end_x, bounds.height),
+        &env.get(theme::BLUE_200).with_alpha(0.6),
+    );
+}
@@ -1,15 +1,21 @@
+use std::any::Any;
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::{cmp::Ordering, sync::Arc};
+use std::{
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
 
 use druid::widget::{Button, LensWrap, TextBox};
 use druid::{
     im::Vector,
-    widget::{CrossAxisAlignment, Flex, Label, LineBreaking, List},
+    lens::Map,
+    widget::{
+        prelude::*, Controller, CrossAxisAlignment, Flex, Label, LineBreaking, List, ProgressBar,
+    },
     Insets, Lens, LensExt, LocalizedString, Menu, MenuItem, Selector, Size, Widget, WidgetExt,
     WindowDesc,
 };
-use itertools::Itertools;
 
 use crate::data::WithCtx;
 use crate::ui::menu;
@@ -18,17 +24,22 @@ use crate::{
     cmd,
     data::{
         config::{SortCriteria, SortOrder},
-        AppState, Ctx, Library, Nav, Playlist, PlaylistAddTrack, PlaylistDetail, PlaylistLink,
-        PlaylistRemoveTrack, PlaylistTracks, Track,
+        utils::NaturalKey,
+        AppState, Config, Ctx, DiffTrack, Library, LoadProgress, Nav, NewPlaylist,
+        NewPlaylistTracks, Playlist, PlaylistAddTrack, PlaylistBackups, PlaylistDetail,
+        PlaylistLink, PlaylistRemoveTrack, PlaylistRestore, PlaylistTracks, QueuePlacement,
+        QueueRequest, QueueSource, Statistics, Track,
     },
     error::Error,
-    webapi::WebApi,
-    widget::{Async, MyWidgetExt, RemoteImage},
+    webapi,
+    widget::{plain_text, Async, Empty, Markup, Maybe, MyWidgetExt, RemoteImage},
 };
 
 use super::{playable, theme, track, utils};
 
 pub const LOAD_LIST: Selector = Selector::new("app.playlist.load-list");
+/// Like `LOAD_LIST`, but keeps showing the current list until the new one loads.
+pub const REFRESH_LIST: Selector = Selector::new("app.playlist.refresh-list");
 pub const LOAD_DETAIL: Selector<(PlaylistLink, AppState)> =
     Selector::new("app.playlist.load-detail");
 pub const ADD_TRACK: Selector<PlaylistAddTrack> = Selector::new("app.playlist.add-track");
@@ -43,6 +54,23 @@ pub const RENAME_PLAYLIST: Selector<PlaylistLink> = Selector::new("app.playlist.
 pub const RENAME_PLAYLIST_CONFIRM: Selector<PlaylistLink> =
     Selector::new("app.playlist.rename-confirm");
 
+pub const RESTORE_PLAYLIST: Selector<PlaylistLink> = Selector::new("app.playlist.restore");
+pub const RESTORE_PLAYLIST_CONFIRM: Selector<PlaylistRestore> =
+    Selector::new("app.playlist.restore-confirm");
+pub const APPLY_PLAYLIST_RESTORE: Selector<PlaylistRestore> =
+    Selector::new("app.playlist.apply-restore");
+
+pub const CREATE_PLAYLIST: Selector<NewPlaylist> = Selector::new("app.playlist.create");
+pub const CREATE_PLAYLIST_CONFIRM: Selector<NewPlaylist> =
+    Selector::new("app.playlist.create-confirm");
+
+/// Number of tracks taken from the listening history by "Save Recently Played
+/// as Playlist".
+const RECENTLY_PLAYED_PLAYLIST_LEN: usize = 50;
+
+/// Tracks listed in each part of the restore changes, the rest is counted.
+const MAX_DIFF_TRACKS: usize = 100;
+
 const SHOW_RENAME_PLAYLIST_CONFIRM: Selector<PlaylistLink> =
     Selector::new("app.playlist.show-rename");
 const SHOW_UNFOLLOW_PLAYLIST_CONFIRM: Selector<UnfollowPlaylist> =
@@ -79,14 +107,23 @@ pub fn list_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_LIST,
-        |_| WebApi::global().get_playlists(),
+        |_| webapi::global().get_playlists(),
         |_, data, d| data.with_library_mut(|l| l.playlists.defer(d)),
         |_, data, r| data.with_library_mut(|l| l.playlists.update(r)),
     )
+    .on_command_async(
+        REFRESH_LIST,
+        |_| webapi::global().get_playlists(),
+        |_, _, _| {},
+        |_, data, (_, r)| match r {
+            Ok(playlists) => data.with_library_mut(|l| l.playlists.resolve((), playlists)),
+            Err(err) => log::warn!("failed to refresh playlists: {}", err),
+        },
+    )
     .on_command_async(
         ADD_TRACK,
         |d| {
-            WebApi::global().add_track_to_playlist(
+            webapi::global().add_track_to_playlist(
                 &d.link.id,
                 &d.track_id
                     .0
@@ -107,7 +144,7 @@ pub fn list_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         UNFOLLOW_PLAYLIST,
-        |link| WebApi::global().unfollow_playlist(link.id.as_ref()),
+        |link| webapi::global().unfollow_playlist(link.id.as_ref()),
         |_, data: &mut AppState, d| data.with_library_mut(|l| l.remove_from_playlist(&d.id)),
         |_, data, (_, r)| {
             if let Err(err) = r {
@@ -119,7 +156,7 @@ pub fn list_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         FOLLOW_PLAYLIST,
-        |link| WebApi::global().follow_playlist(link.id.as_ref()),
+        |link| webapi::global().follow_playlist(link.id.as_ref()),
         |_, data: &mut AppState, d| data.with_library_mut(|l| l.add_playlist(d)),
         |_, data: &mut AppState, (_, r)| {
             if let Err(err) = r {
@@ -131,7 +168,7 @@ pub fn list_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         RENAME_PLAYLIST,
-        |link| WebApi::global().change_playlist_details(link.id.as_ref(), link.name.as_ref()),
+        |link| webapi::global().change_playlist_details(link.id.as_ref(), link.name.as_ref()),
         |_, data: &mut AppState, link| data.with_library_mut(|l| l.rename_playlist(link)),
         |_, data: &mut AppState, (_, r)| {
             if let Err(err) = r {
@@ -141,6 +178,76 @@ pub fn list_widget() -> impl Widget<AppState> {
             }
         },
     )
+    .on_command_async(
+        CREATE_PLAYLIST,
+        create_playlist,
+        |_, _, _| {},
+        |_, data: &mut AppState, (_, r)| match r {
+            Ok(playlist) => {
+                data.with_library_mut(|l| l.add_playlist(playlist));
+                data.info_alert("Playlist created.");
+            }
+            Err(err) => data.error_alert(err),
+        },
+    )
+    .on_command(cmd::SAVE_QUEUE_AS_PLAYLIST, |ctx, _, data| {
+        let uris = data
+            .playback
+            .in_playback_order()
+            .iter()
+            .filter_map(|entry| entry.item.track())
+            .filter(|track| !track.is_local)
+            .filter_map(|track| track.id.0.to_uri())
+            .map(Arc::from)
+            .collect();
+        let window = create_playlist_window(
+            "Save queue as playlist?",
+            "The tracks in the queue will be added to a new playlist",
+            NewPlaylistTracks::Uris(uris),
+        );
+        ctx.new_window(window);
+    })
+    .on_command(cmd::SAVE_HISTORY_AS_PLAYLIST, |ctx, _, _| {
+        let description = format!(
+            "The last {} tracks you played will be added to a new playlist",
+            RECENTLY_PLAYED_PLAYLIST_LEN
+        );
+        let window = create_playlist_window(
+            "Save recently played as playlist?",
+            &description,
+            NewPlaylistTracks::RecentlyPlayed(RECENTLY_PLAYED_PLAYLIST_LEN),
+        );
+        ctx.new_window(window);
+    })
+    .on_command_async(
+        RESTORE_PLAYLIST,
+        |link| load_playlist_restore(&link),
+        |_, _, _| {},
+        |ctx, data, (_, r)| match r {
+            Ok(Some(restore)) if restore.is_empty() => {
+                data.info_alert("The playlist is the same as in its backup.");
+            }
+            Ok(Some(restore)) => {
+                ctx.new_window(restore_playlist_window(restore));
+            }
+            Ok(None) => data.info_alert("None of the backups has this playlist."),
+            Err(err) => data.error_alert(err),
+        },
+    )
+    .on_command_async(
+        APPLY_PLAYLIST_RESTORE,
+        |restore| apply_playlist_restore(&restore),
+        |_, _, _| {},
+        |ctx, data, (restore, r)| {
+            if let Err(err) = r {
+                data.error_alert(err);
+            } else {
+                data.info_alert("Playlist restored.");
+            }
+            ctx.submit_command(REFRESH_LIST);
+            ctx.submit_command(LOAD_DETAIL.with((restore.link, data.clone())));
+        },
+    )
     .on_command(SHOW_UNFOLLOW_PLAYLIST_CONFIRM, |ctx, msg, _| {
         let window = unfollow_confirm_window(msg.clone());
         ctx.new_window(window);
@@ -152,7 +259,7 @@ pub fn list_widget() -> impl Widget<AppState> {
     .on_command_async(
         REMOVE_TRACK,
         |d| {
-            WebApi::global().remove_track_from_playlist(
+            webapi::global().remove_track_from_playlist(
                 &d.link.id,
                 &d.track_id
                     .0
@@ -284,16 +391,230 @@ fn rename_playlist_widget(link: PlaylistLink) -> impl Widget<AppState> {
     )
 }
 
-fn button_section(
+fn load_playlist_restore(link: &PlaylistLink) -> Result<Option<PlaylistRestore>, Error> {
+    let backup = match PlaylistBackups::open() {
+        Some(backups) => backups
+            .latest_of(&link.id)
+            .map_err(|err| Error::WebApiError(err.to_string()))?,
+        None => None,
+    };
+    let (taken_at, record) = match backup {
+        Some(backup) => backup,
+        None => return Ok(None),
+    };
+    let playlist = webapi::global().get_playlist(&link.id)?;
+    let tracks = webapi::global().get_playlist_tracks(&link.id)?;
+    Ok(Some(PlaylistRestore::new(
+        &playlist, &tracks, taken_at, &record,
+    )))
+}
+
+fn apply_playlist_restore(restore: &PlaylistRestore) -> Result<(), Error> {
+    // Don't overwrite changes made after the confirmed ones were computed.
+    let playlist = webapi::global().get_playlist(&restore.link.id)?;
+    if playlist.snapshot_id != restore.snapshot_id {
+        return Err(Error::WebApiError(
+            "The playlist has changed in the meantime, restore it again to review the changes"
+                .to_string(),
+        ));
+    }
+    // Long playlists take several requests to replace.  If one of them fails,
+    // put the current tracks back instead of leaving the playlist truncated.
+    let current: Vec<Arc<str>> = webapi::global()
+        .get_playlist_tracks(&restore.link.id)?
+        .iter()
+        .filter_map(|track| track.id.0.to_uri())
+        .map(Arc::from)
+        .collect();
+    let uris: Vec<_> = restore.uris.iter().cloned().collect();
+    webapi::global()
+        .replace_playlist_tracks(&restore.link.id, &uris)
+        .map_err(
+            |err| match webapi::global().replace_playlist_tracks(&restore.link.id, &current) {
+                Ok(()) => {
+                    Error::WebApiError(format!("{}, the playlist was put back as it was", err))
+                }
+                Err(rollback_err) => Error::WebApiError(format!(
+                    "{}, and putting the playlist back failed too ({}), it might be \
+                     incomplete",
+                    err, rollback_err
+                )),
+            },
+        )
+}
+
+fn restore_playlist_window(restore: PlaylistRestore) -> WindowDesc<AppState> {
+    let win = WindowDesc::new(restore_playlist_widget(restore))
+        .window_size((theme::grid(60.0), theme::grid(70.0)))
+        .title("Restore playlist")
+        .show_title(false)
+        .transparent_titlebar(true);
+    if cfg!(target_os = "macos") {
+        win.menu(menu::main_menu)
+    } else {
+        win
+    }
+}
+
+fn restore_playlist_widget(restore: PlaylistRestore) -> impl Widget<AppState> {
+    let mut description = format!(
+        "The backup is {} old.  Restoring it replaces the tracks of the playlist, with \
+         these changes:",
+        utils::as_human(restore.age)
+    );
+    if restore.skipped_local > 0 {
+        description.push_str(&format!(
+            "\n\n{} local files in the backup can't be added back.",
+            restore.skipped_local
+        ));
+    }
+    let information_section = information_section(
+        &format!("Restore {} from backup?", restore.link.name),
+        &description,
+    );
+
+    let changes = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(diff_section_widget("Added", &restore.added))
+        .with_child(diff_section_widget("Removed", &restore.removed))
+        .with_child(diff_section_widget("Moved", &restore.moved))
+        .padding((theme::grid(2.0), 0.0))
+        .scroll()
+        .vertical();
+
+    let button_section = button_section(
+        "Restore",
+        RESTORE_PLAYLIST_CONFIRM,
+        Box::new(move || restore.clone()),
+    );
+
+    ThemeScope::new(
+        Flex::column()
+            .with_child(information_section)
+            .with_flex_child(changes, 1.0)
+            .with_spacer(theme::grid(2.0))
+            .with_child(button_section)
+            .with_spacer(theme::grid(2.0))
+            .background(theme::BACKGROUND_DARK),
+    )
+}
+
+fn diff_section_widget(title: &str, tracks: &Vector<DiffTrack>) -> impl Widget<AppState> {
+    let mut section = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
+    if tracks.is_empty() {
+        return section;
+    }
+    section.add_child(
+        Label::new(format!("{} ({})", title, tracks.len())).with_font(theme::UI_FONT_MEDIUM),
+    );
+    section.add_spacer(theme::grid(0.5));
+    for track in tracks.iter().take(MAX_DIFF_TRACKS) {
+        section.add_child(
+            Label::new(format!("{} – {}", track.name, track.artist))
+                .with_line_break_mode(LineBreaking::Clip),
+        );
+    }
+    if tracks.len() > MAX_DIFF_TRACKS {
+        section.add_child(
+            Label::new(format!("…and {} more", tracks.len() - MAX_DIFF_TRACKS))
+                .with_text_color(theme::PLACEHOLDER_COLOR),
+        );
+    }
+    section.add_spacer(theme::grid(2.0));
+    section
+}
+
+fn create_playlist(new: NewPlaylist) -> Result<Playlist, Error> {
+    let uris = match new.tracks {
+        NewPlaylistTracks::Uris(uris) => uris.into_iter().collect(),
+        NewPlaylistTracks::RecentlyPlayed(count) => Statistics::open()
+            .ok_or_else(|| Error::WebApiError("Listening history is unavailable".to_string()))?
+            .recent_tracks(count)
+            .map_err(|err| Error::WebApiError(err.to_string()))?,
+    };
+    if uris.is_empty() {
+        return Err(Error::WebApiError(
+            "There are no tracks to save".to_string(),
+        ));
+    }
+    let mut playlist = webapi::global().create_playlist(&new.name)?;
+    webapi::global().add_tracks_to_playlist(&playlist.id, &uris)?;
+    playlist.track_count = uris.len();
+    Ok(playlist)
+}
+
+pub fn create_playlist_window(
+    title: &str,
+    description: &str,
+    tracks: NewPlaylistTracks,
+) -> WindowDesc<AppState> {
+    let win = WindowDesc::new(create_playlist_widget(title, description, tracks))
+        .window_size((theme::grid(45.0), theme::grid(30.0)))
+        .title("Create playlist")
+        .resizable(false)
+        .show_title(false)
+        .transparent_titlebar(true);
+    if cfg!(target_os = "macos") {
+        win.menu(menu::main_menu)
+    } else {
+        win
+    }
+}
+
+fn create_playlist_widget(
+    title: &str,
+    description: &str,
+    tracks: NewPlaylistTracks,
+) -> impl Widget<AppState> {
+    let text_input = TextInput {
+        input: Rc::new(RefCell::new(String::new())),
+    };
+
+    let information_section = information_section(title, description);
+    let input_section = LensWrap::new(
+        TextBox::new()
+            .with_placeholder("Playlist name")
+            .padding_horizontal(theme::grid(2.0))
+            .expand_width(),
+        text_input.clone(),
+    );
+    let button_section = button_section(
+        "Create",
+        CREATE_PLAYLIST_CONFIRM,
+        Box::new(move || {
+            let name = text_input.input.borrow().trim().to_string();
+            NewPlaylist {
+                name: if name.is_empty() {
+                    "New Playlist".into()
+                } else {
+                    name.into()
+                },
+                tracks: tracks.clone(),
+            }
+        }),
+    );
+
+    ThemeScope::new(
+        Flex::column()
+            .with_child(information_section)
+            .with_child(input_section)
+            .with_flex_spacer(2.0)
+            .with_child(button_section)
+            .with_flex_spacer(2.0)
+            .background(theme::BACKGROUND_DARK),
+    )
+}
+
+fn button_section<T: Any>(
     action_button_name: &str,
-    selector: Selector<PlaylistLink>,
-    link_extractor: Box<dyn Fn() -> PlaylistLink>,
+    selector: Selector<T>,
+    payload_extractor: Box<dyn Fn() -> T>,
 ) -> impl Widget<AppState> {
     let action_button = Button::new(action_button_name)
         .fix_height(theme::grid(5.0))
         .fix_width(theme::grid(9.0))
         .on_click(move |ctx, _, _| {
-            ctx.submit_command(selector.with(link_extractor()));
+            ctx.submit_command(selector.with(payload_extractor()));
             ctx.window().close();
         });
     let cancel_button = Button::new("Cancel")
@@ -334,8 +655,8 @@ pub fn playlist_widget() -> impl Widget<WithCtx<Playlist>> {
         .with_line_break_mode(LineBreaking::Clip)
         .lens(Ctx::data().then(Playlist::name));
 
-    let playlist_description = Label::raw()
-        .with_line_break_mode(LineBreaking::WordWrap)
+    let playlist_description = Markup::new()
+        .without_links()
         .with_text_color(theme::PLACEHOLDER_COLOR)
         .with_text_size(theme::TEXT_SIZE_SMALL)
         .lens(Ctx::data().then(Playlist::description));
@@ -375,21 +696,55 @@ fn rounded_cover_widget(size: f64) -> impl Widget<Playlist> {
 }
 
 pub fn detail_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(async_description_widget())
+        .with_child(async_tracks_widget())
+}
+
+fn async_description_widget() -> impl Widget<AppState> {
+    Async::new(
+        || Empty,
+        || {
+            Markup::new()
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .lens(Playlist::description)
+                .padding(theme::grid(1.0))
+        },
+        || Empty,
+    )
+    .lens(AppState::playlist_detail.then(PlaylistDetail::playlist))
+    .on_command_async(
+        LOAD_DETAIL,
+        |arg: (PlaylistLink, AppState)| webapi::global().get_playlist(&arg.0.id),
+        |_, data, d| data.playlist_detail.playlist.defer(d.0),
+        |_, data, (d, r)| data.playlist_detail.playlist.update((d.0, r)),
+    )
+}
+
+fn async_tracks_widget() -> impl Widget<AppState> {
     Async::new(
         utils::spinner_widget,
         || {
-            playable::list_widget_with_find(
-                playable::Display {
-                    track: track::Display {
-                        title: true,
-                        artist: true,
-                        album: true,
-                        cover: true,
-                        ..track::Display::empty()
+            Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(
+                    Maybe::or_empty(load_progress_widget)
+                        .lens(Ctx::data().then(PlaylistTracks::progress)),
+                )
+                .with_child(playable::list_widget_with_find(
+                    playable::Display {
+                        track: track::Display {
+                            title: true,
+                            artist: true,
+                            album: true,
+                            cover: true,
+                            ..track::Display::empty()
+                        },
                     },
-                },
-                cmd::FIND_IN_PLAYLIST,
-            )
+                    cmd::FIND_IN_PLAYLIST,
+                ))
         },
         utils::error_widget,
     )
@@ -400,52 +755,177 @@ pub fn detail_widget() -> impl Widget<AppState> {
         )
         .then(Ctx::in_promise()),
     )
-    .on_command_async(
-        LOAD_DETAIL,
-        |arg: (PlaylistLink, AppState)| {
-            let d = arg.0;
-            let data = arg.1;
-            sort_playlist(&data, WebApi::global().get_playlist_tracks(&d.id))
-        },
-        |_, data, d| data.playlist_detail.tracks.defer(d.0),
-        |_, data, (d, r)| {
-            let r = r.map(|tracks| PlaylistTracks {
-                id: d.0.id.clone(),
-                name: d.0.name.clone(),
-                tracks,
-            });
-            data.playlist_detail.tracks.update((d.0, r))
-        },
-    )
+    .controller(LoadTracks::new())
+}
+
+fn load_progress_widget() -> impl Widget<LoadProgress> {
+    Flex::row()
+        .with_child(
+            Label::dynamic(|progress: &LoadProgress, _| {
+                format!(
+                    "{} of {} tracks loaded",
+                    utils::as_count(progress.loaded),
+                    utils::as_count(progress.total)
+                )
+            })
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_text_size(theme::TEXT_SIZE_SMALL),
+        )
+        .with_default_spacer()
+        .with_flex_child(
+            ProgressBar::new().lens(Map::new(LoadProgress::fraction, |_, _| {
+                // Immutable.
+            })),
+            1.0,
+        )
+        .padding(theme::grid(1.0))
 }
 
-fn sort_playlist(
-    data: &AppState,
-    result: Result<Vector<Arc<Track>>, Error>,
-) -> Result<Vector<Arc<Track>>, Error> {
-    let sort_criteria = data.config.sort_criteria;
-    let sort_order = data.config.sort_order;
+/// Loads the playlist tracks page by page, so the first tracks can be played while
+/// the rest of a big playlist is still loading.
+struct LoadTracks {
+    thread: Option<JoinHandle<()>>,
+    // Identifies the latest request, pages of the earlier ones are dropped.
+    request: usize,
+    // Loaded tracks in the playlist order, before sorting.
+    loaded: Vector<Arc<Track>>,
+}
 
-    let playlist = result.unwrap_or_else(|_| Vector::new());
+impl LoadTracks {
+    const PAGE: Selector<(usize, Vector<Arc<Track>>, LoadProgress)> =
+        Selector::new("app.playlist.tracks-page");
+    const DONE: Selector<(usize, Result<(), Error>)> = Selector::new("app.playlist.tracks-done");
 
-    let mut sorted_playlist: Vector<Arc<Track>> = playlist
+    fn new() -> Self {
+        Self {
+            thread: None,
+            request: 0,
+            loaded: Vector::new(),
+        }
+    }
+
+    fn show_loaded(&self, data: &mut AppState, progress: Option<LoadProgress>) {
+        let link = match data.playlist_detail.tracks.deferred() {
+            Some(link) => link.to_owned(),
+            None => return,
+        };
+        let tracks = PlaylistTracks {
+            id: link.id.clone(),
+            name: link.name.clone(),
+            tracks: sort_playlist(&data.config, self.loaded.clone()),
+            progress,
+        };
+        data.playlist_detail.tracks.resolve(link, tracks);
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for LoadTracks {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(LOAD_DETAIL) => {
+                let link = cmd.get_unchecked(LOAD_DETAIL).0.to_owned();
+                self.request += 1;
+                self.loaded.clear();
+                data.playlist_detail.tracks.defer(link.clone());
+                let old_thread = self.thread.replace(thread::spawn({
+                    let request = self.request;
+                    let widget_id = ctx.widget_id();
+                    let event_sink = ctx.get_external_handle();
+                    move || {
+                        let result = webapi::global().get_playlist_tracks_paged(
+                            &link.id,
+                            &mut |tracks, progress| {
+                                event_sink
+                                    .submit_command(
+                                        Self::PAGE,
+                                        (request, tracks, progress),
+                                        widget_id,
+                                    )
+                                    .unwrap();
+                            },
+                        );
+                        event_sink
+                            .submit_command(Self::DONE, (request, result), widget_id)
+                            .unwrap();
+                    }
+                }));
+                if old_thread.is_some() {
+                    log::warn!("async action pending");
+                }
+            }
+            Event::Command(cmd) if cmd.is(Self::PAGE) => {
+                let (request, tracks, progress) = cmd.get_unchecked(Self::PAGE);
+                if *request == self.request {
+                    self.loaded.append(tracks.to_owned());
+                    self.show_loaded(data, Some(*progress));
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(Self::DONE) => {
+                let (request, result) = cmd.get_unchecked(Self::DONE);
+                if *request == self.request {
+                    self.thread.take();
+                    match result {
+                        Err(err) if !data.playlist_detail.tracks.is_resolved() => {
+                            if let Some(link) = data.playlist_detail.tracks.deferred() {
+                                let link = link.to_owned();
+                                data.playlist_detail.tracks.reject(link, err.to_owned());
+                            }
+                        }
+                        result => {
+                            if let Err(err) = result {
+                                log::error!("failed to load all playlist tracks: {}", err);
+                            }
+                            self.show_loaded(data, None);
+                        }
+                    }
+                }
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+}
+
+fn sort_playlist(config: &Config, playlist: Vector<Arc<Track>>) -> Vector<Arc<Track>> {
+    let sort_criteria = config.sort_criteria;
+    let sort_order = config.sort_order;
+
+    // The names are folded for sorting once per track, not on every comparison.
+    let mut keyed: Vec<_> = playlist
         .into_iter()
-        .sorted_by(|a, b| {
-            let mut method = match sort_criteria {
-                SortCriteria::Title => a.name.cmp(&b.name),
-                SortCriteria::Artist => a.artist_name().cmp(&b.artist_name()),
-                SortCriteria::Album => a.album_name().cmp(&b.album_name()),
-                SortCriteria::Duration => a.duration.cmp(&b.duration),
-                _ => Ordering::Equal,
-            };
-            method = if sort_order == SortOrder::Descending {
-                method.reverse()
-            } else {
-                method
+        .map(|track| {
+            let key = match sort_criteria {
+                SortCriteria::Title => Some(NaturalKey::new(&track.name)),
+                SortCriteria::Artist => Some(NaturalKey::new(&track.artist_name())),
+                SortCriteria::Album => Some(NaturalKey::new(&track.album_name())),
+                _ => None,
             };
-            method
+            (key, track)
         })
         .collect();
+    keyed.sort_by(|(a_key, a), (b_key, b)| {
+        let method = match sort_criteria {
+            SortCriteria::Duration => a.duration.cmp(&b.duration),
+            _ => a_key.cmp(b_key),
+        };
+        if sort_order == SortOrder::Descending {
+            method.reverse()
+        } else {
+            method
+        }
+    });
+    let mut sorted_playlist: Vector<Arc<Track>> =
+        keyed.into_iter().map(|(_, track)| track).collect();
 
     sorted_playlist =
         if sort_criteria == SortCriteria::DateAdded && sort_order == SortOrder::Descending {
@@ -454,7 +934,7 @@ fn sort_playlist(
             sorted_playlist
         };
 
-    Ok(sorted_playlist)
+    sorted_playlist
 }
 
 fn playlist_menu_ctx(playlist: &WithCtx<Playlist>) -> Menu<AppState> {
@@ -470,6 +950,64 @@ fn playlist_menu_ctx(playlist: &WithCtx<Playlist>) -> Menu<AppState> {
         .command(cmd::COPY.with(playlist.url())),
     );
 
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-copy-name").with_placeholder("Copy Name"))
+            .command(cmd::COPY.with(playlist.name.to_string())),
+    );
+
+    if !playlist.description.is_empty() {
+        menu = menu.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-copy-description")
+                    .with_placeholder("Copy Description"),
+            )
+            .command(cmd::COPY.with(plain_text(&playlist.description))),
+        );
+    }
+
+    menu = menu.separator();
+
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-play-next").with_placeholder("Play Next"))
+            .command(cmd::ADD_TO_QUEUE.with(QueueRequest {
+                source: QueueSource::Playlist(playlist.link()),
+                placement: QueuePlacement::Next,
+            })),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-add-to-queue").with_placeholder("Add to Queue"),
+        )
+        .command(cmd::ADD_TO_QUEUE.with(QueueRequest {
+            source: QueueSource::Playlist(playlist.link()),
+            placement: QueuePlacement::Last,
+        })),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-download").with_placeholder("Download"))
+            .command(cmd::DOWNLOAD.with(QueueSource::Playlist(playlist.link()))),
+    );
+
+    menu = menu.separator();
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-save-all-tracks")
+                .with_placeholder("Save All Tracks to Library"),
+        )
+        .command(cmd::SAVE_ALL_TRACKS.with(QueueSource::Playlist(playlist.link()))),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-unsave-all-tracks")
+                .with_placeholder("Remove All Tracks from Library"),
+        )
+        .command(cmd::UNSAVE_ALL_TRACKS.with(QueueSource::Playlist(playlist.link()))),
+    );
+
     if library.contains_playlist(playlist) {
         let created_by_user = library.is_created_by_user(playlist);
 
@@ -492,6 +1030,13 @@ fn playlist_menu_ctx(playlist: &WithCtx<Playlist>) -> Menu<AppState> {
                 )
                 .command(SHOW_RENAME_PLAYLIST_CONFIRM.with(playlist.link())),
             );
+            menu = menu.entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-restore-playlist")
+                        .with_placeholder("Restore from Backup…"),
+                )
+                .command(RESTORE_PLAYLIST.with(playlist.link())),
+            );
         } else {
             let unfollow_msg = UnfollowPlaylist {
                 link: playlist.link(),
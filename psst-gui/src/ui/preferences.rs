@@ -2,28 +2,39 @@ use std::thread::{self, JoinHandle};
 
 use druid::{
     commands,
+    im::Vector,
+    kurbo::Circle,
     text::ParseFormatter,
     widget::{
-        Button, Controller, CrossAxisAlignment, Flex, Label, LineBreaking, MainAxisAlignment,
-        RadioGroup, SizedBox, Slider, TextBox, ViewSwitcher,
+        Button, Controller, CrossAxisAlignment, Either, Flex, Label, LineBreaking, List,
+        MainAxisAlignment, Painter, RadioGroup, SizedBox, Slider, TextBox, ViewSwitcher,
     },
-    Color, Data, Env, Event, EventCtx, LensExt, LifeCycle, LifeCycleCtx, Selector, Widget,
-    WidgetExt,
+    Color, Data, Env, Event, EventCtx, FileDialogOptions, FileInfo, FileSpec, Lens, LensExt,
+    LifeCycle, LifeCycleCtx, RenderContext, Selector, Target, Widget, WidgetExt, WindowDesc,
+};
+use psst_core::{
+    connection::{Credentials, DEVICE_ID},
+    net,
+    player::listen_along,
 };
-use psst_core::connection::Credentials;
 
 use crate::{
     cmd,
     controller::InputController,
     data::{
-        AppState, AudioQuality, Authentication, Config, Preferences, PreferencesTab, Promise,
-        SliderScrollScale, Theme,
+        autostart, config, ActivityRecord, AppState, ArtistLink, AudioQuality, Authentication,
+        BlockedTrack, Blocklist, CacheCleanup, CacheUsage, ClickToPlay, Config, ExportContent,
+        ExportFormat, HistoryRules, Integration, IntegrationHealth, IntegrationStatus,
+        Integrations, Keymap, Library, ListenAlong, ListenAlongMode, MemoryUsage, ParentalLock,
+        Playback, PlaylistBackups, Preferences, PreferencesTab, ProfileData, Promise,
+        ReducedMotion, ScreenCorner, SliderScrollScale, StartupPlayback, Statistics, StatsExport,
+        Theme, UserProfile,
     },
-    webapi::WebApi,
-    widget::{icons, Async, Border, Checkbox, MyWidgetExt},
+    webapi,
+    widget::{icons, Async, Border, Checkbox, Empty, MyWidgetExt, RemoteImage, ThemeScope},
 };
 
-use super::{icons::SvgIcon, theme};
+use super::{icons::SvgIcon, menu, theme, utils};
 
 pub fn account_setup_widget() -> impl Widget<AppState> {
     Flex::column()
@@ -55,20 +66,25 @@ pub fn preferences_widget() -> impl Widget<AppState> {
         .must_fill_main_axis(true)
         .cross_axis_alignment(CrossAxisAlignment::Fill)
         .with_child(
-            tabs_widget()
+            Flex::column()
+                .with_child(tabs_widget())
+                .with_spacer(theme::grid(1.5))
+                .with_child(search_input_widget())
                 .padding(theme::grid(2.0))
                 .background(theme::BACKGROUND_LIGHT),
         )
         .with_child(
             ViewSwitcher::new(
-                |state: &AppState, _| state.preferences.active,
-                |active, _, _| match active {
-                    PreferencesTab::General => general_tab_widget().boxed(),
-                    PreferencesTab::Account => {
-                        account_tab_widget(AccountTab::InPreferences).boxed()
+                |state: &AppState, _| {
+                    if state.preferences.search.trim().is_empty() {
+                        PreferencesView::Tab(state.preferences.active)
+                    } else {
+                        PreferencesView::Search(matching_sections(&state.preferences.search))
                     }
-                    PreferencesTab::Cache => cache_tab_widget().boxed(),
-                    PreferencesTab::About => about_tab_widget().boxed(),
+                },
+                |view, _, _| match view {
+                    PreferencesView::Tab(tab) => tab_widget(*tab).boxed(),
+                    PreferencesView::Search(matches) => search_results_widget(*matches).boxed(),
                 },
             )
             .padding(theme::grid(4.0))
@@ -80,23 +96,440 @@ pub fn preferences_widget() -> impl Widget<AppState> {
                 data.config.save();
             }
 
+            if old_data.config.launch_on_login != data.config.launch_on_login {
+                if let Err(err) = autostart::set_launch_on_login(data.config.launch_on_login) {
+                    log::error!("failed to update the login item: {}", err);
+                }
+            }
+
+            if old_data.config.dns_over_https != data.config.dns_over_https {
+                net::set_dns_over_https(data.config.dns_over_https(), Config::proxy().as_deref());
+            }
+
+            if old_data.config.market != data.config.market {
+                webapi::global().set_market(data.config.market());
+            }
+
+            if old_data.config.memory_cache_limit_mb != data.config.memory_cache_limit_mb {
+                webapi::global().set_image_cache_limit(data.config.image_cache_limit());
+            }
+
+            if old_data.config.bandsintown_app_id != data.config.bandsintown_app_id {
+                webapi::configure_events(
+                    data.config.bandsintown_app_id(),
+                    Config::proxy().as_deref(),
+                );
+            }
+
             // Propagate some flags further to the state.
             if !old_data
                 .config
                 .show_track_cover
                 .same(&data.config.show_track_cover)
+                || !old_data
+                    .config
+                    .click_to_play
+                    .same(&data.config.click_to_play)
+                || old_data.config.filter_explicit != data.config.filter_explicit
+                || old_data.config.memory_cache_limit_mb != data.config.memory_cache_limit_mb
             {
                 ctx.submit_command(PROPAGATE_FLAGS);
             }
         })
         .on_command(PROPAGATE_FLAGS, |_, _, data| {
             data.common_ctx_mut().show_track_cover = data.config.show_track_cover;
+            data.common_ctx_mut().click_to_play = data.config.click_to_play;
+            data.common_ctx_mut().filter_explicit = data.config.filter_explicit;
+            let limit = data.config.track_metadata_limit();
+            data.common_ctx_mut().track_metadata.set_limit(limit);
         })
         .scroll()
         .vertical()
         .content_must_fill(true)
 }
 
+#[derive(Clone, Copy, PartialEq, Data)]
+enum PreferencesView {
+    Tab(PreferencesTab),
+    /// Sections matching the search, as a bit set of indices into `SECTIONS`.
+    Search(u64),
+}
+
+/// A titled group of settings.  The tabs and the search results are both built
+/// from `SECTIONS`, so a new setting is found by the search once its section
+/// lists the words people would look for.
+struct Section {
+    tab: PreferencesTab,
+    title: &'static str,
+    /// Lowercase words matched besides the title, mostly the labels of the
+    /// settings in the section.
+    keywords: &'static [&'static str],
+    widget: fn() -> Box<dyn Widget<AppState>>,
+}
+
+const SECTIONS: &[Section] = &[
+    Section {
+        tab: PreferencesTab::General,
+        title: "Theme",
+        keywords: &[
+            "light",
+            "dark",
+            "interface font",
+            "interface scale",
+            "zoom",
+            "album covers",
+            "canvas",
+            "low power mode",
+        ],
+        widget: || theme_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Audio quality",
+        keywords: &["bitrate", "kbit", "low", "normal", "high"],
+        widget: || audio_quality_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Playback",
+        keywords: &[
+            "single click",
+            "double-click",
+            "smart shuffle",
+            "resume",
+            "audio device",
+            "report played tracks",
+            "sync position",
+            "continue listening",
+            "private session",
+            "count a play",
+            "skips",
+        ],
+        widget: || playback_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Listen Along (experimental)",
+        keywords: &["share", "follow", "host", "announce the next track"],
+        widget: || listen_along_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Accessibility",
+        keywords: &[
+            "screen readers",
+            "window title",
+            "reduce motion",
+            "animations",
+        ],
+        widget: || accessibility_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Track Change Overlay",
+        keywords: &["osd", "notification", "corner", "seconds shown"],
+        widget: || overlay_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Keyboard Shortcuts",
+        keywords: &[
+            "keymap",
+            "hotkeys",
+            "focus search",
+            "find in page",
+            "home",
+            "saved tracks",
+            "saved albums",
+            "saved shows",
+            "downloads",
+            "go back",
+            "zoom",
+        ],
+        widget: || keymap_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Blocklist",
+        keywords: &["block", "unblock", "artists", "tracks", "radio", "shuffle"],
+        widget: || blocklist_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Slider Scrolling",
+        keywords: &["sensitivity", "mouse wheel", "volume", "seek"],
+        widget: || slider_scrolling_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Max Loaded Tracks (requires restart)",
+        keywords: &["limit", "pagination", "playlists"],
+        widget: || paginated_limit_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Startup",
+        keywords: &[
+            "restore the last track",
+            "start minimized",
+            "launch on login",
+            "daemon",
+            "keep playing",
+        ],
+        widget: || startup_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Local Files",
+        keywords: &["music directories", "folders"],
+        widget: || local_files_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Playlist Backup",
+        keywords: &["copies to keep", "every week", "restore"],
+        widget: || playlist_backup_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Downloads",
+        keywords: &["simultaneous downloads", "storage limit", "offline"],
+        widget: || downloads_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Network",
+        keywords: &[
+            "proxy",
+            "socks",
+            "dns-over-https",
+            "market",
+            "country",
+            "bandsintown",
+            "concerts",
+            "ca certificates",
+        ],
+        widget: || network_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Content",
+        keywords: &["explicit", "musicbrainz", "album details"],
+        widget: || content_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::General,
+        title: "Parental Lock",
+        keywords: &["pin", "lock", "unlock"],
+        widget: || parental_lock_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Account,
+        title: "Account",
+        keywords: &["profile", "log out", "premium"],
+        widget: || account_info_widget().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Account,
+        title: "Credentials",
+        keywords: &["username", "password", "log in"],
+        widget: || account_tab_widget(AccountTab::InPreferences).boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Cache,
+        title: "Location",
+        keywords: &["cache directory", "folder"],
+        widget: || cache_location_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Cache,
+        title: "Size",
+        keywords: &["cache size", "disk"],
+        widget: || cache_size_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Cache,
+        title: "Listening History",
+        keywords: &["export", "csv", "json", "play counts", "statistics"],
+        widget: || listening_history_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Cache,
+        title: "Cleanup",
+        keywords: &["cache size limit", "clean up now", "disk"],
+        widget: || cache_cleanup_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Cache,
+        title: "Memory",
+        keywords: &["memory limit", "ram", "images", "track metadata"],
+        widget: || memory_limit_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Diagnostics,
+        title: "Integrations",
+        keywords: &[
+            "media controls",
+            "media keys",
+            "mpris",
+            "listen along",
+            "port",
+            "reconnect",
+            "errors",
+        ],
+        widget: || integrations_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Diagnostics,
+        title: "Session Activity",
+        keywords: &["logins", "reconnections", "audio device changes", "log"],
+        widget: || session_activity_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::Diagnostics,
+        title: "Memory Usage",
+        keywords: &["memory", "ram", "images", "track metadata"],
+        widget: || memory_usage_section().boxed(),
+    },
+    Section {
+        tab: PreferencesTab::About,
+        title: "Build Info",
+        keywords: &["version", "commit hash", "build time", "source"],
+        widget: || build_info_section().boxed(),
+    },
+];
+
+impl Section {
+    fn words(query: &str) -> impl Iterator<Item = String> + '_ {
+        query.split_whitespace().map(str::to_lowercase)
+    }
+
+    fn title_matches(&self, query: &str) -> bool {
+        let title = self.title.to_lowercase();
+        Self::words(query).any(|word| title.contains(&word))
+    }
+
+    fn matching_keywords(&self, query: &str) -> Vec<&'static str> {
+        self.keywords
+            .iter()
+            .copied()
+            .filter(|keyword| Self::words(query).any(|word| keyword.contains(&word)))
+            .collect()
+    }
+
+    /// Every word of the query has to be found in the title or the keywords.
+    fn matches(&self, query: &str) -> bool {
+        let title = self.title.to_lowercase();
+        Self::words(query).all(|word| {
+            title.contains(&word) || self.keywords.iter().any(|keyword| keyword.contains(&word))
+        })
+    }
+}
+
+fn matching_sections(query: &str) -> u64 {
+    SECTIONS
+        .iter()
+        .enumerate()
+        .filter(|(_, section)| section.matches(query))
+        .fold(0, |matches, (index, _)| matches | 1 << index)
+}
+
+fn section_title_widget(title: &'static str) -> impl Widget<AppState> {
+    Label::new(title).with_font(theme::UI_FONT_MEDIUM)
+}
+
+fn tab_widget(tab: PreferencesTab) -> impl Widget<AppState> {
+    let mut col = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .must_fill_main_axis(true);
+    for (index, section) in SECTIONS.iter().filter(|s| s.tab == tab).enumerate() {
+        if index > 0 {
+            col.add_spacer(theme::grid(3.0));
+        }
+        col.add_child(section_title_widget(section.title));
+        col.add_spacer(theme::grid(2.0));
+        col.add_child((section.widget)());
+    }
+    col
+}
+
+fn search_input_widget() -> impl Widget<AppState> {
+    TextBox::new()
+        .with_placeholder("Search settings")
+        .controller(InputController::new())
+        .fix_width(theme::grid(30.0))
+        .lens(AppState::preferences.then(Preferences::search))
+}
+
+/// All matching sections of all tabs, with the matching titles highlighted
+/// and the matching keywords listed.
+fn search_results_widget(matches: u64) -> impl Widget<AppState> {
+    let mut col = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .must_fill_main_axis(true);
+    if matches == 0 {
+        col.add_child(
+            Label::new("No settings match the search.").with_text_color(theme::PLACEHOLDER_COLOR),
+        );
+    }
+    let sections = SECTIONS
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| matches & 1 << index != 0)
+        .map(|(_, section)| section);
+    for (index, section) in sections.enumerate() {
+        if index > 0 {
+            col.add_spacer(theme::grid(3.0));
+        }
+        let title = section_title_widget(section.title)
+            .padding((theme::grid(0.5), theme::grid(0.25)))
+            .background(Painter::new(move |ctx, state: &AppState, env| {
+                if section.title_matches(&state.preferences.search) {
+                    let bounds = ctx
+                        .size()
+                        .to_rect()
+                        .to_rounded_rect(env.get(theme::BUTTON_BORDER_RADIUS));
+                    ctx.fill(bounds, &env.get(theme::GREY_500));
+                }
+            }));
+        col.add_child(
+            Flex::row()
+                .with_child(title)
+                .with_default_spacer()
+                .with_child(
+                    Label::new(tab_name(section.tab))
+                        .with_text_color(theme::PLACEHOLDER_COLOR)
+                        .with_text_size(theme::TEXT_SIZE_SMALL),
+                ),
+        );
+        col.add_child(
+            Label::dynamic(move |state: &AppState, _| {
+                let keywords = section.matching_keywords(&state.preferences.search);
+                if keywords.is_empty() {
+                    String::new()
+                } else {
+                    format!("Matches “{}”", keywords.join("”, “"))
+                }
+            })
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_text_size(theme::TEXT_SIZE_SMALL),
+        );
+        col.add_spacer(theme::grid(1.5));
+        col.add_child((section.widget)());
+    }
+    col
+}
+
+fn tab_name(tab: PreferencesTab) -> &'static str {
+    match tab {
+        PreferencesTab::General => "General",
+        PreferencesTab::Account => "Account",
+        PreferencesTab::Cache => "Cache",
+        PreferencesTab::Diagnostics => "Diagnostics",
+        PreferencesTab::About => "About",
+    }
+}
+
 fn tabs_widget() -> impl Widget<AppState> {
     Flex::row()
         .must_fill_main_axis(true)
@@ -119,6 +552,12 @@ fn tabs_widget() -> impl Widget<AppState> {
             PreferencesTab::Cache,
         ))
         .with_default_spacer()
+        .with_child(tab_link_widget(
+            "Diagnostics",
+            &icons::ACTIVITY,
+            PreferencesTab::Diagnostics,
+        ))
+        .with_default_spacer()
         .with_child(tab_link_widget(
             "About",
             &icons::HEART,
@@ -126,6 +565,135 @@ fn tabs_widget() -> impl Widget<AppState> {
         ))
 }
 
+fn keymap_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(keymap_row("Focus search", Keymap::focus_search))
+        .with_child(keymap_row("Find in page", Keymap::find))
+        .with_child(keymap_row("Home", Keymap::home))
+        .with_child(keymap_row("Library", Keymap::saved_tracks))
+        .with_child(keymap_row("Playlists", Keymap::playlists))
+        .with_child(keymap_row("Search", Keymap::search))
+        .with_child(keymap_row("Queue", Keymap::queue))
+        .with_child(keymap_row("Saved albums", Keymap::saved_albums))
+        .with_child(keymap_row("Saved shows", Keymap::saved_shows))
+        .with_child(keymap_row("Downloads", Keymap::downloads))
+        .with_child(keymap_row("Go back", Keymap::navigate_back))
+        .with_child(keymap_row("Zoom in", Keymap::zoom_in))
+        .with_child(keymap_row("Zoom out", Keymap::zoom_out))
+        .with_child(keymap_row("Reset zoom", Keymap::zoom_reset))
+        .lens(AppState::config.then(Config::keymap))
+}
+
+fn keymap_row(title: &str, binding: impl Lens<Keymap, String> + 'static) -> impl Widget<Keymap> {
+    Flex::row()
+        .with_child(SizedBox::new(Label::new(title)).width(theme::grid(16.0)))
+        .with_child(TextBox::new().with_placeholder("None").lens(binding))
+        .padding((0.0, theme::grid(0.25)))
+}
+
+fn blocklist_widget() -> impl Widget<AppState> {
+    let artists = List::new(|| {
+        blocklist_row(
+            Label::raw().lens(ArtistLink::name),
+            |ctx, artist: &mut ArtistLink| {
+                ctx.submit_command(cmd::UNBLOCK_ARTIST.with(artist.clone()));
+            },
+        )
+    })
+    .lens(Blocklist::artists);
+    let tracks = List::new(|| {
+        blocklist_row(
+            Label::dynamic(|track: &BlockedTrack, _| format!("{} – {}", track.name, track.artist)),
+            |ctx, track: &mut BlockedTrack| {
+                ctx.submit_command(cmd::UNBLOCK_TRACK.with(track.id));
+            },
+        )
+    })
+    .lens(Blocklist::tracks);
+    Either::new(
+        |blocklist: &Blocklist, _| blocklist.artists.is_empty() && blocklist.tracks.is_empty(),
+        Label::new("Nothing is blocked.").with_text_size(theme::TEXT_SIZE_SMALL),
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(artists)
+            .with_child(tracks),
+    )
+    .lens(AppState::config.then(Config::blocklist))
+}
+
+fn blocklist_row<T: Data>(
+    title: impl Widget<T> + 'static,
+    unblock: impl Fn(&mut EventCtx, &mut T) + 'static,
+) -> impl Widget<T> {
+    Flex::row()
+        .with_child(SizedBox::new(title).width(theme::grid(30.0)))
+        .with_child(
+            Label::new("Unblock")
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .padding(theme::grid(0.5))
+                .link()
+                .rounded(theme::BUTTON_BORDER_RADIUS)
+                .on_left_click(move |ctx, _, data, _| unblock(ctx, data)),
+        )
+        .padding((0.0, theme::grid(0.25)))
+}
+
+fn history_rules_widget() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Count a play after"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(history_number(HistoryRules::count_after_secs))
+                .with_spacer(theme::grid(1.0))
+                .with_child(Label::new("seconds or"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(history_number(HistoryRules::count_after_percent))
+                .with_spacer(theme::grid(1.0))
+                .with_child(Label::new("% of the item")),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Don't record items shorter than"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(history_number(HistoryRules::ignore_shorter_than_secs))
+                .with_spacer(theme::grid(1.0))
+                .with_child(Label::new("seconds")),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Don't record podcast episodes").lens(HistoryRules::ignore_episodes),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(Label::new("Don't record these artists, one per line:"))
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            TextBox::multiline()
+                .with_placeholder("None")
+                .fix_size(theme::grid(30.0), theme::grid(8.0))
+                .lens(HistoryRules::ignored_artists),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new("Zero turns a limit off. Plays below the limits are kept as skips.")
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::PLACEHOLDER_COLOR),
+        )
+        .lens(AppState::config.then(Config::history_rules))
+}
+
+fn history_number(number: impl Lens<HistoryRules, usize> + 'static) -> impl Widget<HistoryRules> {
+    TextBox::new()
+        .with_formatter(ParseFormatter::with_format_fn(|usize: &usize| {
+            usize.to_string()
+        }))
+        .fix_width(theme::grid(6.0))
+        .lens(number)
+}
+
 fn tab_link_widget(
     text: &'static str,
     icon: &SvgIcon,
@@ -141,40 +709,77 @@ fn tab_link_widget(
         .active(move |state: &AppState, _| tab == state.preferences.active)
         .on_left_click(move |_, _, state: &mut AppState, _| {
             state.preferences.active = tab;
+            state.preferences.search.clear();
         })
         .env_scope(|env, _| {
             env.set(theme::LINK_ACTIVE_COLOR, env.get(theme::BACKGROUND_DARK));
         })
 }
 
-fn general_tab_widget() -> impl Widget<AppState> {
-    let mut col = Flex::column()
+fn theme_section() -> impl Widget<AppState> {
+    Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
-        .must_fill_main_axis(true);
-
-    // Theme
-    col = col
-        .with_child(Label::new("Theme").with_font(theme::UI_FONT_MEDIUM))
-        .with_spacer(theme::grid(2.0))
         .with_child(
             RadioGroup::column(vec![("Light", Theme::Light), ("Dark", Theme::Dark)])
                 .lens(AppState::config.then(Config::theme)),
-        );
-
-    col = col.with_spacer(theme::grid(1.5));
-
-    // Show track covers
-    col = col.with_child(
-        Checkbox::new("Show album covers for tracks")
-            .lens(AppState::config.then(Config::show_track_cover)),
-    );
-
-    col = col.with_spacer(theme::grid(3.0));
+        )
+        .with_spacer(theme::grid(1.5))
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Interface font"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    TextBox::new()
+                        .with_placeholder("System default")
+                        .controller(InputController::new())
+                        .fix_width(theme::grid(20.0))
+                        .lens(AppState::config.then(Config::ui_font)),
+                ),
+        )
+        .with_spacer(theme::grid(1.5))
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Interface scale"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    Slider::new()
+                        .with_range(config::MIN_UI_SCALE, config::MAX_UI_SCALE)
+                        .lens(AppState::config.then(Config::ui_scale)),
+                )
+                .with_spacer(theme::grid(0.5))
+                .with_child(Label::dynamic(|state: &AppState, _| {
+                    format!("{:.0}%", state.config.ui_scale * 100.0)
+                })),
+        )
+        .with_spacer(theme::grid(0.5))
+        .with_child(
+            Label::new(
+                "The zoom shortcuts under Keyboard Shortcuts scale on top of it, the zoom is \
+                 remembered for each monitor.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.5))
+        .with_child(
+            Checkbox::new("Show album covers for tracks")
+                .lens(AppState::config.then(Config::show_track_cover)),
+        )
+        .with_spacer(theme::grid(1.5))
+        .with_child(
+            Checkbox::new("Show still track canvases instead of the cover in the artwork window")
+                .lens(AppState::config.then(Config::show_canvas)),
+        )
+        .with_spacer(theme::grid(1.5))
+        .with_child(
+            Checkbox::new("Low power mode (fewer repaints, none in the background)")
+                .lens(AppState::config.then(Config::low_power_mode)),
+        )
+}
 
-    // Audio quality
-    col = col
-        .with_child(Label::new("Audio quality").with_font(theme::UI_FONT_MEDIUM))
-        .with_spacer(theme::grid(2.0))
+fn audio_quality_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_child(
             RadioGroup::column(vec![
                 ("Low (96kbit)", AudioQuality::Low),
@@ -182,53 +787,594 @@ fn general_tab_widget() -> impl Widget<AppState> {
                 ("High (320kbit)", AudioQuality::High),
             ])
             .lens(AppState::config.then(Config::audio_quality)),
-        );
+        )
+}
 
-    col = col.with_spacer(theme::grid(3.0));
+fn playback_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            RadioGroup::column(vec![
+                ("Play tracks with a single click", ClickToPlay::SingleClick),
+                (
+                    "Select tracks with a single click, play with a double-click",
+                    ClickToPlay::DoubleClick,
+                ),
+            ])
+            .lens(AppState::config.then(Config::click_to_play)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Smart shuffle (play often skipped tracks later, spread out artists)")
+                .lens(AppState::config.then(Config::smart_shuffle)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Resume when the disconnected audio device comes back")
+                .lens(AppState::config.then(Config::resume_on_reconnect)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Report played tracks to Spotify (recently played, recommendations)")
+                .lens(AppState::config.then(Config::report_playback)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Private session (don't record the listening history)")
+                .lens(AppState::playback.then(Playback::private_session)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(history_rules_widget())
+}
 
-    // Sliders
-    col = col
-        .with_child(Label::new("Slider Scrolling").with_font(theme::UI_FONT_MEDIUM))
-        .with_spacer(theme::grid(2.0))
+fn listen_along_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(
+                "Share your playback with others, or follow the playback of someone else. \
+                 Hosting is only reachable from this computer, unless you enter an address \
+                 to host on, like 0.0.0.0 for all networks. Nothing is shared during a \
+                 private session.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(listen_along_widget())
+        .with_spacer(theme::grid(1.0))
         .with_child(
             Flex::row()
+                .with_child(Label::new("Announce the next track"))
+                .with_spacer(theme::grid(1.0))
                 .with_child(
-                    SizedBox::new(Label::dynamic(|state: &AppState, _| {
-                        format!("{:.1}", state.config.slider_scroll_scale.scale)
-                    }))
-                    .width(20.0),
-                )
-                .with_spacer(theme::grid(0.5))
-                .with_child(
-                    Slider::new().with_range(0.0, 7.0).lens(
-                        AppState::config
-                            .then(Config::slider_scroll_scale)
-                            .then(SliderScrollScale::scale),
-                    ),
+                    TextBox::new()
+                        .with_formatter(ParseFormatter::with_format_fn(|usize: &usize| {
+                            usize.to_string()
+                        }))
+                        .fix_width(theme::grid(6.0))
+                        .lens(AppState::config.then(Config::up_next_lead_secs)),
                 )
-                .with_spacer(theme::grid(0.5))
-                .with_child(Label::new("Sensitivity")),
-        );
-
-    col = col.with_spacer(theme::grid(3.0));
+                .with_spacer(theme::grid(1.0))
+                .with_child(Label::new("seconds before the end (0 for never)")),
+        )
+}
 
-    col = col
+fn accessibility_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_child(
-            Label::new("Max Loaded Tracks (requires restart)").with_font(theme::UI_FONT_MEDIUM),
+            Checkbox::new("Announce track changes to screen readers (in the window title)")
+                .lens(AppState::config.then(Config::announce_track_changes)),
         )
         .with_spacer(theme::grid(2.0))
+        .with_child(Label::new("Reduce motion"))
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            RadioGroup::column(vec![
+                ("Follow the system setting", ReducedMotion::System),
+                ("Always, turn off animations", ReducedMotion::Always),
+                ("Never", ReducedMotion::Never),
+            ])
+            .lens(AppState::config.then(Config::reduced_motion)),
+        )
+}
+
+fn overlay_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Checkbox::new("Show the new track in a corner of the screen, above other windows")
+                .lens(AppState::config.then(Config::osd_enabled)),
+        )
+        .with_spacer(theme::grid(1.0))
         .with_child(
             Flex::row()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
                 .with_child(
-                    TextBox::new().with_formatter(ParseFormatter::with_format_fn(
-                        |usize: &usize| usize.to_string(),
-                    )),
+                    RadioGroup::column(vec![
+                        ("Top left", ScreenCorner::TopLeft),
+                        ("Top right", ScreenCorner::TopRight),
+                        ("Bottom left", ScreenCorner::BottomLeft),
+                        ("Bottom right", ScreenCorner::BottomRight),
+                    ])
+                    .lens(AppState::config.then(Config::osd_position)),
                 )
-                .padding((theme::grid(1.5), 0.0))
-                .lens(AppState::config.then(Config::paginated_limit)),
-        );
+                .with_spacer(theme::grid(2.0))
+                .with_child(Label::new("Seconds shown"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    TextBox::new()
+                        .with_formatter(ParseFormatter::with_format_fn(|usize: &usize| {
+                            usize.to_string()
+                        }))
+                        .lens(AppState::config.then(Config::osd_duration_secs)),
+                )
+                .disabled_if(|state: &AppState, _| !state.config.osd_enabled),
+        )
+}
 
-    col
+fn keymap_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(keymap_widget())
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new(
+                "Combine Ctrl, Alt, Shift, Meta and a key with \"+\", e.g. \"Ctrl+Shift+L\". \
+                 Cmd is Command on macOS and Ctrl elsewhere. Leave empty to turn a shortcut off.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+}
+
+fn blocklist_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(
+                "Blocked artists and tracks are skipped in the radio and when shuffling. Block \
+                 them from their context menus.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(blocklist_widget())
+}
+
+fn slider_scrolling_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_child(
+                    SizedBox::new(Label::dynamic(|state: &AppState, _| {
+                        format!("{:.1}", state.config.slider_scroll_scale.scale)
+                    }))
+                    .width(20.0),
+                )
+                .with_spacer(theme::grid(0.5))
+                .with_child(
+                    Slider::new().with_range(0.0, 7.0).lens(
+                        AppState::config
+                            .then(Config::slider_scroll_scale)
+                            .then(SliderScrollScale::scale),
+                    ),
+                )
+                .with_spacer(theme::grid(0.5))
+                .with_child(Label::new("Sensitivity")),
+        )
+}
+
+fn paginated_limit_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_child(
+                    TextBox::new().with_formatter(ParseFormatter::with_format_fn(
+                        |usize: &usize| usize.to_string(),
+                    )),
+                )
+                .padding((theme::grid(1.5), 0.0))
+                .lens(AppState::config.then(Config::paginated_limit)),
+        )
+}
+
+fn startup_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            RadioGroup::column(vec![
+                ("Start with nothing playing", StartupPlayback::Stopped),
+                ("Restore the last track, paused", StartupPlayback::Paused),
+                ("Resume playing the last track", StartupPlayback::Playing),
+            ])
+            .lens(AppState::config.then(Config::startup_playback)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Start minimized").lens(AppState::config.then(Config::start_minimized)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Launch on login").lens(AppState::config.then(Config::launch_on_login)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Attach to the playback daemon, to keep playing while Psst is closed")
+                .lens(AppState::config.then(Config::attach_to_daemon)),
+        )
+        .with_spacer(theme::grid(0.5))
+        .with_child(
+            Label::new("Start the daemon with `psst-cli daemon`. Takes effect on restart.")
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .with_text_size(theme::TEXT_SIZE_SMALL),
+        )
+}
+
+fn local_files_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::new("Music directories, one per line"))
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            TextBox::multiline()
+                .with_placeholder("/home/me/Music")
+                .fix_width(theme::grid(40.0))
+                .lens(AppState::config.then(Config::local_music_dirs)),
+        )
+}
+
+fn playlist_backup_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Checkbox::new("Save a copy of all playlists every week")
+                .lens(AppState::config.then(Config::playlist_backup)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Copies to keep"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    TextBox::new()
+                        .with_formatter(ParseFormatter::with_format_fn(|usize: &usize| {
+                            usize.to_string()
+                        }))
+                        .lens(AppState::config.then(Config::playlist_backup_keep)),
+                )
+                .disabled_if(|data: &AppState, _| !data.config.playlist_backup),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::dynamic(|_, _| {
+                PlaylistBackups::open()
+                    .map(|backups| format!("Saved to {}", backups.dir().to_string_lossy()))
+                    .unwrap_or_default()
+            })
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+}
+
+fn downloads_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Simultaneous downloads"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    TextBox::new()
+                        .with_formatter(ParseFormatter::with_format_fn(|usize: &usize| {
+                            usize.to_string()
+                        }))
+                        .lens(AppState::config.then(Config::download_concurrency)),
+                ),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Storage limit in GB"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    TextBox::new()
+                        .with_formatter(ParseFormatter::with_format_fn(|usize: &usize| {
+                            usize.to_string()
+                        }))
+                        .lens(AppState::config.then(Config::download_quota_gb)),
+                ),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new("Downloads going over the limit show a warning. Set to 0 for no limit.")
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_line_break_mode(LineBreaking::WordWrap),
+        )
+}
+
+fn network_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(match Config::proxy() {
+                Some(proxy) => format!("Proxy: {}", proxy),
+                None => "No proxy, set the SOCKS_PROXY environment variable to use one.".into(),
+            })
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(Label::new("DNS-over-HTTPS server"))
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("https://1.1.1.1/dns-query")
+                .controller(InputController::new())
+                .fix_width(theme::grid(40.0))
+                .lens(AppState::config.then(Config::dns_over_https)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new(
+                "Resolve the Spotify servers through this server instead of the system DNS. \
+                 Leave empty to use the system DNS.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(2.0))
+        .with_child(Label::new("Market"))
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("US")
+                .controller(InputController::new())
+                .fix_width(theme::grid(8.0))
+                .lens(AppState::config.then(Config::market)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new(
+                "Two-letter country code of the catalog to browse. Leave empty to use the \
+                 country of your account.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(2.0))
+        .with_child(Label::new("Bandsintown app ID"))
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            TextBox::new()
+                .controller(InputController::new())
+                .fix_width(theme::grid(40.0))
+                .lens(AppState::config.then(Config::bandsintown_app_id)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new(
+                "Shows the upcoming concerts on the artist pages. Leave empty to hide them.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(2.0))
+        .with_child(Label::new("Additional CA certificates (requires restart)"))
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            TextBox::new()
+                .with_placeholder("/path/to/bundle.pem")
+                .controller(InputController::new())
+                .fix_width(theme::grid(40.0))
+                .lens(AppState::config.then(Config::ca_certificates)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new(
+                "PEM file with certificate authorities to trust in addition to the built-in \
+                 ones, for networks that inspect encrypted traffic.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            Checkbox::new("Check the connection when logging in fails")
+                .lens(AppState::config.then(Config::connectivity_check)),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new(
+                "Asks connectivitycheck.gstatic.com whether a login portal or a missing \
+                 connection is in the way, to say so in the error.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+}
+
+fn content_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Checkbox::new("Skip explicit content")
+                .lens(AppState::config.then(Config::filter_explicit))
+                .disabled_if(|state: &AppState, _| state.is_locked()),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Checkbox::new("Look up album details on MusicBrainz")
+                .lens(AppState::config.then(Config::musicbrainz_lookup)),
+        )
+}
+
+fn parental_lock_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(
+                "Lock the content filter and the account settings behind a PIN stored on this \
+                 computer.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(parental_lock_widget())
+}
+
+fn parental_lock_widget() -> impl Widget<AppState> {
+    const MIN_PIN_LENGTH: usize = 4;
+
+    let pin_input = || {
+        TextBox::new()
+            .with_placeholder("PIN")
+            .controller(InputController::new())
+            .lens(AppState::preferences.then(Preferences::pin))
+    };
+
+    let pin_rejected = || {
+        Either::new(
+            |state: &AppState, _| state.preferences.pin_rejected,
+            Label::new("Wrong PIN.")
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::RED),
+            Empty,
+        )
+    };
+
+    ViewSwitcher::new(
+        |state: &AppState, _| (state.config.parental_lock.is_some(), state.is_locked()),
+        move |&(has_pin, is_locked), _, _| match (has_pin, is_locked) {
+            (false, _) => Flex::row()
+                .with_child(pin_input())
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    Button::new("Set PIN").on_click(|_, state: &mut AppState, _| {
+                        let pin = state.preferences.pin.trim();
+                        if pin.len() >= MIN_PIN_LENGTH {
+                            state.config.parental_lock = Some(ParentalLock::new(pin));
+                            state.preferences.unlocked = true;
+                        }
+                        state.preferences.pin.clear();
+                    }),
+                )
+                .boxed(),
+            (true, true) => Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(
+                    Flex::row()
+                        .with_child(pin_input())
+                        .with_spacer(theme::grid(1.0))
+                        .with_child(Button::new("Unlock").on_click(
+                            |_, state: &mut AppState, _| {
+                                let unlocked = state
+                                    .config
+                                    .parental_lock
+                                    .as_ref()
+                                    .map_or(true, |lock| lock.verify(&state.preferences.pin));
+                                state.preferences.unlocked = unlocked;
+                                state.preferences.pin_rejected = !unlocked;
+                                state.preferences.pin.clear();
+                            },
+                        )),
+                )
+                .with_spacer(theme::grid(1.0))
+                .with_child(pin_rejected())
+                .boxed(),
+            (true, false) => Flex::row()
+                .with_child(Button::new("Lock").on_click(|_, state: &mut AppState, _| {
+                    state.preferences.unlocked = false;
+                }))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    Button::new("Remove PIN").on_click(|_, state: &mut AppState, _| {
+                        state.config.parental_lock = None;
+                    }),
+                )
+                .boxed(),
+        },
+    )
+}
+
+fn listen_along_widget() -> impl Widget<AppState> {
+    ViewSwitcher::new(
+        |state: &AppState, _| state.playback.listen_along.mode,
+        |mode, _, _| match mode {
+            ListenAlongMode::Off => Flex::row()
+                .with_child(
+                    TextBox::new()
+                        .with_placeholder(format!("host:{}", listen_along::DEFAULT_PORT))
+                        .controller(InputController::new())
+                        .fix_width(theme::grid(30.0))
+                        .lens(
+                            AppState::playback
+                                .then(Playback::listen_along)
+                                .then(ListenAlong::address),
+                        ),
+                )
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    TextBox::new()
+                        .with_placeholder("Code to join with")
+                        .controller(InputController::new())
+                        .fix_width(theme::grid(20.0))
+                        .lens(
+                            AppState::playback
+                                .then(Playback::listen_along)
+                                .then(ListenAlong::token),
+                        ),
+                )
+                .with_spacer(theme::grid(1.0))
+                .with_child(Button::new("Host").on_click(|ctx, _, _| {
+                    ctx.submit_command(cmd::LISTEN_ALONG_HOST.to(Target::Global));
+                }))
+                .with_spacer(theme::grid(1.0))
+                .with_child(Button::new("Join").on_click(|ctx, _, _| {
+                    ctx.submit_command(cmd::LISTEN_ALONG_JOIN.to(Target::Global));
+                }))
+                .boxed(),
+            ListenAlongMode::Hosting => Flex::row()
+                .with_child(Label::dynamic(|state: &AppState, _| {
+                    format!(
+                        "Sharing your playback, followers join with the code {}.",
+                        state.playback.listen_along.token
+                    )
+                }))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    Button::new("Copy Code").on_click(|ctx, state: &mut AppState, _| {
+                        let token = state.playback.listen_along.token.clone();
+                        ctx.submit_command(cmd::COPY.with(token));
+                    }),
+                )
+                .with_spacer(theme::grid(1.0))
+                .with_child(Button::new("Stop").on_click(|ctx, _, _| {
+                    ctx.submit_command(cmd::LISTEN_ALONG_LEAVE.to(Target::Global));
+                }))
+                .boxed(),
+            ListenAlongMode::Following => Flex::row()
+                .with_child(Label::dynamic(|state: &AppState, _| {
+                    format!(
+                        "Listening along with {}.",
+                        state.playback.listen_along.address
+                    )
+                }))
+                .with_spacer(theme::grid(1.0))
+                .with_child(Button::new("Leave").on_click(|ctx, _, _| {
+                    ctx.submit_command(cmd::LISTEN_ALONG_LEAVE.to(Target::Global));
+                }))
+                .boxed(),
+        },
+    )
 }
 
 #[derive(Copy, Clone)]
@@ -243,12 +1389,6 @@ fn account_tab_widget(tab: AccountTab) -> impl Widget<AppState> {
         AccountTab::InPreferences => CrossAxisAlignment::Start,
     });
 
-    if matches!(tab, AccountTab::InPreferences) {
-        col = col
-            .with_child(Label::new("Credentials").with_font(theme::UI_FONT_MEDIUM))
-            .with_spacer(theme::grid(2.0));
-    }
-
     col = col
         .with_child(
             TextBox::new()
@@ -285,6 +1425,9 @@ fn account_tab_widget(tab: AccountTab) -> impl Widget<AppState> {
             })
             .on_left_click(|ctx, _, _, _| {
                 ctx.submit_command(Authenticate::REQUEST);
+            })
+            .disabled_if(move |state: &AppState, _| {
+                matches!(tab, AccountTab::InPreferences) && state.is_locked()
             }),
         )
         .with_spacer(theme::grid(1.0))
@@ -308,104 +1451,523 @@ fn account_tab_widget(tab: AccountTab) -> impl Widget<AppState> {
     col = col.with_spacer(theme::grid(3.0));
 
     if matches!(tab, AccountTab::InPreferences) {
-        col = col.with_child(Button::new("Log Out").on_left_click(|ctx, _, _, _| {
-            ctx.submit_command(cmd::LOG_OUT);
-        }))
+        col = col.with_child(
+            Button::new("Log Out")
+                .on_left_click(|ctx, _, _, _| {
+                    ctx.new_window(log_out_confirm_window());
+                })
+                .disabled_if(|state: &AppState, _| state.is_locked()),
+        )
     }
 
     col.controller(Authenticate::new(tab))
 }
 
+fn account_info_widget() -> impl Widget<AppState> {
+    const AVATAR_SIZE: f64 = 48.0;
+
+    let profile = Async::new(
+        || Label::new("Loading...").with_text_size(theme::TEXT_SIZE_SMALL),
+        || {
+            let avatar = RemoteImage::new(utils::placeholder_widget(), |user: &UserProfile, _| {
+                user.images.front().map(|image| image.url.clone())
+            })
+            .fix_size(AVATAR_SIZE, AVATAR_SIZE)
+            .clip(Circle::new(
+                (AVATAR_SIZE / 2.0, AVATAR_SIZE / 2.0),
+                AVATAR_SIZE / 2.0,
+            ));
+            let details = Flex::column()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(
+                    Label::raw()
+                        .with_font(theme::UI_FONT_MEDIUM)
+                        .lens(UserProfile::display_name),
+                )
+                .with_spacer(2.0)
+                .with_child(
+                    Label::raw()
+                        .with_text_color(theme::PLACEHOLDER_COLOR)
+                        .lens(UserProfile::email),
+                )
+                .with_spacer(2.0)
+                .with_child(
+                    Label::dynamic(|user: &UserProfile, _| user.subscription())
+                        .with_text_color(theme::PLACEHOLDER_COLOR),
+                );
+            Flex::row()
+                .with_child(avatar)
+                .with_spacer(theme::grid(2.0))
+                .with_child(details)
+        },
+        || {
+            Label::new("Failed to load the profile.")
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::RED)
+        },
+    )
+    .lens(AppState::library.then(Library::user_profile.in_arc()));
+
+    let session = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::new(format!("Device ID: {}", DEVICE_ID)))
+        .with_spacer(2.0)
+        .with_child(Label::dynamic(|state: &AppState, _| {
+            format!(
+                "Streaming: Ogg Vorbis, {} kbit/s",
+                state.config.playback().bitrate
+            )
+        }))
+        .with_spacer(2.0)
+        .with_child(Label::dynamic(|state: &AppState, _| {
+            if state.session.is_connected() {
+                "Connected".to_string()
+            } else {
+                "Disconnected".to_string()
+            }
+        }));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(profile)
+        .with_spacer(theme::grid(2.0))
+        .with_child(session)
+}
+
+fn log_out_confirm_window() -> WindowDesc<AppState> {
+    let win = WindowDesc::new(log_out_confirm_widget())
+        .window_size((theme::grid(55.0), theme::grid(40.0)))
+        .title("Log Out")
+        .resizable(false)
+        .show_title(false)
+        .transparent_titlebar(true);
+    if cfg!(target_os = "macos") {
+        win.menu(menu::main_menu)
+    } else {
+        win
+    }
+}
+
+fn log_out_confirm_widget() -> impl Widget<AppState> {
+    let mut col = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::new("Log out?").with_text_size(theme::TEXT_SIZE_LARGE))
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            Label::new(
+                "Psst keeps the following data of the account on this computer. Choose \
+                 Log Out & Delete to overwrite and remove all of it.",
+            )
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0));
+
+    for data in ProfileData::all() {
+        col = col
+            .with_spacer(theme::grid(1.0))
+            .with_child(Label::new(data.description))
+            .with_child(
+                Label::new(data.path.to_string_lossy().to_string())
+                    .with_text_color(theme::PLACEHOLDER_COLOR)
+                    .with_text_size(theme::TEXT_SIZE_SMALL)
+                    .with_line_break_mode(LineBreaking::WordWrap),
+            );
+    }
+
+    let buttons = Flex::row()
+        .with_child(Button::new("Log Out & Delete").on_click(|ctx, _, _| {
+            ctx.submit_command(cmd::LOG_OUT_AND_WIPE.to(Target::Global));
+            ctx.window().close();
+        }))
+        .with_spacer(theme::grid(1.0))
+        .with_child(Button::new("Log Out").on_click(|ctx, _, _| {
+            ctx.submit_command(cmd::LOG_OUT.to(Target::Global));
+            ctx.window().close();
+        }))
+        .with_spacer(theme::grid(1.0))
+        .with_child(Button::new("Cancel").on_click(|ctx, _, _| ctx.window().close()))
+        .align_right();
+
+    ThemeScope::new(
+        col.with_flex_spacer(1.0)
+            .with_child(buttons)
+            .padding(theme::grid(2.0))
+            .background(theme::BACKGROUND_DARK),
+    )
+}
+
 struct Authenticate {
     tab: AccountTab,
     thread: Option<JoinHandle<()>>,
 }
 
-impl Authenticate {
-    fn new(tab: AccountTab) -> Self {
-        Self { tab, thread: None }
+impl Authenticate {
+    fn new(tab: AccountTab) -> Self {
+        Self { tab, thread: None }
+    }
+}
+
+impl Authenticate {
+    const REQUEST: Selector = Selector::new("app.preferences.authenticate-request");
+    const RESPONSE: Selector<Result<Credentials, String>> =
+        Selector::new("app.preferences.authenticate-response");
+    const WIPE_DONE: Selector<Result<(), String>> = Selector::new("app.preferences.wipe-done");
+
+    fn log_out(ctx: &mut EventCtx, data: &mut AppState) {
+        data.config.clear_credentials();
+        data.config.save();
+        data.session.shutdown();
+        ctx.submit_command(cmd::CLOSE_ALL_WINDOWS);
+        ctx.submit_command(cmd::SHOW_ACCOUNT_SETUP);
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for Authenticate {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd)
+                if (cmd.is(Self::REQUEST)
+                    || cmd.is(cmd::LOG_OUT)
+                    || cmd.is(cmd::LOG_OUT_AND_WIPE))
+                    && matches!(self.tab, AccountTab::InPreferences)
+                    && data.is_locked() =>
+            {
+                data.preferences
+                    .auth
+                    .result
+                    .reject((), "Account settings are locked.".to_string());
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(Self::REQUEST) => {
+                // Signal that we're authenticating.
+                data.preferences.auth.result.defer_default();
+
+                // Authenticate in another thread.
+                let config = data.preferences.auth.session_config();
+                let check_connectivity = data.config.connectivity_check;
+                let widget_id = ctx.widget_id();
+                let event_sink = ctx.get_external_handle();
+                let thread = thread::spawn(move || {
+                    let response = Authentication::authenticate_and_get_credentials(
+                        config,
+                        check_connectivity,
+                    );
+                    event_sink
+                        .submit_command(Self::RESPONSE, response, widget_id)
+                        .unwrap();
+                });
+                self.thread.replace(thread);
+
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(Self::RESPONSE) => {
+                self.thread.take();
+
+                // Store the retrieved credentials into the config.
+                let result = cmd.get_unchecked(Self::RESPONSE);
+                let result = result.to_owned().map(|credentials| {
+                    // Load user's local tracks for the WebApi.
+                    webapi::global().load_local_tracks(&credentials.username);
+                    // Save the credentials into config.
+                    data.config.store_credentials(credentials);
+                    data.config.save();
+                });
+                let is_ok = result.is_ok();
+
+                // Signal the auth result to the preferences UI.
+                data.preferences.auth.result.resolve_or_reject((), result);
+
+                if is_ok {
+                    match &self.tab {
+                        AccountTab::FirstSetup => {
+                            // We let the `SessionController` pick up the credentials when the main
+                            // window gets created. Close the account setup window and open the main
+                            // one.
+                            ctx.submit_command(cmd::SHOW_MAIN);
+                            ctx.submit_command(commands::CLOSE_WINDOW);
+                        }
+                        AccountTab::InPreferences => {
+                            // Drop the old connection and connect again with the new credentials.
+                            ctx.submit_command(cmd::SESSION_CONNECT);
+                        }
+                    }
+                    // Only clear username if login is successful.
+                    data.preferences.auth.username.clear();
+                }
+                // Always clear password after login attempt.
+                data.preferences.auth.password.clear();
+
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::LOG_OUT) => {
+                Self::log_out(ctx, data);
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(cmd::LOG_OUT_AND_WIPE) => {
+                // Nothing should be writing into the data while it's being wiped.
+                data.session.shutdown();
+                data.session.activity().close();
+                ctx.submit_command(cmd::PLAY_STOP.to(Target::Global));
+                let (stopped, all_stopped) = crossbeam_channel::bounded::<()>(0);
+                ctx.submit_command(cmd::STOP_FOR_WIPE.with(stopped).to(Target::Global));
+
+                let widget_id = ctx.widget_id();
+                let event_sink = ctx.get_external_handle();
+                thread::spawn(move || {
+                    // Disconnected once all the senders are dropped, that is, once
+                    // everything has stopped.
+                    let _ = all_stopped.recv();
+                    let result = ProfileData::wipe_all().map_err(|err| err.to_string());
+                    event_sink
+                        .submit_command(Self::WIPE_DONE, result, widget_id)
+                        .unwrap();
+                });
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(Self::WIPE_DONE) => {
+                if let Err(err) = cmd.get_unchecked(Self::WIPE_DONE) {
+                    log::error!("failed to delete all data of the account: {}", err);
+                    data.error_alert(format!("Failed to delete all data of the account: {}", err));
+                }
+                if let Some(path) = Config::session_activity_path() {
+                    if let Err(err) = data.session.activity().persist_to(path) {
+                        log::error!("failed to open the session activity log: {}", err);
+                    }
+                }
+                // The config file is gone, this saves it again without the credentials.
+                Self::log_out(ctx, data);
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+}
+
+fn cache_location_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::dynamic(|_: &AppState, _| {
+                Config::cache_dir()
+                    .map(|path| path.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "None".to_string())
+            })
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+}
+
+fn cache_size_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(Label::dynamic(
+            |preferences: &Preferences, _| match preferences.cache_size {
+                Promise::Empty | Promise::Rejected { .. } => "Unknown".to_string(),
+                Promise::Deferred { .. } => "Computing".to_string(),
+                Promise::Resolved { val: 0, .. } => "Empty".to_string(),
+                Promise::Resolved { val, .. } => {
+                    format!("{:.2} MB", val as f64 / 1e6_f64)
+                }
+            },
+        ))
+        .controller(MeasureCacheSize::new())
+        .lens(AppState::preferences)
+}
+
+fn listening_history_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_child(
+                    TextBox::new()
+                        .with_placeholder("From (YYYY-MM-DD)")
+                        .controller(InputController::new())
+                        .lens(Preferences::export.then(StatsExport::from)),
+                )
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    TextBox::new()
+                        .with_placeholder("To (YYYY-MM-DD)")
+                        .controller(InputController::new())
+                        .lens(Preferences::export.then(StatsExport::to)),
+                ),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Flex::row()
+                .cross_axis_alignment(CrossAxisAlignment::Start)
+                .with_child(
+                    RadioGroup::column(vec![
+                        ("Full history", ExportContent::History),
+                        ("Play counts", ExportContent::PlayCounts),
+                    ])
+                    .lens(Preferences::export.then(StatsExport::content)),
+                )
+                .with_spacer(theme::grid(2.0))
+                .with_child(
+                    RadioGroup::column(vec![
+                        ("CSV", ExportFormat::Csv),
+                        ("JSON", ExportFormat::Json),
+                    ])
+                    .lens(Preferences::export.then(StatsExport::format)),
+                ),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(Button::new("Export…").on_left_click(|ctx, _, _, _| {
+            ctx.submit_command(ExportHistory::REQUEST);
+        }))
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Async::new(
+                || Label::new("Exporting...").with_text_size(theme::TEXT_SIZE_SMALL),
+                || {
+                    Label::dynamic(|rows: &usize, _| format!("Exported {} rows.", rows))
+                        .with_text_size(theme::TEXT_SIZE_SMALL)
+                },
+                || {
+                    Label::dynamic(|err: &String, _| err.to_owned())
+                        .with_text_size(theme::TEXT_SIZE_SMALL)
+                        .with_text_color(theme::RED)
+                },
+            )
+            .lens(Preferences::export.then(StatsExport::result)),
+        )
+        .controller(ExportHistory::new())
+        .lens(AppState::preferences)
+}
+
+fn cache_cleanup_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Cache size limit in GB"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    TextBox::new()
+                        .with_formatter(ParseFormatter::with_format_fn(|usize: &usize| {
+                            usize.to_string()
+                        }))
+                        .lens(AppState::config.then(Config::cache_limit_gb)),
+                ),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new(
+                "The cache is cleaned up every few hours in the background, the least \
+                 recently played audio goes first. Downloads are kept. Set to 0 for no limit.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Button::new("Clean Up Now")
+                .on_left_click(|ctx, _, _, _| {
+                    ctx.submit_command(cmd::COLLECT_CACHE_GARBAGE.to(Target::Global));
+                })
+                .disabled_if(|state: &AppState, _| state.preferences.cache_cleanup.running),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::dynamic(|cleanup: &CacheCleanup, _| cleanup.status.clone())
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_line_break_mode(LineBreaking::WordWrap)
+                .lens(AppState::preferences.then(Preferences::cache_cleanup)),
+        )
+}
+
+struct ExportHistory {
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ExportHistory {
+    fn new() -> Self {
+        Self { thread: None }
     }
 }
 
-impl Authenticate {
-    const REQUEST: Selector = Selector::new("app.preferences.authenticate-request");
-    const RESPONSE: Selector<Result<Credentials, String>> =
-        Selector::new("app.preferences.authenticate-response");
+impl ExportHistory {
+    const REQUEST: Selector = Selector::new("app.preferences.export-history-request");
+    const SAVE_AS: Selector<FileInfo> = Selector::new("app.preferences.export-history-save-as");
+    const RESULT: Selector<Result<usize, String>> =
+        Selector::new("app.preferences.export-history-result");
 }
 
-impl<W: Widget<AppState>> Controller<AppState, W> for Authenticate {
+impl<W: Widget<Preferences>> Controller<Preferences, W> for ExportHistory {
     fn event(
         &mut self,
         child: &mut W,
         ctx: &mut EventCtx,
         event: &Event,
-        data: &mut AppState,
+        data: &mut Preferences,
         env: &Env,
     ) {
-        match event {
+        match &event {
             Event::Command(cmd) if cmd.is(Self::REQUEST) => {
-                // Signal that we're authenticating.
-                data.preferences.auth.result.defer_default();
-
-                // Authenticate in another thread.
-                let config = data.preferences.auth.session_config();
-                let widget_id = ctx.widget_id();
-                let event_sink = ctx.get_external_handle();
-                let thread = thread::spawn(move || {
-                    let response = Authentication::authenticate_and_get_credentials(config);
-                    event_sink
-                        .submit_command(Self::RESPONSE, response, widget_id)
-                        .unwrap();
-                });
-                self.thread.replace(thread);
-
+                match data.export.request() {
+                    Ok(request) => {
+                        let file_type = match request.format {
+                            ExportFormat::Csv => FileSpec::new("CSV", &["csv"]),
+                            ExportFormat::Json => FileSpec::new("JSON", &["json"]),
+                        };
+                        let options = FileDialogOptions::new()
+                            .allowed_types(vec![file_type])
+                            .default_name(data.export.default_file_name())
+                            .accept_command(Self::SAVE_AS);
+                        ctx.submit_command(commands::SHOW_SAVE_PANEL.with(options));
+                    }
+                    Err(err) => {
+                        data.export.result.reject((), err);
+                    }
+                }
                 ctx.set_handled();
             }
-            Event::Command(cmd) if cmd.is(Self::RESPONSE) => {
-                self.thread.take();
-
-                // Store the retrieved credentials into the config.
-                let result = cmd.get_unchecked(Self::RESPONSE);
-                let result = result.to_owned().map(|credentials| {
-                    // Load user's local tracks for the WebApi.
-                    WebApi::global().load_local_tracks(&credentials.username);
-                    // Save the credentials into config.
-                    data.config.store_credentials(credentials);
-                    data.config.save();
-                });
-                let is_ok = result.is_ok();
-
-                // Signal the auth result to the preferences UI.
-                data.preferences.auth.result.resolve_or_reject((), result);
-
-                if is_ok {
-                    match &self.tab {
-                        AccountTab::FirstSetup => {
-                            // We let the `SessionController` pick up the credentials when the main
-                            // window gets created. Close the account setup window and open the main
-                            // one.
-                            ctx.submit_command(cmd::SHOW_MAIN);
-                            ctx.submit_command(commands::CLOSE_WINDOW);
-                        }
-                        AccountTab::InPreferences => {
-                            // Drop the old connection and connect again with the new credentials.
-                            ctx.submit_command(cmd::SESSION_CONNECT);
-                        }
+            Event::Command(cmd) if cmd.is(Self::SAVE_AS) => {
+                let path = cmd.get_unchecked(Self::SAVE_AS).path().to_owned();
+                // Re-validate, the form might have changed while the dialog was open.
+                let request = match data.export.request() {
+                    Ok(request) => request,
+                    Err(err) => {
+                        data.export.result.reject((), err);
+                        ctx.set_handled();
+                        return;
                     }
-                    // Only clear username if login is successful.
-                    data.preferences.auth.username.clear();
-                }
-                // Always clear password after login attempt.
-                data.preferences.auth.password.clear();
-
+                };
+                data.export.result.defer_default();
+                let handle = thread::spawn({
+                    let widget_id = ctx.widget_id();
+                    let event_sink = ctx.get_external_handle();
+                    move || {
+                        let result = Statistics::open()
+                            .ok_or_else(|| "Config directory is not available".to_string())
+                            .and_then(|statistics| {
+                                statistics
+                                    .export(&path, &request)
+                                    .map_err(|err| err.to_string())
+                            });
+                        event_sink
+                            .submit_command(Self::RESULT, result, widget_id)
+                            .unwrap();
+                    }
+                });
+                self.thread.replace(handle);
                 ctx.set_handled();
             }
-            Event::Command(cmd) if cmd.is(cmd::LOG_OUT) => {
-                data.config.clear_credentials();
-                data.config.save();
-                data.session.shutdown();
-                ctx.submit_command(cmd::CLOSE_ALL_WINDOWS);
-                ctx.submit_command(cmd::SHOW_ACCOUNT_SETUP);
+            Event::Command(cmd) if cmd.is(Self::RESULT) => {
+                let result = cmd.get_unchecked(Self::RESULT).to_owned();
+                data.export.result.resolve_or_reject((), result);
+                self.thread.take();
                 ctx.set_handled();
             }
             _ => {
@@ -415,41 +1977,6 @@ impl<W: Widget<AppState>> Controller<AppState, W> for Authenticate {
     }
 }
 
-fn cache_tab_widget() -> impl Widget<AppState> {
-    let mut col = Flex::column().cross_axis_alignment(CrossAxisAlignment::Start);
-
-    col = col
-        .with_child(Label::new("Location").with_font(theme::UI_FONT_MEDIUM))
-        .with_spacer(theme::grid(2.0))
-        .with_child(
-            Label::dynamic(|_, _| {
-                Config::cache_dir()
-                    .map(|path| path.to_string_lossy().to_string())
-                    .unwrap_or_else(|| "None".to_string())
-            })
-            .with_line_break_mode(LineBreaking::WordWrap),
-        );
-
-    col = col.with_spacer(theme::grid(3.0));
-
-    col = col
-        .with_child(Label::new("Size").with_font(theme::UI_FONT_MEDIUM))
-        .with_spacer(theme::grid(2.0))
-        .with_child(Label::dynamic(
-            |preferences: &Preferences, _| match preferences.cache_size {
-                Promise::Empty | Promise::Rejected { .. } => "Unknown".to_string(),
-                Promise::Deferred { .. } => "Computing".to_string(),
-                Promise::Resolved { val: 0, .. } => "Empty".to_string(),
-                Promise::Resolved { val, .. } => {
-                    format!("{:.2} MB", val as f64 / 1e6_f64)
-                }
-            },
-        ));
-
-    col.controller(MeasureCacheSize::new())
-        .lens(AppState::preferences)
-}
-
 struct MeasureCacheSize {
     thread: Option<JoinHandle<()>>,
 }
@@ -511,8 +2038,298 @@ impl<W: Widget<Preferences>> Controller<Preferences, W> for MeasureCacheSize {
     }
 }
 
-fn about_tab_widget() -> impl Widget<AppState> {
-    // Build Info
+fn integrations_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(
+                "Whether the parts of Psst talking to the system and to other devices are \
+                 working, with their latest errors.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            integration_widget().lens(AppState::integrations.then(Integrations::media_controls)),
+        )
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            integration_widget().lens(AppState::integrations.then(Integrations::listen_along)),
+        )
+}
+
+fn integration_widget() -> impl Widget<IntegrationHealth> {
+    let status = || {
+        Label::dynamic(|health: &IntegrationHealth, _| health.status_text())
+            .with_line_break_mode(LineBreaking::WordWrap)
+    };
+    let status = Either::new(
+        |health: &IntegrationHealth, _| matches!(health.status, IntegrationStatus::Failed),
+        status().with_text_color(theme::RED),
+        status(),
+    );
+
+    let reconnect = Button::new("Reconnect")
+        .on_left_click(|ctx, _, health: &mut IntegrationHealth, _| {
+            ctx.submit_command(
+                cmd::RECONNECT_INTEGRATION
+                    .with(health.integration)
+                    .to(Target::Global),
+            );
+        })
+        .disabled_if(|health: &IntegrationHealth, _| match health.status {
+            IntegrationStatus::Connecting(_) => true,
+            // Nothing to reconnect to if listening along was never started.
+            IntegrationStatus::Off => health.integration == Integration::ListenAlong,
+            _ => false,
+        });
+
+    let error = || {
+        Flex::row()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(
+                Label::dynamic(|record: &ActivityRecord, _| record.time.to_string())
+                    .with_text_size(theme::TEXT_SIZE_SMALL)
+                    .with_text_color(theme::PLACEHOLDER_COLOR),
+            )
+            .with_spacer(theme::grid(2.0))
+            .with_flex_child(
+                Label::dynamic(|record: &ActivityRecord, _| record.text.to_string())
+                    .with_text_size(theme::TEXT_SIZE_SMALL)
+                    .with_line_break_mode(LineBreaking::WordWrap),
+                1.0,
+            )
+            .padding((0.0, theme::grid(0.25)))
+    };
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_child(
+                    Label::dynamic(|health: &IntegrationHealth, _| {
+                        health.integration.name().to_string()
+                    })
+                    .with_font(theme::UI_FONT_MEDIUM),
+                )
+                .with_default_spacer()
+                .with_flex_child(status, 1.0)
+                .with_default_spacer()
+                .with_child(reconnect),
+        )
+        .with_child(List::new(error).lens(IntegrationHealth::errors))
+}
+
+fn session_activity_section() -> impl Widget<AppState> {
+    let entry = || {
+        Flex::row()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(
+                Label::dynamic(|record: &ActivityRecord, _| record.time.to_string())
+                    .with_text_size(theme::TEXT_SIZE_SMALL)
+                    .with_text_color(theme::PLACEHOLDER_COLOR),
+            )
+            .with_spacer(theme::grid(2.0))
+            .with_flex_child(
+                Label::dynamic(|record: &ActivityRecord, _| record.text.to_string())
+                    .with_text_size(theme::TEXT_SIZE_SMALL)
+                    .with_line_break_mode(LineBreaking::WordWrap),
+                1.0,
+            )
+            .padding((0.0, theme::grid(0.5)))
+    };
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(
+                "Logins, reconnections and audio device changes, newest first, with \
+                 the times in UTC. Useful when the playback stopped and it's not \
+                 clear why.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(Button::new("Refresh").on_left_click(|ctx, _, _, _| {
+            ctx.submit_command(LoadActivity::REFRESH);
+        }))
+        .with_spacer(theme::grid(2.0))
+        .with_child(
+            Either::new(
+                |activity: &Vector<ActivityRecord>, _| activity.is_empty(),
+                Label::new("Nothing recorded yet.").with_text_color(theme::PLACEHOLDER_COLOR),
+                List::new(entry),
+            )
+            .lens(AppState::preferences.then(Preferences::activity)),
+        )
+        .controller(LoadActivity)
+}
+
+struct LoadActivity;
+
+impl LoadActivity {
+    const REFRESH: Selector = Selector::new("app.preferences.refresh-activity");
+
+    fn load(data: &mut AppState) {
+        data.preferences.activity = data
+            .session
+            .activity()
+            .entries()
+            .iter()
+            .rev()
+            .map(ActivityRecord::from)
+            .collect();
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for LoadActivity {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(Self::REFRESH) => {
+                Self::load(data);
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            ctx.submit_command(Self::REFRESH.to(ctx.widget_id()));
+        }
+        child.lifecycle(ctx, event, data, env);
+    }
+}
+
+fn memory_limit_section() -> impl Widget<AppState> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Flex::row()
+                .with_child(Label::new("Memory limit in MB"))
+                .with_spacer(theme::grid(1.0))
+                .with_child(
+                    TextBox::new()
+                        .with_formatter(ParseFormatter::with_format_fn(|usize: &usize| {
+                            usize.to_string()
+                        }))
+                        .lens(AppState::config.then(Config::memory_cache_limit_mb)),
+                ),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(
+            Label::new(
+                "Covers and track details are kept in memory while browsing, the least \
+                 recently used go first once they take more than this. Set to 0 for no limit.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+}
+
+fn memory_usage_section() -> impl Widget<AppState> {
+    let usage = |name: &'static str, unit: &'static str| {
+        Label::dynamic(move |usage: &CacheUsage, _| {
+            format!(
+                "{}: {} {}, {:.1} MB",
+                name,
+                usage.entries,
+                unit,
+                usage.bytes as f64 / 1e6_f64
+            )
+        })
+    };
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(
+                "Memory taken by the in-memory caches, the track details are an estimate. \
+                 The limit is set in the Cache tab.",
+            )
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap),
+        )
+        .with_spacer(theme::grid(1.0))
+        .with_child(Button::new("Refresh").on_left_click(|ctx, _, _, _| {
+            ctx.submit_command(MeasureMemoryUsage::REFRESH);
+        }))
+        .with_spacer(theme::grid(2.0))
+        .with_child(usage("Images", "decoded").lens(MemoryUsage::images))
+        .with_spacer(theme::grid(0.5))
+        .with_child(usage("Track details", "tracks").lens(MemoryUsage::track_metadata))
+        .lens(AppState::preferences.then(Preferences::memory_usage))
+        .controller(MeasureMemoryUsage)
+}
+
+struct MeasureMemoryUsage;
+
+impl MeasureMemoryUsage {
+    const REFRESH: Selector = Selector::new("app.preferences.refresh-memory-usage");
+
+    fn measure(data: &mut AppState) {
+        data.preferences.memory_usage = MemoryUsage {
+            images: webapi::global().image_cache_usage(),
+            track_metadata: data.common_ctx.track_metadata.usage(),
+        };
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for MeasureMemoryUsage {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::Command(cmd) if cmd.is(Self::REFRESH) => {
+                Self::measure(data);
+                ctx.set_handled();
+            }
+            _ => {
+                child.event(ctx, event, data, env);
+            }
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            ctx.submit_command(Self::REFRESH.to(ctx.widget_id()));
+        }
+        child.lifecycle(ctx, event, data, env);
+    }
+}
+
+fn build_info_section() -> impl Widget<AppState> {
     let commit_hash = Flex::row()
         .with_child(Label::new("Commit Hash:   "))
         .with_child(Label::new(psst_core::GIT_VERSION).with_text_color(theme::DISABLED_TEXT_COLOR));
@@ -531,9 +2348,6 @@ fn about_tab_widget() -> impl Widget<AppState> {
 
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
-        .must_fill_main_axis(true)
-        .with_child(Label::new("Build Info").with_font(theme::UI_FONT_MEDIUM))
-        .with_spacer(theme::grid(2.0))
         .with_child(commit_hash)
         .with_child(build_time)
         .with_child(remote_url)
@@ -0,0 +1,125 @@
+use druid::{
+    lens::Map,
+    widget::{CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, Scroll, ViewSwitcher},
+    Data, Widget, WidgetExt,
+};
+
+use crate::{
+    cmd,
+    data::{AppState, NowPlaying, Playable, Playback, QueueEntry, SidePanel},
+    widget::{Empty, Maybe, MyWidgetExt},
+};
+
+use super::theme;
+
+/// Content of the panel docked beside the main view.
+pub fn side_panel_widget() -> impl Widget<AppState> {
+    ViewSwitcher::new(
+        |data: &AppState, _| data.config.side_panel,
+        |panel, _, _| match panel {
+            Some(SidePanel::Queue) => queue_panel_widget().boxed(),
+            None => Empty.boxed(),
+        },
+    )
+    .background(theme::BACKGROUND_LIGHT)
+}
+
+fn queue_panel_widget() -> impl Widget<AppState> {
+    let header = Flex::row()
+        .with_child(Label::new("Queue").with_font(theme::UI_FONT_MEDIUM))
+        .with_flex_spacer(1.0)
+        .with_child(
+            Label::new("Close")
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .padding(theme::grid(0.5))
+                .link()
+                .rounded(theme::BUTTON_BORDER_RADIUS)
+                .on_left_click(|ctx, _, _, _| {
+                    ctx.submit_command(cmd::TOGGLE_SIDE_PANEL.with(SidePanel::Queue));
+                }),
+        )
+        .padding(theme::grid(1.0));
+
+    let now_playing = Maybe::or_empty(|| {
+        section_widget(
+            "Now Playing",
+            entry_widget().lens(Map::new(
+                |now_playing: &NowPlaying| QueueEntry {
+                    item: now_playing.item.clone(),
+                    origin: now_playing.origin.clone(),
+                },
+                |_, _| {},
+            )),
+        )
+    })
+    .lens(Playback::now_playing);
+
+    let up_next =
+        Maybe::or_empty(|| section_widget("Up Next", entry_widget())).lens(Playback::up_next);
+
+    let user_queue = Either::new(
+        |playback: &Playback, _| playback.user_queue.is_empty(),
+        Empty,
+        section_widget(
+            "Next in Queue",
+            List::new(entry_widget).lens(Playback::user_queue),
+        ),
+    );
+
+    let upcoming = Either::new(
+        |playback: &Playback, _| playback.upcoming().is_empty(),
+        Empty,
+        section_widget(
+            "Next From the Context",
+            List::new(entry_widget).lens(Map::new(Playback::upcoming, |_, _| {})),
+        ),
+    );
+
+    let body = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(now_playing)
+        .with_child(up_next)
+        .with_child(user_queue)
+        .with_child(upcoming)
+        .padding(theme::grid(1.0))
+        .lens(AppState::playback);
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(header)
+        .with_flex_child(Scroll::new(body).vertical(), 1.0)
+}
+
+fn section_widget<T: Data>(title: &str, content: impl Widget<T> + 'static) -> impl Widget<T> {
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(title)
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .padding((0.0, theme::grid(0.5))),
+        )
+        .with_child(content)
+        .with_default_spacer()
+}
+
+fn entry_widget() -> impl Widget<QueueEntry> {
+    let title = Label::dynamic(|entry: &QueueEntry, _| entry.item.name().to_string())
+        .with_line_break_mode(LineBreaking::Clip);
+    let subtitle = Label::dynamic(|entry: &QueueEntry, _| match &entry.item {
+        Playable::Track(track) => track.artist_names(),
+        Playable::Episode(episode) => episode.show.name.to_string(),
+    })
+    .with_text_size(theme::TEXT_SIZE_SMALL)
+    .with_text_color(theme::PLACEHOLDER_COLOR)
+    .with_line_break_mode(LineBreaking::Clip);
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(title)
+        .with_spacer(2.0)
+        .with_child(subtitle)
+        .padding((0.0, theme::grid(0.5)))
+        .expand_width()
+}
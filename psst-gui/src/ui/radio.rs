@@ -0,0 +1,263 @@
+use std::{collections::HashSet, sync::Arc};
+
+use druid::{
+    im::Vector,
+    widget::{
+        Button, CrossAxisAlignment, Either, Flex, Label, LineBreaking, List, Slider, TextBox,
+    },
+    LensExt, Selector, Widget, WidgetExt,
+};
+use rand::seq::SliceRandom;
+
+use crate::{
+    cmd,
+    controller::InputController,
+    data::{
+        AppState, Ctx, NewPlaylistTracks, Playable, PlaybackOrigin, PlaybackPayload, RadioBuilder,
+        RadioSeed, Recommendations, WithCtx, MAX_RADIO_SEEDS, MAX_RADIO_WEIGHT, MIN_RADIO_WEIGHT,
+        RADIO_LENGTH,
+    },
+    error::Error,
+    webapi,
+    widget::{Async, MyWidgetExt},
+};
+
+use super::{playable, playlist, theme, track, utils};
+
+pub const GENERATE: Selector<Arc<Vector<RadioSeed>>> = Selector::new("app.radio.generate");
+
+pub fn builder_widget() -> impl Widget<AppState> {
+    let results = Async::new(utils::spinner_widget, results_widget, utils::error_widget)
+        .lens(
+            Ctx::make(
+                AppState::common_ctx,
+                AppState::radio.then(RadioBuilder::results),
+            )
+            .then(Ctx::in_promise()),
+        )
+        .on_command_async(
+            GENERATE,
+            generate,
+            |_, data, seeds| data.radio.results.defer(seeds),
+            |_, data, r| data.radio.results.update(r),
+        );
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new(format!(
+                "Mix a radio of {} tracks from up to {} tracks, artists or genres. Add tracks \
+                 and artists from their menus, and set how much of the radio each gets.",
+                RADIO_LENGTH, MAX_RADIO_SEEDS
+            ))
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .with_text_color(theme::PLACEHOLDER_COLOR)
+            .with_text_size(theme::TEXT_SIZE_SMALL),
+        )
+        .with_default_spacer()
+        .with_child(seeds_widget().lens(AppState::radio))
+        .with_default_spacer()
+        .with_child(genre_widget().lens(AppState::radio))
+        .with_default_spacer()
+        .with_child(actions_widget())
+        .with_default_spacer()
+        .with_child(results)
+}
+
+fn seeds_widget() -> impl Widget<RadioBuilder> {
+    Either::new(
+        |radio: &RadioBuilder, _| radio.seeds.is_empty(),
+        Label::new("No seeds yet.").with_text_size(theme::TEXT_SIZE_SMALL),
+        List::new(seed_widget).lens(RadioBuilder::seeds),
+    )
+}
+
+fn seed_widget() -> impl Widget<RadioSeed> {
+    let kind = Label::dynamic(|seed: &RadioSeed, _| seed.kind_name().to_string())
+        .with_text_color(theme::PLACEHOLDER_COLOR)
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .fix_width(theme::grid(7.0));
+
+    let name = Label::raw()
+        .with_line_break_mode(LineBreaking::Clip)
+        .lens(RadioSeed::name);
+
+    let weight = Slider::new()
+        .with_range(MIN_RADIO_WEIGHT, MAX_RADIO_WEIGHT)
+        .lens(RadioSeed::weight)
+        .fix_width(theme::grid(16.0));
+
+    let weight_label = Label::dynamic(|seed: &RadioSeed, _| format!("{:.0}", seed.weight))
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .fix_width(theme::grid(3.0));
+
+    let remove = Label::new("Remove")
+        .with_text_size(theme::TEXT_SIZE_SMALL)
+        .padding(theme::grid(0.5))
+        .link()
+        .rounded(theme::BUTTON_BORDER_RADIUS)
+        .on_left_click(|ctx, _, seed: &mut RadioSeed, _| {
+            ctx.submit_command(cmd::REMOVE_RADIO_SEED.with(seed.clone()));
+        });
+
+    Flex::row()
+        .with_child(kind)
+        .with_flex_child(name, 1.0)
+        .with_child(weight)
+        .with_child(weight_label)
+        .with_child(remove)
+        .padding((0.0, theme::grid(0.25)))
+}
+
+fn genre_widget() -> impl Widget<RadioBuilder> {
+    let input = TextBox::new()
+        .with_placeholder("Add a genre, e.g. indie-pop")
+        .controller(InputController::new().on_submit(|ctx, genre, _| {
+            if let Some(seed) = RadioSeed::genre(genre) {
+                ctx.submit_command(cmd::ADD_RADIO_SEED.with(seed));
+                genre.clear();
+            }
+        }))
+        .fix_width(theme::grid(30.0))
+        .lens(RadioBuilder::genre);
+
+    Flex::row()
+        .with_child(input)
+        .with_default_spacer()
+        .with_child(
+            Button::new("Add Genre")
+                .on_click(|ctx, radio: &mut RadioBuilder, _| {
+                    if let Some(seed) = RadioSeed::genre(&radio.genre) {
+                        ctx.submit_command(cmd::ADD_RADIO_SEED.with(seed));
+                        radio.genre.clear();
+                    }
+                })
+                .disabled_if(|radio: &RadioBuilder, _| radio.is_full()),
+        )
+}
+
+fn actions_widget() -> impl Widget<AppState> {
+    let generate = Button::dynamic(|radio: &RadioBuilder, _| {
+        if radio.results.deferred().is_some() {
+            "Regenerate".to_string()
+        } else {
+            "Generate".to_string()
+        }
+    })
+    .on_click(|ctx, radio: &mut RadioBuilder, _| {
+        ctx.submit_command(GENERATE.with(Arc::new(radio.seeds.clone())));
+    })
+    .disabled_if(|radio: &RadioBuilder, _| radio.seeds.is_empty())
+    .lens(AppState::radio);
+
+    let play = Button::new("Play")
+        .on_click(|ctx, radio: &mut RadioBuilder, _| {
+            if let Some(recommendations) = radio.results.resolved() {
+                ctx.submit_command(
+                    cmd::PLAY_TRACKS.with(PlaybackPayload {
+                        origin: PlaybackOrigin::Recommendations(recommendations.request.clone()),
+                        items: recommendations
+                            .tracks
+                            .iter()
+                            .map(|track| Playable::Track(track.clone()))
+                            .collect(),
+                        position: 0,
+                    }),
+                );
+            }
+        })
+        .disabled_if(|radio: &RadioBuilder, _| !radio.results.is_resolved())
+        .lens(AppState::radio);
+
+    let save = Button::new("Save as Playlist")
+        .on_click(|ctx, radio: &mut RadioBuilder, _| {
+            if let Some(recommendations) = radio.results.resolved() {
+                let uris = recommendations
+                    .tracks
+                    .iter()
+                    .filter_map(|track| track.id.0.to_uri())
+                    .map(Arc::from)
+                    .collect();
+                ctx.new_window(playlist::create_playlist_window(
+                    "Save radio as playlist?",
+                    "The tracks of the radio will be added to a new playlist",
+                    NewPlaylistTracks::Uris(uris),
+                ));
+            }
+        })
+        .disabled_if(|radio: &RadioBuilder, _| !radio.results.is_resolved())
+        .lens(AppState::radio);
+
+    Flex::row()
+        .with_child(generate)
+        .with_default_spacer()
+        .with_child(play)
+        .with_default_spacer()
+        .with_child(save)
+}
+
+fn results_widget() -> impl Widget<WithCtx<Recommendations>> {
+    Either::new(
+        |recommendations: &WithCtx<Recommendations>, _| recommendations.data.tracks.is_empty(),
+        Label::new("No tracks came up for these seeds.").with_text_size(theme::TEXT_SIZE_SMALL),
+        playable::list_widget(playable::Display {
+            track: track::Display {
+                title: true,
+                artist: true,
+                album: true,
+                ..track::Display::empty()
+            },
+        }),
+    )
+}
+
+/// Load recommendations for every seed on its own and deal the tracks out in
+/// proportion to the weights, in the smooth weighted round-robin order so the
+/// seeds stay interleaved.  The endpoint returns a different selection every
+/// time, and we shuffle on top of that, so regenerating gives a new mix.  The
+/// radios started from the menus also get their tracks, and more of them as they
+/// play, from here.
+pub fn generate(seeds: Arc<Vector<RadioSeed>>) -> Result<Recommendations, Error> {
+    let mut rng = rand::thread_rng();
+    let mut pools = Vec::with_capacity(seeds.len());
+    let mut recommendation_seeds = Vector::new();
+    for seed in seeds.iter() {
+        let recommendations = webapi::global().get_recommendations(Arc::new(seed.request()))?;
+        let mut tracks: Vec<_> = recommendations.tracks.into_iter().collect();
+        tracks.shuffle(&mut rng);
+        pools.push(tracks);
+        recommendation_seeds.append(recommendations.seeds);
+    }
+
+    let mut credits = vec![0.0; pools.len()];
+    let mut seen = HashSet::new();
+    let mut tracks = Vector::new();
+    while tracks.len() < RADIO_LENGTH {
+        let live: Vec<_> = (0..pools.len()).filter(|&i| !pools[i].is_empty()).collect();
+        if live.is_empty() {
+            break;
+        }
+        let total: f64 = live.iter().map(|&i| seeds[i].weight).sum();
+        for &i in &live {
+            credits[i] += seeds[i].weight;
+        }
+        let pick = live
+            .iter()
+            .copied()
+            .max_by(|&a, &b| credits[a].total_cmp(&credits[b]))
+            .unwrap();
+        credits[pick] -= total;
+        while let Some(track) = pools[pick].pop() {
+            if seen.insert(track.id) {
+                tracks.push_back(track);
+                break;
+            }
+        }
+    }
+
+    Ok(Recommendations {
+        request: Arc::new(RadioBuilder::request(&seeds)),
+        seeds: recommendation_seeds,
+        tracks,
+    })
+}
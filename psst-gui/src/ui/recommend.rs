@@ -1,7 +1,7 @@
 use std::{sync::Arc, time::Duration};
 
 use druid::{
-    widget::{CrossAxisAlignment, Flex, Slider},
+    widget::{CrossAxisAlignment, Either, Flex, Label, Slider},
     FontDescriptor, FontFamily, LensExt, Selector, Widget, WidgetExt,
 };
 
@@ -10,8 +10,8 @@ use crate::{
         AppState, Ctx, Recommend, Recommendations, RecommendationsKnobs, RecommendationsParams,
         RecommendationsRequest, Toggled, WithCtx,
     },
-    webapi::WebApi,
-    widget::{Async, Checkbox, MyWidgetExt},
+    webapi,
+    widget::{Async, Checkbox, Empty, MyWidgetExt},
 };
 
 use super::{playable, theme, track, utils};
@@ -38,7 +38,7 @@ pub fn results_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_RESULTS,
-        |d| WebApi::global().get_recommendations(d),
+        |d| webapi::global().get_recommendations(d),
         |_, data, d| data.recommend.results.defer(d),
         |_, data, r| data.recommend.results.update(r),
     )
@@ -55,17 +55,41 @@ pub fn results_widget() -> impl Widget<AppState> {
         .on_debounce(KNOBS_DEBOUNCE_DELAY, |ctx, knobs, _| {
             ctx.submit_command(UPDATE_PARAMS.with(knobs.as_params()));
         })
-        .lens(AppState::recommend.then(Recommend::knobs));
+        .lens(Recommend::knobs);
+
+    let tuning = Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::dynamic(|tuning: &bool, _| {
+                if *tuning {
+                    "Hide tuning".to_string()
+                } else {
+                    "Tune this radio".to_string()
+                }
+            })
+            .with_text_size(theme::TEXT_SIZE_SMALL)
+            .padding(theme::grid(0.5))
+            .link()
+            .rounded(theme::BUTTON_BORDER_RADIUS)
+            .on_left_click(|_, _, tuning, _| *tuning = !*tuning)
+            .lens(Recommend::tuning),
+        )
+        .with_child(Either::new(
+            |recommend: &Recommend, _| recommend.tuning,
+            param_knobs,
+            Empty,
+        ))
+        .lens(AppState::recommend);
 
     Flex::column()
         .cross_axis_alignment(CrossAxisAlignment::Start)
-        .with_child(param_knobs)
+        .with_child(tuning)
         .with_default_spacer()
         .with_child(track_results)
 }
 
 fn params_widget() -> impl Widget<Arc<RecommendationsKnobs>> {
-    let row = |label| {
+    let row = |label, min, max| {
         Flex::column()
             .cross_axis_alignment(CrossAxisAlignment::Start)
             .with_child(
@@ -83,6 +107,7 @@ fn params_widget() -> impl Widget<Arc<RecommendationsKnobs>> {
             .with_spacer(theme::grid(0.4))
             .with_child(
                 Slider::new()
+                    .with_range(min, max)
                     .lens(Toggled::value)
                     .disabled_if(|toggle, _| !toggle.enabled)
                     .padding_left(theme::grid(2.5))
@@ -96,33 +121,69 @@ fn params_widget() -> impl Widget<Arc<RecommendationsKnobs>> {
             )
             .padding(theme::grid(0.5))
     };
-    Flex::row()
+    let knobs = Flex::row()
         .cross_axis_alignment(CrossAxisAlignment::Start)
         .with_flex_child(
             Flex::column()
                 .cross_axis_alignment(CrossAxisAlignment::Start)
-                .with_child(row("Acousticness").lens(RecommendationsKnobs::acousticness.in_arc()))
-                .with_child(row("Danceability").lens(RecommendationsKnobs::danceability.in_arc()))
-                .with_child(row("Energy").lens(RecommendationsKnobs::energy.in_arc())),
+                .with_child(
+                    row("Acousticness", 0.0, 1.0).lens(RecommendationsKnobs::acousticness.in_arc()),
+                )
+                .with_child(
+                    row("Danceability", 0.0, 1.0).lens(RecommendationsKnobs::danceability.in_arc()),
+                )
+                .with_child(row("Energy", 0.0, 1.0).lens(RecommendationsKnobs::energy.in_arc())),
             1.0,
         )
         .with_flex_child(
             Flex::column()
                 .cross_axis_alignment(CrossAxisAlignment::Start)
                 .with_child(
-                    row("Instrumentalness").lens(RecommendationsKnobs::instrumentalness.in_arc()),
+                    row("Instrumentalness", 0.0, 1.0)
+                        .lens(RecommendationsKnobs::instrumentalness.in_arc()),
                 )
-                .with_child(row("Liveness").lens(RecommendationsKnobs::liveness.in_arc()))
-                .with_child(row("Loudness").lens(RecommendationsKnobs::loudness.in_arc())),
+                .with_child(row("Liveness", 0.0, 1.0).lens(RecommendationsKnobs::liveness.in_arc()))
+                .with_child(
+                    row("Loudness", 0.0, 1.0).lens(RecommendationsKnobs::loudness.in_arc()),
+                ),
             1.0,
         )
         .with_flex_child(
             Flex::column()
                 .cross_axis_alignment(CrossAxisAlignment::Start)
-                .with_child(row("Speechiness").lens(RecommendationsKnobs::speechiness.in_arc()))
-                .with_child(row("Valence").lens(RecommendationsKnobs::valence.in_arc())),
+                .with_child(
+                    row("Speechiness", 0.0, 1.0).lens(RecommendationsKnobs::speechiness.in_arc()),
+                )
+                .with_child(row("Valence", 0.0, 1.0).lens(RecommendationsKnobs::valence.in_arc())),
+            1.0,
+        );
+
+    let radio = Flex::row()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_flex_child(
+            row("Popularity", 0.0, 100.0).lens(RecommendationsKnobs::popularity.in_arc()),
+            1.0,
+        )
+        .with_flex_child(
+            row("Min. tempo (BPM)", 40.0, 220.0)
+                .lens(RecommendationsKnobs::lens_min_tempo().in_arc()),
             1.0,
         )
+        .with_flex_child(
+            row("Max. tempo (BPM)", 40.0, 220.0)
+                .lens(RecommendationsKnobs::lens_max_tempo().in_arc()),
+            1.0,
+        );
+
+    let explicit = Checkbox::new("Exclude explicit tracks")
+        .lens(RecommendationsKnobs::exclude_explicit.in_arc())
+        .padding(theme::grid(0.5));
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(radio)
+        .with_child(knobs)
+        .with_child(explicit)
 }
 
 fn track_results_widget() -> impl Widget<WithCtx<Recommendations>> {
@@ -5,16 +5,14 @@ use druid::{
     widget::{CrossAxisAlignment, Either, Flex, Label, LabelText, List, TextBox},
     Data, LensExt, Selector, Widget, WidgetExt,
 };
+use psst_core::uri::SpotifyUri;
 
 use crate::{
     cmd,
     controller::InputController,
-    data::{
-        Album, AppState, Artist, Ctx, Nav, Search, SearchResults, SearchTopic, Show, SpotifyUrl,
-        WithCtx,
-    },
+    data::{Album, AppState, Artist, Ctx, Nav, Search, SearchResults, SearchTopic, Show, WithCtx},
     ui::show,
-    webapi::WebApi,
+    webapi,
     widget::{Async, Empty, MyWidgetExt},
 };
 
@@ -23,7 +21,7 @@ use super::{album, artist, playable, playlist, theme, track, utils};
 const NUMBER_OF_RESULTS_PER_TOPIC: usize = 5;
 
 pub const LOAD_RESULTS: Selector<Arc<str>> = Selector::new("app.search.load-results");
-pub const OPEN_LINK: Selector<SpotifyUrl> = Selector::new("app.search.open-link");
+pub const OPEN_LINK: Selector<SpotifyUri> = Selector::new("app.search.open-link");
 
 pub fn input_widget() -> impl Widget<AppState> {
     TextBox::new()
@@ -51,21 +49,27 @@ pub fn results_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_RESULTS,
-        |q| WebApi::global().search(&q, SearchTopic::all(), NUMBER_OF_RESULTS_PER_TOPIC),
+        |q| webapi::global().search(&q, SearchTopic::all(), NUMBER_OF_RESULTS_PER_TOPIC),
         |_, data, q| data.search.results.defer(q),
-        |_, data, r| data.search.results.update(r),
+        |_, data, (q, r)| {
+            let r = r.map(|mut results| {
+                results.boost_library_matches(&data.library);
+                results
+            });
+            data.search.results.update((q, r))
+        },
     )
     .on_command_async(
         OPEN_LINK,
-        |l| WebApi::global().load_spotify_link(&l),
-        |_, data, l| data.search.results.defer(l.id()),
+        |l| webapi::global().load_spotify_link(&l),
+        |_, data, l| data.search.results.defer(l.id().clone()),
         |ctx, data, (l, r)| match r {
             Ok(nav) => {
                 data.search.results.clear();
                 ctx.submit_command(cmd::NAVIGATE.with(nav));
             }
             Err(err) => {
-                data.search.results.reject(l.id(), err);
+                data.search.results.reject(l.id().clone(), err);
             }
         },
     )
@@ -112,7 +116,11 @@ fn album_results_widget() -> impl Widget<WithCtx<SearchResults>> {
         Empty,
         Flex::column()
             .with_child(header_widget("Albums"))
-            .with_child(List::new(album::album_widget)),
+            .with_child(List::new(|| {
+                with_library_badge(album::album_widget(), |album: &WithCtx<Arc<Album>>| {
+                    album.ctx.library.contains_album(&album.data)
+                })
+            })),
     )
     .lens(Ctx::map(SearchResults::albums))
 }
@@ -142,7 +150,12 @@ fn playlist_results_widget() -> impl Widget<WithCtx<SearchResults>> {
         Flex::column()
             .with_child(header_widget("Playlists"))
             .with_child(
-                List::new(playlist::playlist_widget).lens(Ctx::map(SearchResults::playlists)),
+                List::new(|| {
+                    with_library_badge(playlist::playlist_widget(), |playlist: &WithCtx<_>| {
+                        playlist.ctx.library.contains_playlist(&playlist.data)
+                    })
+                })
+                .lens(Ctx::map(SearchResults::playlists)),
             ),
     )
 }
@@ -153,11 +166,32 @@ fn show_results_widget() -> impl Widget<WithCtx<SearchResults>> {
         Empty,
         Flex::column()
             .with_child(header_widget("Podcasts"))
-            .with_child(List::new(show::show_widget)),
+            .with_child(List::new(|| {
+                with_library_badge(show::show_widget(), |show: &WithCtx<Arc<Show>>| {
+                    show.ctx.library.contains_show(&show.data)
+                })
+            })),
     )
     .lens(Ctx::map(SearchResults::shows))
 }
 
+/// Tracks show their saved state on their own, other results get a badge.
+fn with_library_badge<T: Data>(
+    item: impl Widget<WithCtx<T>> + 'static,
+    is_saved: impl Fn(&WithCtx<T>) -> bool + 'static,
+) -> impl Widget<WithCtx<T>> {
+    Flex::row()
+        .with_flex_child(item, 1.0)
+        .with_child(Either::new(
+            move |item, _| is_saved(item),
+            Label::new("In your library")
+                .with_text_color(theme::PLACEHOLDER_COLOR)
+                .with_text_size(theme::TEXT_SIZE_SMALL)
+                .padding((theme::grid(1.0), 0.0)),
+            Empty,
+        ))
+}
+
 fn header_widget<T: Data>(text: impl Into<LabelText<T>>) -> impl Widget<T> {
     Label::new(text)
         .with_font(theme::UI_FONT_MEDIUM)
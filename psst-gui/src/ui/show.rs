@@ -8,8 +8,8 @@ use druid::{
 use crate::{
     cmd,
     data::{AppState, Ctx, Library, Nav, Show, ShowDetail, ShowEpisodes, ShowLink, WithCtx},
-    webapi::WebApi,
-    widget::{Async, MyWidgetExt, RemoteImage},
+    webapi,
+    widget::{plain_text, Async, MyWidgetExt, RemoteImage},
 };
 
 use super::{library, playable, theme, track, utils};
@@ -35,7 +35,7 @@ pub fn detail_widget() -> impl Widget<AppState> {
 //         )
 //         .on_command_async(
 //             LOAD_DETAIL,
-//             |d| WebApi::global().get_show(&d.id),
+//             |d| webapi::global().get_show(&d.id),
 //             |_, data, d| data.show_detail.show.defer(d),
 //             |_, data, (d, r)| data.show_detail.show.update((d, r)),
 //         )
@@ -64,7 +64,7 @@ fn async_episodes_widget() -> impl Widget<AppState> {
     )
     .on_command_async(
         LOAD_DETAIL,
-        |d| WebApi::global().get_show_episodes(&d.id),
+        |d| webapi::global().get_show_episodes(&d.id),
         |_, data, d| data.show_detail.episodes.defer(d),
         |_, data, (d, r)| {
             let r = r.map(|episodes| ShowEpisodes {
@@ -135,6 +135,21 @@ fn show_menu(show: &Arc<Show>, library: &Arc<Library>) -> Menu<AppState> {
         .command(cmd::COPY.with(show.link().url())),
     );
 
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-copy-name").with_placeholder("Copy Name"))
+            .command(cmd::COPY.with(show.name.to_string())),
+    );
+
+    if !show.description.is_empty() {
+        menu = menu.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-copy-description")
+                    .with_placeholder("Copy Description"),
+            )
+            .command(cmd::COPY.with(plain_text(&show.description))),
+        );
+    }
+
     menu = menu.separator();
 
     if library.contains_show(show) {
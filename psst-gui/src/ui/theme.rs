@@ -10,6 +10,21 @@ pub fn grid(m: f64) -> f64 {
 
 pub const GRID: f64 = 8.0;
 
+/// Pango picks the glyphs missing in the interface font from the first family of
+/// the list having them, so names in CJK scripts and emoji don't turn into boxes.
+/// Core Text and DirectWrite fall back to the system fonts on their own.
+#[cfg(target_os = "linux")]
+const FALLBACK_FAMILIES: &[&str] = &[
+    "Noto Sans CJK JP",
+    "Noto Sans CJK KR",
+    "Noto Sans CJK SC",
+    "Noto Sans CJK TC",
+    "Source Han Sans",
+    "WenQuanYi Micro Hei",
+    "Noto Color Emoji",
+    "Twemoji",
+];
+
 pub const GREY_000: Key<Color> = Key::new("app.grey_000");
 pub const GREY_100: Key<Color> = Key::new("app.grey_100");
 pub const GREY_200: Key<Color> = Key::new("app.grey_200");
@@ -42,6 +57,11 @@ pub const LINK_HOT_COLOR: Key<Color> = Key::new("app.link-hot-color");
 pub const LINK_ACTIVE_COLOR: Key<Color> = Key::new("app.link-active-color");
 pub const LINK_COLD_COLOR: Key<Color> = Key::new("app.link-cold-color");
 
+/// Animations are turned off, see `Config::reduce_motion`.
+pub const REDUCED_MOTION: Key<bool> = Key::new("app.reduced-motion");
+/// Animations run on a slow timer instead of every frame, see `Config::low_power_mode`.
+pub const LOW_POWER: Key<bool> = Key::new("app.low-power");
+
 pub fn setup(env: &mut Env, state: &AppState) {
     match state.config.theme {
         Theme::Light => setup_light_theme(env),
@@ -83,13 +103,14 @@ pub fn setup(env: &mut Env, state: &AppState) {
     env.set(BUTTON_BORDER_RADIUS, 4.0);
     env.set(BUTTON_BORDER_WIDTH, 1.0);
 
+    let ui_font = ui_font_family(state.config.ui_font());
     env.set(
         UI_FONT,
-        FontDescriptor::new(FontFamily::SYSTEM_UI).with_size(13.0),
+        FontDescriptor::new(ui_font.clone()).with_size(13.0),
     );
     env.set(
         UI_FONT_MEDIUM,
-        FontDescriptor::new(FontFamily::SYSTEM_UI)
+        FontDescriptor::new(ui_font)
             .with_size(13.0)
             .with_weight(FontWeight::MEDIUM),
     );
@@ -126,6 +147,28 @@ pub fn setup(env: &mut Env, state: &AppState) {
     env.set(MENU_BUTTON_BG_INACTIVE, env.get(GREY_600));
     env.set(MENU_BUTTON_FG_ACTIVE, env.get(GREY_000));
     env.set(MENU_BUTTON_FG_INACTIVE, env.get(GREY_100));
+
+    env.set(
+        REDUCED_MOTION,
+        state
+            .config
+            .reduce_motion(state.system_prefers_reduced_motion),
+    );
+    env.set(LOW_POWER, state.config.low_power_mode);
+}
+
+#[cfg(target_os = "linux")]
+fn ui_font_family(family: Option<&str>) -> FontFamily {
+    let families = family
+        .into_iter()
+        .chain(["sans-serif"])
+        .chain(FALLBACK_FAMILIES.iter().copied());
+    FontFamily::new_unchecked(families.collect::<Vec<_>>().join(", "))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn ui_font_family(family: Option<&str>) -> FontFamily {
+    family.map_or(FontFamily::SYSTEM_UI, FontFamily::new_unchecked)
 }
 
 fn setup_light_theme(env: &mut Env) {
@@ -7,9 +7,10 @@ use druid::{
 
 use crate::{
     cmd,
+    controller::PrefetchRow,
     data::{
         AppState, Library, Nav, PlaybackOrigin, PlaylistAddTrack, PlaylistRemoveTrack,
-        RecommendationsRequest, Track,
+        QueuePlacement, QueueRequest, QueueSource, RadioSeed, RecommendationsRequest, Track,
     },
     ui::playlist,
     widget::{icons, Empty, MyWidgetExt, RemoteImage},
@@ -112,12 +113,14 @@ pub fn playable_widget(track: &Track, display: Display) -> impl Widget<PlayRow<A
     major.add_flex_child(is_playing, 1.0);
 
     if display.popularity {
-        let track_popularity = Label::<Arc<Track>>::dynamic(|track, _| {
-            track.popularity.map(popularity_stars).unwrap_or_default()
+        let track_popularity = Label::dynamic(|row: &PlayRow<Arc<Track>>, _| {
+            row.ctx
+                .track_popularity(&row.item)
+                .map(popularity_stars)
+                .unwrap_or_default()
         })
         .with_text_size(theme::TEXT_SIZE_SMALL)
-        .with_text_color(theme::PLACEHOLDER_COLOR)
-        .lens(PlayRow::item);
+        .with_text_color(theme::PLACEHOLDER_COLOR);
         major.add_default_spacer();
         major.add_child(track_popularity);
     }
@@ -131,10 +134,10 @@ pub fn playable_widget(track: &Track, display: Display) -> impl Widget<PlayRow<A
     major.add_child(track_duration);
 
     let saved = ViewSwitcher::new(
-        |row: &PlayRow<Arc<Track>>, _| row.ctx.library.saved_tracks.is_resolved(),
+        |row: &PlayRow<Arc<Track>>, _| row.ctx.is_track_saved(&row.item).is_some(),
         |selector: &bool, _, _| match selector {
             true => ViewSwitcher::new(
-                |row: &PlayRow<Arc<Track>>, _| row.ctx.library.contains_track(&row.item),
+                |row: &PlayRow<Arc<Track>>, _| row.ctx.is_track_saved(&row.item) == Some(true),
                 |selector: &bool, _, _| {
                     match selector {
                         true => &icons::HEART_SOLID,
@@ -146,7 +149,7 @@ pub fn playable_widget(track: &Track, display: Display) -> impl Widget<PlayRow<A
             )
             .on_left_click(|ctx, _, row, _| {
                 let track = &row.item;
-                if row.ctx.library.contains_track(track) {
+                if row.ctx.is_track_saved(track) == Some(true) {
                     ctx.submit_command(library::UNSAVE_TRACK.with(track.id))
                 } else {
                     ctx.submit_command(library::SAVE_TRACK.with(track.clone()))
@@ -164,18 +167,24 @@ pub fn playable_widget(track: &Track, display: Display) -> impl Widget<PlayRow<A
                 .with_child(major)
                 .with_spacer(2.0)
                 .with_child(minor)
-                .on_left_click(|ctx, _, row, _| {
-                    ctx.submit_notification(cmd::PLAY.with(row.position))
-                }),
+                .on_left_click(|ctx, mouse, row, _| playable::on_row_click(ctx, mouse, row)),
             1.0,
         )
         .with_default_spacer()
         .with_child(saved)
+        .env_scope(|env, row: &PlayRow<Arc<Track>>| {
+            // Grey out the tracks we can't play, the tooltip tells why.
+            if row.ctx.unavailable_reason(&row.item).is_some() {
+                env.set(theme::TEXT_COLOR, env.get(theme::PLACEHOLDER_COLOR));
+            }
+        })
         .padding(theme::grid(1.0))
         .link()
-        .active(|row, _| row.is_playing)
+        .active(|row, _| row.is_playing || row.is_selected)
         .rounded(theme::BUTTON_BORDER_RADIUS)
+        .tooltip(|row| row.ctx.unavailable_reason(&row.item).map(Into::into))
         .context_menu(track_row_menu)
+        .controller(PrefetchRow::new(display.popularity))
 }
 
 fn cover_widget(size: f64) -> impl Widget<Arc<Track>> {
@@ -218,6 +227,11 @@ pub fn track_menu(
     library: &Library,
     origin: &PlaybackOrigin,
 ) -> Menu<AppState> {
+    if track.is_local {
+        // Local files are unknown to the Web API, so none of the actions below apply.
+        return local_track_menu(track, library, origin);
+    }
+
     let mut menu = Menu::empty();
 
     for artist_link in &track.artists {
@@ -253,6 +267,21 @@ pub fn track_menu(
         )))),
     );
 
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-start-radio").with_placeholder("Start Radio"),
+        )
+        .command(cmd::START_RADIO.with(RadioSeed::track(track).into_radio())),
+    );
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-add-to-radio-builder")
+                .with_placeholder("Add to Radio Builder"),
+        )
+        .command(cmd::ADD_RADIO_SEED.with(RadioSeed::track(track))),
+    );
+
     menu = menu.entry(
         MenuItem::new(
             LocalizedString::new("menu-item-copy-link").with_placeholder("Copy Link to Track"),
@@ -260,6 +289,17 @@ pub fn track_menu(
         .command(cmd::COPY.with(track.url())),
     );
 
+    menu = copy_name_entries(menu, track);
+
+    menu = menu.separator();
+
+    menu = queue_menu_entries(menu, track, origin);
+
+    menu = menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-download").with_placeholder("Download"))
+            .command(cmd::DOWNLOAD.with(QueueSource::Track(track.clone(), origin.clone()))),
+    );
+
     menu = menu.separator();
 
     if library.contains_track(track) {
@@ -340,5 +380,113 @@ pub fn track_menu(
     }
     menu = menu.entry(playlist_menu);
 
+    menu = menu.separator();
+
+    menu = menu.entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-block-track").with_placeholder("Block Track"),
+        )
+        .command(cmd::BLOCK_TRACK.with(track.clone())),
+    );
+    for artist_link in &track.artists {
+        let title = if track.artists.len() > 1 {
+            LocalizedString::new("menu-item-block-artist-name")
+                .with_placeholder(format!("Block Artist “{}”", artist_link.name))
+        } else {
+            LocalizedString::new("menu-item-block-artist").with_placeholder("Block Artist")
+        };
+        menu =
+            menu.entry(MenuItem::new(title).command(cmd::BLOCK_ARTIST.with(artist_link.clone())));
+    }
+
     menu
 }
+
+fn copy_name_entries(menu: Menu<AppState>, track: &Track) -> Menu<AppState> {
+    menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-copy-name").with_placeholder("Copy Name"))
+            .command(cmd::COPY.with(track.name.to_string())),
+    )
+    .entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-copy-artist-and-title")
+                .with_placeholder("Copy Artist – Title"),
+        )
+        .command(cmd::COPY.with(format!("{} – {}", track.artist_names(), track.name))),
+    )
+}
+
+fn local_track_menu(
+    track: &Arc<Track>,
+    library: &Library,
+    origin: &PlaybackOrigin,
+) -> Menu<AppState> {
+    let mut menu = queue_menu_entries(Menu::empty(), track, origin);
+
+    menu = copy_name_entries(menu.separator(), track);
+
+    // Files matched to the catalog can stand in for their streamed counterparts.
+    if let Some(catalog_track) = track.to_catalog_track() {
+        menu = menu.separator().entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-show-recommended")
+                    .with_placeholder("Show Similar Tracks"),
+            )
+            .command(cmd::NAVIGATE.with(Nav::Recommendations(Arc::new(
+                RecommendationsRequest::for_track(catalog_track.id),
+            )))),
+        );
+        menu = if library.contains_track(track) {
+            menu.entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-remove-from-library")
+                        .with_placeholder("Remove Track from Library"),
+                )
+                .command(library::UNSAVE_TRACK.with(catalog_track.id)),
+            )
+        } else {
+            menu.entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-save-to-library")
+                        .with_placeholder("Save Track to Library"),
+                )
+                .command(library::SAVE_TRACK.with(catalog_track)),
+            )
+        };
+    }
+
+    if let Some(path) = &track.local_path {
+        menu = menu.separator().entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-copy-file-path").with_placeholder("Copy File Path"),
+            )
+            .command(cmd::COPY.with(path.to_string())),
+        );
+    }
+
+    menu
+}
+
+fn queue_menu_entries(
+    menu: Menu<AppState>,
+    track: &Arc<Track>,
+    origin: &PlaybackOrigin,
+) -> Menu<AppState> {
+    let source = QueueSource::Track(track.clone(), origin.clone());
+    menu.entry(
+        MenuItem::new(LocalizedString::new("menu-item-play-next").with_placeholder("Play Next"))
+            .command(cmd::ADD_TO_QUEUE.with(QueueRequest {
+                source: source.clone(),
+                placement: QueuePlacement::Next,
+            })),
+    )
+    .entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-add-to-queue").with_placeholder("Add to Queue"),
+        )
+        .command(cmd::ADD_TO_QUEUE.with(QueueRequest {
+            source,
+            placement: QueuePlacement::Last,
+        })),
+    )
+}
@@ -6,7 +6,7 @@ use druid::{
 
 use crate::{
     data::{AppState, Library, UserProfile},
-    webapi::WebApi,
+    webapi,
     widget::{icons, icons::SvgIcon, Async, Empty, MyWidgetExt},
 };
 
@@ -38,7 +38,7 @@ pub fn user_widget() -> impl Widget<AppState> {
     .lens(AppState::library.then(Library::user_profile.in_arc()))
     .on_command_async(
         LOAD_PROFILE,
-        |_| WebApi::global().get_user_profile(),
+        |_| webapi::global().get_user_profile(),
         |_, data, d| data.with_library_mut(|l| l.user_profile.defer(d)),
         |_, data, r| data.with_library_mut(|l| l.user_profile.update(r)),
     );
@@ -3,39 +3,106 @@ use std::{f64::consts::PI, time::Duration};
 use druid::{
     kurbo::Circle,
     widget::{prelude::*, CrossAxisAlignment, Flex, Label, SizedBox},
-    Data, Vec2, Widget, WidgetExt,
+    Data, Point, Rect, Screen, TimerToken, Vec2, Widget, WidgetExt, WindowHandle, WindowState,
 };
 use time_humanize::HumanTime;
 
-use crate::{error::Error, widget::icons};
+use crate::{data::ScreenCorner, error::Error, widget::icons};
 
 use super::theme;
 
+/// Frame interval of animations in the low power mode.
+const LOW_POWER_FRAME_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a paused animation checks whether the window came back to the
+/// foreground, in the low power mode.
+const LOW_POWER_BACKGROUND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The window is minimized or another window has the focus.
+pub fn is_in_background(window: &WindowHandle) -> bool {
+    !window.is_foreground_window() || window.get_window_state() == WindowState::Minimized
+}
+
+/// Origin of a window of `size` in the `corner` of the primary screen, keeping
+/// clear of the screen edges and the task bars.
+pub fn screen_corner_position(corner: ScreenCorner, size: Size) -> Point {
+    const MARGIN: f64 = 24.0;
+
+    let screen = Screen::get_monitors()
+        .into_iter()
+        .find(|monitor| monitor.is_primary())
+        .map(|monitor| monitor.virtual_work_rect())
+        .unwrap_or_else(|| Rect::new(0.0, 0.0, 1280.0, 720.0));
+    let left = screen.x0 + MARGIN;
+    let right = screen.x1 - size.width - MARGIN;
+    let top = screen.y0 + MARGIN;
+    let bottom = screen.y1 - size.height - MARGIN;
+    match corner {
+        ScreenCorner::TopLeft => Point::new(left, top),
+        ScreenCorner::TopRight => Point::new(right, top),
+        ScreenCorner::BottomLeft => Point::new(left, bottom),
+        ScreenCorner::BottomRight => Point::new(right, bottom),
+    }
+}
+
 struct Spinner {
     t: f64,
+    timer: TimerToken,
 }
 
 impl Spinner {
     pub fn new() -> Self {
-        Self { t: 0.0 }
+        Self {
+            t: 0.0,
+            timer: TimerToken::INVALID,
+        }
+    }
+
+    fn step(&mut self, ctx: &mut EventCtx, env: &Env, interval: f64) {
+        // With reduced motion, the spinner stays on its first frame.
+        if env.get(theme::REDUCED_MOTION) {
+            return;
+        }
+        if env.get(theme::LOW_POWER) {
+            // Step on a slow timer instead of every frame, and stop painting while
+            // nobody is looking.
+            if is_in_background(ctx.window()) {
+                self.timer = ctx.request_timer(LOW_POWER_BACKGROUND_INTERVAL);
+                return;
+            }
+            self.timer = ctx.request_timer(LOW_POWER_FRAME_INTERVAL);
+        } else {
+            ctx.request_anim_frame();
+        }
+        self.t += interval;
+        if self.t >= 1.0 {
+            self.t = 0.0;
+        }
+        ctx.request_paint();
     }
 }
 
 impl<T: Data> Widget<T> for Spinner {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut T, _env: &Env) {
-        if let Event::AnimFrame(interval) = event {
-            self.t += (*interval as f64) * 1e-9;
-            if self.t >= 1.0 {
-                self.t = 0.0;
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut T, env: &Env) {
+        match event {
+            Event::AnimFrame(interval) => {
+                self.step(ctx, env, (*interval as f64) * 1e-9);
             }
-            ctx.request_anim_frame();
-            ctx.request_paint();
+            Event::Timer(token) if token == &self.timer => {
+                self.timer = TimerToken::INVALID;
+                self.step(ctx, env, LOW_POWER_FRAME_INTERVAL.as_secs_f64());
+            }
+            _ => {}
         }
     }
 
-    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &T, _env: &Env) {
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, _data: &T, env: &Env) {
         if let LifeCycle::WidgetAdded = event {
-            ctx.request_anim_frame();
+            if env.get(theme::LOW_POWER) {
+                self.timer = ctx.request_timer(LOW_POWER_FRAME_INTERVAL);
+            } else if !env.get(theme::REDUCED_MOTION) {
+                ctx.request_anim_frame();
+            }
             ctx.request_paint();
         }
     }
@@ -109,6 +176,46 @@ pub fn as_minutes_and_seconds(dur: Duration) -> String {
     format!("{}∶{:02}", minutes, seconds)
 }
 
+/// Parse a timestamp typed as `ss`, `mm:ss` or `hh:mm:ss`.
+pub fn parse_timestamp(input: &str) -> Option<Duration> {
+    let parts: Vec<&str> = input.trim().split(&[':', '∶'][..]).collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    let mut secs = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let value: u64 = part.trim().parse().ok()?;
+        // Only the leading component may overflow into the next unit.
+        if i > 0 && value >= 60 {
+            return None;
+        }
+        secs = secs * 60 + value;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+/// Count with thousands separated, e.g. "6,431".
+pub fn as_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut result = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            result.push(',');
+        }
+        result.push(digit);
+    }
+    result
+}
+
+/// Size in bytes, in gigabytes or megabytes, whichever reads better.
+pub fn as_human_size(bytes: u64) -> String {
+    if bytes >= 1_000_000_000 {
+        format!("{:.1} GB", bytes as f64 / 1e9)
+    } else {
+        format!("{:.0} MB", bytes as f64 / 1e6)
+    }
+}
+
 pub fn as_human(dur: Duration) -> String {
     HumanTime::from(dur).to_text_en(
         time_humanize::Accuracy::Rough,
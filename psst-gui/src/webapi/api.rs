@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use druid::{im::Vector, ImageBuf};
+use once_cell::sync::OnceCell;
+use psst_core::uri::SpotifyUri;
+
+use crate::{
+    data::{
+        Album, Artist, ArtistAlbums, CacheUsage, Cached, Canvas, Episode, LoadProgress,
+        MusicBrainzRelease, Nav, Playlist, Recommendations, RecommendationsRequest, SearchResults,
+        SearchTopic, Show, Track, TrackId, UserProfile,
+    },
+    error::Error,
+};
+
+/// Everything the UI loads from Spotify.  The views only talk to the global
+/// instance through this trait, so `Web` can be swapped for a mock while
+/// working on the UI, or for a cached or offline backend.
+pub trait WebApi: Send + Sync {
+    /// Load local track files from the official client's database.
+    fn load_local_tracks(&self, username: &str);
+
+    /// Request the content for this market instead of the country of the account.
+    fn set_market(&self, market: Option<String>);
+
+    /// Remove the cached responses that can't be read anymore.  Returns how many
+    /// were removed.
+    fn collect_cache_garbage(&self) -> usize;
+
+    /// Limit the memory the decoded images take, in bytes.
+    fn set_image_cache_limit(&self, limit: Option<usize>);
+
+    /// Memory taken by the decoded images.
+    fn image_cache_usage(&self) -> CacheUsage;
+
+    // Other endpoints.
+    fn get_user_profile(&self) -> Result<UserProfile, Error>;
+
+    // Artist endpoints.
+    fn get_artist(&self, id: &str) -> Result<Artist, Error>;
+    fn is_following_artist(&self, id: &str) -> Result<bool, Error>;
+    fn follow_artist(&self, id: &str) -> Result<(), Error>;
+    fn unfollow_artist(&self, id: &str) -> Result<(), Error>;
+    fn get_artist_albums(&self, id: &str) -> Result<ArtistAlbums, Error>;
+    fn get_artist_top_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error>;
+    fn get_related_artists(&self, id: &str) -> Result<Cached<Vector<Artist>>, Error>;
+
+    // Album endpoints.
+    fn get_album(&self, id: &str) -> Result<Cached<Arc<Album>>, Error>;
+    fn get_album_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error>;
+
+    // Show endpoints. (Podcasts)
+    fn get_show_episodes(&self, id: &str) -> Result<Vector<Arc<Episode>>, Error>;
+
+    // Track endpoints.
+    fn get_track(&self, id: &str) -> Result<Arc<Track>, Error>;
+    fn get_tracks(&self, ids: &[TrackId]) -> Result<Vector<Arc<Track>>, Error>;
+    fn get_canvas(&self, id: TrackId) -> Result<Option<Canvas>, Error>;
+
+    // Library endpoints.
+    fn get_saved_albums(&self) -> Result<Vector<Arc<Album>>, Error>;
+    fn save_album(&self, id: &str) -> Result<(), Error>;
+    fn unsave_album(&self, id: &str) -> Result<(), Error>;
+    fn get_saved_tracks(&self) -> Result<Vector<Arc<Track>>, Error>;
+    fn get_saved_shows(&self) -> Result<Vector<Arc<Show>>, Error>;
+    fn save_track(&self, id: &str) -> Result<(), Error>;
+    fn unsave_track(&self, id: &str) -> Result<(), Error>;
+    /// Save many tracks at once, calling `on_chunk` with the number of tracks
+    /// saved by each request.
+    fn save_tracks(&self, ids: &[TrackId], on_chunk: &mut dyn FnMut(usize)) -> Result<(), Error>;
+    /// Like `save_tracks`, but removes the tracks from the library.
+    fn unsave_tracks(&self, ids: &[TrackId], on_chunk: &mut dyn FnMut(usize)) -> Result<(), Error>;
+    /// Whether each of the tracks is saved, in the order of `ids`.
+    fn contains_saved_tracks(&self, ids: &[TrackId]) -> Result<Vec<bool>, Error>;
+    fn save_show(&self, id: &str) -> Result<(), Error>;
+    fn unsave_show(&self, id: &str) -> Result<(), Error>;
+
+    // View endpoints.
+    fn get_made_for_you(&self) -> Result<Vector<Playlist>, Error>;
+
+    // Playlist endpoints.
+    fn get_playlists(&self) -> Result<Vector<Playlist>, Error>;
+    fn follow_playlist(&self, id: &str) -> Result<(), Error>;
+    fn unfollow_playlist(&self, id: &str) -> Result<(), Error>;
+    fn get_playlist(&self, id: &str) -> Result<Playlist, Error>;
+    fn get_playlist_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error>;
+    /// Like `get_playlist_tracks`, but hands the tracks over page by page, as soon
+    /// as they arrive.
+    fn get_playlist_tracks_paged(
+        &self,
+        id: &str,
+        on_page: &mut dyn FnMut(Vector<Arc<Track>>, LoadProgress),
+    ) -> Result<(), Error>;
+    /// Create a private playlist owned by the current user.
+    fn create_playlist(&self, name: &str) -> Result<Playlist, Error>;
+    fn change_playlist_details(&self, id: &str, name: &str) -> Result<(), Error>;
+    fn add_track_to_playlist(&self, playlist_id: &str, track_uri: &str) -> Result<(), Error>;
+    fn add_tracks_to_playlist(
+        &self,
+        playlist_id: &str,
+        track_uris: &[Arc<str>],
+    ) -> Result<(), Error>;
+    fn remove_track_from_playlist(&self, playlist_id: &str, track_uri: &str) -> Result<(), Error>;
+    /// Replace all items of the playlist with `track_uris`.
+    fn replace_playlist_tracks(
+        &self,
+        playlist_id: &str,
+        track_uris: &[Arc<str>],
+    ) -> Result<(), Error>;
+
+    // Search endpoints.
+    fn search(
+        &self,
+        query: &str,
+        topics: &[SearchTopic],
+        limit: usize,
+    ) -> Result<SearchResults, Error>;
+    fn load_spotify_link(&self, link: &SpotifyUri) -> Result<Nav, Error>;
+
+    // Recommendation endpoints.
+    fn get_recommendations(
+        &self,
+        data: Arc<RecommendationsRequest>,
+    ) -> Result<Recommendations, Error>;
+
+    // MusicBrainz endpoints.
+    /// Find the release of the album on MusicBrainz by its barcode.
+    fn get_musicbrainz_release(&self, album: &Album) -> Result<Option<MusicBrainzRelease>, Error>;
+
+    // Image endpoints.
+    fn get_cached_image(&self, uri: &Arc<str>) -> Option<ImageBuf>;
+    fn get_image(&self, uri: Arc<str>) -> Result<ImageBuf, Error>;
+}
+
+static GLOBAL_WEBAPI: OnceCell<Arc<dyn WebApi>> = OnceCell::new();
+
+pub fn install_global(api: impl WebApi + 'static) {
+    GLOBAL_WEBAPI
+        .set(Arc::new(api))
+        .map_err(|_| "Cannot install more than once")
+        .unwrap()
+}
+
+/// Global instance.
+pub fn global() -> Arc<dyn WebApi> {
+    GLOBAL_WEBAPI.get().unwrap().clone()
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use druid::im::Vector;
+use itertools::Itertools;
+use psst_core::util::default_ureq_agent_builder;
+use serde::Deserialize;
+use ureq::Agent;
+use url::Url;
+
+use crate::{
+    data::{ArtistEvent, ArtistLink},
+    error::Error,
+};
+
+use super::events::EventsProvider;
+
+/// Events from the Bandsintown API, which needs an app ID issued to the user.
+pub struct Bandsintown {
+    agent: Agent,
+    app_id: String,
+}
+
+impl Bandsintown {
+    pub fn new(app_id: &str, proxy_url: Option<&str>) -> Self {
+        Self {
+            agent: default_ureq_agent_builder(proxy_url).unwrap().build(),
+            app_id: app_id.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Event {
+    url: Arc<str>,
+    datetime: String,
+    venue: Venue,
+}
+
+#[derive(Deserialize)]
+struct Venue {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    city: String,
+    #[serde(default)]
+    region: String,
+    #[serde(default)]
+    country: String,
+}
+
+impl EventsProvider for Bandsintown {
+    fn upcoming_events(&self, artist: &ArtistLink) -> Result<Vector<ArtistEvent>, Error> {
+        let mut url = Url::parse("https://rest.bandsintown.com/artists").unwrap();
+        url.path_segments_mut()
+            .unwrap()
+            .push(&artist.name)
+            .push("events");
+        let events: Vec<Event> = self
+            .agent
+            .request_url("GET", &url)
+            .query("app_id", &self.app_id)
+            .call()?
+            .into_json()?;
+        Ok(events
+            .into_iter()
+            .map(|event| ArtistEvent {
+                date: event.datetime.get(..10).unwrap_or(&event.datetime).into(),
+                location: [event.venue.city, event.venue.region, event.venue.country]
+                    .iter()
+                    .filter(|part| !part.is_empty())
+                    .join(", ")
+                    .into(),
+                venue: event.venue.name.into(),
+                url: event.url,
+            })
+            .collect())
+    }
+}
@@ -8,29 +8,52 @@ use druid::ImageBuf;
 use lru_cache::LruCache;
 use parking_lot::Mutex;
 use psst_core::cache::mkdir_if_not_exists;
+use serde::de::IgnoredAny;
+
+use crate::data::CacheUsage;
+
+/// Directories of the cache that belong to `psst_core::cache::Cache`.
+const CORE_CACHE_DIRS: [&str; 4] = ["track", "episode", "audio", "key"];
 
 pub struct WebApiCache {
     base: Option<PathBuf>,
-    images: Mutex<LruCache<Arc<str>, ImageBuf>>,
+    images: Mutex<ImageCache>,
 }
 
 impl WebApiCache {
     pub fn new(base: Option<PathBuf>) -> Self {
-        const IMAGE_CACHE_SIZE: usize = 256;
         Self {
             base,
-            images: Mutex::new(LruCache::new(IMAGE_CACHE_SIZE)),
+            images: Mutex::new(ImageCache {
+                images: LruCache::new(usize::MAX),
+                bytes: 0,
+                limit: None,
+            }),
         }
     }
 
     pub fn get_image(&self, uri: &Arc<str>) -> Option<ImageBuf> {
-        self.images.lock().get_mut(uri).cloned()
+        self.images.lock().images.get_mut(uri).cloned()
     }
 
     pub fn set_image(&self, uri: Arc<str>, image: ImageBuf) {
         self.images.lock().insert(uri, image);
     }
 
+    pub fn set_image_limit(&self, limit: Option<usize>) {
+        let mut images = self.images.lock();
+        images.limit = limit;
+        images.evict();
+    }
+
+    pub fn image_usage(&self) -> CacheUsage {
+        let images = self.images.lock();
+        CacheUsage {
+            entries: images.images.len(),
+            bytes: images.bytes,
+        }
+    }
+
     pub fn get(&self, bucket: &str, key: &str) -> Option<File> {
         self.key(bucket, key).and_then(|path| File::open(path).ok())
     }
@@ -48,6 +71,40 @@ impl WebApiCache {
         }
     }
 
+    pub fn remove(&self, bucket: &str, key: &str) {
+        if let Some(path) = self.key(bucket, key) {
+            if let Err(err) = fs::remove_file(path) {
+                log::warn!("failed to remove from WebAPI cache: {:?}", err);
+            }
+        }
+    }
+
+    /// Remove the entries that aren't valid JSON, e.g. after the disk ran out of
+    /// space while saving.  They would fail to load on every request otherwise.
+    pub fn remove_unreadable(&self) -> usize {
+        let base = match &self.base {
+            Some(base) => base,
+            None => return 0,
+        };
+        let mut removed = 0;
+        for bucket in fs::read_dir(base).into_iter().flatten().flatten() {
+            // The buckets are mixed with the directories of the core cache.
+            if CORE_CACHE_DIRS.iter().any(|dir| bucket.file_name() == *dir) {
+                continue;
+            }
+            for entry in fs::read_dir(bucket.path()).into_iter().flatten().flatten() {
+                let is_readable = File::open(entry.path())
+                    .ok()
+                    .and_then(|file| serde_json::from_reader::<_, IgnoredAny>(file).ok())
+                    .is_some();
+                if !is_readable && fs::remove_file(entry.path()).is_ok() {
+                    removed += 1;
+                }
+            }
+        }
+        removed
+    }
+
     fn bucket(&self, bucket: &str) -> Option<PathBuf> {
         self.base.as_ref().map(|path| path.join(bucket))
     }
@@ -56,3 +113,35 @@ impl WebApiCache {
         self.bucket(bucket).map(|path| path.join(key))
     }
 }
+
+/// Decoded images, weighted by the size of their pixels.  The least recently used
+/// ones are evicted once they take more memory than the limit, a single cover in
+/// full size weighs as much as hundreds of thumbnails.
+struct ImageCache {
+    images: LruCache<Arc<str>, ImageBuf>,
+    bytes: usize,
+    limit: Option<usize>,
+}
+
+impl ImageCache {
+    fn insert(&mut self, uri: Arc<str>, image: ImageBuf) {
+        self.bytes += image.raw_pixels().len();
+        if let Some(old) = self.images.insert(uri, image) {
+            self.bytes -= old.raw_pixels().len();
+        }
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return,
+        };
+        while self.bytes > limit {
+            match self.images.remove_lru() {
+                Some((_, image)) => self.bytes -= image.raw_pixels().len(),
+                None => break,
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashSet,
     fmt::Display,
     io::{self, Read},
     path::PathBuf,
@@ -13,43 +14,59 @@ use druid::{
     Data, ImageBuf,
 };
 use itertools::Itertools;
-use once_cell::sync::OnceCell;
 use parking_lot::Mutex;
 use serde::{de::DeserializeOwned, Deserialize};
 use serde_json::json;
 use ureq::{Agent, Request, Response};
 
 use psst_core::{
+    item_id::{ItemId, ItemIdType},
+    net,
+    protocol::canvaz::{
+        mod_EntityCanvazRequest::Entity, EntityCanvazRequest, EntityCanvazResponse, Type,
+    },
     session::{access_token::TokenProvider, SessionService},
-    util::default_ureq_agent_builder,
+    uri::SpotifyUri,
+    util::{default_ureq_agent_builder, deserialize_protobuf, serialize_protobuf},
 };
 
 use crate::{
     data::{
-        Album, AlbumType, Artist, ArtistAlbums, AudioAnalysis, Cached, Episode, EpisodeId,
-        EpisodeLink, Nav, Page, Playlist, Range, Recommendations, RecommendationsRequest,
-        SearchResults, SearchTopic, Show, SpotifyUrl, Track, UserProfile,
+        Album, AlbumType, Artist, ArtistAlbums, AudioAnalysis, CacheUsage, Cached, Canvas, Episode,
+        EpisodeId, EpisodeLink, LoadProgress, MusicBrainzRelease, Nav, Page, Playlist, Range,
+        Recommendations, RecommendationsRequest, SearchResults, SearchTopic, Show, Track, TrackId,
+        UserProfile,
     },
     error::Error,
 };
 
-use super::{cache::WebApiCache, local::LocalTrackManager};
+use super::{cache::WebApiCache, local::LocalTrackManager, mercury, WebApi};
+
+/// Most IDs the library endpoints take in one request.
+pub(super) const LIBRARY_IDS_PER_REQUEST: usize = 50;
 
-pub struct WebApi {
+/// Barcodes MusicBrainz didn't know are looked up again after this long, the
+/// release might have been added since.
+const MUSICBRAINZ_MISS_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// `WebApi` backed by the Spotify Web API.
+pub struct Web {
     session: SessionService,
     agent: Agent,
     cache: WebApiCache,
     token_provider: TokenProvider,
     local_track_manager: Mutex<LocalTrackManager>,
     paginated_limit: usize,
+    market: Mutex<Option<String>>,
 }
 
-impl WebApi {
+impl Web {
     pub fn new(
         session: SessionService,
         proxy_url: Option<&str>,
         cache_base: Option<PathBuf>,
         paginated_limit: usize,
+        market: Option<String>,
     ) -> Self {
         let agent = default_ureq_agent_builder(proxy_url).unwrap().build();
         Self {
@@ -59,6 +76,7 @@ impl WebApi {
             token_provider: TokenProvider::new(),
             local_track_manager: Mutex::new(LocalTrackManager::new()),
             paginated_limit,
+            market: Mutex::new(market),
         }
     }
 
@@ -70,6 +88,15 @@ impl WebApi {
         Ok(token.token)
     }
 
+    /// Market to request the content for.  Unless overridden, it's the country of
+    /// the account, which decides what is playable and how tracks are relinked.
+    fn market(&self) -> String {
+        self.market
+            .lock()
+            .clone()
+            .unwrap_or_else(|| "from_token".to_string())
+    }
+
     fn request(&self, method: &str, path: impl Display) -> Result<Request, Error> {
         let token = self.access_token()?;
         let request = self
@@ -95,6 +122,22 @@ impl WebApi {
         self.request("DELETE", path)
     }
 
+    /// GET request to the MusicBrainz API, which asks clients to identify
+    /// themselves in the user agent.
+    fn musicbrainz(&self, path: impl Display) -> Request {
+        self.agent
+            .get(&format!("https://musicbrainz.org/ws/2/{}", path))
+            .set(
+                "User-Agent",
+                concat!(
+                    "Psst/",
+                    env!("CARGO_PKG_VERSION"),
+                    " ( https://github.com/jpochyla/psst )"
+                ),
+            )
+            .query("fmt", "json")
+    }
+
     fn with_retry(f: impl Fn() -> Result<Response, Error>) -> Result<Response, Error> {
         loop {
             let response = f()?;
@@ -135,19 +178,28 @@ impl WebApi {
         request: Request,
         bucket: &str,
         key: &str,
+    ) -> Result<Cached<T>, Error> {
+        self.load_cached_with(bucket, key, || {
+            let response = Self::with_retry(|| Ok(request.clone().call()?))?;
+            let mut body = Vec::new();
+            response.into_reader().read_to_end(&mut body)?;
+            Ok(body)
+        })
+    }
+
+    /// Like `load_cached()`, but the JSON body to cache is produced by `load`.
+    fn load_cached_with<T: Data + DeserializeOwned>(
+        &self,
+        bucket: &str,
+        key: &str,
+        load: impl FnOnce() -> Result<Vec<u8>, Error>,
     ) -> Result<Cached<T>, Error> {
         if let Some(file) = self.cache.get(bucket, key) {
             let cached_at = file.metadata()?.modified()?;
             let value = serde_json::from_reader(file)?;
             Ok(Cached::new(value, cached_at))
         } else {
-            let response = Self::with_retry(|| Ok(request.clone().call()?))?;
-            let body = {
-                let mut reader = response.into_reader();
-                let mut body = Vec::new();
-                reader.read_to_end(&mut body)?;
-                body
-            };
+            let body = load()?;
             let value = serde_json::from_slice(&body)?;
             self.cache.set(bucket, key, &body);
             Ok(Cached::fresh(value))
@@ -159,32 +211,21 @@ impl WebApi {
     fn for_all_pages<T: DeserializeOwned + Clone>(
         &self,
         request: Request,
-        mut func: impl FnMut(Page<T>) -> Result<(), Error>,
+        func: impl FnMut(Page<T>) -> Result<(), Error>,
     ) -> Result<(), Error> {
         // TODO: Some result sets, like very long playlists and saved tracks/albums can
         // be very big.  Implement virtualized scrolling and lazy-loading of results.
-        let mut limit = 50;
-        let mut offset = 0;
-        loop {
-            let req = request
-                .clone()
-                .query("limit", &limit.to_string())
-                .query("offset", &offset.to_string());
-            let page: Page<T> = self.load(req)?;
-
-            let page_total = page.total;
-            let page_offset = page.offset;
-            let page_limit = page.limit;
-            func(page)?;
-
-            if page_total > offset && offset < self.paginated_limit {
-                limit = page_limit;
-                offset = page_offset + page_limit;
-            } else {
-                break;
-            }
-        }
-        Ok(())
+        for_each_page(
+            self.paginated_limit,
+            |limit, offset| {
+                let req = request
+                    .clone()
+                    .query("limit", &limit.to_string())
+                    .query("offset", &offset.to_string());
+                self.load(req)
+            },
+            func,
+        )
     }
 
     /// Load a paginated result set by sending `request` with added pagination
@@ -203,8 +244,86 @@ impl WebApi {
         Ok(results)
     }
 
-    /// Load local track files from the official client's database.
-    pub fn load_local_tracks(&self, username: &str) {
+    // https://developer.spotify.com/documentation/web-api/reference/#/operations/get-multiple-episodes
+    fn get_episodes(
+        &self,
+        ids: impl IntoIterator<Item = EpisodeId>,
+    ) -> Result<Vector<Arc<Episode>>, Error> {
+        #[derive(Deserialize)]
+        struct Episodes {
+            episodes: Vector<Arc<Episode>>,
+        }
+
+        let request = self
+            .get("v1/episodes")?
+            .query("ids", &ids.into_iter().map(|id| id.0.to_base62()).join(","))
+            .query("market", &self.market());
+        let result: Episodes = self.load(request)?;
+        Ok(result.episodes)
+    }
+
+    /// Load the tracks through the session instead, for when we can't get a token
+    /// for the Web API.  Tracks that fail to load are skipped.
+    fn load_track_fallback(&self, ids: &[TrackId]) -> Result<Vector<Arc<Track>>, Error> {
+        log::warn!("web api unavailable, loading track metadata through the session");
+        let mut results = Vector::new();
+        for id in ids {
+            match mercury::load_track(&self.session, id.0) {
+                Ok(track) => results.push_back(track),
+                Err(err) => log::error!("failed to load track {}: {}", id.0.to_base62(), err),
+            }
+        }
+        if results.is_empty() && !ids.is_empty() {
+            return Err(Error::WebApiError(
+                "Track metadata is unavailable".to_string(),
+            ));
+        }
+        Ok(results)
+    }
+
+    /// Drop the tracks that can't be played in our market, so they don't get
+    /// skipped one after another once queued.  Tracks that don't tell are looked
+    /// up in batches.
+    fn retain_playable(&self, tracks: &mut Vector<Arc<Track>>) {
+        let unknown: Vec<TrackId> = tracks
+            .iter()
+            .filter(|track| track.is_playable.is_none() && !track.is_local)
+            .map(|track| track.id)
+            .collect();
+        // Relinked tracks come back under a different ID, but those are playable,
+        // so only the unplayable ones need to be matched.
+        let unplayable: HashSet<TrackId> = if unknown.is_empty() {
+            HashSet::new()
+        } else {
+            match self.get_tracks(&unknown) {
+                Ok(looked_up) => looked_up
+                    .iter()
+                    .filter(|track| track.is_playable == Some(false))
+                    .map(|track| track.id)
+                    .collect(),
+                Err(err) => {
+                    log::warn!("failed to check track availability: {}", err);
+                    HashSet::new()
+                }
+            }
+        };
+        let count = tracks.len();
+        tracks.retain(|track| track.is_playable != Some(false) && !unplayable.contains(&track.id));
+        if tracks.len() < count {
+            log::info!("dropped {} unplayable tracks", count - tracks.len());
+        }
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/tracks/get-audio-analysis/
+    pub fn _get_audio_analysis(&self, track_id: &str) -> Result<AudioAnalysis, Error> {
+        let request = self.get(format!("v1/audio-analysis/{}", track_id))?;
+        let result = self.load_cached(request, "audio-analysis", track_id)?;
+        Ok(result.data)
+    }
+}
+
+impl WebApi for Web {
+    fn load_local_tracks(&self, username: &str) {
         if let Err(err) = self
             .local_track_manager
             .lock()
@@ -213,47 +332,76 @@ impl WebApi {
             log::error!("failed to read local tracks: {}", err);
         }
     }
-}
 
-static GLOBAL_WEBAPI: OnceCell<Arc<WebApi>> = OnceCell::new();
+    fn set_market(&self, market: Option<String>) {
+        *self.market.lock() = market;
+    }
 
-/// Global instance.
-impl WebApi {
-    pub fn install_as_global(self) {
-        GLOBAL_WEBAPI
-            .set(Arc::new(self))
-            .map_err(|_| "Cannot install more than once")
-            .unwrap()
+    fn collect_cache_garbage(&self) -> usize {
+        self.cache.remove_unreadable()
     }
 
-    pub fn global() -> Arc<Self> {
-        GLOBAL_WEBAPI.get().unwrap().clone()
+    fn set_image_cache_limit(&self, limit: Option<usize>) {
+        self.cache.set_image_limit(limit);
+    }
+
+    fn image_cache_usage(&self) -> CacheUsage {
+        self.cache.image_usage()
     }
-}
 
-/// Other endpoints.
-impl WebApi {
-    pub fn get_user_profile(&self) -> Result<UserProfile, Error> {
+    // Other endpoints.
+
+    fn get_user_profile(&self) -> Result<UserProfile, Error> {
         let request = self.get("v1/me")?;
         let result = self.load(request)?;
         Ok(result)
     }
-}
 
-/// Artist endpoints.
-impl WebApi {
+    // Artist endpoints.
+
     // https://developer.spotify.com/documentation/web-api/reference/artists/get-artist/
-    pub fn get_artist(&self, id: &str) -> Result<Artist, Error> {
+    fn get_artist(&self, id: &str) -> Result<Artist, Error> {
+        // Not cached, the follower counts would go stale.
         let request = self.get(format!("v1/artists/{}", id))?;
-        let result = self.load_cached(request, "artist", id)?;
-        Ok(result.data)
+        let result = self.load(request)?;
+        Ok(result)
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/#endpoint-check-current-user-follows
+    fn is_following_artist(&self, id: &str) -> Result<bool, Error> {
+        let request = self
+            .get("v1/me/following/contains")?
+            .query("type", "artist")
+            .query("ids", id);
+        let result: Vec<bool> = self.load(request)?;
+        Ok(result.first().copied().unwrap_or(false))
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/#endpoint-follow-artists-users
+    fn follow_artist(&self, id: &str) -> Result<(), Error> {
+        let request = self
+            .put("v1/me/following")?
+            .query("type", "artist")
+            .query("ids", id);
+        self.send_empty_json(request)?;
+        Ok(())
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/#endpoint-unfollow-artists-users
+    fn unfollow_artist(&self, id: &str) -> Result<(), Error> {
+        let request = self
+            .delete("v1/me/following")?
+            .query("type", "artist")
+            .query("ids", id);
+        self.send_empty_json(request)?;
+        Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/artists/get-artists-albums/
-    pub fn get_artist_albums(&self, id: &str) -> Result<ArtistAlbums, Error> {
+    fn get_artist_albums(&self, id: &str) -> Result<ArtistAlbums, Error> {
         let request = self
             .get(format!("v1/artists/{}/albums", id))?
-            .query("market", "from_token");
+            .query("market", &self.market());
         let result: Vector<Arc<Album>> = self.load_all_pages(request)?;
 
         let mut artist_albums = ArtistAlbums {
@@ -296,7 +444,7 @@ impl WebApi {
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/artists/get-artists-top-tracks/
-    pub fn get_artist_top_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error> {
+    fn get_artist_top_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error> {
         #[derive(Deserialize)]
         struct Tracks {
             tracks: Vector<Arc<Track>>,
@@ -304,13 +452,13 @@ impl WebApi {
 
         let request = self
             .get(format!("v1/artists/{}/top-tracks", id))?
-            .query("market", "from_token");
+            .query("market", &self.market());
         let result: Tracks = self.load(request)?;
         Ok(result.tracks)
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/artists/get-related-artists/
-    pub fn get_related_artists(&self, id: &str) -> Result<Cached<Vector<Artist>>, Error> {
+    fn get_related_artists(&self, id: &str) -> Result<Cached<Vector<Artist>>, Error> {
         #[derive(Clone, Data, Deserialize)]
         struct Artists {
             artists: Vector<Artist>,
@@ -320,45 +468,50 @@ impl WebApi {
         let result: Cached<Artists> = self.load_cached(request, "related-artists", id)?;
         Ok(result.map(|result| result.artists))
     }
-}
 
-/// Album endpoints.
-impl WebApi {
+    // Album endpoints.
+
     // https://developer.spotify.com/documentation/web-api/reference/albums/get-album/
-    pub fn get_album(&self, id: &str) -> Result<Cached<Arc<Album>>, Error> {
+    fn get_album(&self, id: &str) -> Result<Cached<Arc<Album>>, Error> {
+        let market = self.market();
         let request = self
             .get(format!("v1/albums/{}", id))?
-            .query("market", "from_token");
-        let result = self.load_cached(request, "album", id)?;
-        Ok(result)
+            .query("market", &market);
+        // Availability of the tracks differs between markets.
+        let key = format!("{}-{}", id, market);
+        self.load_cached_with("album", &key, || {
+            let mut album: serde_json::Value = self.load(request)?;
+            let total_tracks = album["total_tracks"].as_u64().unwrap_or(0) as usize;
+            let embedded_tracks = album["tracks"]["items"].as_array().map_or(0, Vec::len);
+            if embedded_tracks < total_tracks {
+                // The album only embeds the first page of its tracks, load the rest and
+                // cache them together.
+                let request = self
+                    .get(format!("v1/albums/{}/tracks", id))?
+                    .query("market", &market);
+                let tracks: Vector<serde_json::Value> = self.load_all_pages(request)?;
+                album["tracks"]["items"] = tracks.into_iter().collect();
+            }
+            Ok(serde_json::to_vec(&album)?)
+        })
     }
-}
-
-/// Show endpoints. (Podcasts)
-impl WebApi {
-    // https://developer.spotify.com/documentation/web-api/reference/#/operations/get-multiple-episodes
-    pub fn get_episodes(
-        &self,
-        ids: impl IntoIterator<Item = EpisodeId>,
-    ) -> Result<Vector<Arc<Episode>>, Error> {
-        #[derive(Deserialize)]
-        struct Episodes {
-            episodes: Vector<Arc<Episode>>,
-        }
 
+    // https://developer.spotify.com/documentation/web-api/reference/#/operations/get-an-albums-tracks
+    fn get_album_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error> {
         let request = self
-            .get("v1/episodes")?
-            .query("ids", &ids.into_iter().map(|id| id.0.to_base62()).join(","))
-            .query("market", "from_token");
-        let result: Episodes = self.load(request)?;
-        Ok(result.episodes)
+            .get(format!("v1/albums/{}/tracks", id))?
+            .query("market", &self.market());
+        let result = self.load_all_pages(request)?;
+        Ok(result)
     }
 
+    // Show endpoints. (Podcasts)
+
     // https://developer.spotify.com/documentation/web-api/reference/#/operations/get-a-shows-episodes
-    pub fn get_show_episodes(&self, id: &str) -> Result<Vector<Arc<Episode>>, Error> {
+    fn get_show_episodes(&self, id: &str) -> Result<Vector<Arc<Episode>>, Error> {
         let request = self
             .get(format!("v1/shows/{}/episodes", id))?
-            .query("market", "from_token");
+            .query("market", &self.market());
         let mut results = Vector::new();
 
         self.for_all_pages(request, |page: Page<EpisodeLink>| {
@@ -372,30 +525,95 @@ impl WebApi {
 
         Ok(results)
     }
-}
 
-/// Track endpoints.
-impl WebApi {
+    // Track endpoints.
+
     // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-track
-    pub fn get_track(&self, id: &str) -> Result<Arc<Track>, Error> {
-        let request = self
-            .get(format!("v1/tracks/{}", id))?
-            .query("market", "from_token");
+    fn get_track(&self, id: &str) -> Result<Arc<Track>, Error> {
+        let request = match self.get(format!("v1/tracks/{}", id)) {
+            Ok(request) => request.query("market", &self.market()),
+            Err(err) => {
+                let item_id = ItemId::from_base62(id, ItemIdType::Track).ok_or(err)?;
+                return self
+                    .load_track_fallback(&[TrackId(item_id)])?
+                    .pop_front()
+                    .ok_or_else(|| Error::WebApiError("Track not found".to_string()));
+            }
+        };
         let result = self.load(request)?;
         Ok(result)
     }
-}
 
-/// Library endpoints.
-impl WebApi {
+    // https://developer.spotify.com/documentation/web-api/reference/#/operations/get-several-tracks
+    fn get_tracks(&self, ids: &[TrackId]) -> Result<Vector<Arc<Track>>, Error> {
+        #[derive(Deserialize)]
+        struct Tracks {
+            tracks: Vector<Option<Arc<Track>>>,
+        }
+
+        const MAX_IDS_PER_REQUEST: usize = 50;
+
+        let mut results = Vector::new();
+        for chunk in ids.chunks(MAX_IDS_PER_REQUEST) {
+            let request = match self.get("v1/tracks") {
+                Ok(request) => request,
+                Err(_) => return self.load_track_fallback(ids),
+            };
+            let request = request
+                .query("ids", &chunk.iter().map(|id| id.0.to_base62()).join(","))
+                .query("market", &self.market());
+            let result: Tracks = self.load(request)?;
+            // Unknown IDs come back as nulls.
+            results.extend(result.tracks.into_iter().flatten());
+        }
+        Ok(results)
+    }
+
+    // Not part of the Web API, the official clients load the canvas from the
+    // `spclient` service with the same access token.
+    fn get_canvas(&self, id: TrackId) -> Result<Option<Canvas>, Error> {
+        let request = EntityCanvazRequest {
+            entities: vec![Entity {
+                entity_uri: id.0.to_uri(),
+                etag: None,
+            }],
+        };
+        let body =
+            serialize_protobuf(&request).map_err(|err| Error::WebApiError(err.to_string()))?;
+        let token = self.access_token()?;
+        let response = self
+            .agent
+            .post("https://spclient.wg.spotify.com/canvaz-cache/v0/canvases")
+            .set("Authorization", &format!("Bearer {}", &token))
+            .set("Content-Type", "application/x-protobuf")
+            .send_bytes(&body)?;
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body)?;
+        let response: EntityCanvazResponse =
+            deserialize_protobuf(&body).map_err(|err| Error::WebApiError(err.to_string()))?;
+        Ok(response.canvases.into_iter().find_map(|canvas| {
+            // The service leaves out the zero values, i.e. `IMAGE`.
+            match canvas.type_pb.unwrap_or_default() {
+                Type::IMAGE => Some(Canvas {
+                    url: canvas.url?.into(),
+                }),
+                // We can't decode videos, and animated images would only show their
+                // first frame, so these are never downloaded.
+                Type::VIDEO | Type::VIDEO_LOOPING | Type::VIDEO_LOOPING_RANDOM | Type::GIF => None,
+            }
+        }))
+    }
+
+    // Library endpoints.
+
     // https://developer.spotify.com/documentation/web-api/reference/library/get-users-saved-albums/
-    pub fn get_saved_albums(&self) -> Result<Vector<Arc<Album>>, Error> {
+    fn get_saved_albums(&self) -> Result<Vector<Arc<Album>>, Error> {
         #[derive(Clone, Deserialize)]
         struct SavedAlbum {
             album: Arc<Album>,
         }
 
-        let request = self.get("v1/me/albums")?.query("market", "from_token");
+        let request = self.get("v1/me/albums")?.query("market", &self.market());
 
         Ok(self
             .load_all_pages(request)?
@@ -405,27 +623,27 @@ impl WebApi {
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/save-albums-user/
-    pub fn save_album(&self, id: &str) -> Result<(), Error> {
+    fn save_album(&self, id: &str) -> Result<(), Error> {
         let request = self.put("v1/me/albums")?.query("ids", id);
         self.send_empty_json(request)?;
         Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/remove-albums-user/
-    pub fn unsave_album(&self, id: &str) -> Result<(), Error> {
+    fn unsave_album(&self, id: &str) -> Result<(), Error> {
         let request = self.delete("v1/me/albums")?.query("ids", id);
         self.send_empty_json(request)?;
         Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/get-users-saved-tracks/
-    pub fn get_saved_tracks(&self) -> Result<Vector<Arc<Track>>, Error> {
+    fn get_saved_tracks(&self) -> Result<Vector<Arc<Track>>, Error> {
         #[derive(Clone, Deserialize)]
         struct SavedTrack {
             track: Arc<Track>,
         }
 
-        let request = self.get("v1/me/tracks")?.query("market", "from_token");
+        let request = self.get("v1/me/tracks")?.query("market", &self.market());
 
         Ok(self
             .load_all_pages(request)?
@@ -435,13 +653,13 @@ impl WebApi {
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/#/operations/get-users-saved-shows
-    pub fn get_saved_shows(&self) -> Result<Vector<Arc<Show>>, Error> {
+    fn get_saved_shows(&self) -> Result<Vector<Arc<Show>>, Error> {
         #[derive(Clone, Deserialize)]
         struct SavedShow {
             show: Arc<Show>,
         }
 
-        let request = self.get("v1/me/shows")?.query("market", "from_token");
+        let request = self.get("v1/me/shows")?.query("market", &self.market());
 
         Ok(self
             .load_all_pages(request)?
@@ -451,37 +669,70 @@ impl WebApi {
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/save-tracks-user/
-    pub fn save_track(&self, id: &str) -> Result<(), Error> {
+    fn save_track(&self, id: &str) -> Result<(), Error> {
         let request = self.put("v1/me/tracks")?.query("ids", id);
         self.send_empty_json(request)?;
         Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/library/remove-tracks-user/
-    pub fn unsave_track(&self, id: &str) -> Result<(), Error> {
+    fn unsave_track(&self, id: &str) -> Result<(), Error> {
         let request = self.delete("v1/me/tracks")?.query("ids", id);
         self.send_empty_json(request)?;
         Ok(())
     }
 
+    // https://developer.spotify.com/documentation/web-api/reference/#/operations/save-tracks-user
+    fn save_tracks(&self, ids: &[TrackId], on_chunk: &mut dyn FnMut(usize)) -> Result<(), Error> {
+        for chunk in ids.chunks(LIBRARY_IDS_PER_REQUEST) {
+            let ids = chunk.iter().map(|id| id.0.to_base62()).join(",");
+            let request = self.put("v1/me/tracks")?.query("ids", &ids);
+            self.send_empty_json(request)?;
+            on_chunk(chunk.len());
+        }
+        Ok(())
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-tracks-user
+    fn unsave_tracks(&self, ids: &[TrackId], on_chunk: &mut dyn FnMut(usize)) -> Result<(), Error> {
+        for chunk in ids.chunks(LIBRARY_IDS_PER_REQUEST) {
+            let ids = chunk.iter().map(|id| id.0.to_base62()).join(",");
+            let request = self.delete("v1/me/tracks")?.query("ids", &ids);
+            self.send_empty_json(request)?;
+            on_chunk(chunk.len());
+        }
+        Ok(())
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/#/operations/check-users-saved-tracks
+    fn contains_saved_tracks(&self, ids: &[TrackId]) -> Result<Vec<bool>, Error> {
+        let mut results = Vec::with_capacity(ids.len());
+        for chunk in ids.chunks(LIBRARY_IDS_PER_REQUEST) {
+            let ids = chunk.iter().map(|id| id.0.to_base62()).join(",");
+            let request = self.get("v1/me/tracks/contains")?.query("ids", &ids);
+            let result: Vec<bool> = self.load(request)?;
+            results.extend(result);
+        }
+        Ok(results)
+    }
+
     // https://developer.spotify.com/documentation/web-api/reference/#/operations/save-shows-user
-    pub fn save_show(&self, id: &str) -> Result<(), Error> {
+    fn save_show(&self, id: &str) -> Result<(), Error> {
         let request = self.put("v1/me/shows")?.query("ids", id);
         self.send_empty_json(request)?;
         Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-shows-user
-    pub fn unsave_show(&self, id: &str) -> Result<(), Error> {
+    fn unsave_show(&self, id: &str) -> Result<(), Error> {
         let request = self.delete("v1/me/shows")?.query("ids", id);
         self.send_empty_json(request)?;
         Ok(())
     }
-}
 
-/// View endpoints.
-impl WebApi {
-    pub fn get_made_for_you(&self) -> Result<Vector<Playlist>, Error> {
+    // View endpoints.
+
+    fn get_made_for_you(&self) -> Result<Vector<Playlist>, Error> {
         #[derive(Deserialize)]
         struct View {
             content: Page<Playlist>,
@@ -495,96 +746,121 @@ impl WebApi {
         let result: View = self.load(request)?;
         Ok(result.content.items)
     }
-}
 
-/// Playlist endpoints.
-impl WebApi {
+    // Playlist endpoints.
+
     // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-a-list-of-current-users-playlists
-    pub fn get_playlists(&self) -> Result<Vector<Playlist>, Error> {
+    fn get_playlists(&self) -> Result<Vector<Playlist>, Error> {
         let request = self.get("v1/me/playlists")?;
         let result = self.load_all_pages(request)?;
         Ok(result)
     }
 
-    pub fn follow_playlist(&self, id: &str) -> Result<(), Error> {
+    fn follow_playlist(&self, id: &str) -> Result<(), Error> {
         let request = self.put(format!("v1/playlists/{}/followers", id))?;
         request.send_json(json!({"public": false,}))?;
         Ok(())
     }
 
-    pub fn unfollow_playlist(&self, id: &str) -> Result<(), Error> {
+    fn unfollow_playlist(&self, id: &str) -> Result<(), Error> {
         let request = self.delete(format!("v1/playlists/{}/followers", id))?;
         self.send_empty_json(request)?;
         Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-playlist
-    pub fn get_playlist(&self, id: &str) -> Result<Playlist, Error> {
-        let request = self.get(format!("v1/me/playlists/{}", id))?;
+    fn get_playlist(&self, id: &str) -> Result<Playlist, Error> {
+        let request = self.get(format!("v1/playlists/{}", id))?;
         let result = self.load(request)?;
         Ok(result)
     }
 
-    // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-playlists-tracks
-    pub fn get_playlist_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error> {
-        #[derive(Clone, Deserialize)]
-        struct PlaylistItem {
-            track: OptionalTrack,
-        }
-
-        // Spotify API likes to return _really_ bogus data for local tracks. Much better
-        // would be to ignore parsing this completely if `is_local` is true, but this
-        // will do as well.
-        #[derive(Clone, Deserialize)]
-        #[serde(untagged)]
-        enum OptionalTrack {
-            Track(Arc<Track>),
-            Json(serde_json::Value),
-        }
+    fn get_playlist_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error> {
+        let mut results = Vector::new();
+        self.get_playlist_tracks_paged(id, &mut |tracks, _| results.append(tracks))?;
+        Ok(results)
+    }
 
+    // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-playlists-tracks
+    fn get_playlist_tracks_paged(
+        &self,
+        id: &str,
+        on_page: &mut dyn FnMut(Vector<Arc<Track>>, LoadProgress),
+    ) -> Result<(), Error> {
         let request = self
             .get(format!("v1/playlists/{}/tracks", id))?
-            .query("marker", "from_token")
+            .query("market", &self.market())
             .query("additional_types", "track");
-        let result: Vector<PlaylistItem> = self.load_all_pages(request)?;
 
-        let local_track_manager = self.local_track_manager.lock();
+        self.for_all_pages(request, |page: Page<PlaylistItem>| {
+            let progress = LoadProgress {
+                loaded: page.offset + page.items.len(),
+                total: page.total,
+            };
+            let tracks = {
+                let local_track_manager = self.local_track_manager.lock();
+                page.items
+                    .into_iter()
+                    .filter_map(|item| match item {
+                        PlaylistItem {
+                            track: OptionalTrack::Track(track),
+                            ..
+                        } => Some(track),
+                        PlaylistItem {
+                            track: OptionalTrack::Json(track),
+                            ..
+                        } => local_track_manager.find_local_track(track),
+                    })
+                    .collect()
+            };
+            on_page(tracks, progress);
+            Ok(())
+        })
+    }
 
-        Ok(result
-            .into_iter()
-            .filter_map(|item| match item {
-                PlaylistItem {
-                    track: OptionalTrack::Track(track),
-                    ..
-                } => Some(track),
-                PlaylistItem {
-                    track: OptionalTrack::Json(track),
-                    ..
-                } => local_track_manager.find_local_track(track),
-            })
-            .collect())
+    // https://developer.spotify.com/documentation/web-api/reference/#/operations/create-playlist
+    fn create_playlist(&self, name: &str) -> Result<Playlist, Error> {
+        let request = self.post("v1/me/playlists")?;
+        let response = Self::with_retry(|| {
+            Ok(request.clone().send_json(json!({
+                "name": name,
+                "description": "",
+                "public": false,
+            }))?)
+        })?;
+        let result = response.into_json()?;
+        Ok(result)
     }
 
-    pub fn change_playlist_details(&self, id: &str, name: &str) -> Result<(), Error> {
+    fn change_playlist_details(&self, id: &str, name: &str) -> Result<(), Error> {
         let request = self.put(format!("v1/playlists/{}", id))?;
         request.send_json(json!({ "name": name }))?;
         Ok(())
     }
 
     // https://developer.spotify.com/documentation/web-api/reference/#endpoint-add-tracks-to-playlist
-    pub fn add_track_to_playlist(&self, playlist_id: &str, track_uri: &str) -> Result<(), Error> {
+    fn add_track_to_playlist(&self, playlist_id: &str, track_uri: &str) -> Result<(), Error> {
         let request = self
             .post(format!("v1/playlists/{}/tracks", playlist_id))?
             .query("uris", track_uri);
         self.send_empty_json(request)
     }
 
-    // https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-tracks-playlist
-    pub fn remove_track_from_playlist(
+    fn add_tracks_to_playlist(
         &self,
         playlist_id: &str,
-        track_uri: &str,
+        track_uris: &[Arc<str>],
     ) -> Result<(), Error> {
+        // The endpoint takes at most 100 items per request.
+        for chunk in track_uris.chunks(100) {
+            let request = self.post(format!("v1/playlists/{}/tracks", playlist_id))?;
+            Self::with_retry(|| Ok(request.clone().send_json(json!({ "uris": chunk }))?))?;
+        }
+        Ok(())
+    }
+
+    // https://developer.spotify.com/documentation/web-api/reference/#/operations/remove-tracks-playlist
+    fn remove_track_from_playlist(&self, playlist_id: &str, track_uri: &str) -> Result<(), Error> {
         self.delete(format!("v1/playlists/{}/tracks", playlist_id))?
             .send_json(ureq::json!({
                 "tracks": [{
@@ -594,12 +870,24 @@ impl WebApi {
 
         Ok(())
     }
-}
 
-/// Search endpoints.
-impl WebApi {
+    // https://developer.spotify.com/documentation/web-api/reference/#/operations/reorder-or-replace-playlists-tracks
+    fn replace_playlist_tracks(
+        &self,
+        playlist_id: &str,
+        track_uris: &[Arc<str>],
+    ) -> Result<(), Error> {
+        // Replacing takes at most 100 items too, the rest is added afterwards.
+        let (first, rest) = track_uris.split_at(track_uris.len().min(100));
+        let request = self.put(format!("v1/playlists/{}/tracks", playlist_id))?;
+        Self::with_retry(|| Ok(request.clone().send_json(json!({ "uris": first }))?))?;
+        self.add_tracks_to_playlist(playlist_id, rest)
+    }
+
+    // Search endpoints.
+
     // https://developer.spotify.com/documentation/web-api/reference/search/
-    pub fn search(
+    fn search(
         &self,
         query: &str,
         topics: &[SearchTopic],
@@ -620,7 +908,7 @@ impl WebApi {
             .query("q", query)
             .query("type", &topics)
             .query("limit", &limit.to_string())
-            .query("marker", "from_token");
+            .query("market", &self.market());
         let result: ApiSearchResults = self.load(request)?;
 
         let artists = result.artists.map_or_else(Vector::new, |page| page.items);
@@ -638,13 +926,16 @@ impl WebApi {
         })
     }
 
-    pub fn load_spotify_link(&self, link: &SpotifyUrl) -> Result<Nav, Error> {
+    fn load_spotify_link(&self, link: &SpotifyUri) -> Result<Nav, Error> {
         let nav = match link {
-            SpotifyUrl::Playlist(id) => Nav::PlaylistDetail(self.get_playlist(id)?.link()),
-            SpotifyUrl::Artist(id) => Nav::ArtistDetail(self.get_artist(id)?.link()),
-            SpotifyUrl::Album(id) => Nav::AlbumDetail(self.get_album(id)?.data.link()),
-            SpotifyUrl::Show(id) => Nav::AlbumDetail(self.get_album(id)?.data.link()),
-            SpotifyUrl::Track(id) => Nav::AlbumDetail(
+            SpotifyUri::Playlist(id) => Nav::PlaylistDetail(self.get_playlist(id)?.link()),
+            SpotifyUri::Artist(id) => Nav::ArtistDetail(self.get_artist(id)?.link()),
+            SpotifyUri::Album(id) => Nav::AlbumDetail(self.get_album(id)?.data.link()),
+            SpotifyUri::Show(id) => Nav::AlbumDetail(self.get_album(id)?.data.link()),
+            SpotifyUri::Episode(_) | SpotifyUri::User(_) => {
+                return Err(Error::WebApiError(format!("Can't open {} yet", link)));
+            }
+            SpotifyUri::Track(id) => Nav::AlbumDetail(
                 // TODO: We should highlight the exact track in the album.
                 self.get_track(id)?.album.clone().ok_or_else(|| {
                     Error::WebApiError("Track was found but has no album".to_string())
@@ -653,12 +944,11 @@ impl WebApi {
         };
         Ok(nav)
     }
-}
 
-/// Recommendation endpoints.
-impl WebApi {
+    // Recommendation endpoints.
+
     // https://developer.spotify.com/documentation/web-api/reference/#endpoint-get-recommendations
-    pub fn get_recommendations(
+    fn get_recommendations(
         &self,
         data: Arc<RecommendationsRequest>,
     ) -> Result<Recommendations, Error> {
@@ -668,13 +958,15 @@ impl WebApi {
             .iter()
             .map(|track| track.0.to_base62())
             .join(", ");
+        let seed_genres = data.seed_genres.iter().join(", ");
 
         let mut request = self
             .get("v1/recommendations")?
-            .query("marker", "from_token")
+            .query("market", &self.market())
             .query("limit", "100")
             .query("seed_artists", &seed_artists)
-            .query("seed_tracks", &seed_tracks);
+            .query("seed_tracks", &seed_tracks)
+            .query("seed_genres", &seed_genres);
 
         fn add_range_param(mut req: Request, r: Range<impl ToString>, s: &str) -> Request {
             if let Some(v) = r.min {
@@ -704,28 +996,71 @@ impl WebApi {
         request = add_range_param(request, data.params.valence, "valence");
 
         let mut result: Recommendations = self.load(request)?;
+        self.retain_playable(&mut result.tracks);
+        if data.params.exclude_explicit {
+            result.tracks.retain(|track| !track.explicit);
+        }
         result.request = data;
         Ok(result)
     }
-}
 
-/// Track endpoints.
-impl WebApi {
-    // https://developer.spotify.com/documentation/web-api/reference/tracks/get-audio-analysis/
-    pub fn _get_audio_analysis(&self, track_id: &str) -> Result<AudioAnalysis, Error> {
-        let request = self.get(format!("v1/audio-analysis/{}", track_id))?;
-        let result = self.load_cached(request, "audio-analysis", track_id)?;
-        Ok(result.data)
+    // MusicBrainz endpoints.
+
+    // https://musicbrainz.org/doc/MusicBrainz_API/Search#Release
+    fn get_musicbrainz_release(&self, album: &Album) -> Result<Option<MusicBrainzRelease>, Error> {
+        #[derive(Clone, Data, Deserialize)]
+        struct Releases {
+            releases: Vector<ReleaseId>,
+        }
+
+        #[derive(Clone, Data, Deserialize)]
+        struct ReleaseId {
+            id: Arc<str>,
+        }
+
+        let upc = match &album.external_ids.upc {
+            Some(upc) => upc.as_ref(),
+            None => return Ok(None),
+        };
+        // A 12-digit UPC is often listed as EAN-13 with a leading zero, or the
+        // other way around.
+        let other_form = match upc.strip_prefix('0') {
+            Some(upc) => upc.to_string(),
+            None => format!("0{}", upc),
+        };
+        let request = self
+            .musicbrainz("release")
+            .query(
+                "query",
+                &format!("barcode:{} OR barcode:{}", upc, other_form),
+            )
+            .query("limit", "1");
+        let mut result: Cached<Releases> =
+            self.load_cached(request.clone(), "musicbrainz-barcode", upc)?;
+        if result.data.releases.is_empty() && result.is_older_than(MUSICBRAINZ_MISS_TTL) {
+            self.cache.remove("musicbrainz-barcode", upc);
+            result = self.load_cached(request, "musicbrainz-barcode", upc)?;
+        }
+        let id = match result.data.releases.front() {
+            Some(release) => release.id.clone(),
+            None => return Ok(None),
+        };
+
+        let request = self
+            .musicbrainz(format!("release/{}", id))
+            .query("inc", "labels release-groups url-rels");
+        let result: Cached<MusicBrainzRelease> =
+            self.load_cached(request, "musicbrainz-release", &id)?;
+        Ok(Some(result.data))
     }
-}
 
-/// Image endpoints.
-impl WebApi {
-    pub fn get_cached_image(&self, uri: &Arc<str>) -> Option<ImageBuf> {
+    // Image endpoints.
+
+    fn get_cached_image(&self, uri: &Arc<str>) -> Option<ImageBuf> {
         self.cache.get_image(uri)
     }
 
-    pub fn get_image(&self, uri: Arc<str>) -> Result<ImageBuf, Error> {
+    fn get_image(&self, uri: Arc<str>) -> Result<ImageBuf, Error> {
         let response = self.agent.get(&uri).call()?;
         let format = match response.content_type() {
             "image/jpeg" => Some(ImageFormat::Jpeg),
@@ -745,6 +1080,49 @@ impl WebApi {
     }
 }
 
+/// Load the pages of a result set with `load`, given the limit and offset of each,
+/// until all of it or at least `paginated_limit` items are loaded.
+fn for_each_page<T: Clone>(
+    paginated_limit: usize,
+    mut load: impl FnMut(usize, usize) -> Result<Page<T>, Error>,
+    mut func: impl FnMut(Page<T>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut limit = 50;
+    let mut offset = 0;
+    loop {
+        let page = load(limit, offset)?;
+
+        let page_total = page.total;
+        let page_offset = page.offset;
+        let page_limit = page.limit;
+        func(page)?;
+
+        let next_offset = page_offset + page_limit;
+        if page_limit > 0 && next_offset < page_total && next_offset < paginated_limit {
+            limit = page_limit;
+            offset = next_offset;
+        } else {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Deserialize)]
+struct PlaylistItem {
+    track: OptionalTrack,
+}
+
+// Spotify API likes to return _really_ bogus data for local tracks. Much better
+// would be to ignore parsing this completely if `is_local` is true, but this
+// will do as well.
+#[derive(Clone, Deserialize)]
+#[serde(untagged)]
+enum OptionalTrack {
+    Track(Arc<Track>),
+    Json(serde_json::Value),
+}
+
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
         Error::WebApiError(err.to_string())
@@ -753,7 +1131,12 @@ impl From<io::Error> for Error {
 
 impl From<ureq::Error> for Error {
     fn from(err: ureq::Error) -> Self {
-        Error::WebApiError(err.to_string())
+        match net::certificate_rejection(&err) {
+            Some(reason) => {
+                Error::WebApiError(psst_core::error::Error::CertificateRejected(reason).to_string())
+            }
+            None => Error::WebApiError(err.to_string()),
+        }
     }
 }
 
@@ -768,3 +1151,116 @@ impl From<image::ImageError> for Error {
         Error::WebApiError(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::utils::load_fixture;
+
+    /// Serve `total` numbers in pages of at most `max_limit`, recording the
+    /// requested limits and offsets.
+    fn load_numbers(
+        total: usize,
+        max_limit: usize,
+        paginated_limit: usize,
+    ) -> (Vector<usize>, Vec<(usize, usize)>) {
+        let mut requests = Vec::new();
+        let mut results = Vector::new();
+        for_each_page(
+            paginated_limit,
+            |limit, offset| {
+                requests.push((limit, offset));
+                let limit = limit.min(max_limit);
+                Ok(Page {
+                    items: (offset..total.min(offset + limit)).collect(),
+                    limit,
+                    offset,
+                    total,
+                })
+            },
+            |page| {
+                results.append(page.items);
+                Ok(())
+            },
+        )
+        .unwrap();
+        (results, requests)
+    }
+
+    #[test]
+    fn loads_all_pages_in_order() {
+        let (results, requests) = load_numbers(120, 50, 500);
+
+        assert_eq!(results, (0..120).collect());
+        assert_eq!(requests, [(50, 0), (50, 50), (50, 100)]);
+    }
+
+    #[test]
+    fn loads_single_page_of_empty_result() {
+        let (results, requests) = load_numbers(0, 50, 500);
+
+        assert!(results.is_empty());
+        assert_eq!(requests, [(50, 0)]);
+    }
+
+    #[test]
+    fn follows_page_size_of_the_server() {
+        let (results, requests) = load_numbers(45, 20, 500);
+
+        assert_eq!(results, (0..45).collect());
+        assert_eq!(requests, [(50, 0), (20, 20), (20, 40)]);
+    }
+
+    #[test]
+    fn stops_at_paginated_limit() {
+        let (results, requests) = load_numbers(1000, 50, 120);
+
+        assert_eq!(results, (0..150).collect());
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[test]
+    fn stops_on_error() {
+        let mut pages = 0;
+        let result = for_each_page(
+            500,
+            |_, offset| {
+                if offset > 0 {
+                    return Err(Error::WebApiError("Failed".to_string()));
+                }
+                Ok(Page {
+                    items: Vector::unit(0),
+                    limit: 1,
+                    offset,
+                    total: 10,
+                })
+            },
+            |_| {
+                pages += 1;
+                Ok(())
+            },
+        );
+
+        assert!(result.is_err());
+        assert_eq!(pages, 1);
+    }
+
+    #[test]
+    fn playlist_items_keep_local_and_removed_tracks_as_json() {
+        let page: Page<PlaylistItem> = load_fixture("playlist_tracks.json");
+
+        assert_eq!(page.total, 3);
+        assert!(matches!(
+            &page.items[0].track,
+            OptionalTrack::Track(track) if track.id.0.to_base62() == "4rzfv0JLZfVhOhbSQ8o5jZ"
+        ));
+        assert!(matches!(
+            &page.items[1].track,
+            OptionalTrack::Json(json) if json["is_local"] == true && json["id"].is_null()
+        ));
+        assert!(matches!(
+            &page.items[2].track,
+            OptionalTrack::Json(serde_json::Value::Null)
+        ));
+    }
+}
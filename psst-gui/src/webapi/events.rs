@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use druid::im::Vector;
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    data::{ArtistEvent, ArtistLink},
+    error::Error,
+};
+
+use super::bandsintown::Bandsintown;
+
+/// Source of the upcoming concerts shown on the artist pages.  Spotify doesn't
+/// expose them, so they come from a third party service the user signs up for.
+pub trait EventsProvider: Send + Sync {
+    /// Upcoming events of the artist, soonest first.
+    fn upcoming_events(&self, artist: &ArtistLink) -> Result<Vector<ArtistEvent>, Error>;
+}
+
+static EVENTS_PROVIDER: RwLock<Option<Arc<dyn EventsProvider>>> = const_rwlock(None);
+
+/// Replace the events provider, `None` hides the shelf.
+pub fn install_events_provider(provider: Option<Arc<dyn EventsProvider>>) {
+    *EVENTS_PROVIDER.write() = provider;
+}
+
+/// Install the provider for the given Bandsintown app ID, if any.
+pub fn configure_events(app_id: Option<&str>, proxy_url: Option<&str>) {
+    install_events_provider(
+        app_id
+            .map(|app_id| Arc::new(Bandsintown::new(app_id, proxy_url)) as Arc<dyn EventsProvider>),
+    );
+}
+
+pub fn events_provider() -> Option<Arc<dyn EventsProvider>> {
+    EVENTS_PROVIDER.read().clone()
+}
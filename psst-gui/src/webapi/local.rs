@@ -14,7 +14,9 @@ use druid::im::Vector;
 use serde::Deserialize;
 use serde_json::Value;
 
-use crate::data::{config::Config, AlbumLink, ArtistLink, Image, Track, TrackId};
+use crate::data::{
+    config::Config, is_supported_file, AlbumLink, ArtistLink, Image, Track, TrackId,
+};
 use psst_core::item_id::ItemId;
 
 /**
@@ -147,6 +149,7 @@ impl LocalTrackManager {
             };
 
             if Self::is_matching_in_addition_to_title(parsed_track, &local_track) {
+                let is_playable = is_supported_file(&path);
                 return Some(Arc::new(Track {
                     id: TrackId(ItemId::from_local(path)),
                     name: local_track.name,
@@ -171,8 +174,9 @@ impl LocalTrackManager {
                     explicit: local_track.explicit,
                     is_local: local_track.is_local,
                     local_path: Some(parsed_track.path.clone()),
-                    // TODO: Change this to true once playback is supported.
-                    is_playable: Some(false),
+                    catalog_id: None,
+                    is_playable: Some(is_playable),
+                    restrictions: None,
                     popularity: local_track.popularity,
                 }));
             }
@@ -402,3 +406,24 @@ pub fn advance_until(f: &mut impl io::Read, bytes: &[u8]) -> io::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{utils::load_fixture, Page};
+
+    #[test]
+    fn local_track_json_accepts_missing_ids() {
+        let page: Page<Value> = load_fixture("playlist_tracks.json");
+        let track: LocalTrackJson = serde_json::from_value(page.items[1]["track"].clone()).unwrap();
+
+        assert_eq!(&*track.name, "First Take");
+        assert!(track.is_local);
+        assert_eq!(track.duration, Duration::from_secs(215));
+        let album = track.album.unwrap();
+        assert!(album.id.is_none());
+        assert_eq!(&*album.name, "Demos");
+        assert!(track.artists[0].id.is_none());
+        assert_eq!(&*track.artists[0].name, "The Garage Band");
+    }
+}
@@ -0,0 +1,83 @@
+//! Fallback for when the Web API can't be used, e.g. because no access token can
+//! be obtained.  The session's metadata endpoints carry the basics needed for
+//! playback, so things like the playability or the artist images are left out.
+
+use std::{sync::Arc, time::Duration};
+
+use druid::im::Vector;
+use psst_core::{
+    item_id::{FileId, ItemId, ItemIdType},
+    metadata::Fetch,
+    protocol::metadata,
+    session::SessionService,
+};
+
+use crate::{
+    data::{AlbumLink, ArtistLink, Image, Track, TrackId},
+    error::Error,
+};
+
+pub fn load_track(session: &SessionService, id: ItemId) -> Result<Arc<Track>, Error> {
+    let track =
+        metadata::Track::fetch(session, id).map_err(|err| Error::WebApiError(err.to_string()))?;
+    convert_track(track)
+        .map(Arc::new)
+        .ok_or_else(|| Error::WebApiError("Incomplete track metadata".to_string()))
+}
+
+fn convert_track(track: metadata::Track) -> Option<Track> {
+    let id = ItemId::from_raw(track.gid.as_ref()?, ItemIdType::Track)?;
+    Some(Track {
+        id: TrackId(id),
+        name: track.name?.into(),
+        album: track.album.and_then(convert_album),
+        artists: track
+            .artist
+            .into_iter()
+            .filter_map(convert_artist)
+            .collect(),
+        duration: Duration::from_millis(track.duration.unwrap_or(0) as u64),
+        disc_number: track.disc_number.unwrap_or(1) as usize,
+        track_number: track.number.unwrap_or(0) as usize,
+        explicit: track.explicit.unwrap_or(false),
+        is_local: false,
+        local_path: None,
+        catalog_id: None,
+        is_playable: None,
+        restrictions: None,
+        popularity: track.popularity.map(|popularity| popularity as u32),
+    })
+}
+
+fn convert_album(album: metadata::Album) -> Option<AlbumLink> {
+    let id = ItemId::from_raw(album.gid.as_ref()?, ItemIdType::Unknown)?;
+    let images = album
+        .cover_group
+        .map(|group| group.image)
+        .unwrap_or(album.cover)
+        .into_iter()
+        .filter_map(convert_image)
+        .collect::<Vector<_>>();
+    Some(AlbumLink {
+        id: id.to_base62().into(),
+        name: album.name?.into(),
+        images,
+    })
+}
+
+fn convert_artist(artist: metadata::Artist) -> Option<ArtistLink> {
+    let id = ItemId::from_raw(artist.gid.as_ref()?, ItemIdType::Unknown)?;
+    Some(ArtistLink {
+        id: id.to_base62().into(),
+        name: artist.name?.into(),
+    })
+}
+
+fn convert_image(image: metadata::Image) -> Option<Image> {
+    let file_id = FileId::from_raw(image.file_id.as_ref()?)?;
+    Some(Image {
+        url: format!("https://i.scdn.co/image/{}", file_id.to_base16()).into(),
+        width: image.width.map(|width| width as usize),
+        height: image.height.map(|height| height as usize),
+    })
+}
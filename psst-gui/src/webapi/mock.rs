@@ -0,0 +1,333 @@
+//! In-memory `WebApi` for the tests.  It knows the tracks and playlists it was
+//! set up with and keeps the library of the user, everything else fails with a
+//! "not mocked" error.
+
+use std::{collections::HashMap, sync::Arc};
+
+use druid::{im::Vector, ImageBuf};
+use parking_lot::Mutex;
+use psst_core::uri::SpotifyUri;
+
+use crate::{
+    data::{
+        Album, Artist, ArtistAlbums, CacheUsage, Cached, Canvas, Episode, LoadProgress,
+        MusicBrainzRelease, Nav, Playlist, Recommendations, RecommendationsRequest, SearchResults,
+        SearchTopic, Show, Track, TrackId, UserProfile,
+    },
+    error::Error,
+};
+
+use super::{client::LIBRARY_IDS_PER_REQUEST, WebApi};
+
+#[derive(Default)]
+pub struct MockWebApi {
+    tracks: HashMap<TrackId, Arc<Track>>,
+    playlists: Vec<(Playlist, Vector<Arc<Track>>)>,
+    saved_tracks: Mutex<Vec<TrackId>>,
+    /// Library requests left before they start failing, `None` to never fail.
+    requests_until_failure: Mutex<Option<usize>>,
+}
+
+impl MockWebApi {
+    pub fn with_playlist(mut self, playlist: Playlist, tracks: Vector<Arc<Track>>) -> Self {
+        for track in &tracks {
+            self.tracks.insert(track.id, track.clone());
+        }
+        self.playlists.push((playlist, tracks));
+        self
+    }
+
+    /// Let the library requests fail after `count` of them succeeded.
+    pub fn fail_after_requests(self, count: usize) -> Self {
+        *self.requests_until_failure.lock() = Some(count);
+        self
+    }
+
+    /// Saved tracks, newest first.
+    pub fn saved_track_ids(&self) -> Vec<TrackId> {
+        self.saved_tracks.lock().clone()
+    }
+
+    fn library_request(&self) -> Result<(), Error> {
+        match &mut *self.requests_until_failure.lock() {
+            Some(0) => Err(Error::WebApiError("Mocked failure".to_string())),
+            Some(count) => {
+                *count -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn playlist(&self, id: &str) -> Result<&(Playlist, Vector<Arc<Track>>), Error> {
+        self.playlists
+            .iter()
+            .find(|(playlist, _)| &*playlist.id == id)
+            .ok_or_else(|| Error::WebApiError(format!("Unknown playlist {}", id)))
+    }
+
+    fn track(&self, id: &TrackId) -> Result<Arc<Track>, Error> {
+        self.tracks
+            .get(id)
+            .cloned()
+            .ok_or_else(|| Error::WebApiError(format!("Unknown track {}", id.0.to_base62())))
+    }
+}
+
+fn not_mocked<T>() -> Result<T, Error> {
+    Err(Error::WebApiError("Not mocked".to_string()))
+}
+
+impl WebApi for MockWebApi {
+    fn load_local_tracks(&self, _username: &str) {}
+
+    fn set_market(&self, _market: Option<String>) {}
+
+    fn collect_cache_garbage(&self) -> usize {
+        0
+    }
+
+    fn set_image_cache_limit(&self, _limit: Option<usize>) {}
+
+    fn image_cache_usage(&self) -> CacheUsage {
+        CacheUsage::default()
+    }
+
+    fn get_user_profile(&self) -> Result<UserProfile, Error> {
+        not_mocked()
+    }
+
+    fn get_artist(&self, _id: &str) -> Result<Artist, Error> {
+        not_mocked()
+    }
+
+    fn is_following_artist(&self, _id: &str) -> Result<bool, Error> {
+        not_mocked()
+    }
+
+    fn follow_artist(&self, _id: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn unfollow_artist(&self, _id: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn get_artist_albums(&self, _id: &str) -> Result<ArtistAlbums, Error> {
+        not_mocked()
+    }
+
+    fn get_artist_top_tracks(&self, _id: &str) -> Result<Vector<Arc<Track>>, Error> {
+        not_mocked()
+    }
+
+    fn get_related_artists(&self, _id: &str) -> Result<Cached<Vector<Artist>>, Error> {
+        not_mocked()
+    }
+
+    fn get_album(&self, _id: &str) -> Result<Cached<Arc<Album>>, Error> {
+        not_mocked()
+    }
+
+    fn get_album_tracks(&self, _id: &str) -> Result<Vector<Arc<Track>>, Error> {
+        not_mocked()
+    }
+
+    fn get_show_episodes(&self, _id: &str) -> Result<Vector<Arc<Episode>>, Error> {
+        not_mocked()
+    }
+
+    fn get_track(&self, id: &str) -> Result<Arc<Track>, Error> {
+        let id = TrackId::try_from(id.to_string())
+            .map_err(|_| Error::WebApiError(format!("Invalid track id {}", id)))?;
+        self.track(&id)
+    }
+
+    fn get_tracks(&self, ids: &[TrackId]) -> Result<Vector<Arc<Track>>, Error> {
+        ids.iter().map(|id| self.track(id)).collect()
+    }
+
+    fn get_canvas(&self, _id: TrackId) -> Result<Option<Canvas>, Error> {
+        Ok(None)
+    }
+
+    fn get_saved_albums(&self) -> Result<Vector<Arc<Album>>, Error> {
+        not_mocked()
+    }
+
+    fn save_album(&self, _id: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn unsave_album(&self, _id: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn get_saved_tracks(&self) -> Result<Vector<Arc<Track>>, Error> {
+        self.saved_track_ids()
+            .iter()
+            .map(|id| self.track(id))
+            .collect()
+    }
+
+    fn get_saved_shows(&self) -> Result<Vector<Arc<Show>>, Error> {
+        not_mocked()
+    }
+
+    fn save_track(&self, id: &str) -> Result<(), Error> {
+        let track = self.get_track(id)?;
+        self.save_tracks(&[track.id], &mut |_| {})
+    }
+
+    fn unsave_track(&self, id: &str) -> Result<(), Error> {
+        let track = self.get_track(id)?;
+        self.unsave_tracks(&[track.id], &mut |_| {})
+    }
+
+    fn save_tracks(&self, ids: &[TrackId], on_chunk: &mut dyn FnMut(usize)) -> Result<(), Error> {
+        for chunk in ids.chunks(LIBRARY_IDS_PER_REQUEST) {
+            self.library_request()?;
+            let mut saved = self.saved_tracks.lock();
+            for id in chunk {
+                if !saved.contains(id) {
+                    saved.insert(0, *id);
+                }
+            }
+            drop(saved);
+            on_chunk(chunk.len());
+        }
+        Ok(())
+    }
+
+    fn unsave_tracks(&self, ids: &[TrackId], on_chunk: &mut dyn FnMut(usize)) -> Result<(), Error> {
+        for chunk in ids.chunks(LIBRARY_IDS_PER_REQUEST) {
+            self.library_request()?;
+            self.saved_tracks.lock().retain(|id| !chunk.contains(id));
+            on_chunk(chunk.len());
+        }
+        Ok(())
+    }
+
+    fn contains_saved_tracks(&self, ids: &[TrackId]) -> Result<Vec<bool>, Error> {
+        let saved = self.saved_tracks.lock();
+        Ok(ids.iter().map(|id| saved.contains(id)).collect())
+    }
+
+    fn save_show(&self, _id: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn unsave_show(&self, _id: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn get_made_for_you(&self) -> Result<Vector<Playlist>, Error> {
+        not_mocked()
+    }
+
+    fn get_playlists(&self) -> Result<Vector<Playlist>, Error> {
+        Ok(self
+            .playlists
+            .iter()
+            .map(|(playlist, _)| playlist.clone())
+            .collect())
+    }
+
+    fn follow_playlist(&self, _id: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn unfollow_playlist(&self, _id: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn get_playlist(&self, id: &str) -> Result<Playlist, Error> {
+        self.playlist(id).map(|(playlist, _)| playlist.clone())
+    }
+
+    fn get_playlist_tracks(&self, id: &str) -> Result<Vector<Arc<Track>>, Error> {
+        self.playlist(id).map(|(_, tracks)| tracks.clone())
+    }
+
+    fn get_playlist_tracks_paged(
+        &self,
+        id: &str,
+        on_page: &mut dyn FnMut(Vector<Arc<Track>>, LoadProgress),
+    ) -> Result<(), Error> {
+        let tracks = self.get_playlist_tracks(id)?;
+        let progress = LoadProgress {
+            loaded: tracks.len(),
+            total: tracks.len(),
+        };
+        on_page(tracks, progress);
+        Ok(())
+    }
+
+    fn create_playlist(&self, _name: &str) -> Result<Playlist, Error> {
+        not_mocked()
+    }
+
+    fn change_playlist_details(&self, _id: &str, _name: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn add_track_to_playlist(&self, _playlist_id: &str, _track_uri: &str) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn add_tracks_to_playlist(
+        &self,
+        _playlist_id: &str,
+        _track_uris: &[Arc<str>],
+    ) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn remove_track_from_playlist(
+        &self,
+        _playlist_id: &str,
+        _track_uri: &str,
+    ) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn replace_playlist_tracks(
+        &self,
+        _playlist_id: &str,
+        _track_uris: &[Arc<str>],
+    ) -> Result<(), Error> {
+        not_mocked()
+    }
+
+    fn search(
+        &self,
+        _query: &str,
+        _topics: &[SearchTopic],
+        _limit: usize,
+    ) -> Result<SearchResults, Error> {
+        not_mocked()
+    }
+
+    fn load_spotify_link(&self, _link: &SpotifyUri) -> Result<Nav, Error> {
+        not_mocked()
+    }
+
+    fn get_recommendations(
+        &self,
+        _data: Arc<RecommendationsRequest>,
+    ) -> Result<Recommendations, Error> {
+        not_mocked()
+    }
+
+    fn get_musicbrainz_release(&self, _album: &Album) -> Result<Option<MusicBrainzRelease>, Error> {
+        Ok(None)
+    }
+
+    fn get_cached_image(&self, _uri: &Arc<str>) -> Option<ImageBuf> {
+        None
+    }
+
+    fn get_image(&self, _uri: Arc<str>) -> Result<ImageBuf, Error> {
+        not_mocked()
+    }
+}
@@ -1,6 +1,16 @@
+mod api;
+mod bandsintown;
 mod cache;
 mod client;
+mod events;
 mod local;
+mod mercury;
+#[cfg(test)]
+mod mock;
 
-pub use client::WebApi;
+pub use api::{global, install_global, WebApi};
+pub use client::Web;
+pub use events::{configure_events, events_provider, EventsProvider};
 pub use local::LocalTrackManager;
+#[cfg(test)]
+pub use mock::MockWebApi;
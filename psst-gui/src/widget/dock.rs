@@ -0,0 +1,147 @@
+use druid::{widget::prelude::*, Data, Lens, LensExt, Point, Rect, WidgetPod};
+use druid_shell::Cursor;
+
+use crate::ui::theme;
+
+const BAR_WIDTH: f64 = 1.0;
+/// Area around the bar that can be grabbed for resizing.
+const BAR_GRAB_WIDTH: f64 = 8.0;
+const MIN_PANEL_WIDTH: f64 = 200.0;
+const MIN_MAIN_WIDTH: f64 = 300.0;
+
+/// Lays out `main` with an optional panel docked to its right.  The panel can be
+/// resized by dragging the bar between them, and its width is kept in the data,
+/// so it can be persisted.
+pub struct Dock<T, L> {
+    main: WidgetPod<T, Box<dyn Widget<T>>>,
+    panel: WidgetPod<T, Box<dyn Widget<T>>>,
+    is_open: Box<dyn Fn(&T) -> bool>,
+    width: L,
+    is_open_now: bool,
+    is_dragging: bool,
+}
+
+impl<T: Data, L: Lens<T, f64>> Dock<T, L> {
+    pub fn new(
+        main: impl Widget<T> + 'static,
+        panel: impl Widget<T> + 'static,
+        is_open: impl Fn(&T) -> bool + 'static,
+        width: L,
+    ) -> Self {
+        Self {
+            main: WidgetPod::new(main).boxed(),
+            panel: WidgetPod::new(panel).boxed(),
+            is_open: Box::new(is_open),
+            width,
+            is_open_now: false,
+            is_dragging: false,
+        }
+    }
+
+    fn bar_x(&self) -> f64 {
+        self.panel.layout_rect().x0 - BAR_WIDTH
+    }
+
+    fn is_over_bar(&self, pos: Point) -> bool {
+        (pos.x - self.bar_x() - BAR_WIDTH / 2.0).abs() <= BAR_GRAB_WIDTH / 2.0
+    }
+}
+
+/// The main view keeps its minimal width first, unless the panel would get
+/// narrower than its own minimum.
+fn clamp_panel_width(width: f64, total: f64) -> f64 {
+    width
+        .min(total - MIN_MAIN_WIDTH - BAR_WIDTH)
+        .max(MIN_PANEL_WIDTH)
+}
+
+impl<T: Data, L: Lens<T, f64>> Widget<T> for Dock<T, L> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if self.is_open_now {
+            let total = ctx.size().width;
+            match event {
+                Event::MouseDown(mouse) if self.is_over_bar(mouse.pos) => {
+                    self.is_dragging = true;
+                    ctx.set_active(true);
+                    ctx.set_handled();
+                    return;
+                }
+                Event::MouseMove(mouse) if self.is_dragging => {
+                    let width = clamp_panel_width(total - mouse.pos.x, total).round();
+                    self.width.with_mut(data, |w| *w = width);
+                    ctx.request_layout();
+                    ctx.set_handled();
+                    return;
+                }
+                Event::MouseUp(_) if self.is_dragging => {
+                    self.is_dragging = false;
+                    ctx.set_active(false);
+                    ctx.set_handled();
+                    return;
+                }
+                Event::MouseMove(mouse) if self.is_over_bar(mouse.pos) => {
+                    ctx.set_cursor(&Cursor::ResizeLeftRight);
+                }
+                Event::MouseMove(_) => {
+                    ctx.clear_cursor();
+                }
+                _ => {}
+            }
+            self.panel.event(ctx, event, data, env);
+        }
+        self.main.event(ctx, event, data, env);
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.is_open_now = (self.is_open)(data);
+            self.panel.lifecycle(ctx, event, data, env);
+        } else if self.is_open_now {
+            self.panel.lifecycle(ctx, event, data, env);
+        }
+        self.main.lifecycle(ctx, event, data, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        let is_open = (self.is_open)(data);
+        if is_open != self.is_open_now {
+            self.is_open_now = is_open;
+            ctx.request_layout();
+        }
+        if !self.width.get(old_data).same(&self.width.get(data)) {
+            ctx.request_layout();
+        }
+        if self.is_open_now {
+            self.panel.update(ctx, data, env);
+        }
+        self.main.update(ctx, data, env);
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let size = bc.max();
+        let main_width = if self.is_open_now {
+            let panel_width = clamp_panel_width(self.width.get(data), size.width);
+            let panel_bc = BoxConstraints::tight(Size::new(panel_width, size.height));
+            self.panel.layout(ctx, &panel_bc, data, env);
+            self.panel
+                .set_origin(ctx, Point::new(size.width - panel_width, 0.0));
+            (size.width - panel_width - BAR_WIDTH).max(0.0)
+        } else {
+            size.width
+        };
+        let main_bc = BoxConstraints::tight(Size::new(main_width, size.height));
+        self.main.layout(ctx, &main_bc, data, env);
+        self.main.set_origin(ctx, Point::ORIGIN);
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.main.paint(ctx, data, env);
+        if self.is_open_now {
+            let x = self.bar_x();
+            let bar = Rect::new(x, 0.0, x + BAR_WIDTH, ctx.size().height);
+            ctx.fill(bar, &env.get(theme::BORDER_DARK));
+            self.panel.paint(ctx, data, env);
+        }
+    }
+}
@@ -25,6 +25,18 @@ pub static STORAGE: SvgIcon = SvgIcon {
     svg_size: Size::new(28.0, 28.0),
     op: PaintOp::Fill,
 };
+// List with bullets, for the activity log
+pub static ACTIVITY: SvgIcon = SvgIcon {
+    svg_path: "M4 6H7V9H4Z M10 6.5H24V8.5H10Z M4 12.5H7V15.5H4Z M10 13H24V15H10Z M4 19H7V22H4Z M10 19.5H24V21.5H10Z",
+    svg_size: Size::new(28.0, 28.0),
+    op: PaintOp::Fill,
+};
+
+pub static QUEUE: SvgIcon = SvgIcon {
+    svg_path: "M3 6H19V8H3Z M3 11H19V13H3Z M3 16H12V18H3Z M16 15V23L23 19Z",
+    svg_size: Size::new(26.0, 26.0),
+    op: PaintOp::Fill,
+};
 
 pub static BACK: SvgIcon = SvgIcon {
     svg_path: "M9.70711 0.292893C10.0976 0.683417 10.0976 1.31658 9.70711 1.70711L2.41421 9L9.70711 16.2929C10.0976 16.6834 10.0976 17.3166 9.70711 17.7071C9.31658 18.0976 8.68342 18.0976 8.29289 17.7071L0.292893 9.70711C-0.0976311 9.31658 -0.0976311 8.68342 0.292893 8.29289L8.29289 0.292893C8.68342 -0.0976311 9.31658 -0.0976311 9.70711 0.292893Z",
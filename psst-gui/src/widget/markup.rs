@@ -0,0 +1,215 @@
+use std::sync::Arc;
+
+use druid::{
+    text::{RichText, RichTextBuilder},
+    widget::{prelude::*, LineBreaking, RawLabel},
+    Color, Data, KeyOrValue,
+};
+use psst_core::uri::SpotifyUri;
+
+use crate::{
+    cmd,
+    data::{is_liked_songs_link, Nav},
+    ui::theme,
+};
+
+/// Renders the HTML-ish markup found in playlist descriptions.  Tags other than
+/// links and line breaks are dropped, and only links to Spotify content are kept,
+/// navigating inside the app when clicked.
+pub struct Markup {
+    label: RawLabel<RichText>,
+    text: RichText,
+    text_size: KeyOrValue<f64>,
+    text_color: KeyOrValue<Color>,
+    links: bool,
+}
+
+impl Markup {
+    pub fn new() -> Self {
+        Self {
+            label: RawLabel::new().with_line_break_mode(LineBreaking::WordWrap),
+            text: RichText::new("".into()),
+            text_size: theme::TEXT_SIZE_NORMAL.into(),
+            text_color: theme::TEXT_COLOR.into(),
+            links: true,
+        }
+    }
+
+    pub fn with_text_size(mut self, size: impl Into<KeyOrValue<f64>>) -> Self {
+        self.text_size = size.into();
+        self
+    }
+
+    pub fn with_text_color(mut self, color: impl Into<KeyOrValue<Color>>) -> Self {
+        self.text_color = color.into();
+        self
+    }
+
+    /// Render the links as plain text, for places that are clickable as a whole.
+    pub fn without_links(mut self) -> Self {
+        self.links = false;
+        self
+    }
+
+    fn parse(&self, markup: &str) -> RichText {
+        let mut builder = RichTextBuilder::new();
+        let mut link = None;
+        let mut rest = markup;
+        while !rest.is_empty() {
+            let (text, tag) = match rest.find('<') {
+                Some(start) => match rest[start..].find('>') {
+                    Some(len) => {
+                        let tag = &rest[start + 1..start + len];
+                        let text = &rest[..start];
+                        rest = &rest[start + len + 1..];
+                        (text, Some(tag))
+                    }
+                    // Unterminated tag, treat the rest as text.
+                    None => (std::mem::take(&mut rest), None),
+                },
+                None => (std::mem::take(&mut rest), None),
+            };
+            if !text.is_empty() {
+                let decoded = decode_entities(text);
+                let mut attrs = builder.push(&decoded);
+                attrs.size(self.text_size.clone());
+                if let Some(url) = &link {
+                    // Opening links goes through the search, same as pasting them there.
+                    attrs
+                        .text_color(theme::LINK_COLD_COLOR)
+                        .underline(true)
+                        .link(cmd::NAVIGATE.with(Nav::SearchResults(Arc::clone(url))));
+                } else {
+                    attrs.text_color(self.text_color.clone());
+                }
+            }
+            if let Some(tag) = tag {
+                let tag = tag.trim();
+                let name = tag.to_ascii_lowercase();
+                if is_line_break(&name) {
+                    builder.push("\n");
+                } else if name == "/a" {
+                    link = None;
+                } else if self.links && name.starts_with("a ") {
+                    link = href(tag)
+                        .filter(|href| {
+                            SpotifyUri::parse(href).is_some() || is_liked_songs_link(href)
+                        })
+                        .map(Arc::from);
+                }
+            }
+        }
+        builder.build()
+    }
+}
+
+impl Widget<Arc<str>> for Markup {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut Arc<str>, env: &Env) {
+        let mut text = self.text.clone();
+        self.label.event(ctx, event, &mut text, env);
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &Arc<str>, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.text = self.parse(data);
+        }
+        self.label.lifecycle(ctx, event, &self.text, env);
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Arc<str>, data: &Arc<str>, env: &Env) {
+        let old_text = self.text.clone();
+        if !old_data.same(data) {
+            self.text = self.parse(data);
+        }
+        self.label.update(ctx, &old_text, &self.text, env);
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &Arc<str>,
+        env: &Env,
+    ) -> Size {
+        self.label.layout(ctx, bc, &self.text, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &Arc<str>, env: &Env) {
+        self.label.paint(ctx, &self.text, env);
+    }
+}
+
+/// Value of the `href` attribute of an `a` tag.
+/// Text of `markup` with the tags dropped, for copying it to the clipboard.
+pub fn plain_text(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut rest = markup;
+    while let Some(start) = rest.find('<') {
+        let len = match rest[start..].find('>') {
+            Some(len) => len,
+            // Unterminated tag, keep the rest as text.
+            None => break,
+        };
+        text.push_str(&rest[..start]);
+        let tag = rest[start + 1..start + len].trim().to_ascii_lowercase();
+        if is_line_break(&tag) {
+            text.push('\n');
+        }
+        rest = &rest[start + len + 1..];
+    }
+    text.push_str(rest);
+    decode_entities(&text)
+}
+
+fn is_line_break(tag: &str) -> bool {
+    matches!(tag, "br" | "br/" | "br /" | "/p")
+}
+
+fn href(tag: &str) -> Option<String> {
+    let start = tag.to_ascii_lowercase().find("href=")? + "href=".len();
+    let value = &tag[start..];
+    let value = match value.chars().next()? {
+        quote @ ('"' | '\'') => value[1..].split(quote).next()?,
+        _ => value.split_whitespace().next()?,
+    };
+    Some(decode_entities(value))
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let ch = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = name.strip_prefix('#')?;
+                let hex = code.strip_prefix('x').or_else(|| code.strip_prefix('X'));
+                let code = match hex {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => code.parse().ok()?,
+                };
+                char::from_u32(code)
+            }
+        });
+        match (ch, entity) {
+            (Some(ch), Some((_, end))) => {
+                decoded.push(ch);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
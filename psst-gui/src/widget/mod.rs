@@ -1,14 +1,17 @@
 mod checkbox;
 mod dispatcher;
+mod dock;
 mod empty;
 pub mod icons;
 mod link;
+mod markup;
 mod maybe;
 mod overlay;
 mod promise;
 pub mod remote_image;
 mod theme;
 mod utils;
+mod zoom;
 
 use std::{sync::Arc, time::Duration};
 
@@ -19,19 +22,24 @@ use druid::{
 
 pub use checkbox::Checkbox;
 pub use dispatcher::ViewDispatcher;
+pub use dock::Dock;
 use druid_shell::Cursor;
 pub use empty::Empty;
 pub use icons::Icon;
 pub use link::Link;
+pub use markup::{plain_text, Markup};
 pub use maybe::Maybe;
 pub use overlay::{Overlay, OverlayPosition};
 pub use promise::Async;
 pub use remote_image::RemoteImage;
 pub use theme::ThemeScope;
 pub use utils::{Border, Clip, FadeOut, Logger};
+pub use zoom::Zoom;
 
 use crate::{
-    controller::{ExClick, ExCursor, ExScroll, OnCommand, OnCommandAsync, OnDebounce, OnUpdate},
+    controller::{
+        ExClick, ExCursor, ExScroll, OnCommand, OnCommandAsync, OnDebounce, OnUpdate, Tooltip,
+    },
     data::{AppState, SliderScrollScale},
 };
 
@@ -68,6 +76,13 @@ pub trait MyWidgetExt<T: Data>: Widget<T> + Sized + 'static {
         ControllerHost::new(self, OnDebounce::trailing(duration, handler))
     }
 
+    fn tooltip(
+        self,
+        text: impl Fn(&T) -> Option<String> + 'static,
+    ) -> ControllerHost<Self, Tooltip<T>> {
+        ControllerHost::new(self, Tooltip::new(text))
+    }
+
     fn on_update<F>(self, handler: F) -> ControllerHost<Self, OnUpdate<F>>
     where
         F: Fn(&mut UpdateCtx, &T, &T, &Env) + 'static,
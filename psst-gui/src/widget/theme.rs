@@ -36,7 +36,17 @@ impl<W: Widget<AppState>> Widget<AppState> for ThemeScope<W> {
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
-        if !data.config.theme.same(&old_data.config.theme) {
+        if !data.config.theme.same(&old_data.config.theme)
+            || !data
+                .config
+                .reduced_motion
+                .same(&old_data.config.reduced_motion)
+            || !data
+                .config
+                .low_power_mode
+                .same(&old_data.config.low_power_mode)
+            || !data.config.ui_font.same(&old_data.config.ui_font)
+        {
             self.set_env(data, env);
             ctx.request_layout();
             ctx.request_paint();
@@ -0,0 +1,160 @@
+use druid::{
+    kurbo::Affine, widget::prelude::*, MouseEvent, Point, Rect, Region, Screen, WindowHandle,
+};
+
+use crate::{cmd, data::AppState};
+
+/// Scales the main window by the UI scale and the zoom level of the monitor the
+/// window is on.  Mouse positions are scaled back on the way in, so the widgets
+/// inside don't need to know about it.
+pub struct Zoom<W> {
+    inner: W,
+    monitor: Option<String>,
+    /// Position of the window when `monitor` was looked up.
+    position: Option<Point>,
+    scale: f64,
+}
+
+impl<W> Zoom<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            monitor: None,
+            position: None,
+            scale: 1.0,
+        }
+    }
+
+    /// Look up the monitor again if the window has moved since.  Returns true if
+    /// the scale has changed.
+    fn update_monitor(&mut self, window: &WindowHandle, data: &AppState) -> bool {
+        let position = window.get_position();
+        if self.position != Some(position) {
+            self.position = Some(position);
+            self.monitor = monitor_key(window);
+        }
+        self.update_scale(data)
+    }
+
+    /// Returns true if the scale has changed.
+    fn update_scale(&mut self, data: &AppState) -> bool {
+        let scale = data.config.ui_scale(self.monitor.as_deref());
+        let changed = scale != self.scale;
+        self.scale = scale;
+        changed
+    }
+
+    /// Widgets inside report the regions to repaint unscaled.  Zoomed out, those
+    /// cover the scaled area anyway, zoomed in they fall short and everything has
+    /// to be repainted instead.
+    fn repaints_everything(&self) -> bool {
+        self.scale > 1.0
+    }
+
+    fn unscale_mouse(&self, mouse: &MouseEvent) -> MouseEvent {
+        let mut mouse = mouse.clone();
+        mouse.pos = Point::new(mouse.pos.x / self.scale, mouse.pos.y / self.scale);
+        mouse
+    }
+
+    fn unscale_event(&self, event: &Event) -> Option<Event> {
+        match event {
+            Event::MouseDown(mouse) => Some(Event::MouseDown(self.unscale_mouse(mouse))),
+            Event::MouseUp(mouse) => Some(Event::MouseUp(self.unscale_mouse(mouse))),
+            Event::MouseMove(mouse) => Some(Event::MouseMove(self.unscale_mouse(mouse))),
+            Event::Wheel(mouse) => Some(Event::Wheel(self.unscale_mouse(mouse))),
+            _ => None,
+        }
+    }
+}
+
+/// Monitors don't have a stable name we could use, so they are told apart by
+/// their geometry.
+fn monitor_key(window: &WindowHandle) -> Option<String> {
+    let center = Rect::from_origin_size(window.get_position(), window.get_size()).center();
+    Screen::get_monitors()
+        .into_iter()
+        .map(|monitor| monitor.virtual_rect())
+        .find(|rect| rect.contains(center))
+        .map(|rect| format!("{}x{}+{}+{}", rect.width(), rect.height(), rect.x0, rect.y0))
+}
+
+impl<W: Widget<AppState>> Widget<AppState> for Zoom<W> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        match event {
+            // There's no event for moving the window, so check whether it has moved
+            // to another monitor once the mouse is back in it.
+            Event::WindowConnected | Event::WindowSize(_) | Event::MouseMove(_) => {
+                if self.update_monitor(ctx.window(), data) {
+                    ctx.request_layout();
+                }
+            }
+            Event::Command(cmd) if cmd.is(cmd::ZOOM) => {
+                self.update_monitor(ctx.window(), data);
+                if let Some(monitor) = &self.monitor {
+                    let step = *cmd.get_unchecked(cmd::ZOOM);
+                    ctx.submit_command(cmd::ZOOM_MONITOR.with((monitor.clone(), step)));
+                }
+                ctx.set_handled();
+                return;
+            }
+            _ => {}
+        }
+        if self.scale == 1.0 {
+            self.inner.event(ctx, event, data, env);
+        } else {
+            let unscaled = self.unscale_event(event);
+            self.inner
+                .event(ctx, unscaled.as_ref().unwrap_or(event), data, env);
+            if self.repaints_everything() {
+                ctx.request_paint();
+            }
+        }
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.update_scale(data);
+        }
+        self.inner.lifecycle(ctx, event, data, env);
+        if self.repaints_everything() {
+            ctx.request_paint();
+        }
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        if self.update_scale(data) {
+            ctx.request_layout();
+        }
+        self.inner.update(ctx, old_data, data, env);
+        if self.repaints_everything() {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &AppState,
+        env: &Env,
+    ) -> Size {
+        let bc = BoxConstraints::new(bc.min() / self.scale, bc.max() / self.scale);
+        self.inner.layout(ctx, &bc, data, env) * self.scale
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, env: &Env) {
+        if self.scale == 1.0 {
+            self.inner.paint(ctx, data, env);
+            return;
+        }
+        let mut region = Region::EMPTY;
+        for rect in ctx.region().rects() {
+            region.add_rect(rect.scale_from_origin(1.0 / self.scale));
+        }
+        ctx.with_save(|ctx| {
+            ctx.transform(Affine::scale(self.scale));
+            ctx.with_child_ctx(region, |ctx| self.inner.paint(ctx, data, env));
+        });
+    }
+}
@@ -4,6 +4,7 @@ pb-rs \
     --dont_use_cow \
     --output_directory src \
     "proto/authentication.proto" \
+    "proto/canvaz.proto" \
     "proto/keyexchange.proto" \
     "proto/mercury.proto" \
     "proto/metadata.proto"
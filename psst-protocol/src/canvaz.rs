@@ -0,0 +1,227 @@
+// Automatically generated rust module for 'canvaz.proto' file
+
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(non_camel_case_types)]
+#![allow(unused_imports)]
+#![allow(unknown_lints)]
+#![allow(clippy::all)]
+#![cfg_attr(rustfmt, rustfmt_skip)]
+
+
+use quick_protobuf::{MessageRead, MessageWrite, BytesReader, Writer, WriterBackend, Result};
+use quick_protobuf::sizeofs::*;
+use super::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Type {
+    IMAGE = 0,
+    VIDEO = 1,
+    VIDEO_LOOPING = 2,
+    VIDEO_LOOPING_RANDOM = 3,
+    GIF = 4,
+}
+
+impl Default for Type {
+    fn default() -> Self {
+        Type::IMAGE
+    }
+}
+
+impl From<i32> for Type {
+    fn from(i: i32) -> Self {
+        match i {
+            0 => Type::IMAGE,
+            1 => Type::VIDEO,
+            2 => Type::VIDEO_LOOPING,
+            3 => Type::VIDEO_LOOPING_RANDOM,
+            4 => Type::GIF,
+            _ => Self::default(),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Type {
+    fn from(s: &'a str) -> Self {
+        match s {
+            "IMAGE" => Type::IMAGE,
+            "VIDEO" => Type::VIDEO,
+            "VIDEO_LOOPING" => Type::VIDEO_LOOPING,
+            "VIDEO_LOOPING_RANDOM" => Type::VIDEO_LOOPING_RANDOM,
+            "GIF" => Type::GIF,
+            _ => Self::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct EntityCanvazRequest {
+    pub entities: Vec<canvaz::mod_EntityCanvazRequest::Entity>,
+}
+
+impl<'a> MessageRead<'a> for EntityCanvazRequest {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.entities.push(r.read_message::<canvaz::mod_EntityCanvazRequest::Entity>(bytes)?),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for EntityCanvazRequest {
+    fn get_size(&self) -> usize {
+        0
+        + self.entities.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        for s in &self.entities { w.write_with_tag(10, |w| w.write_message(s))?; }
+        Ok(())
+    }
+}
+
+pub mod mod_EntityCanvazRequest {
+
+use super::*;
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Entity {
+    pub entity_uri: Option<String>,
+    pub etag: Option<String>,
+}
+
+impl<'a> MessageRead<'a> for Entity {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.entity_uri = Some(r.read_string(bytes)?.to_owned()),
+                Ok(18) => msg.etag = Some(r.read_string(bytes)?.to_owned()),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for Entity {
+    fn get_size(&self) -> usize {
+        0
+        + self.entity_uri.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+        + self.etag.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        if let Some(ref s) = self.entity_uri { w.write_with_tag(10, |w| w.write_string(&**s))?; }
+        if let Some(ref s) = self.etag { w.write_with_tag(18, |w| w.write_string(&**s))?; }
+        Ok(())
+    }
+}
+
+}
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct EntityCanvazResponse {
+    pub canvases: Vec<canvaz::mod_EntityCanvazResponse::Canvaz>,
+    pub ttl_in_seconds: Option<i64>,
+}
+
+impl<'a> MessageRead<'a> for EntityCanvazResponse {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.canvases.push(r.read_message::<canvaz::mod_EntityCanvazResponse::Canvaz>(bytes)?),
+                Ok(16) => msg.ttl_in_seconds = Some(r.read_int64(bytes)?),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for EntityCanvazResponse {
+    fn get_size(&self) -> usize {
+        0
+        + self.canvases.iter().map(|s| 1 + sizeof_len((s).get_size())).sum::<usize>()
+        + self.ttl_in_seconds.as_ref().map_or(0, |m| 1 + sizeof_varint(*(m) as u64))
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        for s in &self.canvases { w.write_with_tag(10, |w| w.write_message(s))?; }
+        if let Some(ref s) = self.ttl_in_seconds { w.write_with_tag(16, |w| w.write_int64(*s))?; }
+        Ok(())
+    }
+}
+
+pub mod mod_EntityCanvazResponse {
+
+use super::*;
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Canvaz {
+    pub id: Option<String>,
+    pub url: Option<String>,
+    pub file_id: Option<String>,
+    pub type_pb: Option<canvaz::Type>,
+    pub entity_uri: Option<String>,
+    pub explicit: Option<bool>,
+    pub etag: Option<String>,
+    pub canvas_uri: Option<String>,
+}
+
+impl<'a> MessageRead<'a> for Canvaz {
+    fn from_reader(r: &mut BytesReader, bytes: &'a [u8]) -> Result<Self> {
+        let mut msg = Self::default();
+        while !r.is_eof() {
+            match r.next_tag(bytes) {
+                Ok(10) => msg.id = Some(r.read_string(bytes)?.to_owned()),
+                Ok(18) => msg.url = Some(r.read_string(bytes)?.to_owned()),
+                Ok(26) => msg.file_id = Some(r.read_string(bytes)?.to_owned()),
+                Ok(32) => msg.type_pb = Some(r.read_enum(bytes)?),
+                Ok(42) => msg.entity_uri = Some(r.read_string(bytes)?.to_owned()),
+                Ok(56) => msg.explicit = Some(r.read_bool(bytes)?),
+                Ok(74) => msg.etag = Some(r.read_string(bytes)?.to_owned()),
+                Ok(90) => msg.canvas_uri = Some(r.read_string(bytes)?.to_owned()),
+                Ok(t) => { r.read_unknown(bytes, t)?; }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(msg)
+    }
+}
+
+impl MessageWrite for Canvaz {
+    fn get_size(&self) -> usize {
+        0
+        + self.id.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+        + self.url.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+        + self.file_id.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+        + self.type_pb.as_ref().map_or(0, |m| 1 + sizeof_varint(*(m) as u64))
+        + self.entity_uri.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+        + self.explicit.as_ref().map_or(0, |m| 1 + sizeof_varint(*(m) as u64))
+        + self.etag.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+        + self.canvas_uri.as_ref().map_or(0, |m| 1 + sizeof_len((m).len()))
+    }
+
+    fn write_message<W: WriterBackend>(&self, w: &mut Writer<W>) -> Result<()> {
+        if let Some(ref s) = self.id { w.write_with_tag(10, |w| w.write_string(&**s))?; }
+        if let Some(ref s) = self.url { w.write_with_tag(18, |w| w.write_string(&**s))?; }
+        if let Some(ref s) = self.file_id { w.write_with_tag(26, |w| w.write_string(&**s))?; }
+        if let Some(ref s) = self.type_pb { w.write_with_tag(32, |w| w.write_enum(*s as i32))?; }
+        if let Some(ref s) = self.entity_uri { w.write_with_tag(42, |w| w.write_string(&**s))?; }
+        if let Some(ref s) = self.explicit { w.write_with_tag(56, |w| w.write_bool(*s))?; }
+        if let Some(ref s) = self.etag { w.write_with_tag(74, |w| w.write_string(&**s))?; }
+        if let Some(ref s) = self.canvas_uri { w.write_with_tag(90, |w| w.write_string(&**s))?; }
+        Ok(())
+    }
+}
+
+}
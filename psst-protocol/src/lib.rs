@@ -1,4 +1,5 @@
 pub mod authentication;
+pub mod canvaz;
 pub mod keyexchange;
 pub mod mercury;
 pub mod metadata;